@@ -276,8 +276,8 @@ export async function open_folder_path(path) {
     return await invoke('open_folder_path', { path });
 }
 
-export async function openResult(url) {
-    return await invoke('open_result', { url });
+export async function openResult(url, docId) {
+    return await invoke('open_result', { url, docId });
 }
 
 export async function resizeWindow(height) {
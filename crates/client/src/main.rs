@@ -44,7 +44,7 @@ extern "C" {
     pub async fn search_lenses(query: String) -> Result<JsValue, JsValue>;
 
     #[wasm_bindgen(js_name = "openResult", catch)]
-    pub async fn open(url: String) -> Result<(), JsValue>;
+    pub async fn open(url: String, doc_id: String) -> Result<(), JsValue>;
 
     #[wasm_bindgen(catch)]
     pub async fn open_folder_path(path: String) -> Result<(), JsValue>;
@@ -98,7 +98,7 @@ extern "C" {
     pub async fn search_lenses(query: String) -> Result<JsValue, JsValue>;
 
     #[wasm_bindgen(js_name = "openResult", catch)]
-    pub async fn open(url: String) -> Result<(), JsValue>;
+    pub async fn open(url: String, doc_id: String) -> Result<(), JsValue>;
 
     #[wasm_bindgen(catch)]
     pub async fn open_folder_path(path: String) -> Result<(), JsValue>;
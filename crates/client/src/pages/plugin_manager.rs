@@ -47,6 +47,26 @@ pub fn plugin_comp(props: &PluginProps) -> Html {
                 <div class="text-sm leading-relaxed text-neutral-400">
                     {plugin.description.clone()}
                 </div>
+                {
+                    plugin.tasks
+                        .iter()
+                        .filter(|task| task.percent < 100)
+                        .map(|task| html! {
+                            <div class="text-xs text-neutral-400 pt-2">
+                                <div class="flex flex-row">
+                                    <span>{task.message.clone().unwrap_or_else(|| task.task.clone())}</span>
+                                    <span class="ml-auto">{format!("{}%", task.percent)}</span>
+                                </div>
+                                <div class="w-full h-1 mt-1 bg-neutral-700 rounded">
+                                    <div
+                                        class="h-1 bg-cyan-400 rounded"
+                                        style={format!("width: {}%", task.percent)}
+                                    />
+                                </div>
+                            </div>
+                        })
+                        .collect::<Html>()
+                }
             </div>
             <div class="ml-auto grow">
                 <Toggle
@@ -79,9 +79,10 @@ impl SearchPage {
 
     fn open_result(&mut self, selected: &SearchResult) {
         let url = selected.url.clone();
+        let doc_id = selected.doc_id.clone();
         log::info!("open url: {}", url);
         spawn_local(async move {
-            let _ = open(url).await;
+            let _ = open(url, doc_id).await;
         });
     }
 
@@ -0,0 +1,174 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::{ConnectionTrait, Set};
+use serde::Serialize;
+
+use super::{collection_document, indexed_document};
+
+/// A user curated group of documents, e.g. for a research project.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Eq)]
+#[sea_orm(table_name = "collection")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    /// Unique name, used w/ the `collection:` search filter.
+    #[sea_orm(unique)]
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+impl Related<indexed_document::Entity> for Entity {
+    // The final relation is Collection -> CollectionDocument -> IndexedDocument
+    fn to() -> RelationDef {
+        collection_document::Relation::IndexedDocument.def()
+    }
+
+    fn via() -> Option<RelationDef> {
+        Some(collection_document::Relation::Collection.def().rev())
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {
+    CollectionDocument,
+}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        match self {
+            Self::CollectionDocument => Entity::has_many(collection_document::Entity).into(),
+        }
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {
+    // Triggered before insert / update
+    fn before_save(mut self, insert: bool) -> Result<Self, DbErr> {
+        if insert {
+            self.created_at = Set(chrono::Utc::now());
+            self.updated_at = Set(chrono::Utc::now());
+        } else {
+            self.updated_at = Set(chrono::Utc::now());
+        }
+
+        Ok(self)
+    }
+}
+
+pub async fn find_by_name<C: ConnectionTrait>(db: &C, name: &str) -> Result<Option<Model>, DbErr> {
+    Entity::find().filter(Column::Name.eq(name)).one(db).await
+}
+
+pub async fn add_document<C: ConnectionTrait>(
+    db: &C,
+    collection: &Model,
+    doc: &indexed_document::Model,
+) -> Result<(), DbErr> {
+    let entry = collection_document::ActiveModel {
+        collection_id: Set(collection.id),
+        indexed_document_id: Set(doc.id),
+        created_at: Set(chrono::Utc::now()),
+        updated_at: Set(chrono::Utc::now()),
+        ..Default::default()
+    };
+
+    // Ignore documents that are already part of the collection.
+    collection_document::Entity::insert(entry)
+        .on_conflict(
+            sea_orm::sea_query::OnConflict::columns(vec![
+                collection_document::Column::CollectionId,
+                collection_document::Column::IndexedDocumentId,
+            ])
+            .do_nothing()
+            .to_owned(),
+        )
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn remove_document<C: ConnectionTrait>(
+    db: &C,
+    collection: &Model,
+    doc: &indexed_document::Model,
+) -> Result<(), DbErr> {
+    collection_document::Entity::delete_many()
+        .filter(collection_document::Column::CollectionId.eq(collection.id))
+        .filter(collection_document::Column::IndexedDocumentId.eq(doc.id))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Remove a collection & its membership records. Documents are left untouched.
+pub async fn delete<C: ConnectionTrait>(db: &C, collection: Model) -> Result<(), DbErr> {
+    collection_document::Entity::delete_many()
+        .filter(collection_document::Column::CollectionId.eq(collection.id))
+        .exec(db)
+        .await?;
+    collection.delete(db).await?;
+
+    Ok(())
+}
+
+/// Search index ids of the documents in the collection `name`.
+pub async fn doc_ids<C: ConnectionTrait>(db: &C, name: &str) -> Result<Vec<String>, DbErr> {
+    let collection = match find_by_name(db, name).await? {
+        Some(collection) => collection,
+        None => return Ok(Vec::new()),
+    };
+
+    let docs = collection
+        .find_related(indexed_document::Entity)
+        .all(db)
+        .await?;
+
+    Ok(docs.into_iter().map(|doc| doc.doc_id).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::models::indexed_document;
+    use crate::test::setup_test_db;
+    use sea_orm::{ActiveModelTrait, Set};
+
+    #[tokio::test]
+    async fn test_collection_documents() {
+        let db = setup_test_db().await;
+
+        let doc = indexed_document::ActiveModel {
+            domain: Set("en.wikipedia.org".into()),
+            url: Set("https://en.wikipedia.org/wiki/Rust_(programming_language)".into()),
+            doc_id: Set("rust-doc".into()),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+
+        let collection = super::ActiveModel {
+            name: Set("research".into()),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+
+        // Adding twice should be a no-op
+        super::add_document(&db, &collection, &doc).await.unwrap();
+        super::add_document(&db, &collection, &doc).await.unwrap();
+        assert_eq!(
+            super::doc_ids(&db, "research").await.unwrap(),
+            vec!["rust-doc".to_string()]
+        );
+        assert!(super::doc_ids(&db, "unknown").await.unwrap().is_empty());
+
+        super::remove_document(&db, &collection, &doc)
+            .await
+            .unwrap();
+        assert!(super::doc_ids(&db, "research").await.unwrap().is_empty());
+    }
+}
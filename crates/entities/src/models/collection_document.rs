@@ -0,0 +1,49 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::Set;
+use serde::Serialize;
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Eq)]
+#[sea_orm(table_name = "collection_document")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub collection_id: i64,
+    pub indexed_document_id: i64,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {
+    Collection,
+    IndexedDocument,
+}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        match self {
+            Self::Collection => Entity::belongs_to(super::collection::Entity)
+                .from(Column::CollectionId)
+                .to(super::collection::Column::Id)
+                .into(),
+            Self::IndexedDocument => Entity::belongs_to(super::indexed_document::Entity)
+                .from(Column::IndexedDocumentId)
+                .to(super::indexed_document::Column::Id)
+                .into(),
+        }
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {
+    // Triggered before insert / update
+    fn before_save(mut self, insert: bool) -> Result<Self, DbErr> {
+        if insert {
+            self.created_at = Set(chrono::Utc::now());
+            self.updated_at = Set(chrono::Utc::now());
+        } else {
+            self.updated_at = Set(chrono::Utc::now());
+        }
+
+        Ok(self)
+    }
+}
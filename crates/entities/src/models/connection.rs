@@ -1,6 +1,14 @@
+use chrono::{DateTime, Duration, Utc};
 use sea_orm::entity::prelude::*;
-use sea_orm::Set;
+use sea_orm::{DbBackend, Set, Statement};
 use serde::{Deserialize, Serialize};
+use shared::config::ConnectionQuota;
+
+const RECORD_USAGE: &str = "UPDATE connections SET
+    num_requests = (CASE WHEN usage_date = ? THEN num_requests ELSE 0 END) + ?,
+    num_bytes = (CASE WHEN usage_date = ? THEN num_bytes ELSE 0 END) + ?,
+    usage_date = ?
+    WHERE api_id = ? AND account = ?";
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, FromJsonQueryResult)]
 pub struct Scopes {
@@ -29,6 +37,31 @@ pub struct Model {
     // When this connection was created/updated
     pub created_at: DateTimeUtc,
     pub updated_at: DateTimeUtc,
+    // Day (UTC) that the API usage below was counted on.
+    pub usage_date: Option<Date>,
+    // API requests made & bytes downloaded on `usage_date`.
+    pub num_requests: i64,
+    pub num_bytes: i64,
+    // Syncing & fetching are paused until then after nearing the daily quota.
+    pub paused_until: Option<DateTimeUtc>,
+    // Page token to resume an interrupted sync from. NULL once a sync
+    // completes.
+    pub sync_cursor: Option<String>,
+}
+
+impl Model {
+    /// (requests, bytes) used so far today.
+    pub fn usage_today(&self) -> (i64, i64) {
+        if self.usage_date == Some(Utc::now().naive_utc().date()) {
+            (self.num_requests, self.num_bytes)
+        } else {
+            (0, 0)
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_until.map_or(false, |until| until > Utc::now())
+    }
 }
 
 #[derive(Copy, Clone, Debug, EnumIter)]
@@ -70,6 +103,8 @@ impl ActiveModel {
             granted_at: Set(chrono::Utc::now()),
             created_at: Set(chrono::Utc::now()),
             updated_at: Set(chrono::Utc::now()),
+            num_requests: Set(0),
+            num_bytes: Set(0),
             ..Default::default()
         }
     }
@@ -86,3 +121,172 @@ pub async fn get_by_id(
         .one(db)
         .await
 }
+
+/// Checkpoint sync progress so it can resume after a restart. A `None` cursor
+/// marks the sync as complete.
+pub async fn save_sync_cursor(
+    db: &DatabaseConnection,
+    api_id: &str,
+    account: &str,
+    cursor: Option<String>,
+) -> Result<(), sea_orm::DbErr> {
+    Entity::update_many()
+        .col_expr(Column::SyncCursor, sea_orm::sea_query::Expr::value(cursor))
+        .filter(Column::ApiId.eq(api_id))
+        .filter(Column::Account.eq(account))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Count API usage against today's totals, pausing the connection until
+/// tomorrow (UTC) if it's close to its quota.
+pub async fn record_usage(
+    db: &DatabaseConnection,
+    api_id: &str,
+    account: &str,
+    num_requests: u64,
+    num_bytes: u64,
+    quota: Option<&ConnectionQuota>,
+) -> Result<Option<Model>, sea_orm::DbErr> {
+    let today = Utc::now().naive_utc().date();
+    db.execute(Statement::from_sql_and_values(
+        DbBackend::Sqlite,
+        RECORD_USAGE,
+        vec![
+            today.into(),
+            (num_requests as i64).into(),
+            today.into(),
+            (num_bytes as i64).into(),
+            today.into(),
+            api_id.into(),
+            account.into(),
+        ],
+    ))
+    .await?;
+
+    let conn = match get_by_id(db, api_id, account).await? {
+        Some(conn) => conn,
+        None => return Ok(None),
+    };
+
+    let (num_requests, num_bytes) = conn.usage_today();
+    let is_exhausted = quota.map_or(false, |quota| {
+        quota.is_exhausted(num_requests as u64, num_bytes as u64)
+    });
+
+    if is_exhausted && !conn.is_paused() {
+        log::warn!(
+            "{} ({}) is close to its API quota, pausing until tomorrow",
+            api_id,
+            account
+        );
+
+        let tomorrow = (today + Duration::days(1))
+            .and_hms_opt(0, 0, 0)
+            .map(|midnight| DateTime::<Utc>::from_utc(midnight, Utc));
+        let mut update: ActiveModel = conn.into();
+        update.paused_until = Set(tomorrow);
+        return update.update(db).await.map(Some);
+    }
+
+    Ok(Some(conn))
+}
+
+#[cfg(test)]
+mod test {
+    use shared::config::ConnectionQuota;
+
+    use crate::models::connection;
+    use crate::test::setup_test_db;
+    use sea_orm::ActiveModelTrait;
+
+    #[tokio::test]
+    async fn test_record_usage() {
+        let db = setup_test_db().await;
+        connection::ActiveModel::new(
+            "drive.google.com".into(),
+            "user@example.com".into(),
+            "token".into(),
+            None,
+            None,
+            Vec::new(),
+        )
+        .insert(&db)
+        .await
+        .expect("Unable to insert connection");
+
+        let quota = ConnectionQuota {
+            requests_per_day: Some(10),
+            bytes_per_day: None,
+        };
+
+        let conn = connection::record_usage(
+            &db,
+            "drive.google.com",
+            "user@example.com",
+            5,
+            1024,
+            Some(&quota),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(conn.usage_today(), (5, 1024));
+        assert!(!conn.is_paused());
+
+        let conn = connection::record_usage(
+            &db,
+            "drive.google.com",
+            "user@example.com",
+            4,
+            0,
+            Some(&quota),
+        )
+        .await
+        .unwrap()
+        .unwrap();
+        assert_eq!(conn.usage_today(), (9, 1024));
+        assert!(conn.is_paused());
+    }
+
+    #[tokio::test]
+    async fn test_save_sync_cursor() {
+        let db = setup_test_db().await;
+        connection::ActiveModel::new(
+            "drive.google.com".into(),
+            "user@example.com".into(),
+            "token".into(),
+            None,
+            None,
+            Vec::new(),
+        )
+        .insert(&db)
+        .await
+        .expect("Unable to insert connection");
+
+        connection::save_sync_cursor(
+            &db,
+            "drive.google.com",
+            "user@example.com",
+            Some("page-2".into()),
+        )
+        .await
+        .unwrap();
+        let conn = connection::get_by_id(&db, "drive.google.com", "user@example.com")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(conn.sync_cursor, Some("page-2".to_string()));
+
+        connection::save_sync_cursor(&db, "drive.google.com", "user@example.com", None)
+            .await
+            .unwrap();
+        let conn = connection::get_by_id(&db, "drive.google.com", "user@example.com")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(conn.sync_cursor, None);
+    }
+}
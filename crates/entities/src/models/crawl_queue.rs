@@ -2,21 +2,32 @@ use std::collections::HashSet;
 
 use regex::RegexSet;
 use sea_orm::entity::prelude::*;
-use sea_orm::sea_query::{OnConflict, SqliteQueryBuilder};
+use sea_orm::sea_query::OnConflict;
 use sea_orm::{
-    sea_query, ConnectionTrait, DbBackend, FromQueryResult, InsertResult, QueryOrder, QueryTrait,
-    Set, Statement,
+    sea_query, Condition, ConnectionTrait, DbBackend, FromQueryResult, InsertResult, QueryOrder,
+    QuerySelect, QueryTrait, Set, Statement, TransactionTrait,
 };
 use serde::{Deserialize, Serialize};
 use url::Url;
 
 use super::crawl_tag;
+use super::domain_fetch;
+use super::domain_stats;
 use super::indexed_document;
-use super::tag::{self, get_or_create, TagPair};
+use super::robots;
+use super::tag::{self, get_or_create, TagPair, TagType};
 use shared::config::{LensConfig, LensRule, Limit, UserSettings};
-use shared::regex::{regex_for_domain, regex_for_prefix};
+use shared::normalize::UrlNormalizer;
+use shared::regex::{
+    regex_for_domain, regex_for_path, regex_for_prefix, CREDENTIAL_STORE_PATTERNS,
+};
 
-const MAX_RETRIES: u8 = 5;
+/// Retries for errors that tend to be temporary, see `TaskErrorType::max_retries`.
+const DEFAULT_MAX_RETRIES: u8 = 5;
+/// Longest we'll wait before retrying a failed task.
+const MAX_RETRY_BACKOFF_HOURS: i64 = 24;
+/// Cap on robots.txt crawl-delays so a site can't stall its queue for hours.
+const MAX_CRAWL_DELAY_MS: i64 = 60_000;
 const BATCH_SIZE: usize = 5_000;
 
 #[derive(Debug, Clone, PartialEq, EnumIter, DeriveActiveEnum, Serialize, Deserialize, Eq)]
@@ -30,12 +41,94 @@ pub enum TaskErrorType {
     Parse,
     #[sea_orm(string_value = "Tag")]
     Tag,
+    /// Request timed out, the host may be down or the network flaky.
+    #[sea_orm(string_value = "NetworkTimeout")]
+    NetworkTimeout,
+    /// Invalid/expired certificate or failed TLS handshake.
+    #[sea_orm(string_value = "TlsError")]
+    TlsError,
+    /// Server responded w/ an error status.
+    #[sea_orm(string_value = "HttpStatus")]
+    HttpStatus,
+    #[sea_orm(string_value = "NotFound")]
+    NotFound,
+    #[sea_orm(string_value = "RobotsBlocked")]
+    RobotsBlocked,
+    /// File type or URL scheme we don't know how to handle.
+    #[sea_orm(string_value = "ParserUnsupported")]
+    ParserUnsupported,
+    /// Out of disk space when writing to the index.
+    #[sea_orm(string_value = "IndexFull")]
+    IndexFull,
+    /// Connection is paused after nearing its daily API quota.
+    #[sea_orm(string_value = "QuotaExceeded")]
+    QuotaExceeded,
+    #[sea_orm(string_value = "Other")]
+    Other,
+}
+
+impl TaskErrorType {
+    /// How long to wait before the first retry. Network issues tend to clear
+    /// up quickly, parse failures less so.
+    fn base_backoff(&self) -> chrono::Duration {
+        match self {
+            TaskErrorType::Fetch | TaskErrorType::NetworkTimeout | TaskErrorType::HttpStatus => {
+                chrono::Duration::minutes(1)
+            }
+            TaskErrorType::Parse => chrono::Duration::minutes(15),
+            _ => chrono::Duration::minutes(5),
+        }
+    }
+
+    /// How many times to retry a task that failed w/ this error. Only network
+    /// issues are retried unless the user settings say otherwise.
+    pub fn max_retries(&self, settings: &UserSettings) -> u8 {
+        if let Some(max_retries) = settings.max_retries.get(&self.to_value()) {
+            return *max_retries;
+        }
+
+        match self {
+            TaskErrorType::Fetch | TaskErrorType::NetworkTimeout => DEFAULT_MAX_RETRIES,
+            _ => 0,
+        }
+    }
+
+    /// Time to wait before retrying a task that has already been retried
+    /// `num_retries` times, doubling w/ each retry.
+    pub fn backoff(&self, num_retries: u8) -> chrono::Duration {
+        let backoff = self.base_backoff() * 2_i32.saturating_pow(num_retries.into());
+        backoff.min(chrono::Duration::hours(MAX_RETRY_BACKOFF_HOURS))
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, FromJsonQueryResult)]
 pub struct TaskError {
-    error_type: TaskErrorType,
-    msg: String,
+    pub error_type: TaskErrorType,
+    pub msg: String,
+}
+
+impl TaskError {
+    pub fn new(error_type: TaskErrorType, msg: &str) -> Self {
+        Self {
+            error_type,
+            msg: msg.to_string(),
+        }
+    }
+}
+
+/// Details of the response a page was last fetched from, kept so fetches can
+/// be audited & debugged.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, FromJsonQueryResult)]
+pub struct FetchInfo {
+    /// HTTP status of the final response.
+    pub status: u16,
+    /// URL the response came from, after following redirects.
+    pub final_url: String,
+    /// `Content-Type` header of the response, as sent.
+    pub content_type: Option<String>,
+    /// Time taken to fetch the response, including the body.
+    pub duration_ms: u64,
+    pub fetched_at: DateTimeUtc,
 }
 
 #[derive(Debug, Clone, PartialEq, EnumIter, DeriveActiveEnum, Serialize, Eq)]
@@ -49,6 +142,10 @@ pub enum CrawlStatus {
     Completed,
     #[sea_orm(string_value = "Failed")]
     Failed,
+    /// Crawled, but the page asked not to be indexed, e.g. w/ a `noindex`
+    /// robots meta tag.
+    #[sea_orm(string_value = "Skipped")]
+    Skipped,
 }
 
 #[derive(Debug, Clone, PartialEq, EnumIter, DeriveActiveEnum, Serialize, Eq)]
@@ -68,6 +165,37 @@ impl Default for CrawlType {
     }
 }
 
+/// Where a task came from, used to weight the order tasks are dequeued in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TaskPriority {
+    /// Added by the user, e.g. through the UI or the browser extension.
+    User,
+    /// Seed URLs & bootstrapped URLs of a lens.
+    Lens,
+    /// Links found while crawling, plugins, connections, etc.
+    Normal,
+    /// Refreshing something that's already been indexed.
+    Recrawl,
+}
+
+impl Default for TaskPriority {
+    fn default() -> Self {
+        TaskPriority::Normal
+    }
+}
+
+impl TaskPriority {
+    /// Higher weights are dequeued first.
+    pub fn weight(&self) -> i32 {
+        match self {
+            TaskPriority::User => 100,
+            TaskPriority::Lens => 50,
+            TaskPriority::Normal => 0,
+            TaskPriority::Recrawl => -50,
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Eq)]
 #[sea_orm(table_name = "crawl_queue")]
 pub struct Model {
@@ -87,13 +215,20 @@ pub struct Model {
     /// Number of retries for this task.
     #[sea_orm(default_value = 0)]
     pub num_retries: u8,
+    /// Failed tasks aren't retried before this time.
+    pub next_retry_at: Option<DateTimeUtc>,
     /// Crawl Type
     pub crawl_type: CrawlType,
+    /// Weight of this task, see `TaskPriority`. Higher goes first.
+    #[sea_orm(default_value = 0)]
+    pub priority: i32,
     /// When this was first added to the crawl queue.
     pub created_at: DateTimeUtc,
     /// When this task was last updated.
     pub updated_at: DateTimeUtc,
     pub pipeline: Option<String>,
+    /// Response the task was last fetched from, for web pages.
+    pub fetch_info: Option<FetchInfo>,
 }
 
 impl Related<super::tag::Entity> for Entity {
@@ -124,6 +259,7 @@ impl ActiveModelBehavior for ActiveModel {
     fn new() -> Self {
         Self {
             crawl_type: Set(CrawlType::Normal),
+            priority: Set(TaskPriority::Normal.weight()),
             status: Set(CrawlStatus::Queued),
             created_at: Set(chrono::Utc::now()),
             updated_at: Set(chrono::Utc::now()),
@@ -186,11 +322,12 @@ pub async fn queue_stats(
     db: &DatabaseConnection,
 ) -> anyhow::Result<Vec<QueueCountByStatus>, sea_orm::DbErr> {
     let res = Entity::find()
-        .from_raw_sql(Statement::from_string(
-            DbBackend::Sqlite,
-            "SELECT count(*) as count, domain, status FROM crawl_queue GROUP BY domain, status"
-                .into(),
-        ))
+        .select_only()
+        .column_as(Column::Id.count(), "count")
+        .column(Column::Domain)
+        .column(Column::Status)
+        .group_by(Column::Domain)
+        .group_by(Column::Status)
         .into_model::<QueueCountByStatus>()
         .all(db)
         .await?;
@@ -198,14 +335,34 @@ pub async fn queue_stats(
     Ok(res)
 }
 
-pub async fn reset_processing(db: &DatabaseConnection) -> anyhow::Result<()> {
-    Entity::update_many()
+/// Requeue all in-flight tasks, returns the number of tasks requeued.
+pub async fn reset_processing(db: &DatabaseConnection) -> anyhow::Result<u64> {
+    let res = Entity::update_many()
         .col_expr(Column::Status, sea_query::Expr::value(CrawlStatus::Queued))
         .filter(Column::Status.eq(CrawlStatus::Processing))
         .exec(db)
         .await?;
 
-    Ok(())
+    Ok(res.rows_affected)
+}
+
+/// Requeue tasks that have been processing for longer than `timeout`, most
+/// likely left behind by a crawl that crashed or hung. Returns the number of
+/// tasks requeued.
+pub async fn reset_stale_processing(
+    db: &DatabaseConnection,
+    timeout: chrono::Duration,
+) -> anyhow::Result<u64> {
+    let now = chrono::Utc::now();
+    let res = Entity::update_many()
+        .col_expr(Column::Status, sea_query::Expr::value(CrawlStatus::Queued))
+        .col_expr(Column::UpdatedAt, sea_query::Expr::value(now))
+        .filter(Column::Status.eq(CrawlStatus::Processing))
+        .filter(Column::UpdatedAt.lt(now - timeout))
+        .exec(db)
+        .await?;
+
+    Ok(res.rows_affected)
 }
 
 #[derive(Debug, FromQueryResult)]
@@ -227,13 +384,38 @@ pub async fn num_queued(
     Ok(res)
 }
 
+/// Queued tasks that aren't waiting on a retry backoff.
+pub async fn num_ready(db: &DatabaseConnection) -> anyhow::Result<u64, sea_orm::DbErr> {
+    Entity::find()
+        .filter(Column::Status.eq(CrawlStatus::Queued))
+        .filter(
+            Condition::any()
+                .add(Column::NextRetryAt.is_null())
+                .add(Column::NextRetryAt.lte(chrono::Utc::now())),
+        )
+        .count(db)
+        .await
+}
+
+/// Values are always bound as parameters, in the order they appear in the
+/// query. The query is written for SQLite, the only database we support.
 fn gen_dequeue_sql(user_settings: UserSettings) -> Statement {
+    let now = chrono::Utc::now();
     Statement::from_sql_and_values(
         DbBackend::Sqlite,
         include_str!("sql/dequeue.sqlx"),
         vec![
+            CrawlStatus::Processing.into_value().into(),
+            TagType::Lens.into_value().into(),
+            CrawlStatus::Processing.into_value().into(),
             user_settings.domain_crawl_limit.value().into(),
             user_settings.inflight_domain_limit.value().into(),
+            CrawlStatus::Queued.into_value().into(),
+            now.into(),
+            now.into(),
+            now.into(),
+            domain_stats::MIN_FETCHES.into(),
+            domain_stats::FAILING_SUCCESS_RATE.into(),
         ],
     )
 }
@@ -304,11 +486,19 @@ pub async fn dequeue(
         }
     }
 
+    let crawl_delay_ms = user_settings.domain_crawl_delay_ms as i64;
+
     // Prioritize any bootstrapping tasks first.
     let entity = {
         let result = Entity::find()
             .filter(Column::Status.eq(CrawlStatus::Queued))
             .filter(Column::CrawlType.eq(CrawlType::Bootstrap))
+            .filter(
+                Condition::any()
+                    .add(Column::NextRetryAt.is_null())
+                    .add(Column::NextRetryAt.lte(chrono::Utc::now())),
+            )
+            .order_by_desc(Column::Priority)
             .one(db)
             .await?;
 
@@ -325,6 +515,10 @@ pub async fn dequeue(
 
     // Grab new entity and immediately mark in-progress
     if let Some(task) = entity {
+        if task.crawl_type != CrawlType::Bootstrap {
+            record_domain_fetch(db, &task, crawl_delay_ms).await?;
+        }
+
         let mut update: ActiveModel = task.into();
         update.status = Set(CrawlStatus::Processing);
         return match update.update(db).await {
@@ -340,6 +534,30 @@ pub async fn dequeue(
     Ok(None)
 }
 
+/// Hold off on the task's domain until its crawl delay has passed. Only
+/// applies to web URLs, local files & API tasks aren't rate limited here.
+async fn record_domain_fetch(
+    db: &DatabaseConnection,
+    task: &Model,
+    crawl_delay_ms: i64,
+) -> Result<(), DbErr> {
+    let is_web = Url::parse(&task.url)
+        .map(|url| url.scheme() == "http" || url.scheme() == "https")
+        .unwrap_or(false);
+    if !is_web {
+        return Ok(());
+    }
+
+    let robots_delay_ms = robots::find_by_domain(db, &task.domain)
+        .await?
+        .and_then(|robots| robots.crawl_delay_ms)
+        .unwrap_or_default()
+        .min(MAX_CRAWL_DELAY_MS);
+
+    let delay = chrono::Duration::milliseconds(crawl_delay_ms.max(robots_delay_ms));
+    domain_fetch::record_fetch(db, &task.domain, delay).await
+}
+
 pub async fn dequeue_recrawl(
     db: &DatabaseConnection,
     user_settings: &UserSettings,
@@ -385,6 +603,150 @@ pub async fn dequeue_recrawl(
     Ok(None)
 }
 
+/// Grab the web page in `lens` that's gone the longest w/o being fetched, if
+/// that's been over `refresh_interval`, so it can be refetched.
+pub async fn dequeue_refresh(
+    db: &DatabaseConnection,
+    user_settings: &UserSettings,
+    lens: &str,
+    refresh_interval: chrono::Duration,
+) -> anyhow::Result<Option<Model>, DbErr> {
+    // Check for inflight limits
+    if let Limit::Finite(inflight_crawl_limit) = user_settings.inflight_crawl_limit {
+        let num_in_progress = num_tasks_in_progress(db).await?;
+        if num_in_progress >= inflight_crawl_limit as u64 {
+            return Ok(None);
+        }
+    }
+
+    let now = chrono::Utc::now();
+    let task = Entity::find()
+        .from_raw_sql(Statement::from_sql_and_values(
+            db.get_database_backend(),
+            include_str!("sql/dequeue_refresh.sqlx"),
+            vec![
+                CrawlStatus::Completed.into_value().into(),
+                TagType::Lens.into_value().into(),
+                lens.into(),
+                (now - refresh_interval).into(),
+                now.into(),
+            ],
+        ))
+        .one(db)
+        .await?;
+
+    if let Some(task) = task {
+        record_domain_fetch(db, &task, user_settings.domain_crawl_delay_ms as i64).await?;
+
+        let mut update: ActiveModel = task.into();
+        update.status = Set(CrawlStatus::Processing);
+        return match update.update(db).await {
+            Ok(model) => Ok(Some(model)),
+            // Deleted while being processed?
+            Err(err) => {
+                log::error!("Unable to update crawl task: {}", err);
+                Ok(None)
+            }
+        };
+    }
+
+    Ok(None)
+}
+
+/// Grab the task for `url` so it can be recrawled right away, adding it to the
+/// queue if needed. Returns None if the task is already being processed.
+pub async fn start_recrawl(db: &DatabaseConnection, url: &str) -> Result<Option<Model>, DbErr> {
+    let task = Entity::find().filter(Column::Url.eq(url)).one(db).await?;
+    match task {
+        Some(task) if task.status == CrawlStatus::Processing => Ok(None),
+        Some(task) => {
+            let mut update: ActiveModel = task.into();
+            update.status = Set(CrawlStatus::Processing);
+            Ok(Some(update.update(db).await?))
+        }
+        None => {
+            let parsed = Url::parse(url).map_err(|err| DbErr::Custom(err.to_string()))?;
+            let new_task = ActiveModel {
+                domain: Set(parsed.host_str().unwrap_or("localhost").to_string()),
+                url: Set(url.to_string()),
+                status: Set(CrawlStatus::Processing),
+                priority: Set(TaskPriority::Recrawl.weight()),
+                ..Default::default()
+            };
+            Ok(Some(new_task.insert(db).await?))
+        }
+    }
+}
+
+/// Queue `url` to be crawled next, even if it's already been indexed or isn't
+/// part of any lens. Returns None if the URL can't be crawled at all, e.g.
+/// it's invalid, blocked or disallowed by robots.txt. A task that's already
+/// being crawled is returned as is.
+pub async fn force_enqueue(
+    db: &DatabaseConnection,
+    url: &str,
+    settings: &UserSettings,
+    pipeline: Option<String>,
+    tags: &[TagPair],
+) -> Result<Option<Model>, DbErr> {
+    let overrides = EnqueueSettings {
+        force_allow: true,
+        is_recrawl: true,
+        priority: TaskPriority::User,
+        ..Default::default()
+    };
+
+    let url = match filter_urls(&[], settings, &overrides, &[url.to_string()]).pop() {
+        Some(url) => url,
+        None => return Ok(None),
+    };
+
+    // Requeuing it now would crawl it twice at the same time.
+    if let Some(task) = Entity::find()
+        .filter(Column::Url.eq(url.clone()))
+        .one(db)
+        .await?
+    {
+        if task.status == CrawlStatus::Processing {
+            return Ok(Some(task));
+        }
+    }
+
+    enqueue_all(
+        db,
+        &[url.clone()],
+        &[],
+        settings,
+        &overrides,
+        pipeline.clone(),
+    )
+    .await?;
+
+    let task = match Entity::find().filter(Column::Url.eq(url)).one(db).await? {
+        Some(task) => task,
+        None => return Ok(None),
+    };
+
+    // Start over, even if the task had failed or was waiting to be retried.
+    let mut update: ActiveModel = task.into();
+    update.status = Set(CrawlStatus::Queued);
+    update.priority = Set(TaskPriority::User.weight());
+    update.num_retries = Set(0);
+    update.next_retry_at = Set(None);
+    update.error = Set(None);
+    if pipeline.is_some() {
+        update.pipeline = Set(pipeline);
+    }
+    let task = update.update(db).await?;
+
+    if !tags.is_empty() {
+        let update: ActiveModel = task.clone().into();
+        update.insert_tags(db, tags).await?;
+    }
+
+    Ok(Some(task))
+}
+
 /// Add url to the crawl queue
 #[derive(PartialEq, Eq)]
 pub enum SkipReason {
@@ -399,81 +761,192 @@ pub struct EnqueueSettings {
     pub tags: Vec<TagPair>,
     pub force_allow: bool,
     pub is_recrawl: bool,
+    pub priority: TaskPriority,
 }
 
-fn filter_urls(
-    lenses: &[LensConfig],
-    settings: &UserSettings,
-    overrides: &EnqueueSettings,
-    urls: &[String],
-) -> Vec<String> {
-    let mut allow_list: Vec<String> = Vec::new();
-    let mut skip_list: Vec<String> = Vec::new();
-    let mut restrict_list: Vec<String> = Vec::new();
+/// Credential stores & any paths the user has asked us to never index.
+pub fn path_denylist(settings: &UserSettings) -> RegexSet {
+    let mut denylist = CREDENTIAL_STORE_PATTERNS
+        .iter()
+        .map(|pattern| pattern.to_string())
+        .collect::<Vec<String>>();
+    denylist.extend(
+        settings
+            .index_denylist
+            .iter()
+            .map(|path| regex_for_path(path)),
+    );
+
+    RegexSet::new(denylist).expect("Unable to create path denylist")
+}
 
-    for domain in settings.block_list.iter() {
-        skip_list.push(regex_for_domain(domain));
+/// Is this a file URL that points into the denylist?
+pub fn is_denied_path(denylist: &RegexSet, url: &Url) -> bool {
+    if url.scheme() != "file" {
+        return false;
     }
 
-    for lens in lenses {
-        let ruleset = create_ruleset_from_lens(lens);
-        allow_list.extend(ruleset.allow_list);
-        skip_list.extend(ruleset.skip_list);
-        restrict_list.extend(ruleset.restrict_list);
+    match url.to_file_path() {
+        Ok(path) => denylist.is_match(&path.to_string_lossy().replace('\\', "/")),
+        // Can't tell where this points, so don't risk it.
+        Err(_) => true,
     }
+}
 
-    let allow_list = RegexSet::new(allow_list).expect("Unable to create allow list");
-    let skip_list = RegexSet::new(skip_list).expect("Unable to create skip list");
-    let restrict_list = RegexSet::new(restrict_list).expect("Unable to create restrict list");
+/// Why a URL is left out of the crawl queue.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FilterReason {
+    /// Not a valid URL or a scheme we can't handle.
+    Unsupported,
+    /// Credential store or a path the user asked us to never index.
+    DeniedPath,
+    /// Domain is on the user's block list.
+    BlockedDomain,
+    /// Matches a lens `SkipURL` rule.
+    SkipRule,
+    /// Deeper than a lens `LimitURLDepth` rule allows.
+    Restricted,
+    /// Not part of any lens & external links aren't crawled.
+    NotInLens,
+}
 
-    // Ignore invalid URLs
-    urls.iter()
-        .filter_map(|url| {
-            if let Ok(mut parsed) = Url::parse(url) {
-                // Check that we can handle this scheme
-                if parsed.scheme() != "http"
-                    && parsed.scheme() != "https"
-                    && parsed.scheme() != "file"
-                    && parsed.scheme() != "api"
-                {
-                    return None;
-                }
+impl FilterReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Unsupported => "Unsupported",
+            Self::DeniedPath => "DeniedPath",
+            Self::BlockedDomain => "BlockedDomain",
+            Self::SkipRule => "SkipRule",
+            Self::Restricted => "Restricted",
+            Self::NotInLens => "NotInLens",
+        }
+    }
+}
 
-                // Always ignore fragments, otherwise crawling
-                // https://wikipedia.org/Rust#Blah would be considered different than
-                // https://wikipedia.org/Rust
-                parsed.set_fragment(None);
+/// Lens rules & user settings that decide which URLs get crawled.
+pub struct UrlFilter {
+    allow_list: RegexSet,
+    block_list: RegexSet,
+    skip_list: RegexSet,
+    restrict_list: RegexSet,
+    path_denylist: RegexSet,
+    crawl_external_links: bool,
+    force_allow: bool,
+    normalizer: UrlNormalizer,
+}
 
-                let normalized = parsed.to_string();
+impl UrlFilter {
+    pub fn new(lenses: &[LensConfig], settings: &UserSettings, force_allow: bool) -> Self {
+        let mut allow_list: Vec<String> = Vec::new();
+        let mut skip_list: Vec<String> = Vec::new();
+        let mut restrict_list: Vec<String> = Vec::new();
+
+        for lens in lenses {
+            let ruleset = create_ruleset_from_lens(lens);
+            allow_list.extend(ruleset.allow_list);
+            skip_list.extend(ruleset.skip_list);
+            restrict_list.extend(ruleset.restrict_list);
+        }
 
-                // Ignore domains on blacklist
-                if skip_list.is_match(&normalized)
-                    // Skip if any URLs do not match this restriction
-                    || (!restrict_list.is_empty()
-                        && !restrict_list.is_match(&normalized))
-                {
-                    return None;
-                }
+        let block_list = settings
+            .block_list
+            .iter()
+            .map(|domain| regex_for_domain(domain));
+
+        UrlFilter {
+            allow_list: RegexSet::new(allow_list).expect("Unable to create allow list"),
+            block_list: RegexSet::new(block_list).expect("Unable to create block list"),
+            skip_list: RegexSet::new(skip_list).expect("Unable to create skip list"),
+            restrict_list: RegexSet::new(restrict_list).expect("Unable to create restrict list"),
+            path_denylist: path_denylist(settings),
+            crawl_external_links: settings.crawl_external_links,
+            force_allow,
+            normalizer: UrlNormalizer::new(lenses),
+        }
+    }
 
-                // Should we crawl external links?
-                if settings.crawl_external_links {
-                    return Some(normalized);
-                }
+    /// The normalized URL to crawl, or why it shouldn't be.
+    pub fn check(&self, url: &str) -> Result<String, FilterReason> {
+        let mut parsed = Url::parse(url).map_err(|_| FilterReason::Unsupported)?;
+        // Check that we can handle this scheme
+        if parsed.scheme() != "http"
+            && parsed.scheme() != "https"
+            && parsed.scheme() != "file"
+            && parsed.scheme() != "api"
+            && parsed.scheme() != "import"
+        {
+            return Err(FilterReason::Unsupported);
+        }
 
-                // If external links are not allowed, only allow crawls specified
-                // in our lenses
-                if overrides.force_allow
-                    || (!allow_list.is_empty() && allow_list.is_match(&normalized))
-                {
-                    return Some(normalized);
-                }
-            }
+        // Always ignore fragments, otherwise crawling
+        // https://wikipedia.org/Rust#Blah would be considered different than
+        // https://wikipedia.org/Rust
+        parsed.set_fragment(None);
+        // Lenses can treat variants of a URL as the same page
+        self.normalizer.normalize_url(&mut parsed);
 
-            None
-        })
+        // Never index credential stores, even when forced
+        if is_denied_path(&self.path_denylist, &parsed) {
+            return Err(FilterReason::DeniedPath);
+        }
+
+        let normalized = parsed.to_string();
+
+        // Ignore domains on blacklist
+        if self.block_list.is_match(&normalized) {
+            return Err(FilterReason::BlockedDomain);
+        }
+
+        if self.skip_list.is_match(&normalized) {
+            return Err(FilterReason::SkipRule);
+        }
+
+        // Skip if any URLs do not match this restriction
+        if !self.restrict_list.is_empty() && !self.restrict_list.is_match(&normalized) {
+            return Err(FilterReason::Restricted);
+        }
+
+        // Should we crawl external links? If not, only allow crawls specified
+        // in our lenses
+        if self.crawl_external_links
+            || self.force_allow
+            || (!self.allow_list.is_empty() && self.allow_list.is_match(&normalized))
+        {
+            return Ok(normalized);
+        }
+
+        Err(FilterReason::NotInLens)
+    }
+}
+
+fn filter_urls(
+    lenses: &[LensConfig],
+    settings: &UserSettings,
+    overrides: &EnqueueSettings,
+    urls: &[String],
+) -> Vec<String> {
+    let filter = UrlFilter::new(lenses, settings, overrides.force_allow);
+    urls.iter()
+        .filter_map(|url| filter.check(url).ok())
         .collect::<Vec<String>>()
 }
 
+/// URL rules for lenses that send their URLs through a specific pipeline.
+fn lens_pipelines(lenses: &[LensConfig]) -> Vec<(RegexSet, String)> {
+    lenses
+        .iter()
+        .filter_map(|lens| {
+            let pipeline = lens.pipeline.clone()?;
+            let rules = RegexSet::new(create_ruleset_from_lens(lens).allow_list)
+                .expect("Unable to create lens allow list");
+            Some((rules, pipeline))
+        })
+        .collect()
+}
+
+/// Enqueue `urls`, skipping any that are invalid, blocked, or already indexed.
+/// URLs are processed by `pipeline` if set, otherwise by the pipeline of the
+/// first lens they belong to, if any.
 pub async fn enqueue_all(
     db: &DatabaseConnection,
     urls: &[String],
@@ -484,6 +957,21 @@ pub async fn enqueue_all(
 ) -> anyhow::Result<(), sea_orm::DbErr> {
     // Filter URLs
     let urls = filter_urls(lenses, settings, overrides, urls);
+    let lens_pipelines = lens_pipelines(lenses);
+
+    // Skip anything the site's robots.txt disallows
+    let disallowed = robots::disallowed(db, &urls).await?;
+    let urls = if disallowed.is_empty() {
+        urls
+    } else {
+        log::debug!(
+            "skipping {} urls disallowed by robots.txt",
+            disallowed.len()
+        );
+        urls.into_iter()
+            .filter(|url| !disallowed.contains(url))
+            .collect()
+    };
 
     // Ignore urls already indexed
     let mut is_indexed: HashSet<String> = HashSet::with_capacity(urls.len());
@@ -512,11 +1000,19 @@ pub async fn enqueue_all(
                         _ => parsed.host_str().expect("Invalid URL host"),
                     };
 
+                    let pipeline = pipeline.clone().or_else(|| {
+                        lens_pipelines
+                            .iter()
+                            .find(|(rules, _)| rules.is_match(&url))
+                            .map(|(_, pipeline)| pipeline.clone())
+                    });
+
                     result = Some(ActiveModel {
                         domain: Set(domain.to_string()),
                         crawl_type: Set(overrides.crawl_type.clone()),
+                        priority: Set(overrides.priority.weight()),
                         url: Set(url.to_string()),
-                        pipeline: Set(pipeline.clone()),
+                        pipeline: Set(pipeline),
                         ..Default::default()
                     });
                 }
@@ -530,8 +1026,20 @@ pub async fn enqueue_all(
     }
 
     let on_conflict = if overrides.is_recrawl {
+        // Requeue, w/o lowering the priority of a task that's already waiting.
         OnConflict::column(Column::Url)
-            .update_column(Column::Status)
+            .update_exprs(vec![
+                (
+                    Column::Status,
+                    sea_query::Expr::cust(r#""excluded"."status""#),
+                ),
+                (
+                    Column::Priority,
+                    sea_query::Expr::cust(
+                        r#"MAX("crawl_queue"."priority", "excluded"."priority")"#,
+                    ),
+                ),
+            ])
             .to_owned()
     } else {
         OnConflict::column(Column::Url).do_nothing().to_owned()
@@ -540,20 +1048,13 @@ pub async fn enqueue_all(
     for to_add in to_add.chunks(BATCH_SIZE) {
         let owned = to_add.iter().map(|r| r.to_owned()).collect::<Vec<_>>();
 
-        let (sql, values) = Entity::insert_many(owned)
-            .query()
-            .on_conflict(on_conflict.clone())
-            .build(SqliteQueryBuilder);
-
-        let values: Vec<Value> = values.iter().map(|x| x.to_owned()).collect();
-        match db
-            .execute(Statement::from_sql_and_values(
-                DbBackend::Sqlite,
-                &sql,
-                values,
-            ))
-            .await
-        {
+        let statement = db.get_database_backend().build(
+            Entity::insert_many(owned)
+                .query()
+                .on_conflict(on_conflict.clone()),
+        );
+
+        match db.execute(statement).await {
             Ok(_) => {}
             Err(e) => log::error!("insert_many error: {:?}", e),
         }
@@ -580,22 +1081,166 @@ pub async fn mark_done(
     }
 }
 
-pub async fn mark_failed(db: &DatabaseConnection, id: i64, retry: bool) {
+/// Mark a task as skipped, it was crawled but shouldn't be indexed.
+pub async fn mark_skipped(db: &DatabaseConnection, id: i64) -> Result<(), DbErr> {
+    if let Some(crawl) = Entity::find_by_id(id).one(db).await? {
+        let mut updated: ActiveModel = crawl.into();
+        updated.status = Set(CrawlStatus::Skipped);
+        updated.update(db).await?;
+    }
+
+    Ok(())
+}
+
+/// Record the response a task was fetched from.
+pub async fn set_fetch_info(
+    db: &DatabaseConnection,
+    id: i64,
+    fetch_info: FetchInfo,
+) -> Result<(), DbErr> {
+    if let Some(crawl) = Entity::find_by_id(id).one(db).await? {
+        let mut updated: ActiveModel = crawl.into();
+        updated.fetch_info = Set(Some(fetch_info));
+        updated.update(db).await?;
+    }
+
+    Ok(())
+}
+
+/// Mark a task as failed, queuing it to be retried later if the retry policy
+/// for its error type allows it.
+pub async fn mark_failed(
+    db: &DatabaseConnection,
+    user_settings: &UserSettings,
+    id: i64,
+    error: Option<TaskError>,
+) {
     if let Ok(Some(crawl)) = Entity::find_by_id(id).one(db).await {
         let mut updated: ActiveModel = crawl.clone().into();
 
-        // Bump up number of retries if this failed
-        if retry && crawl.num_retries <= MAX_RETRIES {
+        let error_type = error
+            .as_ref()
+            .map_or(TaskErrorType::Other, |err| err.error_type.clone());
+        // Queue again, backing off a little more each time it fails
+        if crawl.num_retries < error_type.max_retries(user_settings) {
+            let backoff = error_type.backoff(crawl.num_retries);
             updated.num_retries = Set(crawl.num_retries + 1);
-            // Queue again
+            updated.next_retry_at = Set(Some(chrono::Utc::now() + backoff));
             updated.status = Set(CrawlStatus::Queued);
         } else {
             updated.status = Set(CrawlStatus::Failed);
         }
+        updated.error = Set(error);
+        let _ = updated.update(db).await;
+    }
+}
+
+/// Mark a batch of tasks as completed in a single statement, returns the
+/// number of tasks updated.
+pub async fn mark_many_done(db: &DatabaseConnection, ids: &[i64]) -> Result<u64, DbErr> {
+    if ids.is_empty() {
+        return Ok(0);
+    }
+
+    let res = Entity::update_many()
+        .col_expr(
+            Column::Status,
+            sea_query::Expr::value(CrawlStatus::Completed),
+        )
+        .col_expr(
+            Column::UpdatedAt,
+            sea_query::Expr::value(chrono::Utc::now()),
+        )
+        .filter(Column::Id.is_in(ids.to_vec()))
+        .exec(db)
+        .await?;
+
+    Ok(res.rows_affected)
+}
+
+/// Batch version of `mark_failed`. Tasks that share an error & retry count
+/// are updated together, all within one transaction.
+pub async fn mark_many_failed(
+    db: &DatabaseConnection,
+    user_settings: &UserSettings,
+    failed: &[(i64, TaskError)],
+) -> Result<(), DbErr> {
+    if failed.is_empty() {
+        return Ok(());
+    }
+
+    let tasks = Entity::find()
+        .filter(Column::Id.is_in(failed.iter().map(|(id, _)| *id)))
+        .all(db)
+        .await?;
+
+    let mut groups: Vec<((u8, &TaskError), Vec<i64>)> = Vec::new();
+    for task in &tasks {
+        if let Some((_, error)) = failed.iter().find(|(id, _)| *id == task.id) {
+            let key = (task.num_retries, error);
+            match groups.iter_mut().find(|(group, _)| *group == key) {
+                Some((_, ids)) => ids.push(task.id),
+                None => groups.push((key, vec![task.id])),
+            }
+        }
+    }
+
+    let now = chrono::Utc::now();
+    let txn = db.begin().await?;
+    for ((num_retries, error), ids) in groups {
+        let mut update = Entity::update_many()
+            .col_expr(Column::Error, sea_query::Expr::value(Some(error.clone())))
+            .col_expr(Column::UpdatedAt, sea_query::Expr::value(now))
+            .filter(Column::Id.is_in(ids));
+
+        update = if num_retries < error.error_type.max_retries(user_settings) {
+            let backoff = error.error_type.backoff(num_retries);
+            update
+                .col_expr(Column::Status, sea_query::Expr::value(CrawlStatus::Queued))
+                .col_expr(Column::NumRetries, sea_query::Expr::value(num_retries + 1))
+                .col_expr(
+                    Column::NextRetryAt,
+                    sea_query::Expr::value(Some(now + backoff)),
+                )
+        } else {
+            update.col_expr(Column::Status, sea_query::Expr::value(CrawlStatus::Failed))
+        };
+
+        update.exec(&txn).await?;
+    }
+
+    txn.commit().await
+}
+
+/// Put a task back in the queue w/o counting it as a retry, e.g. when its
+/// connection is paused.
+pub async fn requeue(db: &DatabaseConnection, id: i64, error: Option<TaskError>) {
+    if let Ok(Some(crawl)) = Entity::find_by_id(id).one(db).await {
+        let mut updated: ActiveModel = crawl.into();
+        updated.error = Set(error);
+        updated.status = Set(CrawlStatus::Queued);
         let _ = updated.update(db).await;
     }
 }
 
+/// Most recently failed tasks, optionally only those for `domain`.
+pub async fn list_failed(
+    db: &DatabaseConnection,
+    domain: Option<&str>,
+    limit: u64,
+) -> Result<Vec<Model>, DbErr> {
+    let mut query = Entity::find().filter(Column::Status.eq(CrawlStatus::Failed));
+    if let Some(domain) = domain {
+        query = query.filter(Column::Domain.eq(domain));
+    }
+
+    query
+        .order_by_desc(Column::UpdatedAt)
+        .limit(limit)
+        .all(db)
+        .await
+}
+
 /// Remove tasks from the crawl queue that match `rule`. Rule is expected
 /// to be a SQL like statement.
 pub async fn remove_by_rule(db: &DatabaseConnection, rule: &str) -> anyhow::Result<u64> {
@@ -610,6 +1255,32 @@ pub async fn remove_by_rule(db: &DatabaseConnection, rule: &str) -> anyhow::Resu
     Ok(res.rows_affected)
 }
 
+/// Remove web crawls completed before `cutoff` along w/ their tags, returns
+/// the number of tasks removed. Indexed documents are left alone & local
+/// files are kept since their recrawls are scheduled from the queue.
+pub async fn remove_completed(db: &DatabaseConnection, cutoff: DateTimeUtc) -> anyhow::Result<u64> {
+    let condition = Condition::all()
+        .add(Column::Status.is_in([CrawlStatus::Completed, CrawlStatus::Skipped]))
+        .add(Column::UpdatedAt.lt(cutoff))
+        .add(Column::Url.not_like("file://%"));
+
+    crawl_tag::Entity::delete_many()
+        .filter(
+            crawl_tag::Column::CrawlQueueId.in_subquery(
+                Entity::find()
+                    .select_only()
+                    .column(Column::Id)
+                    .filter(condition.clone())
+                    .into_query(),
+            ),
+        )
+        .exec(db)
+        .await?;
+
+    let res = Entity::delete_many().filter(condition).exec(db).await?;
+    Ok(res.rows_affected)
+}
+
 /// Update the URL of a task. Typically used after a crawl to set the canonical URL
 /// extracted from the crawl result. If there's a conflict, this means another crawl task
 /// already points to this same URL and thus can be safely removed.
@@ -648,17 +1319,21 @@ pub async fn update_or_remove_task(
 #[cfg(test)]
 mod test {
     use sea_orm::prelude::*;
-    use sea_orm::{ActiveModelTrait, Set};
+    use sea_orm::{ActiveModelTrait, QueryOrder, Set};
     use url::Url;
 
-    use shared::config::{LensConfig, LensRule, Limit, UserSettings};
+    use shared::config::{LensConfig, LensRule, Limit, UrlNormalization, UserSettings};
     use shared::regex::{regex_for_robots, WildcardType};
 
-    use crate::models::crawl_queue::CrawlType;
-    use crate::models::{crawl_queue, indexed_document};
+    use crate::models::crawl_queue::{CrawlType, TaskPriority};
+    use crate::models::tag::TagType;
+    use crate::models::{
+        connection, crawl_queue, crawl_tag, domain_fetch, domain_stats, indexed_document, robots,
+        tag,
+    };
     use crate::test::setup_test_db;
 
-    use super::{filter_urls, gen_dequeue_sql, EnqueueSettings};
+    use super::{filter_urls, gen_dequeue_sql, EnqueueSettings, FilterReason, UrlFilter};
 
     #[tokio::test]
     async fn test_insert() {
@@ -687,11 +1362,129 @@ mod test {
     #[test]
     fn test_priority_sql() {
         let settings = UserSettings::default();
-        let sql = gen_dequeue_sql(settings);
-        assert_eq!(
-            sql.to_string(),
-            "WITH\nindexed AS (\n    SELECT\n        domain,\n        count(*) as count\n    FROM indexed_document\n    GROUP BY domain\n),\ninflight AS (\n    SELECT\n        domain,\n        count(*) as count\n    FROM crawl_queue\n    WHERE status = \"Processing\"\n    GROUP BY domain\n)\nSELECT\n    cq.*\nFROM crawl_queue cq\nLEFT JOIN indexed ON indexed.domain = cq.domain\nLEFT JOIN inflight ON inflight.domain = cq.domain\nWHERE\n    COALESCE(indexed.count, 0) < 500000 AND\n    COALESCE(inflight.count, 0) < 2 AND\n    status = \"Queued\"\nORDER BY\n    cq.updated_at ASC"
+        let sql = gen_dequeue_sql(settings).to_string();
+        assert!(sql.contains("COALESCE(indexed.count, 0) < 500000 AND"));
+        assert!(sql.contains("COALESCE(inflight.count, 0) < 2 AND"));
+        // Paused connections are compared against the current time
+        assert!(sql.contains("WHERE conn.paused_until > '"));
+        // As are domains cooling down between fetches
+        assert!(sql.contains("AND df.next_fetch_at > '"));
+        assert!(sql.contains("ORDER BY\n    -- User added tasks first, recrawls last. See `TaskPriority`\n    cq.priority DESC,"));
+        assert!(sql.contains("(COALESCE(ds.num_fetches, 0) >= 10 AND ds.success_rate < 0.25) ASC,"));
+        assert!(sql.ends_with("COALESCE(lens_inflight.count, 0) ASC,\n    cq.updated_at ASC"));
+    }
+
+    #[tokio::test]
+    async fn test_dequeue_failing_domains_last() {
+        let settings = UserSettings {
+            domain_crawl_delay_ms: 0,
+            ..Default::default()
+        };
+        let db = setup_test_db().await;
+        let overrides = EnqueueSettings {
+            force_allow: true,
+            ..Default::default()
+        };
+        let urls = vec![
+            "https://failing.com/".to_string(),
+            "https://example.com/".to_string(),
+        ];
+        crawl_queue::enqueue_all(&db, &urls, &[], &settings, &overrides, None)
+            .await
+            .unwrap();
+
+        for _ in 0..domain_stats::MIN_FETCHES {
+            domain_stats::record_fetch(&db, "failing.com", 100, Some("timed out"))
+                .await
+                .unwrap();
+        }
+
+        let task = crawl_queue::dequeue(&db, settings.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(task.domain, "example.com");
+
+        // Still crawled once there's nothing else to do
+        let task = crawl_queue::dequeue(&db, settings).await.unwrap().unwrap();
+        assert_eq!(task.domain, "failing.com");
+    }
+
+    #[tokio::test]
+    async fn test_dequeue_skips_paused_connections() {
+        let settings = UserSettings::default();
+        let db = setup_test_db().await;
+
+        let mut conn = connection::ActiveModel::new(
+            "drive.google.com".into(),
+            "user@example.com".into(),
+            "token".into(),
+            None,
+            None,
+            Vec::new(),
         );
+        conn.paused_until = Set(Some(chrono::Utc::now() + chrono::Duration::hours(1)));
+        conn.insert(&db).await.unwrap();
+
+        let urls = vec![
+            "api://user%40example.com@drive.google.com/file".to_string(),
+            "api://other%40example.com@drive.google.com/file".to_string(),
+        ];
+        let overrides = EnqueueSettings {
+            crawl_type: CrawlType::Api,
+            force_allow: true,
+            ..Default::default()
+        };
+        crawl_queue::enqueue_all(&db, &urls, &[], &settings, &overrides, None)
+            .await
+            .unwrap();
+
+        let task = crawl_queue::dequeue(&db, settings.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(task.url, urls[1]);
+        assert!(crawl_queue::dequeue(&db, settings).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_dequeue_respects_crawl_delay() {
+        let settings = UserSettings {
+            domain_crawl_delay_ms: 60_000,
+            ..Default::default()
+        };
+        let db = setup_test_db().await;
+
+        let urls = vec![
+            "https://example.com/a".to_string(),
+            "https://example.com/b".to_string(),
+            "https://other.com/".to_string(),
+        ];
+        let overrides = EnqueueSettings {
+            force_allow: true,
+            ..Default::default()
+        };
+        crawl_queue::enqueue_all(&db, &urls, &[], &settings, &overrides, None)
+            .await
+            .unwrap();
+
+        let first = crawl_queue::dequeue(&db, settings.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        let fetch = domain_fetch::find_by_domain(&db, &first.domain)
+            .await
+            .unwrap()
+            .expect("fetch not recorded");
+        assert!(fetch.next_fetch_at > chrono::Utc::now());
+
+        // The other domain is free, the cooling down one is skipped
+        let next = crawl_queue::dequeue(&db, settings.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_ne!(next.domain, first.domain);
+        assert!(crawl_queue::dequeue(&db, settings).await.unwrap().is_none());
     }
 
     #[tokio::test]
@@ -724,6 +1517,39 @@ mod test {
         assert_eq!(crawl.len(), 1);
     }
 
+    #[tokio::test]
+    async fn test_enqueue_respects_robots() {
+        let settings = UserSettings::default();
+        let db = setup_test_db().await;
+        robots::upsert(
+            &db,
+            "example.com",
+            vec![robots::RobotsRule {
+                regex: "/private.*".into(),
+                allow_crawl: false,
+            }],
+            None,
+        )
+        .await
+        .unwrap();
+
+        let urls = vec![
+            "https://example.com/private/secret.html".to_string(),
+            "https://example.com/about".to_string(),
+        ];
+        let overrides = EnqueueSettings {
+            force_allow: true,
+            ..Default::default()
+        };
+        crawl_queue::enqueue_all(&db, &urls, &[], &settings, &overrides, None)
+            .await
+            .unwrap();
+
+        let queued = crawl_queue::Entity::find().all(&db).await.unwrap();
+        assert_eq!(queued.len(), 1);
+        assert_eq!(queued[0].url, urls[1]);
+    }
+
     #[tokio::test]
     async fn test_enqueue_with_recrawl() {
         let settings = UserSettings::default();
@@ -826,6 +1652,145 @@ mod test {
         assert_eq!(queue.unwrap().url, url[0]);
     }
 
+    #[tokio::test]
+    async fn test_enqueue_with_lens_pipeline() {
+        let settings = UserSettings::default();
+        let db = setup_test_db().await;
+        let lenses = vec![
+            LensConfig {
+                domains: vec!["rendered.example.com".into()],
+                pipeline: Some("browser-render".into()),
+                ..Default::default()
+            },
+            LensConfig {
+                domains: vec!["plain.example.com".into()],
+                ..Default::default()
+            },
+        ];
+
+        let urls = vec![
+            "https://rendered.example.com/app".into(),
+            "https://plain.example.com/page".into(),
+        ];
+        crawl_queue::enqueue_all(&db, &urls, &lenses, &settings, &Default::default(), None)
+            .await
+            .unwrap();
+
+        let rendered = crawl_queue::Entity::find()
+            .filter(crawl_queue::Column::Url.eq(urls[0].clone()))
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(rendered.pipeline, Some("browser-render".into()));
+
+        let plain = crawl_queue::Entity::find()
+            .filter(crawl_queue::Column::Url.eq(urls[1].clone()))
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(plain.pipeline, None);
+    }
+
+    #[tokio::test]
+    async fn test_dequeue_fair_across_lenses() {
+        let settings = UserSettings::default();
+        let db = setup_test_db().await;
+
+        for (lens, urls) in [
+            (
+                "big",
+                vec![
+                    "https://a.example.com/",
+                    "https://b.example.com/",
+                    "https://c.example.com/",
+                ],
+            ),
+            ("small", vec!["https://small.example.com/"]),
+        ] {
+            let urls = urls.into_iter().map(String::from).collect::<Vec<_>>();
+            let overrides = EnqueueSettings {
+                force_allow: true,
+                tags: vec![(TagType::Lens, lens.to_string())],
+                ..Default::default()
+            };
+            crawl_queue::enqueue_all(&db, &urls, &[], &settings, &overrides, None)
+                .await
+                .unwrap();
+        }
+
+        // Oldest tasks go first
+        let first = crawl_queue::dequeue(&db, settings.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_ne!(first.url, "https://small.example.com/");
+
+        // Then the small lens, even though the big one has older tasks
+        let next = crawl_queue::dequeue(&db, settings).await.unwrap().unwrap();
+        assert_eq!(next.url, "https://small.example.com/");
+    }
+
+    #[tokio::test]
+    async fn test_dequeue_by_priority() {
+        let settings = UserSettings::default();
+        let db = setup_test_db().await;
+
+        for (url, priority) in [
+            ("https://recrawl.example.com/", TaskPriority::Recrawl),
+            ("https://link.example.com/", TaskPriority::Normal),
+            ("https://user.example.com/", TaskPriority::User),
+            ("https://lens.example.com/", TaskPriority::Lens),
+        ] {
+            let overrides = EnqueueSettings {
+                force_allow: true,
+                priority,
+                ..Default::default()
+            };
+            crawl_queue::enqueue_all(&db, &[url.to_string()], &[], &settings, &overrides, None)
+                .await
+                .unwrap();
+        }
+
+        let mut dequeued = Vec::new();
+        while let Some(task) = crawl_queue::dequeue(&db, settings.clone()).await.unwrap() {
+            dequeued.push(task.url);
+        }
+
+        assert_eq!(
+            dequeued,
+            vec![
+                "https://user.example.com/",
+                "https://lens.example.com/",
+                "https://link.example.com/",
+                "https://recrawl.example.com/",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recrawl_keeps_priority() {
+        let settings = UserSettings::default();
+        let db = setup_test_db().await;
+        let url = vec!["https://example.com/".to_string()];
+
+        for priority in [TaskPriority::User, TaskPriority::Recrawl] {
+            let overrides = EnqueueSettings {
+                force_allow: true,
+                is_recrawl: true,
+                priority,
+                ..Default::default()
+            };
+            crawl_queue::enqueue_all(&db, &url, &[], &settings, &overrides, None)
+                .await
+                .unwrap();
+        }
+
+        let task = crawl_queue::Entity::find().one(&db).await.unwrap().unwrap();
+        assert_eq!(task.priority, TaskPriority::User.weight());
+    }
+
     #[tokio::test]
     async fn test_dequeue_with_limit() {
         let settings = UserSettings {
@@ -985,6 +1950,106 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_filter_urls_denylist() {
+        let settings = UserSettings {
+            index_denylist: vec!["/home/alice/private".into()],
+            ..Default::default()
+        };
+        let overrides = EnqueueSettings {
+            force_allow: true,
+            ..Default::default()
+        };
+
+        let to_enqueue = vec![
+            "file:///home/alice/notes/todo.md".into(),
+            "file:///home/alice/private/diary.md".into(),
+            "file:///home/alice/Passwords.kdbx".into(),
+            "file:///home/alice/.config/google-chrome/Default/Login%20Data".into(),
+        ];
+
+        let filtered = filter_urls(&[], &settings, &overrides, &to_enqueue);
+        assert_eq!(
+            filtered,
+            vec!["file:///home/alice/notes/todo.md".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_url_filter_reasons() {
+        let settings = UserSettings {
+            block_list: vec!["ads.example.com".into()],
+            ..Default::default()
+        };
+        let lens = LensConfig {
+            domains: vec!["example.com".into()],
+            rules: vec![
+                LensRule::SkipURL("https://example.com/*?action=*".into()),
+                LensRule::LimitURLDepth("https://example.com/".into(), 2),
+            ],
+            ..Default::default()
+        };
+
+        let filter = UrlFilter::new(&[lens], &settings, false);
+        assert_eq!(
+            filter.check("https://example.com/docs/intro#setup"),
+            Ok("https://example.com/docs/intro".to_string())
+        );
+        assert_eq!(
+            filter.check("https://example.com/docs?action=edit"),
+            Err(FilterReason::SkipRule)
+        );
+        assert_eq!(
+            filter.check("https://example.com/docs/api/v1/items"),
+            Err(FilterReason::Restricted)
+        );
+        assert_eq!(
+            filter.check("https://ads.example.com/"),
+            Err(FilterReason::BlockedDomain)
+        );
+        // Crawl limits apply to every URL, so check what's outside the lens w/o them
+        let filter = UrlFilter::new(
+            &[LensConfig {
+                domains: vec!["example.com".into()],
+                ..Default::default()
+            }],
+            &settings,
+            false,
+        );
+        assert_eq!(
+            filter.check("https://other.com/"),
+            Err(FilterReason::NotInLens)
+        );
+        assert_eq!(
+            filter.check("ftp://example.com/"),
+            Err(FilterReason::Unsupported)
+        );
+        assert_eq!(filter.check("not a url"), Err(FilterReason::Unsupported));
+    }
+
+    #[test]
+    fn test_url_filter_normalization() {
+        let lens = LensConfig {
+            domains: vec!["example.com".into()],
+            url_normalization: UrlNormalization {
+                strip_trailing_slash: true,
+                ignore_www: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let filter = UrlFilter::new(&[lens], &UserSettings::default(), false);
+        assert_eq!(
+            filter.check("https://www.example.com/docs/"),
+            Ok("https://example.com/docs".to_string())
+        );
+        assert_eq!(
+            filter.check("https://example.com/docs#intro"),
+            Ok("https://example.com/docs".to_string())
+        );
+    }
+
     #[tokio::test]
     async fn test_dequeue_recrawl() {
         let settings = UserSettings::default();
@@ -1011,6 +2076,394 @@ mod test {
         assert_eq!(queue.unwrap().url, url);
     }
 
+    #[tokio::test]
+    async fn test_dequeue_refresh() {
+        let settings = UserSettings {
+            domain_crawl_delay_ms: 0,
+            ..Default::default()
+        };
+        let db = setup_test_db().await;
+
+        let urls = vec![
+            "https://example.com/".to_string(),
+            "file:///tmp/notes.md".to_string(),
+        ];
+        let overrides = EnqueueSettings {
+            force_allow: true,
+            tags: vec![(TagType::Lens, "news".to_string())],
+            ..Default::default()
+        };
+        crawl_queue::enqueue_all(&db, &urls, &[], &settings, &overrides, None)
+            .await
+            .unwrap();
+
+        // Crawled a couple days ago
+        let two_days_ago = chrono::Utc::now() - chrono::Duration::days(2);
+        crawl_queue::Entity::update_many()
+            .col_expr(
+                crawl_queue::Column::Status,
+                sea_orm::sea_query::Expr::value(crawl_queue::CrawlStatus::Completed),
+            )
+            .col_expr(
+                crawl_queue::Column::UpdatedAt,
+                sea_orm::sea_query::Expr::value(two_days_ago),
+            )
+            .exec(&db)
+            .await
+            .unwrap();
+
+        let week = chrono::Duration::days(7);
+        let day = chrono::Duration::days(1);
+        assert!(super::dequeue_refresh(&db, &settings, "news", week)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(super::dequeue_refresh(&db, &settings, "other", day)
+            .await
+            .unwrap()
+            .is_none());
+
+        // Only web pages are refreshed, files have their own recrawls
+        let task = super::dequeue_refresh(&db, &settings, "news", day)
+            .await
+            .unwrap()
+            .expect("task to refresh");
+        assert_eq!(task.url, "https://example.com/");
+        assert_eq!(task.status, crawl_queue::CrawlStatus::Processing);
+        assert!(super::dequeue_refresh(&db, &settings, "news", day)
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_start_recrawl() {
+        let db = setup_test_db().await;
+        let url = "https://status.example.com/";
+
+        let task = super::start_recrawl(&db, url)
+            .await
+            .unwrap()
+            .expect("task created");
+        assert_eq!(task.domain, "status.example.com");
+        assert_eq!(task.status, crawl_queue::CrawlStatus::Processing);
+
+        // Already in flight
+        assert!(super::start_recrawl(&db, url).await.unwrap().is_none());
+
+        super::mark_done(&db, task.id, None).await;
+        let again = super::start_recrawl(&db, url)
+            .await
+            .unwrap()
+            .expect("task restarted");
+        assert_eq!(again.id, task.id);
+    }
+
+    #[tokio::test]
+    async fn test_force_enqueue() {
+        let mut settings = UserSettings::default();
+        settings.block_list = vec!["blocked.example.com".to_string()];
+        let db = setup_test_db().await;
+        let url = "https://example.com/page#section";
+
+        let task = super::force_enqueue(&db, url, &settings, None, &[])
+            .await
+            .unwrap()
+            .expect("task created");
+        assert_eq!(task.url, "https://example.com/page");
+        assert_eq!(task.priority, TaskPriority::User.weight());
+
+        // Requeued once it's been crawled, w/ any tags
+        super::mark_done(&db, task.id, None).await;
+        let tags = vec![(TagType::Lens, "reading".to_string())];
+        let again = super::force_enqueue(&db, url, &settings, Some("rss".into()), &tags)
+            .await
+            .unwrap()
+            .expect("task requeued");
+        assert_eq!(again.id, task.id);
+        assert_eq!(again.status, crawl_queue::CrawlStatus::Queued);
+        assert_eq!(again.pipeline, Some("rss".to_string()));
+        let task_tags = again.find_related(tag::Entity).all(&db).await.unwrap();
+        assert_eq!(task_tags.len(), 1);
+        assert_eq!(task_tags[0].value, "reading");
+
+        // Blocked domains are never crawled
+        let blocked =
+            super::force_enqueue(&db, "https://blocked.example.com", &settings, None, &[])
+                .await
+                .unwrap();
+        assert!(blocked.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_force_enqueue_processing() {
+        let settings = UserSettings::default();
+        let db = setup_test_db().await;
+        let url = "https://example.com/page";
+
+        let task = super::start_recrawl(&db, url)
+            .await
+            .unwrap()
+            .expect("task started");
+        let again = super::force_enqueue(&db, url, &settings, None, &[])
+            .await
+            .unwrap()
+            .expect("existing task");
+        assert_eq!(again.id, task.id);
+        assert_eq!(again.status, crawl_queue::CrawlStatus::Processing);
+    }
+
+    #[tokio::test]
+    async fn test_mark_failed() {
+        let db = setup_test_db().await;
+        let url = "https://expired.badssl.com/";
+
+        let task = super::start_recrawl(&db, url)
+            .await
+            .unwrap()
+            .expect("task created");
+
+        let error =
+            super::TaskError::new(super::TaskErrorType::TlsError, "certificate has expired");
+        super::mark_failed(&db, &UserSettings::default(), task.id, Some(error.clone())).await;
+
+        let failed = super::list_failed(&db, Some("expired.badssl.com"), 10)
+            .await
+            .unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].status, crawl_queue::CrawlStatus::Failed);
+        assert_eq!(failed[0].error, Some(error));
+        assert!(super::list_failed(&db, Some("example.com"), 10)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_max_retries() {
+        use super::TaskErrorType;
+
+        let settings = UserSettings::default();
+        assert_eq!(TaskErrorType::Fetch.max_retries(&settings), 5);
+        assert_eq!(TaskErrorType::Parse.max_retries(&settings), 0);
+
+        let mut settings = UserSettings::default();
+        settings.max_retries.insert("Fetch".into(), 10);
+        settings.max_retries.insert("HttpStatus".into(), 2);
+        assert_eq!(TaskErrorType::Fetch.max_retries(&settings), 10);
+        assert_eq!(TaskErrorType::HttpStatus.max_retries(&settings), 2);
+        assert_eq!(TaskErrorType::NetworkTimeout.max_retries(&settings), 5);
+    }
+
+    #[test]
+    fn test_backoff() {
+        use super::TaskErrorType;
+
+        assert_eq!(
+            TaskErrorType::Fetch.backoff(0),
+            chrono::Duration::minutes(1)
+        );
+        assert_eq!(
+            TaskErrorType::Fetch.backoff(3),
+            chrono::Duration::minutes(8)
+        );
+        assert_eq!(
+            TaskErrorType::Parse.backoff(1),
+            chrono::Duration::minutes(30)
+        );
+        assert_eq!(
+            TaskErrorType::Parse.backoff(20),
+            chrono::Duration::hours(24)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mark_failed_with_retry() {
+        // No crawl delay, only the backoff holds the task back
+        let settings = UserSettings {
+            domain_crawl_delay_ms: 0,
+            ..Default::default()
+        };
+        let db = setup_test_db().await;
+        let url = "https://example.com/".to_string();
+        let overrides = EnqueueSettings {
+            force_allow: true,
+            ..Default::default()
+        };
+        crawl_queue::enqueue_all(&db, &[url], &[], &settings, &overrides, None)
+            .await
+            .unwrap();
+
+        let task = crawl_queue::dequeue(&db, settings.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        let error = super::TaskError::new(super::TaskErrorType::NetworkTimeout, "timed out");
+        super::mark_failed(&db, &settings, task.id, Some(error)).await;
+
+        let retry = crawl_queue::Entity::find_by_id(task.id)
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(retry.status, crawl_queue::CrawlStatus::Queued);
+        assert_eq!(retry.num_retries, 1);
+        assert!(retry.next_retry_at.unwrap() > chrono::Utc::now());
+
+        // Not picked up again until the backoff has passed
+        assert!(crawl_queue::dequeue(&db, settings).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_mark_many() {
+        let settings = UserSettings::default();
+        let db = setup_test_db().await;
+        let urls = (0..4)
+            .map(|idx| format!("https://example.com/{}", idx))
+            .collect::<Vec<_>>();
+        let overrides = EnqueueSettings {
+            force_allow: true,
+            ..Default::default()
+        };
+        crawl_queue::enqueue_all(&db, &urls, &[], &settings, &overrides, None)
+            .await
+            .unwrap();
+
+        let ids = crawl_queue::Entity::find()
+            .all(&db)
+            .await
+            .unwrap()
+            .iter()
+            .map(|task| task.id)
+            .collect::<Vec<_>>();
+        assert_eq!(ids.len(), 4);
+
+        let done = super::mark_many_done(&db, &ids[..2]).await.unwrap();
+        assert_eq!(done, 2);
+
+        let timeout = super::TaskError::new(super::TaskErrorType::NetworkTimeout, "timed out");
+        let parse = super::TaskError::new(super::TaskErrorType::Parse, "no content");
+        super::mark_many_failed(
+            &db,
+            &settings,
+            &[(ids[2], timeout.clone()), (ids[3], parse.clone())],
+        )
+        .await
+        .unwrap();
+
+        let tasks = crawl_queue::Entity::find()
+            .filter(crawl_queue::Column::Id.is_in(ids.clone()))
+            .order_by_asc(crawl_queue::Column::Id)
+            .all(&db)
+            .await
+            .unwrap();
+        assert_eq!(tasks[0].status, crawl_queue::CrawlStatus::Completed);
+        assert_eq!(tasks[1].status, crawl_queue::CrawlStatus::Completed);
+        // Timeouts are retried, parse errors aren't.
+        assert_eq!(tasks[2].status, crawl_queue::CrawlStatus::Queued);
+        assert_eq!(tasks[2].num_retries, 1);
+        assert!(tasks[2].next_retry_at.is_some());
+        assert_eq!(tasks[2].error, Some(timeout));
+        assert_eq!(tasks[3].status, crawl_queue::CrawlStatus::Failed);
+        assert_eq!(tasks[3].num_retries, 0);
+        assert_eq!(tasks[3].error, Some(parse));
+    }
+
+    #[tokio::test]
+    async fn test_reset_stale_processing() {
+        let db = setup_test_db().await;
+
+        let two_hours_ago = chrono::Utc::now() - chrono::Duration::hours(2);
+        for (url, updated_at) in [
+            ("https://example.com/hung", two_hours_ago),
+            ("https://example.com/busy", chrono::Utc::now()),
+        ] {
+            let model = crawl_queue::ActiveModel {
+                crawl_type: Set(CrawlType::Normal),
+                domain: Set("example.com".to_string()),
+                status: Set(crawl_queue::CrawlStatus::Processing),
+                url: Set(url.to_string()),
+                updated_at: Set(updated_at),
+                ..Default::default()
+            };
+            model.insert(&db).await.unwrap();
+        }
+
+        let num_reset = super::reset_stale_processing(&db, chrono::Duration::minutes(30))
+            .await
+            .unwrap();
+        assert_eq!(num_reset, 1);
+
+        let hung = crawl_queue::Entity::find()
+            .filter(crawl_queue::Column::Url.eq("https://example.com/hung"))
+            .one(&db)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(hung.status, crawl_queue::CrawlStatus::Queued);
+        assert_eq!(
+            crawl_queue::num_queued(&db, crawl_queue::CrawlStatus::Processing)
+                .await
+                .unwrap(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_remove_completed() {
+        let db = setup_test_db().await;
+
+        let last_month = chrono::Utc::now() - chrono::Duration::days(30);
+        for (url, status, updated_at) in [
+            (
+                "https://example.com/old",
+                crawl_queue::CrawlStatus::Completed,
+                last_month,
+            ),
+            (
+                "https://example.com/new",
+                crawl_queue::CrawlStatus::Completed,
+                chrono::Utc::now(),
+            ),
+            (
+                "https://example.com/queued",
+                crawl_queue::CrawlStatus::Queued,
+                last_month,
+            ),
+            (
+                "file:///tmp/notes.md",
+                crawl_queue::CrawlStatus::Completed,
+                last_month,
+            ),
+        ] {
+            let model = crawl_queue::ActiveModel {
+                crawl_type: Set(CrawlType::Normal),
+                domain: Set("example.com".to_string()),
+                status: Set(status),
+                url: Set(url.to_string()),
+                updated_at: Set(updated_at),
+                ..Default::default()
+            };
+            let model = model.insert(&db).await.unwrap();
+            let model: crawl_queue::ActiveModel = model.into();
+            model
+                .insert_tags(&db, &[(TagType::Source, "test".to_string())])
+                .await
+                .unwrap();
+        }
+
+        let cutoff = chrono::Utc::now() - chrono::Duration::days(7);
+        let removed = super::remove_completed(&db, cutoff).await.unwrap();
+        assert_eq!(removed, 1);
+
+        let remaining = crawl_queue::Entity::find().all(&db).await.unwrap();
+        assert_eq!(remaining.len(), 3);
+        assert!(!remaining
+            .iter()
+            .any(|task| task.url == "https://example.com/old"));
+        assert_eq!(crawl_tag::Entity::find().count(&db).await.unwrap(), 3);
+    }
+
     #[tokio::test]
     async fn test_update_or_remove_task() {
         let db = setup_test_db().await;
@@ -0,0 +1,120 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::{ConnectionTrait, QueryOrder, Set};
+use serde::Serialize;
+
+use super::indexed_document;
+
+/// User note/highlight attached to an indexed document.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Eq)]
+#[sea_orm(table_name = "document_note")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub indexed_document_id: i64,
+    /// The note itself
+    pub content: String,
+    /// Passage from the document the note refers to, if any.
+    pub highlight: Option<String>,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+impl Model {
+    /// Text added to the search index for this note.
+    pub fn indexed_text(&self) -> String {
+        match &self.highlight {
+            Some(highlight) => format!("{}\n{}", highlight, self.content),
+            None => self.content.clone(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {
+    IndexedDocument,
+}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        match self {
+            Self::IndexedDocument => Entity::belongs_to(indexed_document::Entity)
+                .from(Column::IndexedDocumentId)
+                .to(indexed_document::Column::Id)
+                .into(),
+        }
+    }
+}
+
+impl Related<indexed_document::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::IndexedDocument.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {
+    // Triggered before insert / update
+    fn before_save(mut self, insert: bool) -> Result<Self, DbErr> {
+        if insert {
+            self.created_at = Set(chrono::Utc::now());
+            self.updated_at = Set(chrono::Utc::now());
+        } else {
+            self.updated_at = Set(chrono::Utc::now());
+        }
+
+        Ok(self)
+    }
+}
+
+/// Notes for a document, oldest first.
+pub async fn for_document<C: ConnectionTrait>(
+    db: &C,
+    indexed_document_id: i64,
+) -> Result<Vec<Model>, DbErr> {
+    Entity::find()
+        .filter(Column::IndexedDocumentId.eq(indexed_document_id))
+        .order_by_asc(Column::Id)
+        .all(db)
+        .await
+}
+
+#[cfg(test)]
+mod test {
+    use crate::models::indexed_document;
+    use crate::test::setup_test_db;
+    use sea_orm::{ActiveModelTrait, Set};
+
+    #[tokio::test]
+    async fn test_for_document() {
+        let db = setup_test_db().await;
+
+        let doc = indexed_document::ActiveModel {
+            domain: Set("en.wikipedia.org".into()),
+            url: Set("https://en.wikipedia.org/wiki/Rust_(programming_language)".into()),
+            doc_id: Set("rust-doc".into()),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+
+        for content in ["first", "second"] {
+            super::ActiveModel {
+                indexed_document_id: Set(doc.id),
+                content: Set(content.into()),
+                highlight: Set(Some("memory safety".into())),
+                ..Default::default()
+            }
+            .insert(&db)
+            .await
+            .unwrap();
+        }
+
+        let notes = super::for_document(&db, doc.id).await.unwrap();
+        assert_eq!(notes.len(), 2);
+        assert_eq!(notes[0].indexed_text(), "memory safety\nfirst");
+        assert!(super::for_document(&db, doc.id + 1)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
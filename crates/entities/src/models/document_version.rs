@@ -0,0 +1,192 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::{ConnectionTrait, QueryOrder, Set};
+use serde::Serialize;
+
+/// A crawl of a URL whose content differed from the crawl before it. Versions
+/// are tracked by URL so they survive the document being re-indexed.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Eq)]
+#[sea_orm(table_name = "document_version")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub url: String,
+    /// Hash of the content, used to look it up in the archive. Not available
+    /// for documents indexed before versions were tracked.
+    pub content_hash: Option<String>,
+    /// When this version was first crawled.
+    pub crawled_at: DateTimeUtc,
+    /// When a newer version replaced this one, null for the current version.
+    pub superseded_at: Option<DateTimeUtc>,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        panic!("No RelationDef")
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {
+    // Triggered before insert / update
+    fn before_save(mut self, insert: bool) -> Result<Self, DbErr> {
+        if insert {
+            self.created_at = Set(chrono::Utc::now());
+            self.updated_at = Set(chrono::Utc::now());
+        } else {
+            self.updated_at = Set(chrono::Utc::now());
+        }
+
+        Ok(self)
+    }
+}
+
+/// The version of `url` that's currently indexed, if any.
+pub async fn current<C: ConnectionTrait>(db: &C, url: &str) -> Result<Option<Model>, DbErr> {
+    Entity::find()
+        .filter(Column::Url.eq(url))
+        .filter(Column::SupersededAt.is_null())
+        .one(db)
+        .await
+}
+
+/// All known versions of `url`, newest first.
+pub async fn history<C: ConnectionTrait>(db: &C, url: &str) -> Result<Vec<Model>, DbErr> {
+    Entity::find()
+        .filter(Column::Url.eq(url))
+        .order_by_desc(Column::CrawledAt)
+        .all(db)
+        .await
+}
+
+/// The version of `url` that was current @ `timestamp`, if it had been
+/// crawled by then.
+pub async fn as_of<C: ConnectionTrait>(
+    db: &C,
+    url: &str,
+    timestamp: DateTimeUtc,
+) -> Result<Option<Model>, DbErr> {
+    Ok(history(db, url)
+        .await?
+        .into_iter()
+        .find(|version| version.crawled_at <= timestamp))
+}
+
+/// Record a new version of `url` crawled @ `crawled_at`, superseding the
+/// current one.
+pub async fn record<C: ConnectionTrait>(
+    db: &C,
+    url: &str,
+    content_hash: Option<String>,
+    crawled_at: DateTimeUtc,
+) -> Result<Model, DbErr> {
+    Entity::update_many()
+        .col_expr(
+            Column::SupersededAt,
+            sea_orm::sea_query::Expr::value(crawled_at),
+        )
+        .filter(Column::Url.eq(url))
+        .filter(Column::SupersededAt.is_null())
+        .exec(db)
+        .await?;
+
+    ActiveModel {
+        url: Set(url.to_string()),
+        content_hash: Set(content_hash),
+        crawled_at: Set(crawled_at),
+        superseded_at: Set(None),
+        ..Default::default()
+    }
+    .insert(db)
+    .await
+}
+
+/// Remove all but the `keep` newest past versions of `url`, returning the
+/// versions that were removed.
+pub async fn prune<C: ConnectionTrait>(
+    db: &C,
+    url: &str,
+    keep: usize,
+) -> Result<Vec<Model>, DbErr> {
+    let expired = history(db, url)
+        .await?
+        .into_iter()
+        .filter(|version| version.superseded_at.is_some())
+        .skip(keep)
+        .collect::<Vec<Model>>();
+
+    if !expired.is_empty() {
+        Entity::delete_many()
+            .filter(Column::Id.is_in(expired.iter().map(|version| version.id)))
+            .exec(db)
+            .await?;
+    }
+
+    Ok(expired)
+}
+
+/// Remove the version history for `url`.
+pub async fn delete_for_url<C: ConnectionTrait>(db: &C, url: &str) -> Result<(), DbErr> {
+    Entity::delete_many()
+        .filter(Column::Url.eq(url))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::setup_test_db;
+    use chrono::{Duration, Utc};
+
+    #[tokio::test]
+    async fn test_record_and_prune() {
+        let db = setup_test_db().await;
+        let url = "https://example.com/changelog";
+        let start = Utc::now() - Duration::days(10);
+
+        for day in 0..5 {
+            super::record(
+                &db,
+                url,
+                Some(format!("hash-{}", day)),
+                start + Duration::days(day),
+            )
+            .await
+            .unwrap();
+        }
+
+        let current = super::current(&db, url).await.unwrap().unwrap();
+        assert_eq!(current.content_hash, Some("hash-4".into()));
+        assert_eq!(super::history(&db, url).await.unwrap().len(), 5);
+
+        // The current version is never pruned
+        let removed = super::prune(&db, url, 2).await.unwrap();
+        assert_eq!(
+            removed
+                .iter()
+                .map(|version| version.content_hash.clone().unwrap())
+                .collect::<Vec<String>>(),
+            vec!["hash-1".to_string(), "hash-0".to_string()]
+        );
+
+        let history = super::history(&db, url).await.unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(history[0].id, current.id);
+        assert_eq!(history[1].superseded_at, Some(current.crawled_at));
+
+        let version = super::as_of(&db, url, start + Duration::hours(80))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(version.content_hash, Some("hash-3".into()));
+        assert!(super::as_of(&db, url, start - Duration::days(1))
+            .await
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,65 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{ConnectionTrait, Set};
+use serde::Serialize;
+
+/// When a domain was last fetched from & when it can be fetched from again,
+/// so crawls are spaced out per host.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Eq)]
+#[sea_orm(table_name = "domain_fetch")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    #[sea_orm(unique)]
+    pub domain: String,
+    pub last_fetched_at: DateTimeUtc,
+    /// Tasks for this domain aren't dequeued before this time.
+    pub next_fetch_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        panic!("No RelationDef")
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+pub async fn find_by_domain<C: ConnectionTrait>(
+    db: &C,
+    domain: &str,
+) -> Result<Option<Model>, DbErr> {
+    Entity::find()
+        .filter(Column::Domain.eq(domain))
+        .one(db)
+        .await
+}
+
+/// Mark `domain` as just fetched from, holding off on it for `delay`.
+pub async fn record_fetch<C: ConnectionTrait>(
+    db: &C,
+    domain: &str,
+    delay: chrono::Duration,
+) -> Result<(), DbErr> {
+    let now = chrono::Utc::now();
+    let model = ActiveModel {
+        domain: Set(domain.to_string()),
+        last_fetched_at: Set(now),
+        next_fetch_at: Set(now + delay),
+        ..Default::default()
+    };
+
+    Entity::insert(model)
+        .on_conflict(
+            OnConflict::column(Column::Domain)
+                .update_columns(vec![Column::LastFetchedAt, Column::NextFetchAt])
+                .to_owned(),
+        )
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
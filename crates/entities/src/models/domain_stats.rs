@@ -0,0 +1,145 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::{ConnectionTrait, Set};
+use serde::Serialize;
+
+/// Weight of the latest fetch in the rolling averages.
+const ROLLING_WEIGHT: f64 = 0.1;
+/// Fetches needed before a domain can be considered failing.
+pub const MIN_FETCHES: i64 = 10;
+/// Domains w/ a success rate below this are crawled after everything else.
+pub const FAILING_SUCCESS_RATE: f64 = 0.25;
+
+/// How reliable fetching from a domain has been lately.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize)]
+#[sea_orm(table_name = "domain_stats")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    #[sea_orm(unique)]
+    pub domain: String,
+    pub num_fetches: i64,
+    /// Rolling average of fetches that succeeded, from 0 to 1.
+    pub success_rate: f64,
+    /// Rolling average of how long fetches took, in milliseconds.
+    pub avg_latency_ms: f64,
+    pub last_error: Option<String>,
+    pub last_error_at: Option<DateTimeUtc>,
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        panic!("No RelationDef")
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {
+    fn before_save(mut self, _insert: bool) -> Result<Self, DbErr> {
+        self.updated_at = Set(chrono::Utc::now());
+        Ok(self)
+    }
+}
+
+impl Model {
+    /// Mostly failing over enough fetches that it's unlikely to be bad luck.
+    pub fn is_failing(&self) -> bool {
+        self.num_fetches >= MIN_FETCHES && self.success_rate < FAILING_SUCCESS_RATE
+    }
+}
+
+fn rolling(average: f64, sample: f64) -> f64 {
+    average + ROLLING_WEIGHT * (sample - average)
+}
+
+pub async fn find_by_domain<C: ConnectionTrait>(
+    db: &C,
+    domain: &str,
+) -> Result<Option<Model>, DbErr> {
+    Entity::find()
+        .filter(Column::Domain.eq(domain))
+        .one(db)
+        .await
+}
+
+/// Add a fetch from `domain` that took `latency_ms` to its stats, `error`
+/// being why it failed if it did.
+pub async fn record_fetch<C: ConnectionTrait>(
+    db: &C,
+    domain: &str,
+    latency_ms: i64,
+    error: Option<&str>,
+) -> Result<Model, DbErr> {
+    let success = if error.is_some() { 0.0 } else { 1.0 };
+    let latency_ms = latency_ms as f64;
+
+    let mut model = match find_by_domain(db, domain).await? {
+        Some(stats) => {
+            let mut model: ActiveModel = stats.clone().into();
+            model.num_fetches = Set(stats.num_fetches + 1);
+            model.success_rate = Set(rolling(stats.success_rate, success));
+            model.avg_latency_ms = Set(rolling(stats.avg_latency_ms, latency_ms));
+            model
+        }
+        None => ActiveModel {
+            domain: Set(domain.to_string()),
+            num_fetches: Set(1),
+            success_rate: Set(success),
+            avg_latency_ms: Set(latency_ms),
+            ..Default::default()
+        },
+    };
+
+    if let Some(error) = error {
+        model.last_error = Set(Some(error.to_string()));
+        model.last_error_at = Set(Some(chrono::Utc::now()));
+    }
+
+    model.save(db).await?.try_into_model()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{find_by_domain, record_fetch};
+    use crate::test::setup_test_db;
+
+    #[tokio::test]
+    async fn test_record_fetch() {
+        let db = setup_test_db().await;
+
+        let stats = record_fetch(&db, "example.com", 100, None).await.unwrap();
+        assert_eq!(stats.num_fetches, 1);
+        assert_eq!(stats.success_rate, 1.0);
+        assert_eq!(stats.avg_latency_ms, 100.0);
+        assert!(stats.last_error.is_none());
+
+        let stats = record_fetch(&db, "example.com", 200, Some("timed out"))
+            .await
+            .unwrap();
+        assert_eq!(stats.num_fetches, 2);
+        assert!((stats.success_rate - 0.9).abs() < 1e-9);
+        assert!((stats.avg_latency_ms - 110.0).abs() < 1e-9);
+        assert_eq!(stats.last_error.as_deref(), Some("timed out"));
+        assert!(stats.last_error_at.is_some());
+        assert!(!stats.is_failing());
+
+        for _ in 0..20 {
+            record_fetch(&db, "example.com", 200, Some("timed out"))
+                .await
+                .unwrap();
+        }
+
+        let stats = find_by_domain(&db, "example.com").await.unwrap().unwrap();
+        assert!(stats.is_failing());
+
+        // Recovers as fetches start succeeding again
+        for _ in 0..20 {
+            record_fetch(&db, "example.com", 100, None).await.unwrap();
+        }
+        let stats = find_by_domain(&db, "example.com").await.unwrap().unwrap();
+        assert!(!stats.is_failing());
+        assert_eq!(stats.last_error.as_deref(), Some("timed out"));
+    }
+}
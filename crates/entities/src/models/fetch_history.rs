@@ -74,6 +74,17 @@ pub async fn find_by_url(
         .await
 }
 
+/// Forget when `url` was last fetched so it isn't skipped as recently fetched.
+pub async fn delete_by_url(db: &DatabaseConnection, url: &Url) -> Result<(), sea_orm::DbErr> {
+    Entity::delete_many()
+        .filter(Column::Domain.eq(url.host_str().unwrap_or_default().to_string()))
+        .filter(Column::Path.eq(url.path()))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
 pub async fn upsert(
     db: &DatabaseConnection,
     domain: &str,
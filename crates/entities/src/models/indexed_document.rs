@@ -1,6 +1,9 @@
 use crate::models::{document_tag, tag};
 use sea_orm::entity::prelude::*;
-use sea_orm::{ConnectionTrait, FromQueryResult, InsertResult, QuerySelect, Set};
+use sea_orm::{
+    ConnectionTrait, DbBackend, DeleteResult, FromQueryResult, InsertResult, QuerySelect, Set,
+    Statement,
+};
 
 use super::tag::{get_or_create, TagPair};
 
@@ -17,6 +20,11 @@ pub struct Model {
     pub open_url: Option<String>,
     /// Reference to the document in the index
     pub doc_id: String,
+    /// Last time the user opened this document from the search results.
+    pub last_opened_at: Option<DateTimeUtc>,
+    /// When the document itself was written/sent/scheduled, if the source
+    /// tells us. Used to enforce retention policies.
+    pub document_date: Option<DateTimeUtc>,
     /// When this was indexed
     pub created_at: DateTimeUtc,
     /// When this was last updated
@@ -105,6 +113,202 @@ impl ActiveModel {
             .exec(db)
             .await
     }
+
+    /// Detach `tags` from this document. The tags themselves are left alone.
+    pub async fn remove_tags<C: ConnectionTrait>(
+        &self,
+        db: &C,
+        tags: &[TagPair],
+    ) -> Result<DeleteResult, DbErr> {
+        let mut tag_ids = Vec::new();
+        for (label, value) in tags {
+            if let Some(tag) = tag::Entity::find()
+                .filter(tag::Column::Label.eq(label.clone()))
+                .filter(tag::Column::Value.eq(value.as_str()))
+                .one(db)
+                .await?
+            {
+                tag_ids.push(tag.id);
+            }
+        }
+
+        document_tag::Entity::delete_many()
+            .filter(document_tag::Column::IndexedDocumentId.eq(self.id.clone().unwrap()))
+            .filter(document_tag::Column::TagId.is_in(tag_ids))
+            .exec(db)
+            .await
+    }
+}
+
+/// Documents tagged w/ `tag`.
+pub async fn find_by_tag<C: ConnectionTrait>(db: &C, tag: &TagPair) -> Result<Vec<Model>, DbErr> {
+    let (label, value) = tag;
+    let tag = match tag::Entity::find()
+        .filter(tag::Column::Label.eq(label.clone()))
+        .filter(tag::Column::Value.eq(value.as_str()))
+        .one(db)
+        .await?
+    {
+        Some(tag) => tag,
+        None => return Ok(Vec::new()),
+    };
+
+    let doc_ids = document_tag::Entity::find()
+        .filter(document_tag::Column::TagId.eq(tag.id))
+        .all(db)
+        .await?
+        .iter()
+        .map(|doc_tag| doc_tag.indexed_document_id)
+        .collect::<Vec<i64>>();
+
+    Entity::find()
+        .filter(Column::Id.is_in(doc_ids))
+        .all(db)
+        .await
+}
+
+/// Documents tagged w/ `value` under any label, e.g. `archive` matches both
+/// `(Source, "archive")` & `(Lens, "archive")`.
+pub async fn find_by_tag_value<C: ConnectionTrait>(
+    db: &C,
+    value: &str,
+) -> Result<Vec<Model>, DbErr> {
+    let tag_ids = tag::Entity::find()
+        .filter(tag::Column::Value.eq(value))
+        .all(db)
+        .await?
+        .iter()
+        .map(|tag| tag.id)
+        .collect::<Vec<i64>>();
+
+    let doc_ids = document_tag::Entity::find()
+        .filter(document_tag::Column::TagId.is_in(tag_ids))
+        .all(db)
+        .await?
+        .iter()
+        .map(|doc_tag| doc_tag.indexed_document_id)
+        .collect::<Vec<i64>>();
+
+    Entity::find()
+        .filter(Column::Id.is_in(doc_ids))
+        .all(db)
+        .await
+}
+
+#[derive(Debug, FromQueryResult)]
+pub struct DomainActivity {
+    pub domain: String,
+    /// When a document from this domain was last opened, if ever.
+    pub last_opened_at: Option<DateTimeUtc>,
+    /// When the first document from this domain was indexed.
+    pub first_indexed_at: DateTimeUtc,
+}
+
+/// Usage info for each domain, used to demote sources the user no longer opens.
+pub async fn domain_activity<C: ConnectionTrait>(db: &C) -> Result<Vec<DomainActivity>, DbErr> {
+    Entity::find()
+        .select_only()
+        .column(Column::Domain)
+        .column_as(Column::LastOpenedAt.max(), "last_opened_at")
+        .column_as(Column::CreatedAt.min(), "first_indexed_at")
+        .group_by(Column::Domain)
+        .into_model::<DomainActivity>()
+        .all(db)
+        .await
+}
+
+/// Mark the document w/ `doc_id` as opened just now.
+pub async fn mark_opened<C: ConnectionTrait>(db: &C, doc_id: &str) -> Result<(), DbErr> {
+    Entity::update_many()
+        .col_expr(
+            Column::LastOpenedAt,
+            sea_orm::sea_query::Expr::value(chrono::Utc::now()),
+        )
+        .filter(Column::DocId.eq(doc_id))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Document age stats, shared by the freshness queries.
+const FRESHNESS_COLUMNS: &str = "COUNT(*) AS num_docs, \
+    SUM(CASE WHEN julianday(indexed_document.updated_at) < julianday('now') - ? THEN 1 ELSE 0 END) AS num_stale, \
+    MIN(indexed_document.updated_at) AS oldest_updated_at, \
+    AVG((julianday('now') - julianday(indexed_document.updated_at)) * 86400) AS avg_age_secs";
+
+#[derive(Debug, FromQueryResult)]
+pub struct SourceFreshness {
+    pub source: String,
+    pub num_docs: i64,
+    /// Documents not updated within the source's recrawl target.
+    pub num_stale: i64,
+    pub oldest_updated_at: Option<DateTimeUtc>,
+    pub avg_age_secs: Option<f64>,
+}
+
+/// How stale the documents in each lens are, where documents not updated in
+/// `target_days` are stale.
+pub async fn lens_freshness<C: ConnectionTrait>(
+    db: &C,
+    target_days: u32,
+) -> Result<Vec<SourceFreshness>, DbErr> {
+    let sql = format!(
+        "SELECT tags.value AS source, {} FROM indexed_document \
+        JOIN document_tag ON document_tag.indexed_document_id = indexed_document.id \
+        JOIN tags ON tags.id = document_tag.tag_id \
+        WHERE tags.label = 'lens' \
+        GROUP BY tags.value",
+        FRESHNESS_COLUMNS
+    );
+
+    SourceFreshness::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Sqlite,
+        &sql,
+        vec![target_days.into()],
+    ))
+    .all(db)
+    .await
+}
+
+/// How stale the documents synced from each connection are. Connection
+/// documents are indexed under the connection's ID as their domain.
+pub async fn connection_freshness<C: ConnectionTrait>(
+    db: &C,
+    target_days: u32,
+) -> Result<Vec<SourceFreshness>, DbErr> {
+    let sql = format!(
+        "SELECT domain AS source, {} FROM indexed_document \
+        WHERE url LIKE 'api://%' \
+        GROUP BY domain",
+        FRESHNESS_COLUMNS
+    );
+
+    SourceFreshness::find_by_statement(Statement::from_sql_and_values(
+        DbBackend::Sqlite,
+        &sql,
+        vec![target_days.into()],
+    ))
+    .all(db)
+    .await
+}
+
+/// Connection documents from `domain` that are older than `cutoff`. Documents
+/// w/o a date of their own go by when they were first indexed.
+pub async fn expired_for_connection<C: ConnectionTrait>(
+    db: &C,
+    domain: &str,
+    cutoff: DateTimeUtc,
+) -> Result<Vec<Model>, DbErr> {
+    Entity::find()
+        .filter(Column::Domain.eq(domain))
+        .filter(Column::Url.starts_with("api://"))
+        .filter(sea_orm::sea_query::Expr::cust_with_values(
+            "COALESCE(document_date, created_at) < ?",
+            vec![cutoff],
+        ))
+        .all(db)
+        .await
 }
 
 #[derive(Debug, FromQueryResult)]
@@ -182,6 +386,122 @@ mod test {
         assert_eq!(removed.len(), 1);
     }
 
+    #[tokio::test]
+    async fn test_domain_activity() {
+        let db = setup_test_db().await;
+
+        for (url, doc_id) in [
+            ("https://example.com/a", "a"),
+            ("https://example.com/b", "b"),
+            ("https://other.com/c", "c"),
+        ] {
+            let doc = super::ActiveModel {
+                domain: Set(url::Url::parse(url).unwrap().host_str().unwrap().into()),
+                url: Set(url.into()),
+                doc_id: Set(doc_id.into()),
+                ..Default::default()
+            };
+            doc.save(&db).await.unwrap();
+        }
+
+        super::mark_opened(&db, "b").await.unwrap();
+
+        let activity = super::domain_activity(&db).await.unwrap();
+        assert_eq!(activity.len(), 2);
+        for domain in activity {
+            match domain.domain.as_str() {
+                "example.com" => assert!(domain.last_opened_at.is_some()),
+                _ => assert!(domain.last_opened_at.is_none()),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_freshness() -> Result<(), DbErr> {
+        let db = setup_test_db().await;
+
+        let week_ago = chrono::Utc::now() - chrono::Duration::days(7);
+        for (url, doc_id, updated_at) in [
+            ("https://example.com/a", "a", chrono::Utc::now()),
+            ("https://example.com/b", "b", week_ago),
+            ("api://calendar.google.com/primary/1", "c", week_ago),
+        ] {
+            let doc = super::ActiveModel {
+                domain: Set(url::Url::parse(url).unwrap().host_str().unwrap().into()),
+                url: Set(url.into()),
+                doc_id: Set(doc_id.into()),
+                updated_at: Set(updated_at),
+                ..Default::default()
+            };
+            let doc = doc.insert(&db).await?;
+            if doc.url.starts_with("https") {
+                let doc: super::ActiveModel = doc.into();
+                doc.insert_tags(&db, &[(tag::TagType::Lens, "example".to_owned())])
+                    .await?;
+            }
+        }
+
+        let lenses = super::lens_freshness(&db, 3).await?;
+        assert_eq!(lenses.len(), 1);
+        assert_eq!(lenses[0].source, "example");
+        assert_eq!(lenses[0].num_docs, 2);
+        assert_eq!(lenses[0].num_stale, 1);
+        assert!(lenses[0].oldest_updated_at.is_some());
+
+        let connections = super::connection_freshness(&db, 3).await?;
+        assert_eq!(connections.len(), 1);
+        assert_eq!(connections[0].source, "calendar.google.com");
+        assert_eq!(connections[0].num_stale, 1);
+        assert!(connections[0].avg_age_secs.unwrap_or_default() > 6.0 * 86400.0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_expired_for_connection() -> Result<(), DbErr> {
+        let db = setup_test_db().await;
+
+        let now = chrono::Utc::now();
+        let three_years_ago = now - chrono::Duration::days(3 * 365);
+        for (url, doc_id, document_date, created_at) in [
+            // Old event, indexed today
+            (
+                "api://calendar.google.com/primary/1",
+                "a",
+                Some(three_years_ago),
+                now,
+            ),
+            ("api://calendar.google.com/primary/2", "b", Some(now), now),
+            // No date of its own, falls back to when it was indexed
+            (
+                "api://calendar.google.com/primary/3",
+                "c",
+                None,
+                three_years_ago,
+            ),
+            ("https://calendar.google.com/", "d", None, three_years_ago),
+        ] {
+            let doc = super::ActiveModel {
+                domain: Set("calendar.google.com".into()),
+                url: Set(url.into()),
+                doc_id: Set(doc_id.into()),
+                document_date: Set(document_date),
+                created_at: Set(created_at),
+                ..Default::default()
+            };
+            doc.insert(&db).await?;
+        }
+
+        let cutoff = now - chrono::Duration::days(2 * 365);
+        let mut expired = super::expired_for_connection(&db, "calendar.google.com", cutoff)
+            .await?
+            .into_iter()
+            .map(|doc| doc.doc_id)
+            .collect::<Vec<_>>();
+        expired.sort();
+        assert_eq!(expired, vec!["a".to_string(), "c".to_string()]);
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_document_tag_support() -> Result<(), DbErr> {
         let db = setup_test_db().await;
@@ -219,4 +539,51 @@ mod test {
         assert_eq!(doc_tags.len(), 2);
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_remove_tags() -> Result<(), DbErr> {
+        let db = setup_test_db().await;
+
+        let doc = super::ActiveModel {
+            domain: Set("en.wikipedia.com".into()),
+            url: Set("https://en.wikipedia.org/wiki/Rust_(programming_language)".into()),
+            doc_id: Set("1".into()),
+            ..Default::default()
+        };
+        let doc = doc.save(&db).await.unwrap();
+        let mime_type = (tag::TagType::MimeType, "text/html".to_owned());
+        doc.insert_tags(
+            &db,
+            &[(tag::TagType::Source, "web".to_owned()), mime_type.clone()],
+        )
+        .await?;
+
+        let tagged = super::find_by_tag(&db, &mime_type).await?;
+        assert_eq!(tagged.len(), 1);
+
+        doc.remove_tags(&db, &[mime_type.clone()]).await?;
+        assert!(super::find_by_tag(&db, &mime_type).await?.is_empty());
+        let doc_tags = tagged[0].find_related(tag::Entity).all(&db).await?;
+        assert_eq!(doc_tags.len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_find_by_tag_value() -> Result<(), DbErr> {
+        let db = setup_test_db().await;
+
+        let doc = super::ActiveModel {
+            domain: Set("en.wikipedia.com".into()),
+            url: Set("https://en.wikipedia.org/wiki/Rust_(programming_language)".into()),
+            doc_id: Set("1".into()),
+            ..Default::default()
+        };
+        let doc = doc.save(&db).await.unwrap();
+        doc.insert_tags(&db, &[(tag::TagType::Source, "web".to_owned())])
+            .await?;
+
+        assert_eq!(super::find_by_tag_value(&db, "web").await?.len(), 1);
+        assert!(super::find_by_tag_value(&db, "archive").await?.is_empty());
+        Ok(())
+    }
 }
@@ -1,16 +1,27 @@
 use sea_orm::{ConnectOptions, Database, DatabaseConnection};
 
 pub mod bootstrap_queue;
+pub mod collection;
+pub mod collection_document;
 pub mod connection;
 pub mod crawl_queue;
 pub mod crawl_tag;
+pub mod document_note;
 pub mod document_tag;
+pub mod document_version;
+pub mod domain_fetch;
+pub mod domain_stats;
 pub mod fetch_history;
 pub mod indexed_document;
 pub mod lens;
 pub mod link;
+pub mod pinned_result;
 pub mod resource_rule;
+pub mod robots;
+pub mod saved_search;
+pub mod search_click;
 pub mod tag;
+pub mod watched_page;
 
 use shared::config::Config;
 
@@ -0,0 +1,163 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::{ConnectionTrait, QueryOrder, Set};
+use serde::Serialize;
+
+use super::indexed_document;
+
+/// A document pinned to the top of the results for a specific query.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Eq)]
+#[sea_orm(table_name = "pinned_result")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    /// Normalized query string, see `normalize_query`.
+    pub query: String,
+    pub indexed_document_id: i64,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {
+    IndexedDocument,
+}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        match self {
+            Self::IndexedDocument => Entity::belongs_to(indexed_document::Entity)
+                .from(Column::IndexedDocumentId)
+                .to(indexed_document::Column::Id)
+                .into(),
+        }
+    }
+}
+
+impl Related<indexed_document::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::IndexedDocument.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {
+    // Triggered before insert / update
+    fn before_save(mut self, insert: bool) -> Result<Self, DbErr> {
+        if insert {
+            self.created_at = Set(chrono::Utc::now());
+            self.updated_at = Set(chrono::Utc::now());
+        } else {
+            self.updated_at = Set(chrono::Utc::now());
+        }
+
+        Ok(self)
+    }
+}
+
+/// Pins shouldn't depend on the case or spacing used when typing the query.
+pub fn normalize_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+pub async fn pin<C: ConnectionTrait>(
+    db: &C,
+    query: &str,
+    doc: &indexed_document::Model,
+) -> Result<(), DbErr> {
+    let pinned = ActiveModel {
+        query: Set(normalize_query(query)),
+        indexed_document_id: Set(doc.id),
+        created_at: Set(chrono::Utc::now()),
+        updated_at: Set(chrono::Utc::now()),
+        ..Default::default()
+    };
+
+    // Ignore documents that are already pinned for this query.
+    Entity::insert(pinned)
+        .on_conflict(
+            sea_orm::sea_query::OnConflict::columns(vec![Column::Query, Column::IndexedDocumentId])
+                .do_nothing()
+                .to_owned(),
+        )
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn unpin<C: ConnectionTrait>(
+    db: &C,
+    query: &str,
+    doc: &indexed_document::Model,
+) -> Result<(), DbErr> {
+    Entity::delete_many()
+        .filter(Column::Query.eq(normalize_query(query)))
+        .filter(Column::IndexedDocumentId.eq(doc.id))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Search index ids of the documents pinned for `query`, in the order they
+/// were pinned.
+pub async fn doc_ids<C: ConnectionTrait>(db: &C, query: &str) -> Result<Vec<String>, DbErr> {
+    let pinned = Entity::find()
+        .filter(Column::Query.eq(normalize_query(query)))
+        .order_by_asc(Column::Id)
+        .find_also_related(indexed_document::Entity)
+        .all(db)
+        .await?;
+
+    Ok(pinned
+        .into_iter()
+        .filter_map(|(_, doc)| doc.map(|doc| doc.doc_id))
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::models::indexed_document;
+    use crate::test::setup_test_db;
+    use sea_orm::{ActiveModelTrait, Set};
+
+    #[test]
+    fn test_normalize_query() {
+        assert_eq!(
+            super::normalize_query("  Deploy   Runbook "),
+            "deploy runbook".to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pin() {
+        let db = setup_test_db().await;
+
+        let doc = indexed_document::ActiveModel {
+            domain: Set("wiki.example.com".into()),
+            url: Set("https://wiki.example.com/runbooks/deploy".into()),
+            doc_id: Set("runbook".into()),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+
+        super::pin(&db, "deploy runbook", &doc).await.unwrap();
+        super::pin(&db, "Deploy Runbook", &doc).await.unwrap();
+        assert_eq!(
+            super::doc_ids(&db, "deploy  runbook").await.unwrap(),
+            vec!["runbook".to_string()]
+        );
+        assert!(super::doc_ids(&db, "deploy").await.unwrap().is_empty());
+
+        super::unpin(&db, "DEPLOY RUNBOOK", &doc).await.unwrap();
+        assert!(super::doc_ids(&db, "deploy runbook")
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::{HashMap, HashSet};
+
+use regex::RegexSet;
+use sea_orm::entity::prelude::*;
+use sea_orm::sea_query::OnConflict;
+use sea_orm::{ConnectionTrait, Set};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// Refetch a domain's robots.txt once the cached copy is older than this.
+pub const ROBOTS_TTL_HOURS: i64 = 24;
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RobotsRule {
+    /// Path regex, see `shared::regex::regex_for_robots`
+    pub regex: String,
+    pub allow_crawl: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, FromJsonQueryResult)]
+pub struct RobotsRules {
+    pub rules: Vec<RobotsRule>,
+}
+
+/// Parsed robots.txt for a domain, cached so restarts don't refetch every
+/// domain we've crawled.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Eq)]
+#[sea_orm(table_name = "robots")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    #[sea_orm(unique)]
+    pub domain: String,
+    /// No rules means everything is allowed, e.g. when there's no robots.txt.
+    pub rules: RobotsRules,
+    /// Crawl-delay requested by the site, in milliseconds.
+    pub crawl_delay_ms: Option<i64>,
+    pub fetched_at: DateTimeUtc,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        panic!("No RelationDef")
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {
+    // Triggered before insert / update
+    fn before_save(mut self, insert: bool) -> Result<Self, DbErr> {
+        if insert {
+            self.created_at = Set(chrono::Utc::now());
+            self.updated_at = Set(chrono::Utc::now());
+        } else {
+            self.updated_at = Set(chrono::Utc::now());
+        }
+
+        Ok(self)
+    }
+}
+
+impl Model {
+    pub fn is_stale(&self) -> bool {
+        chrono::Utc::now() - self.fetched_at > chrono::Duration::hours(ROBOTS_TTL_HOURS)
+    }
+
+    /// Whether `path` (path + query) can be crawled. Allow rules take
+    /// precedence over disallow rules.
+    pub fn is_allowed(&self, path: &str) -> bool {
+        let rule_set = |allow: bool| {
+            RegexSet::new(
+                self.rules
+                    .rules
+                    .iter()
+                    .filter(|rule| rule.allow_crawl == allow)
+                    .map(|rule| format!("^{}", rule.regex)),
+            )
+        };
+
+        match (rule_set(true), rule_set(false)) {
+            (Ok(allow), Ok(disallow)) => allow.is_match(path) || !disallow.is_match(path),
+            _ => {
+                log::warn!("Invalid robots.txt rules for <{}>", self.domain);
+                true
+            }
+        }
+    }
+}
+
+/// Path + query of a URL, what robots.txt rules are matched against.
+pub fn robots_path(url: &Url) -> String {
+    url[url::Position::BeforePath..].to_string()
+}
+
+pub async fn find_by_domain<C: ConnectionTrait>(
+    db: &C,
+    domain: &str,
+) -> Result<Option<Model>, DbErr> {
+    Entity::find()
+        .filter(Column::Domain.eq(domain))
+        .one(db)
+        .await
+}
+
+/// Save the parsed robots.txt for a domain, replacing what was cached.
+pub async fn upsert<C: ConnectionTrait>(
+    db: &C,
+    domain: &str,
+    rules: Vec<RobotsRule>,
+    crawl_delay_ms: Option<i64>,
+) -> Result<(), DbErr> {
+    let now = chrono::Utc::now();
+    let model = ActiveModel {
+        domain: Set(domain.to_string()),
+        rules: Set(RobotsRules { rules }),
+        crawl_delay_ms: Set(crawl_delay_ms),
+        fetched_at: Set(now),
+        created_at: Set(now),
+        updated_at: Set(now),
+        ..Default::default()
+    };
+
+    Entity::insert(model)
+        .on_conflict(
+            OnConflict::column(Column::Domain)
+                .update_columns(vec![
+                    Column::Rules,
+                    Column::CrawlDelayMs,
+                    Column::FetchedAt,
+                    Column::UpdatedAt,
+                ])
+                .to_owned(),
+        )
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Web URLs from `urls` that the cached robots.txt of their domain disallows.
+/// Domains we haven't fetched a robots.txt for yet are left to the crawler.
+pub async fn disallowed<C: ConnectionTrait>(
+    db: &C,
+    urls: &[String],
+) -> Result<HashSet<String>, DbErr> {
+    let parsed = urls
+        .iter()
+        .filter_map(|url| Url::parse(url).ok().map(|parsed| (url, parsed)))
+        .filter(|(_, parsed)| parsed.scheme() == "http" || parsed.scheme() == "https")
+        .filter_map(|(url, parsed)| {
+            parsed
+                .host_str()
+                .map(|host| (url, host.to_string(), robots_path(&parsed)))
+        })
+        .collect::<Vec<_>>();
+
+    let domains = parsed
+        .iter()
+        .map(|(_, domain, _)| domain.clone())
+        .collect::<HashSet<String>>();
+    if domains.is_empty() {
+        return Ok(HashSet::new());
+    }
+
+    let cached = Entity::find()
+        .filter(Column::Domain.is_in(domains))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|model| (model.domain.clone(), model))
+        .collect::<HashMap<String, Model>>();
+
+    Ok(parsed
+        .into_iter()
+        .filter(|(_, domain, path)| {
+            cached
+                .get(domain)
+                .map_or(false, |robots| !robots.is_allowed(path))
+        })
+        .map(|(url, _, _)| url.to_string())
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{disallowed, find_by_domain, upsert, RobotsRule};
+    use crate::test::setup_test_db;
+
+    fn rules() -> Vec<RobotsRule> {
+        vec![
+            RobotsRule {
+                regex: "/private.*".into(),
+                allow_crawl: false,
+            },
+            RobotsRule {
+                regex: "/private/ok.*".into(),
+                allow_crawl: true,
+            },
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_is_allowed() {
+        let db = setup_test_db().await;
+        upsert(&db, "example.com", rules(), None).await.unwrap();
+
+        let robots = find_by_domain(&db, "example.com")
+            .await
+            .unwrap()
+            .expect("robots not saved");
+        assert!(!robots.is_stale());
+        assert!(robots.is_allowed("/"));
+        assert!(robots.is_allowed("/public/private"));
+        assert!(robots.is_allowed("/private/ok.html"));
+        assert!(!robots.is_allowed("/private/secret.html"));
+    }
+
+    #[tokio::test]
+    async fn test_upsert_replaces_rules() {
+        let db = setup_test_db().await;
+        upsert(&db, "example.com", rules(), None).await.unwrap();
+        upsert(&db, "example.com", Vec::new(), None).await.unwrap();
+
+        let robots = find_by_domain(&db, "example.com")
+            .await
+            .unwrap()
+            .expect("robots not saved");
+        assert!(robots.rules.rules.is_empty());
+        assert!(robots.is_allowed("/private/secret.html"));
+    }
+
+    #[tokio::test]
+    async fn test_disallowed() {
+        let db = setup_test_db().await;
+        upsert(&db, "example.com", rules(), None).await.unwrap();
+
+        let urls = vec![
+            "https://example.com/private/secret.html".to_string(),
+            "https://example.com/about".to_string(),
+            "https://uncached.com/private/secret.html".to_string(),
+            "file:///private/notes.md".to_string(),
+        ];
+        let res = disallowed(&db, &urls).await.unwrap();
+        assert_eq!(res.len(), 1);
+        assert!(res.contains("https://example.com/private/secret.html"));
+    }
+}
@@ -0,0 +1,207 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::{ConnectionTrait, QueryOrder, Set};
+use serde::{Deserialize, Serialize};
+
+/// Only this many unseen matches are kept per search, the user only needs to
+/// know there's something new.
+pub const MAX_NEW_MATCHES: usize = 100;
+
+/// Lenses a saved search is limited to, searches everything if empty.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, FromJsonQueryResult)]
+pub struct SearchLenses {
+    pub lenses: Vec<String>,
+}
+
+/// Documents that matched since the user was last notified.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, FromJsonQueryResult)]
+pub struct NewMatches {
+    pub doc_ids: Vec<String>,
+}
+
+/// A query the user wants to hear about when new documents match it.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Eq)]
+#[sea_orm(table_name = "saved_search")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    #[sea_orm(unique)]
+    pub name: String,
+    pub query: String,
+    pub lenses: SearchLenses,
+    pub new_matches: NewMatches,
+    /// Documents indexed after this haven't been checked against the query.
+    pub last_checked_at: DateTimeUtc,
+    pub last_matched_at: Option<DateTimeUtc>,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        panic!("No RelationDef")
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {
+    // Triggered before insert / update
+    fn before_save(mut self, insert: bool) -> Result<Self, DbErr> {
+        if insert {
+            self.created_at = Set(chrono::Utc::now());
+            self.updated_at = Set(chrono::Utc::now());
+        } else {
+            self.updated_at = Set(chrono::Utc::now());
+        }
+
+        Ok(self)
+    }
+}
+
+pub async fn find_by_name<C: ConnectionTrait>(db: &C, name: &str) -> Result<Option<Model>, DbErr> {
+    Entity::find().filter(Column::Name.eq(name)).one(db).await
+}
+
+pub async fn list<C: ConnectionTrait>(db: &C) -> Result<Vec<Model>, DbErr> {
+    Entity::find().order_by_asc(Column::Name).all(db).await
+}
+
+/// Save a search under `name`, replacing the query & lenses of an existing
+/// one. Only documents indexed from now on count as new matches.
+pub async fn save<C: ConnectionTrait>(
+    db: &C,
+    name: &str,
+    query: &str,
+    lenses: &[String],
+) -> Result<Model, DbErr> {
+    let lenses = SearchLenses {
+        lenses: lenses.to_vec(),
+    };
+
+    match find_by_name(db, name).await? {
+        Some(existing) => {
+            let mut update: ActiveModel = existing.into();
+            update.query = Set(query.to_string());
+            update.lenses = Set(lenses);
+            update.new_matches = Set(NewMatches::default());
+            update.last_checked_at = Set(chrono::Utc::now());
+            update.update(db).await
+        }
+        None => {
+            ActiveModel {
+                name: Set(name.to_string()),
+                query: Set(query.to_string()),
+                lenses: Set(lenses),
+                new_matches: Set(NewMatches::default()),
+                last_checked_at: Set(chrono::Utc::now()),
+                ..Default::default()
+            }
+            .insert(db)
+            .await
+        }
+    }
+}
+
+pub async fn delete<C: ConnectionTrait>(db: &C, name: &str) -> Result<(), DbErr> {
+    Entity::delete_many()
+        .filter(Column::Name.eq(name))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Note that documents indexed up until `checked_at` have been checked &
+/// which of them matched, if any.
+pub async fn mark_checked<C: ConnectionTrait>(
+    db: &C,
+    search: Model,
+    checked_at: DateTimeUtc,
+    doc_ids: &[String],
+) -> Result<Model, DbErr> {
+    let mut new_matches = search.new_matches.clone();
+    for doc_id in doc_ids {
+        if new_matches.doc_ids.len() >= MAX_NEW_MATCHES {
+            break;
+        }
+
+        if !new_matches.doc_ids.contains(doc_id) {
+            new_matches.doc_ids.push(doc_id.clone());
+        }
+    }
+
+    let has_matches = !doc_ids.is_empty();
+    let mut update: ActiveModel = search.into();
+    update.last_checked_at = Set(checked_at);
+    if has_matches {
+        update.new_matches = Set(new_matches);
+        update.last_matched_at = Set(Some(chrono::Utc::now()));
+    }
+    update.update(db).await
+}
+
+/// Saved searches w/ matches the user hasn't been notified about yet,
+/// clearing them.
+pub async fn take_alerts<C: ConnectionTrait>(db: &C) -> Result<Vec<Model>, DbErr> {
+    let pending = list(db)
+        .await?
+        .into_iter()
+        .filter(|search| !search.new_matches.doc_ids.is_empty())
+        .collect::<Vec<_>>();
+
+    for search in &pending {
+        let mut update: ActiveModel = search.clone().into();
+        update.new_matches = Set(NewMatches::default());
+        update.update(db).await?;
+    }
+
+    Ok(pending)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::setup_test_db;
+
+    #[tokio::test]
+    async fn test_saved_search_alerts() {
+        let db = setup_test_db().await;
+
+        let search = super::save(&db, "rust", "rust async", &["rust".into()])
+            .await
+            .unwrap();
+        assert_eq!(search.lenses.lenses, vec!["rust".to_string()]);
+
+        // Saving again updates the existing search
+        let search = super::save(&db, "rust", "rust tokio", &[]).await.unwrap();
+        assert_eq!(search.query, "rust tokio");
+        assert!(search.lenses.lenses.is_empty());
+        assert_eq!(super::list(&db).await.unwrap().len(), 1);
+
+        // Nothing new, nothing to notify about
+        let checked_at = chrono::Utc::now();
+        let search = super::mark_checked(&db, search, checked_at, &[])
+            .await
+            .unwrap();
+        assert!(super::take_alerts(&db).await.unwrap().is_empty());
+
+        let search = super::mark_checked(&db, search, checked_at, &["a".into(), "b".into()])
+            .await
+            .unwrap();
+        super::mark_checked(&db, search, checked_at, &["b".into(), "c".into()])
+            .await
+            .unwrap();
+
+        let alerts = super::take_alerts(&db).await.unwrap();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(
+            alerts[0].new_matches.doc_ids,
+            vec!["a".to_string(), "b".to_string(), "c".to_string()]
+        );
+        // Only alerted once
+        assert!(super::take_alerts(&db).await.unwrap().is_empty());
+
+        super::delete(&db, "rust").await.unwrap();
+        assert!(super::list(&db).await.unwrap().is_empty());
+    }
+}
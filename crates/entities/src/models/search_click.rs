@@ -0,0 +1,134 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::{ConnectionTrait, Set};
+use serde::Serialize;
+
+use super::indexed_document;
+
+/// A search result the user opened, used to rank the documents they keep
+/// going back to higher.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Eq)]
+#[sea_orm(table_name = "search_click")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    pub indexed_document_id: i64,
+    pub clicked_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {
+    IndexedDocument,
+}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        match self {
+            Self::IndexedDocument => Entity::belongs_to(indexed_document::Entity)
+                .from(Column::IndexedDocumentId)
+                .to(indexed_document::Column::Id)
+                .into(),
+        }
+    }
+}
+
+impl Related<indexed_document::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::IndexedDocument.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
+
+/// Note that the document w/ `doc_id` was opened just now. Documents that
+/// aren't in the database (anymore) are ignored.
+pub async fn record<C: ConnectionTrait>(db: &C, doc_id: &str) -> Result<(), DbErr> {
+    let doc = indexed_document::Entity::find()
+        .filter(indexed_document::Column::DocId.eq(doc_id))
+        .one(db)
+        .await?;
+
+    if let Some(doc) = doc {
+        ActiveModel {
+            indexed_document_id: Set(doc.id),
+            clicked_at: Set(chrono::Utc::now()),
+            ..Default::default()
+        }
+        .insert(db)
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Clicks since `since` as (search index id, clicked @) pairs.
+pub async fn since<C: ConnectionTrait>(
+    db: &C,
+    since: DateTimeUtc,
+) -> Result<Vec<(String, DateTimeUtc)>, DbErr> {
+    let clicks = Entity::find()
+        .filter(Column::ClickedAt.gte(since))
+        .find_also_related(indexed_document::Entity)
+        .all(db)
+        .await?;
+
+    Ok(clicks
+        .into_iter()
+        .filter_map(|(click, doc)| doc.map(|doc| (doc.doc_id, click.clicked_at)))
+        .collect())
+}
+
+/// Remove clicks from before `before`, returns the number removed.
+pub async fn prune<C: ConnectionTrait>(db: &C, before: DateTimeUtc) -> Result<u64, DbErr> {
+    let res = Entity::delete_many()
+        .filter(Column::ClickedAt.lt(before))
+        .exec(db)
+        .await?;
+
+    Ok(res.rows_affected)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, Utc};
+    use sea_orm::{ActiveModelTrait, Set};
+
+    use crate::models::indexed_document;
+    use crate::test::setup_test_db;
+
+    #[tokio::test]
+    async fn test_record() {
+        let db = setup_test_db().await;
+
+        indexed_document::ActiveModel {
+            domain: Set("wiki.example.com".into()),
+            url: Set("https://wiki.example.com/runbooks/deploy".into()),
+            doc_id: Set("runbook".into()),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+
+        super::record(&db, "runbook").await.unwrap();
+        super::record(&db, "runbook").await.unwrap();
+        // Not indexed
+        super::record(&db, "missing").await.unwrap();
+
+        let an_hour_ago = Utc::now() - Duration::hours(1);
+        let clicks = super::since(&db, an_hour_ago).await.unwrap();
+        assert_eq!(clicks.len(), 2);
+        assert!(clicks.iter().all(|(doc_id, _)| doc_id == "runbook"));
+        assert!(super::since(&db, Utc::now() + Duration::hours(1))
+            .await
+            .unwrap()
+            .is_empty());
+
+        assert_eq!(super::prune(&db, an_hour_ago).await.unwrap(), 0);
+        assert_eq!(
+            super::prune(&db, Utc::now() + Duration::hours(1))
+                .await
+                .unwrap(),
+            2
+        );
+    }
+}
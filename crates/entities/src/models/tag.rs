@@ -41,12 +41,24 @@ pub enum TagType {
     // Part of this/these lens(es)
     #[sea_orm(string_value = "lens")]
     Lens,
+    // Something notable about the contents of the document, e.g. has-secrets
+    #[sea_orm(string_value = "flag")]
+    Flag,
+    // Notebook/folder/section the document is filed under in its source app
+    #[sea_orm(string_value = "folder")]
+    Folder,
+    // Sent a message in the document, e.g. a chat participant
+    #[sea_orm(string_value = "sender")]
+    Sender,
 }
 
 #[derive(AsRefStr)]
 pub enum TagValue {
     #[strum(serialize = "favorited")]
     Favorited,
+    // Credentials were found & redacted before indexing
+    #[strum(serialize = "has-secrets")]
+    HasSecrets,
 }
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Eq)]
@@ -0,0 +1,192 @@
+use sea_orm::entity::prelude::*;
+use sea_orm::{ConnectionTrait, QueryOrder, Set};
+use serde::Serialize;
+
+/// Watched pages are never checked more often than this.
+pub const MIN_CHECK_INTERVAL_MINS: i64 = 5;
+pub const DEFAULT_CHECK_INTERVAL_MINS: i64 = 60;
+
+/// A URL the user wants to be alerted about when its content changes.
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Eq)]
+#[sea_orm(table_name = "watched_page")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i64,
+    #[sea_orm(unique)]
+    pub url: String,
+    /// How often the page is recrawled.
+    pub check_interval_mins: i64,
+    pub last_checked_at: Option<DateTimeUtc>,
+    /// When a meaningful change was last detected & what it was.
+    pub last_changed_at: Option<DateTimeUtc>,
+    pub change_summary: Option<String>,
+    /// Whether the user has been alerted about the last change.
+    pub is_notified: bool,
+    pub created_at: DateTimeUtc,
+    pub updated_at: DateTimeUtc,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter)]
+pub enum Relation {}
+
+impl RelationTrait for Relation {
+    fn def(&self) -> RelationDef {
+        panic!("No RelationDef")
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {
+    // Triggered before insert / update
+    fn before_save(mut self, insert: bool) -> Result<Self, DbErr> {
+        if insert {
+            self.created_at = Set(chrono::Utc::now());
+            self.updated_at = Set(chrono::Utc::now());
+        } else {
+            self.updated_at = Set(chrono::Utc::now());
+        }
+
+        Ok(self)
+    }
+}
+
+pub async fn find_by_url<C: ConnectionTrait>(db: &C, url: &str) -> Result<Option<Model>, DbErr> {
+    Entity::find().filter(Column::Url.eq(url)).one(db).await
+}
+
+/// Start watching `url`, or update how often it's checked if it's already
+/// being watched.
+pub async fn watch<C: ConnectionTrait>(
+    db: &C,
+    url: &str,
+    check_interval_mins: i64,
+) -> Result<Model, DbErr> {
+    let check_interval_mins = check_interval_mins.max(MIN_CHECK_INTERVAL_MINS);
+    match find_by_url(db, url).await? {
+        Some(existing) => {
+            let mut update: ActiveModel = existing.into();
+            update.check_interval_mins = Set(check_interval_mins);
+            update.update(db).await
+        }
+        None => {
+            ActiveModel {
+                url: Set(url.to_string()),
+                check_interval_mins: Set(check_interval_mins),
+                is_notified: Set(true),
+                ..Default::default()
+            }
+            .insert(db)
+            .await
+        }
+    }
+}
+
+pub async fn unwatch<C: ConnectionTrait>(db: &C, url: &str) -> Result<(), DbErr> {
+    Entity::delete_many()
+        .filter(Column::Url.eq(url))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Watched pages that haven't been checked within their interval.
+pub async fn due<C: ConnectionTrait>(db: &C) -> Result<Vec<Model>, DbErr> {
+    let now = chrono::Utc::now();
+    Ok(Entity::find()
+        .order_by_asc(Column::LastCheckedAt)
+        .all(db)
+        .await?
+        .into_iter()
+        .filter(|page| match page.last_checked_at {
+            Some(checked) => now - checked >= chrono::Duration::minutes(page.check_interval_mins),
+            None => true,
+        })
+        .collect())
+}
+
+pub async fn mark_checked<C: ConnectionTrait>(db: &C, id: i64) -> Result<(), DbErr> {
+    Entity::update_many()
+        .col_expr(
+            Column::LastCheckedAt,
+            sea_orm::sea_query::Expr::value(chrono::Utc::now()),
+        )
+        .filter(Column::Id.eq(id))
+        .exec(db)
+        .await?;
+
+    Ok(())
+}
+
+/// Note that a watched page changed, queueing up an alert. Does nothing if
+/// `url` isn't being watched.
+pub async fn record_change<C: ConnectionTrait>(
+    db: &C,
+    url: &str,
+    summary: &str,
+) -> Result<(), DbErr> {
+    if let Some(page) = find_by_url(db, url).await? {
+        let mut update: ActiveModel = page.into();
+        update.last_changed_at = Set(Some(chrono::Utc::now()));
+        update.change_summary = Set(Some(summary.to_string()));
+        update.is_notified = Set(false);
+        update.update(db).await?;
+    }
+
+    Ok(())
+}
+
+/// Changed pages the user hasn't been alerted about yet, marking them as
+/// notified.
+pub async fn take_alerts<C: ConnectionTrait>(db: &C) -> Result<Vec<Model>, DbErr> {
+    let pending = Entity::find()
+        .filter(Column::IsNotified.eq(false))
+        .order_by_asc(Column::LastChangedAt)
+        .all(db)
+        .await?;
+
+    if !pending.is_empty() {
+        Entity::update_many()
+            .col_expr(Column::IsNotified, sea_orm::sea_query::Expr::value(true))
+            .filter(Column::Id.is_in(pending.iter().map(|page| page.id)))
+            .exec(db)
+            .await?;
+    }
+
+    Ok(pending)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::test::setup_test_db;
+
+    #[tokio::test]
+    async fn test_watch_alerts() {
+        let db = setup_test_db().await;
+        let url = "https://status.example.com/";
+
+        let page = super::watch(&db, url, 1).await.unwrap();
+        assert_eq!(page.check_interval_mins, super::MIN_CHECK_INTERVAL_MINS);
+        assert_eq!(super::due(&db).await.unwrap().len(), 1);
+
+        super::mark_checked(&db, page.id).await.unwrap();
+        assert!(super::due(&db).await.unwrap().is_empty());
+
+        // Unwatched pages are ignored
+        super::record_change(&db, "https://example.com", "New post")
+            .await
+            .unwrap();
+        assert!(super::take_alerts(&db).await.unwrap().is_empty());
+
+        super::record_change(&db, url, "Degraded performance")
+            .await
+            .unwrap();
+        let alerts = super::take_alerts(&db).await.unwrap();
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(
+            alerts[0].change_summary,
+            Some("Degraded performance".to_string())
+        );
+        // Only alerted once
+        assert!(super::take_alerts(&db).await.unwrap().is_empty());
+    }
+}
@@ -2,22 +2,55 @@ use tantivy::schema::*;
 
 pub type FieldName = String;
 pub type SchemaMapping = Vec<(FieldName, TextOptions)>;
+/// Hierarchical facet fields, added to the schema after the text fields.
+pub type FacetMapping = Vec<FieldName>;
 
 pub trait SearchDocument {
     fn as_field_vec() -> SchemaMapping;
 
+    fn as_facet_vec() -> FacetMapping {
+        Vec::new()
+    }
+
     fn as_schema() -> Schema {
-        mapping_to_schema(&Self::as_field_vec())
+        mapping_to_schema_with_facets(&Self::as_field_vec(), &Self::as_facet_vec())
     }
 
     fn as_fields() -> Self;
 }
 
+/// Tokenizers for the language specific content fields, registered on the
+/// index when it's opened.
+pub const TOKENIZER_DE: &str = "lang_de";
+pub const TOKENIZER_ES: &str = "lang_es";
+pub const TOKENIZER_FR: &str = "lang_fr";
+pub const TOKENIZER_CJK: &str = "lang_cjk";
+
+/// Left in the index directory by the migration adding the language fields,
+/// so documents indexed before them are backfilled on the next start.
+pub const LANG_BACKFILL_MARKER: &str = "backfill_lang";
+
+/// Indexed (but not stored) text w/ a custom tokenizer.
+pub fn text_with_tokenizer(tokenizer: &str) -> TextOptions {
+    TextOptions::default().set_indexing_options(
+        TextFieldIndexing::default()
+            .set_tokenizer(tokenizer)
+            .set_index_option(IndexRecordOption::WithFreqsAndPositions),
+    )
+}
+
 pub fn mapping_to_schema(mapping: &SchemaMapping) -> Schema {
+    mapping_to_schema_with_facets(mapping, &[])
+}
+
+pub fn mapping_to_schema_with_facets(mapping: &SchemaMapping, facets: &[FieldName]) -> Schema {
     let mut schema_builder = Schema::builder();
     for (name, opts) in mapping {
         schema_builder.add_text_field(name, opts.clone());
     }
+    for name in facets {
+        schema_builder.add_facet_field(name, FacetOptions::default().set_stored());
+    }
     schema_builder.build()
 }
 
@@ -29,6 +62,33 @@ pub struct DocFields {
     pub description: Field,
     pub title: Field,
     pub url: Field,
+    pub fields: Field,
+    pub code: Field,
+    pub parent_id: Field,
+    pub anchor: Field,
+    pub version_of: Field,
+    pub crawled_at: Field,
+    pub superseded_at: Field,
+    pub numbers: Field,
+    pub tags: Field,
+    pub lang: Field,
+    pub content_de: Field,
+    pub content_es: Field,
+    pub content_fr: Field,
+    pub content_cjk: Field,
+}
+
+impl DocFields {
+    /// Fields holding the title & content of documents in a language the
+    /// default tokenizer doesn't handle well, stemmed/segmented for it.
+    pub fn language_fields(&self) -> [Field; 4] {
+        [
+            self.content_de,
+            self.content_es,
+            self.content_fr,
+            self.content_cjk,
+        ]
+    }
 }
 
 impl SearchDocument for DocFields {
@@ -53,9 +113,39 @@ impl SearchDocument for DocFields {
             ("url".into(), STRING | STORED | FAST),
             // Indexed
             ("content".into(), TEXT | STORED),
+            // Structured "name:value" pairs, used for filtering
+            ("fields".into(), STRING | STORED),
+            // Code blocks, searched w/ `code:` queries
+            ("code".into(), TEXT | STORED),
+            // Sections of long documents are indexed as their own documents,
+            // pointing back to the full document & the anchor to jump to.
+            ("parent_id".into(), STRING | STORED),
+            ("anchor".into(), STRING | STORED),
+            // Past versions of a re-indexed document point back to it, w/ the
+            // RFC 3339 timestamps the version was crawled & replaced at.
+            ("version_of".into(), STRING | STORED),
+            ("crawled_at".into(), STRING | STORED),
+            ("superseded_at".into(), STRING | STORED),
+            // Numeric "name:value" pairs, e.g. size & word count. Values are
+            // zero padded so range queries on the terms compare them as numbers.
+            ("numbers".into(), STRING | STORED),
+            // ISO 639-3 code of the language detected in the document
+            ("lang".into(), STRING | STORED),
+            // Title & content again, tokenized for the detected language.
+            // Only one is set per document & they aren't stored since the
+            // content already is.
+            ("content_de".into(), text_with_tokenizer(TOKENIZER_DE)),
+            ("content_es".into(), text_with_tokenizer(TOKENIZER_ES)),
+            ("content_fr".into(), text_with_tokenizer(TOKENIZER_FR)),
+            ("content_cjk".into(), text_with_tokenizer(TOKENIZER_CJK)),
         ]
     }
 
+    fn as_facet_vec() -> FacetMapping {
+        // Tags as "/label/value" facets, counted to drill down into results.
+        vec!["tags".into()]
+    }
+
     fn as_fields() -> Self {
         let schema = Self::as_schema();
         Self {
@@ -67,6 +157,36 @@ impl SearchDocument for DocFields {
                 .expect("No description in schema"),
             title: schema.get_field("title").expect("No title in schema"),
             url: schema.get_field("url").expect("No url in schema"),
+            fields: schema.get_field("fields").expect("No fields in schema"),
+            code: schema.get_field("code").expect("No code in schema"),
+            parent_id: schema
+                .get_field("parent_id")
+                .expect("No parent_id in schema"),
+            anchor: schema.get_field("anchor").expect("No anchor in schema"),
+            version_of: schema
+                .get_field("version_of")
+                .expect("No version_of in schema"),
+            crawled_at: schema
+                .get_field("crawled_at")
+                .expect("No crawled_at in schema"),
+            superseded_at: schema
+                .get_field("superseded_at")
+                .expect("No superseded_at in schema"),
+            numbers: schema.get_field("numbers").expect("No numbers in schema"),
+            tags: schema.get_field("tags").expect("No tags in schema"),
+            lang: schema.get_field("lang").expect("No lang in schema"),
+            content_de: schema
+                .get_field("content_de")
+                .expect("No content_de in schema"),
+            content_es: schema
+                .get_field("content_es")
+                .expect("No content_es in schema"),
+            content_fr: schema
+                .get_field("content_fr")
+                .expect("No content_fr in schema"),
+            content_cjk: schema
+                .get_field("content_cjk")
+                .expect("No content_cjk in schema"),
         }
     }
 }
@@ -2,8 +2,10 @@ use sea_orm::{sea_query::Index, ConnectionTrait, DatabaseConnection, Schema};
 use shared::config::Config;
 
 use crate::models::{
-    bootstrap_queue, crawl_queue, crawl_tag, create_connection, document_tag, fetch_history,
-    indexed_document, lens, link, resource_rule, tag,
+    bootstrap_queue, collection, collection_document, connection, crawl_queue, crawl_tag,
+    create_connection, document_note, document_tag, document_version, domain_fetch, domain_stats,
+    fetch_history, indexed_document, lens, link, pinned_result, resource_rule, robots,
+    saved_search, search_click, tag, watched_page,
 };
 
 #[allow(dead_code)]
@@ -53,6 +55,30 @@ async fn setup_schema(db: &DatabaseConnection) -> anyhow::Result<(), sea_orm::Db
         ),
     )
     .await?;
+    db.execute(
+        builder.build(
+            schema
+                .create_table_from_entity(robots::Entity)
+                .if_not_exists(),
+        ),
+    )
+    .await?;
+    db.execute(
+        builder.build(
+            schema
+                .create_table_from_entity(domain_fetch::Entity)
+                .if_not_exists(),
+        ),
+    )
+    .await?;
+    db.execute(
+        builder.build(
+            schema
+                .create_table_from_entity(domain_stats::Entity)
+                .if_not_exists(),
+        ),
+    )
+    .await?;
     db.execute(
         builder.build(
             schema
@@ -101,6 +127,87 @@ async fn setup_schema(db: &DatabaseConnection) -> anyhow::Result<(), sea_orm::Db
     )
     .await?;
 
+    db.execute(
+        builder.build(
+            schema
+                .create_table_from_entity(collection::Entity)
+                .if_not_exists(),
+        ),
+    )
+    .await?;
+
+    db.execute(
+        builder.build(
+            schema
+                .create_table_from_entity(collection_document::Entity)
+                .if_not_exists(),
+        ),
+    )
+    .await?;
+
+    db.execute(
+        builder.build(
+            schema
+                .create_table_from_entity(document_note::Entity)
+                .if_not_exists(),
+        ),
+    )
+    .await?;
+
+    db.execute(
+        builder.build(
+            schema
+                .create_table_from_entity(pinned_result::Entity)
+                .if_not_exists(),
+        ),
+    )
+    .await?;
+
+    db.execute(
+        builder.build(
+            schema
+                .create_table_from_entity(search_click::Entity)
+                .if_not_exists(),
+        ),
+    )
+    .await?;
+
+    db.execute(
+        builder.build(
+            schema
+                .create_table_from_entity(saved_search::Entity)
+                .if_not_exists(),
+        ),
+    )
+    .await?;
+
+    db.execute(
+        builder.build(
+            schema
+                .create_table_from_entity(document_version::Entity)
+                .if_not_exists(),
+        ),
+    )
+    .await?;
+
+    db.execute(
+        builder.build(
+            schema
+                .create_table_from_entity(watched_page::Entity)
+                .if_not_exists(),
+        ),
+    )
+    .await?;
+
+    db.execute(
+        builder.build(
+            schema
+                .create_table_from_entity(connection::Entity)
+                .if_not_exists(),
+        ),
+    )
+    .await?;
+
     db.execute(
         builder.build(
             &Index::create()
@@ -140,5 +247,31 @@ async fn setup_schema(db: &DatabaseConnection) -> anyhow::Result<(), sea_orm::Db
     )
     .await?;
 
+    db.execute(
+        builder.build(
+            &Index::create()
+                .unique()
+                .name("idx-collection-document-collection-id-doc-id")
+                .table(collection_document::Entity)
+                .col(collection_document::Column::CollectionId)
+                .col(collection_document::Column::IndexedDocumentId)
+                .to_owned(),
+        ),
+    )
+    .await?;
+
+    db.execute(
+        builder.build(
+            &Index::create()
+                .unique()
+                .name("idx-pinned-result-query-doc-id")
+                .table(pinned_result::Entity)
+                .col(pinned_result::Column::Query)
+                .col(pinned_result::Column::IndexedDocumentId)
+                .to_owned(),
+        ),
+    )
+    .await?;
+
     Ok(())
 }
@@ -21,6 +21,31 @@ mod m20221121_000001_add_data_to_crawl_queue;
 mod m20221123_000001_add_document_tag_constraint;
 mod m20221124_000001_add_tags_for_existing_lenses;
 mod m20221210_000001_add_crawl_tags_table;
+mod m20221213_000001_add_fields_to_search_schema;
+mod m20221214_000001_add_code_to_search_schema;
+mod m20221215_000001_add_sections_to_search_schema;
+mod m20221216_000001_add_collections_table;
+mod m20221217_000001_add_pinned_result_table;
+mod m20221218_000001_add_document_note_table;
+mod m20221219_000001_add_last_opened_col;
+mod m20221220_000001_add_document_version_table;
+mod m20221221_000001_add_versions_to_search_schema;
+mod m20221222_000001_add_watched_page_table;
+mod m20221223_000001_add_numbers_to_search_schema;
+mod m20221224_000001_add_connection_usage_cols;
+mod m20221225_000001_add_connection_sync_cursor;
+mod m20221226_000001_add_document_date_col;
+mod m20221227_000001_add_robots_table;
+mod m20221228_000001_add_domain_fetch_table;
+mod m20221229_000001_add_crawl_priority;
+mod m20221230_000001_add_crawl_retry_at;
+mod m20221231_000001_add_crawl_queue_indexes;
+mod m20230101_000001_add_tags_to_search_schema;
+mod m20230102_000001_add_domain_stats_table;
+mod m20230103_000001_add_search_click_table;
+mod m20230104_000001_add_saved_search_table;
+mod m20230105_000001_add_fetch_info_to_crawl_queue;
+mod m20230106_000001_add_lang_fields_to_search_schema;
 mod utils;
 
 pub struct Migrator;
@@ -47,6 +72,31 @@ impl MigratorTrait for Migrator {
             Box::new(m20221123_000001_add_document_tag_constraint::Migration),
             Box::new(m20221124_000001_add_tags_for_existing_lenses::Migration),
             Box::new(m20221210_000001_add_crawl_tags_table::Migration),
+            Box::new(m20221213_000001_add_fields_to_search_schema::Migration),
+            Box::new(m20221214_000001_add_code_to_search_schema::Migration),
+            Box::new(m20221215_000001_add_sections_to_search_schema::Migration),
+            Box::new(m20221216_000001_add_collections_table::Migration),
+            Box::new(m20221217_000001_add_pinned_result_table::Migration),
+            Box::new(m20221218_000001_add_document_note_table::Migration),
+            Box::new(m20221219_000001_add_last_opened_col::Migration),
+            Box::new(m20221220_000001_add_document_version_table::Migration),
+            Box::new(m20221221_000001_add_versions_to_search_schema::Migration),
+            Box::new(m20221222_000001_add_watched_page_table::Migration),
+            Box::new(m20221223_000001_add_numbers_to_search_schema::Migration),
+            Box::new(m20221224_000001_add_connection_usage_cols::Migration),
+            Box::new(m20221225_000001_add_connection_sync_cursor::Migration),
+            Box::new(m20221226_000001_add_document_date_col::Migration),
+            Box::new(m20221227_000001_add_robots_table::Migration),
+            Box::new(m20221228_000001_add_domain_fetch_table::Migration),
+            Box::new(m20221229_000001_add_crawl_priority::Migration),
+            Box::new(m20221230_000001_add_crawl_retry_at::Migration),
+            Box::new(m20221231_000001_add_crawl_queue_indexes::Migration),
+            Box::new(m20230101_000001_add_tags_to_search_schema::Migration),
+            Box::new(m20230102_000001_add_domain_stats_table::Migration),
+            Box::new(m20230103_000001_add_search_click_table::Migration),
+            Box::new(m20230104_000001_add_saved_search_table::Migration),
+            Box::new(m20230105_000001_add_fetch_info_to_crawl_queue::Migration),
+            Box::new(m20230106_000001_add_lang_fields_to_search_schema::Migration),
         ]
     }
 }
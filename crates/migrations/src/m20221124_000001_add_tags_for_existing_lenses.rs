@@ -5,8 +5,8 @@ use entities::{
         tag::{get_or_create, TagType},
     },
     sea_orm::{
-        ColumnTrait, ConnectionTrait, DatabaseTransaction, EntityTrait, QueryFilter, Set,
-        TransactionTrait,
+        ColumnTrait, ConnectionTrait, DatabaseTransaction, EntityTrait, FromQueryResult,
+        QueryFilter, QuerySelect, Set, TransactionTrait,
     },
 };
 use sea_orm_migration::prelude::*;
@@ -21,6 +21,12 @@ impl MigrationName for Migration {
     }
 }
 
+// Only select the id, columns added by later migrations won't exist yet.
+#[derive(FromQueryResult)]
+struct DocId {
+    id: i64,
+}
+
 async fn add_tags_for_url<C>(tx: &C, name: &str, url: &str) -> Result<(), DbErr>
 where
     C: ConnectionTrait,
@@ -33,7 +39,10 @@ where
     // Update existing documents
     let start_time = Instant::now();
     let existing_docs = indexed_document::Entity::find()
+        .select_only()
+        .column(indexed_document::Column::Id)
         .filter(indexed_document::Column::Url.contains(url))
+        .into_model::<DocId>()
         .all(tx)
         .await?;
 
@@ -0,0 +1,51 @@
+use sea_orm_migration::prelude::*;
+use shared::config::Config;
+use tantivy::schema::*;
+
+use entities::schema::SchemaMapping;
+
+use crate::utils::search_schema::migrate_index;
+
+pub struct Migration;
+
+impl Migration {
+    pub fn before_schema(&self) -> SchemaMapping {
+        vec![
+            ("id".into(), STRING | STORED | FAST),
+            ("domain".into(), STRING | STORED | FAST),
+            ("title".into(), TEXT | STORED | FAST),
+            ("description".into(), TEXT | STORED),
+            ("url".into(), STRING | STORED | FAST),
+            ("content".into(), TEXT | STORED),
+        ]
+    }
+
+    pub fn after_schema(&self) -> SchemaMapping {
+        let mut schema = self.before_schema();
+        // Structured "name:value" pairs used for filtering
+        schema.push(("fields".into(), STRING | STORED));
+        schema
+    }
+}
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20221213_000001_add_fields_to_search_schema"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        let config = Config::new();
+        migrate_index(
+            &config.index_dir(),
+            &self.before_schema(),
+            &self.after_schema(),
+        )
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
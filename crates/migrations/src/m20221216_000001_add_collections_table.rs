@@ -0,0 +1,65 @@
+use crate::sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::ConnectionTrait;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20221216_000001_add_collections_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                r#"CREATE TABLE IF NOT EXISTS "collection" (
+                    "id" integer NOT NULL PRIMARY KEY AUTOINCREMENT,
+                    "name" text NOT NULL UNIQUE,
+                    "description" text,
+                    "created_at" text NOT NULL,
+                    "updated_at" text NOT NULL
+                );"#
+                .to_string(),
+            ))
+            .await?;
+
+        // Add through table
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                r#"CREATE TABLE IF NOT EXISTS "collection_document" (
+                    "id" integer NOT NULL PRIMARY KEY AUTOINCREMENT,
+                    "collection_id" integer NOT NULL,
+                    "indexed_document_id" integer NOT NULL,
+                    "created_at" text NOT NULL,
+                    "updated_at" text NOT NULL,
+                    FOREIGN KEY(collection_id)       REFERENCES collection(id),
+                    FOREIGN KEY(indexed_document_id) REFERENCES indexed_document(id)
+                );"#
+                .to_string(),
+            ))
+            .await?;
+
+        // A document should only be in a collection once.
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                "CREATE UNIQUE INDEX IF NOT EXISTS `idx-collection-document-collection-id-doc-id` ON `collection_document` (`collection_id`, `indexed_document_id`);"
+                    .to_string(),
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+use crate::sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::ConnectionTrait;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20221217_000001_add_pinned_result_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                r#"CREATE TABLE IF NOT EXISTS "pinned_result" (
+                    "id" integer NOT NULL PRIMARY KEY AUTOINCREMENT,
+                    "query" text NOT NULL,
+                    "indexed_document_id" integer NOT NULL,
+                    "created_at" text NOT NULL,
+                    "updated_at" text NOT NULL,
+                    FOREIGN KEY(indexed_document_id) REFERENCES indexed_document(id)
+                );"#
+                .to_string(),
+            ))
+            .await?;
+
+        // Pins are looked up by query & a document is only pinned once per query.
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                "CREATE UNIQUE INDEX IF NOT EXISTS `idx-pinned-result-query-doc-id` ON `pinned_result` (`query`, `indexed_document_id`);"
+                    .to_string(),
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,48 @@
+use crate::sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::ConnectionTrait;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20221218_000001_add_document_note_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                r#"CREATE TABLE IF NOT EXISTS "document_note" (
+                    "id" integer NOT NULL PRIMARY KEY AUTOINCREMENT,
+                    "indexed_document_id" integer NOT NULL,
+                    "content" text NOT NULL,
+                    "highlight" text,
+                    "created_at" text NOT NULL,
+                    "updated_at" text NOT NULL,
+                    FOREIGN KEY(indexed_document_id) REFERENCES indexed_document(id)
+                );"#
+                .to_string(),
+            ))
+            .await?;
+
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                "CREATE INDEX IF NOT EXISTS `idx-document-note-doc-id` ON `document_note` (`indexed_document_id`);"
+                    .to_string(),
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,31 @@
+use entities::models::connection;
+use sea_orm_migration::prelude::*;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20221225_000001_add_connection_sync_cursor"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Page token of the last synced page, so syncs can resume after a restart.
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(connection::Entity)
+                    .add_column(ColumnDef::new(Alias::new("sync_cursor")).string())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
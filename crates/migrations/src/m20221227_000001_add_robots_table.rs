@@ -0,0 +1,46 @@
+use crate::sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::ConnectionTrait;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20221227_000001_add_robots_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            r#"CREATE TABLE IF NOT EXISTS "robots" (
+                "id" integer NOT NULL PRIMARY KEY AUTOINCREMENT,
+                "domain" text NOT NULL UNIQUE,
+                "rules" text NOT NULL,
+                "fetched_at" text NOT NULL,
+                "created_at" text NOT NULL,
+                "updated_at" text NOT NULL
+            );"#
+            .to_string(),
+        ))
+        .await?;
+
+        // Rules parsed from robots.txt files used to live in resource_rules &
+        // were never refreshed. Clear them out so they're refetched into the
+        // robots cache.
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            "DELETE FROM resource_rules".to_string(),
+        ))
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,45 @@
+use crate::sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::ConnectionTrait;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20221228_000001_add_domain_fetch_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            r#"CREATE TABLE IF NOT EXISTS "domain_fetch" (
+                "id" integer NOT NULL PRIMARY KEY AUTOINCREMENT,
+                "domain" text NOT NULL UNIQUE,
+                "last_fetched_at" text NOT NULL,
+                "next_fetch_at" text NOT NULL
+            );"#
+            .to_string(),
+        ))
+        .await?;
+
+        // Crawl-delay from robots.txt, in milliseconds
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Alias::new("robots"))
+                    .add_column(ColumnDef::new(Alias::new("crawl_delay_ms")).big_integer())
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+use crate::sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::ConnectionTrait;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20221231_000001_add_crawl_queue_indexes"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Match how the queue is read so large queues aren't scanned in full:
+        //  - (status, crawl_type): picking bootstrap tasks when dequeuing
+        //  - (domain, status): per-domain inflight counts & queue stats
+        //  - (status, updated_at): oldest tasks first, recrawls & pruning
+        let indexes = [
+            (
+                "idx-crawl-queue-status-crawl-type",
+                "`status`, `crawl_type`",
+            ),
+            ("idx-crawl-queue-domain-status", "`domain`, `status`"),
+            (
+                "idx-crawl-queue-status-updated-at",
+                "`status`, `updated_at`",
+            ),
+        ];
+
+        for (name, columns) in indexes {
+            manager
+                .get_connection()
+                .execute(Statement::from_string(
+                    manager.get_database_backend(),
+                    format!(
+                        "CREATE INDEX IF NOT EXISTS `{}` ON `crawl_queue` ({});",
+                        name, columns
+                    ),
+                ))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,57 @@
+use sea_orm_migration::prelude::*;
+use shared::config::Config;
+use tantivy::schema::*;
+
+use entities::schema::{mapping_to_schema, mapping_to_schema_with_facets, SchemaMapping};
+
+use crate::utils::search_schema::migrate_index_schema;
+
+pub struct Migration;
+
+impl Migration {
+    pub fn before_schema(&self) -> SchemaMapping {
+        vec![
+            ("id".into(), STRING | STORED | FAST),
+            ("domain".into(), STRING | STORED | FAST),
+            ("title".into(), TEXT | STORED | FAST),
+            ("description".into(), TEXT | STORED),
+            ("url".into(), STRING | STORED | FAST),
+            ("content".into(), TEXT | STORED),
+            ("fields".into(), STRING | STORED),
+            ("code".into(), TEXT | STORED),
+            ("parent_id".into(), STRING | STORED),
+            ("anchor".into(), STRING | STORED),
+            ("version_of".into(), STRING | STORED),
+            ("crawled_at".into(), STRING | STORED),
+            ("superseded_at".into(), STRING | STORED),
+            ("numbers".into(), STRING | STORED),
+        ]
+    }
+
+    pub fn after_schema(&self) -> Schema {
+        // Tags facet, used to count results by tag
+        mapping_to_schema_with_facets(&self.before_schema(), &["tags".into()])
+    }
+}
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20230101_000001_add_tags_to_search_schema"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        let config = Config::new();
+        migrate_index_schema(
+            &config.index_dir(),
+            mapping_to_schema(&self.before_schema()),
+            self.after_schema(),
+        )
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,39 @@
+use crate::sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::ConnectionTrait;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20230102_000001_add_domain_stats_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        let db = manager.get_connection();
+        db.execute(Statement::from_string(
+            manager.get_database_backend(),
+            r#"CREATE TABLE IF NOT EXISTS "domain_stats" (
+                "id" integer NOT NULL PRIMARY KEY AUTOINCREMENT,
+                "domain" text NOT NULL UNIQUE,
+                "num_fetches" integer NOT NULL,
+                "success_rate" real NOT NULL,
+                "avg_latency_ms" real NOT NULL,
+                "last_error" text,
+                "last_error_at" text,
+                "updated_at" text NOT NULL
+            );"#
+            .to_string(),
+        ))
+        .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
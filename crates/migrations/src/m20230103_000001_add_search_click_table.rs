@@ -0,0 +1,46 @@
+use crate::sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::ConnectionTrait;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20230103_000001_add_search_click_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                r#"CREATE TABLE IF NOT EXISTS "search_click" (
+                    "id" integer NOT NULL PRIMARY KEY AUTOINCREMENT,
+                    "indexed_document_id" integer NOT NULL,
+                    "clicked_at" text NOT NULL,
+                    FOREIGN KEY(indexed_document_id) REFERENCES indexed_document(id)
+                );"#
+                .to_string(),
+            ))
+            .await?;
+
+        // Clicks are loaded & pruned by age.
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                "CREATE INDEX IF NOT EXISTS `idx-search-click-clicked-at` ON `search_click` (`clicked_at`);"
+                    .to_string(),
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
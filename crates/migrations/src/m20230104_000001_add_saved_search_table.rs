@@ -0,0 +1,41 @@
+use crate::sea_orm::Statement;
+use sea_orm_migration::prelude::*;
+use sea_orm_migration::sea_orm::ConnectionTrait;
+
+pub struct Migration;
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20230104_000001_add_saved_search_table"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .get_connection()
+            .execute(Statement::from_string(
+                manager.get_database_backend(),
+                r#"CREATE TABLE IF NOT EXISTS "saved_search" (
+                    "id" integer NOT NULL PRIMARY KEY AUTOINCREMENT,
+                    "name" text NOT NULL UNIQUE,
+                    "query" text NOT NULL,
+                    "lenses" text NOT NULL,
+                    "new_matches" text NOT NULL,
+                    "last_checked_at" text NOT NULL,
+                    "last_matched_at" text,
+                    "created_at" text NOT NULL,
+                    "updated_at" text NOT NULL
+                );"#
+                .to_string(),
+            ))
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+use sea_orm_migration::prelude::*;
+use shared::config::Config;
+use tantivy::schema::*;
+
+use entities::schema::{
+    mapping_to_schema_with_facets, text_with_tokenizer, SchemaMapping, LANG_BACKFILL_MARKER,
+    TOKENIZER_CJK, TOKENIZER_DE, TOKENIZER_ES, TOKENIZER_FR,
+};
+
+use crate::utils::search_schema::migrate_index_schema;
+
+pub struct Migration;
+
+impl Migration {
+    pub fn before_schema(&self) -> SchemaMapping {
+        vec![
+            ("id".into(), STRING | STORED | FAST),
+            ("domain".into(), STRING | STORED | FAST),
+            ("title".into(), TEXT | STORED | FAST),
+            ("description".into(), TEXT | STORED),
+            ("url".into(), STRING | STORED | FAST),
+            ("content".into(), TEXT | STORED),
+            ("fields".into(), STRING | STORED),
+            ("code".into(), TEXT | STORED),
+            ("parent_id".into(), STRING | STORED),
+            ("anchor".into(), STRING | STORED),
+            ("version_of".into(), STRING | STORED),
+            ("crawled_at".into(), STRING | STORED),
+            ("superseded_at".into(), STRING | STORED),
+            ("numbers".into(), STRING | STORED),
+        ]
+    }
+
+    pub fn after_schema(&self) -> SchemaMapping {
+        let mut mapping = self.before_schema();
+        // Detected language & the language specific content fields
+        mapping.extend(vec![
+            ("lang".into(), STRING | STORED),
+            ("content_de".into(), text_with_tokenizer(TOKENIZER_DE)),
+            ("content_es".into(), text_with_tokenizer(TOKENIZER_ES)),
+            ("content_fr".into(), text_with_tokenizer(TOKENIZER_FR)),
+            ("content_cjk".into(), text_with_tokenizer(TOKENIZER_CJK)),
+        ]);
+        mapping
+    }
+}
+
+impl MigrationName for Migration {
+    fn name(&self) -> &str {
+        "m20230106_000001_add_lang_fields_to_search_schema"
+    }
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        let index_dir = Config::new().index_dir();
+        let tags = ["tags".into()];
+        migrate_index_schema(
+            &index_dir,
+            mapping_to_schema_with_facets(&self.before_schema(), &tags),
+            mapping_to_schema_with_facets(&self.after_schema(), &tags),
+        )?;
+
+        // The language fields are derived from the stored title & content, so
+        // existing documents are filled in by the backend once it's started.
+        if index_dir.join("meta.json").exists() {
+            std::fs::write(index_dir.join(LANG_BACKFILL_MARKER), "").map_err(|err| {
+                DbErr::Custom(format!("Unable to mark index for backfill: {}", err))
+            })?;
+        }
+
+        Ok(())
+    }
+
+    async fn down(&self, _: &SchemaManager) -> Result<(), DbErr> {
+        Ok(())
+    }
+}
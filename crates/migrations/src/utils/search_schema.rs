@@ -0,0 +1,112 @@
+use std::path::PathBuf;
+
+use entities::schema::{mapping_to_schema, SchemaMapping};
+use sea_orm_migration::prelude::DbErr;
+use tantivy::directory::MmapDirectory;
+use tantivy::schema::Schema;
+use tantivy::{DocAddress, Document, Index, IndexReader, ReloadPolicy};
+
+use super::migration_utils;
+
+/// Rebuilds the index @ `index_path` using the `after` schema. Stored values are
+/// carried over for any field that exists in both schemas, new fields are left
+/// empty until the document is recrawled.
+pub fn migrate_index(
+    index_path: &PathBuf,
+    before: &SchemaMapping,
+    after: &SchemaMapping,
+) -> Result<(), DbErr> {
+    migrate_index_schema(
+        index_path,
+        mapping_to_schema(before),
+        mapping_to_schema(after),
+    )
+}
+
+/// Same as `migrate_index`, for schemas that aren't only text fields.
+pub fn migrate_index_schema(
+    index_path: &PathBuf,
+    old_schema: Schema,
+    new_schema: Schema,
+) -> Result<(), DbErr> {
+    // Nothing indexed yet, the index will be created w/ the new schema.
+    if !index_path.join("meta.json").exists() {
+        return Ok(());
+    }
+
+    let dir = MmapDirectory::open(index_path)
+        .map_err(|err| DbErr::Custom(format!("Unable to open index: {}", err)))?;
+    let old_index = match Index::open_or_create(dir, old_schema.clone()) {
+        Ok(index) => index,
+        Err(err) => {
+            // Potentially already migrated?
+            println!("Error opening index: {}", err);
+            return Ok(());
+        }
+    };
+
+    let reader: IndexReader = old_index
+        .reader_builder()
+        .reload_policy(ReloadPolicy::Manual)
+        .try_into()
+        .map_err(|err| DbErr::Custom(format!("Unable to read index: {}", err)))?;
+    let searcher = reader.searcher();
+
+    let new_index_path = index_path
+        .parent()
+        .expect("Expected parent path")
+        .join("migrated_index");
+    if let Err(e) = std::fs::create_dir_all(&new_index_path) {
+        return Err(DbErr::Custom(format!("Can't create new index: {}", e)));
+    }
+
+    let new_dir = MmapDirectory::open(&new_index_path)
+        .map_err(|err| DbErr::Custom(format!("Unable to open new index: {}", err)))?;
+    let new_index = Index::open_or_create(new_dir, new_schema.clone())
+        .map_err(|err| DbErr::Custom(format!("Unable to create new index: {}", err)))?;
+    let mut writer = new_index
+        .writer(50_000_000)
+        .map_err(|err| DbErr::Custom(format!("Unable to create writer: {}", err)))?;
+
+    for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
+        for doc_id in segment_reader.doc_ids_alive() {
+            let old_doc = searcher
+                .doc(DocAddress::new(segment_ord as u32, doc_id))
+                .map_err(|err| DbErr::Custom(format!("Unable to read doc: {}", err)))?;
+
+            let mut new_doc = Document::default();
+            for field_value in old_doc.field_values() {
+                let name = old_schema.get_field_name(field_value.field());
+                if let Some(new_field) = new_schema.get_field(name) {
+                    new_doc.add_field_value(new_field, field_value.value().clone());
+                }
+            }
+
+            if let Err(e) = writer.add_document(new_doc) {
+                return Err(DbErr::Custom(format!("Unable to migrate doc: {}", e)));
+            }
+        }
+    }
+
+    if let Err(e) = writer.commit() {
+        return Err(DbErr::Custom(format!("Unable to commit changes: {}", e)));
+    }
+
+    // Release any file handles before moving things around.
+    drop(writer);
+    drop(searcher);
+    drop(reader);
+
+    if let Err(e) = migration_utils::backup_dir(index_path) {
+        return Err(DbErr::Custom(format!("Unable to backup old index: {}", e)));
+    }
+
+    if let Err(e) = migration_utils::replace_dir(&new_index_path, index_path) {
+        return Err(DbErr::Custom(format!(
+            "Unable to move new index into place: {}",
+            e
+        )));
+    }
+
+    Ok(())
+}
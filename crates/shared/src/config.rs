@@ -1,14 +1,18 @@
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
 
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
-pub use spyglass_lens::{LensConfig, LensRule, PipelineConfiguration};
+pub use spyglass_lens::{
+    ExtractRule, LensConfig, LensRule, PipelineConfiguration, QualityFilter, UrlNormalization,
+};
+use strum_macros::{AsRefStr, EnumString};
 
 use crate::{
     form::{FormType, SettingOpts},
-    plugin::PluginConfig,
+    plugin::{PluginConfig, PluginPermissions},
 };
 
 pub const MAX_TOTAL_INFLIGHT: u32 = 100;
@@ -48,6 +52,277 @@ impl Limit {
     }
 }
 
+/// Where archived page content is stored.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum ArchiveStorage {
+    /// Stored in the `archive` folder inside the data directory.
+    Local,
+    /// Any S3-compatible object store (AWS, Minio, R2, etc.)
+    S3 {
+        endpoint: String,
+        bucket: String,
+        region: String,
+        access_key: String,
+        secret_key: String,
+    },
+    /// WebDAV server, e.g. Nextcloud. `url` should point at the folder to use.
+    WebDav {
+        url: String,
+        username: Option<String>,
+        password: Option<String>,
+    },
+}
+
+impl Default for ArchiveStorage {
+    fn default() -> Self {
+        Self::Local
+    }
+}
+
+/// Opens documents whose URL matches `pattern` (a regex) in a native app, using
+/// `template` to build the app URI. Capture groups can be referenced w/ `$1`,
+/// `$name`, etc., e.g.
+///  - pattern: "^file://(/.*\\.rs)$", template: "vscode://file$1"
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct DeepLinkRule {
+    pub pattern: String,
+    pub template: String,
+}
+
+/// What a content rule looks for in a document before it's indexed.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum ContentMatcher {
+    /// Well-known API key/token formats (AWS, GitHub, Slack, Stripe, etc.) &
+    /// private key blocks.
+    ApiKeys,
+    /// Credentials found by known token formats & `password = ...` style
+    /// assignments.
+    Secrets,
+    /// Same as `Secrets`, also flagging random looking (high entropy) strings
+    /// to catch keys in unknown formats. Not on by default since base64 data,
+    /// hashes & the like look random too.
+    HighEntropySecrets,
+    /// US social security numbers
+    Ssn,
+    /// Any user defined regex.
+    Regex(String),
+}
+
+/// What to do w/ a document when a content rule matches.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum ContentAction {
+    /// Don't index the document at all.
+    Skip,
+    /// Index the document w/ the matches replaced.
+    Redact,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ContentRule {
+    pub matcher: ContentMatcher,
+    pub action: ContentAction,
+}
+
+/// Ranks sources (domains) the user hasn't opened anything from in a while
+/// lower than ones they're actively using. A source's boost halves every
+/// `half_life_days` since a document from it was last opened, or since it was
+/// first indexed if nothing has been opened.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct UsageDecay {
+    pub enabled: bool,
+    pub half_life_days: u32,
+    /// Lowest boost a source can decay to.
+    pub min_boost: f32,
+}
+
+impl Default for UsageDecay {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            half_life_days: 90,
+            min_boost: 0.5,
+        }
+    }
+}
+
+/// Merges the small index segments left behind by lots of little commits
+/// while nothing is being crawled, so searches stay fast on long running
+/// installs.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct IndexMaintenance {
+    pub enabled: bool,
+    /// How long nothing has to be crawled before merging.
+    pub idle_minutes: u32,
+    /// Segments w/ fewer documents than this are merged together.
+    pub small_segment_docs: u32,
+}
+
+impl Default for IndexMaintenance {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            idle_minutes: 10,
+            small_segment_docs: 10_000,
+        }
+    }
+}
+
+/// Language & conventions used to read dates in searches, e.g. the
+/// `after:"letzten Dienstag"` filter or whether `03/04/2024` is in March or
+/// April. English words are understood in every locale.
+#[derive(
+    AsRefStr, Clone, Copy, Debug, Default, Deserialize, EnumString, Serialize, PartialEq, Eq,
+)]
+#[serde(rename_all = "kebab-case")]
+#[strum(serialize_all = "kebab-case", ascii_case_insensitive)]
+pub enum DateLocale {
+    /// US English, month/day/year w/ weeks starting on Sunday.
+    #[default]
+    En,
+    /// British English, day/month/year.
+    EnGb,
+    De,
+    Fr,
+    Es,
+}
+
+/// HTTP endpoint that bookmarklets & mobile share sheets can send pages to.
+/// Requests need the token in the `share_token` file of the data directory.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ShareEndpoint {
+    pub port: u16,
+    /// Listen on all interfaces so phones on the same network can reach it,
+    /// rather than only this machine.
+    #[serde(default)]
+    pub allow_remote: bool,
+}
+
+/// Read-only search page for sharing a curated index (e.g. team docs) w/ a
+/// household or team. Only the listed lenses are searched & nothing else, such
+/// as settings, notes or crawling, is exposed. While it's on, the JSON-RPC API
+/// & share endpoint aren't started, so the desktop app can't connect.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct SearchPortal {
+    /// Interface to listen on, e.g. `127.0.0.1`, a LAN address like
+    /// `192.168.1.10` or `0.0.0.0` for all of them.
+    pub address: IpAddr,
+    pub port: u16,
+    /// Names of the lenses anyone who can reach the portal can search. Leave
+    /// empty to only allow `users`.
+    #[serde(default)]
+    pub lenses: Vec<String>,
+    /// Users who sign in w/ their own token to search other lenses.
+    #[serde(default)]
+    pub users: Vec<ApiUser>,
+}
+
+/// Someone allowed to search a shared install, limited to a set of lenses,
+/// e.g. work lenses for a colleague & only public ones for everyone else. Each
+/// user's token is in the `api_tokens` folder of the data directory, in a file
+/// named after them.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ApiUser {
+    /// Letters, numbers, `-` & `_` only since it's used as a file name.
+    pub name: String,
+    /// Names of the lenses they can search.
+    pub lenses: Vec<String>,
+}
+
+/// How often documents from each kind of source should be refreshed. Used to
+/// report how stale the index is.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct RecrawlTargets {
+    pub lens_days: u32,
+    pub connection_days: u32,
+}
+
+impl Default for RecrawlTargets {
+    fn default() -> Self {
+        Self {
+            lens_days: 30,
+            connection_days: 1,
+        }
+    }
+}
+
+/// Daily API budget for a connection. Syncing & fetching pause once usage
+/// nears either limit & resume the next day (UTC).
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ConnectionQuota {
+    pub requests_per_day: Option<u64>,
+    pub bytes_per_day: Option<u64>,
+}
+
+impl ConnectionQuota {
+    /// Pause once this much of a budget has been used, leaving headroom for
+    /// requests already in flight.
+    pub const PAUSE_AT: f64 = 0.9;
+
+    pub fn default_quotas() -> HashMap<String, ConnectionQuota> {
+        HashMap::from([
+            (
+                "calendar.google.com".to_string(),
+                ConnectionQuota {
+                    requests_per_day: Some(100_000),
+                    bytes_per_day: None,
+                },
+            ),
+            (
+                "drive.google.com".to_string(),
+                ConnectionQuota {
+                    requests_per_day: Some(100_000),
+                    bytes_per_day: Some(10 * 1024 * 1024 * 1024),
+                },
+            ),
+        ])
+    }
+
+    /// Whether `num_requests` & `num_bytes` used today are close enough to
+    /// the budget to pause.
+    pub fn is_exhausted(&self, num_requests: u64, num_bytes: u64) -> bool {
+        let near_limit = |used: u64, limit: Option<u64>| {
+            limit.map_or(false, |limit| used as f64 >= limit as f64 * Self::PAUSE_AT)
+        };
+
+        near_limit(num_requests, self.requests_per_day) || near_limit(num_bytes, self.bytes_per_day)
+    }
+}
+
+/// Another app's local data or export to pull documents from.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum ImportSource {
+    /// Apple Notes' local database on macOS. `path` overrides the default
+    /// `NoteStore.sqlite` location.
+    AppleNotes { path: Option<PathBuf> },
+    /// Highlights & notes from Apple Books on macOS. `path` overrides the
+    /// default location of its `Documents` folder.
+    AppleBooks { path: Option<PathBuf> },
+    /// Highlights & notes from a Kindle's `My Clippings.txt`
+    Kindle { path: PathBuf },
+    /// Folder of OneNote notebooks exported as single file web pages (`.mht`)
+    /// or Word documents, laid out as `<notebook>/<section>/<page>`.
+    OneNote { path: PathBuf },
+    /// Podcasts subscribed to in an OPML export. `transcripts` also indexes
+    /// episode transcripts for feeds that publish them.
+    Podcasts {
+        path: PathBuf,
+        #[serde(default)]
+        transcripts: bool,
+    },
+    /// Telegram Desktop export (`result.json`), w/ one or more chats.
+    Telegram { path: PathBuf },
+    /// WhatsApp chat export (`.txt`), or a folder of them.
+    WhatsApp { path: PathBuf },
+    /// YouTube watch history from a Google Takeout export
+    /// (`watch-history.json`). `transcripts` also indexes each video's
+    /// captions.
+    YouTube {
+        path: PathBuf,
+        #[serde(default)]
+        transcripts: bool,
+    },
+}
+
 pub type PluginSettings = HashMap<String, HashMap<String, String>>;
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct UserSettings {
@@ -58,6 +333,10 @@ pub struct UserSettings {
     pub inflight_crawl_limit: Limit,
     /// Number of in-flight crawls allowed per domain.
     pub inflight_domain_limit: Limit,
+    /// Minimum time (in ms) between fetches from the same domain. A longer
+    /// crawl-delay in the site's robots.txt takes precedence.
+    #[serde(default = "UserSettings::default_domain_crawl_delay_ms")]
+    pub domain_crawl_delay_ms: u64,
     /// Have we run the wizard?
     pub run_wizard: bool,
     /// Domains explicitly allowed, regardless of what's in the blocklist.
@@ -73,6 +352,10 @@ pub struct UserSettings {
     /// Should we crawl links that don't match our lens rules?
     #[serde(default)]
     pub crawl_external_links: bool,
+    /// Should we follow links marked `rel="nofollow"`? They're usually logins,
+    /// ads & user submitted links.
+    #[serde(default)]
+    pub follow_nofollow_links: bool,
     /// Should we disable telemetry
     #[serde(default)]
     pub disable_telemetry: bool,
@@ -83,6 +366,105 @@ pub struct UserSettings {
     pub disable_autolaunch: bool,
     #[serde(default = "UserSettings::default_port")]
     pub port: u16,
+    /// Where to store archived content.
+    #[serde(default)]
+    pub archive_storage: ArchiveStorage,
+    /// Max size (in MB) of the local cache used when archiving to remote storage.
+    #[serde(default = "UserSettings::default_archive_cache_size")]
+    pub archive_cache_size: u64,
+    /// Custom rules for opening results in native apps, checked before the
+    /// built-in ones.
+    #[serde(default)]
+    pub deep_links: Vec<DeepLinkRule>,
+    /// Rules checked against document content before it's indexed, used to
+    /// keep secrets & personal info out of the index.
+    #[serde(default = "UserSettings::default_content_rules")]
+    pub content_rules: Vec<ContentRule>,
+    /// How results from sources the user no longer opens are demoted.
+    #[serde(default)]
+    pub usage_decay: UsageDecay,
+    /// Number of past versions kept per URL when a page changes, searchable
+    /// w/ `as_of:` queries.
+    #[serde(default = "UserSettings::default_max_document_versions")]
+    pub max_document_versions: usize,
+    /// Label photos w/ what's in them using a local vision model, so they can
+    /// be found by their contents. Off by default since it's CPU heavy.
+    #[serde(default)]
+    pub image_captioning: bool,
+    /// Watch the screenshots folder & OCR new screenshots as they're taken so
+    /// they can be found by the text in them. Requires tesseract.
+    #[serde(default)]
+    pub screenshot_ocr: bool,
+    /// Folder to watch for screenshots instead of the OS default.
+    #[serde(default)]
+    pub screenshots_dir: Option<PathBuf>,
+    /// Notes, chats, etc. imported from other apps.
+    #[serde(default)]
+    pub imports: Vec<ImportSource>,
+    /// Files & folders that are never indexed, on top of the built-in list of
+    /// password manager vaults & credential stores. Supports `*` wildcards.
+    #[serde(default)]
+    pub index_denylist: Vec<String>,
+    /// Lock search & hide the index after this many minutes w/o a search, for
+    /// shared machines.
+    #[serde(default)]
+    pub privacy_lock_mins: Option<u64>,
+    /// Command that unlocks search when it exits successfully, e.g. an OS
+    /// biometric prompt. Program first, then its arguments.
+    #[serde(default)]
+    pub privacy_unlock_command: Vec<String>,
+    #[serde(default)]
+    pub recrawl_targets: RecrawlTargets,
+    /// OTLP (gRPC) collector to export crawl/parse/index spans to, e.g.
+    /// `http://localhost:4317` for a local Jaeger.
+    #[serde(default)]
+    pub otlp_endpoint: Option<String>,
+    /// Daily API budgets, by connection id (e.g. `drive.google.com`).
+    #[serde(default = "ConnectionQuota::default_quotas")]
+    pub connection_quotas: HashMap<String, ConnectionQuota>,
+    /// Drop documents older than this many days, by connection id, e.g.
+    /// only keep the last 2 years of calendar events.
+    #[serde(default)]
+    pub connection_retention_days: HashMap<String, u32>,
+    /// Connections each plugin may request access tokens for, by plugin name.
+    #[serde(default)]
+    pub plugin_connection_grants: HashMap<String, Vec<String>>,
+    /// Domains each plugin may make HTTP requests to, by plugin name. Only
+    /// domains the plugin also lists in its manifest are allowed.
+    #[serde(default)]
+    pub plugin_domain_grants: HashMap<String, Vec<String>>,
+    /// Permissions approved for each plugin, by plugin name. Only the ones a
+    /// plugin also declares in its manifest are used.
+    #[serde(default = "UserSettings::default_plugin_permission_grants")]
+    pub plugin_permission_grants: HashMap<String, PluginPermissions>,
+    /// How many times failed crawls are retried, by error type (e.g. `"Fetch"`,
+    /// `"Parse"`), overriding the defaults. 0 never retries.
+    #[serde(default)]
+    pub max_retries: HashMap<String, u8>,
+    /// Remove completed web crawls from the crawl queue after this many days
+    /// to keep the database small. Indexed documents are kept, but pages a
+    /// lens refreshes less often than this won't be refetched. Kept forever
+    /// when not set.
+    #[serde(default)]
+    pub completed_task_retention_days: Option<u32>,
+    /// Typos tolerated per search term, e.g. so "kubernets" still finds
+    /// "kubernetes". Short words always match exactly & 0 turns it off.
+    #[serde(default = "UserSettings::default_fuzzy_distance")]
+    pub fuzzy_distance: u8,
+    /// Accept pages shared from bookmarklets & share sheets, off when not set.
+    #[serde(default)]
+    pub share_endpoint: Option<ShareEndpoint>,
+    /// Serve a read-only search page for some lenses, off when not set.
+    #[serde(default)]
+    pub search_portal: Option<SearchPortal>,
+    #[serde(default)]
+    pub index_maintenance: IndexMaintenance,
+    #[serde(default)]
+    pub date_locale: DateLocale,
+    /// Stop keeping track of which results are opened & ranking the ones
+    /// opened most often (& recently) higher.
+    #[serde(default)]
+    pub disable_personalization: bool,
 }
 
 impl UserSettings {
@@ -94,10 +476,85 @@ impl UserSettings {
         "CmdOrCtrl+Shift+/".to_string()
     }
 
+    pub fn default_domain_crawl_delay_ms() -> u64 {
+        1_000
+    }
+
     pub fn default_port() -> u16 {
         4664
     }
 
+    pub fn default_archive_cache_size() -> u64 {
+        512
+    }
+
+    pub fn default_max_document_versions() -> usize {
+        10
+    }
+
+    pub fn default_fuzzy_distance() -> u8 {
+        1
+    }
+
+    /// Bundled plugins are approved for what their manifests ask for.
+    pub fn default_plugin_permission_grants() -> HashMap<String, PluginPermissions> {
+        let filesystem = |paths: &[&str]| paths.iter().map(|path| path.to_string()).collect();
+
+        HashMap::from([
+            (
+                "chrome-importer".to_string(),
+                PluginPermissions {
+                    filesystem: filesystem(&[
+                        "$CHROME_DATA_FOLDER",
+                        "$BASE_CONFIG_DIR/google-chrome",
+                        "$BASE_DATA_DIR/Google/Chrome",
+                    ]),
+                    enqueue: true,
+                    ..Default::default()
+                },
+            ),
+            (
+                "firefox-importer".to_string(),
+                PluginPermissions {
+                    filesystem: filesystem(&[
+                        "$FIREFOX_DATA_FOLDER",
+                        "$HOME_DIR/.mozilla/firefox",
+                        "$HOME_DIR/Library/Application Support/Firefox",
+                        "$BASE_DATA_DIR/Mozilla/Firefox",
+                    ]),
+                    enqueue: true,
+                    ..Default::default()
+                },
+            ),
+            (
+                "local-file-importer".to_string(),
+                PluginPermissions {
+                    filesystem: filesystem(&["$FOLDERS_LIST"]),
+                    enqueue: true,
+                    delete_docs: true,
+                    ..Default::default()
+                },
+            ),
+        ])
+    }
+
+    pub fn default_content_rules() -> Vec<ContentRule> {
+        vec![
+            ContentRule {
+                matcher: ContentMatcher::ApiKeys,
+                action: ContentAction::Redact,
+            },
+            ContentRule {
+                matcher: ContentMatcher::Ssn,
+                action: ContentAction::Redact,
+            },
+            ContentRule {
+                matcher: ContentMatcher::Secrets,
+                action: ContentAction::Redact,
+            },
+        ]
+    }
+
     pub fn constraint_limits(&mut self) {
         // Make sure crawler limits are reasonable
         match self.inflight_crawl_limit {
@@ -138,6 +595,24 @@ impl From<UserSettings> for Vec<(String, SettingOpts)> {
                 form_type: FormType::Bool,
                 help_text: Some("Stop sending data to any 3rd-party service. See https://spyglass.fyi/telemetry for more info.".into())
             }),
+            ("_.disable_personalization".into(), SettingOpts {
+                label: "Disable Personalized Ranking".into(),
+                value: serde_json::to_string(&settings.disable_personalization).expect("Unable to ser disable_personalization value"),
+                form_type: FormType::Bool,
+                help_text: Some("Stop ranking the results you open most often higher. Opened results are no longer tracked.".into())
+            }),
+            ("_.image_captioning".into(), SettingOpts {
+                label: "Image Captioning".into(),
+                value: serde_json::to_string(&settings.image_captioning).expect("Unable to ser image_captioning value"),
+                form_type: FormType::Bool,
+                help_text: Some("Label photos with what's in them using a local vision model so they can be found by their contents. Requires the captioning model in the models folder of your data directory & a restart.".into())
+            }),
+            ("_.screenshot_ocr".into(), SettingOpts {
+                label: "Screenshot OCR".into(),
+                value: serde_json::to_string(&settings.screenshot_ocr).expect("Unable to ser screenshot_ocr value"),
+                form_type: FormType::Bool,
+                help_text: Some("Read the text in new screenshots as they're taken so they can be searched. Requires tesseract to be installed & a restart.".into())
+            }),
             ("_.port".into(), SettingOpts {
                 label: "Spyglass Daemon Port".into(),
                 value: settings.port.to_string(),
@@ -174,6 +649,42 @@ impl From<UserSettings> for Vec<(String, SettingOpts)> {
             ));
         }
 
+        config.push((
+            "_.domain_crawl_delay_ms".into(),
+            SettingOpts {
+                label: "Delay between requests to a site (ms)".into(),
+                value: settings.domain_crawl_delay_ms.to_string(),
+                form_type: FormType::Number,
+                help_text: Some(
+                    "Minimum time between requests to the same site. Sites can ask for a longer delay in their robots.txt.".into(),
+                ),
+            },
+        ));
+
+        config.push((
+            "_.fuzzy_distance".into(),
+            SettingOpts {
+                label: "Typo tolerance".into(),
+                value: settings.fuzzy_distance.to_string(),
+                form_type: FormType::Number,
+                help_text: Some(
+                    "Number of typos allowed per search word (0-2), so misspelled searches still find results. Set to 0 to only match exact words.".into(),
+                ),
+            },
+        ));
+
+        config.push((
+            "_.date_locale".into(),
+            SettingOpts {
+                label: "Date language".into(),
+                value: settings.date_locale.as_ref().to_string(),
+                form_type: FormType::Text,
+                help_text: Some(
+                    "Language used to read dates in searches like after:\"last tuesday\". One of en, en-gb, de, fr or es.".into(),
+                ),
+            },
+        ));
+
         config
     }
 }
@@ -187,6 +698,7 @@ impl Default for UserSettings {
             inflight_crawl_limit: Limit::Finite(10),
             // Limit to 2 crawlers for a domain
             inflight_domain_limit: Limit::Finite(2),
+            domain_crawl_delay_ms: UserSettings::default_domain_crawl_delay_ms(),
             run_wizard: false,
             allow_list: Vec::new(),
             block_list: vec!["web.archive.org".to_string()],
@@ -195,10 +707,39 @@ impl Default for UserSettings {
             // Where to store the metadata & index
             data_directory: UserSettings::default_data_dir(),
             crawl_external_links: false,
+            follow_nofollow_links: false,
             disable_telemetry: false,
             plugin_settings: Default::default(),
             disable_autolaunch: false,
             port: UserSettings::default_port(),
+            archive_storage: ArchiveStorage::default(),
+            archive_cache_size: UserSettings::default_archive_cache_size(),
+            deep_links: Vec::new(),
+            content_rules: UserSettings::default_content_rules(),
+            usage_decay: UsageDecay::default(),
+            max_document_versions: UserSettings::default_max_document_versions(),
+            image_captioning: false,
+            screenshot_ocr: false,
+            screenshots_dir: None,
+            imports: Vec::new(),
+            index_denylist: Vec::new(),
+            privacy_lock_mins: None,
+            privacy_unlock_command: Vec::new(),
+            recrawl_targets: RecrawlTargets::default(),
+            otlp_endpoint: None,
+            connection_quotas: ConnectionQuota::default_quotas(),
+            connection_retention_days: HashMap::new(),
+            plugin_connection_grants: HashMap::new(),
+            plugin_domain_grants: HashMap::new(),
+            plugin_permission_grants: UserSettings::default_plugin_permission_grants(),
+            max_retries: HashMap::new(),
+            completed_task_retention_days: None,
+            fuzzy_distance: UserSettings::default_fuzzy_distance(),
+            share_endpoint: None,
+            search_portal: None,
+            index_maintenance: IndexMaintenance::default(),
+            date_locale: DateLocale::default(),
+            disable_personalization: false,
         }
     }
 }
@@ -250,24 +791,26 @@ impl Config {
                 continue;
             }
 
-            // Load plugin settings
-            let plugin_config = path.join("manifest.ron");
-            if !plugin_config.exists() || !plugin_config.is_file() {
-                log::warn!("Invalid plugin manifest: {}", path.as_path().display());
-                continue;
+            if let Some(config) = Self::load_plugin_manifest(&path) {
+                settings.insert(config.name.clone(), config);
             }
+        }
 
-            if let Ok(file_contents) = std::fs::read_to_string(plugin_config) {
-                if let Ok(plugin_config) = ron::from_str::<PluginConfig>(&file_contents) {
-                    let mut config = plugin_config.clone();
-                    config.path = Some(path.join("main.wasm"));
+        settings
+    }
 
-                    settings.insert(plugin_config.name.clone(), config.clone());
-                }
-            }
+    /// Read the manifest of the plugin installed in `path`.
+    pub fn load_plugin_manifest(path: &Path) -> Option<PluginConfig> {
+        let plugin_config = path.join("manifest.ron");
+        if !plugin_config.exists() || !plugin_config.is_file() {
+            log::warn!("Invalid plugin manifest: {}", path.display());
+            return None;
         }
 
-        settings
+        let file_contents = std::fs::read_to_string(plugin_config).ok()?;
+        let mut config = ron::from_str::<PluginConfig>(&file_contents).ok()?;
+        config.path = Some(path.join("main.wasm"));
+        Some(config)
     }
 
     pub fn app_identifier() -> String {
@@ -296,6 +839,18 @@ impl Config {
         self.data_dir().join("index")
     }
 
+    /// Token JSON-RPC requests need as a bearer token. Created by the backend
+    /// the first time it starts & only readable by the user who owns it.
+    pub fn rpc_token_path(&self) -> PathBuf {
+        self.data_dir().join("rpc_token")
+    }
+
+    /// Archived content when using local storage, or the local cache when
+    /// archiving to remote storage.
+    pub fn archive_dir(&self) -> PathBuf {
+        self.data_dir().join("archive")
+    }
+
     pub fn logs_dir(&self) -> PathBuf {
         self.data_dir().join("logs")
     }
@@ -311,6 +866,12 @@ impl Config {
         Self::prefs_dir().join("settings.ron")
     }
 
+    /// User editable synonyms used to expand search queries, see
+    /// `search::synonyms` in the backend for the format.
+    pub fn synonyms_file() -> PathBuf {
+        Self::prefs_dir().join("synonyms.txt")
+    }
+
     pub fn plugins_dir(&self) -> PathBuf {
         self.data_dir().join("plugins")
     }
@@ -323,6 +884,11 @@ impl Config {
         self.data_dir().join("pipelines")
     }
 
+    /// Local ML models, e.g. the one used for image captioning.
+    pub fn models_dir(&self) -> PathBuf {
+        self.data_dir().join("models")
+    }
+
     pub fn new() -> Self {
         let prefs_dir = Config::prefs_dir();
         fs::create_dir_all(prefs_dir).expect("Unable to create config folder");
@@ -345,6 +911,9 @@ impl Config {
         let index_dir = config.index_dir();
         fs::create_dir_all(index_dir).expect("Unable to create index folder");
 
+        let archive_dir = config.archive_dir();
+        fs::create_dir_all(archive_dir).expect("Unable to create archive folder");
+
         let logs_dir = config.logs_dir();
         fs::create_dir_all(logs_dir).expect("Unable to create logs folder");
 
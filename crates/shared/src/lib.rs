@@ -2,6 +2,7 @@ pub mod config;
 pub mod constants;
 pub mod event;
 pub mod form;
+pub mod normalize;
 pub mod plugin;
 pub mod regex;
 pub mod request;
@@ -0,0 +1,204 @@
+use regex::RegexSet;
+use url::Url;
+
+use crate::config::{LensConfig, UrlNormalization};
+
+/// `url` w/ its `www.` added or removed.
+fn toggle_www(url: &Url) -> Option<Url> {
+    let host = url.host_str()?;
+    let toggled = match host.strip_prefix("www.") {
+        Some(bare) => bare.to_string(),
+        None => format!("www.{}", host),
+    };
+
+    let mut url = url.clone();
+    url.set_host(Some(&toggled)).ok()?;
+    Some(url)
+}
+
+fn collapse_slashes(path: &str) -> String {
+    let mut collapsed = String::with_capacity(path.len());
+    for ch in path.chars() {
+        if ch == '/' && collapsed.ends_with('/') {
+            continue;
+        }
+        collapsed.push(ch);
+    }
+
+    collapsed
+}
+
+struct LensNormalization {
+    allowed: RegexSet,
+    skipped: RegexSet,
+    domains: Vec<String>,
+    rules: UrlNormalization,
+}
+
+impl LensNormalization {
+    fn matches(&self, url: &Url) -> bool {
+        let matches = |url: &Url| {
+            let url = url.as_str();
+            self.allowed.is_match(url) && !self.skipped.is_match(url)
+        };
+
+        // The other form of the host is part of the lens too.
+        matches(url)
+            || (self.rules.ignore_www && toggle_www(url).map_or(false, |url| matches(&url)))
+    }
+
+    fn apply(&self, url: &mut Url) {
+        if self.rules.lowercase_host {
+            if let Some(host) = url.host_str().map(|host| host.to_lowercase()) {
+                let _ = url.set_host(Some(&host));
+            }
+        }
+
+        if self.rules.ignore_www {
+            if let Some(host) = url.host_str().map(|host| host.to_string()) {
+                let bare = host.strip_prefix("www.").unwrap_or(&host);
+                let with_www = format!("www.{}", bare);
+                // Use the form the lens lists, w/o `www.` if it lists both.
+                let preferred = if self.domains.contains(&with_www)
+                    && !self.domains.iter().any(|domain| domain == bare)
+                {
+                    with_www.as_str()
+                } else {
+                    bare
+                };
+
+                if preferred != host {
+                    let _ = url.set_host(Some(preferred));
+                }
+            }
+        }
+
+        if self.rules.collapse_slashes && url.path().contains("//") {
+            let path = collapse_slashes(url.path());
+            url.set_path(&path);
+        }
+
+        if self.rules.strip_trailing_slash && url.path().len() > 1 && url.path().ends_with('/') {
+            let path = url.path().trim_end_matches('/').to_string();
+            url.set_path(&path);
+        }
+    }
+}
+
+/// Applies the URL normalization rules of the lenses a URL belongs to.
+pub struct UrlNormalizer {
+    lenses: Vec<LensNormalization>,
+}
+
+impl UrlNormalizer {
+    pub fn new(lenses: &[LensConfig]) -> Self {
+        let lenses = lenses
+            .iter()
+            .filter(|lens| !lens.url_normalization.is_empty())
+            .filter_map(|lens| {
+                let filters = lens.into_regexes();
+                Some(LensNormalization {
+                    allowed: RegexSet::new(filters.allowed).ok()?,
+                    skipped: RegexSet::new(filters.skipped).ok()?,
+                    domains: lens.domains.clone(),
+                    rules: lens.url_normalization.clone(),
+                })
+            })
+            .collect();
+
+        UrlNormalizer { lenses }
+    }
+
+    /// No lens asks for its URLs to be normalized.
+    pub fn is_empty(&self) -> bool {
+        self.lenses.is_empty()
+    }
+
+    pub fn normalize_url(&self, url: &mut Url) {
+        for lens in &self.lenses {
+            if lens.matches(url) {
+                lens.apply(url);
+            }
+        }
+    }
+
+    /// `url` normalized, or left as is if it can't be parsed.
+    pub fn normalize(&self, url: &str) -> String {
+        if self.is_empty() {
+            return url.to_string();
+        }
+
+        match Url::parse(url) {
+            Ok(mut parsed) => {
+                self.normalize_url(&mut parsed);
+                parsed.to_string()
+            }
+            Err(_) => url.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::UrlNormalizer;
+    use crate::config::{LensConfig, UrlNormalization};
+
+    fn lens(domain: &str, rules: UrlNormalization) -> LensConfig {
+        LensConfig {
+            name: domain.to_string(),
+            domains: vec![domain.to_string()],
+            url_normalization: rules,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_normalize() {
+        let normalizer = UrlNormalizer::new(&[
+            lens(
+                "docs.rs",
+                UrlNormalization {
+                    strip_trailing_slash: true,
+                    collapse_slashes: true,
+                    ..Default::default()
+                },
+            ),
+            lens(
+                "www.example.com",
+                UrlNormalization {
+                    ignore_www: true,
+                    ..Default::default()
+                },
+            ),
+            lens(
+                "wiki.org",
+                UrlNormalization {
+                    ignore_www: true,
+                    ..Default::default()
+                },
+            ),
+        ]);
+
+        assert_eq!(
+            normalizer.normalize("https://docs.rs//tokio/latest/"),
+            "https://docs.rs/tokio/latest"
+        );
+        assert_eq!(normalizer.normalize("https://docs.rs/"), "https://docs.rs/");
+        // Uses the form of the domain the lens lists
+        assert_eq!(
+            normalizer.normalize("https://example.com/about"),
+            "https://www.example.com/about"
+        );
+        assert_eq!(
+            normalizer.normalize("https://www.wiki.org/Rust"),
+            "https://wiki.org/Rust"
+        );
+        // Other sites are left alone
+        assert_eq!(
+            normalizer.normalize("https://www.other.com/about/"),
+            "https://www.other.com/about/"
+        );
+
+        assert!(UrlNormalizer::new(&[lens("docs.rs", Default::default())]).is_empty());
+    }
+}
@@ -11,10 +11,82 @@ pub enum PluginType {
     /// - Enqueues URLs to the crawl queue.
     /// - Can register to handle specific protocols if not HTTP
     Lens,
+    /// Parses documents the crawler doesn't understand, e.g. org-mode notes.
+    /// - Lists the file extensions & MIME types it handles in its manifest.
+    /// - Matching files & pages are sent to its `parse_document` export.
+    Parser,
+}
+
+/// File extensions & MIME types a `Parser` plugin handles.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ParserFormats {
+    /// Extensions w/o the leading dot, e.g. `"org"`.
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// MIME types w/o parameters, e.g. `"application/onenote"`.
+    #[serde(default)]
+    pub mime_types: Vec<String>,
+}
+
+impl ParserFormats {
+    /// Whether documents w/ this `extension` or `mime_type` are handled,
+    /// ignoring case.
+    pub fn handles(&self, extension: Option<&str>, mime_type: Option<&str>) -> bool {
+        let has_extension = extension.map_or(false, |extension| {
+            self.extensions
+                .iter()
+                .any(|ext| ext.trim_start_matches('.').eq_ignore_ascii_case(extension))
+        });
+        let has_mime_type = mime_type.map_or(false, |mime_type| {
+            self.mime_types
+                .iter()
+                .any(|mime| mime.eq_ignore_ascii_case(mime_type))
+        });
+
+        has_extension || has_mime_type
+    }
 }
 
 pub type PluginUserSettings = HashMap<String, SettingOpts>;
 
+/// Host calls a plugin needs. Calls not covered by what the plugin declares in
+/// its manifest & the user approves are rejected.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PluginPermissions {
+    /// Folders the plugin may list, walk, watch & copy files from, subfolders
+    /// included. `$NAME` is replaced w/ the plugin setting or host folder of
+    /// the same name, e.g. `"$FOLDERS_LIST"` or `"$HOME_DIR/.mozilla"`.
+    #[serde(default)]
+    pub filesystem: Vec<String>,
+    /// Make HTTP requests, only to domains listed in `allowed_domains`.
+    #[serde(default)]
+    pub network: bool,
+    /// Add URLs to the crawl queue.
+    #[serde(default)]
+    pub enqueue: bool,
+    /// Remove documents from the index.
+    #[serde(default)]
+    pub delete_docs: bool,
+}
+
+impl PluginPermissions {
+    /// Permissions in both `self` & `other`, i.e. what a plugin declared &
+    /// the user approved.
+    pub fn intersect(&self, other: &PluginPermissions) -> PluginPermissions {
+        PluginPermissions {
+            filesystem: self
+                .filesystem
+                .iter()
+                .filter(|path| other.filesystem.contains(path))
+                .cloned()
+                .collect(),
+            network: self.network && other.network,
+            enqueue: self.enqueue && other.enqueue,
+            delete_docs: self.delete_docs && other.delete_docs,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct PluginConfig {
     pub name: String,
@@ -29,6 +101,21 @@ pub struct PluginConfig {
     pub user_settings: PluginUserSettings,
     #[serde(default)]
     pub is_enabled: bool,
+    /// Connections (by id, e.g. `drive.google.com`) this plugin wants access
+    /// tokens for & the scopes it needs. Tokens are only handed out once the
+    /// user grants the plugin access to the connection.
+    #[serde(default)]
+    pub connections: HashMap<String, Vec<String>>,
+    /// Domains this plugin wants to make HTTP requests to, subdomains
+    /// included. Requests only go through for domains the user approves.
+    #[serde(default)]
+    pub allowed_domains: Vec<String>,
+    /// Host calls this plugin needs, only used once approved by the user.
+    #[serde(default)]
+    pub permissions: PluginPermissions,
+    /// Documents this plugin parses, if it's a `Parser`.
+    #[serde(default)]
+    pub parser_formats: ParserFormats,
 }
 
 impl PluginConfig {
@@ -41,3 +128,23 @@ impl PluginConfig {
             .join("data")
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::ParserFormats;
+
+    #[test]
+    fn test_parser_formats() {
+        let formats = ParserFormats {
+            extensions: vec!["org".into(), ".one".into()],
+            mime_types: vec!["application/onenote".into()],
+        };
+
+        assert!(formats.handles(Some("org"), None));
+        assert!(formats.handles(Some("ONE"), None));
+        assert!(formats.handles(None, Some("Application/OneNote")));
+        assert!(formats.handles(Some("bin"), Some("application/onenote")));
+        assert!(!formats.handles(Some("md"), Some("text/markdown")));
+        assert!(!formats.handles(None, None));
+    }
+}
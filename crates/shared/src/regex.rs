@@ -24,6 +24,48 @@ pub fn regex_for_prefix(prefix: &str) -> String {
     format!("^{}.*", prefix)
 }
 
+/// Password manager vaults, keychains & browser credential stores. These are
+/// never indexed, matched (case-insensitively) against `/` separated paths.
+pub const CREDENTIAL_STORE_PATTERNS: &[&str] = &[
+    // Password manager vaults & exports
+    r"(?i)\.(kdbx|kdb|1pif|1pux|opvault|agilekeychain|psafe3)$",
+    r"(?i)/(1password|bitwarden|lastpass|dashlane|keepassxc|enpass|keeper)( [^/]*)?/",
+    r"(?i)/group containers/[^/]*\.com\.(agilebits|bitwarden)[^/]*/",
+    // OS keychains & keyrings
+    r"(?i)/library/keychains/",
+    r"(?i)\.keychain(-db)?$",
+    r"(?i)/\.local/share/keyrings/",
+    r"(?i)/microsoft/(credentials|protect)/",
+    // Browser saved logins & cookies
+    r"(?i)/(login data|login data for account|web data|cookies)(-journal)?$",
+    r"(?i)/(logins\.json|logins-backup\.json|key3\.db|key4\.db|signons\.sqlite|cookies\.sqlite)$",
+    // Keys & credentials used by dev tools
+    r"(?i)/\.(ssh|gnupg|password-store)/",
+    r"(?i)/\.aws/credentials$",
+    r"(?i)/\.(netrc|pgpass|git-credentials)$",
+];
+
+/// Convert a path w/ optional `*` wildcards into a regex matching that path &
+/// anything under it. A leading `~` is expanded to the home directory.
+pub fn regex_for_path(path: &str) -> String {
+    let mut path = path.replace('\\', "/");
+    if let Some(rest) = path.strip_prefix('~') {
+        if let Some(dirs) = directories::BaseDirs::new() {
+            path = format!("{}{}", dirs.home_dir().display(), rest).replace('\\', "/");
+        }
+    }
+
+    let mut regex = String::from("(?i)^");
+    for ch in path.trim_end_matches('/').chars() {
+        match ch {
+            '*' => regex.push_str("[^/]*"),
+            _ => regex.push_str(&regex::escape(&ch.to_string())),
+        }
+    }
+
+    format!("{}(/.*)?$", regex)
+}
+
 /// Convert a robots.txt rule into a proper regex string
 pub fn regex_for_robots(rule: &str, wildcard_type: WildcardType) -> Option<String> {
     if rule.is_empty() {
@@ -70,7 +112,7 @@ pub fn regex_for_robots(rule: &str, wildcard_type: WildcardType) -> Option<Strin
 
 #[cfg(test)]
 mod test {
-    use super::{regex_for_domain, regex_for_prefix};
+    use super::{regex_for_domain, regex_for_path, regex_for_prefix, CREDENTIAL_STORE_PATTERNS};
     use regex::Regex;
 
     #[test]
@@ -133,4 +175,42 @@ mod test {
             assert!(!regex.is_match(test));
         }
     }
+
+    #[test]
+    fn test_regex_for_path() {
+        let regex = Regex::new(&regex_for_path("/Users/alice/Secrets/*.txt")).unwrap();
+        assert!(regex.is_match("/Users/alice/Secrets/bank.txt"));
+        assert!(regex.is_match("/users/alice/secrets/bank.txt"));
+        assert!(!regex.is_match("/Users/alice/Secrets/old/bank.md"));
+
+        let regex = Regex::new(&regex_for_path("C:\\Users\\alice\\Vault\\")).unwrap();
+        assert!(regex.is_match("C:/Users/alice/Vault"));
+        assert!(regex.is_match("C:/Users/alice/Vault/taxes.pdf"));
+        assert!(!regex.is_match("C:/Users/alice/Vaulted/taxes.pdf"));
+    }
+
+    #[test]
+    fn test_credential_store_patterns() {
+        let patterns = regex::RegexSet::new(CREDENTIAL_STORE_PATTERNS).unwrap();
+        for denied in [
+            "/home/alice/Documents/Passwords.kdbx",
+            "/Users/alice/Library/Keychains/login.keychain-db",
+            "/Users/alice/Library/Application Support/Google/Chrome/Default/Login Data",
+            "/home/alice/.mozilla/firefox/abc.default/logins.json",
+            "/home/alice/.config/Bitwarden/data.json",
+            "C:/Users/alice/AppData/Local/1Password/data/1password.sqlite",
+            "/home/alice/.ssh/id_ed25519",
+            "/home/alice/.aws/credentials",
+        ] {
+            assert!(patterns.is_match(denied), "{} should be denied", denied);
+        }
+
+        for allowed in [
+            "/home/alice/Documents/passwords-policy.md",
+            "/home/alice/notes/ssh.md",
+            "/home/alice/projects/cookies/recipe.txt",
+        ] {
+            assert!(!patterns.is_match(allowed), "{} should be allowed", allowed);
+        }
+    }
 }
@@ -1,9 +1,79 @@
 use serde::{Deserialize, Serialize};
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CollectionParam {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+/// Part of a document's content to read, defaults to the start of it.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ContentRangeParam {
+    /// Anchor of a section to read instead of the whole document.
+    pub section: Option<String>,
+    /// Byte offsets into the document's (or section's) content.
+    pub start: Option<u64>,
+    pub end: Option<u64>,
+}
+
+/// URLs pushed into the crawl queue by an external tool or script.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct EnqueueParam {
+    pub urls: Vec<String>,
+    /// Tags added to each URL as (type, value) pairs, e.g. `("source", "rss")`.
+    #[serde(default)]
+    pub tags: Vec<(String, String)>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct NoteParam {
+    pub content: String,
+    /// Passage of the document the note refers to.
+    pub highlight: Option<String>,
+}
+
+/// How the query of a search is interpreted.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub enum SearchMode {
+    /// Tokenized search w/ filters, ranked by relevance.
+    #[default]
+    Standard,
+    /// Matches documents containing the query exactly, e.g. `Config::new(`.
+    Exact,
+    /// Matches documents against the query as a regular expression.
+    Regex,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct SearchParam {
+    /// Lens triggers applied to the query.
     pub lenses: Vec<String>,
     pub query: String,
+    /// Names of lenses to search within. Results come from any of the lenses,
+    /// e.g. to only search work related lenses.
+    #[serde(default)]
+    pub lens_names: Vec<String>,
+    /// Exact & regex searches scan stored content, so they're slower & only
+    /// look at a limited number of documents.
+    #[serde(default)]
+    pub mode: SearchMode,
+    /// Max length of result snippets in characters, 0 to leave them out.
+    #[serde(default)]
+    pub snippet_length: Option<usize>,
+    /// Page the user is searching from, e.g. the tab open in the browser.
+    /// Results from the same site are ranked higher.
+    #[serde(default)]
+    pub context_url: Option<String>,
+}
+
+/// A search to be notified about when new documents match it.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SavedSearchParam {
+    pub name: String,
+    pub query: String,
+    /// Lens names to limit the search to, searches everything if empty.
+    #[serde(default)]
+    pub lenses: Vec<String>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -18,6 +18,53 @@ impl QueueStatus {
 #[derive(Debug, Deserialize, Serialize)]
 pub struct AppStatus {
     pub num_docs: u64,
+    /// Search is locked until unlocked w/ the local token.
+    #[serde(default)]
+    pub is_locked: bool,
+    /// Crawling is paused, nothing is fetched until it's resumed.
+    #[serde(default)]
+    pub is_paused: bool,
+}
+
+/// Latency summary for one part of the benchmark, in milliseconds.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct BenchmarkTiming {
+    pub count: usize,
+    pub avg_ms: f64,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub max_ms: f64,
+}
+
+/// A backup of the search index & database, see `create_backup`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BackupResult {
+    pub path: String,
+    pub created_at: String,
+    pub num_docs: u64,
+}
+
+/// Segments in the index before & after optimizing it, see `optimize_index`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct OptimizeResult {
+    pub segments_before: usize,
+    pub segments_after: usize,
+}
+
+/// Results of a standardized benchmark run, meant to be attached to
+/// performance issues so numbers are comparable between machines.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BenchmarkResult {
+    pub version: String,
+    pub os: String,
+    pub arch: String,
+    pub num_cpus: usize,
+    pub num_docs: usize,
+    pub index_ms: f64,
+    pub docs_per_sec: f64,
+    pub commit_ms: f64,
+    pub query: BenchmarkTiming,
+    pub dequeue: BenchmarkTiming,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -31,6 +78,19 @@ pub struct SupportedConnection {
 pub struct UserConnection {
     pub id: String,
     pub account: String,
+    #[serde(default)]
+    pub quota: QuotaStatus,
+}
+
+/// API usage today (UTC) for a connection & its daily limits, if any.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct QuotaStatus {
+    pub num_requests: u64,
+    pub requests_per_day: Option<u64>,
+    pub num_bytes: u64,
+    pub bytes_per_day: Option<u64>,
+    /// Syncing & fetching are paused until this time (RFC 3339).
+    pub paused_until: Option<String>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -39,11 +99,102 @@ pub struct ListConnectionResult {
     pub user_connections: Vec<UserConnection>,
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct CollectionResult {
+    pub name: String,
+    pub description: Option<String>,
+    pub num_docs: u64,
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct CrawlStats {
     pub by_domain: Vec<(String, QueueStatus)>,
 }
 
+/// A crawl that failed & why, e.g. `TlsError`, `RobotsBlocked` or `IndexFull`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FailedCrawl {
+    pub url: String,
+    pub domain: String,
+    pub error_type: Option<String>,
+    pub error_msg: Option<String>,
+    pub num_retries: u8,
+    pub failed_at: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum CoverageStatus {
+    Indexed,
+    /// Waiting in the crawl queue.
+    Queued,
+    Failed,
+    /// Left out by lens rules, user settings or robots.txt.
+    Skipped,
+    /// Never found by the crawler.
+    Missing,
+}
+
+/// A sampled URL & why it isn't indexed.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct CoverageUrl {
+    pub url: String,
+    pub status: CoverageStatus,
+    /// e.g. `SkipRule`, `RobotsBlocked` or the crawl error type.
+    pub reason: Option<String>,
+}
+
+/// Coverage of one section of a site, e.g. `docs.rs/tokio/*`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct CoveragePattern {
+    pub pattern: String,
+    pub num_indexed: u32,
+    pub num_queued: u32,
+    pub num_failed: u32,
+    pub num_skipped: u32,
+    pub num_missing: u32,
+    /// A few of the URLs that aren't indexed.
+    pub examples: Vec<CoverageUrl>,
+}
+
+/// How much of the site(s) a lens covers, from a sample of their URLs.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LensCoverage {
+    pub lens: String,
+    /// URLs came from the lens sitemaps rather than what's been crawled.
+    pub from_sitemap: bool,
+    pub num_urls: u32,
+    pub num_sampled: u32,
+    pub num_indexed: u32,
+    /// Least covered patterns first.
+    pub patterns: Vec<CoveragePattern>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum FreshnessSource {
+    Lens,
+    Connection,
+}
+
+/// How stale the documents from a lens or connection are.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SourceFreshness {
+    pub name: String,
+    pub source: FreshnessSource,
+    pub num_docs: u64,
+    /// Documents not refreshed within the source's recrawl target.
+    pub num_stale: u64,
+    pub percent_stale: f32,
+    pub recrawl_target_days: u32,
+    pub oldest_updated_at: Option<String>,
+    pub avg_age_secs: u64,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct FreshnessReport {
+    /// Stalest sources first.
+    pub sources: Vec<SourceFreshness>,
+}
+
 #[derive(Clone, Deserialize, Serialize, PartialEq, Eq)]
 pub struct InstallableLens {
     pub author: String,
@@ -54,6 +205,19 @@ pub struct InstallableLens {
     pub html_url: String,
 }
 
+/// A plugin listed in the plugin registry.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct InstallablePlugin {
+    pub name: String,
+    pub author: String,
+    pub description: String,
+    pub version: String,
+    /// Gzip'd tarball w/ the plugin's `manifest.ron` & `main.wasm`.
+    pub download_url: String,
+    /// Hex encoded SHA-256 of the archive.
+    pub sha256: String,
+}
+
 #[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
 pub struct LensResult {
     pub author: String,
@@ -74,6 +238,187 @@ pub struct PluginResult {
     pub title: String,
     pub description: String,
     pub is_enabled: bool,
+    /// Long-running tasks the plugin has reported progress for.
+    #[serde(default)]
+    pub tasks: Vec<PluginTaskProgress>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PluginTaskProgress {
+    pub task: String,
+    /// 0-100
+    pub percent: u8,
+    pub message: Option<String>,
+    /// RFC 3339 timestamp
+    pub updated_at: String,
+}
+
+/// Where a plugin log line came from.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum PluginLogSource {
+    /// Logged w/ `spyglass_plugin::log`.
+    Log,
+    /// Printed to stdout outside of a host call.
+    Stdout,
+    Stderr,
+    /// Reported by the host, e.g. failed calls, denied permissions & timeouts.
+    Host,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PluginLogEntry {
+    /// RFC 3339 timestamp
+    pub timestamp: String,
+    pub source: PluginLogSource,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct NoteResult {
+    pub id: i64,
+    pub content: String,
+    pub highlight: Option<String>,
+}
+
+/// A crawl of a document whose content differed from the one before it.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct VersionResult {
+    /// RFC 3339 timestamps
+    pub crawled_at: String,
+    pub superseded_at: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Unchanged,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct DiffChange {
+    pub kind: ChangeKind,
+    pub text: String,
+}
+
+/// Changes to a document's content between two of its versions.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct VersionDiff {
+    pub doc_id: String,
+    /// Crawl timestamps of the versions that were compared.
+    pub from: String,
+    pub to: String,
+    pub changes: Vec<DiffChange>,
+}
+
+/// Response a document was last fetched from, to audit & debug crawls.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct FetchInfoResult {
+    pub url: String,
+    /// HTTP status of the final response.
+    pub status: u16,
+    /// URL the response came from, after following redirects.
+    pub final_url: String,
+    pub content_type: Option<String>,
+    pub duration_ms: u64,
+    /// RFC 3339 timestamp
+    pub fetched_at: String,
+}
+
+/// Whether a page, e.g. the one open in the browser, is in the index.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PageStatus {
+    pub url: String,
+    /// Set if the page has been indexed.
+    pub doc_id: Option<String>,
+    /// RFC 3339 timestamp of when the page was last indexed.
+    pub indexed_at: Option<String>,
+    /// Waiting to be crawled or being crawled right now.
+    pub is_queued: bool,
+}
+
+/// A page the user is watching for changes.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct WatchedPage {
+    pub url: String,
+    pub check_interval_mins: i64,
+    /// RFC 3339 timestamps
+    pub last_checked_at: Option<String>,
+    pub last_changed_at: Option<String>,
+    /// What changed the last time the page changed.
+    pub change_summary: Option<String>,
+}
+
+/// A section of a long document that can be read on its own.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct DocSection {
+    pub anchor: String,
+    pub heading: String,
+    pub num_bytes: u64,
+}
+
+/// Stored content of a document, or a part of it, for previews.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct DocContent {
+    pub doc_id: String,
+    pub title: String,
+    pub url: String,
+    /// Anchor of the section the content is from, if one was asked for.
+    pub section: Option<String>,
+    pub content: String,
+    /// Byte range of `content` within the whole, to page through long documents.
+    pub start: u64,
+    pub end: u64,
+    pub total_bytes: u64,
+    pub sections: Vec<DocSection>,
+}
+
+/// Parse output of a document that was just refetched & reindexed, to debug
+/// how it's indexed.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ReindexResult {
+    /// None if the document was kept out of the index.
+    pub doc_id: Option<String>,
+    /// Canonical URL the document was indexed under.
+    pub url: String,
+    /// `New`, `Updated` or `Ignore`
+    pub status: String,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    /// Indexed content, cut off for long documents.
+    pub content: String,
+    pub total_bytes: u64,
+    pub content_hash: Option<String>,
+    pub tags: Vec<(String, String)>,
+    pub fields: Vec<(String, String)>,
+    pub numbers: Vec<(String, f64)>,
+    pub sections: Vec<DocSection>,
+    pub num_code_blocks: u32,
+    /// Links found on the page, which are added to the crawl queue.
+    pub links: Vec<String>,
+    /// RFC 3339 timestamp
+    pub document_date: Option<String>,
+    /// Content rule that kept the document out of the index, if any.
+    pub skipped_by: Option<String>,
+    /// Content rules that redacted part of the document.
+    pub redacted: Vec<String>,
+}
+
+/// A search the user is notified about when new documents match it.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct SavedSearch {
+    pub name: String,
+    pub query: String,
+    pub lenses: Vec<String>,
+    /// RFC 3339 timestamp
+    pub last_matched_at: Option<String>,
+}
+
+/// Newly indexed documents that match a saved search.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SavedSearchAlert {
+    pub search: SavedSearch,
+    pub results: Vec<SearchResult>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -94,9 +439,47 @@ pub struct SearchResult {
     pub description: String,
     pub url: String,
     pub tags: Vec<(String, String)>,
+    /// Structured (name, value) fields extracted from the document.
+    #[serde(default)]
+    pub fields: Vec<(String, String)>,
+    /// Anchor of the section that best matched the query, for long documents.
+    #[serde(default)]
+    pub anchor: Option<String>,
+    /// URI that opens the document in its native app (VS Code, Obsidian, etc.)
+    #[serde(default)]
+    pub app_url: Option<String>,
+    /// Pinned to the top of the results for this query by the user.
+    #[serde(default)]
+    pub pinned: bool,
+    /// Notes the user attached to this document.
+    #[serde(default)]
+    pub notes: Vec<NoteResult>,
+    /// When the past version that matched an `as_of:` query was crawled.
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub snippet: Option<SearchSnippet>,
     pub score: f32,
 }
 
+/// Passage of a result that matched the query.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct SearchSnippet {
+    /// Field the passage is from, e.g. `content` or `title`.
+    pub field: String,
+    pub text: String,
+    /// Byte ranges of `text` that matched the query.
+    pub highlights: Vec<(usize, usize)>,
+}
+
+/// Number of results w/ a tag, used to narrow down a search.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct SearchFacet {
+    pub label: String,
+    pub value: String,
+    pub count: u64,
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct SearchResults {
     pub results: Vec<SearchResult>,
@@ -1,14 +1,16 @@
 use std::fmt;
 use std::path::PathBuf;
+use std::time::Duration;
 
 use blake2::{Blake2s256, Digest};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 
 pub mod pipeline;
 mod utils;
 
 pub use crate::pipeline::PipelineConfiguration;
-use utils::{regex_for_domain, regex_for_prefix, regex_for_robots};
+use utils::{parse_interval, regex_for_domain, regex_for_prefix, regex_for_robots};
 
 /// Different rules that filter out the URLs that would be crawled for a lens
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -47,6 +49,119 @@ impl LensRule {
     }
 }
 
+/// Pulls a structured field out of pages crawled by a lens.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum ExtractRule {
+    /// Text of the first element matching a CSS selector. Use `@attr` at the end
+    /// of the selector to grab an attribute instead, e.g.
+    ///  - Css("price", "span.price")
+    ///  - Css("published", "meta[itemprop=datePublished]@content")
+    Css(String, String),
+    /// Value at a dot-separated path inside the page's JSON-LD, e.g.
+    ///  - JsonPath("author", "author.name")
+    JsonPath(String, String),
+}
+
+impl ExtractRule {
+    /// Name of the field this rule populates.
+    pub fn field(&self) -> &str {
+        match self {
+            Self::Css(field, _) | Self::JsonPath(field, _) => field,
+        }
+    }
+}
+
+/// How a lens' URLs are normalized before they're crawled, indexed & shown,
+/// so variants of the same page aren't duplicated.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct UrlNormalization {
+    /// Lowercase the host. Web URL hosts are always lowercased, this covers
+    /// other schemes.
+    #[serde(default)]
+    pub lowercase_host: bool,
+    /// `/docs/` & `/docs` are the same page.
+    #[serde(default)]
+    pub strip_trailing_slash: bool,
+    /// `/docs//intro` & `/docs/intro` are the same page.
+    #[serde(default)]
+    pub collapse_slashes: bool,
+    /// `www.example.com` & `example.com` are the same site. URLs use the
+    /// form listed in the lens' domains.
+    #[serde(default)]
+    pub ignore_www: bool,
+}
+
+impl UrlNormalization {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Checks that keep junk & error pages out of the index. Links on these
+/// pages are still followed.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct QualityFilter {
+    /// Skip pages w/ fewer words of content than this.
+    #[serde(default)]
+    pub min_words: u32,
+    /// Skip pages where more than this share (0-1) of the text is navigation,
+    /// footers & other boilerplate around the main content.
+    #[serde(default)]
+    pub max_boilerplate_ratio: Option<f32>,
+    /// Fail "page not found" pages that are served as if they were fine.
+    #[serde(default = "QualityFilter::default_skip_soft_404")]
+    pub skip_soft_404: bool,
+    /// Fail pages that only ask to sign in before showing their content.
+    #[serde(default = "QualityFilter::default_skip_login_walls")]
+    pub skip_login_walls: bool,
+    /// Extra patterns (regexes) marking short pages as errors, matched
+    /// against their title & content, e.g. `"(?i)access denied"`.
+    #[serde(default)]
+    pub error_page_patterns: Vec<String>,
+}
+
+impl Default for QualityFilter {
+    fn default() -> Self {
+        Self {
+            min_words: 0,
+            max_boilerplate_ratio: None,
+            skip_soft_404: Self::default_skip_soft_404(),
+            skip_login_walls: Self::default_skip_login_walls(),
+            error_page_patterns: Vec::new(),
+        }
+    }
+}
+
+impl QualityFilter {
+    fn default_skip_soft_404() -> bool {
+        true
+    }
+
+    fn default_skip_login_walls() -> bool {
+        true
+    }
+
+    /// Only skip what both filters would, so a page wanted by one lens isn't
+    /// dropped because of another.
+    pub fn merge(&self, other: &QualityFilter) -> QualityFilter {
+        QualityFilter {
+            min_words: self.min_words.min(other.min_words),
+            max_boilerplate_ratio: self
+                .max_boilerplate_ratio
+                .zip(other.max_boilerplate_ratio)
+                .map(|(a, b)| a.max(b)),
+            skip_soft_404: self.skip_soft_404 && other.skip_soft_404,
+            skip_login_walls: self.skip_login_walls && other.skip_login_walls,
+            error_page_patterns: self
+                .error_page_patterns
+                .iter()
+                .filter(|pattern| other.error_page_patterns.contains(pattern))
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
 pub struct LensFilters {
     pub allowed: Vec<String>,
     pub skipped: Vec<String>,
@@ -69,8 +184,43 @@ pub struct LensConfig {
     pub rules: Vec<LensRule>,
     #[serde(default)]
     pub trigger: String,
+    /// Pipeline that processes URLs in this lens, e.g. `"browser-render"`.
+    /// URLs are crawled normally when not set.
     #[serde(default)]
     pub pipeline: Option<String>,
+    /// Structured fields to extract from documents in this lens.
+    #[serde(default)]
+    pub extract: Vec<ExtractRule>,
+    /// Index the entire page rather than only the main content. Useful for
+    /// sites where reader mode strips out content.
+    #[serde(default)]
+    pub disable_reader_mode: bool,
+    /// Never demote this lens' sources for not being opened recently, e.g. for
+    /// reference docs that are rarely needed but should always rank well.
+    #[serde(default)]
+    pub disable_usage_decay: bool,
+    /// Search for the words the user typed only, w/o also searching for their
+    /// synonyms. Useful for lenses w/ precise jargon, e.g. "go" the game.
+    #[serde(default)]
+    pub disable_synonyms: bool,
+    /// Index pages & follow their links even if their robots meta tag or
+    /// `X-Robots-Tag` header says not to, e.g. for an intranet the user owns.
+    #[serde(default)]
+    pub ignore_robots_meta: bool,
+    /// Rules for treating variants of a URL as the same page.
+    #[serde(default)]
+    pub url_normalization: UrlNormalization,
+    /// Heuristics for skipping junk pages, e.g. error pages served w/ a 200.
+    #[serde(default)]
+    pub quality: QualityFilter,
+    /// Sitemaps (or sitemap indexes) to pull URLs from, e.g.
+    /// `"https://docs.rs/sitemap.xml"`. Gzip'd sitemaps are supported.
+    #[serde(default)]
+    pub sitemaps: Vec<String>,
+    /// How often web pages in this lens are refetched, e.g. `"7d"`, `"12h"`
+    /// or `"30m"`. Pages are only crawled once when not set.
+    #[serde(default)]
+    pub refresh_interval: Option<String>,
     // Used internally & should not be serialized/deserialized
     #[serde(skip)]
     pub file_path: PathBuf,
@@ -109,6 +259,24 @@ impl LensConfig {
         LensFilters { allowed, skipped }
     }
 
+    /// Parsed `refresh_interval`, `None` if unset or invalid.
+    pub fn refresh_interval(&self) -> Option<Duration> {
+        self.refresh_interval.as_deref().and_then(parse_interval)
+    }
+
+    /// Whether `url` falls under the domains/urls/rules of this lens.
+    pub fn matches_url(&self, url: &str) -> bool {
+        let filters = self.into_regexes();
+        let is_match = |regexes: &Vec<String>| {
+            regexes
+                .iter()
+                .filter_map(|regex| Regex::new(regex).ok())
+                .any(|regex| regex.is_match(url))
+        };
+
+        is_match(&filters.allowed) && !is_match(&filters.skipped)
+    }
+
     pub fn from_string(contents: &str) -> anyhow::Result<Self> {
         let mut hasher = Blake2s256::new();
         hasher.update(contents);
@@ -137,6 +305,8 @@ impl LensConfig {
 
 #[cfg(test)]
 mod test {
+    use std::time::Duration;
+
     use crate::LensRule;
 
     use super::LensConfig;
@@ -161,6 +331,39 @@ mod test {
             .contains(&"^https://oldschool.runescape.wiki/w/.*".to_string()));
     }
 
+    #[test]
+    fn test_matches_url() {
+        let config = LensConfig {
+            domains: vec!["paulgraham.com".to_string()],
+            rules: vec![LensRule::SkipURL("https://paulgraham.com/rss*".to_string())],
+            ..Default::default()
+        };
+
+        assert!(config.matches_url("https://paulgraham.com/ds.html"));
+        assert!(!config.matches_url("https://paulgraham.com/rss.html"));
+        assert!(!config.matches_url("https://example.com/ds.html"));
+    }
+
+    #[test]
+    fn test_refresh_interval() {
+        let lens = |interval: &str| LensConfig {
+            refresh_interval: Some(interval.to_string()),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            lens("7d").refresh_interval(),
+            Some(Duration::from_secs(7 * 24 * 60 * 60))
+        );
+        assert_eq!(
+            lens(" 12h ").refresh_interval(),
+            Some(Duration::from_secs(12 * 60 * 60))
+        );
+        assert_eq!(lens("0d").refresh_interval(), None);
+        assert_eq!(lens("weekly").refresh_interval(), None);
+        assert_eq!(LensConfig::default().refresh_interval(), None);
+    }
+
     #[test]
     fn test_rules_display() {
         let rule = LensRule::SkipURL("http://example.com".to_string());
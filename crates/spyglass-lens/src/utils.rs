@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 /// Convert a base domain string, e.g. "example.com" into a regex
 /// that can be used to match against URLs, e.g. "^(http://|https://)example.com.*"
 pub fn regex_for_domain(domain: &str) -> String {
@@ -12,6 +14,28 @@ pub fn regex_for_domain(domain: &str) -> String {
     format!("^(http://|https://){}.*", regex)
 }
 
+/// Parse an interval such as "30m", "12h", "7d" or "2w". Zero length
+/// intervals are treated as invalid.
+pub fn parse_interval(interval: &str) -> Option<Duration> {
+    let interval = interval.trim();
+    let unit = interval.chars().last()?;
+    let amount = interval[..interval.len() - unit.len_utf8()]
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .filter(|amount| *amount > 0)?;
+
+    let secs = match unit {
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        'w' => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+
+    amount.checked_mul(secs).map(Duration::from_secs)
+}
+
 pub fn regex_for_prefix(prefix: &str) -> String {
     if prefix.ends_with('$') {
         return format!("^{}", prefix);
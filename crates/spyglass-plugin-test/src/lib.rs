@@ -0,0 +1,421 @@
+//! Test harness for spyglass plugins.
+//!
+//! Loads a compiled plugin (`.wasm`) & runs it against a mock host instead of
+//! the spyglass daemon. Host calls are answered from an in-memory filesystem
+//! & scripted responses, and captured so tests can assert on what the plugin
+//! asked for.
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use serde::{de::DeserializeOwned, Serialize};
+use spyglass_plugin::{
+    consts::env, AccessToken, PluginCommandRequest, PluginEvent, PluginSubscription, SearchFilter,
+};
+use tempfile::TempDir;
+use wasmer::{Exports, Function, Instance, Module, Store, WasmerEnv};
+use wasmer_wasi::{Pipe, WasiEnv, WasiError, WasiState};
+
+mod mock;
+pub use mock::{HostCall, MockFs, MockHost};
+
+/// Passed to the `spyglass` imports whenever the plugin calls into the host.
+#[derive(WasmerEnv, Clone)]
+struct HostEnv {
+    name: String,
+    wasi_env: WasiEnv,
+    host: Arc<Mutex<MockHost>>,
+}
+
+pub struct PluginHarnessBuilder {
+    name: String,
+    wasm_path: PathBuf,
+    envs: HashMap<String, String>,
+    data_files: Vec<(PathBuf, Vec<u8>)>,
+    host: MockHost,
+}
+
+impl PluginHarnessBuilder {
+    /// Set an environment variable, e.g. one of the plugin's user settings.
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        self.envs.insert(key.to_string(), value.to_string());
+        self
+    }
+
+    /// Add a file to the plugin data directory, which the plugin sees mounted
+    /// at `/`.
+    pub fn data_file(mut self, path: &str, contents: impl Into<Vec<u8>>) -> Self {
+        let path = PathBuf::from(path.trim_start_matches('/'));
+        self.data_files.push((path, contents.into()));
+        self
+    }
+
+    /// Add a file to the mock host filesystem.
+    pub fn host_file(mut self, path: impl AsRef<Path>) -> Self {
+        self.host.fs.add_file(path);
+        self
+    }
+
+    /// Add a directory to the mock host filesystem.
+    pub fn host_dir(mut self, path: impl AsRef<Path>) -> Self {
+        self.host.fs.add_dir(path);
+        self
+    }
+
+    /// URLs "returned" when the plugin runs a sqlite query against `path`.
+    pub fn sqlite_results(mut self, path: &str, urls: &[&str]) -> Self {
+        self.host.sqlite_results.insert(
+            path.to_string(),
+            urls.iter().map(|url| url.to_string()).collect(),
+        );
+        self
+    }
+
+    /// Response to send when the plugin requests an access token for `api_id`.
+    /// Requests for connections w/o a scripted response are denied.
+    pub fn access_token(mut self, api_id: &str, token: Result<AccessToken, String>) -> Self {
+        self.host.access_tokens.insert(api_id.to_string(), token);
+        self
+    }
+
+    pub fn build(self) -> anyhow::Result<PluginHarness> {
+        let data_dir = tempfile::tempdir()?;
+        for (path, contents) in &self.data_files {
+            let path = data_dir.path().join(path);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, contents)?;
+        }
+
+        let store = Store::default();
+        let module = Module::from_file(&store, &self.wasm_path)?;
+
+        let mut wasi_env = WasiState::new(&self.name)
+            .map_dir("/", data_dir.path())?
+            .env(env::BASE_CONFIG_DIR, "/config")
+            .env(env::BASE_DATA_DIR, "/data")
+            .env(env::HOST_HOME_DIR, "/home")
+            .env(env::HOST_OS, std::env::consts::OS)
+            .envs(self.envs)
+            .stdin(Box::new(Pipe::new()))
+            .stdout(Box::new(Pipe::new()))
+            .finalize()?;
+
+        let host = Arc::new(Mutex::new(self.host));
+        let env = HostEnv {
+            name: self.name.clone(),
+            wasi_env: wasi_env.clone(),
+            host: host.clone(),
+        };
+
+        let mut exports = Exports::new();
+        exports.insert(
+            "plugin_cmd",
+            Function::new_native_with_env(&store, env.clone(), plugin_cmd),
+        );
+        exports.insert(
+            "plugin_log",
+            Function::new_native_with_env(&store, env, plugin_log),
+        );
+
+        let mut import_object = wasi_env.import_object(&module)?;
+        import_object.register("spyglass", exports);
+        let instance = Instance::new(&module, &import_object)?;
+
+        Ok(PluginHarness {
+            name: self.name,
+            instance,
+            wasi_env,
+            host,
+            data_dir,
+        })
+    }
+}
+
+/// A plugin instance running against a [`MockHost`].
+pub struct PluginHarness {
+    name: String,
+    instance: Instance,
+    wasi_env: WasiEnv,
+    host: Arc<Mutex<MockHost>>,
+    data_dir: TempDir,
+}
+
+impl PluginHarness {
+    pub fn builder(wasm_path: impl AsRef<Path>) -> PluginHarnessBuilder {
+        let wasm_path = wasm_path.as_ref().to_path_buf();
+        let name = wasm_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_else(|| "plugin".to_string());
+
+        PluginHarnessBuilder {
+            name,
+            wasm_path,
+            envs: HashMap::new(),
+            data_files: Vec::new(),
+            host: MockHost::default(),
+        }
+    }
+
+    /// Run the plugin's `load`, same as the host does when the plugin is
+    /// enabled.
+    pub fn load(&mut self) -> anyhow::Result<()> {
+        self.call("_start")
+    }
+
+    /// Send an event to the plugin's `update`.
+    pub fn update(&mut self, event: PluginEvent) -> anyhow::Result<()> {
+        wasi_write(&self.wasi_env, &event)?;
+        self.call("update")
+    }
+
+    /// Send a scripted sequence of events, in order.
+    pub fn replay(&mut self, events: &[PluginEvent]) -> anyhow::Result<()> {
+        for event in events {
+            self.update(event.clone())?;
+        }
+
+        Ok(())
+    }
+
+    /// Run tasks the plugin scheduled w/ `schedule_task`, including ones
+    /// scheduled while running them, until none are left. Stops after
+    /// `max_tasks` in case the plugin keeps rescheduling itself.
+    pub fn run_scheduled(&mut self, max_tasks: usize) -> anyhow::Result<usize> {
+        let mut num_run = 0;
+        while num_run < max_tasks {
+            let next = {
+                let mut host = self.host();
+                if host.scheduled.is_empty() {
+                    break;
+                }
+                host.scheduled.remove(0)
+            };
+
+            self.update(next)?;
+            num_run += 1;
+        }
+
+        Ok(num_run)
+    }
+
+    /// Latest progress (0-100) the plugin reported for `task`.
+    pub fn progress(&self, task: &str) -> Option<u8> {
+        self.host().progress.get(task).copied()
+    }
+
+    /// Ask a lens plugin for its search filters.
+    pub fn search_filters(&mut self) -> anyhow::Result<Vec<SearchFilter>> {
+        self.call("search_filter")?;
+        wasi_read(&self.wasi_env)
+    }
+
+    /// Where the plugin data directory lives on the host, to check what the
+    /// plugin wrote.
+    pub fn data_dir(&self) -> &Path {
+        self.data_dir.path()
+    }
+
+    /// Mock host state, e.g. to add files to the host filesystem mid-test.
+    pub fn host(&self) -> MutexGuard<'_, MockHost> {
+        self.host.lock().expect("Mock host lock poisoned")
+    }
+
+    /// Every call the plugin made into the host, in order.
+    pub fn calls(&self) -> Vec<HostCall> {
+        self.host().calls.clone()
+    }
+
+    /// URLs the plugin added to the crawl queue.
+    pub fn enqueued(&self) -> Vec<String> {
+        self.host().enqueued.clone()
+    }
+
+    /// Messages the plugin logged, including anything printed to stdout that
+    /// wasn't a host call.
+    pub fn logs(&self) -> Vec<String> {
+        self.host()
+            .calls
+            .iter()
+            .filter_map(|call| match call {
+                HostCall::Log(msg) => Some(msg.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn subscriptions(&self) -> Vec<PluginSubscription> {
+        self.commands(|cmd| match cmd {
+            PluginCommandRequest::Subscribe(sub) => Some(sub.clone()),
+            _ => None,
+        })
+    }
+
+    /// URLs the plugin asked to remove from the index.
+    pub fn deleted(&self) -> Vec<String> {
+        self.commands(|cmd| match cmd {
+            PluginCommandRequest::DeleteDoc { url } => Some(url.clone()),
+            _ => None,
+        })
+    }
+
+    fn commands<T>(&self, filter: impl Fn(&PluginCommandRequest) -> Option<T>) -> Vec<T> {
+        self.host()
+            .calls
+            .iter()
+            .filter_map(|call| match call {
+                HostCall::Command(cmd) => filter(cmd),
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn call(&mut self, func_name: &str) -> anyhow::Result<()> {
+        let func = self.instance.exports.get_function(func_name)?;
+        let res = func.call(&[]);
+        // Catch anything printed after the last host call.
+        drain_stdout(&self.wasi_env, &self.host);
+
+        match res {
+            Ok(_) => Ok(()),
+            Err(err) => match err.downcast::<WasiError>() {
+                Ok(WasiError::Exit(0)) => Ok(()),
+                Ok(err) => Err(anyhow::anyhow!("<{}> exited: {}", self.name, err)),
+                Err(err) => Err(anyhow::anyhow!("<{}> {}: {}", self.name, func_name, err)),
+            },
+        }
+    }
+}
+
+/// Handle a plugin call into the host. Unlike the daemon this runs inline, so
+/// everything the plugin asked for has been captured once the call returns.
+fn plugin_cmd(env: &HostEnv) {
+    let buf = match wasi_read_string(&env.wasi_env) {
+        Ok(buf) => buf,
+        Err(err) => {
+            log_host(env, &format!("unable to read cmd: {}", err));
+            return;
+        }
+    };
+
+    // The command is the last line, anything before it was printed by the plugin.
+    let mut lines = buf
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect::<Vec<_>>();
+    let cmd = lines.pop().unwrap_or_default();
+    for line in lines {
+        log_host(env, line);
+    }
+
+    let cmd = match ron::from_str::<PluginCommandRequest>(cmd) {
+        Ok(cmd) => cmd,
+        Err(err) => {
+            log_host(env, &format!("invalid cmd {}: {}", cmd, err));
+            return;
+        }
+    };
+
+    let response = env
+        .host
+        .lock()
+        .map_err(|_| anyhow::anyhow!("Mock host lock poisoned"))
+        .and_then(|mut host| host.handle_cmd(cmd));
+
+    match response {
+        Ok(Some(response)) => {
+            if let Err(err) = wasi_write_string(&env.wasi_env, &response) {
+                log_host(env, &format!("unable to respond: {}", err));
+            }
+        }
+        Ok(None) => {}
+        Err(err) => log_host(env, &format!("unable to handle cmd: {}", err)),
+    }
+}
+
+fn plugin_log(env: &HostEnv) {
+    if let Ok(msg) = wasi_read_string(&env.wasi_env) {
+        log_host(env, msg.trim());
+    }
+}
+
+fn log_host(env: &HostEnv, msg: &str) {
+    if let Ok(mut host) = env.host.lock() {
+        host.log(msg);
+    } else {
+        eprintln!("{}: {}", env.name, msg);
+    }
+}
+
+fn drain_stdout(wasi_env: &WasiEnv, host: &Arc<Mutex<MockHost>>) {
+    if let (Ok(buf), Ok(mut host)) = (wasi_read_string(wasi_env), host.lock()) {
+        buf.lines()
+            .filter(|line| !line.trim().is_empty())
+            .for_each(|line| host.log(line.trim()));
+    }
+}
+
+// --------------------------------------------------------------------------------
+// wasi <> host comms, same wire format as the daemon
+// --------------------------------------------------------------------------------
+
+fn wasi_read_string(wasi_env: &WasiEnv) -> anyhow::Result<String> {
+    let mut state = wasi_env.state();
+    let stdout = state
+        .fs
+        .stdout_mut()?
+        .as_mut()
+        .ok_or_else(|| anyhow::Error::msg("Unable to unwrap stdout"))?;
+
+    let mut buf = String::new();
+    stdout.read_to_string(&mut buf)?;
+    Ok(buf)
+}
+
+fn wasi_write_string(env: &WasiEnv, buf: &str) -> anyhow::Result<()> {
+    let mut state = env.state();
+    let stdin = state
+        .fs
+        .stdin_mut()?
+        .as_mut()
+        .ok_or_else(|| anyhow::Error::msg("Unable to get stdin pipe"))?;
+    writeln!(stdin, "{}\r", buf)?;
+    Ok(())
+}
+
+fn wasi_read<T: DeserializeOwned>(env: &WasiEnv) -> anyhow::Result<T> {
+    let buf = wasi_read_string(env)?;
+    Ok(ron::from_str(buf.trim())?)
+}
+
+fn wasi_write(env: &WasiEnv, obj: &(impl Serialize + ?Sized)) -> anyhow::Result<()> {
+    wasi_write_string(env, &ron::to_string(&obj)?)
+}
+
+#[cfg(test)]
+mod test {
+    use spyglass_plugin::PluginEvent;
+
+    use super::PluginHarness;
+
+    const HELLO_WASM: &str = "../../plugins/test-plugin/hello.wasm";
+
+    #[test]
+    fn test_load_captures_stdout() {
+        let mut harness = PluginHarness::builder(HELLO_WASM)
+            .data_file("/data.json", "{}")
+            .build()
+            .expect("Unable to load plugin");
+
+        harness.load().expect("Unable to run plugin");
+        assert_eq!(harness.logs(), vec!["Hello, None".to_string()]);
+        assert!(harness.enqueued().is_empty());
+        assert!(harness.data_dir().join("data.json").exists());
+
+        // Not a spyglass plugin, so there's no update to call
+        assert!(harness.update(PluginEvent::IntervalUpdate).is_err());
+    }
+}
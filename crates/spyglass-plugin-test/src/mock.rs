@@ -0,0 +1,237 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use spyglass_plugin::{
+    utils::path_to_uri, AccessToken, ListDirEntry, PluginCommandRequest, PluginEvent,
+};
+
+/// In-memory view of the host filesystem, used to answer `ListDir` &
+/// `WalkAndEnqueue` requests.
+#[derive(Clone, Debug, Default)]
+pub struct MockFs {
+    dirs: BTreeSet<PathBuf>,
+    files: BTreeSet<PathBuf>,
+}
+
+impl MockFs {
+    /// Add a file & all of its parent directories.
+    pub fn add_file(&mut self, path: impl AsRef<Path>) {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            self.add_dir(parent);
+        }
+        self.files.insert(path.to_path_buf());
+    }
+
+    /// Add a directory & all of its parents.
+    pub fn add_dir(&mut self, path: impl AsRef<Path>) {
+        for dir in path.as_ref().ancestors() {
+            if dir.as_os_str().is_empty() {
+                continue;
+            }
+            self.dirs.insert(dir.to_path_buf());
+        }
+    }
+
+    /// Direct children of `path`, directories first.
+    pub fn list_dir(&self, path: impl AsRef<Path>) -> Vec<ListDirEntry> {
+        let path = path.as_ref();
+        let is_child = |entry: &&PathBuf| entry.parent() == Some(path);
+
+        let dirs = self.dirs.iter().filter(is_child).map(|dir| ListDirEntry {
+            path: dir.display().to_string(),
+            is_file: false,
+            is_dir: true,
+        });
+        let files = self.files.iter().filter(is_child).map(|file| ListDirEntry {
+            path: file.display().to_string(),
+            is_file: true,
+            is_dir: false,
+        });
+
+        dirs.chain(files).collect()
+    }
+
+    /// Files under `path` (recursively) w/ one of the `extensions`.
+    pub fn walk(&self, path: impl AsRef<Path>, extensions: &HashSet<String>) -> Vec<PathBuf> {
+        let path = path.as_ref();
+        self.files
+            .iter()
+            .filter(|file| file.starts_with(path))
+            .filter(|file| {
+                file.extension()
+                    .and_then(|ext| ext.to_str())
+                    .map_or(false, |ext| extensions.contains(ext))
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+/// Everything the plugin asked the host to do, in order.
+#[derive(Clone, Debug)]
+pub enum HostCall {
+    Command(PluginCommandRequest),
+    Log(String),
+}
+
+/// Host side state shared w/ the exported functions.
+#[derive(Debug, Default)]
+pub struct MockHost {
+    pub fs: MockFs,
+    /// Scripted results for `SqliteQuery`, by db path.
+    pub sqlite_results: HashMap<String, Vec<String>>,
+    /// Scripted responses for `AccessToken`, by api id.
+    pub access_tokens: HashMap<String, Result<AccessToken, String>>,
+    pub calls: Vec<HostCall>,
+    /// URLs that would've been added to the crawl queue.
+    pub enqueued: Vec<String>,
+    /// Latest progress reported for each task.
+    pub progress: HashMap<String, u8>,
+    /// Tasks the plugin scheduled that haven't been run yet.
+    pub scheduled: Vec<PluginEvent>,
+}
+
+impl MockHost {
+    /// Record a command & return the response to send back to the plugin, if
+    /// the real host sends one.
+    pub fn handle_cmd(&mut self, cmd: PluginCommandRequest) -> anyhow::Result<Option<String>> {
+        let response = match &cmd {
+            PluginCommandRequest::AccessToken { api_id, .. } => {
+                let token = self
+                    .access_tokens
+                    .get(api_id)
+                    .cloned()
+                    .unwrap_or_else(|| Err(format!("no {} connection found", api_id)));
+                Some(ron::to_string(&token)?)
+            }
+            PluginCommandRequest::Enqueue { urls } => {
+                self.enqueued.extend(urls.iter().cloned());
+                None
+            }
+            PluginCommandRequest::ListDir { path } => {
+                Some(ron::to_string(&self.fs.list_dir(path))?)
+            }
+            PluginCommandRequest::ReportProgress { task, percent, .. } => {
+                self.progress.insert(task.clone(), *percent);
+                None
+            }
+            PluginCommandRequest::ScheduleTask { task, payload, .. } => {
+                // Delays are skipped, tests run scheduled tasks w/ `run_scheduled`
+                self.scheduled.push(PluginEvent::RunTask {
+                    task: task.clone(),
+                    payload: payload.clone(),
+                });
+                None
+            }
+            PluginCommandRequest::SqliteQuery { path, .. } => {
+                if let Some(urls) = self.sqlite_results.get(path) {
+                    self.enqueued.extend(urls.iter().cloned());
+                }
+                None
+            }
+            PluginCommandRequest::WalkAndEnqueue { path, extensions } => {
+                let files = self.fs.walk(path, extensions);
+                self.enqueued.extend(files.into_iter().map(path_to_uri));
+                // The plugin doesn't wait on the walk stats, so nothing is
+                // sent back here.
+                None
+            }
+            PluginCommandRequest::DeleteDoc { .. }
+            | PluginCommandRequest::Subscribe(_)
+            | PluginCommandRequest::SyncFile { .. } => None,
+        };
+
+        self.calls.push(HostCall::Command(cmd));
+        Ok(response)
+    }
+
+    pub fn log(&mut self, msg: &str) {
+        self.calls.push(HostCall::Log(msg.to_string()));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+
+    use spyglass_plugin::PluginCommandRequest;
+
+    use super::{MockFs, MockHost};
+
+    #[test]
+    fn test_list_dir() {
+        let mut fs = MockFs::default();
+        fs.add_file("/notes/todo.md");
+        fs.add_file("/notes/work/plan.txt");
+        fs.add_dir("/notes/empty");
+
+        let entries = fs.list_dir("/notes");
+        let paths = entries
+            .iter()
+            .map(|entry| entry.path.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(paths, vec!["/notes/empty", "/notes/work", "/notes/todo.md"]);
+        assert!(entries[0].is_dir);
+        assert!(entries[2].is_file);
+        assert!(fs.list_dir("/missing").is_empty());
+    }
+
+    #[test]
+    fn test_walk_and_enqueue() {
+        let mut host = MockHost::default();
+        host.fs.add_file("/notes/todo.md");
+        host.fs.add_file("/notes/work/plan.txt");
+        host.fs.add_file("/notes/work/image.png");
+
+        let res = host
+            .handle_cmd(PluginCommandRequest::WalkAndEnqueue {
+                path: "/notes".into(),
+                extensions: HashSet::from(["md".to_string(), "txt".to_string()]),
+            })
+            .expect("Unable to handle cmd");
+        assert!(res.is_none());
+        assert_eq!(
+            host.enqueued,
+            vec![
+                "file://localhost/notes/todo.md".to_string(),
+                "file://localhost/notes/work/plan.txt".to_string(),
+            ]
+        );
+        assert_eq!(host.calls.len(), 1);
+    }
+
+    #[test]
+    fn test_schedule_task() {
+        let mut host = MockHost::default();
+        host.handle_cmd(PluginCommandRequest::ScheduleTask {
+            task: "import".into(),
+            payload: "page=2".into(),
+            delay_secs: 60,
+        })
+        .expect("Unable to handle cmd");
+        host.handle_cmd(PluginCommandRequest::ReportProgress {
+            task: "import".into(),
+            percent: 50,
+            message: None,
+        })
+        .expect("Unable to handle cmd");
+
+        assert_eq!(host.scheduled.len(), 1);
+        assert_eq!(host.progress.get("import"), Some(&50));
+    }
+
+    #[test]
+    fn test_unscripted_access_token() {
+        let mut host = MockHost::default();
+        let res = host
+            .handle_cmd(PluginCommandRequest::AccessToken {
+                api_id: "calendar.google.com".into(),
+                account: None,
+                scopes: Vec::new(),
+            })
+            .expect("Unable to handle cmd")
+            .expect("Expected a response");
+        assert!(res.starts_with("Err("));
+    }
+}
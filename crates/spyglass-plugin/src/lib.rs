@@ -40,6 +40,17 @@ macro_rules! register_plugin {
             })
         }
 
+        #[no_mangle]
+        pub fn parse_document() {
+            STATE.with(|state| {
+                let parsed = match $crate::object_from_stdin::<$crate::ParseRequest>() {
+                    Ok(request) => state.borrow_mut().parse_document(request),
+                    Err(err) => Err(err.to_string()),
+                };
+                let _ = $crate::object_to_stdout(&parsed);
+            })
+        }
+
         #[no_mangle]
         pub fn search_filter() {
             STATE.with(|state| {
@@ -61,6 +72,35 @@ pub trait SpyglassPlugin {
     fn search_filter(&mut self) -> Vec<SearchFilter> {
         vec![SearchFilter::None]
     }
+    /// Optional function.
+    /// Only called for Parser plugins, w/ documents in one of the formats listed
+    /// in the plugin manifest.
+    fn parse_document(&mut self, request: ParseRequest) -> Result<ParsedDocument, String> {
+        Err(format!("Unable to parse {}", request.url))
+    }
+}
+
+/// Document sent to a Parser plugin.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ParseRequest {
+    pub url: String,
+    /// Lowercase file extension, if the URL has one.
+    pub extension: Option<String>,
+    /// MIME type w/o parameters, if the server sent one.
+    pub mime_type: Option<String>,
+    /// Raw bytes of the document.
+    pub content: Vec<u8>,
+}
+
+/// Text & metadata a Parser plugin pulled out of a document.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ParsedDocument {
+    pub title: Option<String>,
+    /// Generated from the content if not set.
+    pub description: Option<String>,
+    pub content: String,
+    /// Structured (name, value) pairs, e.g. tags or authors.
+    pub fields: Vec<(String, String)>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -93,20 +133,31 @@ impl fmt::Display for PluginSubscription {
     }
 }
 
-#[derive(Clone, Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize)]
 pub enum PluginEvent {
     IntervalUpdate,
     // File watcher updates
     FileCreated(PathBuf),
     FileUpdated(PathBuf),
     FileDeleted(PathBuf),
+    // A task scheduled by the plugin w/ `schedule_task`
+    RunTask { task: String, payload: String },
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub enum PluginCommandRequest {
+    // Request an access token for a connection the user has granted this
+    // plugin access to.
+    AccessToken {
+        api_id: String,
+        account: Option<String>,
+        scopes: Vec<String>,
+    },
     DeleteDoc {
         url: String,
     },
+    // Make an HTTP request to a domain the plugin has been allowed to reach
+    Http(HttpRequest),
     // Enqueue a list of URLs into the crawl queue
     Enqueue {
         urls: Vec<String>,
@@ -115,6 +166,18 @@ pub enum PluginCommandRequest {
     ListDir {
         path: String,
     },
+    // Report progress of a long-running task, 0-100
+    ReportProgress {
+        task: String,
+        percent: u8,
+        message: Option<String>,
+    },
+    // Ask the host to call back w/ a `RunTask` event after a delay
+    ScheduleTask {
+        task: String,
+        payload: String,
+        delay_secs: u64,
+    },
     // Subscribe to PluginEvents
     Subscribe(PluginSubscription),
     // Run a sqlite query on a db file. NOTE: This is a workaround due to the fact
@@ -135,6 +198,36 @@ pub enum PluginCommandRequest {
     },
 }
 
+/// Short-lived access token for a connection, refreshed by the host.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AccessToken {
+    pub token: String,
+    pub account: String,
+    /// Unix timestamp (secs) the token expires at, if it expires.
+    pub expires_at: Option<i64>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+pub enum HttpMethod {
+    Get,
+    Post,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HttpRequest {
+    pub method: HttpMethod,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct ListDirEntry {
     pub path: String,
@@ -2,7 +2,32 @@ use serde::{de::DeserializeOwned, Serialize};
 use std::io;
 use std::{collections::HashSet, path::PathBuf};
 
-use crate::{ListDirEntry, PluginCommandRequest, PluginSubscription};
+use crate::{
+    AccessToken, HttpMethod, HttpRequest, HttpResponse, ListDirEntry, PluginCommandRequest,
+    PluginSubscription,
+};
+
+/// Request an access token for a connection the user has granted this plugin
+/// access to. `scopes` must be a subset of those listed in the plugin manifest.
+/// If `account` is `None`, the first account for the connection is used.
+pub fn access_token(
+    api_id: &str,
+    account: Option<&str>,
+    scopes: &[String],
+) -> Result<AccessToken, String> {
+    object_to_stdout(&PluginCommandRequest::AccessToken {
+        api_id: api_id.to_string(),
+        account: account.map(|account| account.to_string()),
+        scopes: scopes.to_vec(),
+    })
+    .map_err(|err| err.to_string())?;
+
+    unsafe {
+        plugin_cmd();
+    }
+
+    object_from_stdin::<Result<AccessToken, String>>().map_err(|err| err.to_string())?
+}
 
 pub fn delete_doc(url: &str) {
     if object_to_stdout(&PluginCommandRequest::DeleteDoc {
@@ -16,6 +41,43 @@ pub fn delete_doc(url: &str) {
     }
 }
 
+fn http_request(request: HttpRequest) -> Result<HttpResponse, String> {
+    object_to_stdout(&PluginCommandRequest::Http(request)).map_err(|err| err.to_string())?;
+
+    unsafe {
+        plugin_cmd();
+    }
+
+    object_from_stdin::<Result<HttpResponse, String>>().map_err(|err| err.to_string())?
+}
+
+/// GET `url` w/ the given headers. Only works for domains listed in the
+/// plugin manifest's `allowed_domains` that the user has approved.
+pub fn http_get(url: &str, headers: &[(&str, &str)]) -> Result<HttpResponse, String> {
+    http_request(HttpRequest {
+        method: HttpMethod::Get,
+        url: url.to_string(),
+        headers: headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+        body: None,
+    })
+}
+
+/// POST `body` to `url` w/ the given headers, see `http_get`.
+pub fn http_post(url: &str, headers: &[(&str, &str)], body: &str) -> Result<HttpResponse, String> {
+    http_request(HttpRequest {
+        method: HttpMethod::Post,
+        url: url.to_string(),
+        headers: headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect(),
+        body: Some(body.to_string()),
+    })
+}
+
 pub fn subscribe(event: PluginSubscription) {
     if object_to_stdout(&PluginCommandRequest::Subscribe(event)).is_ok() {
         unsafe {
@@ -49,6 +111,39 @@ pub fn list_dir(path: &str) -> Result<Vec<ListDirEntry>, ron::error::SpannedErro
     Ok(Vec::new())
 }
 
+/// Report progress of a long-running task, e.g. a big import, so users can
+/// see it hasn't stalled. `percent` is clamped to 100.
+pub fn report_progress(task: &str, percent: u8, message: Option<&str>) {
+    if object_to_stdout(&PluginCommandRequest::ReportProgress {
+        task: task.to_string(),
+        percent: percent.min(100),
+        message: message.map(|msg| msg.to_string()),
+    })
+    .is_ok()
+    {
+        unsafe {
+            plugin_cmd();
+        }
+    }
+}
+
+/// Have the host call `update` w/ a `PluginEvent::RunTask` containing `payload`
+/// after `delay_secs`. Use this to split big jobs into chunks, each one
+/// scheduling the next, instead of doing everything in a single call.
+pub fn schedule_task(task: &str, payload: &str, delay_secs: u64) {
+    if object_to_stdout(&PluginCommandRequest::ScheduleTask {
+        task: task.to_string(),
+        payload: payload.to_string(),
+        delay_secs,
+    })
+    .is_ok()
+    {
+        unsafe {
+            plugin_cmd();
+        }
+    }
+}
+
 /// Recursively walk & enqueue contents of a path.
 pub fn walk_and_enqueue_dir(
     path: PathBuf,
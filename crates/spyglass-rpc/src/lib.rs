@@ -1,10 +1,16 @@
 use jsonrpsee::core::Error;
 use jsonrpsee::proc_macros::rpc;
 
-use shared::request::{SearchLensesParam, SearchParam};
+use shared::request::{
+    CollectionParam, ContentRangeParam, EnqueueParam, NoteParam, SavedSearchParam,
+    SearchLensesParam, SearchParam,
+};
 use shared::response::{
-    AppStatus, CrawlStats, LensResult, ListConnectionResult, PluginResult, SearchLensesResp,
-    SearchResults,
+    AppStatus, BackupResult, BenchmarkResult, CollectionResult, CrawlStats, DocContent,
+    FailedCrawl, FetchInfoResult, FreshnessReport, LensCoverage, LensResult, ListConnectionResult,
+    NoteResult, OptimizeResult, PageStatus, PluginLogEntry, PluginResult, ReindexResult,
+    SavedSearch, SavedSearchAlert, SearchFacet, SearchLensesResp, SearchResult, SearchResults,
+    VersionDiff, VersionResult, WatchedPage,
 };
 
 /// Rpc trait
@@ -17,45 +23,263 @@ pub trait Rpc {
     #[method(name = "authorize_connection")]
     async fn authorize_connection(&self, id: String) -> Result<(), Error>;
 
+    #[method(name = "add_note")]
+    async fn add_note(&self, doc_id: String, note: NoteParam) -> Result<NoteResult, Error>;
+
+    #[method(name = "add_to_collection")]
+    async fn add_to_collection(&self, name: String, doc_id: String) -> Result<(), Error>;
+
     #[method(name = "app_status")]
     async fn app_status(&self) -> Result<AppStatus, Error>;
 
+    /// Run several searches in one call, results are returned in the same
+    /// order as `queries`.
+    #[method(name = "batch_search")]
+    async fn batch_search(&self, queries: Vec<SearchParam>) -> Result<Vec<SearchResults>, Error>;
+
     #[method(name = "crawl_stats")]
     async fn crawl_stats(&self) -> Result<CrawlStats, Error>;
 
+    /// Back up the search index & database to a `.tar.gz` archive @ `path`,
+    /// which has to be absolute. Restore it w/ the `--restore` command line flag.
+    #[method(name = "create_backup")]
+    async fn create_backup(&self, path: String) -> Result<BackupResult, Error>;
+
+    #[method(name = "create_collection")]
+    async fn create_collection(&self, collection: CollectionParam) -> Result<(), Error>;
+
+    #[method(name = "delete_collection")]
+    async fn delete_collection(&self, name: String) -> Result<(), Error>;
+
     #[method(name = "delete_doc")]
     async fn delete_doc(&self, id: String) -> Result<(), Error>;
 
     #[method(name = "delete_domain")]
     async fn delete_domain(&self, domain: String) -> Result<(), Error>;
 
+    #[method(name = "delete_note")]
+    async fn delete_note(&self, id: i64) -> Result<(), Error>;
+
+    #[method(name = "delete_saved_search")]
+    async fn delete_saved_search(&self, name: String) -> Result<(), Error>;
+
+    #[method(name = "diff_versions")]
+    async fn diff_versions(
+        &self,
+        doc_id: String,
+        from: String,
+        to: String,
+    ) -> Result<VersionDiff, Error>;
+
+    /// Add URLs to the crawl queue, bypassing the lens & domain filters.
+    #[method(name = "enqueue_urls")]
+    async fn enqueue_urls(&self, queue: EnqueueParam) -> Result<(), Error>;
+
+    #[method(name = "freshness_report")]
+    async fn freshness_report(&self) -> Result<FreshnessReport, Error>;
+
+    /// Stored content of a document for previews, optionally just a section
+    /// or byte range of it. Long content is returned in chunks.
+    #[method(name = "get_doc_content")]
+    async fn get_doc_content(
+        &self,
+        doc_id: String,
+        range: Option<ContentRangeParam>,
+    ) -> Result<DocContent, Error>;
+
+    #[method(name = "get_favicon")]
+    async fn get_favicon(&self, domain: String) -> Result<Option<String>, Error>;
+
+    /// Status, final URL after redirects, content type & fetch duration of the
+    /// response a document was last fetched from.
+    #[method(name = "get_fetch_info")]
+    async fn get_fetch_info(&self, doc_id: String) -> Result<Option<FetchInfoResult>, Error>;
+
+    #[method(name = "get_preview_image")]
+    async fn get_preview_image(&self, doc_id: String) -> Result<Option<String>, Error>;
+
+    /// Thumbnail for a local image, PDF or video, as a data URI. Generated &
+    /// cached the first time it's asked for.
+    #[method(name = "get_thumbnail")]
+    async fn get_thumbnail(&self, doc_id: String) -> Result<Option<String>, Error>;
+
+    /// Crawl a URL right away, e.g. the page open in the browser, optionally
+    /// adding it to a lens. If `wait` is set, returns the doc_id of the page
+    /// once it's been indexed.
+    #[method(name = "index_url")]
+    async fn index_url(
+        &self,
+        url: String,
+        lens: Option<String>,
+        wait: Option<bool>,
+    ) -> Result<Option<String>, Error>;
+
+    /// Install a plugin by its name in the plugin registry or from the URL of
+    /// its archive. The archive's checksum is verified before it's unpacked,
+    /// installs by URL must pass the SHA-256 checksum published by the author.
+    #[method(name = "install_plugin")]
+    async fn install_plugin(
+        &self,
+        url_or_name: String,
+        sha256: Option<String>,
+    ) -> Result<PluginResult, Error>;
+
+    /// Sample the URLs in a lens' sitemap(s), or what's been crawled for its
+    /// domains, & report which parts of the site are indexed, missing or
+    /// skipped & why.
+    #[method(name = "lens_coverage")]
+    async fn lens_coverage(&self, name: String) -> Result<LensCoverage, Error>;
+
+    #[method(name = "list_collections")]
+    async fn list_collections(&self) -> Result<Vec<CollectionResult>, Error>;
+
     #[method(name = "list_connections")]
     async fn list_connections(&self) -> Result<ListConnectionResult, Error>;
 
+    #[method(name = "list_failed_crawls")]
+    async fn list_failed_crawls(&self, domain: Option<String>) -> Result<Vec<FailedCrawl>, Error>;
+
+    #[method(name = "list_favorites")]
+    async fn list_favorites(&self) -> Result<Vec<SearchResult>, Error>;
+
     #[method(name = "list_installed_lenses")]
     async fn list_installed_lenses(&self) -> Result<Vec<LensResult>, Error>;
 
+    #[method(name = "list_notes")]
+    async fn list_notes(&self, doc_id: String) -> Result<Vec<NoteResult>, Error>;
+
     #[method(name = "list_plugins")]
     async fn list_plugins(&self) -> Result<Vec<PluginResult>, Error>;
 
+    #[method(name = "list_saved_searches")]
+    async fn list_saved_searches(&self) -> Result<Vec<SavedSearch>, Error>;
+
+    #[method(name = "list_versions")]
+    async fn list_versions(&self, doc_id: String) -> Result<Vec<VersionResult>, Error>;
+
+    #[method(name = "list_watched_pages")]
+    async fn list_watched_pages(&self) -> Result<Vec<WatchedPage>, Error>;
+
+    #[method(name = "lock_search")]
+    async fn lock_search(&self) -> Result<(), Error>;
+
+    /// Merge the search index into a single segment, which can take a while
+    /// for big indexes. Small segments are also merged in the background
+    /// while crawling is idle.
+    #[method(name = "optimize_index")]
+    async fn optimize_index(&self) -> Result<OptimizeResult, Error>;
+
+    /// Whether `url` has been indexed or is waiting to be crawled.
+    #[method(name = "page_status")]
+    async fn page_status(&self, url: String) -> Result<PageStatus, Error>;
+
+    #[method(name = "pin_result")]
+    async fn pin_result(&self, query: String, doc_id: String) -> Result<(), Error>;
+
+    /// The last `num` (default 100) lines a plugin logged or printed & the
+    /// errors the host ran into calling it, to debug plugins that aren't
+    /// syncing.
+    #[method(name = "plugin_logs")]
+    async fn plugin_logs(
+        &self,
+        name: String,
+        num: Option<u32>,
+    ) -> Result<Vec<PluginLogEntry>, Error>;
+
+    /// Remove completed crawls older than `older_than_days` from the crawl
+    /// queue, returns the number of tasks removed.
+    #[method(name = "prune_crawl_queue")]
+    async fn prune_crawl_queue(&self, older_than_days: u32) -> Result<u64, Error>;
+
     #[method(name = "recrawl_domain")]
     async fn recrawl_domain(&self, domain: String) -> Result<(), Error>;
 
+    #[method(name = "record_open")]
+    async fn record_open(&self, doc_id: String) -> Result<(), Error>;
+
+    /// Refetch, parse & index a document by doc_id or URL, even if it was
+    /// fetched recently, returning the parse output for inspection.
+    #[method(name = "reindex_doc")]
+    async fn reindex_doc(&self, id: String) -> Result<ReindexResult, Error>;
+
+    #[method(name = "remove_from_collection")]
+    async fn remove_from_collection(&self, name: String, doc_id: String) -> Result<(), Error>;
+
     #[method(name = "resync_connection")]
     async fn resync_connection(&self, id: String, account: String) -> Result<(), Error>;
 
     #[method(name = "revoke_connection")]
     async fn revoke_connection(&self, id: String, account: String) -> Result<(), Error>;
 
+    #[method(name = "run_benchmark")]
+    async fn run_benchmark(
+        &self,
+        num_docs: Option<u32>,
+        num_queries: Option<u32>,
+    ) -> Result<BenchmarkResult, Error>;
+
+    /// Save a search to be notified about new documents that match it,
+    /// replacing any saved search w/ the same name.
+    #[method(name = "save_search")]
+    async fn save_search(&self, search: SavedSearchParam) -> Result<SavedSearch, Error>;
+
     #[method(name = "search_docs")]
     async fn search_docs(&self, query: SearchParam) -> Result<SearchResults, Error>;
 
+    #[method(name = "search_facets")]
+    async fn search_facets(&self, query: SearchParam) -> Result<Vec<SearchFacet>, Error>;
+
     #[method(name = "search_lenses")]
     async fn search_lenses(&self, query: SearchLensesParam) -> Result<SearchLensesResp, Error>;
 
+    #[method(name = "star_doc")]
+    async fn star_doc(&self, doc_id: String) -> Result<(), Error>;
+
+    /// Index a page from its DOM snapshot, e.g. sent by the browser extension,
+    /// instead of fetching it. Returns the doc_id of the page, or None if a
+    /// content rule kept it out of the index.
+    #[method(name = "submit_page")]
+    async fn submit_page(&self, url: String, html: String) -> Result<Option<String>, Error>;
+
+    #[method(name = "take_watch_alerts")]
+    async fn take_watch_alerts(&self) -> Result<Vec<WatchedPage>, Error>;
+
     #[method(name = "toggle_pause")]
     async fn toggle_pause(&self, is_paused: bool) -> Result<(), Error>;
 
     #[method(name = "toggle_plugin")]
     async fn toggle_plugin(&self, name: String) -> Result<(), Error>;
+
+    #[method(name = "unlock_search")]
+    async fn unlock_search(&self, token: Option<String>) -> Result<(), Error>;
+
+    #[method(name = "unpin_result")]
+    async fn unpin_result(&self, query: String, doc_id: String) -> Result<(), Error>;
+
+    #[method(name = "unstar_doc")]
+    async fn unstar_doc(&self, doc_id: String) -> Result<(), Error>;
+
+    #[method(name = "unwatch_page")]
+    async fn unwatch_page(&self, url: String) -> Result<(), Error>;
+
+    #[method(name = "update_collection")]
+    async fn update_collection(
+        &self,
+        name: String,
+        collection: CollectionParam,
+    ) -> Result<(), Error>;
+
+    #[method(name = "update_note")]
+    async fn update_note(&self, id: i64, note: NoteParam) -> Result<NoteResult, Error>;
+
+    /// Long-polls for new matches to saved searches, returning as soon as
+    /// there are any or after `timeout_secs` w/ nothing.
+    #[method(name = "wait_saved_search_alerts")]
+    async fn wait_saved_search_alerts(
+        &self,
+        timeout_secs: Option<u64>,
+    ) -> Result<Vec<SavedSearchAlert>, Error>;
+
+    #[method(name = "watch_page")]
+    async fn watch_page(&self, url: String, check_interval_mins: Option<i64>) -> Result<(), Error>;
 }
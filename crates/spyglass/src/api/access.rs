@@ -0,0 +1,118 @@
+//! Who can use the API. The JSON-RPC server only answers requests w/ the
+//! owner's token, see `Config::rpc_token_path`, while users of a shared install
+//! get their own token that only lets them search their lenses.
+
+use std::path::Path;
+
+use anyhow::anyhow;
+use libspyglass::lock::{constant_time_eq, load_or_create_token};
+use shared::config::{ApiUser, Config};
+
+/// Folder in the data directory w/ a token file per API user.
+pub const API_TOKENS_DIR: &str = "api_tokens";
+
+/// Token sent in an `Authorization: Bearer <token>` header.
+pub fn bearer_token(authorization: Option<&str>) -> Option<&str> {
+    authorization.and_then(|header| header.strip_prefix("Bearer "))
+}
+
+/// Whether the request w/ the `authorization` header is from the owner.
+pub fn is_owner(owner_token: &str, authorization: Option<&str>) -> bool {
+    match bearer_token(authorization) {
+        Some(token) => constant_time_eq(token.trim().as_bytes(), owner_token.as_bytes()),
+        None => false,
+    }
+}
+
+/// Tokens for the users who can search a shared install, each limited to
+/// their own lenses. Tokens are created the first time a user is configured.
+pub struct ApiAccess {
+    users: Vec<(String, ApiUser)>,
+}
+
+impl ApiAccess {
+    pub fn from_config(config: &Config, users: &[ApiUser]) -> anyhow::Result<Self> {
+        Self::load(&config.data_dir().join(API_TOKENS_DIR), users)
+    }
+
+    /// Load (or create) the tokens for `users` from the files in `dir`.
+    pub fn load(dir: &Path, users: &[ApiUser]) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+
+        let mut loaded = Vec::new();
+        for user in users {
+            let is_valid = !user.name.is_empty()
+                && user
+                    .name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            if !is_valid {
+                return Err(anyhow!("Invalid API user name: {:?}", user.name));
+            }
+
+            let token = load_or_create_token(&dir.join(&user.name));
+            loaded.push((token, user.clone()));
+        }
+
+        Ok(Self { users: loaded })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.users.is_empty()
+    }
+
+    /// User `token` belongs to, if it's valid.
+    pub fn user(&self, token: &str) -> Option<&ApiUser> {
+        self.users
+            .iter()
+            .find(|(expected, _)| constant_time_eq(token.trim().as_bytes(), expected.as_bytes()))
+            .map(|(_, user)| user)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use shared::config::ApiUser;
+
+    use super::{is_owner, ApiAccess};
+
+    #[test]
+    fn test_api_access() {
+        let dir = std::env::temp_dir().join(format!("api-tokens-{}", uuid::Uuid::new_v4()));
+        let users = vec![
+            ApiUser {
+                name: "jane".into(),
+                lenses: vec!["work".into()],
+            },
+            ApiUser {
+                name: "guest".into(),
+                lenses: vec!["public".into()],
+            },
+        ];
+
+        let access = ApiAccess::load(&dir, &users).expect("Unable to load tokens");
+        let token = std::fs::read_to_string(dir.join("jane")).unwrap();
+        assert_eq!(access.user(&token), Some(&users[0]));
+        assert_eq!(access.user("wrong"), None);
+
+        // Tokens stay the same between runs
+        let reloaded = ApiAccess::load(&dir, &users).unwrap();
+        assert_eq!(reloaded.user(&token), Some(&users[0]));
+
+        let invalid = ApiUser {
+            name: "../jane".into(),
+            lenses: Vec::new(),
+        };
+        assert!(ApiAccess::load(&dir, &[invalid]).is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_is_owner() {
+        assert!(is_owner("secret", Some("Bearer secret")));
+        assert!(!is_owner("secret", Some("Bearer wrong")));
+        assert!(!is_owner("secret", Some("secret")));
+        assert!(!is_owner("secret", None));
+    }
+}
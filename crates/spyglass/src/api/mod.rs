@@ -1,18 +1,32 @@
+use bytes::Bytes;
 use entities::sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
 use jsonrpsee::core::{async_trait, Error};
+use jsonrpsee::RpcModule;
+use libspyglass::lock::load_or_create_token;
 use libspyglass::state::AppState;
 use libspyglass::task::{CollectTask, ManagerCommand};
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use warp::http::StatusCode;
+use warp::Filter;
 
-use jsonrpsee::http_server::{HttpServerBuilder, HttpServerHandle};
-
-use shared::request::{SearchLensesParam, SearchParam};
+use shared::config::Config;
+use shared::request::{
+    CollectionParam, ContentRangeParam, EnqueueParam, NoteParam, SavedSearchParam,
+    SearchLensesParam, SearchParam,
+};
 use shared::response as resp;
 use spyglass_rpc::RpcServer;
 
+mod access;
 mod auth;
+mod portal;
 mod response;
 mod route;
+mod share;
+
+pub use portal::start_search_portal;
+pub use share::start_share_server;
 
 pub struct SpyglassRpc {
     state: AppState,
@@ -28,38 +42,241 @@ impl RpcServer for SpyglassRpc {
         route::authorize_connection(self.state.clone(), id).await
     }
 
+    async fn add_note(&self, doc_id: String, note: NoteParam) -> Result<resp::NoteResult, Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::add_note(self.state.clone(), doc_id, note).await
+    }
+
+    async fn add_to_collection(&self, name: String, doc_id: String) -> Result<(), Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::add_to_collection(self.state.clone(), name, doc_id).await
+    }
+
     async fn app_status(&self) -> Result<resp::AppStatus, Error> {
         route::app_status(self.state.clone()).await
     }
 
+    async fn batch_search(
+        &self,
+        queries: Vec<SearchParam>,
+    ) -> Result<Vec<resp::SearchResults>, Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::batch_search(self.state.clone(), queries).await
+    }
+
     async fn crawl_stats(&self) -> Result<resp::CrawlStats, Error> {
         route::crawl_stats(self.state.clone()).await
     }
 
+    async fn create_backup(&self, path: String) -> Result<resp::BackupResult, Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::create_backup(self.state.clone(), path).await
+    }
+
+    async fn create_collection(&self, collection: CollectionParam) -> Result<(), Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::create_collection(self.state.clone(), collection).await
+    }
+
+    async fn delete_collection(&self, name: String) -> Result<(), Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::delete_collection(self.state.clone(), name).await
+    }
+
     async fn delete_doc(&self, id: String) -> Result<(), Error> {
+        route::check_privacy_lock(&self.state)?;
         route::delete_doc(self.state.clone(), id).await
     }
 
     async fn delete_domain(&self, domain: String) -> Result<(), Error> {
+        route::check_privacy_lock(&self.state)?;
         route::delete_domain(self.state.clone(), domain).await
     }
 
+    async fn delete_note(&self, id: i64) -> Result<(), Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::delete_note(self.state.clone(), id).await
+    }
+
+    async fn delete_saved_search(&self, name: String) -> Result<(), Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::delete_saved_search(self.state.clone(), name).await
+    }
+
+    async fn diff_versions(
+        &self,
+        doc_id: String,
+        from: String,
+        to: String,
+    ) -> Result<resp::VersionDiff, Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::diff_versions(self.state.clone(), doc_id, from, to).await
+    }
+
+    async fn enqueue_urls(&self, queue: EnqueueParam) -> Result<(), Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::enqueue_urls(self.state.clone(), queue).await
+    }
+
+    async fn freshness_report(&self) -> Result<resp::FreshnessReport, Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::freshness_report(self.state.clone()).await
+    }
+
+    async fn get_doc_content(
+        &self,
+        doc_id: String,
+        range: Option<ContentRangeParam>,
+    ) -> Result<resp::DocContent, Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::get_doc_content(self.state.clone(), doc_id, range.unwrap_or_default()).await
+    }
+
+    async fn get_favicon(&self, domain: String) -> Result<Option<String>, Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::get_favicon(self.state.clone(), domain).await
+    }
+
+    async fn get_fetch_info(&self, doc_id: String) -> Result<Option<resp::FetchInfoResult>, Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::get_fetch_info(self.state.clone(), doc_id).await
+    }
+
+    async fn get_preview_image(&self, doc_id: String) -> Result<Option<String>, Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::get_preview_image(self.state.clone(), doc_id).await
+    }
+
+    async fn get_thumbnail(&self, doc_id: String) -> Result<Option<String>, Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::get_thumbnail(self.state.clone(), doc_id).await
+    }
+
+    async fn index_url(
+        &self,
+        url: String,
+        lens: Option<String>,
+        wait: Option<bool>,
+    ) -> Result<Option<String>, Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::index_url(self.state.clone(), url, lens, wait.unwrap_or_default()).await
+    }
+
+    async fn install_plugin(
+        &self,
+        url_or_name: String,
+        sha256: Option<String>,
+    ) -> Result<resp::PluginResult, Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::install_plugin(self.state.clone(), url_or_name, sha256).await
+    }
+
+    async fn lens_coverage(&self, name: String) -> Result<resp::LensCoverage, Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::lens_coverage(self.state.clone(), name).await
+    }
+
+    async fn list_collections(&self) -> Result<Vec<resp::CollectionResult>, Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::list_collections(self.state.clone()).await
+    }
+
     async fn list_connections(&self) -> Result<resp::ListConnectionResult, Error> {
         route::list_connections(self.state.clone()).await
     }
 
+    async fn list_failed_crawls(
+        &self,
+        domain: Option<String>,
+    ) -> Result<Vec<resp::FailedCrawl>, Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::list_failed_crawls(self.state.clone(), domain).await
+    }
+
+    async fn list_favorites(&self) -> Result<Vec<resp::SearchResult>, Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::list_favorites(self.state.clone()).await
+    }
+
     async fn list_installed_lenses(&self) -> Result<Vec<resp::LensResult>, Error> {
         route::list_installed_lenses(self.state.clone()).await
     }
 
+    async fn list_notes(&self, doc_id: String) -> Result<Vec<resp::NoteResult>, Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::list_notes(self.state.clone(), doc_id).await
+    }
+
     async fn list_plugins(&self) -> Result<Vec<resp::PluginResult>, Error> {
         route::list_plugins(self.state.clone()).await
     }
 
+    async fn list_saved_searches(&self) -> Result<Vec<resp::SavedSearch>, Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::list_saved_searches(self.state.clone()).await
+    }
+
+    async fn list_versions(&self, doc_id: String) -> Result<Vec<resp::VersionResult>, Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::list_versions(self.state.clone(), doc_id).await
+    }
+
+    async fn list_watched_pages(&self) -> Result<Vec<resp::WatchedPage>, Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::list_watched_pages(self.state.clone()).await
+    }
+
+    async fn lock_search(&self) -> Result<(), Error> {
+        route::lock_search(self.state.clone()).await
+    }
+
+    async fn optimize_index(&self) -> Result<resp::OptimizeResult, Error> {
+        route::optimize_index(self.state.clone()).await
+    }
+
+    async fn page_status(&self, url: String) -> Result<resp::PageStatus, Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::page_status(self.state.clone(), url).await
+    }
+
+    async fn pin_result(&self, query: String, doc_id: String) -> Result<(), Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::pin_result(self.state.clone(), query, doc_id).await
+    }
+
+    async fn plugin_logs(
+        &self,
+        name: String,
+        num: Option<u32>,
+    ) -> Result<Vec<resp::PluginLogEntry>, Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::plugin_logs(self.state.clone(), name, num).await
+    }
+
+    async fn prune_crawl_queue(&self, older_than_days: u32) -> Result<u64, Error> {
+        route::prune_crawl_queue(self.state.clone(), older_than_days).await
+    }
+
     async fn recrawl_domain(&self, domain: String) -> Result<(), Error> {
+        route::check_privacy_lock(&self.state)?;
         route::recrawl_domain(self.state.clone(), domain).await
     }
 
+    async fn record_open(&self, doc_id: String) -> Result<(), Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::record_open(self.state.clone(), doc_id).await
+    }
+
+    async fn reindex_doc(&self, id: String) -> Result<resp::ReindexResult, Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::reindex_doc(self.state.clone(), id).await
+    }
+
+    async fn remove_from_collection(&self, name: String, doc_id: String) -> Result<(), Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::remove_from_collection(self.state.clone(), name, doc_id).await
+    }
+
     async fn resync_connection(&self, api_id: String, account: String) -> Result<(), Error> {
         let _ = self
             .state
@@ -87,10 +304,29 @@ impl RpcServer for SpyglassRpc {
         Ok(())
     }
 
+    async fn run_benchmark(
+        &self,
+        num_docs: Option<u32>,
+        num_queries: Option<u32>,
+    ) -> Result<resp::BenchmarkResult, Error> {
+        route::run_benchmark(num_docs, num_queries).await
+    }
+
+    async fn save_search(&self, search: SavedSearchParam) -> Result<resp::SavedSearch, Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::save_search(self.state.clone(), search).await
+    }
+
     async fn search_docs(&self, query: SearchParam) -> Result<resp::SearchResults, Error> {
+        route::check_privacy_lock(&self.state)?;
         route::search(self.state.clone(), query).await
     }
 
+    async fn search_facets(&self, query: SearchParam) -> Result<Vec<resp::SearchFacet>, Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::search_facets(self.state.clone(), query).await
+    }
+
     async fn search_lenses(
         &self,
         query: SearchLensesParam,
@@ -98,6 +334,21 @@ impl RpcServer for SpyglassRpc {
         route::search_lenses(self.state.clone(), query).await
     }
 
+    async fn star_doc(&self, doc_id: String) -> Result<(), Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::star_doc(self.state.clone(), doc_id).await
+    }
+
+    async fn submit_page(&self, url: String, html: String) -> Result<Option<String>, Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::submit_page(self.state.clone(), url, html).await
+    }
+
+    async fn take_watch_alerts(&self) -> Result<Vec<resp::WatchedPage>, Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::take_watch_alerts(self.state.clone()).await
+    }
+
     async fn toggle_pause(&self, is_paused: bool) -> Result<(), Error> {
         route::toggle_pause(self.state.clone(), is_paused).await
     }
@@ -105,18 +356,124 @@ impl RpcServer for SpyglassRpc {
     async fn toggle_plugin(&self, name: String) -> Result<(), Error> {
         route::toggle_plugin(self.state.clone(), name).await
     }
+
+    async fn unlock_search(&self, token: Option<String>) -> Result<(), Error> {
+        route::unlock_search(self.state.clone(), token).await
+    }
+
+    async fn unpin_result(&self, query: String, doc_id: String) -> Result<(), Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::unpin_result(self.state.clone(), query, doc_id).await
+    }
+
+    async fn unstar_doc(&self, doc_id: String) -> Result<(), Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::unstar_doc(self.state.clone(), doc_id).await
+    }
+
+    async fn unwatch_page(&self, url: String) -> Result<(), Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::unwatch_page(self.state.clone(), url).await
+    }
+
+    async fn update_collection(
+        &self,
+        name: String,
+        collection: CollectionParam,
+    ) -> Result<(), Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::update_collection(self.state.clone(), name, collection).await
+    }
+
+    async fn update_note(&self, id: i64, note: NoteParam) -> Result<resp::NoteResult, Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::update_note(self.state.clone(), id, note).await
+    }
+
+    async fn wait_saved_search_alerts(
+        &self,
+        timeout_secs: Option<u64>,
+    ) -> Result<Vec<resp::SavedSearchAlert>, Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::wait_saved_search_alerts(self.state.clone(), timeout_secs).await
+    }
+
+    async fn watch_page(&self, url: String, check_interval_mins: Option<i64>) -> Result<(), Error> {
+        route::check_privacy_lock(&self.state)?;
+        route::watch_page(self.state.clone(), url, check_interval_mins).await
+    }
 }
 
-pub async fn start_api_server(state: AppState) -> anyhow::Result<(SocketAddr, HttpServerHandle)> {
-    let server_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), state.user_settings.port);
-    let server = HttpServerBuilder::default().build(server_addr).await?;
+/// Largest JSON-RPC request accepted, in bytes.
+const MAX_RPC_BODY_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Answer a JSON-RPC request, if it's from the owner.
+async fn handle_rpc(
+    rpc: &RpcModule<SpyglassRpc>,
+    owner_token: &str,
+    authorization: Option<String>,
+    body: Bytes,
+) -> Box<dyn warp::Reply> {
+    if !access::is_owner(owner_token, authorization.as_deref()) {
+        return Box::new(warp::reply::with_status(
+            "Invalid token",
+            StatusCode::UNAUTHORIZED,
+        ));
+    }
 
-    let rpc_module = SpyglassRpc {
-        state: state.clone(),
+    let request = match std::str::from_utf8(&body) {
+        Ok(request) => request,
+        Err(_) => {
+            return Box::new(warp::reply::with_status(
+                "Invalid request",
+                StatusCode::BAD_REQUEST,
+            ))
+        }
     };
-    let addr = server.local_addr()?;
-    let server_handle = server.start(rpc_module.into_rpc())?;
+
+    match rpc.raw_json_request(request).await {
+        Ok((response, _)) => Box::new(warp::reply::with_header(
+            response,
+            "content-type",
+            "application/json",
+        )),
+        Err(err) => Box::new(warp::reply::with_status(
+            err.to_string(),
+            StatusCode::BAD_REQUEST,
+        )),
+    }
+}
+
+/// Start the JSON-RPC server. Other users on the machine can reach localhost
+/// too, so every request needs the owner's token as a bearer token.
+pub async fn start_api_server(state: AppState, config: Config) -> anyhow::Result<SocketAddr> {
+    let server_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), state.user_settings.port);
+    let owner_token = Arc::new(load_or_create_token(&config.rpc_token_path()));
+
+    let rpc = Arc::new(
+        SpyglassRpc {
+            state: state.clone(),
+        }
+        .into_rpc(),
+    );
+    let api = warp::post()
+        .and(warp::path::end())
+        .and(warp::header::optional::<String>("authorization"))
+        .and(warp::body::content_length_limit(MAX_RPC_BODY_BYTES))
+        .and(warp::body::bytes())
+        .then(move |authorization, body| {
+            let rpc = rpc.clone();
+            let owner_token = owner_token.clone();
+            async move { handle_rpc(&rpc, &owner_token, authorization, body).await }
+        });
+
+    let mut shutdown_rx = state.shutdown_cmd_tx.lock().await.subscribe();
+    let (addr, server) =
+        warp::serve(api).try_bind_with_graceful_shutdown(server_addr, async move {
+            let _ = shutdown_rx.recv().await;
+        })?;
+    tokio::spawn(server);
 
     log::info!("starting server @ {}", addr);
-    Ok((addr, server_handle))
+    Ok(addr)
 }
@@ -0,0 +1,240 @@
+//! Read-only search page for sharing a curated index w/ others on the network,
+//! see `SearchPortal`. Only searches are served, scoped to the portal's
+//! lenses, & only what's needed to show a result is returned. Users w/ a
+//! token (sent as a bearer token) search their own lenses instead.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use serde::Serialize;
+use url::Url;
+use warp::http::StatusCode;
+use warp::Filter;
+
+use libspyglass::state::AppState;
+use shared::config::{Config, SearchPortal};
+use shared::request::{SearchMode, SearchParam};
+use shared::response::SearchResult;
+
+use super::access::{bearer_token, ApiAccess};
+use super::route;
+
+const PORTAL_PAGE: &str = include_str!("portal.html");
+/// Longer queries are cut off, no one needs more than this to find something.
+const MAX_QUERY_CHARS: usize = 256;
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+struct PortalResult {
+    title: String,
+    url: String,
+    domain: String,
+    description: String,
+    snippet: Option<String>,
+}
+
+impl PortalResult {
+    /// Results that can't be opened from another machine, e.g. local files,
+    /// are left out.
+    fn from_result(result: SearchResult) -> Option<Self> {
+        let url = Url::parse(&result.url).ok()?;
+        if url.scheme() != "http" && url.scheme() != "https" {
+            return None;
+        }
+
+        Some(PortalResult {
+            title: result.title,
+            url: result.url,
+            domain: result.domain,
+            description: result.description,
+            snippet: result.snippet.map(|snippet| snippet.text),
+        })
+    }
+}
+
+/// Lenses a search can use: the ones asked for in `requested` (a comma
+/// separated list), limited to the `allowed` ones, or all the allowed lenses
+/// if none were asked for.
+fn scope_lenses(allowed: &[String], requested: Option<&str>) -> Vec<String> {
+    match requested.filter(|requested| !requested.trim().is_empty()) {
+        Some(requested) => requested
+            .split(',')
+            .map(|lens| lens.trim())
+            .filter(|lens| allowed.iter().any(|allowed| allowed == lens))
+            .map(|lens| lens.to_string())
+            .collect(),
+        None => allowed.to_vec(),
+    }
+}
+
+async fn handle_search(
+    state: AppState,
+    portal: &SearchPortal,
+    access: &ApiAccess,
+    authorization: Option<String>,
+    params: HashMap<String, String>,
+) -> Box<dyn warp::Reply> {
+    if state.privacy_lock.is_locked() {
+        return Box::new(warp::reply::with_status(
+            "Search is locked",
+            StatusCode::LOCKED,
+        ));
+    }
+
+    let allowed = match bearer_token(authorization.as_deref()) {
+        Some(token) => match access.user(token) {
+            Some(user) => &user.lenses,
+            None => {
+                return Box::new(warp::reply::with_status(
+                    "Invalid token",
+                    StatusCode::UNAUTHORIZED,
+                ))
+            }
+        },
+        None => &portal.lenses,
+    };
+
+    // Searching w/o lenses would search everything, so there has to be at
+    // least one to scope the search to.
+    let lenses = scope_lenses(allowed, params.get("lenses").map(|lenses| lenses.as_str()));
+    if lenses.is_empty() {
+        return Box::new(warp::reply::with_status(
+            "No lenses to search",
+            StatusCode::FORBIDDEN,
+        ));
+    }
+
+    let query = params
+        .get("q")
+        .map(|query| query.chars().take(MAX_QUERY_CHARS).collect::<String>())
+        .unwrap_or_default();
+    if query.trim().is_empty() {
+        return Box::new(warp::reply::json(&Vec::<PortalResult>::new()));
+    }
+
+    let search = SearchParam {
+        lenses: Vec::new(),
+        query,
+        lens_names: lenses,
+        mode: SearchMode::Standard,
+        snippet_length: None,
+        context_url: None,
+    };
+
+    match route::search(state, search).await {
+        Ok(found) => {
+            let results = found
+                .results
+                .into_iter()
+                .filter_map(PortalResult::from_result)
+                .collect::<Vec<_>>();
+            Box::new(warp::reply::json(&results))
+        }
+        Err(err) => {
+            log::error!("Unable to search from portal: {}", err);
+            Box::new(warp::reply::with_status(
+                "Unable to search",
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ))
+        }
+    }
+}
+
+/// Start serving the search page on the portal's address.
+pub async fn start_search_portal(
+    state: AppState,
+    config: &Config,
+    portal: SearchPortal,
+) -> anyhow::Result<SocketAddr> {
+    let access = Arc::new(ApiAccess::from_config(config, &portal.users)?);
+    if portal.lenses.is_empty() && access.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Search portal needs at least one lens or user"
+        ));
+    }
+
+    let page = warp::get()
+        .and(warp::path::end())
+        .map(|| warp::reply::html(PORTAL_PAGE));
+
+    let route_portal = Arc::new(portal.clone());
+    let route_state = state.clone();
+    let search = warp::get()
+        .and(warp::path("search"))
+        .and(warp::path::end())
+        .and(warp::header::optional::<String>("authorization"))
+        .and(warp::query::<HashMap<String, String>>())
+        .then(move |authorization, params| {
+            let state = route_state.clone();
+            let portal = route_portal.clone();
+            let access = access.clone();
+            async move { handle_search(state, &portal, &access, authorization, params).await }
+        });
+
+    let mut shutdown_rx = state.shutdown_cmd_tx.lock().await.subscribe();
+    let (addr, server) = warp::serve(page.or(search)).try_bind_with_graceful_shutdown(
+        SocketAddr::new(portal.address, portal.port),
+        async move {
+            let _ = shutdown_rx.recv().await;
+        },
+    )?;
+    tokio::spawn(server);
+
+    log::info!(
+        "search portal for {:?} & {} users listening @ http://{}",
+        portal.lenses,
+        portal.users.len(),
+        addr
+    );
+    Ok(addr)
+}
+
+#[cfg(test)]
+mod test {
+    use shared::response::SearchResult;
+
+    use super::{scope_lenses, PortalResult};
+
+    fn result(url: &str) -> SearchResult {
+        SearchResult {
+            doc_id: "doc".into(),
+            crawl_uri: url.into(),
+            domain: "example.com".into(),
+            title: "Salinas River".into(),
+            description: "A river in California".into(),
+            url: url.into(),
+            tags: Vec::new(),
+            fields: Vec::new(),
+            anchor: None,
+            app_url: None,
+            pinned: false,
+            notes: Vec::new(),
+            version: None,
+            snippet: None,
+            score: 1.0,
+        }
+    }
+
+    #[test]
+    fn test_from_result() {
+        let portal = PortalResult::from_result(result("https://example.com/river"))
+            .expect("Web pages should be shown");
+        assert_eq!(portal.url, "https://example.com/river");
+        assert_eq!(portal.title, "Salinas River");
+
+        assert!(PortalResult::from_result(result("file:///home/user/notes.md")).is_none());
+        assert!(PortalResult::from_result(result("javascript:alert(1)")).is_none());
+    }
+
+    #[test]
+    fn test_scope_lenses() {
+        let allowed = vec!["work".to_string(), "public".to_string()];
+        assert_eq!(scope_lenses(&allowed, None), allowed);
+        assert_eq!(scope_lenses(&allowed, Some("")), allowed);
+        assert_eq!(
+            scope_lenses(&allowed, Some("public, private")),
+            vec!["public".to_string()]
+        );
+        assert!(scope_lenses(&allowed, Some("private")).is_empty());
+    }
+}
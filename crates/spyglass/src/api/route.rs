@@ -1,30 +1,53 @@
 use futures::StreamExt;
 use jsonrpsee::core::Error;
-use std::collections::HashMap;
-use std::time::SystemTime;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
 use tracing::instrument;
 use url::Url;
 
-use entities::models::crawl_queue::CrawlStatus;
+use entities::models::crawl_queue::{CrawlStatus, EnqueueSettings, TaskPriority};
 use entities::models::lens::LensType;
 use entities::models::{
-    bootstrap_queue, connection, crawl_queue, fetch_history, indexed_document, lens, tag,
+    bootstrap_queue, collection, connection, crawl_queue, document_note, document_version,
+    fetch_history, indexed_document, lens, pinned_result, saved_search, search_click, tag,
+    watched_page,
 };
 use entities::schema::{DocFields, SearchDocument};
 use entities::sea_orm::{prelude::*, sea_query, sea_query::Expr, QueryOrder, Set};
+use shared::config::{Config, LensConfig};
+use shared::normalize::UrlNormalizer;
 use shared::request;
 use shared::response::{
-    AppStatus, CrawlStats, LensResult, ListConnectionResult, PluginResult, QueueStatus,
-    SearchLensesResp, SearchMeta, SearchResult, SearchResults, SupportedConnection, UserConnection,
+    AppStatus, BackupResult, BenchmarkResult, CollectionResult, CrawlStats, DocContent, DocSection,
+    FailedCrawl, FetchInfoResult, FreshnessReport, FreshnessSource, LensCoverage, LensResult,
+    ListConnectionResult, NoteResult, OptimizeResult, PageStatus, PluginLogEntry, PluginResult,
+    QueueStatus, QuotaStatus, ReindexResult, SavedSearch, SavedSearchAlert, SearchFacet,
+    SearchLensesResp, SearchMeta, SearchResult, SearchResults, SourceFreshness,
+    SupportedConnection, UserConnection, VersionDiff, VersionResult, WatchedPage,
 };
 use spyglass_plugin::SearchFilter;
+use tantivy::schema::{Document, Field};
 
 use libgoog::{ClientType, Credentials, GoogClient};
+use libspyglass::backup;
+use libspyglass::benchmark;
+use libspyglass::content::{diff::diff_text, thumbnail, ContentVerdict};
+use libspyglass::crawler::{coverage, images};
 use libspyglass::oauth::{self, connection_secret};
-use libspyglass::plugin::PluginCommand;
-use libspyglass::search::{lens::lens_to_filters, Searcher};
+use libspyglass::plugin::{install, logs, plugin_register, PluginCommand};
+use libspyglass::search::{
+    clicks::{self, ClickBoosts},
+    decay::DomainDecay,
+    deeplink::VaultCache,
+    lens::{filter_names, lens_names_to_filters, lens_to_filters, limits_scope},
+    maintenance, note_doc_id, parse_as_of, part_number, preview,
+    snippet::{Snippets, DEFAULT_SNIPPET_CHARS},
+    version_doc_id, version_timestamp, FilterNames, QueryOptions, Searcher, Synonyms,
+};
 use libspyglass::state::AppState;
-use libspyglass::task::{AppPause, CollectTask, ManagerCommand};
+use libspyglass::task::{index_snapshot, reindex_url, CollectTask, ManagerCommand};
 
 use super::auth::create_auth_listener;
 use super::response;
@@ -43,6 +66,7 @@ pub async fn add_queue(
             domain: Set(parsed.host_str().expect("Invalid host str").to_string()),
             url: Set(queue_item.url.to_owned()),
             crawl_type: Set(crawl_queue::CrawlType::Normal),
+            priority: Set(crawl_queue::TaskPriority::User.weight()),
             ..Default::default()
         };
 
@@ -55,6 +79,186 @@ pub async fn add_queue(
     Ok("ok".to_string())
 }
 
+#[instrument(skip(state, queue))]
+pub async fn enqueue_urls(state: AppState, queue: request::EnqueueParam) -> Result<(), Error> {
+    let mut tags = Vec::new();
+    for (label, value) in queue.tags {
+        let tag_type = tag::TagType::try_from_value(&label)
+            .map_err(|_| Error::Custom(format!("Unknown tag type: {}", label)))?;
+        tags.push((tag_type, value));
+    }
+
+    log::debug!("enqueuing {} urls", queue.urls.len());
+    let overrides = EnqueueSettings {
+        force_allow: true,
+        priority: TaskPriority::User,
+        tags,
+        ..Default::default()
+    };
+    crawl_queue::enqueue_all(
+        &state.db,
+        &queue.urls,
+        &[],
+        &state.user_settings,
+        &overrides,
+        None,
+    )
+    .await
+    .map_err(|err| Error::Custom(err.to_string()))?;
+
+    let _ = state.schedule_work(ManagerCommand::CheckForJobs).await;
+    Ok(())
+}
+
+async fn find_collection(state: &AppState, name: &str) -> Result<collection::Model, Error> {
+    match collection::find_by_name(&state.db, name).await {
+        Ok(Some(collection)) => Ok(collection),
+        Ok(None) => Err(Error::Custom(format!("Unknown collection: {}", name))),
+        Err(err) => Err(Error::Custom(err.to_string())),
+    }
+}
+
+async fn find_indexed_doc(
+    state: &AppState,
+    doc_id: &str,
+) -> Result<indexed_document::Model, Error> {
+    match indexed_document::Entity::find()
+        .filter(indexed_document::Column::DocId.eq(doc_id))
+        .one(&state.db)
+        .await
+    {
+        Ok(Some(doc)) => Ok(doc),
+        Ok(None) => Err(Error::Custom(format!("Unknown document: {}", doc_id))),
+        Err(err) => Err(Error::Custom(err.to_string())),
+    }
+}
+
+fn watched_page_result(page: watched_page::Model) -> WatchedPage {
+    WatchedPage {
+        url: page.url,
+        check_interval_mins: page.check_interval_mins,
+        last_checked_at: page.last_checked_at.map(|at| at.to_rfc3339()),
+        last_changed_at: page.last_changed_at.map(|at| at.to_rfc3339()),
+        change_summary: page.change_summary,
+    }
+}
+
+fn saved_search_result(search: saved_search::Model) -> SavedSearch {
+    SavedSearch {
+        name: search.name,
+        query: search.query,
+        lenses: search.lenses.lenses,
+        last_matched_at: search.last_matched_at.map(|at| at.to_rfc3339()),
+    }
+}
+
+fn favorite_tag() -> tag::TagPair {
+    (
+        tag::TagType::Favorited,
+        tag::TagValue::Favorited.as_ref().to_string(),
+    )
+}
+
+fn note_result(note: document_note::Model) -> NoteResult {
+    NoteResult {
+        id: note.id,
+        content: note.content,
+        highlight: note.highlight,
+    }
+}
+
+/// Add the note to the search index w/ its document
+async fn index_note(
+    state: &AppState,
+    doc_id: &str,
+    note: &document_note::Model,
+) -> Result<(), Error> {
+    if let Ok(mut writer) = state.index.writer.lock() {
+        Searcher::upsert_note(
+            &mut writer,
+            &state.index.reader,
+            doc_id,
+            note.id,
+            &note.indexed_text(),
+        )
+        .map_err(|err| Error::Custom(err.to_string()))?;
+    }
+
+    Searcher::save(state)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))
+}
+
+/// Update the tags of a document in the search index to match the database.
+async fn index_tags(state: &AppState, doc: &indexed_document::Model) -> Result<(), Error> {
+    let tags = doc
+        .find_related(tag::Entity)
+        .all(&state.db)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?
+        .iter()
+        .map(|tag| (tag.label.as_ref().to_string(), tag.value.clone()))
+        .collect::<Vec<(String, String)>>();
+
+    if let Ok(mut writer) = state.index.writer.lock() {
+        Searcher::update_tags(&mut writer, &state.index.reader, &doc.doc_id, &tags)
+            .map_err(|err| Error::Custom(err.to_string()))?;
+    }
+
+    Searcher::save(state)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))
+}
+
+async fn find_note(
+    state: &AppState,
+    id: i64,
+) -> Result<(document_note::Model, indexed_document::Model), Error> {
+    match document_note::Entity::find_by_id(id)
+        .find_also_related(indexed_document::Entity)
+        .one(&state.db)
+        .await
+    {
+        Ok(Some((note, Some(doc)))) => Ok((note, doc)),
+        Ok(_) => Err(Error::Custom(format!("Unknown note: {}", id))),
+        Err(err) => Err(Error::Custom(err.to_string())),
+    }
+}
+
+/// Attach a note to an indexed document
+#[instrument(skip(state))]
+pub async fn add_note(
+    state: AppState,
+    doc_id: String,
+    param: request::NoteParam,
+) -> Result<NoteResult, Error> {
+    let doc = find_indexed_doc(&state, &doc_id).await?;
+
+    let note = document_note::ActiveModel {
+        indexed_document_id: Set(doc.id),
+        content: Set(param.content),
+        highlight: Set(param.highlight),
+        ..Default::default()
+    }
+    .insert(&state.db)
+    .await
+    .map_err(|err| Error::Custom(err.to_string()))?;
+
+    index_note(&state, &doc.doc_id, &note).await?;
+    Ok(note_result(note))
+}
+
+/// Add a document to a collection
+#[instrument(skip(state))]
+pub async fn add_to_collection(state: AppState, name: String, doc_id: String) -> Result<(), Error> {
+    let collection = find_collection(&state, &name).await?;
+    let doc = find_indexed_doc(&state, &doc_id).await?;
+
+    collection::add_document(&state.db, &collection, &doc)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))
+}
+
 #[instrument(skip(state))]
 pub async fn authorize_connection(state: AppState, api_id: String) -> Result<(), Error> {
     log::debug!("authorizing <{}>", api_id);
@@ -136,15 +340,101 @@ pub async fn authorize_connection(state: AppState, api_id: String) -> Result<(),
 /// Fun stats about index size, etc.
 #[instrument(skip(state))]
 pub async fn app_status(state: AppState) -> Result<AppStatus, Error> {
+    if state.privacy_lock.is_locked() {
+        return Ok(AppStatus {
+            num_docs: 0,
+            is_locked: true,
+            is_paused: state.is_paused(),
+        });
+    }
+
     // Grab details about index
     let index = state.index;
     let reader = index.reader.searcher();
 
     Ok(AppStatus {
         num_docs: reader.num_docs(),
+        is_locked: false,
+        is_paused: state.is_paused(),
+    })
+}
+
+/// Refuse requests that expose indexed content while search is locked.
+pub fn check_privacy_lock(state: &AppState) -> Result<(), Error> {
+    if state.privacy_lock.touch() {
+        Ok(())
+    } else {
+        Err(Error::Custom("Search is locked".to_string()))
+    }
+}
+
+#[instrument(skip(state))]
+pub async fn lock_search(state: AppState) -> Result<(), Error> {
+    state.privacy_lock.lock();
+    Ok(())
+}
+
+/// Merge the whole index into one segment, see `search::maintenance`.
+#[instrument(skip(state))]
+pub async fn optimize_index(state: AppState) -> Result<OptimizeResult, Error> {
+    let optimized = maintenance::optimize(&state.index)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to optimize index: {}", err)))?;
+
+    log::info!(
+        "optimized index from {} to {} segments",
+        optimized.segments_before,
+        optimized.segments_after
+    );
+    Ok(OptimizeResult {
+        segments_before: optimized.segments_before,
+        segments_after: optimized.segments_after,
     })
 }
 
+/// Unlock search w/ the local token, or the configured unlock command when no
+/// token is given.
+#[instrument(skip(state, token))]
+pub async fn unlock_search(state: AppState, token: Option<String>) -> Result<(), Error> {
+    if state.privacy_lock.unlock(token).await {
+        Ok(())
+    } else {
+        Err(Error::Custom("Unable to unlock search".to_string()))
+    }
+}
+
+/// Max number of queries accepted in a single batch search.
+const MAX_BATCH_QUERIES: usize = 32;
+
+/// Run several searches concurrently, returning results in the same order as
+/// `queries`.
+#[instrument(skip(state))]
+pub async fn batch_search(
+    state: AppState,
+    queries: Vec<request::SearchParam>,
+) -> Result<Vec<SearchResults>, Error> {
+    if queries.len() > MAX_BATCH_QUERIES {
+        return Err(Error::Custom(format!(
+            "Too many queries, a batch can have at most {}",
+            MAX_BATCH_QUERIES
+        )));
+    }
+
+    let handles = queries
+        .into_iter()
+        .map(|query| tokio::spawn(search(state.clone(), query)));
+
+    let mut results = Vec::new();
+    for handle in futures::future::join_all(handles).await {
+        match handle {
+            Ok(result) => results.push(result?),
+            Err(err) => return Err(Error::Custom(err.to_string())),
+        }
+    }
+
+    Ok(results)
+}
+
 #[instrument(skip(state))]
 pub async fn crawl_stats(state: AppState) -> Result<CrawlStats, Error> {
     let queue_stats = crawl_queue::queue_stats(&state.db).await;
@@ -186,6 +476,98 @@ pub async fn crawl_stats(state: AppState) -> Result<CrawlStats, Error> {
     Ok(CrawlStats { by_domain })
 }
 
+/// How stale the documents from each lens & connection are, compared to how
+/// often they should be recrawled.
+#[instrument(skip(state))]
+pub async fn freshness_report(state: AppState) -> Result<FreshnessReport, Error> {
+    let targets = &state.user_settings.recrawl_targets;
+    let lenses = indexed_document::lens_freshness(&state.db, targets.lens_days)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?;
+    let connections = indexed_document::connection_freshness(&state.db, targets.connection_days)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?;
+
+    let lenses = lenses
+        .into_iter()
+        .map(|stats| (stats, FreshnessSource::Lens, targets.lens_days));
+    let connections = connections
+        .into_iter()
+        .map(|stats| (stats, FreshnessSource::Connection, targets.connection_days));
+
+    let mut sources = lenses
+        .chain(connections)
+        .map(|(stats, source, target_days)| SourceFreshness {
+            name: stats.source,
+            source,
+            num_docs: stats.num_docs as u64,
+            num_stale: stats.num_stale as u64,
+            percent_stale: if stats.num_docs > 0 {
+                stats.num_stale as f32 / stats.num_docs as f32 * 100.0
+            } else {
+                0.0
+            },
+            recrawl_target_days: target_days,
+            oldest_updated_at: stats.oldest_updated_at.map(|at| at.to_rfc3339()),
+            avg_age_secs: stats.avg_age_secs.unwrap_or_default().max(0.0) as u64,
+        })
+        .collect::<Vec<SourceFreshness>>();
+
+    sources.sort_by(|a, b| b.percent_stale.total_cmp(&a.percent_stale));
+    Ok(FreshnessReport { sources })
+}
+
+/// Back up the search index & database, see `libspyglass::backup`.
+#[instrument(skip(state))]
+pub async fn create_backup(state: AppState, path: String) -> Result<BackupResult, Error> {
+    let dest = Path::new(&path);
+    if !dest.is_absolute() {
+        return Err(Error::Custom("Backup path must be absolute".into()));
+    }
+
+    let manifest = backup::create_backup(&state, dest)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to create backup: {}", err)))?;
+
+    Ok(BackupResult {
+        path,
+        created_at: manifest.created_at.to_rfc3339(),
+        num_docs: manifest.num_docs,
+    })
+}
+
+/// Create a new, empty collection
+#[instrument(skip(state))]
+pub async fn create_collection(
+    state: AppState,
+    param: request::CollectionParam,
+) -> Result<(), Error> {
+    let name = param.name.trim();
+    if name.is_empty() {
+        return Err(Error::Custom("Collection name can't be empty".into()));
+    }
+
+    let new_collection = collection::ActiveModel {
+        name: Set(name.to_string()),
+        description: Set(param.description),
+        ..Default::default()
+    };
+
+    match new_collection.insert(&state.db).await {
+        Ok(_) => Ok(()),
+        Err(err) => Err(Error::Custom(err.to_string())),
+    }
+}
+
+/// Remove a collection. Documents in the collection stay in the index.
+#[instrument(skip(state))]
+pub async fn delete_collection(state: AppState, name: String) -> Result<(), Error> {
+    let collection = find_collection(&state, &name).await?;
+    collection::delete(&state.db, collection)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))
+}
+
 /// Remove a doc from the index
 #[instrument(skip(state))]
 pub async fn delete_doc(state: AppState, id: String) -> Result<(), Error> {
@@ -197,6 +579,30 @@ pub async fn delete_doc(state: AppState, id: String) -> Result<(), Error> {
     Ok(())
 }
 
+/// Remove a note from its document
+#[instrument(skip(state))]
+pub async fn delete_note(state: AppState, id: i64) -> Result<(), Error> {
+    let (note, doc) = find_note(&state, id).await?;
+
+    if let Ok(mut writer) = state.index.writer.lock() {
+        let _ = Searcher::remove_from_index(&mut writer, &note_doc_id(&doc.doc_id, note.id));
+    }
+    let _ = Searcher::save(&state).await;
+
+    note.delete(&state.db)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?;
+    Ok(())
+}
+
+/// Stop notifying the user about a saved search
+#[instrument(skip(state))]
+pub async fn delete_saved_search(state: AppState, name: String) -> Result<(), Error> {
+    saved_search::delete(&state.db, &name)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))
+}
+
 /// Remove a domain from crawl queue & index
 #[instrument(skip(state))]
 pub async fn delete_domain(state: AppState, domain: String) -> Result<(), Error> {
@@ -237,108 +643,753 @@ pub async fn delete_domain(state: AppState, domain: String) -> Result<(), Error>
     Ok(())
 }
 
+/// Content of `doc` as of `version`, from the archive if it's still there,
+/// otherwise from the copy kept in the index.
+async fn version_content(
+    state: &AppState,
+    doc: &indexed_document::Model,
+    version: &document_version::Model,
+) -> Option<String> {
+    if let Some(hash) = &version.content_hash {
+        if let Ok(Some(data)) = state.archive.get(hash).await {
+            return Some(String::from_utf8_lossy(&data).into_owned());
+        }
+    }
+
+    let index_id = match version.superseded_at {
+        Some(_) => version_doc_id(&doc.doc_id, &version.crawled_at),
+        None => doc.doc_id.clone(),
+    };
+
+    let fields = DocFields::as_fields();
+    Searcher::get_by_id(&state.index.reader, &index_id).and_then(|retrieved| {
+        retrieved
+            .get_first(fields.content)
+            .and_then(|value| value.as_text())
+            .map(|value| value.to_string())
+    })
+}
+
+/// Diff the content of a document between the versions that were current @
+/// the `from` & `to` timestamps.
 #[instrument(skip(state))]
-pub async fn list_connections(state: AppState) -> Result<ListConnectionResult, Error> {
-    match connection::Entity::find().all(&state.db).await {
-        Ok(enabled) => {
-            // TODO: Move this into a config / db table?
-            let all_conns = oauth::supported_connections();
-            let supported = all_conns
-                .values()
-                .cloned()
-                .collect::<Vec<SupportedConnection>>();
+pub async fn diff_versions(
+    state: AppState,
+    doc_id: String,
+    from: String,
+    to: String,
+) -> Result<VersionDiff, Error> {
+    let doc = find_indexed_doc(&state, &doc_id).await?;
+
+    let mut versions = Vec::new();
+    for timestamp in [&from, &to] {
+        let parsed = parse_as_of(timestamp)
+            .ok_or_else(|| Error::Custom(format!("Invalid timestamp: {}", timestamp)))?;
+        let version = document_version::as_of(&state.db, &doc.url, parsed)
+            .await
+            .map_err(|err| Error::Custom(err.to_string()))?
+            .ok_or_else(|| {
+                Error::Custom(format!("No version of {} as of {}", doc_id, timestamp))
+            })?;
+        let content = version_content(&state, &doc, &version)
+            .await
+            .ok_or_else(|| {
+                Error::Custom(format!(
+                    "Content as of {} is no longer available",
+                    timestamp
+                ))
+            })?;
+
+        versions.push((version_timestamp(&version.crawled_at), content));
+    }
 
-            // Get list of enabled connections
-            let user_connections = enabled
-                .iter()
-                .map(|conn| UserConnection {
-                    id: conn.api_id.clone(),
-                    account: conn.account.clone(),
-                })
-                .collect::<Vec<UserConnection>>();
+    let (to, after) = versions.remove(1);
+    let (from, before) = versions.remove(0);
+    Ok(VersionDiff {
+        doc_id,
+        from,
+        to,
+        changes: diff_text(&before, &after),
+    })
+}
 
-            Ok(ListConnectionResult {
-                supported,
-                user_connections,
-            })
-        }
+/// Fetch a cached image from the archive as a data URI. Empty blobs are
+/// placeholders for images that couldn't be fetched.
+async fn cached_image(state: &AppState, key: &str) -> Result<Option<String>, Error> {
+    match state.archive.get(key).await {
+        Ok(Some(data)) if !data.is_empty() => Ok(Some(images::to_data_uri(&data))),
+        Ok(_) => Ok(None),
         Err(err) => Err(Error::Custom(err.to_string())),
     }
 }
 
-/// List of installed lenses
+/// Stored content of a document, or one of its sections, from `range.start`
+/// to `range.end` so clients can preview it.
 #[instrument(skip(state))]
-pub async fn list_installed_lenses(state: AppState) -> Result<Vec<LensResult>, Error> {
-    let mut lenses: Vec<LensResult> = state
-        .lenses
-        .iter()
-        .map(|lens| LensResult {
-            author: lens.author.clone(),
-            title: lens.name.clone(),
-            description: lens.description.clone().unwrap_or_else(|| "".into()),
-            hash: lens.hash.clone(),
-            file_path: Some(lens.file_path.clone()),
-            ..Default::default()
-        })
-        .collect();
+pub async fn get_doc_content(
+    state: AppState,
+    doc_id: String,
+    range: request::ContentRangeParam,
+) -> Result<DocContent, Error> {
+    let fields = DocFields::as_fields();
+    let retrieved = Searcher::get_by_id(&state.index.reader, &doc_id)
+        .ok_or_else(|| Error::Custom(format!("Unknown document: {}", doc_id)))?;
+    let stored_text = |field: Field| {
+        retrieved
+            .get_first(field)
+            .and_then(|value| value.as_text())
+            .unwrap_or_default()
+            .to_string()
+    };
 
-    lenses.sort_by(|x, y| x.title.cmp(&y.title));
+    let sections = preview::sections(&state.index.reader, &doc_id)
+        .map_err(|err| Error::Custom(err.to_string()))?;
+    let content = match &range.section {
+        Some(anchor) => sections
+            .iter()
+            .find(|section| &section.anchor == anchor)
+            .map(|section| section.content.clone())
+            .ok_or_else(|| Error::Custom(format!("Unknown section: {}", anchor)))?,
+        None => {
+            let parts = preview::parts(&state.index.reader, &doc_id)
+                .map_err(|err| Error::Custom(err.to_string()))?;
+            let mut content = stored_text(fields.content);
+            content.extend(parts);
+            content
+        }
+    };
 
-    Ok(lenses)
+    let bytes = preview::byte_range(
+        &content,
+        range.start.map(|start| start as usize),
+        range.end.map(|end| end as usize),
+    );
+
+    Ok(DocContent {
+        doc_id,
+        title: stored_text(fields.title),
+        url: stored_text(fields.url),
+        section: range.section,
+        content: content[bytes.clone()].to_string(),
+        start: bytes.start as u64,
+        end: bytes.end as u64,
+        total_bytes: content.len() as u64,
+        sections: sections
+            .into_iter()
+            .map(|section| DocSection {
+                num_bytes: section.content.len() as u64,
+                anchor: section.anchor,
+                heading: section.heading,
+            })
+            .collect(),
+    })
 }
 
-pub async fn list_plugins(state: AppState) -> Result<Vec<PluginResult>, Error> {
-    let mut plugins = Vec::new();
-    let result = lens::Entity::find()
-        .filter(lens::Column::LensType.eq(LensType::Plugin))
-        .all(&state.db)
-        .await;
+/// Favicon for a domain, if we've cached one.
+#[instrument(skip(state))]
+pub async fn get_favicon(state: AppState, domain: String) -> Result<Option<String>, Error> {
+    cached_image(&state, &images::favicon_key(&domain)).await
+}
 
-    if let Ok(results) = result {
-        for plugin in results {
-            plugins.push(PluginResult {
-                author: plugin.author,
-                title: plugin.name,
-                description: plugin.description.clone().unwrap_or_default(),
-                is_enabled: plugin.is_enabled,
-            });
-        }
-    }
+/// Response the document was last fetched from, if it's a web page that's
+/// been fetched since this was recorded.
+#[instrument(skip(state))]
+pub async fn get_fetch_info(
+    state: AppState,
+    doc_id: String,
+) -> Result<Option<FetchInfoResult>, Error> {
+    let doc = find_indexed_doc(&state, &doc_id).await?;
+    let task = crawl_queue::Entity::find()
+        .filter(crawl_queue::Column::Url.eq(doc.url.clone()))
+        .one(&state.db)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?;
+
+    Ok(task
+        .and_then(|task| task.fetch_info)
+        .map(|info| FetchInfoResult {
+            url: doc.url,
+            status: info.status,
+            final_url: info.final_url,
+            content_type: info.content_type,
+            duration_ms: info.duration_ms,
+            fetched_at: info.fetched_at.to_rfc3339(),
+        }))
+}
 
-    plugins.sort_by(|a, b| a.title.cmp(&b.title));
-    Ok(plugins)
+/// Preview (og:image, etc.) thumbnail for a document, if we've cached one.
+#[instrument(skip(state))]
+pub async fn get_preview_image(state: AppState, doc_id: String) -> Result<Option<String>, Error> {
+    cached_image(&state, &images::preview_key(&doc_id)).await
 }
 
-/// Show the list of URLs in the queue and their status
-#[allow(dead_code)]
+/// Thumbnail for a local image, PDF or video, generated the first time it's
+/// asked for. Files that can't be thumbnailed get an empty blob so we don't
+/// keep trying.
 #[instrument(skip(state))]
-pub async fn list_queue(state: AppState) -> Result<response::ListQueue, Error> {
-    let db = &state.db;
-    let queue = crawl_queue::Entity::find().all(db).await;
+pub async fn get_thumbnail(state: AppState, doc_id: String) -> Result<Option<String>, Error> {
+    let doc = find_indexed_doc(&state, &doc_id).await?;
+    let path = match Url::parse(&doc.url).ok() {
+        Some(url) if url.scheme() == "file" => match url.to_file_path() {
+            Ok(path) => path,
+            Err(_) => return Ok(None),
+        },
+        _ => return Ok(None),
+    };
 
-    match queue {
-        Ok(queue) => Ok(response::ListQueue { queue }),
-        Err(err) => Err(Error::Custom(err.to_string())),
+    if thumbnail::ThumbnailSource::from_path(&path).is_none() || !path.exists() {
+        return Ok(None);
+    }
+
+    let key = thumbnail::thumbnail_key(&doc_id, &path);
+    if !state.archive.contains(&key).await {
+        let data = match thumbnail::generate(&path).await {
+            Ok(data) => data,
+            Err(err) => {
+                log::debug!("Unable to create thumbnail for {}: {}", path.display(), err);
+                Vec::new()
+            }
+        };
+
+        if let Err(err) = state.archive.put(&key, data.into()).await {
+            log::warn!("Unable to cache thumbnail for {}: {}", doc_id, err);
+        }
     }
+
+    cached_image(&state, &key).await
 }
 
+/// How long to wait for a crawl to finish & how often to check on it.
+const INDEX_URL_TIMEOUT: Duration = Duration::from_secs(60);
+const INDEX_URL_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Crawl `url` right away, bypassing the lens & domain filters, optionally
+/// adding it to `lens`. When `wait` is set, returns the doc_id of the page
+/// once it's indexed, or None if that takes too long.
 #[instrument(skip(state))]
-pub async fn recrawl_domain(state: AppState, domain: String) -> Result<(), Error> {
-    log::info!("handling recrawl domain: {}", domain);
-    let db = &state.db;
+pub async fn index_url(
+    state: AppState,
+    url: String,
+    lens: Option<String>,
+    wait: bool,
+) -> Result<Option<String>, Error> {
+    let parsed = Url::parse(&url).map_err(|err| Error::Custom(err.to_string()))?;
+
+    let mut pipeline = None;
+    let mut tags = Vec::new();
+    if let Some(name) = lens {
+        let lens = state
+            .lenses
+            .get(&name)
+            .ok_or_else(|| Error::Custom(format!("Unknown lens: {}", name)))?;
+        pipeline = lens.pipeline.clone();
+        tags.push((tag::TagType::Lens, lens.name.clone()));
+    }
 
-    let _ = fetch_history::Entity::delete_many()
-        .filter(fetch_history::Column::Domain.eq(domain.clone()))
-        .exec(db)
-        .await;
+    // Otherwise the crawl is skipped if the page was fetched recently.
+    if parsed.host_str().is_some() {
+        fetch_history::delete_by_url(&state.db, &parsed)
+            .await
+            .map_err(|err| Error::Custom(err.to_string()))?;
+    }
 
-    // Handle cases where we incorrectly stored the web.archive.org URL in the fetch_history
-    let _ = fetch_history::Entity::delete_many()
-        .filter(fetch_history::Column::Path.contains(&domain))
-        .exec(db)
-        .await;
+    let task = crawl_queue::force_enqueue(&state.db, &url, &state.user_settings, pipeline, &tags)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?
+        .ok_or_else(|| Error::Custom(format!("Unable to crawl {}", url)))?;
+    let _ = state.schedule_work(ManagerCommand::CheckForJobs).await;
 
-    let res = crawl_queue::Entity::update_many()
+    if !wait {
+        return Ok(None);
+    }
+
+    wait_for_crawl(&state, &task).await
+}
+
+/// Wait for `task` to be crawled, returning the doc_id of the indexed page or
+/// None if that takes too long.
+async fn wait_for_crawl(
+    state: &AppState,
+    task: &crawl_queue::Model,
+) -> Result<Option<String>, Error> {
+    let started = Instant::now();
+    while started.elapsed() < INDEX_URL_TIMEOUT {
+        tokio::time::sleep(INDEX_URL_POLL_INTERVAL).await;
+        let current = crawl_queue::Entity::find_by_id(task.id)
+            .one(&state.db)
+            .await
+            .map_err(|err| Error::Custom(err.to_string()))?;
+
+        match current {
+            Some(current) if current.status == CrawlStatus::Failed => {
+                let reason = current
+                    .error
+                    .map(|err| err.msg)
+                    .unwrap_or_else(|| "unknown error".to_string());
+                return Err(Error::Custom(format!(
+                    "Unable to crawl {}: {}",
+                    task.url, reason
+                )));
+            }
+            Some(current)
+                if matches!(
+                    current.status,
+                    CrawlStatus::Queued | CrawlStatus::Processing
+                ) =>
+            {
+                continue
+            }
+            // Done, skipped, or removed after being crawled under a different URL
+            _ => {
+                let doc = indexed_document::Entity::find()
+                    .filter(indexed_document::Column::Url.eq(task.url.clone()))
+                    .one(&state.db)
+                    .await
+                    .map_err(|err| Error::Custom(err.to_string()))?;
+                return Ok(doc.map(|doc| doc.doc_id));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Download a plugin from the registry (by name) or a URL to its archive,
+/// verify it & start it up.
+#[instrument(skip(state))]
+pub async fn install_plugin(
+    state: AppState,
+    url_or_name: String,
+    sha256: Option<String>,
+) -> Result<PluginResult, Error> {
+    let cmd_tx = state
+        .plugin_cmd_tx
+        .lock()
+        .await
+        .clone()
+        .ok_or_else(|| Error::Custom("Plugin manager isn't running".into()))?;
+
+    let config = Config {
+        lenses: HashMap::new(),
+        pipelines: HashMap::new(),
+        user_settings: state.user_settings.clone(),
+    };
+    let mut plugin = install::install(&config, &url_or_name, sha256.as_deref())
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to install plugin: {}", err)))?;
+
+    // Settings from an earlier install of the plugin still apply.
+    if let Some(overrides) = state.user_settings.plugin_settings.get(&plugin.name) {
+        for (key, value) in plugin.user_settings.iter_mut() {
+            if let Some(user_value) = overrides.get(key) {
+                value.value = user_value.to_string();
+            }
+        }
+    }
+
+    plugin_register(&state, plugin.clone(), &cmd_tx).await;
+
+    let is_enabled = lens::Entity::find()
+        .filter(lens::Column::Name.eq(plugin.name.clone()))
+        .one(&state.db)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?
+        .map_or(plugin.is_enabled, |lens| lens.is_enabled);
+
+    Ok(PluginResult {
+        author: plugin.author,
+        title: plugin.name,
+        description: plugin.description,
+        is_enabled,
+        tasks: Vec::new(),
+    })
+}
+
+/// Lens pages shared from bookmarklets & share sheets are saved to.
+pub const SAVED_LENS: &str = "Saved";
+
+/// Save a page shared from a bookmarklet or share sheet to the "Saved" lens.
+/// Text the user had selected is kept as a highlight on the page once it's
+/// been crawled.
+#[instrument(skip(state, text))]
+pub async fn save_shared_page(
+    state: AppState,
+    url: String,
+    text: Option<String>,
+) -> Result<(), Error> {
+    Url::parse(&url).map_err(|err| Error::Custom(err.to_string()))?;
+
+    let tags = [(tag::TagType::Lens, SAVED_LENS.to_string())];
+    let task = crawl_queue::force_enqueue(&state.db, &url, &state.user_settings, None, &tags)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?
+        .ok_or_else(|| Error::Custom(format!("Unable to crawl {}", url)))?;
+    let _ = state.schedule_work(ManagerCommand::CheckForJobs).await;
+
+    let text = match text.filter(|text| !text.trim().is_empty()) {
+        Some(text) => text,
+        None => return Ok(()),
+    };
+
+    tokio::spawn(async move {
+        let doc_id = match wait_for_crawl(&state, &task).await {
+            Ok(Some(doc_id)) => doc_id,
+            Ok(None) => {
+                log::warn!("Gave up waiting to highlight <{}>", task.url);
+                return;
+            }
+            Err(err) => {
+                log::warn!("Unable to highlight <{}>: {}", task.url, err);
+                return;
+            }
+        };
+
+        let note = request::NoteParam {
+            content: String::new(),
+            highlight: Some(text),
+        };
+        if let Err(err) = add_note(state, doc_id, note).await {
+            log::error!("Unable to highlight <{}>: {}", task.url, err);
+        }
+    });
+
+    Ok(())
+}
+
+/// Check a sample of the URLs in a lens' sitemap(s) to see how much of the
+/// site is indexed & why the rest isn't
+#[instrument(skip(state))]
+pub async fn lens_coverage(state: AppState, name: String) -> Result<LensCoverage, Error> {
+    let lens = state
+        .lenses
+        .get(&name)
+        .map(|lens| lens.value().clone())
+        .ok_or_else(|| Error::Custom(format!("Unknown lens: {}", name)))?;
+
+    coverage::check(&state, &lens)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))
+}
+
+/// List the user's collections
+#[instrument(skip(state))]
+pub async fn list_collections(state: AppState) -> Result<Vec<CollectionResult>, Error> {
+    let collections = collection::Entity::find()
+        .order_by_asc(collection::Column::Name)
+        .all(&state.db)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?;
+
+    let mut results = Vec::new();
+    for collection in collections {
+        let num_docs = collection
+            .find_related(indexed_document::Entity)
+            .count(&state.db)
+            .await
+            .unwrap_or_default();
+
+        results.push(CollectionResult {
+            name: collection.name,
+            description: collection.description,
+            num_docs: num_docs as u64,
+        });
+    }
+
+    Ok(results)
+}
+
+#[instrument(skip(state))]
+pub async fn list_connections(state: AppState) -> Result<ListConnectionResult, Error> {
+    match connection::Entity::find().all(&state.db).await {
+        Ok(enabled) => {
+            // TODO: Move this into a config / db table?
+            let all_conns = oauth::supported_connections();
+            let supported = all_conns
+                .values()
+                .cloned()
+                .collect::<Vec<SupportedConnection>>();
+
+            // Get list of enabled connections
+            let user_connections = enabled
+                .iter()
+                .map(|conn| {
+                    let quota = state.user_settings.connection_quotas.get(&conn.api_id);
+                    let (num_requests, num_bytes) = conn.usage_today();
+                    UserConnection {
+                        id: conn.api_id.clone(),
+                        account: conn.account.clone(),
+                        quota: QuotaStatus {
+                            num_requests: num_requests as u64,
+                            requests_per_day: quota.and_then(|quota| quota.requests_per_day),
+                            num_bytes: num_bytes as u64,
+                            bytes_per_day: quota.and_then(|quota| quota.bytes_per_day),
+                            paused_until: conn
+                                .paused_until
+                                .filter(|_| conn.is_paused())
+                                .map(|until| until.to_rfc3339()),
+                        },
+                    }
+                })
+                .collect::<Vec<UserConnection>>();
+
+            Ok(ListConnectionResult {
+                supported,
+                user_connections,
+            })
+        }
+        Err(err) => Err(Error::Custom(err.to_string())),
+    }
+}
+
+/// List of installed lenses
+#[instrument(skip(state))]
+pub async fn list_installed_lenses(state: AppState) -> Result<Vec<LensResult>, Error> {
+    let mut lenses: Vec<LensResult> = state
+        .lenses
+        .iter()
+        .map(|lens| LensResult {
+            author: lens.author.clone(),
+            title: lens.name.clone(),
+            description: lens.description.clone().unwrap_or_else(|| "".into()),
+            hash: lens.hash.clone(),
+            file_path: Some(lens.file_path.clone()),
+            ..Default::default()
+        })
+        .collect();
+
+    lenses.sort_by(|x, y| x.title.cmp(&y.title));
+
+    Ok(lenses)
+}
+
+/// Max number of failed crawls returned by `list_failed_crawls`
+const MAX_FAILED_CRAWLS: u64 = 100;
+
+/// Most recent crawl failures, w/ a typed reason so clients can suggest a fix.
+#[instrument(skip(state))]
+pub async fn list_failed_crawls(
+    state: AppState,
+    domain: Option<String>,
+) -> Result<Vec<FailedCrawl>, Error> {
+    let failed = crawl_queue::list_failed(&state.db, domain.as_deref(), MAX_FAILED_CRAWLS)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?;
+
+    Ok(failed
+        .into_iter()
+        .map(|task| FailedCrawl {
+            url: task.url,
+            domain: task.domain,
+            error_type: task.error.as_ref().map(|err| err.error_type.to_value()),
+            error_msg: task.error.map(|err| err.msg),
+            num_retries: task.num_retries,
+            failed_at: task.updated_at.to_rfc3339(),
+        })
+        .collect())
+}
+
+/// List starred documents, e.g. for a favorites sidebar
+#[instrument(skip(state))]
+pub async fn list_favorites(state: AppState) -> Result<Vec<SearchResult>, Error> {
+    let favorites = indexed_document::find_by_tag(&state.db, &favorite_tag())
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?;
+
+    let mut results = Vec::new();
+    let mut vaults = VaultCache::default();
+    for indexed in favorites {
+        if let Some(retrieved) = Searcher::get_by_id(&state.index.reader, &indexed.doc_id) {
+            results.push(search_result(&state, &retrieved, indexed, &mut vaults).await);
+        }
+    }
+
+    Ok(results)
+}
+
+/// List the notes attached to a document
+#[instrument(skip(state))]
+pub async fn list_notes(state: AppState, doc_id: String) -> Result<Vec<NoteResult>, Error> {
+    let doc = find_indexed_doc(&state, &doc_id).await?;
+    let notes = document_note::for_document(&state.db, doc.id)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?;
+
+    Ok(notes.into_iter().map(note_result).collect())
+}
+
+pub async fn list_plugins(state: AppState) -> Result<Vec<PluginResult>, Error> {
+    let mut plugins = Vec::new();
+    let result = lens::Entity::find()
+        .filter(lens::Column::LensType.eq(LensType::Plugin))
+        .all(&state.db)
+        .await;
+
+    if let Ok(results) = result {
+        for plugin in results {
+            plugins.push(PluginResult {
+                author: plugin.author,
+                title: plugin.name.clone(),
+                description: plugin.description.clone().unwrap_or_default(),
+                is_enabled: plugin.is_enabled,
+                tasks: state
+                    .plugin_progress
+                    .get(&plugin.name)
+                    .map(|tasks| tasks.clone())
+                    .unwrap_or_default(),
+            });
+        }
+    }
+
+    plugins.sort_by(|a, b| a.title.cmp(&b.title));
+    Ok(plugins)
+}
+
+/// Plugin log lines returned when the number isn't given.
+const DEFAULT_PLUGIN_LOG_LINES: usize = 100;
+
+/// The last `num` lines a plugin logged or printed, along w/ the errors the
+/// host ran into calling it, oldest first.
+#[instrument(skip(state))]
+pub async fn plugin_logs(
+    state: AppState,
+    name: String,
+    num: Option<u32>,
+) -> Result<Vec<PluginLogEntry>, Error> {
+    let is_loaded = state
+        .plugin_manager
+        .lock()
+        .await
+        .find_by_name(name.clone())
+        .is_some();
+    if !is_loaded && !state.plugin_logs.contains_key(&name) {
+        return Err(Error::Custom(format!("Unknown plugin: {}", name)));
+    }
+
+    let num = num
+        .map_or(DEFAULT_PLUGIN_LOG_LINES, |num| num as usize)
+        .min(logs::MAX_LOG_LINES);
+    Ok(state
+        .plugin_logs
+        .get(&name)
+        .map(|entries| entries.tail(num))
+        .unwrap_or_default())
+}
+
+/// Show the list of URLs in the queue and their status
+#[allow(dead_code)]
+#[instrument(skip(state))]
+pub async fn list_queue(state: AppState) -> Result<response::ListQueue, Error> {
+    let db = &state.db;
+    let queue = crawl_queue::Entity::find().all(db).await;
+
+    match queue {
+        Ok(queue) => Ok(response::ListQueue { queue }),
+        Err(err) => Err(Error::Custom(err.to_string())),
+    }
+}
+
+#[instrument(skip(state))]
+pub async fn list_saved_searches(state: AppState) -> Result<Vec<SavedSearch>, Error> {
+    let searches = saved_search::list(&state.db)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?;
+
+    Ok(searches.into_iter().map(saved_search_result).collect())
+}
+
+/// Crawls of a document where its content changed, newest first
+#[instrument(skip(state))]
+pub async fn list_versions(state: AppState, doc_id: String) -> Result<Vec<VersionResult>, Error> {
+    let doc = find_indexed_doc(&state, &doc_id).await?;
+    let versions = document_version::history(&state.db, &doc.url)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?;
+
+    Ok(versions
+        .iter()
+        .map(|version| VersionResult {
+            crawled_at: version_timestamp(&version.crawled_at),
+            superseded_at: version.superseded_at.as_ref().map(version_timestamp),
+        })
+        .collect())
+}
+
+/// List the pages being watched for changes
+#[instrument(skip(state))]
+pub async fn list_watched_pages(state: AppState) -> Result<Vec<WatchedPage>, Error> {
+    let pages = watched_page::Entity::find()
+        .order_by_asc(watched_page::Column::Url)
+        .all(&state.db)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?;
+
+    Ok(pages.into_iter().map(watched_page_result).collect())
+}
+
+/// Whether `url` has been indexed or is waiting to be crawled.
+#[instrument(skip(state))]
+pub async fn page_status(state: AppState, url: String) -> Result<PageStatus, Error> {
+    let doc = indexed_document::Entity::find()
+        .filter(indexed_document::Column::Url.eq(url.clone()))
+        .one(&state.db)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?;
+
+    let task = crawl_queue::Entity::find()
+        .filter(crawl_queue::Column::Url.eq(url.clone()))
+        .one(&state.db)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?;
+    let is_queued = matches!(
+        task.map(|task| task.status),
+        Some(CrawlStatus::Queued | CrawlStatus::Processing)
+    );
+
+    Ok(PageStatus {
+        url,
+        indexed_at: doc.as_ref().map(|doc| doc.updated_at.to_rfc3339()),
+        doc_id: doc.map(|doc| doc.doc_id),
+        is_queued,
+    })
+}
+
+/// Pin a document to the top of the results for `query`
+#[instrument(skip(state))]
+pub async fn pin_result(state: AppState, query: String, doc_id: String) -> Result<(), Error> {
+    let doc = find_indexed_doc(&state, &doc_id).await?;
+    pinned_result::pin(&state.db, &query, &doc)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))
+}
+
+#[instrument(skip(state))]
+pub async fn prune_crawl_queue(state: AppState, older_than_days: u32) -> Result<u64, Error> {
+    let cutoff = chrono::Utc::now() - chrono::Duration::days(older_than_days.into());
+    let removed = crawl_queue::remove_completed(&state.db, cutoff)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?;
+
+    log::info!("pruned {} completed tasks from the crawl queue", removed);
+    Ok(removed)
+}
+
+#[instrument(skip(state))]
+pub async fn recrawl_domain(state: AppState, domain: String) -> Result<(), Error> {
+    log::info!("handling recrawl domain: {}", domain);
+    let db = &state.db;
+
+    let _ = fetch_history::Entity::delete_many()
+        .filter(fetch_history::Column::Domain.eq(domain.clone()))
+        .exec(db)
+        .await;
+
+    // Handle cases where we incorrectly stored the web.archive.org URL in the fetch_history
+    let _ = fetch_history::Entity::delete_many()
+        .filter(fetch_history::Column::Path.contains(&domain))
+        .exec(db)
+        .await;
+
+    let res = crawl_queue::Entity::update_many()
         .col_expr(
             crawl_queue::Column::Status,
             sea_query::Expr::value(CrawlStatus::Queued),
@@ -355,6 +1406,312 @@ pub async fn recrawl_domain(state: AppState, domain: String) -> Result<(), Error
     Ok(())
 }
 
+/// Note that the user opened a document, used to rank sources they still use
+/// & the documents they open most often.
+#[instrument(skip(state))]
+pub async fn record_open(state: AppState, doc_id: String) -> Result<(), Error> {
+    indexed_document::mark_opened(&state.db, &doc_id)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?;
+
+    if state.user_settings.disable_personalization {
+        return Ok(());
+    }
+
+    search_click::record(&state.db, &doc_id)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?;
+    let too_old = chrono::Utc::now() - chrono::Duration::days(clicks::MAX_CLICK_AGE_DAYS);
+    if let Err(err) = search_click::prune(&state.db, too_old).await {
+        log::warn!("Unable to prune old search clicks: {}", err);
+    }
+
+    Ok(())
+}
+
+/// Refetch, parse & index a document right away, by doc_id or URL, returning
+/// the parse output to see why a page indexes badly
+#[instrument(skip(state))]
+pub async fn reindex_doc(state: AppState, id: String) -> Result<ReindexResult, Error> {
+    let indexed = indexed_document::Entity::find()
+        .filter(indexed_document::Column::DocId.eq(id.clone()))
+        .one(&state.db)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?;
+    let url = indexed.map(|doc| doc.url).unwrap_or_else(|| id.clone());
+    let parsed =
+        Url::parse(&url).map_err(|_| Error::Custom(format!("Unknown document: {}", id)))?;
+
+    let reindexed = reindex_url(&state, &parsed)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to index {}: {}", url, err)))?;
+    let crawl = reindexed.crawl_result;
+
+    let doc_id = indexed_document::Entity::find()
+        .filter(indexed_document::Column::Url.eq(crawl.url.clone()))
+        .one(&state.db)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?
+        .map(|doc| doc.doc_id);
+
+    let (skipped_by, redacted) = match reindexed.verdict {
+        ContentVerdict::Skip(rule) => (Some(rule), Vec::new()),
+        ContentVerdict::Index(redacted) => (None, redacted),
+    };
+
+    let content = crawl.content.unwrap_or_default();
+    let bytes = preview::byte_range(&content, None, None);
+    let mut links = crawl.links.into_iter().collect::<Vec<_>>();
+    links.sort();
+
+    Ok(ReindexResult {
+        doc_id,
+        url: crawl.url,
+        status: format!("{:?}", reindexed.result),
+        title: crawl.title,
+        description: crawl.description,
+        content: content[bytes].to_string(),
+        total_bytes: content.len() as u64,
+        content_hash: crawl.content_hash,
+        tags: crawl
+            .tags
+            .into_iter()
+            .map(|(label, value)| (label.as_ref().to_string(), value))
+            .collect(),
+        fields: crawl.fields,
+        numbers: crawl.numbers,
+        sections: crawl
+            .sections
+            .into_iter()
+            .map(|section| DocSection {
+                num_bytes: section.content.len() as u64,
+                anchor: section.anchor,
+                heading: section.heading,
+            })
+            .collect(),
+        num_code_blocks: crawl.code.len() as u32,
+        links,
+        document_date: crawl.document_date.map(|date| date.to_rfc3339()),
+        skipped_by,
+        redacted,
+    })
+}
+
+/// Remove a document from a collection
+#[instrument(skip(state))]
+pub async fn remove_from_collection(
+    state: AppState,
+    name: String,
+    doc_id: String,
+) -> Result<(), Error> {
+    let collection = find_collection(&state, &name).await?;
+    let doc = find_indexed_doc(&state, &doc_id).await?;
+
+    collection::remove_document(&state.db, &collection, &doc)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))
+}
+
+/// Run the standardized benchmark against a scratch index & database.
+#[instrument]
+pub async fn run_benchmark(
+    num_docs: Option<u32>,
+    num_queries: Option<u32>,
+) -> Result<BenchmarkResult, Error> {
+    benchmark::run(
+        num_docs.map_or(benchmark::DEFAULT_NUM_DOCS, |num| num as usize),
+        num_queries.map_or(benchmark::DEFAULT_NUM_QUERIES, |num| num as usize),
+    )
+    .await
+    .map_err(|err| Error::Custom(err.to_string()))
+}
+
+/// Save a search to notify the user about new documents that match it
+#[instrument(skip(state))]
+pub async fn save_search(
+    state: AppState,
+    search: request::SavedSearchParam,
+) -> Result<SavedSearch, Error> {
+    let name = search.name.trim();
+    if name.is_empty() || search.query.trim().is_empty() {
+        return Err(Error::Custom(
+            "Saved searches need a name & query".to_string(),
+        ));
+    }
+
+    if let Some(unknown) = search
+        .lenses
+        .iter()
+        .find(|lens| !state.lenses.contains_key(lens.as_str()))
+    {
+        return Err(Error::Custom(format!("Unknown lens: {}", unknown)));
+    }
+
+    let saved = saved_search::save(&state.db, name, &search.query, &search.lenses)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?;
+
+    Ok(saved_search_result(saved))
+}
+
+/// Point a result URL at a specific section of the document
+fn url_with_anchor(url: &str, anchor: Option<&str>) -> String {
+    match (Url::parse(url), anchor) {
+        (Ok(mut parsed), Some(anchor)) => {
+            parsed.set_fragment(Some(anchor));
+            parsed.to_string()
+        }
+        _ => url.to_string(),
+    }
+}
+
+const MAX_DESCRIPTION_LEN: usize = 256;
+
+/// Shorten a description w/o splitting a multi-byte character.
+fn truncate_description(description: &mut String) {
+    if description.len() > MAX_DESCRIPTION_LEN {
+        let mut end = MAX_DESCRIPTION_LEN;
+        while !description.is_char_boundary(end) {
+            end -= 1;
+        }
+        description.truncate(end);
+    }
+}
+
+/// Build the result for an indexed document. Callers fill in anything specific
+/// to how the document was found, e.g. the score.
+async fn search_result(
+    state: &AppState,
+    retrieved: &Document,
+    indexed: indexed_document::Model,
+    vaults: &mut VaultCache,
+) -> SearchResult {
+    let fields = DocFields::as_fields();
+    let text = |field: Field| {
+        retrieved
+            .get_first(field)
+            .and_then(|value| value.as_text())
+            .unwrap_or_default()
+            .to_string()
+    };
+
+    let crawl_uri = text(fields.url);
+
+    let tags = indexed
+        .find_related(tag::Entity)
+        .all(&state.db)
+        .await
+        .unwrap_or_default()
+        .iter()
+        .map(|tag| (tag.label.as_ref().to_string(), tag.value.clone()))
+        .collect::<Vec<(String, String)>>();
+
+    let doc_fields = retrieved
+        .get_all(fields.fields)
+        .filter_map(|value| value.as_text())
+        .filter_map(|value| value.split_once(':'))
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect::<Vec<(String, String)>>();
+
+    let app_url = state.deep_links.app_url(&crawl_uri, vaults);
+
+    let notes = document_note::for_document(&state.db, indexed.id)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(note_result)
+        .collect();
+
+    let mut description = text(fields.description);
+    truncate_description(&mut description);
+
+    SearchResult {
+        doc_id: indexed.doc_id,
+        domain: text(fields.domain),
+        title: text(fields.title),
+        crawl_uri: crawl_uri.clone(),
+        description,
+        url: indexed.open_url.unwrap_or(crawl_uri),
+        tags,
+        fields: doc_fields,
+        anchor: None,
+        app_url,
+        pinned: false,
+        notes,
+        version: None,
+        snippet: None,
+        score: 0.0,
+    }
+}
+
+/// Synonyms to expand a search w/, unless it's scoped to a lens that has
+/// turned them off.
+fn search_synonyms(state: &AppState, search_req: &request::SearchParam) -> Option<Arc<Synonyms>> {
+    let disabled = state.lenses.iter().any(|entry| {
+        let lens = entry.value();
+        lens.disable_synonyms
+            && (search_req.lens_names.contains(&lens.name)
+                || search_req.lenses.contains(&lens.trigger))
+    });
+
+    if disabled {
+        None
+    } else {
+        Some(state.synonyms.clone())
+    }
+}
+
+/// How searches read queries, from the user's settings.
+fn query_options<'a>(
+    state: &AppState,
+    synonyms: Option<&'a Synonyms>,
+    filter_names: &'a FilterNames,
+) -> QueryOptions<'a> {
+    QueryOptions {
+        fuzzy_distance: state.user_settings.fuzzy_distance,
+        synonyms,
+        date_locale: state.user_settings.date_locale,
+        pinned: None,
+        filter_names: Some(filter_names),
+    }
+}
+
+/// Tag counts for the results of a search, to drill down into them. Only
+/// standard searches have facets.
+#[instrument(skip(state))]
+pub async fn search_facets(
+    state: AppState,
+    search_req: request::SearchParam,
+) -> Result<Vec<SearchFacet>, Error> {
+    if search_req.mode != request::SearchMode::Standard {
+        return Ok(Vec::new());
+    }
+
+    let synonyms = search_synonyms(&state, &search_req);
+    let names = filter_names(&state);
+    let counts = Searcher::tag_counts(
+        &state.db,
+        &state.index,
+        &search_req.query,
+        query_options(&state, synonyms.as_deref(), &names),
+    )
+    .await
+    .map_err(|err| Error::Custom(err.to_string()))?;
+
+    Ok(counts
+        .into_iter()
+        .map(|(label, value, count)| SearchFacet {
+            label,
+            value,
+            count,
+        })
+        .collect())
+}
+
+/// Boost for results from the site a search was made from, see
+/// `SearchParam::context_url`.
+const CONTEXT_DOMAIN_BOOST: f32 = 1.5;
+
 /// Search the user's indexed documents
 #[instrument(skip(state))]
 pub async fn search(
@@ -367,7 +1724,7 @@ pub async fn search(
     let index = &state.index;
     let searcher = index.reader.searcher();
 
-    let applied: Vec<SearchFilter> = futures::stream::iter(search_req.lenses.iter())
+    let mut applied: Vec<SearchFilter> = futures::stream::iter(search_req.lenses.iter())
         .filter_map(|trigger| async {
             let vec = lens_to_filters(state.clone(), trigger).await;
             if vec.is_empty() {
@@ -384,58 +1741,180 @@ pub async fn search(
         .flatten()
         .collect::<Vec<SearchFilter>>();
 
-    let docs =
-        Searcher::search_with_lens(state.db.clone(), &applied, index, &search_req.query).await;
+    // Searches scoped to lenses that no longer exist, or that don't allow any
+    // URLs, shouldn't fall back to searching everything.
+    let scoped = lens_names_to_filters(state.clone(), &search_req.lens_names).await;
+    if !search_req.lens_names.is_empty() && !limits_scope(&scoped) {
+        return Ok(SearchResults {
+            results: Vec::new(),
+            meta: SearchMeta {
+                query: search_req.query,
+                num_docs: searcher.num_docs(),
+                wall_time_ms: 0,
+            },
+        });
+    }
+    applied.extend(scoped);
+
+    let lenses = state
+        .lenses
+        .iter()
+        .map(|entry| entry.value().clone())
+        .collect::<Vec<LensConfig>>();
+    let mut decay = DomainDecay::load(&state.db, &state.user_settings.usage_decay, &lenses).await;
+    if let Some(context_url) = &search_req.context_url {
+        match Url::parse(context_url) {
+            Ok(url) => {
+                if let Some(host) = url.host_str() {
+                    decay.boost_domain(host, CONTEXT_DOMAIN_BOOST);
+                }
+            }
+            Err(err) => log::warn!("Ignoring invalid context url {}: {}", context_url, err),
+        }
+    }
+
+    // Loaded once, to both rank & flag the pinned results.
+    let pinned = pinned_result::doc_ids(&state.db, &search_req.query)
+        .await
+        .unwrap_or_default();
+    let names = filter_names(&state);
+
+    let docs = match search_req.mode {
+        request::SearchMode::Standard => {
+            let synonyms = search_synonyms(&state, &search_req);
+            let clicks = if state.user_settings.disable_personalization {
+                ClickBoosts::default()
+            } else {
+                ClickBoosts::load(&state.db).await
+            };
+            Searcher::search_with_lens(
+                state.db.clone(),
+                &applied,
+                index,
+                &search_req.query,
+                &decay,
+                &clicks,
+                QueryOptions {
+                    pinned: Some(&pinned),
+                    ..query_options(&state, synonyms.as_deref(), &names)
+                },
+            )
+            .await
+        }
+        request::SearchMode::Exact | request::SearchMode::Regex => {
+            let pattern = if search_req.mode == request::SearchMode::Exact {
+                regex::escape(&search_req.query)
+            } else {
+                search_req.query.clone()
+            };
+            let pattern = regex::Regex::new(&pattern)
+                .map_err(|err| Error::Custom(format!("Invalid search pattern: {}", err)))?;
+
+            let index = index.clone();
+            tokio::task::spawn_blocking(move || Searcher::scan(&index, &applied, &pattern))
+                .await
+                .map_err(|err| Error::Custom(err.to_string()))?
+        }
+    };
+    let snippets = match search_req.snippet_length.unwrap_or(DEFAULT_SNIPPET_CHARS) {
+        0 => None,
+        max_chars => Some(Snippets::new(index, &search_req.query, &names, max_chars)),
+    };
+
+    // Documents indexed under variants of the same URL are shown once.
+    let normalizer = UrlNormalizer::new(&lenses);
+    let mut seen_urls = HashSet::new();
 
     let mut results: Vec<SearchResult> = Vec::new();
+    let mut vaults = VaultCache::default();
     for (score, doc_addr) in docs {
         if let Ok(retrieved) = searcher.doc(doc_addr) {
+            // Sections of long documents point back to the full document.
+            let anchor = retrieved
+                .get_first(fields.anchor)
+                .and_then(|value| value.as_text())
+                .map(|value| value.to_string());
+            let parent_id = retrieved
+                .get_first(fields.parent_id)
+                .and_then(|value| value.as_text())
+                .map(|value| value.to_string());
+
+            let (retrieved, section_description, part) = match parent_id {
+                Some(parent_id) => {
+                    // Already have a better match for this document
+                    if let Some(existing) = results.iter_mut().find(|r| r.doc_id == parent_id) {
+                        if existing.anchor.is_none() {
+                            existing.url = url_with_anchor(&existing.url, anchor.as_deref());
+                            existing.anchor = anchor;
+                        }
+                        continue;
+                    }
+
+                    let description = retrieved
+                        .get_first(fields.description)
+                        .and_then(|value| value.as_text())
+                        .map(|value| value.to_string());
+                    // Snippets of oversized documents come from the part that
+                    // matched.
+                    let is_part = retrieved
+                        .get_first(fields.id)
+                        .and_then(|value| value.as_text())
+                        .map_or(false, |id| part_number(id).is_some());
+
+                    match Searcher::get_by_id(&index.reader, &parent_id) {
+                        Some(parent) => (parent, description, is_part.then_some(retrieved)),
+                        None => continue,
+                    }
+                }
+                None => (retrieved, None, None),
+            };
+
+            // Past versions are shown as the document they're a version of.
+            let version_of = retrieved
+                .get_first(fields.version_of)
+                .and_then(|value| value.as_text())
+                .map(|value| value.to_string());
+            let version = version_of.as_ref().and_then(|_| {
+                retrieved
+                    .get_first(fields.crawled_at)
+                    .and_then(|value| value.as_text())
+                    .map(|value| value.to_string())
+            });
+
             let doc_id = retrieved
                 .get_first(fields.id)
                 .expect("Missing doc_id in schema");
-            let domain = retrieved
-                .get_first(fields.domain)
-                .expect("Missing domain in schema");
-            let title = retrieved
-                .get_first(fields.title)
-                .expect("Missing title in schema");
-            let description = retrieved
-                .get_first(fields.description)
-                .expect("Missing description in schema");
-            let url = retrieved
-                .get_first(fields.url)
-                .expect("Missing url in schema");
-
-            if let Some(doc_id) = doc_id.as_text() {
+
+            if let Some(doc_id) = version_of.as_deref().or_else(|| doc_id.as_text()) {
+                // Full document already matched via one of its sections
+                if results.iter().any(|r| r.doc_id == doc_id) {
+                    continue;
+                }
+
                 let indexed = indexed_document::Entity::find()
                     .filter(indexed_document::Column::DocId.eq(doc_id))
                     .one(&state.db)
                     .await;
 
-                let crawl_uri = url.as_text().unwrap_or_default().to_string();
-
                 if let Ok(Some(indexed)) = indexed {
-                    let tags = indexed
-                        .find_related(tag::Entity)
-                        .all(&state.db)
-                        .await
-                        .unwrap_or_default()
-                        .iter()
-                        .map(|tag| (tag.label.as_ref().to_string(), tag.value.clone()))
-                        .collect::<Vec<(String, String)>>();
-
-                    let mut result = SearchResult {
-                        doc_id: doc_id.to_string(),
-                        domain: domain.as_text().unwrap_or_default().to_string(),
-                        title: title.as_text().unwrap_or_default().to_string(),
-                        crawl_uri: crawl_uri.clone(),
-                        description: description.as_text().unwrap_or_default().to_string(),
-                        url: indexed.open_url.unwrap_or(crawl_uri),
-                        tags,
-                        score,
-                    };
-
-                    result.description.truncate(256);
+                    if !seen_urls.insert(normalizer.normalize(&indexed.url)) {
+                        continue;
+                    }
+
+                    let mut result = search_result(&state, &retrieved, indexed, &mut vaults).await;
+                    if let Some(mut section_description) = section_description {
+                        truncate_description(&mut section_description);
+                        result.description = section_description;
+                    }
+                    result.url = url_with_anchor(&result.url, anchor.as_deref());
+                    result.anchor = anchor;
+                    result.pinned = pinned.iter().any(|id| id == doc_id);
+                    result.version = version;
+                    result.snippet = snippets
+                        .as_ref()
+                        .and_then(|snippets| snippets.for_doc(part.as_ref().unwrap_or(&retrieved)));
+                    result.score = score;
+
                     results.push(result);
                 }
             }
@@ -504,17 +1983,52 @@ pub async fn search_lenses(
     }
 }
 
+/// Tag a document as a favorite, boosting it in search results
 #[instrument(skip(state))]
-pub async fn toggle_pause(state: AppState, is_paused: bool) -> Result<(), Error> {
-    // Scope so that the app_state mutex is correctly released.
-    if let Some(sender) = state.pause_cmd_tx.lock().await.as_ref() {
-        let _ = sender.send(if is_paused {
-            AppPause::Pause
-        } else {
-            AppPause::Run
-        });
+pub async fn star_doc(state: AppState, doc_id: String) -> Result<(), Error> {
+    let doc = find_indexed_doc(&state, &doc_id).await?;
+    let model: indexed_document::ActiveModel = doc.clone().into();
+    model
+        .insert_tags(&state.db, &[favorite_tag()])
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?;
+    index_tags(&state, &doc).await
+}
+
+/// Index the page open in the browser from its DOM snapshot rather than
+/// fetching it again, which also covers pages behind a login. Returns the
+/// doc_id of the page, or None if a content rule kept it out of the index.
+#[instrument(skip(state, html))]
+pub async fn submit_page(
+    state: AppState,
+    url: String,
+    html: String,
+) -> Result<Option<String>, Error> {
+    let parsed = Url::parse(&url).map_err(|err| Error::Custom(err.to_string()))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(Error::Custom(format!("{} is not a web page", url)));
     }
 
+    index_snapshot(&state, &parsed, &html)
+        .await
+        .map_err(|err| Error::Custom(format!("Unable to index {}: {}", url, err)))
+}
+
+/// Watched pages that changed since the last time this was called, used to
+/// notify the user.
+#[instrument(skip(state))]
+pub async fn take_watch_alerts(state: AppState) -> Result<Vec<WatchedPage>, Error> {
+    let alerts = watched_page::take_alerts(&state.db)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?;
+
+    Ok(alerts.into_iter().map(watched_page_result).collect())
+}
+
+#[instrument(skip(state))]
+pub async fn toggle_pause(state: AppState, is_paused: bool) -> Result<(), Error> {
+    log::info!("{} crawler", if is_paused { "pausing" } else { "resuming" });
+    state.set_paused(is_paused).await;
     Ok(())
 }
 
@@ -550,3 +2064,153 @@ pub async fn toggle_plugin(state: AppState, name: String) -> Result<(), Error> {
 
     Ok(())
 }
+
+/// Remove a pinned document from the results for `query`
+#[instrument(skip(state))]
+pub async fn unpin_result(state: AppState, query: String, doc_id: String) -> Result<(), Error> {
+    let doc = find_indexed_doc(&state, &doc_id).await?;
+    pinned_result::unpin(&state.db, &query, &doc)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))
+}
+
+#[instrument(skip(state))]
+pub async fn unstar_doc(state: AppState, doc_id: String) -> Result<(), Error> {
+    let doc = find_indexed_doc(&state, &doc_id).await?;
+    let model: indexed_document::ActiveModel = doc.clone().into();
+    model
+        .remove_tags(&state.db, &[favorite_tag()])
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?;
+    index_tags(&state, &doc).await
+}
+
+/// Stop watching a page for changes
+#[instrument(skip(state))]
+pub async fn unwatch_page(state: AppState, url: String) -> Result<(), Error> {
+    watched_page::unwatch(&state.db, &url)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))
+}
+
+/// Rename a collection and/or update its description
+#[instrument(skip(state))]
+pub async fn update_collection(
+    state: AppState,
+    name: String,
+    param: request::CollectionParam,
+) -> Result<(), Error> {
+    let new_name = param.name.trim();
+    if new_name.is_empty() {
+        return Err(Error::Custom("Collection name can't be empty".into()));
+    }
+
+    let mut update: collection::ActiveModel = find_collection(&state, &name).await?.into();
+    update.name = Set(new_name.to_string());
+    update.description = Set(param.description);
+
+    match update.update(&state.db).await {
+        Ok(_) => Ok(()),
+        Err(err) => Err(Error::Custom(err.to_string())),
+    }
+}
+
+/// Update the contents of a note
+#[instrument(skip(state))]
+pub async fn update_note(
+    state: AppState,
+    id: i64,
+    param: request::NoteParam,
+) -> Result<NoteResult, Error> {
+    let (note, doc) = find_note(&state, id).await?;
+
+    let mut update: document_note::ActiveModel = note.into();
+    update.content = Set(param.content);
+    update.highlight = Set(param.highlight);
+    let note = update
+        .update(&state.db)
+        .await
+        .map_err(|err| Error::Custom(err.to_string()))?;
+
+    index_note(&state, &doc.doc_id, &note).await?;
+    Ok(note_result(note))
+}
+
+/// How long a client waits for saved search alerts by default & at most.
+const ALERT_WAIT_DEFAULT: Duration = Duration::from_secs(30);
+const ALERT_WAIT_MAX: Duration = Duration::from_secs(50);
+
+/// Saved searches w/ new matches, waiting up to `timeout_secs` for some if
+/// there aren't any yet so clients can long-poll for them.
+#[instrument(skip(state))]
+pub async fn wait_saved_search_alerts(
+    state: AppState,
+    timeout_secs: Option<u64>,
+) -> Result<Vec<SavedSearchAlert>, Error> {
+    let timeout = timeout_secs
+        .map_or(ALERT_WAIT_DEFAULT, Duration::from_secs)
+        .min(ALERT_WAIT_MAX);
+    let deadline = tokio::time::Instant::now() + timeout;
+
+    let alerts = loop {
+        // Created before checking so matches found in between still wake us up.
+        let notified = state.saved_search_alerts.notified();
+        let alerts = saved_search::take_alerts(&state.db)
+            .await
+            .map_err(|err| Error::Custom(err.to_string()))?;
+
+        if !alerts.is_empty() || tokio::time::Instant::now() >= deadline {
+            break alerts;
+        }
+
+        let _ = tokio::time::timeout_at(deadline, notified).await;
+    };
+
+    let mut results = Vec::new();
+    for search in alerts {
+        let docs = indexed_document::Entity::find()
+            .filter(indexed_document::Column::DocId.is_in(search.new_matches.doc_ids.clone()))
+            .all(&state.db)
+            .await
+            .map_err(|err| Error::Custom(err.to_string()))?;
+
+        let mut matches = Vec::new();
+        let mut vaults = VaultCache::default();
+        for indexed in docs {
+            if let Some(retrieved) = Searcher::get_by_id(&state.index.reader, &indexed.doc_id) {
+                matches.push(search_result(&state, &retrieved, indexed, &mut vaults).await);
+            }
+        }
+
+        results.push(SavedSearchAlert {
+            search: saved_search_result(search),
+            results: matches,
+        });
+    }
+
+    Ok(results)
+}
+
+/// Recrawl a page every `check_interval_mins` & alert the user when its
+/// content changes
+#[instrument(skip(state))]
+pub async fn watch_page(
+    state: AppState,
+    url: String,
+    check_interval_mins: Option<i64>,
+) -> Result<(), Error> {
+    let url = match Url::parse(&url) {
+        Ok(url) if url.scheme() == "http" || url.scheme() == "https" => url,
+        _ => return Err(Error::Custom(format!("Unable to watch {}", url))),
+    };
+
+    watched_page::watch(
+        &state.db,
+        url.as_str(),
+        check_interval_mins.unwrap_or(watched_page::DEFAULT_CHECK_INTERVAL_MINS),
+    )
+    .await
+    .map_err(|err| Error::Custom(err.to_string()))?;
+
+    Ok(())
+}
@@ -0,0 +1,194 @@
+//! Minimal HTTP endpoint for saving pages from bookmarklets & mobile share
+//! sheets, which can't easily make JSON-RPC calls. Pages are POSTed to
+//! `/share` as JSON or a form w/ a `url`, the `text` the user had selected &
+//! the share token, either as a `token` field or a bearer token.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+
+use bytes::Bytes;
+use serde::Deserialize;
+use warp::http::StatusCode;
+use warp::Filter;
+
+use libspyglass::lock::{constant_time_eq, load_or_create_token};
+use libspyglass::state::AppState;
+use shared::config::{Config, ShareEndpoint};
+
+use super::access::bearer_token;
+use super::route;
+
+/// File in the data directory holding the token share requests need.
+pub const SHARE_TOKEN_FILE: &str = "share_token";
+/// Shared pages are a URL & maybe a bit of text, anything bigger isn't one.
+const MAX_BODY_BYTES: u64 = 64 * 1024;
+
+#[derive(Debug, Default, Deserialize, PartialEq, Eq)]
+struct SharedPage {
+    #[serde(default)]
+    url: Option<String>,
+    /// Text the user had selected on the page.
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    token: Option<String>,
+}
+
+impl SharedPage {
+    /// Share sheets on some phones put the link in the shared text rather
+    /// than the URL.
+    fn url(&self) -> Option<String> {
+        self.url.clone().or_else(|| {
+            self.text.as_ref().and_then(|text| {
+                text.split_whitespace()
+                    .find(|word| word.starts_with("https://") || word.starts_with("http://"))
+                    .map(|word| word.to_string())
+            })
+        })
+    }
+}
+
+fn parse_body(content_type: Option<&str>, body: &[u8]) -> Option<SharedPage> {
+    let is_json = content_type
+        .map(|content_type| content_type.starts_with("application/json"))
+        .unwrap_or_default();
+    if is_json {
+        return serde_json::from_slice(body).ok();
+    }
+
+    let mut form: HashMap<String, String> =
+        url::form_urlencoded::parse(body).into_owned().collect();
+    Some(SharedPage {
+        url: form.remove("url"),
+        text: form.remove("text"),
+        token: form.remove("token"),
+    })
+}
+
+fn is_authorized(expected: &str, authorization: Option<&str>, page: &SharedPage) -> bool {
+    let token = bearer_token(authorization).or(page.token.as_deref());
+
+    match token {
+        Some(token) => constant_time_eq(token.trim().as_bytes(), expected.as_bytes()),
+        None => false,
+    }
+}
+
+async fn handle_share(
+    state: AppState,
+    token: &str,
+    authorization: Option<String>,
+    content_type: Option<String>,
+    body: Bytes,
+) -> warp::reply::WithStatus<String> {
+    let reply =
+        |message: &str, status: StatusCode| warp::reply::with_status(message.to_string(), status);
+
+    let page = match parse_body(content_type.as_deref(), &body) {
+        Some(page) => page,
+        None => return reply("Invalid request", StatusCode::BAD_REQUEST),
+    };
+
+    if !is_authorized(token, authorization.as_deref(), &page) {
+        return reply("Invalid token", StatusCode::UNAUTHORIZED);
+    }
+
+    let url = match page.url() {
+        Some(url) => url,
+        None => return reply("Missing url", StatusCode::BAD_REQUEST),
+    };
+
+    match route::save_shared_page(state, url, page.text).await {
+        Ok(()) => reply("Saved!", StatusCode::ACCEPTED),
+        Err(err) => reply(&err.to_string(), StatusCode::BAD_REQUEST),
+    }
+}
+
+/// Start listening for shared pages on the port set in `endpoint`.
+pub async fn start_share_server(
+    state: AppState,
+    config: &Config,
+    endpoint: ShareEndpoint,
+) -> anyhow::Result<SocketAddr> {
+    let token = Arc::new(load_or_create_token(
+        &config.data_dir().join(SHARE_TOKEN_FILE),
+    ));
+    let ip = if endpoint.allow_remote {
+        IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+    } else {
+        IpAddr::V4(Ipv4Addr::LOCALHOST)
+    };
+
+    let route_state = state.clone();
+    let share = warp::post()
+        .and(warp::path("share"))
+        .and(warp::path::end())
+        .and(warp::header::optional::<String>("authorization"))
+        .and(warp::header::optional::<String>("content-type"))
+        .and(warp::body::content_length_limit(MAX_BODY_BYTES))
+        .and(warp::body::bytes())
+        .then(move |authorization, content_type, body| {
+            let state = route_state.clone();
+            let token = token.clone();
+            async move { handle_share(state, &token, authorization, content_type, body).await }
+        });
+
+    let mut shutdown_rx = state.shutdown_cmd_tx.lock().await.subscribe();
+    let (addr, server) = warp::serve(share).try_bind_with_graceful_shutdown(
+        SocketAddr::new(ip, endpoint.port),
+        async move {
+            let _ = shutdown_rx.recv().await;
+        },
+    )?;
+    tokio::spawn(server);
+
+    log::info!("share endpoint listening @ {}", addr);
+    Ok(addr)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_authorized, parse_body, SharedPage};
+
+    #[test]
+    fn test_parse_body() {
+        let page = parse_body(
+            Some("application/x-www-form-urlencoded"),
+            b"url=https%3A%2F%2Fexample.com%2F&text=hello+world&token=secret",
+        )
+        .expect("Unable to parse form");
+        assert_eq!(page.url(), Some("https://example.com/".to_string()));
+        assert_eq!(page.text, Some("hello world".to_string()));
+        assert_eq!(page.token, Some("secret".to_string()));
+
+        let page = parse_body(
+            Some("application/json"),
+            br#"{"text": "Check this out https://example.com/post"}"#,
+        )
+        .expect("Unable to parse json");
+        assert_eq!(page.url(), Some("https://example.com/post".to_string()));
+
+        assert!(parse_body(Some("application/json"), b"not json").is_none());
+    }
+
+    #[test]
+    fn test_is_authorized() {
+        let page = SharedPage {
+            token: Some("secret".into()),
+            ..Default::default()
+        };
+        assert!(is_authorized("secret", None, &page));
+        assert!(is_authorized(
+            "secret",
+            Some("Bearer secret"),
+            &SharedPage::default()
+        ));
+        assert!(!is_authorized(
+            "secret",
+            Some("Bearer wrong"),
+            &SharedPage::default()
+        ));
+        assert!(!is_authorized("secret", None, &SharedPage::default()));
+    }
+}
@@ -0,0 +1,171 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use bytes::Bytes;
+
+#[derive(Default)]
+struct CacheIndex {
+    /// key -> (size in bytes, last access tick)
+    entries: HashMap<String, (u64, u64)>,
+    total_bytes: u64,
+    tick: u64,
+}
+
+/// Size-bounded, least-recently-used disk cache for remote blobs.
+pub struct LocalCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    index: Mutex<CacheIndex>,
+}
+
+impl LocalCache {
+    pub fn new(dir: PathBuf, max_bytes: u64) -> Self {
+        let mut index = CacheIndex::default();
+
+        // Rebuild the index from whatever is already on disk, oldest first so
+        // the access order roughly survives restarts.
+        if let Ok(read_dir) = std::fs::read_dir(&dir) {
+            let mut existing = read_dir
+                .flatten()
+                .filter_map(|entry| {
+                    let meta = entry.metadata().ok()?;
+                    if !meta.is_file() {
+                        return None;
+                    }
+                    let key = entry.file_name().to_str()?.to_string();
+                    Some((key, meta.len(), meta.modified().ok()))
+                })
+                .collect::<Vec<_>>();
+            existing.sort_by_key(|(_, _, modified)| *modified);
+
+            for (key, size, _) in existing {
+                index.tick += 1;
+                index.total_bytes += size;
+                index.entries.insert(key, (size, index.tick));
+            }
+        }
+
+        Self {
+            dir,
+            max_bytes,
+            index: Mutex::new(index),
+        }
+    }
+
+    pub async fn get(&self, key: &str) -> Option<Bytes> {
+        {
+            let mut index = self.index.lock().ok()?;
+            index.tick += 1;
+            let tick = index.tick;
+            let entry = index.entries.get_mut(key)?;
+            entry.1 = tick;
+        }
+
+        match tokio::fs::read(self.dir.join(key)).await {
+            Ok(data) => Some(Bytes::from(data)),
+            Err(_) => {
+                // Removed out from under us, forget about it.
+                self.forget(key);
+                None
+            }
+        }
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.index
+            .lock()
+            .map_or(false, |index| index.entries.contains_key(key))
+    }
+
+    pub async fn put(&self, key: &str, data: &Bytes) {
+        let size = data.len() as u64;
+        if size > self.max_bytes {
+            return;
+        }
+
+        if let Err(err) = tokio::fs::write(self.dir.join(key), data).await {
+            log::warn!("Unable to write {} to archive cache: {}", key, err);
+            return;
+        }
+
+        let evicted = if let Ok(mut index) = self.index.lock() {
+            index.tick += 1;
+            let tick = index.tick;
+            if let Some((old_size, _)) = index.entries.insert(key.to_string(), (size, tick)) {
+                index.total_bytes -= old_size;
+            }
+            index.total_bytes += size;
+            evict(&mut index, self.max_bytes)
+        } else {
+            Vec::new()
+        };
+
+        for key in evicted {
+            let _ = tokio::fs::remove_file(self.dir.join(&key)).await;
+        }
+    }
+
+    pub async fn remove(&self, key: &str) {
+        self.forget(key);
+        let _ = tokio::fs::remove_file(self.dir.join(key)).await;
+    }
+
+    fn forget(&self, key: &str) {
+        if let Ok(mut index) = self.index.lock() {
+            if let Some((size, _)) = index.entries.remove(key) {
+                index.total_bytes -= size;
+            }
+        }
+    }
+}
+
+/// Drop least recently used entries until we're under the limit, returning
+/// the keys that were evicted.
+fn evict(index: &mut CacheIndex, max_bytes: u64) -> Vec<String> {
+    let mut evicted = Vec::new();
+    while index.total_bytes > max_bytes {
+        let oldest = index
+            .entries
+            .iter()
+            .min_by_key(|(_, (_, tick))| *tick)
+            .map(|(key, _)| key.clone());
+
+        match oldest {
+            Some(key) => {
+                if let Some((size, _)) = index.entries.remove(&key) {
+                    index.total_bytes -= size;
+                }
+                evicted.push(key);
+            }
+            None => break,
+        }
+    }
+
+    evicted
+}
+
+#[cfg(test)]
+mod test {
+    use super::LocalCache;
+    use bytes::Bytes;
+
+    #[tokio::test]
+    async fn test_lru_eviction() {
+        let dir = std::env::temp_dir().join(format!("archive-cache-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("Unable to create cache dir");
+
+        let cache = LocalCache::new(dir.clone(), 10);
+        cache.put("a", &Bytes::from("1234")).await;
+        cache.put("b", &Bytes::from("1234")).await;
+        // Touch "a" so "b" becomes the least recently used
+        assert!(cache.get("a").await.is_some());
+        cache.put("c", &Bytes::from("1234")).await;
+
+        assert!(cache.get("a").await.is_some());
+        assert!(cache.get("b").await.is_none());
+        assert!(cache.get("c").await.is_some());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
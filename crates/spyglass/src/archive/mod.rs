@@ -0,0 +1,164 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use bytes::Bytes;
+use dashmap::DashMap;
+use shared::config::{ArchiveStorage, Config};
+
+mod cache;
+mod remote;
+
+use cache::LocalCache;
+use remote::RemoteStore;
+
+pub enum ArchivePath {
+    LocalPath(PathBuf),
+    Memory,
+}
+
+#[derive(Clone)]
+enum Backend {
+    Local(PathBuf),
+    Memory(Arc<DashMap<String, Bytes>>),
+    /// Remote storage fronted by a size-limited local cache.
+    Remote {
+        store: Arc<RemoteStore>,
+        cache: Arc<LocalCache>,
+    },
+}
+
+/// Content-addressed blob store used to archive crawled content. Keys are
+/// expected to be content hashes.
+#[derive(Clone)]
+pub struct BlobArchive {
+    backend: Backend,
+}
+
+impl BlobArchive {
+    pub fn with_path(path: &ArchivePath) -> Self {
+        let backend = match path {
+            ArchivePath::LocalPath(path) => Backend::Local(path.to_owned()),
+            ArchivePath::Memory => Backend::Memory(Default::default()),
+        };
+
+        Self { backend }
+    }
+
+    pub fn from_config(config: &Config) -> anyhow::Result<Self> {
+        let settings = &config.user_settings;
+        let backend = match &settings.archive_storage {
+            ArchiveStorage::Local => Backend::Local(config.archive_dir()),
+            storage => {
+                let max_bytes = settings.archive_cache_size * 1024 * 1024;
+                Backend::Remote {
+                    store: Arc::new(RemoteStore::new(storage)?),
+                    cache: Arc::new(LocalCache::new(config.archive_dir(), max_bytes)),
+                }
+            }
+        };
+
+        Ok(Self { backend })
+    }
+
+    pub async fn put(&self, key: &str, data: Bytes) -> anyhow::Result<()> {
+        match &self.backend {
+            Backend::Local(root) => {
+                let path = local_path(root, key);
+                if let Some(parent) = path.parent() {
+                    tokio::fs::create_dir_all(parent).await?;
+                }
+                tokio::fs::write(path, &data).await?;
+            }
+            Backend::Memory(blobs) => {
+                blobs.insert(key.to_string(), data);
+            }
+            Backend::Remote { store, cache } => {
+                store.put(key, data.clone()).await?;
+                cache.put(key, &data).await;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn get(&self, key: &str) -> anyhow::Result<Option<Bytes>> {
+        match &self.backend {
+            Backend::Local(root) => match tokio::fs::read(local_path(root, key)).await {
+                Ok(data) => Ok(Some(Bytes::from(data))),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(err) => Err(err.into()),
+            },
+            Backend::Memory(blobs) => Ok(blobs.get(key).map(|entry| entry.value().clone())),
+            Backend::Remote { store, cache } => {
+                if let Some(data) = cache.get(key).await {
+                    return Ok(Some(data));
+                }
+
+                let data = store.get(key).await?;
+                if let Some(data) = &data {
+                    cache.put(key, data).await;
+                }
+                Ok(data)
+            }
+        }
+    }
+
+    pub async fn contains(&self, key: &str) -> bool {
+        match &self.backend {
+            Backend::Local(root) => local_path(root, key).exists(),
+            Backend::Memory(blobs) => blobs.contains_key(key),
+            Backend::Remote { store, cache } => {
+                cache.contains(key) || store.exists(key).await.unwrap_or_default()
+            }
+        }
+    }
+
+    pub async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        match &self.backend {
+            Backend::Local(root) => match tokio::fs::remove_file(local_path(root, key)).await {
+                Err(err) if err.kind() != std::io::ErrorKind::NotFound => return Err(err.into()),
+                _ => {}
+            },
+            Backend::Memory(blobs) => {
+                blobs.remove(key);
+            }
+            Backend::Remote { store, cache } => {
+                cache.remove(key).await;
+                store.delete(key).await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Fan out blobs into sub-folders so we don't end up w/ millions of files
+/// in a single directory.
+fn local_path(root: &std::path::Path, key: &str) -> PathBuf {
+    if key.len() > 2 {
+        root.join(&key[0..2]).join(key)
+    } else {
+        root.join(key)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ArchivePath, BlobArchive};
+    use bytes::Bytes;
+
+    #[tokio::test]
+    async fn test_put_get_delete() {
+        let archive = BlobArchive::with_path(&ArchivePath::Memory);
+        archive
+            .put("abcdef", Bytes::from("hello world"))
+            .await
+            .expect("Unable to put");
+
+        let data = archive.get("abcdef").await.expect("Unable to get");
+        assert_eq!(data, Some(Bytes::from("hello world")));
+
+        archive.delete("abcdef").await.expect("Unable to delete");
+        assert_eq!(archive.get("abcdef").await.expect("Unable to get"), None);
+    }
+}
@@ -0,0 +1,238 @@
+use bytes::Bytes;
+use chrono::Utc;
+use reqwest::{Client, RequestBuilder, StatusCode};
+use sha2::{Digest, Sha256};
+use shared::config::ArchiveStorage;
+use url::Url;
+
+pub enum RemoteStore {
+    S3 {
+        client: Client,
+        endpoint: Url,
+        bucket: String,
+        region: String,
+        access_key: String,
+        secret_key: String,
+    },
+    WebDav {
+        client: Client,
+        url: Url,
+        username: Option<String>,
+        password: Option<String>,
+    },
+}
+
+impl RemoteStore {
+    pub fn new(storage: &ArchiveStorage) -> anyhow::Result<Self> {
+        let client = Client::new();
+        match storage {
+            ArchiveStorage::Local => Err(anyhow::anyhow!("Local storage is not remote")),
+            ArchiveStorage::S3 {
+                endpoint,
+                bucket,
+                region,
+                access_key,
+                secret_key,
+            } => Ok(Self::S3 {
+                client,
+                endpoint: Url::parse(endpoint)?,
+                bucket: bucket.to_owned(),
+                region: region.to_owned(),
+                access_key: access_key.to_owned(),
+                secret_key: secret_key.to_owned(),
+            }),
+            ArchiveStorage::WebDav {
+                url,
+                username,
+                password,
+            } => {
+                // Make sure joins are relative to the folder rather than replacing
+                // the last path segment.
+                let url = if url.ends_with('/') {
+                    url.to_owned()
+                } else {
+                    format!("{}/", url)
+                };
+
+                Ok(Self::WebDav {
+                    client,
+                    url: Url::parse(&url)?,
+                    username: username.to_owned(),
+                    password: password.to_owned(),
+                })
+            }
+        }
+    }
+
+    pub async fn put(&self, key: &str, data: Bytes) -> anyhow::Result<()> {
+        let resp = self
+            .request(reqwest::Method::PUT, key, &data)?
+            .body(data)
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            return Err(anyhow::anyhow!(
+                "Unable to upload {}: {}",
+                key,
+                resp.status()
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub async fn get(&self, key: &str) -> anyhow::Result<Option<Bytes>> {
+        let resp = self.request(reqwest::Method::GET, key, &[])?.send().await?;
+
+        match resp.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => Ok(Some(resp.bytes().await?)),
+            status => Err(anyhow::anyhow!("Unable to fetch {}: {}", key, status)),
+        }
+    }
+
+    pub async fn exists(&self, key: &str) -> anyhow::Result<bool> {
+        let resp = self
+            .request(reqwest::Method::HEAD, key, &[])?
+            .send()
+            .await?;
+
+        match resp.status() {
+            StatusCode::NOT_FOUND => Ok(false),
+            status if status.is_success() => Ok(true),
+            status => Err(anyhow::anyhow!("Unable to check {}: {}", key, status)),
+        }
+    }
+
+    pub async fn delete(&self, key: &str) -> anyhow::Result<()> {
+        let resp = self
+            .request(reqwest::Method::DELETE, key, &[])?
+            .send()
+            .await?;
+
+        match resp.status() {
+            StatusCode::NOT_FOUND => Ok(()),
+            status if status.is_success() => Ok(()),
+            status => Err(anyhow::anyhow!("Unable to delete {}: {}", key, status)),
+        }
+    }
+
+    fn request(
+        &self,
+        method: reqwest::Method,
+        key: &str,
+        payload: &[u8],
+    ) -> anyhow::Result<RequestBuilder> {
+        match self {
+            Self::S3 {
+                client,
+                endpoint,
+                bucket,
+                region,
+                access_key,
+                secret_key,
+            } => {
+                // Path-style addressing works across most S3-compatible providers.
+                let url = endpoint.join(&format!("{}/{}", bucket, key))?;
+                let host = match (url.host_str(), url.port()) {
+                    (Some(host), Some(port)) => format!("{}:{}", host, port),
+                    (Some(host), None) => host.to_string(),
+                    _ => return Err(anyhow::anyhow!("Invalid S3 endpoint: {}", endpoint)),
+                };
+
+                let now = Utc::now();
+                let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+                let date = now.format("%Y%m%d").to_string();
+                let payload_hash = hex::encode(Sha256::digest(payload));
+
+                let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+                let canonical_request = format!(
+                    "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+                    method.as_str(),
+                    url.path(),
+                    host,
+                    payload_hash,
+                    amz_date,
+                    signed_headers,
+                    payload_hash
+                );
+
+                let scope = format!("{}/{}/s3/aws4_request", date, region);
+                let string_to_sign = format!(
+                    "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+                    amz_date,
+                    scope,
+                    hex::encode(Sha256::digest(canonical_request.as_bytes()))
+                );
+
+                let signing_key = [region.as_str(), "s3", "aws4_request"].iter().fold(
+                    hmac_sha256(format!("AWS4{}", secret_key).as_bytes(), date.as_bytes()),
+                    |key, part| hmac_sha256(&key, part.as_bytes()),
+                );
+                let signature = hex::encode(hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+
+                Ok(client
+                    .request(method, url)
+                    .header("x-amz-content-sha256", payload_hash)
+                    .header("x-amz-date", amz_date)
+                    .header(
+                        "Authorization",
+                        format!(
+                            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+                            access_key, scope, signed_headers, signature
+                        ),
+                    ))
+            }
+            Self::WebDav {
+                client,
+                url,
+                username,
+                password,
+            } => {
+                let request = client.request(method, url.join(key)?);
+                Ok(match username {
+                    Some(username) => request.basic_auth(username, password.as_ref()),
+                    None => request,
+                })
+            }
+        }
+    }
+}
+
+/// HMAC-SHA256 (RFC 2104), used to sign S3 requests.
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    const BLOCK_SIZE: usize = 64;
+
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        let hashed = Sha256::digest(key);
+        block[..hashed.len()].copy_from_slice(&hashed);
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+
+    let mut inner = Sha256::new();
+    inner.update(block.iter().map(|b| b ^ 0x36).collect::<Vec<u8>>());
+    inner.update(data);
+
+    let mut outer = Sha256::new();
+    outer.update(block.iter().map(|b| b ^ 0x5c).collect::<Vec<u8>>());
+    outer.update(inner.finalize());
+    outer.finalize().to_vec()
+}
+
+#[cfg(test)]
+mod test {
+    use super::hmac_sha256;
+
+    #[test]
+    fn test_hmac_sha256() {
+        // RFC 4231, test case 2
+        let sig = hmac_sha256(b"Jefe", b"what do ya want for nothing?");
+        assert_eq!(
+            hex::encode(sig),
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+}
@@ -0,0 +1,454 @@
+//! Backups of the search index & database as a single `.tar.gz` archive, e.g.
+//! to move an install to a new machine. Backups can be made while running,
+//! but restoring replaces the index & database so it has to be done while
+//! nothing has them open, i.e. from the command line before starting up.
+
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use chrono::{DateTime, Utc};
+use entities::sea_orm::{ConnectionTrait, DbBackend, Statement};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use migration::{MigrationName, Migrator, MigratorTrait};
+use serde::{Deserialize, Serialize};
+use shared::config::Config;
+use tantivy::directory::Directory;
+use tantivy::Index;
+
+use crate::search::Searcher;
+use crate::state::AppState;
+use entities::schema::{DocFields, SearchDocument};
+
+const MANIFEST_FILE: &str = "manifest.json";
+const DB_FILE: &str = "db.sqlite";
+const INDEX_DIR: &str = "index";
+const INDEX_META_FILE: &str = "meta.json";
+/// Suffix for the index & database being replaced by a restore, kept around
+/// until the next one in case the backup wasn't what the user wanted.
+const REPLACED_SUFFIX: &str = "before-restore";
+
+/// Describes what's in a backup, checked before restoring it.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct BackupManifest {
+    /// Version of the app that made the backup.
+    pub app_version: String,
+    pub created_at: DateTime<Utc>,
+    /// Latest database migration applied when the backup was made.
+    pub migration: Option<String>,
+    pub num_docs: u64,
+}
+
+/// Latest migration applied to the database, if it's been migrated.
+async fn latest_migration<C: ConnectionTrait>(db: &C) -> Option<String> {
+    let row = db
+        .query_one(Statement::from_string(
+            DbBackend::Sqlite,
+            "SELECT version FROM seaql_migrations ORDER BY version DESC LIMIT 1".into(),
+        ))
+        .await
+        .ok()
+        .flatten()?;
+
+    row.try_get::<String>("", "version").ok()
+}
+
+/// Add the last committed state of the index to `archive`. Only files of the
+/// committed segments are included, so in-progress writes don't end up in the
+/// backup.
+fn append_index<W: std::io::Write>(
+    searcher: &Searcher,
+    archive: &mut tar::Builder<W>,
+) -> anyhow::Result<u64> {
+    // Hold the writer so nothing is committed while we're copying.
+    let mut writer = searcher
+        .writer
+        .lock()
+        .map_err(|err| anyhow!("Unable to lock index writer: {}", err))?;
+    writer.commit()?;
+
+    let index = &searcher.index;
+    let metas = index.load_metas()?;
+    let num_docs = metas
+        .segments
+        .iter()
+        .map(|segment| segment.num_docs() as u64)
+        .sum();
+
+    let mut append = |name: &Path, data: &[u8]| -> anyhow::Result<()> {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(Utc::now().timestamp() as u64);
+        header.set_cksum();
+        archive.append_data(&mut header, Path::new(INDEX_DIR).join(name), data)?;
+        Ok(())
+    };
+
+    let directory = index.directory();
+    for segment in &metas.segments {
+        for path in segment.list_files() {
+            let data = directory.open_read(&path)?.read_bytes()?;
+            append(&path, data.as_slice())?;
+        }
+    }
+    append(
+        Path::new(INDEX_META_FILE),
+        &serde_json::to_vec_pretty(&metas)?,
+    )?;
+
+    drop(writer);
+    Ok(num_docs)
+}
+
+/// Snapshot the index & database into a `.tar.gz` archive @ `dest`.
+pub async fn create_backup(state: &AppState, dest: &Path) -> anyhow::Result<BackupManifest> {
+    let id = uuid::Uuid::new_v4();
+    let staging = dest.with_extension(format!("{}.partial", id));
+    let db_snapshot = dest.with_extension(format!("{}.sqlite", id));
+
+    // A consistent copy of the database, even if it's being written to.
+    state
+        .db
+        .execute(Statement::from_sql_and_values(
+            DbBackend::Sqlite,
+            "VACUUM INTO ?",
+            vec![db_snapshot.display().to_string().into()],
+        ))
+        .await?;
+    let migration = latest_migration(&state.db).await;
+
+    let searcher = state.index.clone();
+    let archive_path = staging.clone();
+    let snapshot_path = db_snapshot.clone();
+    let result = tokio::task::spawn_blocking(move || -> anyhow::Result<BackupManifest> {
+        let file = File::create(&archive_path)?;
+        let mut archive = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+
+        let num_docs = append_index(&searcher, &mut archive)?;
+        archive.append_path_with_name(&snapshot_path, DB_FILE)?;
+
+        let manifest = BackupManifest {
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at: Utc::now(),
+            migration,
+            num_docs,
+        };
+        let data = serde_json::to_vec_pretty(&manifest)?;
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(manifest.created_at.timestamp() as u64);
+        header.set_cksum();
+        archive.append_data(&mut header, MANIFEST_FILE, data.as_slice())?;
+
+        archive.into_inner()?.finish()?;
+        Ok(manifest)
+    })
+    .await?;
+
+    let _ = fs::remove_file(&db_snapshot);
+    match result {
+        Ok(manifest) => {
+            // Only replace an existing backup once the new one is complete.
+            fs::rename(&staging, dest)?;
+            log::info!(
+                "backed up {} documents to {}",
+                manifest.num_docs,
+                dest.display()
+            );
+            Ok(manifest)
+        }
+        Err(err) => {
+            let _ = fs::remove_file(&staging);
+            Err(err)
+        }
+    }
+}
+
+/// Unpack a backup into `dir`, returning its manifest.
+fn unpack(archive: &Path, dir: &Path) -> anyhow::Result<BackupManifest> {
+    let file = File::open(archive)?;
+    tar::Archive::new(GzDecoder::new(file)).unpack(dir)?;
+
+    let manifest = fs::read(dir.join(MANIFEST_FILE))
+        .map_err(|_| anyhow!("{} is not a backup", archive.display()))?;
+    Ok(serde_json::from_slice(&manifest)?)
+}
+
+/// Make sure an unpacked backup is complete & can be used by this version of
+/// the app before restoring it.
+fn validate(manifest: &BackupManifest, dir: &Path) -> anyhow::Result<()> {
+    if !dir.join(DB_FILE).is_file() {
+        return Err(anyhow!("Backup is missing its database"));
+    }
+
+    // Databases from newer versions may have tables/columns we don't know
+    // about, older ones are migrated on startup.
+    let migration = manifest
+        .migration
+        .as_ref()
+        .ok_or_else(|| anyhow!("Backup is missing its database version"))?;
+    if !Migrator::migrations()
+        .iter()
+        .any(|known| known.name() == migration)
+    {
+        return Err(anyhow!(
+            "Backup was made with a newer version ({}), update before restoring it",
+            manifest.app_version
+        ));
+    }
+
+    let index = Index::open_in_dir(dir.join(INDEX_DIR))?;
+    if index.schema() != DocFields::as_schema() {
+        return Err(anyhow!(
+            "Backup's search index schema doesn't match this version ({})",
+            manifest.app_version
+        ));
+    }
+
+    let num_docs = index.reader()?.searcher().num_docs();
+    if num_docs != manifest.num_docs {
+        return Err(anyhow!(
+            "Backup's search index is incomplete, expected {} documents but found {}",
+            manifest.num_docs,
+            num_docs
+        ));
+    }
+
+    Ok(())
+}
+
+fn replaced_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}", REPLACED_SUFFIX));
+    path.with_file_name(name)
+}
+
+/// Move `from` to `to`, keeping whatever was @ `to` as a `before-restore`
+/// copy. Renames are atomic as long as both are on the same filesystem,
+/// which they are since backups are unpacked into the data directory.
+fn swap(from: &Path, to: &Path) -> anyhow::Result<()> {
+    let replaced = replaced_path(to);
+    if replaced.is_dir() {
+        fs::remove_dir_all(&replaced)?;
+    } else if replaced.exists() {
+        fs::remove_file(&replaced)?;
+    }
+
+    if to.exists() {
+        fs::rename(to, &replaced)?;
+    }
+
+    if let Err(err) = fs::rename(from, to) {
+        // Put things back the way they were
+        if replaced.exists() {
+            let _ = fs::rename(&replaced, to);
+        }
+        return Err(err.into());
+    }
+
+    Ok(())
+}
+
+/// Replace the index & database w/ the ones from a backup. The current ones
+/// are kept next to them w/ a `.before-restore` suffix.
+pub fn restore_backup(config: &Config, archive: &Path) -> anyhow::Result<BackupManifest> {
+    let data_dir = config.data_dir();
+    let staging = data_dir.join(format!("restore-{}", uuid::Uuid::new_v4()));
+
+    let result = unpack(archive, &staging).and_then(|manifest| {
+        validate(&manifest, &staging)?;
+
+        let index_dir = config.index_dir();
+        swap(&staging.join(INDEX_DIR), &index_dir)?;
+        if let Err(err) = swap(&staging.join(DB_FILE), &data_dir.join(DB_FILE)) {
+            // Don't leave the restored index w/ the old database.
+            let replaced = replaced_path(&index_dir);
+            if replaced.exists() {
+                fs::remove_dir_all(&index_dir)?;
+                fs::rename(&replaced, &index_dir)?;
+            }
+            return Err(err);
+        }
+
+        // The write-ahead log belongs to the database that was replaced.
+        for suffix in ["-wal", "-shm"] {
+            let path = data_dir.join(format!("{}{}", DB_FILE, suffix));
+            if path.exists() {
+                fs::rename(&path, replaced_path(&path))?;
+            }
+        }
+
+        Ok(manifest)
+    });
+
+    let _ = fs::remove_dir_all(&staging);
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use entities::models::create_connection;
+    use entities::sea_orm::{ConnectionTrait, DbBackend, Statement};
+    use migration::{MigrationName, Migrator, MigratorTrait};
+    use shared::config::Config;
+    use tantivy::Index;
+
+    use super::{swap, unpack, validate, BackupManifest, DB_FILE, INDEX_DIR};
+    use crate::search::{DocumentUpdate, IndexPath, Searcher};
+    use crate::state::AppState;
+
+    /// App state w/ a single document in its index.
+    async fn state_with_doc() -> AppState {
+        let db = create_connection(&Config::default(), true).await.unwrap();
+        let state = AppState::builder()
+            .with_db(db)
+            .with_index(&IndexPath::Memory)
+            .build();
+
+        {
+            let mut writer = state.index.writer.lock().unwrap();
+            Searcher::upsert_document(
+                &mut writer,
+                DocumentUpdate {
+                    title: "Salinas River",
+                    url: "https://example.com/salinas",
+                    content: "The Salinas River runs through the Salinas Valley.",
+                    ..Default::default()
+                },
+            )
+            .expect("Unable to add doc");
+        }
+
+        state
+    }
+
+    #[tokio::test]
+    async fn test_create_backup() {
+        let state = state_with_doc().await;
+
+        let dir = std::env::temp_dir().join(format!("backup-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dest = dir.join("backup.tar.gz");
+
+        let manifest = super::create_backup(&state, &dest)
+            .await
+            .expect("Unable to create backup");
+        assert_eq!(manifest.num_docs, 1);
+
+        let unpacked = dir.join("unpacked");
+        assert_eq!(unpack(&dest, &unpacked).unwrap(), manifest);
+        assert!(unpacked.join(DB_FILE).is_file());
+        assert!(unpacked.join(INDEX_DIR).join("meta.json").is_file());
+
+        // Test databases aren't migrated, so there's no version to check.
+        assert!(validate(&manifest, &unpacked).is_err());
+        let from_future = BackupManifest {
+            migration: Some("m29990101_000001_from_the_future".into()),
+            ..manifest.clone()
+        };
+        assert!(validate(&from_future, &unpacked).is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_restore_backup() {
+        let state = state_with_doc().await;
+        // Running the migrations would also migrate the index in the user's
+        // data directory, so only record the latest one as applied.
+        let latest = Migrator::migrations().last().unwrap().name().to_string();
+        for sql in [
+            "CREATE TABLE seaql_migrations (version TEXT PRIMARY KEY, applied_at INTEGER)"
+                .to_string(),
+            format!(
+                "INSERT INTO seaql_migrations (version, applied_at) VALUES ('{}', 0)",
+                latest
+            ),
+        ] {
+            state
+                .db
+                .execute(Statement::from_string(DbBackend::Sqlite, sql))
+                .await
+                .unwrap();
+        }
+
+        let dir = std::env::temp_dir().join(format!("restore-{}", uuid::Uuid::new_v4()));
+        let mut config = Config::default();
+        config.user_settings.data_directory = dir.clone();
+        assert_eq!(config.data_dir(), dir);
+
+        // The install being restored over, w/ a write-ahead log that doesn't
+        // belong to the restored database.
+        std::fs::create_dir_all(config.index_dir()).unwrap();
+        std::fs::write(config.index_dir().join("file"), "old").unwrap();
+        std::fs::write(dir.join(DB_FILE), "old").unwrap();
+        std::fs::write(dir.join("db.sqlite-wal"), "old wal").unwrap();
+        std::fs::write(dir.join("db.sqlite-shm"), "old shm").unwrap();
+
+        let dest = dir.join("backup.tar.gz");
+        let manifest = super::create_backup(&state, &dest)
+            .await
+            .expect("Unable to create backup");
+        assert_eq!(manifest.migration, Some(latest));
+
+        let unpacked = dir.join("unpacked");
+        unpack(&dest, &unpacked).unwrap();
+        validate(&manifest, &unpacked).expect("Backup should be valid");
+
+        let restored = super::restore_backup(&config, &dest).expect("Unable to restore");
+        assert_eq!(restored, manifest);
+
+        // Index & database are swapped in, the old ones kept
+        let index = Index::open_in_dir(config.index_dir()).unwrap();
+        assert_eq!(index.reader().unwrap().searcher().num_docs(), 1);
+        assert_eq!(
+            std::fs::read_to_string(dir.join("index.before-restore").join("file")).unwrap(),
+            "old"
+        );
+        assert_ne!(std::fs::read(dir.join(DB_FILE)).unwrap(), b"old");
+        assert_eq!(
+            std::fs::read_to_string(dir.join("db.sqlite.before-restore")).unwrap(),
+            "old"
+        );
+
+        // The old write-ahead log is moved out of the way
+        assert!(!dir.join("db.sqlite-wal").exists());
+        assert!(!dir.join("db.sqlite-shm").exists());
+        assert_eq!(
+            std::fs::read_to_string(dir.join("db.sqlite-wal.before-restore")).unwrap(),
+            "old wal"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("db.sqlite-shm.before-restore")).unwrap(),
+            "old shm"
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_swap() {
+        let dir = std::env::temp_dir().join(format!("swap-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("new")).unwrap();
+        std::fs::create_dir_all(dir.join("index")).unwrap();
+        std::fs::write(dir.join("new").join("file"), "new").unwrap();
+        std::fs::write(dir.join("index").join("file"), "old").unwrap();
+
+        swap(&dir.join("new"), &dir.join("index")).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(dir.join("index").join("file")).unwrap(),
+            "new"
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.join("index.before-restore").join("file")).unwrap(),
+            "old"
+        );
+        assert!(!dir.join("new").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
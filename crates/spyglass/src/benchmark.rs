@@ -0,0 +1,203 @@
+use std::time::{Duration, Instant};
+
+use anyhow::anyhow;
+use entities::models::crawl_queue::{self, EnqueueSettings};
+use shared::config::{Limit, UserSettings};
+use shared::response::{BenchmarkResult, BenchmarkTiming};
+
+use crate::search::clicks::ClickBoosts;
+use crate::search::decay::DomainDecay;
+use crate::search::{DocumentUpdate, IndexPath, QueryOptions, Searcher};
+
+pub const DEFAULT_NUM_DOCS: usize = 1_000;
+pub const DEFAULT_NUM_QUERIES: usize = 100;
+const MAX_NUM_DOCS: usize = 10_000;
+const MAX_NUM_QUERIES: usize = 1_000;
+const NUM_DEQUEUES: usize = 100;
+/// Words per synthetic document.
+const DOC_LENGTH: usize = 250;
+
+const WORDS: [&str; 48] = [
+    "apple", "bridge", "candle", "desert", "engine", "forest", "garden", "harbor", "island",
+    "jungle", "kettle", "ladder", "meadow", "needle", "orange", "pepper", "quartz", "river",
+    "saddle", "timber", "umbrella", "valley", "window", "yellow", "zebra", "anchor", "basket",
+    "castle", "dragon", "feather", "glacier", "hammer", "insect", "jacket", "kitten", "lantern",
+    "marble", "nectar", "oyster", "pillow", "quiver", "rocket", "silver", "tunnel", "velvet",
+    "walnut", "yogurt", "zephyr",
+];
+
+/// Deterministic word generator so every run indexes & searches the same
+/// data, making results comparable between machines.
+struct Words {
+    state: u64,
+}
+
+impl Words {
+    fn new(seed: u64) -> Self {
+        Self { state: seed.max(1) }
+    }
+
+    fn next_word(&mut self) -> &'static str {
+        // xorshift64
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        WORDS[(self.state % WORDS.len() as u64) as usize]
+    }
+
+    fn take(&mut self, num_words: usize) -> String {
+        (0..num_words)
+            .map(|_| self.next_word())
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+}
+
+fn bench_url(idx: usize) -> String {
+    format!("https://bench{}.example.com/{}", idx % 100, idx)
+}
+
+fn to_ms(duration: &Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// Summarize a set of timings, in milliseconds.
+fn timing(mut durations: Vec<Duration>) -> BenchmarkTiming {
+    if durations.is_empty() {
+        return BenchmarkTiming::default();
+    }
+
+    durations.sort();
+    let total: Duration = durations.iter().sum();
+    let percentile = |pct: usize| to_ms(&durations[(durations.len() - 1) * pct / 100]);
+
+    BenchmarkTiming {
+        count: durations.len(),
+        avg_ms: to_ms(&total) / durations.len() as f64,
+        p50_ms: percentile(50),
+        p95_ms: percentile(95),
+        max_ms: percentile(100),
+    }
+}
+
+/// Index `num_docs` synthetic documents, run `num_queries` searches against
+/// them & time dequeuing crawl tasks. Everything runs against a scratch index
+/// & database, the user's data is never touched.
+pub async fn run(num_docs: usize, num_queries: usize) -> anyhow::Result<BenchmarkResult> {
+    let num_docs = num_docs.clamp(1, MAX_NUM_DOCS);
+    let num_queries = num_queries.clamp(1, MAX_NUM_QUERIES);
+    let searcher = Searcher::with_index(&IndexPath::Memory)?;
+
+    let writer = searcher.writer.clone();
+    let (index_time, commit_time) = tokio::task::spawn_blocking(move || {
+        let mut writer = writer
+            .lock()
+            .map_err(|_| anyhow!("Unable to lock index writer"))?;
+        let mut words = Words::new(42);
+
+        let start = Instant::now();
+        for idx in 0..num_docs {
+            let title = words.take(5);
+            let content = words.take(DOC_LENGTH);
+            let url = bench_url(idx);
+            Searcher::upsert_document(
+                &mut writer,
+                DocumentUpdate {
+                    title: &title,
+                    description: "",
+                    domain: "example.com",
+                    url: &url,
+                    content: &content,
+                    ..Default::default()
+                },
+            )?;
+        }
+        let index_time = start.elapsed();
+
+        let start = Instant::now();
+        writer.commit()?;
+        Ok::<_, anyhow::Error>((index_time, start.elapsed()))
+    })
+    .await??;
+    searcher.reader.reload()?;
+
+    let db = entities::test::setup_test_db().await;
+    let mut words = Words::new(7);
+    let mut query_times = Vec::with_capacity(num_queries);
+    for _ in 0..num_queries {
+        let query = words.take(2);
+        let start = Instant::now();
+        let _ = Searcher::search_with_lens(
+            db.clone(),
+            &Vec::new(),
+            &searcher,
+            &query,
+            &DomainDecay::default(),
+            &ClickBoosts::default(),
+            QueryOptions::default(),
+        )
+        .await;
+        query_times.push(start.elapsed());
+    }
+
+    let settings = UserSettings {
+        inflight_crawl_limit: Limit::Infinite,
+        inflight_domain_limit: Limit::Infinite,
+        domain_crawl_delay_ms: 0,
+        ..Default::default()
+    };
+    let urls = (0..num_docs).map(bench_url).collect::<Vec<String>>();
+    let overrides = EnqueueSettings {
+        force_allow: true,
+        ..Default::default()
+    };
+    crawl_queue::enqueue_all(&db, &urls, &[], &settings, &overrides, None).await?;
+
+    let mut dequeue_times = Vec::with_capacity(NUM_DEQUEUES);
+    for _ in 0..NUM_DEQUEUES.min(num_docs) {
+        let start = Instant::now();
+        crawl_queue::dequeue(&db, settings.clone()).await?;
+        dequeue_times.push(start.elapsed());
+    }
+
+    Ok(BenchmarkResult {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        num_cpus: std::thread::available_parallelism().map_or(1, |num| num.get()),
+        num_docs,
+        index_ms: to_ms(&index_time),
+        docs_per_sec: num_docs as f64 / index_time.as_secs_f64().max(f64::EPSILON),
+        commit_ms: to_ms(&commit_time),
+        query: timing(query_times),
+        dequeue: timing(dequeue_times),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{run, timing, Words};
+    use std::time::Duration;
+
+    #[test]
+    fn test_words_are_deterministic() {
+        assert_eq!(Words::new(42).take(10), Words::new(42).take(10));
+        assert_ne!(Words::new(42).take(10), Words::new(7).take(10));
+    }
+
+    #[test]
+    fn test_timing() {
+        let timing = timing((1..=100).map(Duration::from_millis).collect());
+        assert_eq!(timing.count, 100);
+        assert_eq!(timing.p50_ms.round(), 50.0);
+        assert_eq!(timing.max_ms.round(), 100.0);
+    }
+
+    #[tokio::test]
+    async fn test_run() {
+        let result = run(50, 5).await.unwrap();
+        assert_eq!(result.num_docs, 50);
+        assert_eq!(result.query.count, 5);
+        assert_eq!(result.dequeue.count, 50);
+    }
+}
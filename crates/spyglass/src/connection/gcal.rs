@@ -1,3 +1,4 @@
+use chrono::{DateTime, NaiveDate, Utc};
 use entities::models::crawl_queue::{CrawlType, EnqueueSettings};
 use entities::models::tag::{TagPair, TagType};
 use entities::sea_orm::{ActiveModelTrait, Set};
@@ -8,11 +9,12 @@ use std::time::Duration;
 
 use crate::crawler::{CrawlError, CrawlResult};
 use crate::oauth;
+use crate::search::geo;
 use crate::state::AppState;
 use entities::models::{connection, crawl_queue};
 use url::Url;
 
-use super::Connection;
+use super::{record_usage, save_sync_cursor, sync_start, Connection};
 
 pub struct GCalConnection {
     client: GoogClient,
@@ -91,6 +93,12 @@ impl GCalConnection {
     }
 }
 
+/// Start date of an event, from either an all-day date or a full timestamp.
+fn event_date(start: &str) -> Option<DateTime<Utc>> {
+    let date = NaiveDate::parse_from_str(start.get(..10)?, "%Y-%m-%d").ok()?;
+    Some(DateTime::<Utc>::from_utc(date.and_hms_opt(0, 0, 0)?, Utc))
+}
+
 #[async_trait]
 impl Connection for GCalConnection {
     fn id() -> String {
@@ -103,9 +111,13 @@ impl Connection for GCalConnection {
 
     async fn sync(&mut self, state: &AppState) {
         log::debug!("syncing w/ connection");
+        // Pick up where an interrupted sync left off
+        let mut next_page = match sync_start(state, &Self::id(), &self.user).await {
+            Some(cursor) => cursor,
+            None => return,
+        };
 
         // stream pages of files from the integration & add them to the crawl queue
-        let mut next_page = None;
         let mut num_events = 0;
 
         // Grab the next page of files
@@ -138,6 +150,13 @@ impl Connection for GCalConnection {
             .await
             {
                 log::error!("Unable to enqueue: {}", err.to_string());
+            } else {
+                save_sync_cursor(state, &Self::id(), &self.user, next_page.clone()).await;
+            }
+
+            if record_usage(state, &Self::id(), &self.user, 1, 0).await {
+                log::warn!("pausing sync, {} is near its API quota", Self::id());
+                break;
             }
 
             if next_page.is_none() {
@@ -189,9 +208,19 @@ impl Connection for GCalConnection {
                         )
                     };
                     let title = format!("{} ({})", &event.summary, event.start.date);
+                    let location = event.location.clone().unwrap_or_default();
                     let mut crawl_result =
                         CrawlResult::new(uri, Some(event.html_link), &content, &title, None);
                     crawl_result.tags = tags;
+                    crawl_result.document_date = event_date(&event.start.date.to_string());
+
+                    // Events in a known place can be found w/ `near:` filters
+                    if !location.is_empty() {
+                        if let Some((_, point)) = geo::locate(&location) {
+                            crawl_result.numbers.extend(point.as_numbers());
+                        }
+                        crawl_result.fields.push(("location".to_string(), location));
+                    }
 
                     Ok(crawl_result)
                 }
@@ -202,3 +231,19 @@ impl Connection for GCalConnection {
         Err(CrawlError::FetchError("Invalid URL".to_string()))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::event_date;
+
+    #[test]
+    fn test_event_date() {
+        let date = event_date("2022-12-01").map(|date| date.to_rfc3339());
+        assert_eq!(date, Some("2022-12-01T00:00:00+00:00".to_string()));
+
+        let date = event_date("2022-12-01T09:30:00-08:00").map(|date| date.to_rfc3339());
+        assert_eq!(date, Some("2022-12-01T00:00:00+00:00".to_string()));
+
+        assert!(event_date("tomorrow").is_none());
+    }
+}
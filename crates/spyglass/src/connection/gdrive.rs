@@ -12,7 +12,7 @@ use crate::state::AppState;
 use entities::models::{connection, crawl_queue};
 use url::Url;
 
-use super::Connection;
+use super::{record_usage, save_sync_cursor, sync_start, Connection};
 
 pub struct DriveConnection {
     client: GoogClient,
@@ -108,12 +108,16 @@ impl Connection for DriveConnection {
 
     async fn sync(&mut self, state: &AppState) {
         log::debug!("syncing w/ connection");
+        // Pick up where an interrupted sync left off
+        let mut next_page = match sync_start(state, &Self::id(), &self.user).await {
+            Some(cursor) => cursor,
+            None => return,
+        };
 
         // Ignore shortcuts
         let ignore_query = "mimeType != 'application/vnd.google-apps.shortcut'".to_string();
 
         // stream pages of files from the integration & add them to the crawl queue
-        let mut next_page = None;
         let mut num_files = 0;
 
         // Grab the next page of files
@@ -150,6 +154,13 @@ impl Connection for DriveConnection {
             .await
             {
                 log::error!("Unable to enqueue: {}", err.to_string());
+            } else {
+                save_sync_cursor(state, &Self::id(), &self.user, next_page.clone()).await;
+            }
+
+            if record_usage(state, &Self::id(), &self.user, 1, 0).await {
+                log::warn!("pausing sync, {} is near its API quota", Self::id());
+                break;
             }
 
             if next_page.is_none() {
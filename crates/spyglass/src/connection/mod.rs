@@ -1,4 +1,5 @@
 use anyhow::Result;
+use entities::models::connection;
 use jsonrpsee::core::async_trait;
 
 use crate::crawler::{CrawlError, CrawlResult};
@@ -44,3 +45,53 @@ pub async fn load_connection(
         _ => Err(anyhow::anyhow!("Not suppported connection")),
     }
 }
+
+/// Count API requests & downloaded bytes against the connection's daily quota.
+/// Returns true if the connection is now paused.
+pub async fn record_usage(
+    state: &AppState,
+    api_id: &str,
+    account: &str,
+    num_requests: u64,
+    num_bytes: u64,
+) -> bool {
+    let quota = state.user_settings.connection_quotas.get(api_id);
+    match connection::record_usage(&state.db, api_id, account, num_requests, num_bytes, quota).await
+    {
+        Ok(conn) => conn.map_or(false, |conn| conn.is_paused()),
+        Err(err) => {
+            log::error!("Unable to record usage for {}: {}", api_id, err);
+            false
+        }
+    }
+}
+
+/// Resume point for a connection's sync, if a previous one was interrupted.
+/// `None` if the connection is paused & shouldn't sync at all.
+pub async fn sync_start(state: &AppState, api_id: &str, account: &str) -> Option<Option<String>> {
+    match connection::get_by_id(&state.db, api_id, account).await {
+        Ok(Some(conn)) if conn.is_paused() => {
+            log::info!("{} is paused near its API quota, skipping sync", api_id);
+            None
+        }
+        Ok(Some(conn)) => {
+            if let Some(cursor) = &conn.sync_cursor {
+                log::info!("resuming {} sync from {}", api_id, cursor);
+            }
+            Some(conn.sync_cursor)
+        }
+        _ => Some(None),
+    }
+}
+
+/// Checkpoint sync progress once a page has been queued.
+pub async fn save_sync_cursor(
+    state: &AppState,
+    api_id: &str,
+    account: &str,
+    cursor: Option<String>,
+) {
+    if let Err(err) = connection::save_sync_cursor(&state.db, api_id, account, cursor).await {
+        log::error!("Unable to checkpoint {} sync: {}", api_id, err);
+    }
+}
@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use anyhow::anyhow;
+use image::imageops::FilterType;
+use tract_onnx::prelude::*;
+
+/// CLIP style image encoder, taking a 1x3x224x224 image & returning its
+/// embedding.
+pub const IMAGE_ENCODER: &str = "image_encoder.onnx";
+/// Labels the image embedding is compared against, as a JSON map of label to
+/// its (precomputed) text embedding.
+pub const LABELS: &str = "labels.json";
+
+const IMAGE_SIZE: u32 = 224;
+// Normalization used when the CLIP image encoder was trained.
+const MEAN: [f32; 3] = [0.481_454_66, 0.457_827_5, 0.408_210_73];
+const STD: [f32; 3] = [0.268_629_54, 0.261_302_58, 0.275_777_1];
+/// Scales similarities before they're turned into probabilities, same as
+/// CLIP's learned logit scale.
+const LOGIT_SCALE: f32 = 100.0;
+/// Labels less likely than this aren't used.
+const MIN_LABEL_PROBABILITY: f32 = 0.1;
+const MAX_LABELS: usize = 5;
+
+/// Generates labels describing what's in an image w/ a local vision model.
+pub struct Captioner {
+    model: TypedRunnableModel<TypedModel>,
+    labels: Vec<(String, Vec<f32>)>,
+}
+
+fn normalize(embedding: &[f32]) -> Vec<f32> {
+    let norm = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm == 0.0 {
+        return embedding.to_vec();
+    }
+
+    embedding.iter().map(|x| x / norm).collect()
+}
+
+/// Labels most similar to `embedding`, most likely first.
+fn top_labels(embedding: &[f32], labels: &[(String, Vec<f32>)]) -> Vec<String> {
+    let embedding = normalize(embedding);
+    let logits = labels
+        .iter()
+        .map(|(_, label)| {
+            LOGIT_SCALE
+                * embedding
+                    .iter()
+                    .zip(label.iter())
+                    .map(|(a, b)| a * b)
+                    .sum::<f32>()
+        })
+        .collect::<Vec<f32>>();
+
+    // Softmax, shifted by the max for numerical stability
+    let max = logits.iter().cloned().fold(f32::NEG_INFINITY, f32::max);
+    let exps = logits
+        .iter()
+        .map(|logit| (logit - max).exp())
+        .collect::<Vec<f32>>();
+    let total = exps.iter().sum::<f32>();
+
+    let mut ranked = labels
+        .iter()
+        .zip(exps.iter())
+        .map(|((label, _), exp)| (label, exp / total))
+        .filter(|(_, probability)| *probability >= MIN_LABEL_PROBABILITY)
+        .collect::<Vec<_>>();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+    ranked
+        .into_iter()
+        .take(MAX_LABELS)
+        .map(|(label, _)| label.to_string())
+        .collect()
+}
+
+impl Captioner {
+    /// Load the image encoder & labels from `model_dir`.
+    pub fn load(model_dir: &Path) -> anyhow::Result<Self> {
+        let model = tract_onnx::onnx()
+            .model_for_path(model_dir.join(IMAGE_ENCODER))?
+            .with_input_fact(
+                0,
+                f32::fact([1, 3, IMAGE_SIZE as usize, IMAGE_SIZE as usize]).into(),
+            )?
+            .into_optimized()?
+            .into_runnable()?;
+
+        let labels: HashMap<String, Vec<f32>> =
+            serde_json::from_str(&std::fs::read_to_string(model_dir.join(LABELS))?)?;
+        if labels.is_empty() {
+            return Err(anyhow!("No labels found in {:?}", model_dir.join(LABELS)));
+        }
+
+        let labels = labels
+            .into_iter()
+            .map(|(label, embedding)| (label, normalize(&embedding)))
+            .collect();
+
+        Ok(Self { model, labels })
+    }
+
+    /// Labels describing the image @ `path`, e.g. ["receipt", "hardware store"].
+    pub fn caption(&self, path: &Path) -> anyhow::Result<Vec<String>> {
+        let image = image::open(path)?
+            .resize_to_fill(IMAGE_SIZE, IMAGE_SIZE, FilterType::Triangle)
+            .to_rgb8();
+
+        let input: Tensor = tract_ndarray::Array4::from_shape_fn(
+            (1, 3, IMAGE_SIZE as usize, IMAGE_SIZE as usize),
+            |(_, channel, y, x)| {
+                let value = image.get_pixel(x as u32, y as u32)[channel] as f32 / 255.0;
+                (value - MEAN[channel]) / STD[channel]
+            },
+        )
+        .into();
+
+        let output = self.model.run(tvec!(input.into()))?;
+        let embedding = output
+            .first()
+            .ok_or_else(|| anyhow!("Image encoder returned no output"))?
+            .to_array_view::<f32>()?
+            .iter()
+            .cloned()
+            .collect::<Vec<f32>>();
+
+        Ok(top_labels(&embedding, &self.labels))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::top_labels;
+
+    #[test]
+    fn test_top_labels() {
+        let labels = vec![
+            ("receipt".to_string(), vec![1.0, 0.0, 0.0]),
+            ("dog".to_string(), vec![0.0, 1.0, 0.0]),
+            ("store".to_string(), vec![0.7071, 0.0, 0.7071]),
+        ];
+
+        assert_eq!(
+            top_labels(&[0.9, 0.0, 0.1], &labels),
+            vec!["receipt".to_string()]
+        );
+        assert_eq!(
+            top_labels(&[0.0, 2.0, 0.0], &labels),
+            vec!["dog".to_string()]
+        );
+    }
+}
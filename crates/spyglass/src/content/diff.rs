@@ -0,0 +1,171 @@
+use regex::Regex;
+use shared::response::{ChangeKind, DiffChange};
+
+/// Things that change on many pages w/o the content itself changing, e.g.
+/// "updated 5 minutes ago", view counts, copyright years, etc.
+const NOISE_PATTERNS: [&str; 3] = [
+    r"(?i)\b(?:\d+|an?|a few)\s+(?:second|minute|min|hour|hr|day|week|month|year)s?\s+ago\b",
+    r"(?i)\b(?:just now|yesterday|today)\b",
+    r"\d+(?:[.,:/-]\d+)*",
+];
+
+/// Longest summary of a change shown in an alert.
+const SUMMARY_LENGTH: usize = 200;
+
+/// Splits text into sentences. Indexed content is mostly whitespace joined
+/// paragraphs, so this gives more readable diffs than comparing lines.
+fn sentences(text: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    for (idx, ch) in text.char_indices() {
+        let end = idx + ch.len_utf8();
+        let is_boundary = ch == '\n'
+            || (matches!(ch, '.' | '!' | '?') && text[end..].starts_with(char::is_whitespace));
+
+        if is_boundary {
+            let sentence = text[start..end].trim();
+            if !sentence.is_empty() {
+                sentences.push(sentence);
+            }
+            start = end;
+        }
+    }
+
+    let sentence = text[start..].trim();
+    if !sentence.is_empty() {
+        sentences.push(sentence);
+    }
+
+    sentences
+}
+
+/// Sentence level diff of `before` -> `after`, w/ consecutive sentences of
+/// the same kind merged together.
+pub fn diff_text(before: &str, after: &str) -> Vec<DiffChange> {
+    let before = sentences(before);
+    let after = sentences(after);
+
+    let mut changes: Vec<DiffChange> = Vec::new();
+    for result in diff::slice(&before, &after) {
+        let (kind, text) = match result {
+            diff::Result::Left(text) => (ChangeKind::Removed, *text),
+            diff::Result::Right(text) => (ChangeKind::Added, *text),
+            diff::Result::Both(text, _) => (ChangeKind::Unchanged, *text),
+        };
+
+        match changes.last_mut() {
+            Some(last) if last.kind == kind => {
+                last.text.push(' ');
+                last.text.push_str(text);
+            }
+            _ => changes.push(DiffChange {
+                kind,
+                text: text.to_string(),
+            }),
+        }
+    }
+
+    changes
+}
+
+/// Replaces anything matching `NOISE_PATTERNS` w/ a placeholder.
+fn mask_noise(text: &str) -> String {
+    let mut masked = text.to_string();
+    for pattern in NOISE_PATTERNS {
+        let regex = Regex::new(pattern).expect("Invalid noise pattern");
+        masked = regex.replace_all(&masked, "#").into_owned();
+    }
+
+    masked
+}
+
+/// Whether `before` -> `after` changed anything besides timestamps, counters,
+/// & the like.
+pub fn is_meaningful_change(before: &str, after: &str) -> bool {
+    diff_text(&mask_noise(before), &mask_noise(after))
+        .iter()
+        .any(|change| change.kind != ChangeKind::Unchanged)
+}
+
+/// Short description of a change, e.g. for a notification.
+pub fn summarize(changes: &[DiffChange]) -> String {
+    let added = changes
+        .iter()
+        .filter(|change| change.kind == ChangeKind::Added)
+        .map(|change| change.text.as_str())
+        .collect::<Vec<&str>>()
+        .join(" ");
+
+    let summary = if added.is_empty() {
+        "Content was removed".to_string()
+    } else {
+        added
+    };
+
+    match summary.char_indices().nth(SUMMARY_LENGTH) {
+        Some((idx, _)) => format!("{}…", &summary[..idx]),
+        None => summary,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{diff_text, is_meaningful_change, sentences, summarize};
+    use shared::response::ChangeKind;
+
+    #[test]
+    fn test_sentences() {
+        assert_eq!(
+            sentences("Version 1.2 is out! Upgrade now.\nThanks"),
+            vec!["Version 1.2 is out!", "Upgrade now.", "Thanks"]
+        );
+    }
+
+    #[test]
+    fn test_diff_text() {
+        let changes = diff_text(
+            "The bridge is open. Tolls are $2. Have a nice day.",
+            "The bridge is closed. Tolls are $2. Detours are posted. Have a nice day.",
+        );
+
+        let changes = changes
+            .iter()
+            .map(|change| (change.kind.clone(), change.text.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changes,
+            vec![
+                (ChangeKind::Removed, "The bridge is open."),
+                (ChangeKind::Added, "The bridge is closed."),
+                (ChangeKind::Unchanged, "Tolls are $2."),
+                (ChangeKind::Added, "Detours are posted."),
+                (ChangeKind::Unchanged, "Have a nice day."),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_is_meaningful_change() {
+        assert!(!is_meaningful_change(
+            "All systems operational. Updated 5 minutes ago. 1,024 views.",
+            "All systems operational. Updated an hour ago. 1,311 views.",
+        ));
+        assert!(!is_meaningful_change(
+            "Last checked 2022-12-01 10:00 UTC",
+            "Last checked 2022-12-02 08:30 UTC",
+        ));
+        assert!(is_meaningful_change(
+            "All systems operational. Updated 5 minutes ago.",
+            "API latency is degraded. Updated just now.",
+        ));
+    }
+
+    #[test]
+    fn test_summarize() {
+        let changes = diff_text("Status: ok.", "Status: ok. API latency is degraded.");
+        assert_eq!(summarize(&changes), "API latency is degraded.");
+
+        let changes = diff_text("Status: ok. Maintenance tonight.", "Status: ok.");
+        assert_eq!(summarize(&changes), "Content was removed");
+    }
+}
@@ -0,0 +1,121 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::anyhow;
+use chrono::{DateTime, Local, NaiveDateTime};
+use shared::config::UserSettings;
+use tokio::process::Command;
+
+/// Tag added to indexed screenshots, e.g. for `-tag:screenshot` queries.
+pub const SCREENSHOT_TAG: &str = "screenshot";
+
+const SCREENSHOT_EXTENSIONS: [&str; 3] = ["png", "jpg", "jpeg"];
+
+/// Where the OS saves screenshots by default.
+fn default_screenshots_dir() -> Option<PathBuf> {
+    if cfg!(target_os = "macos") {
+        dirs::desktop_dir()
+    } else {
+        dirs::picture_dir().map(|dir| dir.join("Screenshots"))
+    }
+}
+
+/// Folder watched for new screenshots, if one can be found.
+pub fn screenshots_dir(settings: &UserSettings) -> Option<PathBuf> {
+    settings
+        .screenshots_dir
+        .clone()
+        .or_else(default_screenshots_dir)
+}
+
+/// Whether `path` is a screenshot saved to the screenshots folder. The macOS
+/// default is the desktop, so files there also need a screenshot's name.
+pub fn is_screenshot(settings: &UserSettings, path: &Path) -> bool {
+    let is_image = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map_or(false, |ext| {
+            SCREENSHOT_EXTENSIONS
+                .iter()
+                .any(|supported| supported.eq_ignore_ascii_case(ext))
+        });
+
+    let in_dir = match (screenshots_dir(settings), path.parent()) {
+        (Some(dir), Some(parent)) => parent == dir,
+        _ => false,
+    };
+
+    let has_name = settings.screenshots_dir.is_some()
+        || !cfg!(target_os = "macos")
+        || path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map_or(false, |name| {
+                name.starts_with("Screenshot") || name.starts_with("Screen Shot")
+            });
+
+    is_image && in_dir && has_name
+}
+
+/// When the screenshot @ `path` was taken. Screenshots don't carry EXIF data
+/// so the file's creation time is used instead.
+pub fn captured_at(path: &Path) -> Option<NaiveDateTime> {
+    let metadata = path.metadata().ok()?;
+    let time = metadata.created().or_else(|_| metadata.modified()).ok()?;
+    Some(DateTime::<Local>::from(time).naive_local())
+}
+
+/// Text in the image @ `path`, using the tesseract CLI.
+pub async fn ocr(path: &Path) -> anyhow::Result<String> {
+    let output = Command::new("tesseract")
+        .arg(path)
+        .arg("stdout")
+        .output()
+        .await
+        .map_err(|err| anyhow!("Unable to run tesseract, is it installed? {}", err))?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "tesseract failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" "))
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use shared::config::UserSettings;
+
+    use super::is_screenshot;
+
+    #[test]
+    fn test_is_screenshot() {
+        let settings = UserSettings {
+            screenshots_dir: Some(PathBuf::from("/home/user/Screenshots")),
+            ..Default::default()
+        };
+
+        assert!(is_screenshot(
+            &settings,
+            &PathBuf::from("/home/user/Screenshots/Screenshot from 2022-12-01.png")
+        ));
+        assert!(is_screenshot(
+            &settings,
+            &PathBuf::from("/home/user/Screenshots/capture.JPG")
+        ));
+        assert!(!is_screenshot(
+            &settings,
+            &PathBuf::from("/home/user/Screenshots/notes.txt")
+        ));
+        assert!(!is_screenshot(
+            &settings,
+            &PathBuf::from("/home/user/Pictures/Screenshot.png")
+        ));
+    }
+}
@@ -0,0 +1,301 @@
+/// Heuristics for spotting junk pages (near empty, mostly navigation) & error
+/// pages served w/ a 200 (not found, sign in walls) so they can be left out of
+/// the index.
+use std::fmt;
+
+use regex::{Regex, RegexSet};
+use url::Url;
+
+use shared::config::{LensConfig, QualityFilter};
+
+use crate::crawler::CrawlResult;
+
+/// Real pages can mention "not found" or signing in, only short ones are
+/// treated as errors.
+const ERROR_PAGE_MAX_WORDS: usize = 300;
+
+const SOFT_404_TITLE: &str = r"(?i)\b(404|not found|page (does not|doesn't) exist|page (is )?(missing|unavailable)|no longer available)\b";
+const SOFT_404_CONTENT: &str = r"(?i)(page (you|you're|you are) (were )?(looking|searching) for (could not|couldn't|can't|cannot|does not|doesn't|no longer)|(page|article|post) (could not|couldn't|can't|cannot) be found|(page|article|post) (does not|doesn't|no longer) exists?|\b404\b.{0,40}\bnot found\b)";
+
+const LOGIN_WALL_TITLE: &str =
+    r"(?i)^\W*(sign in|sign-in|signin|log in|log-in|login|authentication required)\b";
+const LOGIN_WALL_CONTENT: &str = r"(?i)((sign|log) ?in (to|or (sign|create)[^.]{0,30} to) (continue|view|see|access|read)|(you|you'll) (must|need to|have to) (be logged in|be signed in|sign in|log in)|(please|kindly) (sign|log) ?in\b|login required)";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JunkReason {
+    TooShort,
+    Boilerplate,
+    Soft404,
+    LoginWall,
+    /// Matched one of the lens' `error_page_patterns`.
+    ErrorPattern,
+}
+
+impl JunkReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JunkReason::TooShort => "TooShort",
+            JunkReason::Boilerplate => "Boilerplate",
+            JunkReason::Soft404 => "Soft404",
+            JunkReason::LoginWall => "LoginWall",
+            JunkReason::ErrorPattern => "ErrorPattern",
+        }
+    }
+
+    /// An error served as a regular page, rather than a real page that's not
+    /// worth indexing.
+    pub fn is_error_page(&self) -> bool {
+        matches!(
+            self,
+            JunkReason::Soft404 | JunkReason::LoginWall | JunkReason::ErrorPattern
+        )
+    }
+}
+
+impl fmt::Display for JunkReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Quality rules for `url`, combined across every lens it belongs to.
+pub fn rules_for_url(lenses: &[LensConfig], url: &Url) -> QualityFilter {
+    let mut rules: Option<QualityFilter> = None;
+    for lens in lenses {
+        let filters = lens.into_regexes();
+        let (allowed, skipped) = match (
+            RegexSet::new(filters.allowed),
+            RegexSet::new(filters.skipped),
+        ) {
+            (Ok(allowed), Ok(skipped)) => (allowed, skipped),
+            _ => continue,
+        };
+
+        if allowed.is_match(url.as_str()) && !skipped.is_match(url.as_str()) {
+            rules = Some(match rules {
+                Some(rules) => rules.merge(&lens.quality),
+                None => lens.quality.clone(),
+            });
+        }
+    }
+
+    rules.unwrap_or_default()
+}
+
+/// Either the title or the content matches their built-in pattern.
+fn matches(title_pattern: &str, content_pattern: &str, title: &str, content: &str) -> bool {
+    let title_pattern = Regex::new(title_pattern).expect("Invalid error page pattern");
+    let content_pattern = Regex::new(content_pattern).expect("Invalid error page pattern");
+    title_pattern.is_match(title) || content_pattern.is_match(content)
+}
+
+/// Why a short page looks like an error page, if it does.
+fn error_page(rules: &QualityFilter, title: &str, content: &str) -> Option<JunkReason> {
+    if rules.skip_soft_404 && matches(SOFT_404_TITLE, SOFT_404_CONTENT, title, content) {
+        return Some(JunkReason::Soft404);
+    }
+
+    if rules.skip_login_walls && matches(LOGIN_WALL_TITLE, LOGIN_WALL_CONTENT, title, content) {
+        return Some(JunkReason::LoginWall);
+    }
+
+    for pattern in &rules.error_page_patterns {
+        match Regex::new(pattern) {
+            Ok(regex) if regex.is_match(title) || regex.is_match(content) => {
+                return Some(JunkReason::ErrorPattern)
+            }
+            Ok(_) => {}
+            Err(err) => log::warn!("Invalid error page pattern {}: {}", pattern, err),
+        }
+    }
+
+    None
+}
+
+/// Why the page should be left out of the index, if it should be. Only
+/// scraped web pages are checked.
+pub fn check(rules: &QualityFilter, result: &CrawlResult) -> Option<JunkReason> {
+    let page_words = result.page_word_count?;
+    let content = result.content.as_deref().unwrap_or_default();
+    let num_words = content.split_whitespace().count();
+
+    if num_words <= ERROR_PAGE_MAX_WORDS {
+        let title = result.title.as_deref().unwrap_or_default();
+        if let Some(reason) = error_page(rules, title, content) {
+            return Some(reason);
+        }
+    }
+
+    if num_words < rules.min_words as usize {
+        return Some(JunkReason::TooShort);
+    }
+
+    if let Some(max_ratio) = rules.max_boilerplate_ratio {
+        if page_words > 0 {
+            let boilerplate = page_words.saturating_sub(num_words) as f32 / page_words as f32;
+            if boilerplate > max_ratio {
+                return Some(JunkReason::Boilerplate);
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use shared::config::{LensConfig, QualityFilter};
+    use url::Url;
+
+    use super::{check, rules_for_url, JunkReason};
+    use crate::crawler::CrawlResult;
+
+    fn page(title: &str, content: &str, page_words: usize) -> CrawlResult {
+        CrawlResult {
+            title: Some(title.to_string()),
+            content: Some(content.to_string()),
+            page_word_count: Some(page_words),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let rules = QualityFilter {
+            min_words: 5,
+            max_boilerplate_ratio: Some(0.8),
+            ..Default::default()
+        };
+
+        let article = "Rust is a multi-paradigm, general-purpose programming language.";
+        assert_eq!(check(&rules, &page("Rust", article, 20)), None);
+        assert_eq!(
+            check(&rules, &page("Rust", "Coming soon", 20)),
+            Some(JunkReason::TooShort)
+        );
+        assert_eq!(
+            check(&rules, &page("Rust", article, 100)),
+            Some(JunkReason::Boilerplate)
+        );
+        assert_eq!(
+            check(&rules, &page("Page Not Found | Example", article, 20)),
+            Some(JunkReason::Soft404)
+        );
+        assert_eq!(
+            check(
+                &rules,
+                &page(
+                    "Example",
+                    "Sorry, the page you were looking for doesn't exist anymore.",
+                    20
+                )
+            ),
+            Some(JunkReason::Soft404)
+        );
+        // Long pages about errors aren't errors
+        let long = format!("HTTP 404 not found {}", "explained ".repeat(400));
+        assert_eq!(check(&rules, &page("HTTP 404 explained", &long, 410)), None);
+
+        assert_eq!(
+            check(
+                &rules,
+                &page("Sign in - Example", "Please sign in to continue.", 20)
+            ),
+            Some(JunkReason::LoginWall)
+        );
+        assert_eq!(
+            check(
+                &rules,
+                &page(
+                    "Example",
+                    "You must be logged in to view this discussion.",
+                    20
+                )
+            ),
+            Some(JunkReason::LoginWall)
+        );
+        // Turned off per lens
+        let lenient = QualityFilter {
+            skip_soft_404: false,
+            skip_login_walls: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            check(&lenient, &page("Page Not Found | Example", article, 20)),
+            None
+        );
+
+        let custom = QualityFilter {
+            error_page_patterns: vec!["(?i)access denied".into(), "(invalid".into()],
+            ..Default::default()
+        };
+        assert_eq!(
+            check(&custom, &page("Access Denied", article, 20)),
+            Some(JunkReason::ErrorPattern)
+        );
+        assert_eq!(check(&custom, &page("Rust", article, 20)), None);
+
+        // Only scraped pages are checked
+        let file = CrawlResult {
+            content: Some("tiny".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(check(&rules, &file), None);
+    }
+
+    #[test]
+    fn test_rules_for_url() {
+        let lens = |name: &str, domain: &str, quality: QualityFilter| LensConfig {
+            name: name.to_string(),
+            domains: vec![domain.to_string()],
+            quality,
+            ..Default::default()
+        };
+
+        let lenses = vec![
+            lens(
+                "strict",
+                "example.com",
+                QualityFilter {
+                    min_words: 100,
+                    max_boilerplate_ratio: Some(0.5),
+                    error_page_patterns: vec!["(?i)access denied".into()],
+                    ..Default::default()
+                },
+            ),
+            lens(
+                "loose",
+                "example.com",
+                QualityFilter {
+                    min_words: 20,
+                    max_boilerplate_ratio: Some(0.9),
+                    skip_soft_404: false,
+                    ..Default::default()
+                },
+            ),
+            lens(
+                "other",
+                "other.com",
+                QualityFilter {
+                    min_words: 50,
+                    ..Default::default()
+                },
+            ),
+        ];
+
+        let url = Url::parse("https://example.com/about").unwrap();
+        assert_eq!(
+            rules_for_url(&lenses, &url),
+            QualityFilter {
+                min_words: 20,
+                max_boilerplate_ratio: Some(0.9),
+                skip_soft_404: false,
+                skip_login_walls: true,
+                error_page_patterns: Vec::new(),
+            }
+        );
+
+        let url = Url::parse("https://unknown.com/").unwrap();
+        assert_eq!(rules_for_url(&lenses, &url), QualityFilter::default());
+    }
+}
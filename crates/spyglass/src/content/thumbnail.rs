@@ -0,0 +1,158 @@
+//! Small previews of local images, PDFs & videos so results can be shown as a
+//! grid. PDFs & videos are rendered w/ the poppler (pdftoppm) & ffmpeg CLIs.
+
+use std::io::Cursor;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use anyhow::anyhow;
+use image::{DynamicImage, ImageOutputFormat};
+use sha2::{Digest, Sha256};
+use tokio::process::Command;
+
+use crate::parser::{image::is_image, video::is_video};
+
+/// Thumbnails fit in a square this many pixels wide.
+pub const THUMBNAIL_SIZE: u32 = 256;
+/// Grab the video frame this far in, the very first one is often black.
+const VIDEO_FRAME_SECS: &str = "1";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ThumbnailSource {
+    Image,
+    Pdf,
+    Video,
+}
+
+impl ThumbnailSource {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?;
+        if is_image(ext) {
+            Some(Self::Image)
+        } else if ext.eq_ignore_ascii_case("pdf") {
+            Some(Self::Pdf)
+        } else if is_video(ext) {
+            Some(Self::Video)
+        } else {
+            None
+        }
+    }
+}
+
+/// Archive key for the thumbnail of `doc_id` @ `path`. The file's modified
+/// time is part of the key so edited files get a new thumbnail.
+pub fn thumbnail_key(doc_id: &str, path: &Path) -> String {
+    let modified = path
+        .metadata()
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|modified| modified.as_secs())
+        .unwrap_or_default();
+
+    hex::encode(Sha256::digest(
+        format!("thumbnail:{}:{}", doc_id, modified).as_bytes(),
+    ))
+}
+
+/// Shrink `image` to fit the thumbnail size & encode it as a JPEG.
+fn encode(image: DynamicImage) -> anyhow::Result<Vec<u8>> {
+    let thumbnail = image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE).to_rgb8();
+    let mut data = Vec::new();
+    DynamicImage::ImageRgb8(thumbnail)
+        .write_to(&mut Cursor::new(&mut data), ImageOutputFormat::Jpeg(80))?;
+    Ok(data)
+}
+
+/// Run `cmd`, returning whatever it wrote to stdout.
+async fn run(cmd: &mut Command, name: &str) -> anyhow::Result<Vec<u8>> {
+    let output = cmd
+        .output()
+        .await
+        .map_err(|err| anyhow!("Unable to run {}, is it installed? {}", name, err))?;
+
+    if !output.status.success() || output.stdout.is_empty() {
+        return Err(anyhow!(
+            "{} failed: {}",
+            name,
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    Ok(output.stdout)
+}
+
+/// First page of the PDF @ `path` as a PNG.
+async fn render_pdf(path: &Path) -> anyhow::Result<Vec<u8>> {
+    run(
+        Command::new("pdftoppm")
+            .args(["-png", "-f", "1", "-l", "1", "-singlefile"])
+            .args(["-scale-to", &THUMBNAIL_SIZE.to_string()])
+            .arg(path),
+        "pdftoppm",
+    )
+    .await
+}
+
+/// A frame from the video @ `path` as a PNG. Falls back to the first frame
+/// for clips shorter than `VIDEO_FRAME_SECS`.
+async fn render_video(path: &Path) -> anyhow::Result<Vec<u8>> {
+    let frame = |seek: &str| {
+        let mut cmd = Command::new("ffmpeg");
+        cmd.args(["-v", "error", "-ss", seek, "-i"])
+            .arg(path)
+            .args(["-frames:v", "1", "-f", "image2pipe", "-vcodec", "png", "-"]);
+        cmd
+    };
+
+    match run(&mut frame(VIDEO_FRAME_SECS), "ffmpeg").await {
+        Ok(data) => Ok(data),
+        Err(_) => run(&mut frame("0"), "ffmpeg").await,
+    }
+}
+
+/// JPEG thumbnail for the file @ `path`, if it's a supported type.
+pub async fn generate(path: &Path) -> anyhow::Result<Vec<u8>> {
+    let source = ThumbnailSource::from_path(path)
+        .ok_or_else(|| anyhow!("No thumbnails for {}", path.display()))?;
+
+    let image = match source {
+        ThumbnailSource::Image => {
+            let path = path.to_path_buf();
+            tokio::task::spawn_blocking(move || image::open(path)).await??
+        }
+        ThumbnailSource::Pdf => image::load_from_memory(&render_pdf(path).await?)?,
+        ThumbnailSource::Video => image::load_from_memory(&render_video(path).await?)?,
+    };
+
+    Ok(tokio::task::spawn_blocking(move || encode(image)).await??)
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use image::{DynamicImage, GenericImageView, RgbImage};
+
+    use super::{encode, ThumbnailSource, THUMBNAIL_SIZE};
+
+    #[test]
+    fn test_source() {
+        let source = |path: &str| ThumbnailSource::from_path(Path::new(path));
+        assert_eq!(source("/photos/river.JPG"), Some(ThumbnailSource::Image));
+        assert_eq!(source("/docs/report.pdf"), Some(ThumbnailSource::Pdf));
+        assert_eq!(source("/videos/trip.mov"), Some(ThumbnailSource::Video));
+        assert_eq!(source("/docs/notes.md"), None);
+        assert_eq!(source("/docs/README"), None);
+    }
+
+    #[test]
+    fn test_encode() {
+        let image = DynamicImage::ImageRgb8(RgbImage::new(1024, 512));
+        let data = encode(image).expect("Unable to encode");
+        assert!(data.starts_with(b"\xFF\xD8\xFF"));
+
+        let thumbnail = image::load_from_memory(&data).expect("Invalid thumbnail");
+        assert_eq!(thumbnail.dimensions(), (THUMBNAIL_SIZE, THUMBNAIL_SIZE / 2));
+    }
+}
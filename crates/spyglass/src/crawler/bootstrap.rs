@@ -13,7 +13,7 @@ use tokio_retry::strategy::ExponentialBackoff;
 use tokio_retry::Retry;
 use url::Url;
 
-use entities::models::crawl_queue::{self, EnqueueSettings};
+use entities::models::crawl_queue::{self, EnqueueSettings, TaskPriority};
 use entities::models::tag::TagType;
 use entities::sea_orm::DatabaseConnection;
 use shared::config::{LensConfig, UserSettings};
@@ -132,6 +132,7 @@ pub async fn bootstrap(
     let overrides = crawl_queue::EnqueueSettings {
         crawl_type: crawl_queue::CrawlType::Bootstrap,
         tags: vec![(TagType::Lens, lens.name.to_string())],
+        priority: TaskPriority::Lens,
         ..Default::default()
     };
 
@@ -187,6 +188,7 @@ pub async fn bootstrap(
             // No overrides required
             &EnqueueSettings {
                 force_allow: true,
+                priority: TaskPriority::Lens,
                 ..Default::default()
             },
             pipeline,
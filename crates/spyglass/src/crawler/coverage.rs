@@ -0,0 +1,330 @@
+/// Reports how much of a lens' site(s) has been indexed by checking a sample
+/// of their URLs against the index, crawl queue & crawl rules.
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use url::Url;
+
+use entities::models::crawl_queue::{self, CrawlStatus, FilterReason, UrlFilter};
+use entities::models::{indexed_document, robots};
+use entities::sea_orm::{ActiveEnum, ColumnTrait, EntityTrait, QueryFilter, QuerySelect};
+use shared::config::LensConfig;
+use shared::response::{CoveragePattern, CoverageStatus, CoverageUrl, LensCoverage};
+
+use super::client::HTTPClient;
+use super::sitemap;
+use crate::state::AppState;
+
+/// Most URLs checked for a single report.
+pub const MAX_SAMPLE: usize = 500;
+/// Example URLs kept per pattern, enough to spot what's going wrong.
+const MAX_EXAMPLES: usize = 5;
+
+/// Groups URLs by host & first path segment, e.g. `docs.rs/tokio/*`.
+pub fn url_pattern(url: &str) -> String {
+    let parsed = match Url::parse(url) {
+        Ok(parsed) => parsed,
+        Err(_) => return url.to_string(),
+    };
+
+    let host = parsed.host_str().unwrap_or("localhost");
+    match parsed
+        .path_segments()
+        .and_then(|mut segments| segments.next())
+        .filter(|segment| !segment.is_empty())
+    {
+        Some(segment) => format!("{}/{}/*", host, segment),
+        None => format!("{}/", host),
+    }
+}
+
+/// Up to `size` URLs spread evenly across `urls`. URLs are sorted first so
+/// every section of the site is sampled.
+pub fn sample(mut urls: Vec<String>, size: usize) -> Vec<String> {
+    urls.sort();
+    urls.dedup();
+    if urls.len() <= size {
+        return urls;
+    }
+
+    let step = urls.len() as f64 / size as f64;
+    (0..size)
+        .map(|idx| urls[(idx as f64 * step) as usize].clone())
+        .collect()
+}
+
+fn classify(
+    checked: &Result<String, FilterReason>,
+    robots_blocked: bool,
+    is_indexed: bool,
+    task: Option<&crawl_queue::Model>,
+) -> (CoverageStatus, Option<String>) {
+    if let Err(reason) = checked {
+        return (CoverageStatus::Skipped, Some(reason.as_str().to_string()));
+    }
+
+    if is_indexed {
+        return (CoverageStatus::Indexed, None);
+    }
+
+    if robots_blocked {
+        return (CoverageStatus::Skipped, Some("RobotsBlocked".to_string()));
+    }
+
+    match task {
+        Some(task) => match task.status {
+            CrawlStatus::Queued | CrawlStatus::Processing => (CoverageStatus::Queued, None),
+            CrawlStatus::Failed => (
+                CoverageStatus::Failed,
+                task.error.as_ref().map(|err| err.error_type.to_value()),
+            ),
+            // Crawled, but not indexed, e.g. a duplicate or redirect.
+            CrawlStatus::Completed => (CoverageStatus::Missing, Some("NotIndexed".to_string())),
+            CrawlStatus::Skipped => (CoverageStatus::Skipped, Some("NoIndex".to_string())),
+        },
+        None => (CoverageStatus::Missing, None),
+    }
+}
+
+/// Tally the checked URLs per pattern, least covered patterns first.
+pub fn summarize(
+    lens: &str,
+    from_sitemap: bool,
+    num_urls: usize,
+    checked: Vec<CoverageUrl>,
+) -> LensCoverage {
+    let num_sampled = checked.len() as u32;
+    let mut patterns: BTreeMap<String, CoveragePattern> = BTreeMap::new();
+    for url in checked {
+        let pattern = url_pattern(&url.url);
+        let entry = patterns
+            .entry(pattern.clone())
+            .or_insert_with(|| CoveragePattern {
+                pattern,
+                ..Default::default()
+            });
+
+        match url.status {
+            CoverageStatus::Indexed => entry.num_indexed += 1,
+            CoverageStatus::Queued => entry.num_queued += 1,
+            CoverageStatus::Failed => entry.num_failed += 1,
+            CoverageStatus::Skipped => entry.num_skipped += 1,
+            CoverageStatus::Missing => entry.num_missing += 1,
+        }
+
+        if url.status != CoverageStatus::Indexed && entry.examples.len() < MAX_EXAMPLES {
+            entry.examples.push(url);
+        }
+    }
+
+    let mut patterns = patterns.into_values().collect::<Vec<_>>();
+    let coverage = |pattern: &CoveragePattern| {
+        let total = pattern.num_indexed
+            + pattern.num_queued
+            + pattern.num_failed
+            + pattern.num_skipped
+            + pattern.num_missing;
+        pattern.num_indexed as f32 / total.max(1) as f32
+    };
+    patterns.sort_by(|a, b| coverage(a).total_cmp(&coverage(b)));
+
+    LensCoverage {
+        lens: lens.to_string(),
+        from_sitemap,
+        num_urls: num_urls as u32,
+        num_sampled,
+        num_indexed: patterns.iter().map(|pattern| pattern.num_indexed).sum(),
+        patterns,
+    }
+}
+
+/// URLs listed in the lens sitemaps, or the `/sitemap.xml` of its domains if
+/// it doesn't list any.
+async fn sitemap_urls(lens: &LensConfig) -> Vec<String> {
+    let sitemaps = if lens.sitemaps.is_empty() {
+        lens.domains
+            .iter()
+            .map(|domain| format!("https://{}/sitemap.xml", domain))
+            .collect()
+    } else {
+        lens.sitemaps.clone()
+    };
+
+    let client = HTTPClient::new();
+    let mut urls = Vec::new();
+    for sitemap in sitemaps {
+        if let Ok(url) = Url::parse(&sitemap) {
+            urls.extend(
+                sitemap::fetch_all(&client, &url)
+                    .await
+                    .into_iter()
+                    .map(|entry| entry.loc),
+            );
+        }
+    }
+
+    urls
+}
+
+/// URLs the crawler has found so far for sites w/o a sitemap.
+async fn crawled_urls(state: &AppState, lens: &LensConfig) -> anyhow::Result<Vec<String>> {
+    let limit = (MAX_SAMPLE * 10) as u64;
+    let mut urls = crawl_queue::Entity::find()
+        .filter(crawl_queue::Column::Domain.is_in(lens.domains.clone()))
+        .limit(limit)
+        .all(&state.db)
+        .await?
+        .into_iter()
+        .map(|task| task.url)
+        .collect::<Vec<_>>();
+
+    urls.extend(
+        indexed_document::Entity::find()
+            .filter(indexed_document::Column::Domain.is_in(lens.domains.clone()))
+            .limit(limit)
+            .all(&state.db)
+            .await?
+            .into_iter()
+            .map(|doc| doc.url),
+    );
+
+    Ok(urls)
+}
+
+/// Check a sample of the lens' URLs to see which are indexed & why the rest
+/// aren't.
+pub async fn check(state: &AppState, lens: &LensConfig) -> anyhow::Result<LensCoverage> {
+    let mut urls = sitemap_urls(lens).await;
+    let from_sitemap = !urls.is_empty();
+    if !from_sitemap {
+        urls = crawled_urls(state, lens).await?;
+    }
+
+    let num_urls = urls.iter().collect::<HashSet<_>>().len();
+    let sampled = sample(urls, MAX_SAMPLE);
+
+    let filter = UrlFilter::new(&[lens.clone()], &state.user_settings, false);
+    let checked = sampled
+        .iter()
+        .map(|url| (url, filter.check(url)))
+        .collect::<Vec<_>>();
+    let allowed = checked
+        .iter()
+        .filter_map(|(_, checked)| checked.clone().ok())
+        .collect::<Vec<_>>();
+
+    let disallowed = robots::disallowed(&state.db, &allowed).await?;
+    let indexed = indexed_document::Entity::find()
+        .filter(indexed_document::Column::Url.is_in(allowed.clone()))
+        .all(&state.db)
+        .await?
+        .into_iter()
+        .map(|doc| doc.url)
+        .collect::<HashSet<_>>();
+    let tasks = crawl_queue::Entity::find()
+        .filter(crawl_queue::Column::Url.is_in(allowed.clone()))
+        .all(&state.db)
+        .await?
+        .into_iter()
+        .map(|task| (task.url.clone(), task))
+        .collect::<HashMap<_, _>>();
+
+    let checked = checked
+        .into_iter()
+        .map(|(url, checked)| {
+            let normalized = checked.as_ref().unwrap_or(url);
+            let (status, reason) = classify(
+                &checked,
+                disallowed.contains(normalized),
+                indexed.contains(normalized),
+                tasks.get(normalized),
+            );
+
+            CoverageUrl {
+                url: url.to_string(),
+                status,
+                reason,
+            }
+        })
+        .collect();
+
+    Ok(summarize(&lens.name, from_sitemap, num_urls, checked))
+}
+
+#[cfg(test)]
+mod test {
+    use shared::response::{CoverageStatus, CoverageUrl};
+
+    use super::{sample, summarize, url_pattern};
+
+    #[test]
+    fn test_url_pattern() {
+        assert_eq!(
+            url_pattern("https://docs.rs/tokio/latest/"),
+            "docs.rs/tokio/*"
+        );
+        assert_eq!(url_pattern("https://docs.rs/about"), "docs.rs/about/*");
+        assert_eq!(url_pattern("https://docs.rs/"), "docs.rs/");
+        assert_eq!(url_pattern("https://docs.rs"), "docs.rs/");
+    }
+
+    #[test]
+    fn test_sample() {
+        let urls = (0..10)
+            .map(|idx| format!("https://example.com/{}", idx))
+            .collect::<Vec<_>>();
+        assert_eq!(sample(urls.clone(), 20).len(), 10);
+
+        let sampled = sample(urls, 5);
+        assert_eq!(
+            sampled,
+            vec![
+                "https://example.com/0".to_string(),
+                "https://example.com/2".to_string(),
+                "https://example.com/4".to_string(),
+                "https://example.com/6".to_string(),
+                "https://example.com/8".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_summarize() {
+        let url = |url: &str, status: CoverageStatus, reason: Option<&str>| CoverageUrl {
+            url: url.to_string(),
+            status,
+            reason: reason.map(|reason| reason.to_string()),
+        };
+
+        let checked = vec![
+            url("https://example.com/blog/a", CoverageStatus::Indexed, None),
+            url("https://example.com/blog/b", CoverageStatus::Indexed, None),
+            url("https://example.com/docs/a", CoverageStatus::Indexed, None),
+            url(
+                "https://example.com/docs/b?action=edit",
+                CoverageStatus::Skipped,
+                Some("SkipRule"),
+            ),
+            url("https://example.com/docs/c", CoverageStatus::Missing, None),
+        ];
+
+        let coverage = summarize("example", true, 100, checked);
+        assert_eq!(coverage.num_urls, 100);
+        assert_eq!(coverage.num_sampled, 5);
+        assert_eq!(coverage.num_indexed, 3);
+        assert_eq!(coverage.patterns.len(), 2);
+
+        // Least covered first
+        let docs = &coverage.patterns[0];
+        assert_eq!(docs.pattern, "example.com/docs/*");
+        assert_eq!(docs.num_indexed, 1);
+        assert_eq!(docs.num_skipped, 1);
+        assert_eq!(docs.num_missing, 1);
+        assert_eq!(docs.examples.len(), 2);
+        assert_eq!(docs.examples[0].reason, Some("SkipRule".to_string()));
+
+        let blog = &coverage.patterns[1];
+        assert_eq!(blog.pattern, "example.com/blog/*");
+        assert_eq!(blog.num_indexed, 2);
+        assert!(blog.examples.is_empty());
+    }
+}
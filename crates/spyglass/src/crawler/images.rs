@@ -0,0 +1,118 @@
+use bytes::Bytes;
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use super::client::HTTPClient;
+use crate::state::AppState;
+
+/// Anything bigger than this is probably not a thumbnail.
+const MAX_IMAGE_BYTES: usize = 1024 * 1024;
+
+fn archive_key(kind: &str, id: &str) -> String {
+    hex::encode(Sha256::digest(format!("{}:{}", kind, id).as_bytes()))
+}
+
+pub fn favicon_key(domain: &str) -> String {
+    archive_key("favicon", domain)
+}
+
+pub fn preview_key(doc_id: &str) -> String {
+    archive_key("preview", doc_id)
+}
+
+async fn fetch_image(client: &HTTPClient, url: &Url) -> anyhow::Result<Bytes> {
+    let resp = client.get(url).await?.error_for_status()?;
+
+    let is_image = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map_or(true, |value| value.starts_with("image/"));
+    if !is_image {
+        return Err(anyhow::anyhow!("<{}> is not an image", url));
+    }
+
+    if resp.content_length().unwrap_or_default() as usize > MAX_IMAGE_BYTES {
+        return Err(anyhow::anyhow!("<{}> is too large", url));
+    }
+
+    let data = resp.bytes().await?;
+    if data.len() > MAX_IMAGE_BYTES {
+        return Err(anyhow::anyhow!("<{}> is too large", url));
+    }
+
+    Ok(data)
+}
+
+/// Fetch & store the favicon for the document's domain (if we haven't already)
+/// and the document's preview image, if it has one.
+pub async fn cache_images(state: AppState, page_url: Url, doc_id: String, image: Option<String>) {
+    let client = HTTPClient::new();
+
+    if let Some(domain) = page_url.host_str() {
+        let key = favicon_key(domain);
+        if !state.archive.contains(&key).await {
+            let favicon = page_url.join("/favicon.ico").expect("Invalid favicon URL");
+            // Store an empty blob when there's no favicon so we don't keep trying.
+            let data = match fetch_image(&client, &favicon).await {
+                Ok(data) => data,
+                Err(err) => {
+                    log::debug!("no favicon for {}: {}", domain, err);
+                    Bytes::new()
+                }
+            };
+
+            if let Err(err) = state.archive.put(&key, data).await {
+                log::warn!("Unable to cache favicon for {}: {}", domain, err);
+            }
+        }
+    }
+
+    // Preview images may be relative to the page
+    if let Some(image_url) = image.and_then(|image| page_url.join(&image).ok()) {
+        match fetch_image(&client, &image_url).await {
+            Ok(data) => {
+                if let Err(err) = state.archive.put(&preview_key(&doc_id), data).await {
+                    log::warn!("Unable to cache preview for {}: {}", doc_id, err);
+                }
+            }
+            Err(err) => log::debug!("Unable to fetch preview <{}>: {}", image_url, err),
+        }
+    }
+}
+
+/// Encode image data into a data URI that can be used directly in an `<img>`
+pub fn to_data_uri(data: &[u8]) -> String {
+    let mime = if data.starts_with(b"\x89PNG") {
+        "image/png"
+    } else if data.starts_with(b"\xFF\xD8\xFF") {
+        "image/jpeg"
+    } else if data.starts_with(b"GIF8") {
+        "image/gif"
+    } else if data.starts_with(b"RIFF") && data.get(8..12) == Some(&b"WEBP"[..]) {
+        "image/webp"
+    } else if data.starts_with(b"<svg") || data.starts_with(b"<?xml") {
+        "image/svg+xml"
+    } else {
+        "image/x-icon"
+    };
+
+    format!("data:{};base64,{}", mime, base64::encode(data))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{favicon_key, preview_key, to_data_uri};
+
+    #[test]
+    fn test_keys() {
+        assert_ne!(favicon_key("example.com"), preview_key("example.com"));
+        assert_eq!(favicon_key("example.com"), favicon_key("example.com"));
+    }
+
+    #[test]
+    fn test_to_data_uri() {
+        assert_eq!(to_data_uri(b"\x89PNG"), "data:image/png;base64,iVBORw==");
+        assert!(to_data_uri(b"GIF89a").starts_with("data:image/gif;base64,"));
+    }
+}
@@ -1,4 +1,5 @@
 use std::collections::HashSet;
+use std::ffi::OsStr;
 use std::path::Path;
 
 use addr::parse_domain_name;
@@ -11,24 +12,36 @@ use sha2::{Digest, Sha256};
 use thiserror::Error;
 use url::{Host, Url};
 
-use entities::models::{crawl_queue, fetch_history};
+use entities::models::crawl_queue::{FetchInfo, TaskError, TaskErrorType};
+use entities::models::{connection, crawl_queue, domain_stats, fetch_history, watched_page};
 use entities::sea_orm::prelude::*;
+use shared::config::ExtractRule;
+use spyglass_plugin::{ParseRequest, ParsedDocument};
 
-use crate::connection::load_connection;
+use crate::connection::{load_connection, record_usage};
+use crate::content::ocr;
 use crate::crawler::bootstrap::create_archive_url;
+use crate::importer;
 use crate::parser;
-use crate::scraper::{html_to_text, DEFAULT_DESC_LENGTH};
+use crate::plugin::MAX_PARSE_BYTES;
+use crate::scraper::{extract_fields, html_to_text, Section, DEFAULT_DESC_LENGTH};
 use crate::state::AppState;
 
 pub mod bootstrap;
 pub mod client;
+pub mod coverage;
+pub mod images;
 pub mod robots;
+pub mod sitemap;
 
 use client::HTTPClient;
-use robots::check_resource_rules;
+use robots::{check_resource_rules, RobotsDirectives};
 
 // TODO: Make this configurable by domain
 const FETCH_DELAY_MS: i64 = 1000 * 60 * 60 * 24;
+/// Most links followed from a single page, keeps pathological pages (e.g.
+/// calendars or faceted search) from flooding the crawl queue.
+const MAX_LINKS_PER_PAGE: usize = 1_000;
 
 #[derive(Debug, Clone, PartialEq, Eq, Error)]
 pub enum CrawlError {
@@ -48,10 +61,75 @@ pub enum CrawlError {
     Timeout,
     #[error("crawl unsupported: {0}")]
     Unsupported(String),
+    #[error("unable to establish a secure connection: {0}")]
+    TlsError(String),
+    #[error("server responded w/ status {0}")]
+    HttpStatus(u16),
+    /// Out of disk space while writing to the index.
+    #[error("index is full: {0}")]
+    IndexFull(String),
+    /// Connection is near its daily API quota, crawl will resume tomorrow.
+    #[error("{0} is paused near its API quota")]
+    QuotaExceeded(String),
     #[error("other crawl error: {0}")]
     Other(String),
 }
 
+impl CrawlError {
+    /// Whether the error points to a problem w/ the host rather than the
+    /// document, e.g. the server being down.
+    pub fn is_host_failure(&self) -> bool {
+        match self {
+            CrawlError::Timeout | CrawlError::FetchError(_) | CrawlError::TlsError(_) => true,
+            CrawlError::HttpStatus(status) => *status == 429 || *status >= 500,
+            _ => false,
+        }
+    }
+}
+
+impl From<&CrawlError> for TaskError {
+    fn from(err: &CrawlError) -> Self {
+        let error_type = match err {
+            CrawlError::Denied(rule) if rule == "robots.txt" => TaskErrorType::RobotsBlocked,
+            CrawlError::Denied(_) | CrawlError::RecentlyFetched | CrawlError::Other(_) => {
+                TaskErrorType::Other
+            }
+            CrawlError::FetchError(_) => TaskErrorType::Fetch,
+            CrawlError::ParseError(_) => TaskErrorType::Parse,
+            CrawlError::NotFound => TaskErrorType::NotFound,
+            CrawlError::Timeout => TaskErrorType::NetworkTimeout,
+            CrawlError::Unsupported(_) => TaskErrorType::ParserUnsupported,
+            CrawlError::TlsError(_) => TaskErrorType::TlsError,
+            CrawlError::HttpStatus(_) => TaskErrorType::HttpStatus,
+            CrawlError::IndexFull(_) => TaskErrorType::IndexFull,
+            CrawlError::QuotaExceeded(_) => TaskErrorType::QuotaExceeded,
+        };
+
+        TaskError::new(error_type, &err.to_string())
+    }
+}
+
+/// Classify a failed request so timeouts are retried & certificate problems
+/// are reported as such.
+fn request_error(err: &anyhow::Error) -> CrawlError {
+    if let Some(req_err) = err.downcast_ref::<reqwest::Error>() {
+        if req_err.is_timeout() {
+            return CrawlError::Timeout;
+        }
+    }
+
+    let is_tls = err.chain().any(|cause| {
+        let msg = cause.to_string().to_lowercase();
+        msg.contains("certificate") || msg.contains("tls") || msg.contains("ssl")
+    });
+
+    if is_tls {
+        CrawlError::TlsError(err.to_string())
+    } else {
+        CrawlError::FetchError(err.to_string())
+    }
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct CrawlResult {
     /// Used to determine
@@ -72,6 +150,24 @@ pub struct CrawlResult {
     pub links: HashSet<String>,
     /// Tags to apply to this document
     pub tags: Vec<TagPair>,
+    /// Structured (name, value) fields pulled from the document.
+    pub fields: Vec<(String, String)>,
+    /// Numeric (name, value) fields, e.g. file size or an extracted price.
+    pub numbers: Vec<(String, f64)>,
+    /// Code blocks found in the document, indexed separately for `code:` queries.
+    pub code: Vec<String>,
+    /// Anchored sections of the document, used to deep link into long pages.
+    pub sections: Vec<Section>,
+    /// When the document was written/sent/scheduled, if the source knows.
+    pub document_date: Option<DateTime<Utc>>,
+    /// Page asked not to be indexed, e.g. w/ `<meta name="robots" content="noindex">`.
+    /// Its links are still followed.
+    pub noindex: bool,
+    /// Words of text on the whole page, including navigation, footers, etc.
+    /// Only known for scraped web pages.
+    pub page_word_count: Option<usize>,
+    /// Response the page was fetched from, for web pages.
+    pub fetch_info: Option<FetchInfo>,
 }
 
 impl CrawlResult {
@@ -103,6 +199,56 @@ impl CrawlResult {
             ..Default::default()
         }
     }
+
+    /// Result for a document a parser plugin pulled the text out of.
+    fn from_parsed(url: &Url, parsed: ParsedDocument) -> Self {
+        let description = parsed.description.unwrap_or_else(|| {
+            parsed
+                .content
+                .split_whitespace()
+                .take(DEFAULT_DESC_LENGTH)
+                .collect::<Vec<&str>>()
+                .join(" ")
+        });
+
+        let mut result = CrawlResult::new(
+            url,
+            Some(url.to_string()),
+            &parsed.content,
+            "",
+            Some(description),
+        );
+        result.title = parsed.title;
+        result.fields = parsed.fields;
+        result
+    }
+
+    /// Follow a page's robots directives, unless a lens says to ignore them.
+    fn apply_robots(&mut self, directives: RobotsDirectives, options: &ScrapeOptions) {
+        if options.ignore_robots_meta {
+            return;
+        }
+
+        self.noindex |= directives.noindex;
+        if directives.nofollow {
+            self.links.clear();
+        }
+    }
+}
+
+/// Lowercase extension of the last segment of the `url` path, if it has one.
+fn url_extension(url: &Url) -> Option<String> {
+    Path::new(url.path())
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+}
+
+/// Pages are scraped as HTML unless the server says they're something else.
+fn is_html(mime_type: Option<&str>) -> bool {
+    mime_type.map_or(true, |mime_type| {
+        mime_type == "text/html" || mime_type == "application/xhtml+xml"
+    })
 }
 
 fn normalize_href(url: &str, href: &str) -> Option<String> {
@@ -134,6 +280,35 @@ fn normalize_href(url: &str, href: &str) -> Option<String> {
     None
 }
 
+/// Resolve the hrefs found on `url` into unique, crawlable URLs. Links that
+/// only differ by their fragment point to the same page.
+fn normalize_links(url: &str, links: &HashSet<String>) -> HashSet<String> {
+    let mut normalized = links
+        .iter()
+        .filter_map(|link| normalize_href(url, link))
+        .filter_map(|link| Url::parse(&link).ok())
+        .filter(|link| link.scheme() == "http" || link.scheme() == "https")
+        .map(|mut link| {
+            link.set_fragment(None);
+            link.to_string()
+        })
+        .collect::<Vec<_>>();
+    normalized.sort();
+    normalized.dedup();
+
+    if normalized.len() > MAX_LINKS_PER_PAGE {
+        log::debug!(
+            "<{}> has {} links, only following {}",
+            url,
+            normalized.len(),
+            MAX_LINKS_PER_PAGE
+        );
+        normalized.truncate(MAX_LINKS_PER_PAGE);
+    }
+
+    normalized.into_iter().collect()
+}
+
 #[derive(Debug, Clone)]
 pub struct Crawler {
     pub client: HTTPClient,
@@ -205,6 +380,56 @@ fn determine_canonical(original: &Url, extracted: Option<Url>) -> String {
     }
 }
 
+/// Lens specific options applied when scraping a page.
+#[derive(Clone, Debug)]
+pub struct ScrapeOptions {
+    /// Only index the main content of the page.
+    pub reader_mode: bool,
+    /// Structured fields to pull out of the page.
+    pub extract_rules: Vec<ExtractRule>,
+    /// Index pages & follow their links even if they ask us not to.
+    pub ignore_robots_meta: bool,
+    /// Follow links marked `rel="nofollow"`.
+    pub follow_nofollow_links: bool,
+}
+
+impl Default for ScrapeOptions {
+    fn default() -> Self {
+        Self {
+            reader_mode: true,
+            extract_rules: Vec::new(),
+            ignore_robots_meta: false,
+            follow_nofollow_links: false,
+        }
+    }
+}
+
+impl ScrapeOptions {
+    /// Combine the options from every lens `url` belongs to. Reader mode is
+    /// turned off if any of those lenses disable it.
+    pub fn for_url(state: &AppState, url: &Url) -> Self {
+        let mut options = Self {
+            follow_nofollow_links: state.user_settings.follow_nofollow_links,
+            ..Default::default()
+        };
+        for lens in state.lenses.iter() {
+            // Skip the (relatively expensive) URL check when the lens has
+            // nothing to contribute.
+            let has_options =
+                lens.disable_reader_mode || lens.ignore_robots_meta || !lens.extract.is_empty();
+            if !has_options || !lens.matches_url(url.as_str()) {
+                continue;
+            }
+
+            options.reader_mode &= !lens.disable_reader_mode;
+            options.ignore_robots_meta |= lens.ignore_robots_meta;
+            options.extract_rules.extend(lens.extract.clone());
+        }
+
+        options
+    }
+}
+
 impl Crawler {
     pub fn new() -> Self {
         Crawler {
@@ -212,9 +437,17 @@ impl Crawler {
         }
     }
 
-    /// Fetches and parses the content of a page.
-    async fn crawl(&self, url: &Url, parse_results: bool) -> Result<CrawlResult, CrawlError> {
+    /// Fetches and parses the content of a page, using the scrape `options`
+    /// from the lenses this page belongs to.
+    async fn crawl(
+        &self,
+        state: &AppState,
+        url: &Url,
+        parse_results: bool,
+        options: &ScrapeOptions,
+    ) -> Result<CrawlResult, CrawlError> {
         let url = url.clone();
+        let started = std::time::Instant::now();
 
         // Fetch & store page data.
         let res = self.client.get(&url).await;
@@ -223,7 +456,7 @@ impl Crawler {
             // Log out reason for failure.
             log::warn!("Unable to fetch <{}> due to {}", &url, err.to_string());
             // Unable to connect to host
-            return Err(CrawlError::FetchError(err.to_string()));
+            return Err(request_error(&err));
         }
 
         let res = res.expect("Expected valid response");
@@ -232,14 +465,80 @@ impl Crawler {
                 // Pull URL from request, this handles cases where we are 301 redirected
                 // to a different URL.
                 let end_url = res.url().to_owned();
+                let status = res.status().as_u16();
+                let content_type = res
+                    .headers()
+                    .get(reqwest::header::CONTENT_TYPE)
+                    .and_then(|value| value.to_str().ok())
+                    .map(|value| value.to_string());
+                // Called once the body is read so the duration covers it too.
+                let fetch_info = || FetchInfo {
+                    status,
+                    final_url: end_url.to_string(),
+                    content_type: content_type.clone(),
+                    duration_ms: started.elapsed().as_millis() as u64,
+                    fetched_at: Utc::now(),
+                };
+                let directives = res
+                    .headers()
+                    .get_all("x-robots-tag")
+                    .iter()
+                    .filter_map(|value| value.to_str().ok())
+                    .fold(RobotsDirectives::default(), |directives, value| {
+                        directives.merge(RobotsDirectives::parse(value))
+                    });
+                let mime_type = content_type
+                    .as_deref()
+                    .and_then(|value| value.split(';').next())
+                    .map(|value| value.trim().to_lowercase());
+
+                // Hand anything that isn't a web page to a parser plugin that
+                // understands it.
+                let extension = url_extension(&end_url);
+                let parser = if parse_results && !is_html(mime_type.as_deref()) {
+                    state
+                        .plugin_manager
+                        .lock()
+                        .await
+                        .find_parser(extension.as_deref(), mime_type.as_deref())
+                } else {
+                    None
+                };
+
+                if let Some(parser) = parser {
+                    let content = res
+                        .bytes()
+                        .await
+                        .map_err(|err| CrawlError::ParseError(err.to_string()))?;
+                    let request = ParseRequest {
+                        url: end_url.to_string(),
+                        extension,
+                        mime_type,
+                        content: content.to_vec(),
+                    };
+                    let parsed = parser
+                        .parse_document(&request)
+                        .await
+                        .map_err(|err| CrawlError::ParseError(err.to_string()))?;
+
+                    let mut result = CrawlResult::from_parsed(&end_url, parsed);
+                    result.apply_robots(directives, options);
+                    result.fetch_info = Some(fetch_info());
+                    return Ok(result);
+                }
+
                 match res.text().await {
                     Ok(raw_body) => {
                         if parse_results {
-                            Ok(self.scrape_page(&end_url, &raw_body).await)
+                            let mut result = self.scrape_page(&end_url, &raw_body, options).await;
+                            result.apply_robots(directives, options);
+                            result.fetch_info = Some(fetch_info());
+                            Ok(result)
                         } else {
                             Ok(CrawlResult {
                                 url: end_url.to_string(),
                                 open_url: Some(end_url.to_string()),
+                                fetch_info: Some(fetch_info()),
                                 ..Default::default()
                             })
                         }
@@ -247,21 +546,41 @@ impl Crawler {
                     Err(err) => Err(CrawlError::ParseError(err.to_string())),
                 }
             }
-            Err(err) => {
-                if err.status() == Some(reqwest::StatusCode::NOT_FOUND) {
-                    Err(CrawlError::NotFound)
-                } else {
-                    Err(CrawlError::FetchError(err.to_string()))
-                }
-            }
+            Err(err) => match err.status() {
+                Some(reqwest::StatusCode::NOT_FOUND) => Err(CrawlError::NotFound),
+                Some(status) => Err(CrawlError::HttpStatus(status.as_u16())),
+                None => Err(CrawlError::FetchError(err.to_string())),
+            },
         }
     }
 
-    pub async fn scrape_page(&self, url: &Url, raw_body: &str) -> CrawlResult {
+    #[tracing::instrument(skip(self, url, raw_body, options), fields(url = %url))]
+    pub async fn scrape_page(
+        &self,
+        url: &Url,
+        raw_body: &str,
+        options: &ScrapeOptions,
+    ) -> CrawlResult {
         // TODO: Cache the raw_body on the filesystem?
 
         // Parse the html.
-        let parse_result = html_to_text(raw_body);
+        let parse_result = html_to_text(raw_body, options.reader_mode);
+        let mut fields = parse_result.metadata;
+        let extracted = extract_fields(raw_body, &options.extract_rules);
+        // Extracted values that are numbers (prices, ratings, etc.) can also be
+        // filtered by range.
+        let numbers = extracted
+            .iter()
+            .filter_map(|(name, value)| {
+                value
+                    .trim()
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|value| value.is_finite())
+                    .map(|value| (name.clone(), value))
+            })
+            .collect();
+        fields.extend(extracted);
 
         // Hash the body content, used to detect changes (eventually).
         let mut hasher = Sha256::new();
@@ -270,23 +589,37 @@ impl Crawler {
         log::trace!("content hash: {:?}", content_hash);
 
         let canonical_url = determine_canonical(url, parse_result.canonical_url);
+        let directives = RobotsDirectives::from_meta(&parse_result.meta);
 
-        CrawlResult {
+        let mut links = parse_result.links;
+        if !options.follow_nofollow_links {
+            links.retain(|link| !parse_result.nofollow_links.contains(link));
+        }
+
+        let mut result = CrawlResult {
             content_hash,
             content: Some(parse_result.content),
             description: Some(parse_result.description),
             title: parse_result.title,
             url: canonical_url.clone(),
             open_url: Some(canonical_url),
-            links: parse_result.links,
+            links,
+            fields,
+            numbers,
+            code: parse_result.code,
+            sections: parse_result.sections,
+            page_word_count: Some(parse_result.page_word_count),
             ..Default::default()
-        }
+        };
+        result.apply_robots(directives, options);
+        result
     }
 
     // TODO: Load web indexing as a plugin?
     /// Attempts to crawl a job from the crawl_queue specific by <id>
     /// * Checks whether we can crawl using any saved rules or looking at the robots.txt
     /// * Fetches & parses the page
+    #[tracing::instrument(skip(self, state))]
     pub async fn fetch_by_job(
         &self,
         state: &AppState,
@@ -314,10 +647,15 @@ impl Crawler {
             Err(_) => return Err(CrawlError::NotFound),
         };
 
-        // Have we crawled this recently?
+        // Have we crawled this recently? Watched pages are refetched on their
+        // own, shorter, interval w/ some slack so scheduled checks aren't skipped.
+        let fetch_delay = match watched_page::find_by_url(&state.db, url.as_str()).await {
+            Ok(Some(watched)) => Duration::minutes(watched.check_interval_mins) / 2,
+            _ => Duration::milliseconds(FETCH_DELAY_MS),
+        };
         if let Ok(Some(history)) = fetch_history::find_by_url(&state.db, &url).await {
             let since_last_fetch = Utc::now() - history.updated_at;
-            if since_last_fetch < Duration::milliseconds(FETCH_DELAY_MS) {
+            if since_last_fetch < fetch_delay {
                 log::trace!("Recently fetched, skipping");
                 return Err(CrawlError::RecentlyFetched);
             }
@@ -328,10 +666,38 @@ impl Crawler {
         // handle any fetching/parsing.
         match url.scheme() {
             "api" => self.handle_api_fetch(state, &crawl, &url).await,
-            "file" => self.handle_file_fetch(&crawl, &url).await,
+            "file" => self.handle_file_fetch(state, &crawl, &url).await,
+            importer::IMPORT_SCHEME => importer::fetch(state, &url).await,
             "http" | "https" => {
-                self.handle_http_fetch(&state.db, &crawl, &url, parse_results)
+                let options = ScrapeOptions::for_url(state, &url);
+                let started = std::time::Instant::now();
+                let result = self
+                    .handle_http_fetch(state, &crawl, &url, parse_results, &options)
+                    .await;
+
+                // Skipped before anything was requested from the host.
+                if !matches!(
+                    result,
+                    Err(CrawlError::Denied(_)) | Err(CrawlError::RecentlyFetched)
+                ) {
+                    let error = match &result {
+                        Err(err) if err.is_host_failure() => Some(err.to_string()),
+                        _ => None,
+                    };
+                    let latency_ms = started.elapsed().as_millis() as i64;
+                    if let Err(err) = domain_stats::record_fetch(
+                        &state.db,
+                        &crawl.domain,
+                        latency_ms,
+                        error.as_deref(),
+                    )
                     .await
+                    {
+                        log::warn!("Unable to update stats for {}: {}", crawl.domain, err);
+                    }
+                }
+
+                result
             }
             // unknown scheme, ignore
             scheme => {
@@ -350,17 +716,48 @@ impl Crawler {
         let account = percent_decode_str(uri.username()).decode_utf8_lossy();
         let api_id = uri.host_str().unwrap_or_default();
 
-        match load_connection(state, api_id, &account).await {
+        if let Ok(Some(conn)) = connection::get_by_id(&state.db, api_id, &account).await {
+            if conn.is_paused() {
+                return Err(CrawlError::QuotaExceeded(api_id.to_string()));
+            }
+        }
+
+        let result = match load_connection(state, api_id, &account).await {
             Ok(mut conn) => conn.as_mut().get(uri).await,
-            Err(err) => Err(CrawlError::Unsupported(format!("{}: {}", api_id, err))),
+            Err(err) => return Err(CrawlError::Unsupported(format!("{}: {}", api_id, err))),
+        };
+
+        let num_bytes = result
+            .as_ref()
+            .map_or(0, |crawl| crawl.content.as_ref().map_or(0, |c| c.len()));
+        record_usage(state, api_id, &account, 1, num_bytes as u64).await;
+
+        // Don't index anything the connection's retention policy would drop.
+        if let (Ok(crawl), Some(days)) = (
+            &result,
+            state.user_settings.connection_retention_days.get(api_id),
+        ) {
+            let cutoff = Utc::now() - Duration::days(*days as i64);
+            if crawl.document_date.map_or(false, |date| date < cutoff) {
+                return Err(CrawlError::Denied("retention policy".to_string()));
+            }
         }
+
+        result
     }
 
     async fn handle_file_fetch(
         &self,
+        state: &AppState,
         _: &crawl_queue::Model,
         url: &Url,
     ) -> Result<CrawlResult, CrawlError> {
+        // Anything queued before it was added to the denylist
+        let denylist = crawl_queue::path_denylist(&state.user_settings);
+        if crawl_queue::is_denied_path(&denylist, url) {
+            return Err(CrawlError::Denied("index denylist".to_string()));
+        }
+
         // Attempt to convert from the URL to a file path
         let file_path = match url.to_file_path() {
             Ok(path) => path,
@@ -373,14 +770,122 @@ impl Crawler {
             return Err(CrawlError::NotFound);
         }
 
+        let file_size = path.metadata().map(|meta| meta.len()).unwrap_or_default();
         let file_name = path
             .file_name()
             .and_then(|x| x.to_str())
             .map(|x| x.to_string())
             .expect("Unable to convert path file name to string");
 
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .unwrap_or_default();
+        let mut media = if parser::is_media(extension) {
+            Some(parser::media_metadata(extension, path).unwrap_or_default())
+        } else {
+            None
+        };
+
+        // Formats we can't read ourselves may have a parser plugin
+        let plugin_extension = (!extension.is_empty()).then(|| extension.to_lowercase());
+        let plugin_parser = match &plugin_extension {
+            Some(ext) if media.is_none() && !parser::supports_filetype(OsStr::new(ext)) => state
+                .plugin_manager
+                .lock()
+                .await
+                .find_parser(Some(ext.as_str()), None),
+            _ => None,
+        };
+
+        if let Some(plugin_parser) = plugin_parser {
+            if file_size > MAX_PARSE_BYTES as u64 {
+                return Err(CrawlError::ParseError(format!(
+                    "too large to parse ({} bytes)",
+                    file_size
+                )));
+            }
+
+            let content =
+                std::fs::read(path).map_err(|err| CrawlError::FetchError(err.to_string()))?;
+            let request = ParseRequest {
+                url: url.to_string(),
+                extension: plugin_extension,
+                mime_type: None,
+                content,
+            };
+            let parsed = plugin_parser
+                .parse_document(&request)
+                .await
+                .map_err(|err| CrawlError::ParseError(err.to_string()))?;
+
+            let mut result = CrawlResult::from_parsed(url, parsed);
+            result.title = result.title.or(Some(file_name));
+            result.numbers.push(("size".to_string(), file_size as f64));
+            return Ok(result);
+        }
+
+        // Pull the text out of screenshots so they can be found by what was
+        // on screen.
+        let is_screenshot =
+            state.user_settings.screenshot_ocr && ocr::is_screenshot(&state.user_settings, path);
+        let screenshot_text = if is_screenshot {
+            if let Some(media) = media.as_mut() {
+                if media.captured_at.is_none() {
+                    media.captured_at = ocr::captured_at(path);
+                }
+            }
+
+            match ocr::ocr(path).await {
+                Ok(text) => text,
+                Err(err) => {
+                    log::warn!("Unable to OCR {:?}: {}", path, err);
+                    String::new()
+                }
+            }
+        } else {
+            String::new()
+        };
+
+        // Describe what's in photos when captioning is turned on
+        let labels = match &state.captioner {
+            Some(captioner) if parser::image::is_image(extension) => {
+                let captioner = captioner.clone();
+                let image_path = path.to_path_buf();
+                match tokio::task::spawn_blocking(move || captioner.caption(&image_path)).await {
+                    Ok(Ok(labels)) => labels,
+                    Ok(Err(err)) => {
+                        log::warn!("Unable to caption {:?}: {}", path, err);
+                        Vec::new()
+                    }
+                    Err(err) => {
+                        log::warn!("Captioning {:?} failed: {}", path, err);
+                        Vec::new()
+                    }
+                }
+            }
+            _ => Vec::new(),
+        };
+
         // Attempt to read file
         let contents = match path.extension() {
+            // No text to pull out of photos/videos, they're found by name &
+            // metadata instead.
+            Some(_) if media.is_some() => {
+                let summary = media
+                    .as_ref()
+                    .map(|media| media.summary())
+                    .unwrap_or_default();
+                format!(
+                    "{} {} {} {}",
+                    file_name,
+                    summary,
+                    labels.join(", "),
+                    screenshot_text
+                )
+                .trim()
+                .to_string()
+            }
             Some(ext) if parser::supports_filetype(ext) => match parser::parse_file(ext, path) {
                 Err(err) => return Err(CrawlError::ParseError(err.to_string())),
                 Ok(contents) => contents,
@@ -397,6 +902,31 @@ impl Crawler {
         hasher.update(contents.as_bytes());
         let content_hash = Some(hex::encode(&hasher.finalize()[..]));
 
+        let (code, sections) = match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("md") || ext.eq_ignore_ascii_case("markdown") => {
+                (
+                    parser::markdown::code_blocks(&contents),
+                    parser::markdown::sections(&contents),
+                )
+            }
+            _ => (Vec::new(), Vec::new()),
+        };
+
+        let mut numbers = vec![("size".to_string(), file_size as f64)];
+        let mut fields = Vec::new();
+        if let Some(media) = &media {
+            numbers.extend(media.numbers());
+            fields.extend(media.fields());
+        }
+        if is_screenshot {
+            if let Some(captured_at) = media.as_ref().and_then(|media| media.captured_at) {
+                numbers.push(("captured_at".to_string(), captured_at.timestamp() as f64));
+            }
+        }
+        for label in labels {
+            fields.push(("label".to_string(), label));
+        }
+
         // TODO: Better description building for text files?
         let description = if !contents.is_empty() {
             let desc = contents
@@ -419,6 +949,10 @@ impl Crawler {
             url: url.to_string(),
             open_url: Some(url.to_string()),
             links: Default::default(),
+            fields,
+            numbers,
+            code,
+            sections,
             ..Default::default()
         })
     }
@@ -426,10 +960,11 @@ impl Crawler {
     /// Handle HTTP related requests
     async fn handle_http_fetch(
         &self,
-        db: &DatabaseConnection,
+        state: &AppState,
         crawl: &crawl_queue::Model,
         url: &Url,
         parse_results: bool,
+        options: &ScrapeOptions,
     ) -> Result<CrawlResult, CrawlError> {
         // Modify bootstrapped URLs to pull from the Internet Archive
         let url: Url = if crawl.crawl_type == crawl_queue::CrawlType::Bootstrap {
@@ -442,15 +977,15 @@ impl Crawler {
         // When looking at bootstrapped tasks, check the original URL
         if crawl.crawl_type == crawl_queue::CrawlType::Bootstrap {
             let og_url = Url::parse(&crawl.url).expect("Invalid crawl URL");
-            if !check_resource_rules(db, &self.client, &og_url).await {
+            if !check_resource_rules(&state.db, &self.client, &og_url).await {
                 return Err(CrawlError::Denied("robots.txt".to_string()));
             }
-        } else if !check_resource_rules(db, &self.client, &url).await {
+        } else if !check_resource_rules(&state.db, &self.client, &url).await {
             return Err(CrawlError::Denied("robots.txt".to_string()));
         }
 
         // Crawl & save the data
-        match self.crawl(&url, parse_results).await {
+        match self.crawl(state, &url, parse_results, options).await {
             Err(err) => {
                 log::debug!("issue fetching {:?} - {}", url, err.to_string());
                 Err(err)
@@ -470,12 +1005,7 @@ impl Crawler {
 
                 // Normalize links from scrape result. If the links start with "/" they
                 // should be appended to the current URL.
-                let normalized_links = result
-                    .links
-                    .iter()
-                    .filter_map(|link| normalize_href(&result.url, link))
-                    .collect();
-                result.links = normalized_links;
+                result.links = normalize_links(&result.url, &result.links);
 
                 log::trace!(
                     "crawl result: {:?} - {:?}\n{:?}",
@@ -493,8 +1023,15 @@ impl Crawler {
                     path = format!("{}?{}", path, query);
                 }
 
-                let _ = fetch_history::upsert(db, domain, &path, result.content_hash.clone(), 200)
-                    .await;
+                let status = result.fetch_info.as_ref().map_or(200, |info| info.status);
+                let _ = fetch_history::upsert(
+                    &state.db,
+                    domain,
+                    &path,
+                    result.content_hash.clone(),
+                    status,
+                )
+                .await;
 
                 Ok(result)
             }
@@ -510,7 +1047,10 @@ mod test {
     use entities::test::setup_test_db;
     use spyglass_plugin::utils::path_to_uri;
 
-    use crate::crawler::{determine_canonical, normalize_href, Crawler};
+    use crate::crawler::{
+        determine_canonical, is_html, normalize_href, normalize_links, url_extension, CrawlError,
+        Crawler, ScrapeOptions, MAX_LINKS_PER_PAGE,
+    };
     use crate::state::AppState;
     use std::path::Path;
     use url::Url;
@@ -519,8 +1059,12 @@ mod test {
     #[ignore]
     async fn test_crawl() {
         let crawler = Crawler::new();
+        let state = AppState::builder().with_db(setup_test_db().await).build();
         let url = Url::parse("https://oldschool.runescape.wiki").unwrap();
-        let result = crawler.crawl(&url, true).await.expect("success");
+        let result = crawler
+            .crawl(&state, &url, true, &ScrapeOptions::default())
+            .await
+            .expect("success");
 
         assert_eq!(result.title, Some("Old School RuneScape Wiki".to_string()));
         assert_eq!(result.url, "https://oldschool.runescape.wiki/".to_string());
@@ -634,6 +1178,30 @@ mod test {
         assert!(res.is_err());
     }
 
+    #[test]
+    fn test_is_host_failure() {
+        assert!(CrawlError::Timeout.is_host_failure());
+        assert!(CrawlError::HttpStatus(503).is_host_failure());
+        assert!(CrawlError::HttpStatus(429).is_host_failure());
+        assert!(!CrawlError::HttpStatus(404).is_host_failure());
+        assert!(!CrawlError::NotFound.is_host_failure());
+        assert!(!CrawlError::ParseError("no content".into()).is_host_failure());
+    }
+
+    #[test]
+    fn test_plugin_parser_routing() {
+        let url = |url: &str| Url::parse(url).unwrap();
+        assert_eq!(
+            url_extension(&url("https://example.com/notes/todo.ORG?raw=1")),
+            Some("org".to_string())
+        );
+        assert_eq!(url_extension(&url("https://example.com/notes/")), None);
+
+        assert!(is_html(None));
+        assert!(is_html(Some("text/html")));
+        assert!(!is_html(Some("application/onenote")));
+    }
+
     #[test]
     fn test_normalize_href() {
         let url = "https://example.com";
@@ -664,6 +1232,31 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_normalize_links() {
+        let links = vec![
+            "/docs",
+            "/docs#install",
+            "https://example.com/docs",
+            "mailto:hi@example.com",
+            "javascript:void(0)",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect();
+
+        let normalized = normalize_links("https://example.com/", &links);
+        assert_eq!(normalized.len(), 1);
+        assert!(normalized.contains("https://example.com/docs"));
+
+        // Capped so a single page can't flood the queue
+        let links = (0..MAX_LINKS_PER_PAGE * 2)
+            .map(|idx| format!("/page/{}", idx))
+            .collect();
+        let normalized = normalize_links("https://example.com/", &links);
+        assert_eq!(normalized.len(), MAX_LINKS_PER_PAGE);
+    }
+
     #[test]
     fn test_determine_canonical() {
         // Test a correct override
@@ -702,6 +1295,42 @@ mod test {
         assert_eq!(res, "https://docs.rs/test/0.0.1/lib.rs.html");
     }
 
+    #[tokio::test]
+    async fn test_scrape_robots_meta() {
+        let crawler = Crawler::new();
+        let url = Url::parse("https://example.com/drafts").unwrap();
+        let page = |content: &str| {
+            format!(
+                "<html><head><meta name=\"robots\" content=\"{}\"></head>\
+                <body><p>Draft</p><a href=\"/drafts/1\">First</a></body></html>",
+                content
+            )
+        };
+
+        let result = crawler
+            .scrape_page(&url, &page("noindex"), &ScrapeOptions::default())
+            .await;
+        assert!(result.noindex);
+        assert_eq!(result.links.len(), 1);
+
+        let result = crawler
+            .scrape_page(&url, &page("noindex, nofollow"), &ScrapeOptions::default())
+            .await;
+        assert!(result.noindex);
+        assert!(result.links.is_empty());
+
+        // Lenses can opt out
+        let options = ScrapeOptions {
+            ignore_robots_meta: true,
+            ..Default::default()
+        };
+        let result = crawler
+            .scrape_page(&url, &page("noindex, nofollow"), &options)
+            .await;
+        assert!(!result.noindex);
+        assert_eq!(result.links.len(), 1);
+    }
+
     #[tokio::test]
     async fn test_file_fetch() {
         let crawler = Crawler::new();
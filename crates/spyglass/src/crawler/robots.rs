@@ -4,12 +4,13 @@
 /// - https://www.robotstxt.org/robotstxt.html
 use regex::RegexSet;
 use reqwest::StatusCode;
+use std::collections::HashMap;
 use std::convert::From;
 use url::Url;
 
-use entities::models::resource_rule;
+use entities::models::{resource_rule, robots};
 use entities::sea_orm::prelude::*;
-use entities::sea_orm::{DatabaseConnection, Set};
+use entities::sea_orm::DatabaseConnection;
 use shared::regex::{regex_for_robots, WildcardType};
 
 use super::client::HTTPClient;
@@ -44,6 +45,78 @@ pub fn filter_set(rules: &[ParsedRule], allow: bool) -> RegexSet {
     RegexSet::new(rules).expect("Invalid regex rules")
 }
 
+/// Directives that take a value, e.g. `max-snippet: 20`, so aren't mistaken
+/// for a user agent.
+const VALUE_DIRECTIVES: [&str; 4] = [
+    "max-snippet",
+    "max-image-preview",
+    "max-video-preview",
+    "unavailable_after",
+];
+
+/// Indexing directives from a page's `<meta name="robots">` tag or its
+/// `X-Robots-Tag` header.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RobotsDirectives {
+    /// Don't index the page.
+    pub noindex: bool,
+    /// Don't follow links on the page.
+    pub nofollow: bool,
+}
+
+impl RobotsDirectives {
+    /// Parse a comma separated list of directives, e.g. `noindex, nofollow`.
+    /// Directives for another crawler, e.g. `googlebot: noindex`, are ignored.
+    pub fn parse(value: &str) -> Self {
+        let value = value.trim().to_lowercase();
+        let value = match value.split_once(':') {
+            Some((agent, rest))
+                if !agent.contains(',') && !VALUE_DIRECTIVES.contains(&agent.trim()) =>
+            {
+                if agent.trim() != BOT_AGENT_NAME {
+                    return Self::default();
+                }
+                rest.to_string()
+            }
+            _ => value,
+        };
+
+        let mut directives = Self::default();
+        for directive in value.split(',').map(|directive| directive.trim()) {
+            match directive {
+                "noindex" => directives.noindex = true,
+                "nofollow" => directives.nofollow = true,
+                "none" => {
+                    directives.noindex = true;
+                    directives.nofollow = true;
+                }
+                _ => {}
+            }
+        }
+
+        directives
+    }
+
+    /// Directives from the `robots` & `spyglass` meta tags.
+    pub fn from_meta(meta: &HashMap<String, String>) -> Self {
+        meta.iter()
+            .filter(|(name, _)| {
+                name.eq_ignore_ascii_case("robots") || name.eq_ignore_ascii_case(BOT_AGENT_NAME)
+            })
+            .fold(Self::default(), |directives, (_, value)| {
+                directives.merge(Self::parse(value))
+            })
+    }
+
+    /// Most restrictive of both.
+    pub fn merge(self, other: Self) -> Self {
+        RobotsDirectives {
+            noindex: self.noindex || other.noindex,
+            nofollow: self.nofollow || other.nofollow,
+        }
+    }
+}
+
 /// Parse a robots.txt file and return a vector of parsed rules
 pub fn parse(domain: &str, txt: &str) -> Vec<ParsedRule> {
     let mut rules = Vec::new();
@@ -95,10 +168,114 @@ pub fn parse(domain: &str, txt: &str) -> Vec<ParsedRule> {
     rules
 }
 
+/// Crawl-delay (in seconds) requested for us in a robots.txt file, if any.
+pub fn parse_crawl_delay(txt: &str) -> Option<f64> {
+    let mut user_agent: Option<String> = None;
+    for line in txt.split('\n') {
+        if let Some((prefix, end)) = line.trim().split_once(':') {
+            let prefix = prefix.to_lowercase();
+            if prefix.starts_with("user-agent") {
+                user_agent = Some(end.trim().to_string());
+            } else if prefix.starts_with("crawl-delay") {
+                let applies = user_agent
+                    .as_ref()
+                    .map_or(false, |agent| agent == "*" || agent == BOT_AGENT_NAME);
+                if applies {
+                    if let Ok(delay) = end.trim().parse::<f64>() {
+                        if delay.is_finite() && delay >= 0.0 {
+                            return Some(delay);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    None
+}
+
+impl From<ParsedRule> for robots::RobotsRule {
+    fn from(rule: ParsedRule) -> Self {
+        robots::RobotsRule {
+            regex: rule.regex,
+            allow_crawl: rule.allow_crawl,
+        }
+    }
+}
+
+/// Robots.txt rules for the domain of `url`. Uses the cached copy unless it's
+/// missing or stale, in which case the robots.txt is fetched & cached again.
+pub async fn robots_for(
+    db: &DatabaseConnection,
+    client: &HTTPClient,
+    url: &Url,
+) -> Option<robots::Model> {
+    let domain = url.host_str()?;
+    let cached = robots::find_by_domain(db, domain).await.ok().flatten();
+    if let Some(cached) = &cached {
+        if !cached.is_stale() {
+            return Some(cached.clone());
+        }
+    }
+
+    log::info!("fetching robots.txt for <{}>", domain);
+    let mut robots_url = url.clone();
+    robots_url.set_path("/robots.txt");
+    robots_url.set_query(None);
+
+    let parsed = match client.get(&robots_url).await {
+        Ok(res) if res.status() == StatusCode::OK => match res.text().await {
+            Ok(body) => Some((
+                parse(domain, &body),
+                parse_crawl_delay(&body).map(|secs| (secs * 1000.0) as i64),
+            )),
+            Err(err) => {
+                log::warn!("Unable to read robots.txt for <{}>: {}", domain, err);
+                None
+            }
+        },
+        // No robots.txt? Treat as an allow all
+        Ok(res) if res.status().is_client_error() => Some((Vec::new(), None)),
+        Ok(res) => {
+            log::warn!("robots.txt for <{}> returned {}", domain, res.status());
+            None
+        }
+        Err(err) => {
+            log::error!("Unable to check robots.txt {}", err.to_string());
+            None
+        }
+    };
+
+    // Couldn't fetch a new copy, keep using the stale one until we can
+    let (rules, crawl_delay_ms) = match parsed {
+        Some(parsed) => parsed,
+        None => return cached,
+    };
+
+    let rules = rules
+        .into_iter()
+        .map(robots::RobotsRule::from)
+        .collect::<Vec<_>>();
+    if let Err(err) = robots::upsert(db, domain, rules, crawl_delay_ms).await {
+        log::error!("Unable to cache robots.txt for <{}>: {}", domain, err);
+    }
+
+    robots::find_by_domain(db, domain).await.ok().flatten()
+}
+
 // Checks whether we're allow to crawl this url
 pub async fn check_resource_rules(db: &DatabaseConnection, client: &HTTPClient, url: &Url) -> bool {
     let domain = url.host_str().unwrap_or_default();
-    let path = url[url::Position::BeforePath..].to_string();
+    let path = robots::robots_path(url);
+
+    if domain != "localhost" {
+        if let Some(robots) = robots_for(db, client, url).await {
+            if !robots.is_allowed(&path) {
+                log::info!("Unable to crawl `{}` due to robots.txt", url.as_str());
+                return false;
+            }
+        }
+    }
 
     let rules = resource_rule::Entity::find()
         .filter(resource_rule::Column::Domain.eq(domain))
@@ -106,60 +283,6 @@ pub async fn check_resource_rules(db: &DatabaseConnection, client: &HTTPClient,
         .await
         .expect("Unable to add resource rules");
 
-    if rules.is_empty() && domain != "localhost" {
-        log::info!("No rules found for <{}>, fetching robot.txt", domain);
-        let mut robots_url = url.clone();
-        robots_url.set_path("/robots.txt");
-
-        let res = client.get(&robots_url).await;
-        match res {
-            Err(err) => log::error!("Unable to check robots.txt {}", err.to_string()),
-            Ok(res) => {
-                match res.status() {
-                    StatusCode::OK => {
-                        if let Ok(body) = res.text().await {
-                            let parsed_rules = parse(domain, &body);
-                            // No rules? Treat as an allow all
-                            if parsed_rules.is_empty() {
-                                let new_rule = resource_rule::ActiveModel {
-                                    domain: Set(domain.to_owned()),
-                                    rule: Set("/".to_owned()),
-                                    no_index: Set(false),
-                                    allow_crawl: Set(true),
-                                    ..Default::default()
-                                };
-                                let _ = new_rule.insert(db).await;
-                            } else {
-                                for rule in parsed_rules.iter() {
-                                    let new_rule = resource_rule::ActiveModel {
-                                        domain: Set(rule.domain.to_owned()),
-                                        rule: Set(rule.regex.to_owned()),
-                                        no_index: Set(false),
-                                        allow_crawl: Set(rule.allow_crawl),
-                                        ..Default::default()
-                                    };
-                                    let _ = new_rule.insert(db).await;
-                                }
-                            }
-                        }
-                    }
-                    // No robots.txt? Treat as an allow all
-                    StatusCode::NOT_FOUND => {
-                        let new_rule = resource_rule::ActiveModel {
-                            domain: Set(domain.to_owned()),
-                            rule: Set("/".to_owned()),
-                            no_index: Set(false),
-                            allow_crawl: Set(true),
-                            ..Default::default()
-                        };
-                        let _ = new_rule.insert(db).await;
-                    }
-                    _ => {}
-                }
-            }
-        }
-    }
-
     // Check path against rules, if we find any matches that disallow, skip it
     let rules_into: Vec<ParsedRule> = rules.iter().map(|x| x.to_owned().into()).collect();
 
@@ -202,10 +325,12 @@ pub async fn check_resource_rules(db: &DatabaseConnection, client: &HTTPClient,
 
 #[cfg(test)]
 mod test {
-    use super::{check_resource_rules, filter_set, parse, ParsedRule};
+    use super::{
+        check_resource_rules, filter_set, parse, parse_crawl_delay, ParsedRule, RobotsDirectives,
+    };
     use crate::crawler::Crawler;
 
-    use entities::models::resource_rule;
+    use entities::models::{resource_rule, robots};
     use entities::sea_orm::{ActiveModelTrait, Set};
     use entities::test::setup_test_db;
     use regex::Regex;
@@ -219,6 +344,55 @@ mod test {
         assert_eq!(matches.len(), 59);
     }
 
+    #[test]
+    fn test_parse_directives() {
+        let parse = RobotsDirectives::parse;
+        assert_eq!(
+            parse("noindex, nofollow"),
+            RobotsDirectives {
+                noindex: true,
+                nofollow: true
+            }
+        );
+        assert_eq!(parse("NOINDEX"), parse("noindex"));
+        assert!(parse("noindex").noindex && !parse("noindex").nofollow);
+        assert!(parse("none").noindex && parse("none").nofollow);
+        assert_eq!(parse("index, follow"), RobotsDirectives::default());
+        // Only directives for us, or every crawler, apply
+        assert!(parse("spyglass: noindex").noindex);
+        assert!(!parse("googlebot: noindex").noindex);
+        assert!(parse("max-snippet: 20, noindex").noindex);
+
+        let meta = [
+            ("description".to_string(), "noindex".to_string()),
+            ("robots".to_string(), "nofollow".to_string()),
+            ("spyglass".to_string(), "noindex".to_string()),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            RobotsDirectives::from_meta(&meta),
+            RobotsDirectives {
+                noindex: true,
+                nofollow: true
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_crawl_delay() {
+        let robots_txt = "User-agent: googlebot\nCrawl-delay: 30\n\nUser-agent: *\nDisallow: /private\nCrawl-delay: 2.5\n";
+        assert_eq!(parse_crawl_delay(robots_txt), Some(2.5));
+        assert_eq!(
+            parse_crawl_delay("User-agent: googlebot\nCrawl-delay: 30\n"),
+            None
+        );
+        assert_eq!(
+            parse_crawl_delay("User-agent: *\nCrawl-delay: soon\n"),
+            None
+        );
+    }
+
     #[test]
     fn test_parse_large() {
         let robots_txt = include_str!("../../../../fixtures/robots/reddit_com.txt");
@@ -309,4 +483,24 @@ mod test {
 
         assert_eq!(res, true);
     }
+
+    #[tokio::test]
+    async fn test_check_cached_robots() {
+        let crawler = Crawler::new();
+        let db = setup_test_db().await;
+
+        let robots_txt = include_str!("../../../../fixtures/robots/oldschool_runescape_wiki.txt");
+        let rules = parse("oldschool.runescape.wiki", robots_txt)
+            .into_iter()
+            .map(robots::RobotsRule::from)
+            .collect();
+        robots::upsert(&db, "oldschool.runescape.wiki", rules, None)
+            .await
+            .expect("Unable to cache robots.txt");
+
+        // Denied by the cached robots.txt, w/o refetching it
+        let url = url::Url::parse("https://oldschool.runescape.wiki/api.php").unwrap();
+        let res = check_resource_rules(&db, &crawler.client, &url).await;
+        assert_eq!(res, false);
+    }
 }
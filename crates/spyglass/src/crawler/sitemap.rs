@@ -0,0 +1,314 @@
+/// Bulk enqueue URLs listed in a site's sitemap.xml.
+/// See https://www.sitemaps.org/protocol.html for the format.
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use flate2::read::GzDecoder;
+use url::Url;
+
+use entities::models::crawl_queue::{self, EnqueueSettings, TaskPriority};
+use entities::models::indexed_document;
+use entities::models::tag::TagType;
+use entities::sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
+use shared::config::LensConfig;
+
+use super::client::HTTPClient;
+use crate::parser::xml::{self, XmlNode};
+use crate::state::AppState;
+
+/// Max number of sitemap files fetched for a single sitemap (index).
+const MAX_SITEMAPS: usize = 100;
+const BATCH_SIZE: usize = 1_000;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SitemapEntry {
+    pub loc: String,
+    pub lastmod: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Sitemap {
+    UrlSet(Vec<SitemapEntry>),
+    /// Sitemap index files point to other sitemaps.
+    Index(Vec<String>),
+}
+
+/// W3C datetime, either a full timestamp or just a date.
+fn parse_lastmod(lastmod: &str) -> Option<DateTime<Utc>> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(lastmod) {
+        return Some(date.with_timezone(&Utc));
+    }
+
+    NaiveDate::parse_from_str(lastmod, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|date| DateTime::<Utc>::from_utc(date, Utc))
+}
+
+fn entries(root: &XmlNode, name: &str) -> Vec<SitemapEntry> {
+    root.children(name)
+        .filter_map(|node| {
+            Some(SitemapEntry {
+                loc: node.child_text("loc")?.to_string(),
+                lastmod: node.child_text("lastmod").and_then(parse_lastmod),
+            })
+        })
+        .collect()
+}
+
+pub fn parse(body: &str) -> anyhow::Result<Sitemap> {
+    let root = xml::parse(body)?;
+    match root.name.as_str() {
+        "urlset" => Ok(Sitemap::UrlSet(entries(&root, "url"))),
+        "sitemapindex" => Ok(Sitemap::Index(
+            entries(&root, "sitemap")
+                .into_iter()
+                .map(|entry| entry.loc)
+                .collect(),
+        )),
+        other => Err(anyhow::anyhow!("Not a sitemap, root element: <{}>", other)),
+    }
+}
+
+/// Sitemaps can be served gzip'd (`sitemap.xml.gz`), check for the gzip magic
+/// bytes rather than trusting the file extension.
+fn decode(bytes: &[u8]) -> anyhow::Result<String> {
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        let mut body = String::new();
+        GzDecoder::new(bytes).read_to_string(&mut body)?;
+        Ok(body)
+    } else {
+        Ok(String::from_utf8_lossy(bytes).to_string())
+    }
+}
+
+/// Most recently modified first, entries w/o a lastmod last. Duplicate URLs
+/// keep their newest lastmod.
+pub fn prioritize(entries: Vec<SitemapEntry>) -> Vec<SitemapEntry> {
+    let mut by_loc: HashMap<String, Option<DateTime<Utc>>> = HashMap::new();
+    for entry in entries {
+        let lastmod = by_loc.entry(entry.loc).or_default();
+        *lastmod = (*lastmod).max(entry.lastmod);
+    }
+
+    let mut entries = by_loc
+        .into_iter()
+        .map(|(loc, lastmod)| SitemapEntry { loc, lastmod })
+        .collect::<Vec<_>>();
+    entries.sort_by(|a, b| b.lastmod.cmp(&a.lastmod).then_with(|| a.loc.cmp(&b.loc)));
+    entries
+}
+
+/// Fetch a sitemap & any sitemaps it points to.
+pub(crate) async fn fetch_all(client: &HTTPClient, url: &Url) -> Vec<SitemapEntry> {
+    let mut to_fetch = vec![url.to_string()];
+    let mut seen = HashSet::new();
+    let mut found = Vec::new();
+
+    while let Some(next) = to_fetch.pop() {
+        if seen.len() >= MAX_SITEMAPS {
+            log::warn!("Stopped after {} sitemaps for <{}>", MAX_SITEMAPS, url);
+            break;
+        }
+
+        if !seen.insert(next.clone()) {
+            continue;
+        }
+
+        let sitemap = match Url::parse(&next) {
+            Ok(next_url) => fetch_sitemap(client, &next_url).await,
+            Err(err) => Err(anyhow::anyhow!(err)),
+        };
+
+        match sitemap {
+            Ok(Sitemap::UrlSet(entries)) => found.extend(entries),
+            Ok(Sitemap::Index(sitemaps)) => to_fetch.extend(sitemaps),
+            Err(err) => log::warn!("Unable to read sitemap <{}>: {}", next, err),
+        }
+    }
+
+    found
+}
+
+async fn fetch_sitemap(client: &HTTPClient, url: &Url) -> anyhow::Result<Sitemap> {
+    let res = client.get(url).await?;
+    if !res.status().is_success() {
+        return Err(anyhow::anyhow!("status {}", res.status()));
+    }
+
+    let bytes = res.bytes().await?;
+    parse(&decode(&bytes)?)
+}
+
+/// Enqueue the URLs in a lens sitemap, most recently modified first. Indexed
+/// pages are only recrawled if the sitemap says they changed since.
+pub async fn ingest(
+    state: &AppState,
+    lens: &LensConfig,
+    url: &Url,
+    pipeline: Option<String>,
+) -> anyhow::Result<usize> {
+    let client = HTTPClient::new();
+    let entries = prioritize(fetch_all(&client, url).await);
+    log::info!("found {} urls in sitemap <{}>", entries.len(), url);
+
+    let tags = vec![(TagType::Lens, lens.name.to_string())];
+    let overrides = EnqueueSettings {
+        tags: tags.clone(),
+        priority: TaskPriority::Lens,
+        ..Default::default()
+    };
+    let recrawl = EnqueueSettings {
+        tags,
+        is_recrawl: true,
+        priority: TaskPriority::Recrawl,
+        ..Default::default()
+    };
+
+    let mut count = 0;
+    for chunk in entries.chunks(BATCH_SIZE) {
+        let urls = chunk
+            .iter()
+            .map(|entry| entry.loc.clone())
+            .collect::<Vec<_>>();
+        let indexed = indexed_document::Entity::find()
+            .filter(indexed_document::Column::Url.is_in(urls.clone()))
+            .all(&state.db)
+            .await?
+            .into_iter()
+            .map(|doc| (doc.url, doc.updated_at))
+            .collect::<HashMap<_, _>>();
+
+        let changed = chunk
+            .iter()
+            .filter(|entry| match (indexed.get(&entry.loc), entry.lastmod) {
+                (Some(updated_at), Some(lastmod)) => lastmod > *updated_at,
+                _ => false,
+            })
+            .map(|entry| entry.loc.clone())
+            .collect::<Vec<_>>();
+
+        let lenses = [lens.clone()];
+        crawl_queue::enqueue_all(
+            &state.db,
+            &urls,
+            &lenses,
+            &state.user_settings,
+            &overrides,
+            pipeline.clone(),
+        )
+        .await?;
+
+        if !changed.is_empty() {
+            crawl_queue::enqueue_all(
+                &state.db,
+                &changed,
+                &lenses,
+                &state.user_settings,
+                &recrawl,
+                pipeline.clone(),
+            )
+            .await?;
+        }
+
+        count += urls.len() - indexed.len() + changed.len();
+    }
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use super::{decode, parse, parse_lastmod, prioritize, Sitemap, SitemapEntry};
+
+    const URLSET: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+        <urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+            <url>
+                <loc>https://example.com/old</loc>
+                <lastmod>2021-01-01</lastmod>
+            </url>
+            <url>
+                <loc>https://example.com/new</loc>
+                <lastmod>2022-12-01T10:00:00+00:00</lastmod>
+            </url>
+            <url>
+                <loc>https://example.com/unknown</loc>
+            </url>
+        </urlset>"#;
+
+    #[test]
+    fn test_parse_urlset() {
+        let sitemap = parse(URLSET).expect("Unable to parse sitemap");
+        match sitemap {
+            Sitemap::UrlSet(entries) => {
+                assert_eq!(entries.len(), 3);
+                assert_eq!(entries[0].loc, "https://example.com/old");
+                assert_eq!(entries[0].lastmod, parse_lastmod("2021-01-01"));
+                assert!(entries[2].lastmod.is_none());
+            }
+            _ => panic!("Expected a urlset"),
+        }
+    }
+
+    #[test]
+    fn test_parse_index() {
+        let index = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">
+                <sitemap><loc>https://example.com/sitemap1.xml.gz</loc></sitemap>
+                <sitemap><loc>https://example.com/sitemap2.xml</loc></sitemap>
+            </sitemapindex>"#;
+
+        assert_eq!(
+            parse(index).unwrap(),
+            Sitemap::Index(vec![
+                "https://example.com/sitemap1.xml.gz".to_string(),
+                "https://example.com/sitemap2.xml".to_string(),
+            ])
+        );
+        assert!(parse("<html><body></body></html>").is_err());
+    }
+
+    #[test]
+    fn test_decode_gzip() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(URLSET.as_bytes()).unwrap();
+        let gzipped = encoder.finish().unwrap();
+
+        assert_eq!(decode(&gzipped).unwrap(), URLSET);
+        assert_eq!(decode(URLSET.as_bytes()).unwrap(), URLSET);
+    }
+
+    #[test]
+    fn test_prioritize() {
+        let entries = match parse(URLSET).unwrap() {
+            Sitemap::UrlSet(entries) => entries,
+            _ => panic!("Expected a urlset"),
+        };
+
+        let mut entries = entries;
+        // Duplicates keep the newest lastmod
+        entries.push(SitemapEntry {
+            loc: "https://example.com/old".into(),
+            lastmod: parse_lastmod("2023-01-01"),
+        });
+
+        let urls = prioritize(entries)
+            .into_iter()
+            .map(|entry| entry.loc)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            urls,
+            vec![
+                "https://example.com/old",
+                "https://example.com/new",
+                "https://example.com/unknown",
+            ]
+        );
+    }
+}
@@ -0,0 +1,241 @@
+use std::io::Read;
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use flate2::read::GzDecoder;
+use jsonrpsee::core::async_trait;
+use rusqlite::{Connection, OpenFlags, OptionalExtension};
+
+use super::{ImportedDoc, Importer};
+
+/// Core Data timestamps count seconds from 2001-01-01 rather than the unix epoch.
+const CORE_DATA_EPOCH_OFFSET: i64 = 978_307_200;
+/// Guards against cycles when walking up nested folders.
+const MAX_FOLDER_DEPTH: usize = 16;
+
+const LIST_QUERY: &str = "SELECT n.Z_PK FROM ZICCLOUDSYNCINGOBJECT n \
+    JOIN ZICNOTEDATA d ON d.ZNOTE = n.Z_PK \
+    WHERE n.ZTITLE1 IS NOT NULL AND IFNULL(n.ZMARKEDFORDELETION, 0) = 0";
+
+const NOTE_QUERY: &str =
+    "SELECT n.ZTITLE1, n.ZIDENTIFIER, n.ZMODIFICATIONDATE1, d.ZDATA, n.ZFOLDER \
+    FROM ZICCLOUDSYNCINGOBJECT n \
+    JOIN ZICNOTEDATA d ON d.ZNOTE = n.Z_PK \
+    WHERE n.Z_PK = ?1 AND IFNULL(n.ZMARKEDFORDELETION, 0) = 0";
+
+const FOLDER_QUERY: &str = "SELECT ZTITLE2, ZPARENT FROM ZICCLOUDSYNCINGOBJECT WHERE Z_PK = ?1";
+
+/// Imports notes from the Apple Notes database on macOS.
+pub struct AppleNotesImporter {
+    path: Option<PathBuf>,
+}
+
+impl AppleNotesImporter {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self { path }
+    }
+
+    fn db_path(&self) -> Option<PathBuf> {
+        self.path.clone().or_else(|| {
+            dirs::home_dir().map(|home| {
+                home.join("Library/Group Containers/group.com.apple.notes/NoteStore.sqlite")
+            })
+        })
+    }
+
+    fn open(&self) -> anyhow::Result<Connection> {
+        let path = self
+            .db_path()
+            .ok_or_else(|| anyhow!("Unable to find Apple Notes database"))?;
+
+        // Notes keeps the database open, so only ever read from it.
+        Ok(Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?)
+    }
+
+    /// Names of the folder w/ `folder_id` & its parents, outermost first.
+    fn folders(conn: &Connection, folder_id: Option<i64>) -> anyhow::Result<Vec<String>> {
+        let mut folders = Vec::new();
+        let mut next = folder_id;
+        while let Some(folder_id) = next {
+            if folders.len() >= MAX_FOLDER_DEPTH {
+                break;
+            }
+
+            let folder = conn
+                .query_row(FOLDER_QUERY, [folder_id], |row| {
+                    Ok((
+                        row.get::<_, Option<String>>(0)?,
+                        row.get::<_, Option<i64>>(1)?,
+                    ))
+                })
+                .optional()?;
+
+            match folder {
+                Some((name, parent)) => {
+                    if let Some(name) = name {
+                        folders.push(name);
+                    }
+                    next = parent;
+                }
+                None => break,
+            }
+        }
+
+        folders.reverse();
+        Ok(folders)
+    }
+}
+
+#[async_trait]
+impl Importer for AppleNotesImporter {
+    fn id(&self) -> &'static str {
+        "apple-notes"
+    }
+
+    async fn list(&self) -> anyhow::Result<Vec<String>> {
+        let conn = self.open()?;
+        let mut stmt = conn.prepare(LIST_QUERY)?;
+        let ids = stmt
+            .query_map([], |row| row.get::<_, i64>(0))?
+            .filter_map(|id| id.ok())
+            .map(|id| id.to_string())
+            .collect();
+
+        Ok(ids)
+    }
+
+    async fn get(&self, doc_id: &str) -> anyhow::Result<Option<ImportedDoc>> {
+        let note_id: i64 = doc_id.parse()?;
+        let conn = self.open()?;
+
+        let note = conn
+            .query_row(NOTE_QUERY, [note_id], |row| {
+                Ok((
+                    row.get::<_, Option<String>>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    row.get::<_, Option<f64>>(2)?,
+                    row.get::<_, Option<Vec<u8>>>(3)?,
+                    row.get::<_, Option<i64>>(4)?,
+                ))
+            })
+            .optional()?;
+
+        let (title, identifier, modified, data, folder_id) = match note {
+            Some(note) => note,
+            None => return Ok(None),
+        };
+
+        let content = match data {
+            Some(data) => note_text(&data)?,
+            None => String::new(),
+        };
+
+        Ok(Some(ImportedDoc {
+            id: doc_id.to_string(),
+            title: title.unwrap_or_default(),
+            content,
+            open_url: identifier.map(|id| format!("notes://showNote?identifier={}", id)),
+            folders: Self::folders(&conn, folder_id)?,
+            updated_at: modified.and_then(from_core_data),
+            ..Default::default()
+        }))
+    }
+}
+
+pub(super) fn from_core_data(timestamp: f64) -> Option<DateTime<Utc>> {
+    NaiveDateTime::from_timestamp_opt(timestamp as i64 + CORE_DATA_EPOCH_OFFSET, 0)
+        .map(|time| DateTime::<Utc>::from_utc(time, Utc))
+}
+
+fn read_varint(buf: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *buf.get(*pos)?;
+        *pos += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+
+    None
+}
+
+/// First length delimited protobuf field w/ `number` in `buf`.
+fn proto_field(buf: &[u8], number: u64) -> Option<&[u8]> {
+    let mut pos = 0;
+    while pos < buf.len() {
+        let key = read_varint(buf, &mut pos)?;
+        match key & 0x7 {
+            0 => {
+                read_varint(buf, &mut pos)?;
+            }
+            1 => pos += 8,
+            2 => {
+                let len = read_varint(buf, &mut pos)? as usize;
+                let value = buf.get(pos..pos.checked_add(len)?)?;
+                if key >> 3 == number {
+                    return Some(value);
+                }
+                pos += len;
+            }
+            5 => pos += 4,
+            _ => return None,
+        }
+    }
+
+    None
+}
+
+/// Plain text of a note, stored as a gzipped protobuf w/ the text @
+/// document (2) -> note (3) -> text (2).
+fn note_text(data: &[u8]) -> anyhow::Result<String> {
+    let mut decoded = Vec::new();
+    GzDecoder::new(data).read_to_end(&mut decoded)?;
+
+    let text = proto_field(&decoded, 2)
+        .and_then(|document| proto_field(document, 3))
+        .and_then(|note| proto_field(note, 2))
+        .ok_or_else(|| anyhow!("Unable to find note text"))?;
+
+    // Attachments are marked w/ object replacement characters
+    Ok(String::from_utf8_lossy(text).replace('\u{fffc}', ""))
+}
+
+#[cfg(test)]
+mod test {
+    use std::io::Write;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+
+    use super::note_text;
+
+    fn length_delimited(number: u8, value: &[u8]) -> Vec<u8> {
+        let mut field = vec![(number << 3) | 2, value.len() as u8];
+        field.extend_from_slice(value);
+        field
+    }
+
+    #[test]
+    fn test_note_text() {
+        let text = "Groceries\nmilk, eggs\u{fffc}";
+        // Version field before the note, which should be skipped over.
+        let mut note = vec![0x08, 0x01];
+        note.extend(length_delimited(2, text.as_bytes()));
+        let document = length_delimited(3, &note);
+        let mut proto = vec![0x08, 0x00];
+        proto.extend(length_delimited(2, &document));
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&proto).unwrap();
+        let data = encoder.finish().unwrap();
+
+        assert_eq!(note_text(&data).unwrap(), "Groceries\nmilk, eggs");
+        assert!(note_text(b"not gzipped").is_err());
+    }
+}
@@ -0,0 +1,349 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use entities::models::tag::TagType;
+use jsonrpsee::core::async_trait;
+use rusqlite::{Connection, OpenFlags};
+use sha2::{Digest, Sha256};
+
+use super::apple_notes::from_core_data;
+use super::{ImportedDoc, Importer};
+
+/// Separates clippings in a Kindle's `My Clippings.txt`
+const CLIPPING_SEPARATOR: &str = "==========";
+
+const ANNOTATION_QUERY: &str = "SELECT ZANNOTATIONUUID, ZANNOTATIONASSETID, \
+    ZANNOTATIONSELECTEDTEXT, ZANNOTATIONNOTE, ZANNOTATIONMODIFICATIONDATE \
+    FROM ZAEANNOTATION \
+    WHERE IFNULL(ZANNOTATIONDELETED, 0) = 0 \
+    AND (ZANNOTATIONSELECTEDTEXT IS NOT NULL OR ZANNOTATIONNOTE IS NOT NULL)";
+
+const BOOK_QUERY: &str = "SELECT ZASSETID, ZTITLE, ZAUTHOR FROM ZBKLIBRARYASSET";
+
+/// A highlighted passage (or note) from a book.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Highlight {
+    pub id: String,
+    pub book: String,
+    pub author: Option<String>,
+    /// Where in the book the highlight is, e.g. "page 12"
+    pub location: Option<String>,
+    pub text: String,
+    pub note: Option<String>,
+    pub added_at: Option<DateTime<Utc>>,
+    /// Opens the book in its reader app.
+    pub open_url: Option<String>,
+}
+
+impl Highlight {
+    fn to_doc(&self) -> ImportedDoc {
+        let title = match &self.location {
+            Some(location) => format!("{} ({})", self.book, location),
+            None => self.book.clone(),
+        };
+
+        let content = [Some(&self.text), self.note.as_ref()]
+            .into_iter()
+            .flatten()
+            .filter(|text| !text.is_empty())
+            .cloned()
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        let mut doc = ImportedDoc {
+            id: self.id.clone(),
+            title,
+            content,
+            open_url: self.open_url.clone(),
+            folders: vec![self.book.clone()],
+            updated_at: self.added_at,
+            fields: vec![("book".to_string(), self.book.clone())],
+            ..Default::default()
+        };
+
+        if let Some(author) = &self.author {
+            doc.tags.push((TagType::Owner, author.clone()));
+            doc.fields.push(("author".to_string(), author.clone()));
+        }
+
+        doc
+    }
+}
+
+/// Imports highlights & notes from a Kindle's `My Clippings.txt`
+pub struct KindleImporter {
+    path: PathBuf,
+}
+
+impl KindleImporter {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    fn highlights(&self) -> anyhow::Result<Vec<Highlight>> {
+        // Point at either the mounted Kindle's documents folder or the file
+        let path = if self.path.is_dir() {
+            self.path.join("My Clippings.txt")
+        } else {
+            self.path.clone()
+        };
+
+        let clippings = String::from_utf8_lossy(&std::fs::read(path)?).to_string();
+        Ok(parse_clippings(&clippings))
+    }
+}
+
+/// "Book Title (Author Name)" -> ("Book Title", Some("Author Name"))
+fn split_book_author(line: &str) -> (String, Option<String>) {
+    let line = line.trim_start_matches('\u{feff}').trim();
+    if line.ends_with(')') {
+        if let Some(idx) = line.rfind(" (") {
+            let author = &line[idx + 2..line.len() - 1];
+            return (line[..idx].trim().to_string(), Some(author.to_string()));
+        }
+    }
+
+    (line.to_string(), None)
+}
+
+/// Parse the highlights & notes in `My Clippings.txt`. Bookmarks have no text
+/// so they're skipped.
+pub fn parse_clippings(clippings: &str) -> Vec<Highlight> {
+    clippings
+        .split(CLIPPING_SEPARATOR)
+        .filter_map(|clipping| {
+            let mut lines = clipping.trim().lines();
+            let (book, author) = split_book_author(lines.next()?);
+            // e.g. "- Your Highlight on page 12 | Location 180-182 | Added on Thursday, December 1, 2022 9:30:15 AM"
+            let meta = lines.next()?.trim_start_matches('-').trim();
+            let text = lines.collect::<Vec<&str>>().join("\n").trim().to_string();
+            if text.is_empty() {
+                return None;
+            }
+
+            let parts = meta.split(" | ").collect::<Vec<&str>>();
+            let is_note = parts
+                .first()
+                .map_or(false, |part| part.starts_with("Your Note"));
+            let location = parts
+                .iter()
+                .filter(|part| !part.starts_with("Added on"))
+                .map(|part| {
+                    part.split_once(" on ")
+                        .map_or(*part, |(_, location)| location)
+                })
+                .collect::<Vec<&str>>()
+                .join(", ");
+            let added_at = parts
+                .iter()
+                .find_map(|part| part.strip_prefix("Added on "))
+                .and_then(|added| {
+                    NaiveDateTime::parse_from_str(added, "%A, %B %d, %Y %I:%M:%S %p").ok()
+                })
+                .map(|added| DateTime::<Utc>::from_utc(added, Utc));
+
+            let id = hex::encode(Sha256::digest(format!("{}\n{}", book, meta).as_bytes()));
+            let (text, note) = if is_note {
+                (String::new(), Some(text))
+            } else {
+                (text, None)
+            };
+
+            Some(Highlight {
+                id: id[..16].to_string(),
+                book,
+                author,
+                location: Some(location).filter(|location| !location.is_empty()),
+                text,
+                note,
+                added_at,
+                open_url: None,
+            })
+        })
+        .collect()
+}
+
+/// Imports highlights & notes from Apple Books on macOS.
+pub struct AppleBooksImporter {
+    path: Option<PathBuf>,
+}
+
+impl AppleBooksImporter {
+    pub fn new(path: Option<PathBuf>) -> Self {
+        Self { path }
+    }
+
+    fn documents_dir(&self) -> Option<PathBuf> {
+        self.path.clone().or_else(|| {
+            dirs::home_dir()
+                .map(|home| home.join("Library/Containers/com.apple.iBooksX/Data/Documents"))
+        })
+    }
+
+    /// Apple Books names its databases w/ a version suffix, e.g.
+    /// `AEAnnotation/AEAnnotation_v10312011_1727_local.sqlite`
+    fn open(&self, store: &str) -> anyhow::Result<Connection> {
+        let dir = self
+            .documents_dir()
+            .ok_or_else(|| anyhow!("Unable to find Apple Books data"))?
+            .join(store);
+
+        let db = std::fs::read_dir(&dir)?
+            .flatten()
+            .map(|entry| entry.path())
+            .find(|path| {
+                path.extension().map_or(false, |ext| ext == "sqlite")
+                    && path
+                        .file_name()
+                        .and_then(|name| name.to_str())
+                        .map_or(false, |name| name.starts_with(store))
+            })
+            .ok_or_else(|| anyhow!("No {} database found in {:?}", store, dir))?;
+
+        Ok(Connection::open_with_flags(
+            db,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?)
+    }
+
+    fn highlights(&self) -> anyhow::Result<Vec<Highlight>> {
+        let library = self.open("BKLibrary")?;
+        let mut stmt = library.prepare(BOOK_QUERY)?;
+        let books = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    (
+                        row.get::<_, Option<String>>(1)?,
+                        row.get::<_, Option<String>>(2)?,
+                    ),
+                ))
+            })?
+            .flatten()
+            .collect::<HashMap<String, (Option<String>, Option<String>)>>();
+
+        let annotations = self.open("AEAnnotation")?;
+        let mut stmt = annotations.prepare(ANNOTATION_QUERY)?;
+        let highlights = stmt
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                    row.get::<_, Option<f64>>(4)?,
+                ))
+            })?
+            .flatten()
+            .map(|(id, asset_id, text, note, modified)| {
+                let (book, author) = books.get(&asset_id).cloned().unwrap_or_default();
+                Highlight {
+                    id,
+                    open_url: Some(format!("ibooks://assetid/{}", asset_id)),
+                    book: book.unwrap_or(asset_id),
+                    author,
+                    location: None,
+                    text: text.unwrap_or_default(),
+                    note: note.filter(|note| !note.trim().is_empty()),
+                    added_at: modified.and_then(from_core_data),
+                }
+            })
+            .collect();
+
+        Ok(highlights)
+    }
+}
+
+#[async_trait]
+impl Importer for KindleImporter {
+    fn id(&self) -> &'static str {
+        "kindle"
+    }
+
+    async fn list(&self) -> anyhow::Result<Vec<String>> {
+        Ok(self.highlights()?.into_iter().map(|h| h.id).collect())
+    }
+
+    async fn get(&self, doc_id: &str) -> anyhow::Result<Option<ImportedDoc>> {
+        Ok(self
+            .highlights()?
+            .iter()
+            .find(|highlight| highlight.id == doc_id)
+            .map(|highlight| highlight.to_doc()))
+    }
+}
+
+#[async_trait]
+impl Importer for AppleBooksImporter {
+    fn id(&self) -> &'static str {
+        "apple-books"
+    }
+
+    async fn list(&self) -> anyhow::Result<Vec<String>> {
+        Ok(self.highlights()?.into_iter().map(|h| h.id).collect())
+    }
+
+    async fn get(&self, doc_id: &str) -> anyhow::Result<Option<ImportedDoc>> {
+        Ok(self
+            .highlights()?
+            .iter()
+            .find(|highlight| highlight.id == doc_id)
+            .map(|highlight| highlight.to_doc()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use entities::models::tag::TagType;
+
+    use super::parse_clippings;
+
+    #[test]
+    fn test_parse_clippings() {
+        let clippings = "\u{feff}Why We Sleep (Matthew Walker)\r\n\
+            - Your Highlight on page 12 | Location 180-182 | Added on Thursday, December 1, 2022 9:30:15 PM\r\n\
+            \r\n\
+            Caffeine has an average half-life of five to seven hours.\r\n\
+            ==========\r\n\
+            Why We Sleep (Matthew Walker)\r\n\
+            - Your Bookmark on page 14 | Location 201 | Added on Thursday, December 1, 2022 9:41:02 PM\r\n\
+            \r\n\
+            \r\n\
+            ==========\r\n\
+            Piranesi (Clarke, Susanna)\r\n\
+            - Your Note on Location 55 | Added on Friday, December 2, 2022 8:00:00 AM\r\n\
+            \r\n\
+            the house is infinite\r\n\
+            ==========\r\n";
+
+        let highlights = parse_clippings(clippings);
+        assert_eq!(highlights.len(), 2);
+
+        let highlight = &highlights[0];
+        assert_eq!(highlight.book, "Why We Sleep");
+        assert_eq!(highlight.author.as_deref(), Some("Matthew Walker"));
+        assert_eq!(
+            highlight.location.as_deref(),
+            Some("page 12, Location 180-182")
+        );
+        assert_eq!(
+            highlight.added_at.map(|added| added.to_rfc3339()),
+            Some("2022-12-01T21:30:15+00:00".to_string())
+        );
+
+        let doc = highlight.to_doc();
+        assert_eq!(doc.title, "Why We Sleep (page 12, Location 180-182)");
+        assert_eq!(
+            doc.tags,
+            vec![(TagType::Owner, "Matthew Walker".to_string())]
+        );
+        assert_eq!(doc.folders, vec!["Why We Sleep".to_string()]);
+
+        let note = &highlights[1];
+        assert_eq!(note.author.as_deref(), Some("Clarke, Susanna"));
+        assert_eq!(note.note.as_deref(), Some("the house is infinite"));
+        assert_ne!(note.id, highlight.id);
+    }
+}
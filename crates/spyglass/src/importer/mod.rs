@@ -0,0 +1,322 @@
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use entities::models::crawl_queue::{self, CrawlType, EnqueueSettings};
+use entities::models::tag::{TagPair, TagType};
+use jsonrpsee::core::async_trait;
+use percent_encoding::percent_decode_str;
+use shared::config::ImportSource;
+use url::Url;
+
+use crate::crawler::{CrawlError, CrawlResult};
+use crate::state::AppState;
+
+pub mod apple_notes;
+pub mod books;
+pub mod onenote;
+pub mod podcasts;
+pub mod telegram;
+pub mod whatsapp;
+pub mod youtube;
+
+/// Scheme used for documents pulled out of other apps, e.g.
+/// `import://apple-notes/1234`
+pub const IMPORT_SCHEME: &str = "import";
+
+/// A document read out of another app's data or export.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImportedDoc {
+    /// Identifies the document within its source.
+    pub id: String,
+    pub title: String,
+    pub content: String,
+    /// URL used to open the document in its app, if it has one.
+    pub open_url: Option<String>,
+    /// Notebook/folder the document is filed under, outermost first.
+    pub folders: Vec<String>,
+    pub updated_at: Option<DateTime<Utc>>,
+    /// Any other tags to apply, e.g. who sent the messages in a chat.
+    pub tags: Vec<TagPair>,
+    /// Structured (name, value) fields, e.g. the days a chat was active.
+    pub fields: Vec<(String, String)>,
+}
+
+impl ImportedDoc {
+    pub fn to_crawl_result(&self, uri: &Url) -> CrawlResult {
+        let mut result =
+            CrawlResult::new(uri, self.open_url.clone(), &self.content, &self.title, None);
+
+        result.tags = self
+            .folders
+            .iter()
+            .map(|folder| (TagType::Folder, folder.clone()))
+            .chain(self.tags.iter().cloned())
+            .collect();
+        result.fields = self.fields.clone();
+        result.document_date = self.updated_at;
+
+        if let Some(updated_at) = &self.updated_at {
+            result.fields.push((
+                "updated".to_string(),
+                updated_at.format("%Y-%m-%d").to_string(),
+            ));
+            result
+                .numbers
+                .push(("updated_at".to_string(), updated_at.timestamp() as f64));
+        }
+
+        result
+    }
+}
+
+/// A single message in an exported chat.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChatMessage {
+    pub sender: String,
+    pub sent_at: NaiveDateTime,
+    pub text: String,
+}
+
+/// Turn a conversation into a single document, tagged w/ everyone who sent a
+/// message & the days it was active.
+pub fn chat_to_doc(id: &str, title: &str, messages: &[ChatMessage]) -> ImportedDoc {
+    let content = messages
+        .iter()
+        .map(|msg| {
+            format!(
+                "{} ({}): {}",
+                msg.sender,
+                msg.sent_at.format("%Y-%m-%d %H:%M"),
+                msg.text
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let senders = messages
+        .iter()
+        .map(|msg| msg.sender.clone())
+        .collect::<BTreeSet<String>>();
+    let days = messages
+        .iter()
+        .map(|msg| msg.sent_at.format("%Y-%m-%d").to_string())
+        .collect::<BTreeSet<String>>();
+
+    ImportedDoc {
+        id: id.to_string(),
+        title: title.to_string(),
+        content,
+        updated_at: messages
+            .iter()
+            .map(|msg| msg.sent_at)
+            .max()
+            .map(|sent_at| DateTime::<Utc>::from_utc(sent_at, Utc)),
+        tags: senders
+            .into_iter()
+            .map(|sender| (TagType::Sender, sender))
+            .collect(),
+        fields: days
+            .into_iter()
+            .map(|day| ("date".to_string(), day))
+            .collect(),
+        ..Default::default()
+    }
+}
+
+/// Reads documents out of another app's local data or exported files.
+#[async_trait]
+pub trait Importer {
+    /// Used as the host of this importer's `import://` URIs.
+    fn id(&self) -> &'static str;
+
+    /// IDs of every document currently in the source.
+    async fn list(&self) -> anyhow::Result<Vec<String>>;
+
+    /// Read a single document, `None` if it no longer exists.
+    async fn get(&self, doc_id: &str) -> anyhow::Result<Option<ImportedDoc>>;
+}
+
+pub fn load_importer(source: &ImportSource) -> Box<dyn Importer + Send + Sync> {
+    match source {
+        ImportSource::AppleBooks { path } => Box::new(books::AppleBooksImporter::new(path.clone())),
+        ImportSource::AppleNotes { path } => {
+            Box::new(apple_notes::AppleNotesImporter::new(path.clone()))
+        }
+        ImportSource::Kindle { path } => Box::new(books::KindleImporter::new(path.clone())),
+        ImportSource::OneNote { path } => Box::new(onenote::OneNoteImporter::new(path.clone())),
+        ImportSource::Podcasts { path, transcripts } => {
+            Box::new(podcasts::PodcastImporter::new(path.clone(), *transcripts))
+        }
+        ImportSource::Telegram { path } => Box::new(telegram::TelegramImporter::new(path.clone())),
+        ImportSource::WhatsApp { path } => Box::new(whatsapp::WhatsAppImporter::new(path.clone())),
+        ImportSource::YouTube { path, transcripts } => {
+            Box::new(youtube::YouTubeImporter::new(path.clone(), *transcripts))
+        }
+    }
+}
+
+pub fn to_uri(importer_id: &str, doc_id: &str) -> Url {
+    let mut uri = Url::parse(&format!("{}://{}/", IMPORT_SCHEME, importer_id))
+        .expect("Unable to create import URI");
+    uri.path_segments_mut()
+        .expect("Import URI can't be a base")
+        .pop_if_empty()
+        .push(doc_id);
+
+    uri
+}
+
+/// (importer id, document id) for an `import://` URI.
+pub fn parse_uri(uri: &Url) -> Option<(String, String)> {
+    if uri.scheme() != IMPORT_SCHEME {
+        return None;
+    }
+
+    let importer_id = uri.host_str()?.to_string();
+    let doc_id = percent_decode_str(uri.path().trim_start_matches('/'))
+        .decode_utf8_lossy()
+        .to_string();
+
+    if doc_id.is_empty() {
+        None
+    } else {
+        Some((importer_id, doc_id))
+    }
+}
+
+/// Add every document in `source` to the crawl queue.
+pub async fn sync(state: &AppState, source: ImportSource) {
+    let importer = load_importer(&source);
+    let importer_id = importer.id();
+
+    let doc_ids = match importer.list().await {
+        Ok(doc_ids) => doc_ids,
+        Err(err) => {
+            log::error!("Unable to import from {}: {}", importer_id, err);
+            return;
+        }
+    };
+
+    log::debug!(
+        "found {} docs to import from {}",
+        doc_ids.len(),
+        importer_id
+    );
+    let urls = doc_ids
+        .iter()
+        .map(|doc_id| to_uri(importer_id, doc_id).to_string())
+        .collect::<Vec<String>>();
+
+    let enqueue_settings = EnqueueSettings {
+        crawl_type: CrawlType::Api,
+        tags: vec![(TagType::Source, importer_id.to_string())],
+        force_allow: true,
+        is_recrawl: true,
+    };
+
+    if let Err(err) = crawl_queue::enqueue_all(
+        &state.db,
+        &urls,
+        &[],
+        &state.user_settings,
+        &enqueue_settings,
+        None,
+    )
+    .await
+    {
+        log::error!("Unable to enqueue imported docs: {}", err);
+    }
+}
+
+/// Read the document an `import://` URI points to from whichever configured
+/// source has it.
+pub async fn fetch(state: &AppState, uri: &Url) -> Result<CrawlResult, CrawlError> {
+    let (importer_id, doc_id) = parse_uri(uri).ok_or(CrawlError::NotFound)?;
+    for source in &state.user_settings.imports {
+        let importer = load_importer(source);
+        if importer.id() != importer_id {
+            continue;
+        }
+
+        match importer.get(&doc_id).await {
+            Ok(Some(doc)) => return Ok(doc.to_crawl_result(uri)),
+            Ok(None) => {}
+            Err(err) => return Err(CrawlError::FetchError(err.to_string())),
+        }
+    }
+
+    Err(CrawlError::NotFound)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+    use entities::models::tag::TagType;
+
+    use super::{chat_to_doc, parse_uri, to_uri, ChatMessage};
+
+    #[test]
+    fn test_chat_to_doc() {
+        let at = |day, hour| {
+            NaiveDate::from_ymd_opt(2022, 12, day)
+                .and_then(|date| date.and_hms_opt(hour, 0, 0))
+                .unwrap()
+        };
+        let messages = vec![
+            ChatMessage {
+                sender: "Bob".to_string(),
+                sent_at: at(1, 9),
+                text: "lunch?".to_string(),
+            },
+            ChatMessage {
+                sender: "Alice".to_string(),
+                sent_at: at(2, 12),
+                text: "sure".to_string(),
+            },
+        ];
+
+        let doc = chat_to_doc("chat-1", "Alice", &messages);
+        assert_eq!(
+            doc.content,
+            "Bob (2022-12-01 09:00): lunch?\nAlice (2022-12-02 12:00): sure"
+        );
+        assert_eq!(
+            doc.tags,
+            vec![
+                (TagType::Sender, "Alice".to_string()),
+                (TagType::Sender, "Bob".to_string())
+            ]
+        );
+        assert_eq!(
+            doc.fields,
+            vec![
+                ("date".to_string(), "2022-12-01".to_string()),
+                ("date".to_string(), "2022-12-02".to_string())
+            ]
+        );
+        assert_eq!(doc.updated_at.unwrap().naive_utc(), at(2, 12));
+    }
+
+    #[test]
+    fn test_import_uri() {
+        let uri = to_uri("onenote", "/exports/Work/Meetings/Standup 12-01.mht");
+        assert_eq!(
+            uri.as_str(),
+            "import://onenote/%2Fexports%2FWork%2FMeetings%2FStandup%2012-01.mht"
+        );
+        assert_eq!(
+            parse_uri(&uri),
+            Some((
+                "onenote".to_string(),
+                "/exports/Work/Meetings/Standup 12-01.mht".to_string()
+            ))
+        );
+
+        let uri = to_uri("apple-notes", "42");
+        assert_eq!(uri.as_str(), "import://apple-notes/42");
+        assert_eq!(
+            parse_uri(&uri),
+            Some(("apple-notes".to_string(), "42".to_string()))
+        );
+    }
+}
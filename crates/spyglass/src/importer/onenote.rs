@@ -0,0 +1,253 @@
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use ignore::WalkBuilder;
+use jsonrpsee::core::async_trait;
+
+use super::{ImportedDoc, Importer};
+use crate::parser;
+use crate::scraper::html_to_text;
+
+const PAGE_EXTENSIONS: [&str; 3] = ["mht", "mhtml", "docx"];
+
+/// Imports pages from exported OneNote notebooks.
+pub struct OneNoteImporter {
+    root: PathBuf,
+}
+
+impl OneNoteImporter {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn is_page(path: &Path) -> bool {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .map_or(false, |ext| {
+                PAGE_EXTENSIONS
+                    .iter()
+                    .any(|supported| supported.eq_ignore_ascii_case(ext))
+            })
+    }
+
+    /// Notebook & section(s) a page is filed under, based on where it sits in
+    /// the export folder.
+    fn folders(&self, page: &Path) -> Vec<String> {
+        page.parent()
+            .and_then(|parent| parent.strip_prefix(&self.root).ok())
+            .map(|relative| {
+                relative
+                    .components()
+                    .map(|component| component.as_os_str().to_string_lossy().to_string())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+#[async_trait]
+impl Importer for OneNoteImporter {
+    fn id(&self) -> &'static str {
+        "onenote"
+    }
+
+    async fn list(&self) -> anyhow::Result<Vec<String>> {
+        if !self.root.is_dir() {
+            return Err(anyhow::anyhow!(
+                "OneNote export folder {:?} not found",
+                self.root
+            ));
+        }
+
+        let pages = WalkBuilder::new(&self.root)
+            .standard_filters(true)
+            .build()
+            .flatten()
+            .filter(|entry| entry.file_type().map_or(false, |ft| ft.is_file()))
+            .filter(|entry| Self::is_page(entry.path()))
+            .map(|entry| entry.path().display().to_string())
+            .collect();
+
+        Ok(pages)
+    }
+
+    async fn get(&self, doc_id: &str) -> anyhow::Result<Option<ImportedDoc>> {
+        let path = Path::new(doc_id);
+        // Only read pages from inside the export folder
+        if !path.starts_with(&self.root) || !path.is_file() || !Self::is_page(path) {
+            return Ok(None);
+        }
+
+        let extension = path.extension().unwrap_or_default();
+        let content = if extension.eq_ignore_ascii_case("docx") {
+            parser::parse_file(OsStr::new("docx"), path)?
+        } else {
+            let raw = String::from_utf8_lossy(&std::fs::read(path)?).to_string();
+            match mht_to_html(&raw) {
+                Some(html) => html_to_text(&html, false).content,
+                None => return Err(anyhow::anyhow!("No HTML found in {:?}", path)),
+            }
+        };
+
+        let updated_at = path
+            .metadata()
+            .and_then(|meta| meta.modified())
+            .ok()
+            .map(DateTime::<Utc>::from);
+
+        Ok(Some(ImportedDoc {
+            id: doc_id.to_string(),
+            title: path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            content,
+            open_url: url::Url::from_file_path(path)
+                .ok()
+                .map(|url| url.to_string()),
+            folders: self.folders(path),
+            updated_at,
+            ..Default::default()
+        }))
+    }
+}
+
+/// Split a MIME part into its (lowercased) headers & body.
+fn split_part(part: &str) -> (String, &str) {
+    let part = part.trim_start_matches(['\r', '\n']);
+    let (headers, body) = part
+        .split_once("\r\n\r\n")
+        .or_else(|| part.split_once("\n\n"))
+        .unwrap_or(("", part));
+
+    (headers.to_lowercase(), body)
+}
+
+fn decode_quoted_printable(body: &str) -> String {
+    let bytes = body.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut idx = 0;
+    while idx < bytes.len() {
+        if bytes[idx] == b'=' {
+            // Soft line break
+            if bytes[idx + 1..].starts_with(b"\r\n") {
+                idx += 3;
+                continue;
+            } else if bytes[idx + 1..].starts_with(b"\n") {
+                idx += 2;
+                continue;
+            }
+
+            let hex = bytes
+                .get(idx + 1..idx + 3)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+            if let Some(byte) = hex {
+                decoded.push(byte);
+                idx += 3;
+                continue;
+            }
+        }
+
+        decoded.push(bytes[idx]);
+        idx += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Boundary between the parts of a multipart MIME document, from its headers.
+fn mime_boundary(raw: &str) -> Option<String> {
+    let headers_end = raw
+        .find("\r\n\r\n")
+        .or_else(|| raw.find("\n\n"))
+        .unwrap_or(raw.len());
+    let headers = &raw[..headers_end];
+
+    // Boundaries are case sensitive, only the parameter name isn't.
+    let start = headers.to_ascii_lowercase().find("boundary=")? + "boundary=".len();
+    let boundary = headers[start..]
+        .trim_start_matches('"')
+        .split(|c| matches!(c, '"' | ';' | '\r' | '\n'))
+        .next()?;
+
+    if boundary.is_empty() {
+        None
+    } else {
+        Some(boundary.to_string())
+    }
+}
+
+/// Pull the HTML page out of a single file web page (`.mht`) archive.
+fn mht_to_html(raw: &str) -> Option<String> {
+    let boundary = mime_boundary(raw);
+    let parts = match &boundary {
+        Some(boundary) => raw.split(&format!("--{}", boundary)).skip(1).collect(),
+        None => vec![raw],
+    };
+
+    for part in parts {
+        let (headers, body) = split_part(part);
+        if !headers.contains("text/html") {
+            continue;
+        }
+
+        let html = if headers.contains("quoted-printable") {
+            decode_quoted_printable(body)
+        } else if headers.contains("base64") {
+            let body = body.split_whitespace().collect::<String>();
+            String::from_utf8_lossy(&base64::decode(body).ok()?).to_string()
+        } else {
+            body.to_string()
+        };
+
+        return Some(html);
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::{Path, PathBuf};
+
+    use super::{mht_to_html, OneNoteImporter};
+
+    #[test]
+    fn test_mht_to_html() {
+        let raw = "MIME-Version: 1.0\r\n\
+            Content-Type: multipart/related; boundary=\"----=_NextPart_01D9\"\r\n\
+            \r\n\
+            ------=_NextPart_01D9\r\n\
+            Content-Type: text/html; charset=\"utf-8\"\r\n\
+            Content-Transfer-Encoding: quoted-printable\r\n\
+            \r\n\
+            <html><body><p class=3D\"title\">Standup</p><p>Ship the =\r\n\
+            release</p></body></html>\r\n\
+            ------=_NextPart_01D9\r\n\
+            Content-Type: image/png\r\n\
+            Content-Transfer-Encoding: base64\r\n\
+            \r\n\
+            iVBORw0KGgo=\r\n\
+            ------=_NextPart_01D9--\r\n";
+
+        assert_eq!(
+            mht_to_html(raw).unwrap().trim(),
+            "<html><body><p class=\"title\">Standup</p><p>Ship the release</p></body></html>"
+        );
+        assert!(mht_to_html("Content-Type: image/png\r\n\r\nabc").is_none());
+    }
+
+    #[test]
+    fn test_folders() {
+        let importer = OneNoteImporter::new(PathBuf::from("/exports"));
+        assert_eq!(
+            importer.folders(Path::new("/exports/Work/Meetings/Standup.mht")),
+            vec!["Work".to_string(), "Meetings".to_string()]
+        );
+        assert!(importer
+            .folders(Path::new("/elsewhere/Page.mht"))
+            .is_empty());
+    }
+}
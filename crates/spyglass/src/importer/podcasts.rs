@@ -0,0 +1,357 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use jsonrpsee::core::async_trait;
+
+use super::{ImportedDoc, Importer};
+use crate::parser::xml::{self, XmlNode};
+use crate::scraper::html_to_text;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+/// Long enough for the crawler to get through the episodes queued by a sync.
+const FEED_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
+/// Transcript formats we can read, most preferred first.
+const TRANSCRIPT_TYPES: [&str; 5] = [
+    "text/vtt",
+    "application/x-subrip",
+    "application/srt",
+    "text/plain",
+    "text/html",
+];
+
+/// Imports episodes (titles & show notes) of the podcasts in an OPML
+/// subscription list, which most podcast apps can export.
+pub struct PodcastImporter {
+    opml: PathBuf,
+    transcripts: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Episode {
+    guid: String,
+    title: String,
+    show_notes: String,
+    link: Option<String>,
+    published_at: Option<DateTime<Utc>>,
+    transcript_url: Option<String>,
+}
+
+struct CachedFeed {
+    /// Subscription list the feed was found in.
+    opml: PathBuf,
+    url: String,
+    show: String,
+    episodes: Vec<Episode>,
+    fetched_at: Instant,
+}
+
+/// Feeds parsed by the last sync, so they're not downloaded again for each of
+/// their episodes. Only feeds in the subscription list at the time are kept.
+static FEED_CACHE: Mutex<Vec<CachedFeed>> = Mutex::new(Vec::new());
+
+impl PodcastImporter {
+    pub fn new(opml: PathBuf, transcripts: bool) -> Self {
+        Self { opml, transcripts }
+    }
+
+    fn feed_urls(&self) -> anyhow::Result<Vec<String>> {
+        let opml = xml::parse(&std::fs::read_to_string(&self.opml)?)?;
+        Ok(feed_urls(&opml))
+    }
+
+    /// (podcast title, episode) from a feed parsed by the last sync, `None` if
+    /// the feed isn't cached.
+    fn cached_episode(&self, feed_url: &str, guid: &str) -> Option<(String, Option<Episode>)> {
+        let cache = FEED_CACHE.lock().ok()?;
+        let feed = cache.iter().find(|feed| {
+            feed.opml == self.opml
+                && feed.url == feed_url
+                && feed.fetched_at.elapsed() < FEED_CACHE_TTL
+        })?;
+
+        let episode = feed.episodes.iter().find(|episode| episode.guid == guid);
+        Some((feed.show.clone(), episode.cloned()))
+    }
+
+    fn cache_feed(&self, feed_url: &str, show: &str, episodes: Vec<Episode>) {
+        if let Ok(mut cache) = FEED_CACHE.lock() {
+            cache.retain(|feed| feed.opml != self.opml || feed.url != feed_url);
+            cache.push(CachedFeed {
+                opml: self.opml.clone(),
+                url: feed_url.to_string(),
+                show: show.to_string(),
+                episodes,
+                fetched_at: Instant::now(),
+            });
+        }
+    }
+
+    /// (podcast title, episode) if the episode is still in one of the
+    /// subscribed feeds.
+    async fn episode(
+        &self,
+        feed_url: &str,
+        guid: &str,
+    ) -> anyhow::Result<Option<(String, Episode)>> {
+        if let Some((show, episode)) = self.cached_episode(feed_url, guid) {
+            return Ok(episode.map(|episode| (show, episode)));
+        }
+
+        // Unsubscribed since the episode was found
+        if !self.feed_urls()?.iter().any(|url| url == feed_url) {
+            return Ok(None);
+        }
+
+        let (show, episodes) = parse_feed(&xml::parse(&fetch(feed_url).await?)?);
+        let episode = episodes
+            .iter()
+            .find(|episode| episode.guid == guid)
+            .cloned();
+        self.cache_feed(feed_url, &show, episodes);
+        Ok(episode.map(|episode| (show, episode)))
+    }
+}
+
+async fn fetch(url: &str) -> anyhow::Result<String> {
+    let client = reqwest::Client::builder().timeout(FETCH_TIMEOUT).build()?;
+    Ok(client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?)
+}
+
+/// Feeds subscribed to in an OPML file.
+fn feed_urls(opml: &XmlNode) -> Vec<String> {
+    opml.descendants("outline")
+        .into_iter()
+        .filter_map(|outline| outline.attr("xmlUrl"))
+        .map(|url| url.to_string())
+        .collect()
+}
+
+/// (podcast title, episodes) in an RSS feed.
+fn parse_feed(feed: &XmlNode) -> (String, Vec<Episode>) {
+    let channel = match feed.child("channel") {
+        Some(channel) => channel,
+        None => return (String::new(), Vec::new()),
+    };
+
+    let title = channel.child_text("title").unwrap_or_default().to_string();
+    let episodes = channel
+        .children("item")
+        .filter_map(|item| {
+            let title = item.child_text("title")?.to_string();
+            let enclosure = item.child("enclosure").and_then(|e| e.attr("url"));
+            // Not every feed has guids, fallback to what should be unique.
+            let guid = item
+                .child_text("guid")
+                .or(enclosure)
+                .unwrap_or(&title)
+                .to_string();
+
+            let show_notes = item
+                .child_text("content:encoded")
+                .or_else(|| item.child_text("description"))
+                .or_else(|| item.child_text("itunes:summary"))
+                .map(|notes| html_to_text(notes, false).content)
+                .unwrap_or_default();
+
+            let transcript_url = TRANSCRIPT_TYPES.iter().find_map(|kind| {
+                item.children("podcast:transcript")
+                    .find(|transcript| transcript.attr("type") == Some(kind))
+                    .and_then(|transcript| transcript.attr("url"))
+                    .map(|url| url.to_string())
+            });
+
+            Some(Episode {
+                guid,
+                title,
+                show_notes,
+                link: item.child_text("link").or(enclosure).map(|x| x.to_string()),
+                published_at: item
+                    .child_text("pubDate")
+                    .and_then(|date| DateTime::parse_from_rfc2822(date).ok())
+                    .map(|date| date.with_timezone(&Utc)),
+                transcript_url,
+            })
+        })
+        .collect();
+
+    (title, episodes)
+}
+
+/// Text of a VTT/SRT transcript, w/o cue numbers & timings.
+fn transcript_text(transcript: &str) -> String {
+    transcript
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| {
+            !line.is_empty()
+                && *line != "WEBVTT"
+                && !line.contains("-->")
+                && !line.chars().all(|c| c.is_ascii_digit())
+        })
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Episodes are identified by `<feed url> <episode guid>`, feed URLs can't
+/// contain spaces.
+fn episode_id(feed_url: &str, guid: &str) -> String {
+    format!("{} {}", feed_url, guid)
+}
+
+#[async_trait]
+impl Importer for PodcastImporter {
+    fn id(&self) -> &'static str {
+        "podcasts"
+    }
+
+    async fn list(&self) -> anyhow::Result<Vec<String>> {
+        let feed_urls = self.feed_urls()?;
+        // Drop unsubscribed feeds along w/ their episodes.
+        if let Ok(mut cache) = FEED_CACHE.lock() {
+            cache.retain(|feed| feed.opml != self.opml || feed_urls.contains(&feed.url));
+        }
+
+        let mut ids = Vec::new();
+        for feed_url in feed_urls {
+            let feed = match fetch(&feed_url).await.and_then(|feed| xml::parse(&feed)) {
+                Ok(feed) => feed,
+                Err(err) => {
+                    log::warn!("Unable to fetch podcast feed {}: {}", feed_url, err);
+                    continue;
+                }
+            };
+
+            let (show, episodes) = parse_feed(&feed);
+            ids.extend(
+                episodes
+                    .iter()
+                    .map(|episode| episode_id(&feed_url, &episode.guid)),
+            );
+            self.cache_feed(&feed_url, &show, episodes);
+        }
+
+        Ok(ids)
+    }
+
+    async fn get(&self, doc_id: &str) -> anyhow::Result<Option<ImportedDoc>> {
+        let (feed_url, guid) = match doc_id.split_once(' ') {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+
+        let (show, episode) = match self.episode(feed_url, guid).await? {
+            Some(episode) => episode,
+            None => return Ok(None),
+        };
+
+        let mut content = episode.show_notes.clone();
+        if self.transcripts {
+            if let Some(url) = &episode.transcript_url {
+                match fetch(url).await {
+                    Ok(transcript) => {
+                        content.push('\n');
+                        content.push_str(&transcript_text(&transcript));
+                    }
+                    Err(err) => log::warn!("Unable to fetch transcript {}: {}", url, err),
+                }
+            }
+        }
+
+        Ok(Some(ImportedDoc {
+            id: doc_id.to_string(),
+            title: episode.title,
+            content,
+            open_url: episode.link,
+            folders: vec![show.clone()],
+            updated_at: episode.published_at,
+            fields: vec![("show".to_string(), show)],
+            ..Default::default()
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::parser::xml;
+
+    use super::{feed_urls, parse_feed, transcript_text};
+
+    #[test]
+    fn test_feed_urls() {
+        let opml = xml::parse(
+            r#"<opml version="1.0">
+                <head><title>Subscriptions</title></head>
+                <body>
+                  <outline text="feeds">
+                    <outline type="rss" text="Sleep Science" xmlUrl="https://example.com/sleep.xml" />
+                    <outline type="rss" text="Coffee Talk" xmlUrl="https://example.com/coffee.xml" />
+                  </outline>
+                </body>
+            </opml>"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            feed_urls(&opml),
+            vec![
+                "https://example.com/sleep.xml".to_string(),
+                "https://example.com/coffee.xml".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_feed() {
+        let feed = xml::parse(
+            r#"<rss version="2.0" xmlns:podcast="https://podcastindex.org/namespace/1.0">
+                <channel>
+                  <title>Sleep Science</title>
+                  <item>
+                    <title>Caffeine &amp; your sleep</title>
+                    <guid>ep-42</guid>
+                    <link>https://example.com/episodes/42</link>
+                    <pubDate>Thu, 01 Dec 2022 09:30:00 +0000</pubDate>
+                    <description><![CDATA[<p>Why that <b>afternoon coffee</b> keeps you up.</p>]]></description>
+                    <podcast:transcript url="https://example.com/42.json" type="application/json" />
+                    <podcast:transcript url="https://example.com/42.vtt" type="text/vtt" />
+                  </item>
+                </channel>
+            </rss>"#,
+        )
+        .unwrap();
+
+        let (show, episodes) = parse_feed(&feed);
+        assert_eq!(show, "Sleep Science");
+        assert_eq!(episodes.len(), 1);
+
+        let episode = &episodes[0];
+        assert_eq!(episode.guid, "ep-42");
+        assert_eq!(episode.title, "Caffeine & your sleep");
+        assert!(episode.show_notes.contains("afternoon coffee"));
+        assert_eq!(
+            episode.transcript_url.as_deref(),
+            Some("https://example.com/42.vtt")
+        );
+        assert_eq!(
+            episode.published_at.map(|date| date.timestamp()),
+            Some(1_669_887_000)
+        );
+    }
+
+    #[test]
+    fn test_transcript_text() {
+        let vtt = "WEBVTT\n\n1\n00:00:00.000 --> 00:00:02.000\nCaffeine has a half life\n\n2\n00:00:02.000 --> 00:00:04.000\nof about five hours.";
+        assert_eq!(
+            transcript_text(vtt),
+            "Caffeine has a half life of about five hours."
+        );
+    }
+}
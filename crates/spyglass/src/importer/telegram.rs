@@ -0,0 +1,183 @@
+use std::path::PathBuf;
+
+use chrono::NaiveDateTime;
+use jsonrpsee::core::async_trait;
+use serde_json::Value;
+
+use super::{chat_to_doc, ChatMessage, ImportedDoc, Importer};
+
+/// Imports chats from a Telegram Desktop JSON export, one document per chat.
+pub struct TelegramImporter {
+    path: PathBuf,
+}
+
+impl TelegramImporter {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    fn read_export(&self) -> anyhow::Result<Value> {
+        // Point at either the export folder or the result.json in it.
+        let path = if self.path.is_dir() {
+            self.path.join("result.json")
+        } else {
+            self.path.clone()
+        };
+
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+/// Chats in an export, which is either a full account export (w/ a list of
+/// chats) or a single chat.
+fn chats(export: &Value) -> Vec<&Value> {
+    match export["chats"]["list"].as_array() {
+        Some(chats) => chats.iter().collect(),
+        None if export["messages"].is_array() => vec![export],
+        None => Vec::new(),
+    }
+}
+
+fn chat_id(chat: &Value) -> Option<String> {
+    match &chat["id"] {
+        Value::Number(id) => Some(id.to_string()),
+        Value::String(id) => Some(id.clone()),
+        _ => None,
+    }
+}
+
+/// Message text, which is either a plain string or a list of strings &
+/// formatted entities (links, mentions, etc.)
+fn message_text(text: &Value) -> String {
+    match text {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts
+            .iter()
+            .map(|part| match part {
+                Value::String(text) => text.as_str(),
+                entity => entity["text"].as_str().unwrap_or_default(),
+            })
+            .collect(),
+        _ => String::new(),
+    }
+}
+
+/// Messages sent in the chat, skipping service messages (joins, pins, etc.)
+/// & messages w/ no text.
+fn parse_messages(chat: &Value) -> Vec<ChatMessage> {
+    chat["messages"]
+        .as_array()
+        .map(|messages| {
+            messages
+                .iter()
+                .filter(|msg| msg["type"].as_str() == Some("message"))
+                .filter_map(|msg| {
+                    let sent_at = msg["date"].as_str().and_then(|date| {
+                        NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S").ok()
+                    })?;
+                    let text = message_text(&msg["text"]);
+                    if text.trim().is_empty() {
+                        return None;
+                    }
+
+                    Some(ChatMessage {
+                        sender: msg["from"]
+                            .as_str()
+                            .unwrap_or("Deleted Account")
+                            .to_string(),
+                        sent_at,
+                        text,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[async_trait]
+impl Importer for TelegramImporter {
+    fn id(&self) -> &'static str {
+        "telegram"
+    }
+
+    async fn list(&self) -> anyhow::Result<Vec<String>> {
+        let export = self.read_export()?;
+        Ok(chats(&export).into_iter().filter_map(chat_id).collect())
+    }
+
+    async fn get(&self, doc_id: &str) -> anyhow::Result<Option<ImportedDoc>> {
+        let export = self.read_export()?;
+        let chat = chats(&export)
+            .into_iter()
+            .find(|chat| chat_id(chat).as_deref() == Some(doc_id));
+
+        Ok(chat.map(|chat| {
+            let title = chat["name"].as_str().unwrap_or("Saved Messages");
+            chat_to_doc(doc_id, title, &parse_messages(chat))
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::{chat_id, chats, parse_messages};
+
+    #[test]
+    fn test_parse_export() {
+        let export = json!({
+            "chats": {
+                "list": [{
+                    "name": "Book Club",
+                    "type": "private_group",
+                    "id": 4815162342_i64,
+                    "messages": [
+                        {
+                            "id": 1,
+                            "type": "service",
+                            "date": "2022-12-01T09:00:00",
+                            "actor": "Alice",
+                            "action": "create_group",
+                            "text": ""
+                        },
+                        {
+                            "id": 2,
+                            "type": "message",
+                            "date": "2022-12-01T09:30:15",
+                            "from": "Alice",
+                            "text": "next up is Piranesi"
+                        },
+                        {
+                            "id": 3,
+                            "type": "message",
+                            "date": "2022-12-01T09:31:00",
+                            "from": "Bob",
+                            "text": [
+                                "reviews are ",
+                                { "type": "link", "text": "https://example.com/piranesi" }
+                            ]
+                        },
+                        {
+                            "id": 4,
+                            "type": "message",
+                            "date": "2022-12-01T09:32:00",
+                            "from": "Bob",
+                            "photo": "photos/photo_1.jpg",
+                            "text": ""
+                        }
+                    ]
+                }]
+            }
+        });
+
+        let chats = chats(&export);
+        assert_eq!(chats.len(), 1);
+        assert_eq!(chat_id(chats[0]), Some("4815162342".to_string()));
+
+        let messages = parse_messages(chats[0]);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].sender, "Alice");
+        assert_eq!(messages[1].text, "reviews are https://example.com/piranesi");
+    }
+}
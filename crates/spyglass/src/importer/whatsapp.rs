@@ -0,0 +1,263 @@
+use std::path::{Path, PathBuf};
+
+use chrono::{NaiveDate, NaiveDateTime};
+use ignore::WalkBuilder;
+use jsonrpsee::core::async_trait;
+use regex::Regex;
+
+use super::{chat_to_doc, ChatMessage, ImportedDoc, Importer};
+
+/// Name of the chat inside a zipped export.
+const ZIPPED_CHAT_FILE: &str = "_chat.txt";
+const CHAT_FILE_PREFIXES: [&str; 2] = ["WhatsApp Chat with ", "WhatsApp Chat - "];
+
+/// Start of a message, either `[12/01/22, 09:30:15] ` (iOS) or
+/// `12/01/22, 09:30 - ` (Android).
+const MESSAGE_START: &str = r"^\[?(\d{1,2})[/.\-](\d{1,2})[/.\-](\d{2,4}),? (\d{1,2}):(\d{2})(?::(\d{2}))?\s?([AaPp]\.?[Mm]\.?)?\]?(?: -)? ";
+
+/// Imports WhatsApp chat exports, one document per chat.
+pub struct WhatsAppImporter {
+    root: PathBuf,
+}
+
+impl WhatsAppImporter {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    fn is_chat(path: &Path) -> bool {
+        path.file_name()
+            .and_then(|name| name.to_str())
+            .map_or(false, |name| {
+                name == ZIPPED_CHAT_FILE
+                    || (name.ends_with(".txt")
+                        && CHAT_FILE_PREFIXES
+                            .iter()
+                            .any(|prefix| name.starts_with(prefix)))
+            })
+    }
+}
+
+/// Who the chat is with, from the export's file name (or folder name for
+/// unzipped exports).
+fn chat_title(path: &Path) -> String {
+    let name = if path
+        .file_name()
+        .map_or(false, |name| name == ZIPPED_CHAT_FILE)
+    {
+        path.parent().and_then(|parent| parent.file_name())
+    } else {
+        path.file_stem()
+    };
+
+    let name = name
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    CHAT_FILE_PREFIXES
+        .iter()
+        .find_map(|prefix| name.strip_prefix(prefix))
+        .unwrap_or(&name)
+        .to_string()
+}
+
+struct RawMessage {
+    date: (u32, u32, i32),
+    time: (u32, u32, u32),
+    sender: String,
+    text: String,
+}
+
+/// Parse the messages in an exported chat. System messages (e.g. "Messages
+/// are end-to-end encrypted") are skipped.
+pub fn parse_chat(export: &str) -> Vec<ChatMessage> {
+    let start = Regex::new(MESSAGE_START).expect("Invalid WhatsApp message regex");
+
+    let mut raw: Vec<RawMessage> = Vec::new();
+    // Whether the last line started a message we're keeping, so any
+    // following lines without a timestamp are part of it.
+    let mut in_message = false;
+    for line in export.lines() {
+        // Exports are sprinkled w/ direction marks & narrow spaces.
+        let line = line
+            .replace(['\u{200e}', '\u{200f}', '\u{feff}'], "")
+            .replace('\u{202f}', " ");
+
+        let caps = match start.captures(&line) {
+            Some(caps) => caps,
+            None => {
+                if in_message {
+                    if let Some(last) = raw.last_mut() {
+                        last.text.push('\n');
+                        last.text.push_str(&line);
+                    }
+                }
+                continue;
+            }
+        };
+
+        let num = |idx: usize| {
+            caps.get(idx)
+                .and_then(|val| val.as_str().parse::<u32>().ok())
+                .unwrap_or_default()
+        };
+
+        let mut hour = num(4);
+        if let Some(meridiem) = caps.get(7) {
+            let is_pm = meridiem.as_str().to_lowercase().starts_with('p');
+            hour = hour % 12 + if is_pm { 12 } else { 0 };
+        }
+
+        let mut year = num(3) as i32;
+        if year < 100 {
+            year += 2000;
+        }
+
+        let rest = &line[caps.get(0).map_or(0, |m| m.end())..];
+        match rest.split_once(": ") {
+            Some((sender, text)) => {
+                in_message = true;
+                raw.push(RawMessage {
+                    date: (num(1), num(2), year),
+                    time: (hour, num(5), num(6)),
+                    sender: sender.trim().to_string(),
+                    text: text.to_string(),
+                });
+            }
+            None => in_message = false,
+        }
+    }
+
+    // Exports use the phone's date format, so work out whether days or
+    // months come first from the dates themselves. Most locales put the day
+    // first, so that's assumed when it's ambiguous.
+    let day_first = raw.iter().any(|msg| msg.date.0 > 12) || !raw.iter().any(|msg| msg.date.1 > 12);
+
+    raw.into_iter()
+        .filter_map(|msg| {
+            let (first, second, year) = msg.date;
+            let (day, month) = if day_first {
+                (first, second)
+            } else {
+                (second, first)
+            };
+
+            let (hour, min, sec) = msg.time;
+            let sent_at: NaiveDateTime =
+                NaiveDate::from_ymd_opt(year, month, day)?.and_hms_opt(hour, min, sec)?;
+
+            Some(ChatMessage {
+                sender: msg.sender,
+                sent_at,
+                text: msg.text,
+            })
+        })
+        .collect()
+}
+
+#[async_trait]
+impl Importer for WhatsAppImporter {
+    fn id(&self) -> &'static str {
+        "whatsapp"
+    }
+
+    async fn list(&self) -> anyhow::Result<Vec<String>> {
+        if self.root.is_file() {
+            return Ok(vec![self.root.display().to_string()]);
+        }
+
+        if !self.root.is_dir() {
+            return Err(anyhow::anyhow!("WhatsApp export {:?} not found", self.root));
+        }
+
+        let chats = WalkBuilder::new(&self.root)
+            .standard_filters(true)
+            .build()
+            .flatten()
+            .filter(|entry| entry.file_type().map_or(false, |ft| ft.is_file()))
+            .filter(|entry| Self::is_chat(entry.path()))
+            .map(|entry| entry.path().display().to_string())
+            .collect();
+
+        Ok(chats)
+    }
+
+    async fn get(&self, doc_id: &str) -> anyhow::Result<Option<ImportedDoc>> {
+        let path = Path::new(doc_id);
+        // Only read chats from inside the export folder
+        if !path.starts_with(&self.root) || !path.is_file() {
+            return Ok(None);
+        }
+
+        let export = String::from_utf8_lossy(&std::fs::read(path)?).to_string();
+        let messages = parse_chat(&export);
+
+        let mut doc = chat_to_doc(doc_id, &chat_title(path), &messages);
+        doc.open_url = url::Url::from_file_path(path)
+            .ok()
+            .map(|url| url.to_string());
+
+        Ok(Some(doc))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use chrono::NaiveDate;
+
+    use super::{chat_title, parse_chat};
+
+    #[test]
+    fn test_parse_ios_chat() {
+        let export =
+            "\u{200e}[13/12/22, 09:30:15] Alice: Messages and calls are end-to-end encrypted.\n\
+            [13/12/22, 09:31:02] Bob: are we still on for lunch?\n\
+            [13/12/22, 09:31:40] Alice: yes!\n\
+            see you at noon\n\
+            [14/12/22, 1:05:00\u{202f}PM] Bob: \u{200e}image omitted";
+
+        let messages = parse_chat(export);
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[1].sender, "Bob");
+        assert_eq!(messages[2].text, "yes!\nsee you at noon");
+        assert_eq!(
+            messages[3].sent_at,
+            NaiveDate::from_ymd_opt(2022, 12, 14)
+                .and_then(|date| date.and_hms_opt(13, 5, 0))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_android_chat() {
+        let export =
+            "12/13/22, 09:30 - Messages and calls are end-to-end encrypted. Tap to learn more.\n\
+            12/13/22, 09:31 - Bob: are we still on for lunch?\n\
+            12/13/22, 09:32 - Alice changed the group description\n\
+            12/13/22, 09:33 - Alice: yep";
+
+        let messages = parse_chat(export);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].text, "are we still on for lunch?");
+        assert_eq!(
+            messages[1].sent_at,
+            NaiveDate::from_ymd_opt(2022, 12, 13)
+                .and_then(|date| date.and_hms_opt(9, 33, 0))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_chat_title() {
+        assert_eq!(
+            chat_title(Path::new("/exports/WhatsApp Chat with Alice.txt")),
+            "Alice"
+        );
+        assert_eq!(
+            chat_title(Path::new("/exports/WhatsApp Chat - Book Club/_chat.txt")),
+            "Book Club"
+        );
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use entities::models::tag::TagType;
+use jsonrpsee::core::async_trait;
+use regex::Regex;
+use serde::Deserialize;
+use url::Url;
+
+use super::{ImportedDoc, Importer};
+use crate::parser::xml;
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+/// Caption tracks listed in a video's watch page.
+const CAPTION_TRACK_URL: &str = r#""captionTracks":\[\{"baseUrl":"([^"]+)""#;
+
+/// Imports YouTube watch history from a Google Takeout export.
+pub struct YouTubeImporter {
+    /// `watch-history.json` in the export (Takeout must be set to export
+    /// history as JSON).
+    path: PathBuf,
+    transcripts: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HistoryEntry {
+    title: String,
+    title_url: Option<String>,
+    #[serde(default)]
+    subtitles: Vec<HistoryLink>,
+    #[serde(default)]
+    details: Vec<HistoryLink>,
+    time: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoryLink {
+    name: String,
+}
+
+#[derive(Debug, PartialEq)]
+struct WatchedVideo {
+    id: String,
+    title: String,
+    url: String,
+    channel: Option<String>,
+    /// Most recent time the video was watched.
+    watched_at: DateTime<Utc>,
+}
+
+impl YouTubeImporter {
+    pub fn new(path: PathBuf, transcripts: bool) -> Self {
+        Self { path, transcripts }
+    }
+
+    fn history(&self) -> anyhow::Result<HashMap<String, WatchedVideo>> {
+        let path = if self.path.is_dir() {
+            self.path.join("watch-history.json")
+        } else {
+            self.path.clone()
+        };
+
+        Ok(parse_history(&std::fs::read_to_string(path)?)?)
+    }
+}
+
+fn video_id(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    url.query_pairs()
+        .find(|(key, _)| key == "v")
+        .map(|(_, id)| id.to_string())
+}
+
+/// Videos in the watch history, by video ID. Ads & removed videos are skipped.
+fn parse_history(history: &str) -> Result<HashMap<String, WatchedVideo>, serde_json::Error> {
+    let entries: Vec<HistoryEntry> = serde_json::from_str(history)?;
+
+    let mut videos: HashMap<String, WatchedVideo> = HashMap::new();
+    for entry in entries {
+        let is_ad = entry
+            .details
+            .iter()
+            .any(|detail| detail.name.contains("Google Ads"));
+        let url = match entry.title_url {
+            Some(url) if !is_ad => url,
+            _ => continue,
+        };
+
+        let id = match video_id(&url) {
+            Some(id) => id,
+            None => continue,
+        };
+
+        if let Some(existing) = videos.get(&id) {
+            if existing.watched_at >= entry.time {
+                continue;
+            }
+        }
+
+        let title = entry
+            .title
+            .strip_prefix("Watched ")
+            .unwrap_or(&entry.title)
+            .to_string();
+
+        videos.insert(
+            id.clone(),
+            WatchedVideo {
+                id,
+                title,
+                url,
+                channel: entry.subtitles.into_iter().next().map(|link| link.name),
+                watched_at: entry.time,
+            },
+        );
+    }
+
+    Ok(videos)
+}
+
+async fn fetch(url: &str) -> anyhow::Result<String> {
+    let client = reqwest::Client::builder().timeout(FETCH_TIMEOUT).build()?;
+    Ok(client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?)
+}
+
+/// Text of a timed text caption track.
+fn caption_text(captions: &str) -> anyhow::Result<String> {
+    let captions = xml::parse(captions)?;
+    let text = captions
+        .descendants("text")
+        .iter()
+        .map(|line| {
+            // Caption text is escaped twice
+            line.text
+                .replace("&#39;", "'")
+                .replace("&quot;", "\"")
+                .replace("&amp;", "&")
+                .replace('\n', " ")
+        })
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    Ok(text)
+}
+
+/// Fetch the transcript for a video from the captions listed on its watch
+/// page, if it has any.
+async fn fetch_transcript(video_url: &str) -> anyhow::Result<Option<String>> {
+    let page = fetch(video_url).await?;
+    let track = Regex::new(CAPTION_TRACK_URL)?
+        .captures(&page)
+        .and_then(|caps| caps.get(1))
+        .map(|url| url.as_str().replace("\\u0026", "&"));
+
+    match track {
+        Some(track) => Ok(Some(caption_text(&fetch(&track).await?)?)),
+        None => Ok(None),
+    }
+}
+
+#[async_trait]
+impl Importer for YouTubeImporter {
+    fn id(&self) -> &'static str {
+        "youtube"
+    }
+
+    async fn list(&self) -> anyhow::Result<Vec<String>> {
+        Ok(self.history()?.into_keys().collect())
+    }
+
+    async fn get(&self, doc_id: &str) -> anyhow::Result<Option<ImportedDoc>> {
+        let video = match self.history()?.remove(doc_id) {
+            Some(video) => video,
+            None => return Ok(None),
+        };
+
+        let mut content = vec![video.title.clone()];
+        if let Some(channel) = &video.channel {
+            content.push(channel.clone());
+        }
+
+        if self.transcripts {
+            match fetch_transcript(&video.url).await {
+                Ok(Some(transcript)) => content.push(transcript),
+                Ok(None) => {}
+                Err(err) => log::warn!("Unable to fetch transcript for {}: {}", video.url, err),
+            }
+        }
+
+        let mut doc = ImportedDoc {
+            id: video.id,
+            title: video.title,
+            content: content.join("\n"),
+            open_url: Some(video.url),
+            updated_at: Some(video.watched_at),
+            ..Default::default()
+        };
+
+        if let Some(channel) = video.channel {
+            doc.tags.push((TagType::Owner, channel.clone()));
+            doc.fields.push(("channel".to_string(), channel));
+        }
+
+        Ok(Some(doc))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{caption_text, parse_history};
+
+    #[test]
+    fn test_parse_history() {
+        let history = r#"[
+            {
+                "header": "YouTube",
+                "title": "Watched How caffeine affects sleep",
+                "titleUrl": "https://www.youtube.com/watch?v=abc123",
+                "subtitles": [{ "name": "Sleep Science", "url": "https://www.youtube.com/channel/UC1" }],
+                "time": "2022-12-02T09:30:15.123Z",
+                "products": ["YouTube"]
+            },
+            {
+                "header": "YouTube",
+                "title": "Watched How caffeine affects sleep",
+                "titleUrl": "https://www.youtube.com/watch?v=abc123",
+                "subtitles": [{ "name": "Sleep Science", "url": "https://www.youtube.com/channel/UC1" }],
+                "time": "2022-12-01T09:30:15.123Z",
+                "products": ["YouTube"]
+            },
+            {
+                "header": "YouTube",
+                "title": "Watched a video that has been removed",
+                "time": "2022-11-30T09:30:15.123Z",
+                "products": ["YouTube"]
+            },
+            {
+                "header": "YouTube",
+                "title": "Watched Buy our coffee",
+                "titleUrl": "https://www.youtube.com/watch?v=ad456",
+                "details": [{ "name": "From Google Ads" }],
+                "time": "2022-11-29T09:30:15.123Z",
+                "products": ["YouTube"]
+            }
+        ]"#;
+
+        let videos = parse_history(history).unwrap();
+        assert_eq!(videos.len(), 1);
+
+        let video = videos.get("abc123").unwrap();
+        assert_eq!(video.title, "How caffeine affects sleep");
+        assert_eq!(video.channel.as_deref(), Some("Sleep Science"));
+        assert_eq!(
+            video.watched_at.to_rfc3339(),
+            "2022-12-02T09:30:15.123+00:00"
+        );
+    }
+
+    #[test]
+    fn test_caption_text() {
+        let captions = r#"<?xml version="1.0" encoding="utf-8" ?><transcript>
+            <text start="0.5" dur="2.1">caffeine&amp;#39;s half life</text>
+            <text start="2.6" dur="1.9">is about
+five hours</text></transcript>"#;
+
+        assert_eq!(
+            caption_text(captions).unwrap(),
+            "caffeine's half life is about five hours"
+        );
+    }
+}
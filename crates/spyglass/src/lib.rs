@@ -1,8 +1,14 @@
 #[macro_use]
 extern crate html5ever;
 
+pub mod archive;
+pub mod backup;
+pub mod benchmark;
 pub mod connection;
+pub mod content;
 pub mod crawler;
+pub mod importer;
+pub mod lock;
 pub mod oauth;
 pub mod parser;
 pub mod pipeline;
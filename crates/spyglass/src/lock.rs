@@ -0,0 +1,192 @@
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use shared::config::{Config, UserSettings};
+use tokio::process::Command;
+
+/// File in the data directory holding the token used to unlock search.
+pub const LOCK_TOKEN_FILE: &str = "lock_token";
+
+/// Locks search & hides the index on shared machines, either on request or
+/// after a period w/o any searches. Unlocking needs the local token (only
+/// readable by the user who owns the data directory) or a successful run of
+/// the configured unlock command, e.g. an OS biometric prompt.
+pub struct PrivacyLock {
+    token: String,
+    /// Lock after being idle this long.
+    timeout: Option<Duration>,
+    unlock_command: Vec<String>,
+    is_locked: AtomicBool,
+    last_active: Mutex<Instant>,
+}
+
+impl PrivacyLock {
+    pub fn new(token: &str, settings: &UserSettings) -> Self {
+        Self {
+            token: token.to_string(),
+            timeout: settings
+                .privacy_lock_mins
+                .map(|mins| Duration::from_secs(mins * 60)),
+            unlock_command: settings.privacy_unlock_command.clone(),
+            is_locked: AtomicBool::new(false),
+            last_active: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Load the token from the data directory, creating one on first run.
+    pub fn from_config(config: &Config) -> Self {
+        let token = load_or_create_token(&config.data_dir().join(LOCK_TOKEN_FILE));
+        Self::new(&token, &config.user_settings)
+    }
+
+    /// Whether search is locked, locking first if we've been idle too long.
+    pub fn is_locked(&self) -> bool {
+        if let (Some(timeout), Ok(last_active)) = (self.timeout, self.last_active.lock()) {
+            if last_active.elapsed() >= timeout {
+                self.is_locked.store(true, Ordering::SeqCst);
+            }
+        }
+
+        self.is_locked.load(Ordering::SeqCst)
+    }
+
+    /// Record activity, which resets the idle timeout. Returns false if
+    /// search is locked.
+    pub fn touch(&self) -> bool {
+        if self.is_locked() {
+            return false;
+        }
+
+        if let Ok(mut last_active) = self.last_active.lock() {
+            *last_active = Instant::now();
+        }
+
+        true
+    }
+
+    pub fn lock(&self) {
+        self.is_locked.store(true, Ordering::SeqCst);
+    }
+
+    /// Unlock w/ the local token or, if no token is given, the unlock
+    /// command. Returns whether search is now unlocked.
+    pub async fn unlock(&self, token: Option<String>) -> bool {
+        let is_authed = match token {
+            Some(token) => constant_time_eq(token.trim().as_bytes(), self.token.as_bytes()),
+            None => self.run_unlock_command().await,
+        };
+
+        if is_authed {
+            if let Ok(mut last_active) = self.last_active.lock() {
+                *last_active = Instant::now();
+            }
+            self.is_locked.store(false, Ordering::SeqCst);
+        }
+
+        is_authed
+    }
+
+    async fn run_unlock_command(&self) -> bool {
+        let (program, args) = match self.unlock_command.split_first() {
+            Some(cmd) => cmd,
+            None => return false,
+        };
+
+        match Command::new(program).args(args).status().await {
+            Ok(status) => status.success(),
+            Err(err) => {
+                log::error!("Unable to run unlock command {}: {}", program, err);
+                false
+            }
+        }
+    }
+}
+
+/// Read the token stored @ `path`, creating a random one if there isn't one.
+pub fn load_or_create_token(path: &Path) -> String {
+    match std::fs::read_to_string(path) {
+        Ok(token) if !token.trim().is_empty() => token.trim().to_string(),
+        _ => {
+            let token = uuid::Uuid::new_v4().simple().to_string();
+            if let Err(err) = write_token(path, &token) {
+                log::error!("Unable to save token to {:?}: {}", path, err);
+            }
+            token
+        }
+    }
+}
+
+/// The file is created only readable by its owner, so the token is never
+/// readable by anyone else, even briefly.
+fn write_token(path: &Path, token: &str) -> std::io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    options.open(path)?.write_all(token.as_bytes())
+}
+
+/// Compare tokens w/o leaking how much of them matched through timing.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod test {
+    use shared::config::UserSettings;
+
+    use super::{load_or_create_token, PrivacyLock};
+
+    #[tokio::test]
+    async fn test_lock_unlock() {
+        let lock = PrivacyLock::new("secret", &UserSettings::default());
+        assert!(lock.touch());
+
+        lock.lock();
+        assert!(lock.is_locked());
+        assert!(!lock.touch());
+
+        assert!(!lock.unlock(Some("wrong".into())).await);
+        // No unlock command configured
+        assert!(!lock.unlock(None).await);
+        assert!(lock.is_locked());
+
+        assert!(lock.unlock(Some("secret".into())).await);
+        assert!(lock.touch());
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout() {
+        let settings = UserSettings {
+            privacy_lock_mins: Some(0),
+            ..Default::default()
+        };
+
+        let lock = PrivacyLock::new("secret", &settings);
+        assert!(lock.is_locked());
+        assert!(lock.unlock(Some("secret".into())).await);
+    }
+
+    #[test]
+    fn test_token_file() {
+        let path = std::env::temp_dir().join(format!("token-{}", uuid::Uuid::new_v4()));
+        let token = load_or_create_token(&path);
+        assert_eq!(load_or_create_token(&path), token);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+
+        let _ = std::fs::remove_file(&path);
+    }
+}
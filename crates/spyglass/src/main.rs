@@ -1,19 +1,25 @@
 extern crate notify;
 use clap::Parser;
+use opentelemetry::sdk::{trace, Resource};
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
 use std::io;
+use std::path::PathBuf;
 use tokio::signal;
 use tokio::sync::{broadcast, mpsc};
 use tracing_log::LogTracer;
 use tracing_subscriber::{fmt, layer::SubscriberExt, EnvFilter};
 
 use entities::models::{crawl_queue, lens};
+use entities::schema::LANG_BACKFILL_MARKER;
+use libspyglass::backup;
 use libspyglass::pipeline;
 use libspyglass::plugin;
 use libspyglass::state::AppState;
 use libspyglass::task::{self, AppPause, AppShutdown, ManagerCommand};
 #[allow(unused_imports)]
 use migration::Migrator;
-use shared::config::Config;
+use shared::config::{Config, UserSettings};
 
 mod api;
 
@@ -34,6 +40,13 @@ struct CliArgs {
     /// Run migrations & basic checks.
     #[arg(short, long)]
     check: bool,
+    /// Back up the search index & database to a `.tar.gz` archive, then exit.
+    #[arg(long, value_name = "PATH")]
+    backup: Option<PathBuf>,
+    /// Replace the search index & database w/ a backup made by `--backup`,
+    /// then exit. Spyglass must not be running.
+    #[arg(long, value_name = "PATH")]
+    restore: Option<PathBuf>,
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -54,6 +67,20 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         )))
     };
 
+    let rt = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .thread_name("spyglass-backend")
+        .build()
+        .expect("Unable to create tokio runtime");
+
+    // The OTLP exporter batches spans on the runtime, so it needs to be
+    // created within it.
+    let otlp_layer = {
+        let _guard = rt.enter();
+        otlp_tracer(&config.user_settings)
+            .map(|tracer| tracing_opentelemetry::layer().with_tracer(tracer))
+    };
+
     let file_appender = tracing_appender::rolling::daily(config.logs_dir(), "server.log");
     let (non_blocking, _guard) = tracing_appender::non_blocking(file_appender);
 
@@ -77,17 +104,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         )
         .with(fmt::Layer::new().with_writer(io::stdout))
         .with(fmt::Layer::new().with_ansi(false).with_writer(non_blocking))
-        .with(sentry_tracing::layer());
+        .with(sentry_tracing::layer())
+        .with(otlp_layer);
 
     tracing::subscriber::set_global_default(subscriber).expect("Unable to set a global subscriber");
     LogTracer::init()?;
 
     log::info!("Loading prefs from: {:?}", Config::prefs_dir());
-    let rt = tokio::runtime::Builder::new_multi_thread()
-        .enable_all()
-        .thread_name("spyglass-backend")
-        .build()
-        .expect("Unable to create tokio runtime");
 
     // Run any migrations, only on headless mode.
     #[cfg(debug_assertions)]
@@ -115,15 +138,67 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
+    // Restore before anything opens the index or database.
+    if let Some(archive) = &args.restore {
+        let manifest = backup::restore_backup(&config, archive).map_err(|err| {
+            log::error!("Unable to restore backup: {}", err);
+            err
+        })?;
+        log::info!(
+            "Restored {} documents from backup made @ {}",
+            manifest.num_docs,
+            manifest.created_at
+        );
+        return Ok(());
+    }
+
     // Initialize/Load user preferences
     let mut state = rt.block_on(AppState::new(&config));
+    if let Some(dest) = &args.backup {
+        rt.block_on(backup::create_backup(&state, dest))
+            .map_err(|err| {
+                log::error!("Unable to create backup: {}", err);
+                err
+            })?;
+        return Ok(());
+    }
     if !args.check {
         rt.block_on(start_backend(&mut state, &config));
     }
 
+    // Flush any spans still waiting to be exported
+    opentelemetry::global::shutdown_tracer_provider();
     Ok(())
 }
 
+/// Export spans to an OTLP collector (Jaeger, Tempo, etc.) if one is configured.
+fn otlp_tracer(settings: &UserSettings) -> Option<trace::Tracer> {
+    let endpoint = settings.otlp_endpoint.as_ref()?;
+    let exporter = opentelemetry_otlp::new_exporter()
+        .tonic()
+        .with_endpoint(endpoint);
+
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(exporter)
+        .with_trace_config(
+            trace::config().with_resource(Resource::new(vec![KeyValue::new(
+                "service.name",
+                "spyglass",
+            )])),
+        )
+        .install_batch(opentelemetry::runtime::Tokio);
+
+    match tracer {
+        Ok(tracer) => Some(tracer),
+        Err(err) => {
+            // Logging isn't setup yet
+            eprintln!("Unable to export traces to {}: {}", endpoint, err);
+            None
+        }
+    }
+}
+
 async fn start_backend(state: &mut AppState, config: &Config) {
     // Initialize crawl_queue, requeue all in-flight tasks.
     let _ = crawl_queue::reset_processing(&state.db).await;
@@ -131,6 +206,19 @@ async fn start_backend(state: &mut AppState, config: &Config) {
         log::error!("Unable to reset lenses: {}", e);
     }
 
+    // Fill in the language fields of documents indexed before they existed.
+    let backfill_marker = config.index_dir().join(LANG_BACKFILL_MARKER);
+    if backfill_marker.exists() {
+        let index = state.index.clone();
+        tokio::task::spawn_blocking(move || match index.backfill_language_fields() {
+            Ok(updated) => {
+                log::info!("Added language fields to {} documents", updated);
+                let _ = std::fs::remove_file(&backfill_marker);
+            }
+            Err(err) => log::error!("Unable to backfill language fields: {}", err),
+        });
+    }
+
     // Create channels for scheduler / crawlers
     let (worker_cmd_tx, worker_cmd_rx) = mpsc::channel(
         state
@@ -202,6 +290,12 @@ async fn start_backend(state: &mut AppState, config: &Config) {
         pause_tx.subscribe(),
     ));
 
+    // OCR & index new screenshots as they're taken, if enabled.
+    let screenshot_watcher_handle = tokio::spawn(task::screenshot_watcher(
+        state.clone(),
+        pause_tx.subscribe(),
+    ));
+
     // Loads and processes pipeline commands
     let _pipeline_handler = tokio::spawn(pipeline::initialize_pipelines(
         state.clone(),
@@ -217,8 +311,26 @@ async fn start_backend(state: &mut AppState, config: &Config) {
         plugin_cmd_rx,
     ));
 
-    // API server
-    let api_server = tokio::spawn(api::start_api_server(state.clone()));
+    if let Some(portal) = state.user_settings.search_portal.clone() {
+        // Read-only search page for sharing some lenses. Nothing else is
+        // served in portal mode, so the rest of the API stays off.
+        log::info!("search portal enabled, JSON-RPC API & share endpoint are disabled");
+        if let Err(err) = api::start_search_portal(state.clone(), config, portal).await {
+            log::error!("Unable to start search portal: {}", err);
+        }
+    } else {
+        // API server
+        if let Err(err) = api::start_api_server(state.clone(), config.clone()).await {
+            log::error!("Unable to start API server: {}", err);
+        }
+
+        // Accept pages from bookmarklets & share sheets, if enabled.
+        if let Some(endpoint) = state.user_settings.share_endpoint.clone() {
+            if let Err(err) = api::start_share_server(state.clone(), config, endpoint).await {
+                log::error!("Unable to start share endpoint: {}", err);
+            }
+        }
+    }
 
     // Gracefully handle shutdowns
     match signal::ctrl_c().await {
@@ -246,7 +358,7 @@ async fn start_backend(state: &mut AppState, config: &Config) {
         manager_handle,
         worker_handle,
         pm_handle,
-        api_server,
-        lens_watcher_handle
+        lens_watcher_handle,
+        screenshot_watcher_handle
     );
 }
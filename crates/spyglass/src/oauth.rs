@@ -1,7 +1,17 @@
 use libgoog::types::AuthScope;
+use serde::Deserialize;
 use shared::response::SupportedConnection;
 use std::collections::HashMap;
 
+const GOOGLE_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+
+#[derive(Deserialize)]
+pub struct RefreshedToken {
+    pub access_token: String,
+    /// Seconds until the new token expires.
+    pub expires_in: Option<i64>,
+}
+
 /// TODO: Move this into a configuration file?
 pub fn supported_connections() -> HashMap<String, SupportedConnection> {
     let conns = vec![
@@ -57,3 +67,23 @@ pub fn connection_secret(id: &str) -> Option<(String, String, Vec<AuthScope>)> {
         None
     }
 }
+
+/// Exchange a refresh token for a new access token.
+pub async fn refresh_access_token(id: &str, refresh_token: &str) -> anyhow::Result<RefreshedToken> {
+    let (client_id, client_secret, _) = connection_secret(id)
+        .ok_or_else(|| anyhow::anyhow!("Connection <{}> not supported", id))?;
+
+    let resp = reqwest::Client::new()
+        .post(GOOGLE_TOKEN_URL)
+        .form(&[
+            ("client_id", client_id.as_str()),
+            ("client_secret", client_secret.as_str()),
+            ("refresh_token", refresh_token),
+            ("grant_type", "refresh_token"),
+        ])
+        .send()
+        .await?
+        .error_for_status()?;
+
+    Ok(serde_json::from_str(&resp.text().await?)?)
+}
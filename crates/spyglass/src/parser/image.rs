@@ -0,0 +1,92 @@
+use std::{fs::File, io::BufReader, path::Path};
+
+use chrono::NaiveDate;
+use exif::{In, Reader, Tag, Value};
+
+use super::MediaMetadata;
+use crate::search::geo::GeoPoint;
+
+const IMAGE_EXTENSIONS: [&str; 6] = ["jpg", "jpeg", "tif", "tiff", "heic", "png"];
+
+pub fn is_image(extension: &str) -> bool {
+    IMAGE_EXTENSIONS
+        .iter()
+        .any(|ext| ext.eq_ignore_ascii_case(extension))
+}
+
+fn ascii(exif: &exif::Exif, tag: Tag) -> Option<String> {
+    match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Ascii(values) => values
+            .first()
+            .map(|value| String::from_utf8_lossy(value).trim().to_string())
+            .filter(|value| !value.is_empty()),
+        _ => None,
+    }
+}
+
+/// Degrees/minutes/seconds GPS coordinate as decimal degrees, negated for the
+/// southern & western hemispheres.
+fn gps_coordinate(exif: &exif::Exif, tag: Tag, ref_tag: Tag, negative_ref: &str) -> Option<f64> {
+    let degrees = match &exif.get_field(tag, In::PRIMARY)?.value {
+        Value::Rational(parts) if parts.len() >= 3 => {
+            parts[0].to_f64() + parts[1].to_f64() / 60.0 + parts[2].to_f64() / 3600.0
+        }
+        _ => return None,
+    };
+
+    let is_negative =
+        ascii(exif, ref_tag).map_or(false, |value| value.eq_ignore_ascii_case(negative_ref));
+
+    Some(if is_negative { -degrees } else { degrees })
+}
+
+/// Camera, capture time, dimensions & location from the EXIF data of the
+/// photo @ `path`.
+pub fn metadata(path: &Path) -> Option<MediaMetadata> {
+    let file = File::open(path).ok()?;
+    let exif = Reader::new()
+        .read_from_container(&mut BufReader::new(&file))
+        .ok()?;
+
+    // Model usually includes the make, but not always
+    let camera = match (ascii(&exif, Tag::Make), ascii(&exif, Tag::Model)) {
+        (Some(make), Some(model)) if !model.starts_with(&make) => {
+            Some(format!("{} {}", make, model))
+        }
+        (make, model) => model.or(make),
+    };
+
+    let captured_at =
+        exif.get_field(Tag::DateTimeOriginal, In::PRIMARY)
+            .or_else(|| exif.get_field(Tag::DateTime, In::PRIMARY))
+            .and_then(|field| match &field.value {
+                Value::Ascii(values) => values.first(),
+                _ => None,
+            })
+            .and_then(|value| exif::DateTime::from_ascii(value).ok())
+            .and_then(|dt| {
+                NaiveDate::from_ymd_opt(dt.year.into(), dt.month.into(), dt.day.into())?
+                    .and_hms_opt(dt.hour.into(), dt.minute.into(), dt.second.into())
+            });
+
+    let dimension = |tag: Tag| {
+        exif.get_field(tag, In::PRIMARY)
+            .and_then(|field| field.value.get_uint(0))
+            .map(u64::from)
+    };
+
+    let location =
+        gps_coordinate(&exif, Tag::GPSLatitude, Tag::GPSLatitudeRef, "S").and_then(|lat| {
+            let lng = gps_coordinate(&exif, Tag::GPSLongitude, Tag::GPSLongitudeRef, "W")?;
+            GeoPoint::new(lat, lng)
+        });
+
+    Some(MediaMetadata {
+        camera,
+        captured_at,
+        width: dimension(Tag::PixelXDimension),
+        height: dimension(Tag::PixelYDimension),
+        duration_secs: None,
+        location,
+    })
+}
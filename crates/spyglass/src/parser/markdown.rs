@@ -0,0 +1,118 @@
+use crate::scraper::Section;
+
+/// Pull fenced code blocks (``` or ~~~) out of a markdown document.
+pub fn code_blocks(text: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    // Fence that opened the current block, if we're in one.
+    let mut fence: Option<&str> = None;
+    let mut current = Vec::new();
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        match fence {
+            Some(open) => {
+                if trimmed.trim_end() == open {
+                    if !current.is_empty() {
+                        blocks.push(current.join("\n"));
+                    }
+                    current.clear();
+                    fence = None;
+                } else {
+                    current.push(line);
+                }
+            }
+            None => {
+                if trimmed.starts_with("```") {
+                    fence = Some("```");
+                } else if trimmed.starts_with("~~~") {
+                    fence = Some("~~~");
+                }
+            }
+        }
+    }
+
+    // Unclosed fences run until the end of the document
+    if !current.is_empty() {
+        blocks.push(current.join("\n"));
+    }
+
+    blocks
+}
+
+/// Anchor GitHub & most markdown renderers generate for a heading.
+fn slugify(heading: &str) -> String {
+    heading
+        .trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| match c {
+            ' ' => Some('-'),
+            c if c.is_alphanumeric() || c == '-' || c == '_' => Some(c),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Split a markdown document into sections at each heading. Content before
+/// the first heading is skipped.
+pub fn sections(text: &str) -> Vec<Section> {
+    let mut sections: Vec<Section> = Vec::new();
+    let mut in_fence = false;
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+        }
+
+        let is_heading = !in_fence
+            && trimmed.starts_with('#')
+            && trimmed.trim_start_matches('#').starts_with(' ');
+
+        if is_heading {
+            let heading = trimmed.trim_start_matches('#').trim();
+            sections.push(Section {
+                anchor: slugify(heading),
+                heading: heading.to_string(),
+                content: String::new(),
+            });
+        } else if let Some(section) = sections.last_mut() {
+            section.content.push_str(line);
+            section.content.push('\n');
+        }
+    }
+
+    for section in sections.iter_mut() {
+        section.content = section.content.trim().to_string();
+    }
+    sections.retain(|section| !section.anchor.is_empty() && !section.content.is_empty());
+    sections
+}
+
+#[cfg(test)]
+mod test {
+    use super::{code_blocks, sections};
+
+    #[test]
+    fn test_code_blocks() {
+        let text = "# Title\n\n```rust\nfn main() {\n    println!(\"hi\");\n}\n```\n\nSome text\n~~~\nls -la\n~~~\n";
+        assert_eq!(
+            code_blocks(text),
+            vec![
+                "fn main() {\n    println!(\"hi\");\n}".to_string(),
+                "ls -la".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sections() {
+        let text = "Intro\n# Getting Started\nInstall it.\n```\n# not a heading\n```\n## API (v2)\nCall it.\n";
+        let found = sections(text);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].anchor, "getting-started");
+        assert_eq!(found[0].content, "Install it.\n```\n# not a heading\n```");
+        assert_eq!(found[1].anchor, "api-v2");
+        assert_eq!(found[1].heading, "API (v2)");
+    }
+}
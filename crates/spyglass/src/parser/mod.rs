@@ -5,8 +5,93 @@ use std::{
     path::Path,
 };
 
+use chrono::NaiveDateTime;
+
+use crate::search::geo::GeoPoint;
+
 mod docx_parser;
+pub mod image;
+pub mod markdown;
+pub mod video;
 mod xlsx_parser;
+pub mod xml;
+
+/// Metadata pulled from photos & videos, which otherwise have no text to index.
+#[derive(Debug, Default, PartialEq)]
+pub struct MediaMetadata {
+    pub camera: Option<String>,
+    pub captured_at: Option<NaiveDateTime>,
+    pub width: Option<u64>,
+    pub height: Option<u64>,
+    pub duration_secs: Option<f64>,
+    pub location: Option<GeoPoint>,
+}
+
+impl MediaMetadata {
+    /// Searchable text describing the media, e.g. "Canon EOS R5 2022-12-01".
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(camera) = &self.camera {
+            parts.push(camera.clone());
+        }
+        if let Some(captured_at) = &self.captured_at {
+            parts.push(captured_at.format("%Y-%m-%d").to_string());
+        }
+        if let (Some(width), Some(height)) = (self.width, self.height) {
+            parts.push(format!("{}x{}", width, height));
+        }
+
+        parts.join(" ")
+    }
+
+    pub fn fields(&self) -> Vec<(String, String)> {
+        let mut fields = Vec::new();
+        if let Some(camera) = &self.camera {
+            fields.push(("camera".to_string(), camera.clone()));
+        }
+        if let Some(captured_at) = &self.captured_at {
+            fields.push((
+                "captured".to_string(),
+                captured_at.format("%Y-%m-%d").to_string(),
+            ));
+        }
+
+        fields
+    }
+
+    pub fn numbers(&self) -> Vec<(String, f64)> {
+        let mut numbers = Vec::new();
+        if let Some(width) = self.width {
+            numbers.push(("width".to_string(), width as f64));
+        }
+        if let Some(height) = self.height {
+            numbers.push(("height".to_string(), height as f64));
+        }
+        if let Some(duration) = self.duration_secs {
+            numbers.push(("duration".to_string(), duration));
+        }
+        if let Some(location) = &self.location {
+            numbers.extend(location.as_numbers());
+        }
+
+        numbers
+    }
+}
+
+pub fn is_media(extension: &str) -> bool {
+    image::is_image(extension) || video::is_video(extension)
+}
+
+/// Metadata for the photo/video @ `file_path`, if it can be read.
+pub fn media_metadata(extension: &str, file_path: &Path) -> Option<MediaMetadata> {
+    if image::is_image(extension) {
+        image::metadata(file_path)
+    } else if video::is_video(extension) {
+        video::metadata(file_path)
+    } else {
+        None
+    }
+}
 
 /*
  * Processes the file extension to identify if there is a special
@@ -41,3 +126,37 @@ pub fn parse_file(extension: &OsStr, file_path: &Path) -> io::Result<String> {
         format!("Extension {:?} not supported", extension),
     ))
 }
+
+#[cfg(test)]
+mod test {
+    use chrono::NaiveDate;
+
+    use super::MediaMetadata;
+
+    #[test]
+    fn test_media_metadata() {
+        let metadata = MediaMetadata {
+            camera: Some("Canon EOS R5".to_string()),
+            captured_at: NaiveDate::from_ymd_opt(2022, 12, 1).and_then(|d| d.and_hms_opt(9, 30, 0)),
+            width: Some(8192),
+            height: Some(5464),
+            ..Default::default()
+        };
+
+        assert_eq!(metadata.summary(), "Canon EOS R5 2022-12-01 8192x5464");
+        assert_eq!(
+            metadata.fields(),
+            vec![
+                ("camera".to_string(), "Canon EOS R5".to_string()),
+                ("captured".to_string(), "2022-12-01".to_string()),
+            ]
+        );
+        assert_eq!(
+            metadata.numbers(),
+            vec![
+                ("width".to_string(), 8192.0),
+                ("height".to_string(), 5464.0)
+            ]
+        );
+    }
+}
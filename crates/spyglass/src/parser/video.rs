@@ -0,0 +1,42 @@
+use std::{fs::File, io::BufReader, path::Path};
+
+use chrono::NaiveDateTime;
+use mp4::{Mp4Reader, TrackType};
+
+use super::MediaMetadata;
+
+const VIDEO_EXTENSIONS: [&str; 3] = ["mp4", "m4v", "mov"];
+
+/// MP4 timestamps count from 1904 rather than 1970.
+const MP4_EPOCH_OFFSET_SECS: i64 = 2_082_844_800;
+
+pub fn is_video(extension: &str) -> bool {
+    VIDEO_EXTENSIONS
+        .iter()
+        .any(|ext| ext.eq_ignore_ascii_case(extension))
+}
+
+/// Duration, dimensions & creation time of the video @ `path`.
+pub fn metadata(path: &Path) -> Option<MediaMetadata> {
+    let file = File::open(path).ok()?;
+    let size = file.metadata().ok()?.len();
+    let video = Mp4Reader::read_header(BufReader::new(file), size).ok()?;
+
+    let track = video
+        .tracks()
+        .values()
+        .find(|track| matches!(track.track_type(), Ok(TrackType::Video)));
+
+    let captured_at = match video.moov.mvhd.creation_time as i64 {
+        0 => None,
+        created => NaiveDateTime::from_timestamp_opt(created - MP4_EPOCH_OFFSET_SECS, 0),
+    };
+
+    Some(MediaMetadata {
+        captured_at,
+        width: track.map(|track| track.width().into()),
+        height: track.map(|track| track.height().into()),
+        duration_secs: Some(video.duration().as_secs_f64()),
+        ..Default::default()
+    })
+}
@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+/// Minimal XML element tree, enough to pull data out of feeds & OPML files.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct XmlNode {
+    /// Element name, including any namespace prefix (e.g. `itunes:author`)
+    pub name: String,
+    pub attrs: HashMap<String, String>,
+    /// Text & CDATA directly inside this element.
+    pub text: String,
+    pub children: Vec<XmlNode>,
+}
+
+impl XmlNode {
+    fn from_start(start: &BytesStart) -> Self {
+        let attrs = start
+            .attributes()
+            .flatten()
+            .filter_map(|attr| {
+                let key = String::from_utf8_lossy(attr.key.as_ref()).to_string();
+                let value = attr.unescape_value().ok()?.to_string();
+                Some((key, value))
+            })
+            .collect();
+
+        Self {
+            name: String::from_utf8_lossy(start.name().as_ref()).to_string(),
+            attrs,
+            ..Default::default()
+        }
+    }
+
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs.get(name).map(|value| value.as_str())
+    }
+
+    /// First direct child w/ `name`.
+    pub fn child(&self, name: &str) -> Option<&XmlNode> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    /// Direct children w/ `name`.
+    pub fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a XmlNode> + 'a {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    /// Trimmed text of the first direct child w/ `name`.
+    pub fn child_text(&self, name: &str) -> Option<&str> {
+        self.child(name)
+            .map(|child| child.text.trim())
+            .filter(|text| !text.is_empty())
+    }
+
+    /// Every element w/ `name` under this one, depth first.
+    pub fn descendants(&self, name: &str) -> Vec<&XmlNode> {
+        let mut found = Vec::new();
+        for child in &self.children {
+            if child.name == name {
+                found.push(child);
+            }
+            found.extend(child.descendants(name));
+        }
+
+        found
+    }
+}
+
+/// Parse `xml` into a tree, returning the root element.
+pub fn parse(xml: &str) -> anyhow::Result<XmlNode> {
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(false);
+
+    // Elements that are still open, innermost last.
+    let mut stack: Vec<XmlNode> = vec![XmlNode::default()];
+    loop {
+        match reader.read_event()? {
+            Event::Start(start) => stack.push(XmlNode::from_start(&start)),
+            Event::Empty(start) => {
+                let node = XmlNode::from_start(&start);
+                if let Some(parent) = stack.last_mut() {
+                    parent.children.push(node);
+                }
+            }
+            Event::End(_) => {
+                // The document root is never popped
+                if stack.len() > 1 {
+                    if let Some(node) = stack.pop() {
+                        if let Some(parent) = stack.last_mut() {
+                            parent.children.push(node);
+                        }
+                    }
+                }
+            }
+            Event::Text(text) => {
+                if let Some(node) = stack.last_mut() {
+                    node.text.push_str(&text.unescape()?);
+                }
+            }
+            Event::CData(data) => {
+                if let Some(node) = stack.last_mut() {
+                    node.text
+                        .push_str(&String::from_utf8_lossy(&data.into_inner()));
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    // Close any elements left open by a truncated document
+    while stack.len() > 1 {
+        if let Some(node) = stack.pop() {
+            if let Some(parent) = stack.last_mut() {
+                parent.children.push(node);
+            }
+        }
+    }
+
+    stack
+        .pop()
+        .and_then(|document| document.children.into_iter().next())
+        .ok_or_else(|| anyhow::anyhow!("No root element found"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse;
+
+    #[test]
+    fn test_parse() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <rss version="2.0" xmlns:itunes="http://www.itunes.com/dtds/podcast-1.0.dtd">
+              <channel>
+                <title>Sleep Science &amp; You</title>
+                <itunes:author>Dr. Nap</itunes:author>
+                <item>
+                  <title>Caffeine</title>
+                  <description><![CDATA[<p>How <b>coffee</b> affects sleep</p>]]></description>
+                  <enclosure url="https://example.com/ep1.mp3" type="audio/mpeg" />
+                </item>
+                <item><title>Naps</title></item>
+              </channel>
+            </rss>"#;
+
+        let root = parse(xml).unwrap();
+        assert_eq!(root.name, "rss");
+        assert_eq!(root.attr("version"), Some("2.0"));
+
+        let channel = root.child("channel").unwrap();
+        assert_eq!(channel.child_text("title"), Some("Sleep Science & You"));
+        assert_eq!(channel.child_text("itunes:author"), Some("Dr. Nap"));
+
+        let items = channel.children("item").collect::<Vec<_>>();
+        assert_eq!(items.len(), 2);
+        assert_eq!(
+            items[0].child_text("description"),
+            Some("<p>How <b>coffee</b> affects sleep</p>")
+        );
+        assert_eq!(
+            items[0].child("enclosure").and_then(|e| e.attr("url")),
+            Some("https://example.com/ep1.mp3")
+        );
+        assert_eq!(root.descendants("title").len(), 3);
+    }
+}
@@ -1,9 +1,11 @@
+use crate::content::ContentVerdict;
 use crate::pipeline::collector::DefaultCollector;
 use crate::pipeline::PipelineContext;
-use crate::search::Searcher;
+use crate::search::{DocumentUpdate, Searcher};
 use crate::state::AppState;
 use crate::task::CrawlTask;
 
+use entities::models::crawl_queue::{TaskError, TaskErrorType};
 use entities::models::{crawl_queue, indexed_document};
 use shared::config::{Config, LensConfig, PipelineConfiguration};
 use tokio::sync::mpsc;
@@ -81,18 +83,34 @@ async fn start_crawl(
 
             match parse_result {
                 Ok(parse_result) => {
-                    let crawl_result = parse_result.content;
+                    let mut crawl_result = parse_result.content;
                     // Update job status
                     let _ = crawl_queue::mark_done(&state.db, task.id, None).await;
 
+                    // Check content rules before anything makes it into the index.
+                    match state.content_rules.apply(&mut crawl_result) {
+                        ContentVerdict::Skip(rule) => {
+                            log::info!("Skipping <{}>, content matched {}", crawl_result.url, rule);
+                            let _ = Searcher::delete_by_url(&state, &crawl_result.url).await;
+                            crawl_result.content = None;
+                        }
+                        ContentVerdict::Index(redacted) if !redacted.is_empty() => {
+                            log::info!(
+                                "Redacted {} from <{}>",
+                                redacted.join(", "),
+                                crawl_result.url
+                            );
+                        }
+                        _ => {}
+                    }
+
                     // Add all valid, non-duplicate, non-indexed links found to crawl queue
                     let to_enqueue: Vec<String> = crawl_result.links.into_iter().collect();
 
-                    // Collect all lenses with a pipeline ... any pipeline since there is only one
+                    // Links are routed to the pipeline of the lens they belong to
                     let lenses: Vec<LensConfig> = state
                         .lenses
                         .iter()
-                        .filter(|entry| entry.value().pipeline.is_some())
                         .map(|entry| entry.value().clone())
                         .collect();
 
@@ -102,7 +120,7 @@ async fn start_crawl(
                         &lenses,
                         &state.user_settings,
                         &Default::default(),
-                        Some(pipeline_name.to_owned()),
+                        None,
                     )
                     .await
                     {
@@ -132,12 +150,19 @@ async fn start_crawl(
                             if let Ok(mut index_writer) = state.index.writer.lock() {
                                 match Searcher::upsert_document(
                                     &mut index_writer,
-                                    existing.clone().map(|f| f.doc_id),
-                                    &crawl_result.title.unwrap_or_default(),
-                                    &crawl_result.description.unwrap_or_default(),
-                                    url_host,
-                                    url.as_str(),
-                                    &content,
+                                    DocumentUpdate {
+                                        doc_id: existing.clone().map(|f| f.doc_id),
+                                        title: &crawl_result.title.unwrap_or_default(),
+                                        description: &crawl_result.description.unwrap_or_default(),
+                                        domain: url_host,
+                                        url: url.as_str(),
+                                        content: &content,
+                                        fields: &crawl_result.fields,
+                                        numbers: &crawl_result.numbers,
+                                        code: &crawl_result.code,
+                                        sections: &crawl_result.sections,
+                                        ..Default::default()
+                                    },
                                 ) {
                                     Ok(new_doc_id) => Some(new_doc_id),
                                     _ => None,
@@ -171,14 +196,17 @@ async fn start_crawl(
                 Err(err) => {
                     log::info!("Unable to crawl id: {} - {:?}", task.id, err);
                     // mark crawl as failed
-                    crawl_queue::mark_failed(&state.db, task.id, false).await;
+                    let error = TaskError::new(TaskErrorType::Parse, &err);
+                    crawl_queue::mark_failed(&state.db, &state.user_settings, task.id, Some(error))
+                        .await;
                 }
             }
         }
         Err(err) => {
             log::info!("Unable to crawl id: {} - {:?}", task.id, err);
             // mark crawl as failed
-            crawl_queue::mark_failed(&state.db, task.id, false).await;
+            let error = TaskError::new(TaskErrorType::Collect, &err);
+            crawl_queue::mark_failed(&state.db, &state.user_settings, task.id, Some(error)).await;
         }
     }
 }
@@ -5,7 +5,7 @@ pub mod parser;
 use crate::search::lens;
 use crate::state::AppState;
 use crate::task::CrawlTask;
-use entities::models::crawl_queue;
+use entities::models::crawl_queue::{self, TaskError, TaskErrorType};
 use shared::config::Config;
 use shared::config::PipelineConfiguration;
 use std::collections::HashMap;
@@ -122,7 +122,11 @@ pub async fn initialize_pipelines(
                         }
                         None => {
                             log::warn!("No pipeline configuration found for pipeline {:?}, failing crawl id: {}", &pipeline, task.id);
-                            fail_crawl_cmd(&app_state, task.id).await;
+                            let error = TaskError::new(
+                                TaskErrorType::Other,
+                                &format!("No pipeline configuration found for {}", pipeline),
+                            );
+                            fail_crawl_cmd(&app_state, task.id, error).await;
                         }
                     }
                 }
@@ -134,9 +138,9 @@ pub async fn initialize_pipelines(
 }
 
 // Helper function used to set any crawl failures with the status of failed.
-pub async fn fail_crawl_cmd(state: &AppState, task_uid: i64) {
+pub async fn fail_crawl_cmd(state: &AppState, task_uid: i64, error: TaskError) {
     // mark crawl as failed
-    crawl_queue::mark_failed(&state.db, task_uid, false).await;
+    crawl_queue::mark_failed(&state.db, &state.user_settings, task_uid, Some(error)).await;
 }
 
 /// Read pipelines into the AppState
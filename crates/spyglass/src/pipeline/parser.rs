@@ -1,5 +1,5 @@
 use super::PipelineContext;
-use crate::crawler::{CrawlResult, Crawler};
+use crate::crawler::{CrawlResult, Crawler, ScrapeOptions};
 use url::Url;
 
 pub struct DefaultParser {
@@ -15,13 +15,16 @@ pub struct ParseResult {
 impl DefaultParser {
     pub async fn parse(
         &self,
-        _context: &mut PipelineContext,
+        context: &mut PipelineContext,
         _task_id: i64,
         crawl_result: &CrawlResult,
     ) -> Result<ParseResult, String> {
         if let Some(raw_content) = &crawl_result.content {
             let url = Url::parse(&crawl_result.url).expect("Invalid fetch URL");
-            let scrape_result = self.crawler.scrape_page(&url, raw_content).await;
+            // Scrape w/ the options from the lenses this page belongs to, same
+            // as a regular crawl.
+            let options = ScrapeOptions::for_url(&context.state, &url);
+            let scrape_result = self.crawler.scrape_page(&url, raw_content, &options).await;
             return Result::Ok(ParseResult {
                 content: scrape_result,
             });
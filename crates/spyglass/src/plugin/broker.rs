@@ -0,0 +1,150 @@
+use chrono::Utc;
+use entities::models::connection;
+use entities::sea_orm::{ActiveModelTrait, ColumnTrait, EntityTrait, QueryFilter, Set};
+use spyglass_plugin::AccessToken;
+
+use super::PluginEnv;
+use crate::oauth;
+
+/// Refresh tokens that expire within this many seconds before handing them out,
+/// so plugins always get one they can use right away.
+const MIN_TOKEN_LIFETIME_SECS: i64 = 5 * 60;
+
+/// Make sure a plugin is allowed to use `scopes` of a connection. Plugins need
+/// the user's grant, can only ask for scopes listed in their manifest & only
+/// ones the user authorized when adding the connection.
+fn check_scopes(
+    is_granted: bool,
+    plugin_scopes: Option<&Vec<String>>,
+    conn_scopes: &[String],
+    requested: &[String],
+) -> Result<(), String> {
+    if !is_granted {
+        return Err("connection access not granted to plugin".into());
+    }
+
+    let plugin_scopes = plugin_scopes.ok_or("connection not listed in plugin manifest")?;
+    if requested.is_empty() {
+        return Err("no scopes requested".into());
+    }
+
+    for scope in requested {
+        if !plugin_scopes.contains(scope) {
+            return Err(format!("scope {} not listed in plugin manifest", scope));
+        }
+
+        if !conn_scopes.contains(scope) {
+            return Err(format!("scope {} not authorized for connection", scope));
+        }
+    }
+
+    Ok(())
+}
+
+/// Hand out a fresh access token for a connection the user has granted the
+/// plugin access to. Refresh tokens never leave the host.
+pub(crate) async fn access_token(
+    env: &PluginEnv,
+    api_id: &str,
+    account: Option<&str>,
+    scopes: &[String],
+) -> Result<AccessToken, String> {
+    let state = &env.app_state;
+    let is_granted = state
+        .user_settings
+        .plugin_connection_grants
+        .get(&env.name)
+        .map_or(false, |grants| grants.iter().any(|id| id == api_id));
+
+    let mut query = connection::Entity::find().filter(connection::Column::ApiId.eq(api_id));
+    if let Some(account) = account {
+        query = query.filter(connection::Column::Account.eq(account));
+    }
+
+    let conn = query
+        .one(&state.db)
+        .await
+        .map_err(|err| err.to_string())?
+        .ok_or_else(|| format!("no {} connection found", api_id))?;
+
+    check_scopes(
+        is_granted,
+        env.connections.get(api_id),
+        &conn.scopes.scopes,
+        scopes,
+    )?;
+
+    let expires_at = conn
+        .expires_in
+        .map(|secs| conn.granted_at.timestamp() + secs);
+    let is_expiring = expires_at.map_or(false, |at| {
+        at - Utc::now().timestamp() < MIN_TOKEN_LIFETIME_SECS
+    });
+
+    if !is_expiring {
+        return Ok(AccessToken {
+            token: conn.access_token,
+            account: conn.account,
+            expires_at,
+        });
+    }
+
+    let refresh_token = conn
+        .refresh_token
+        .clone()
+        .ok_or("access token expired & no refresh token available")?;
+    let refreshed = oauth::refresh_access_token(api_id, &refresh_token)
+        .await
+        .map_err(|err| format!("unable to refresh token: {}", err))?;
+
+    let account = conn.account.clone();
+    let now = Utc::now();
+    let mut update: connection::ActiveModel = conn.into();
+    update.access_token = Set(refreshed.access_token.clone());
+    update.expires_in = Set(refreshed.expires_in);
+    update.granted_at = Set(now);
+    if let Err(err) = update.save(&state.db).await {
+        log::error!("Unable to save refreshed credentials: {}", err);
+    }
+
+    log::info!("<{}> was issued a token for {}", env.name, api_id);
+    Ok(AccessToken {
+        token: refreshed.access_token,
+        account,
+        expires_at: refreshed.expires_in.map(|secs| now.timestamp() + secs),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::check_scopes;
+
+    #[test]
+    fn test_check_scopes() {
+        let plugin_scopes = vec!["drive.readonly".to_string()];
+        let conn_scopes = vec!["drive.readonly".to_string(), "email".to_string()];
+        let requested = vec!["drive.readonly".to_string()];
+
+        assert!(check_scopes(true, Some(&plugin_scopes), &conn_scopes, &requested).is_ok());
+        // User hasn't granted access
+        assert!(check_scopes(false, Some(&plugin_scopes), &conn_scopes, &requested).is_err());
+        // Not in the plugin manifest
+        assert!(check_scopes(true, None, &conn_scopes, &requested).is_err());
+        assert!(check_scopes(
+            true,
+            Some(&plugin_scopes),
+            &conn_scopes,
+            &["email".to_string()]
+        )
+        .is_err());
+        // Not authorized by the user for this connection
+        assert!(check_scopes(
+            true,
+            Some(&plugin_scopes),
+            &["email".to_string()],
+            &requested
+        )
+        .is_err());
+        assert!(check_scopes(true, Some(&plugin_scopes), &conn_scopes, &[]).is_err());
+    }
+}
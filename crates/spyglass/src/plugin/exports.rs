@@ -10,13 +10,21 @@ use wasmer::{Exports, Function, Store};
 use wasmer_wasi::WasiEnv;
 
 use super::{
-    wasi_read, wasi_read_string, wasi_write, PluginCommand, PluginConfig, PluginEnv, PluginId,
+    broker, http, logs, permissions, plugin_env_vars, wasi_read, wasi_read_string, wasi_write,
+    PluginCommand, PluginConfig, PluginEnv, PluginId,
 };
 use crate::search::Searcher;
 use crate::state::AppState;
 
 use entities::models::crawl_queue::{enqueue_all, EnqueueSettings};
-use spyglass_plugin::{utils::path_to_uri, ListDirEntry, PluginCommandRequest};
+use shared::response::{PluginLogSource, PluginTaskProgress};
+use spyglass_plugin::{
+    utils::path_to_uri, HttpResponse, ListDirEntry, PluginCommandRequest, PluginEvent,
+};
+
+/// Scheduled tasks are capped to this delay so a typo can't park a task for
+/// days.
+const MAX_TASK_DELAY_SECS: u64 = 60 * 60;
 
 pub fn register_exports(
     plugin_id: PluginId,
@@ -27,6 +35,16 @@ pub fn register_exports(
     env: &WasiEnv,
 ) -> Exports {
     let mut exports = Exports::new();
+    // Only what the plugin asks for & the user approves is allowed.
+    let permissions = state
+        .user_settings
+        .plugin_permission_grants
+        .get(&plugin.name)
+        .map(|granted| plugin.permissions.intersect(granted))
+        .unwrap_or_default();
+    let allowed_paths =
+        permissions::expand_paths(&permissions.filesystem, &plugin_env_vars(plugin));
+
     let env = PluginEnv {
         id: plugin_id,
         name: plugin.name.clone(),
@@ -34,6 +52,10 @@ pub fn register_exports(
         data_dir: plugin.data_folder(),
         wasi_env: env.clone(),
         cmd_writer: cmd_writer.clone(),
+        connections: plugin.connections.clone(),
+        allowed_domains: plugin.allowed_domains.clone(),
+        permissions,
+        allowed_paths,
     };
 
     exports.insert(
@@ -51,13 +73,43 @@ async fn handle_plugin_cmd_request(
     cmd: &PluginCommandRequest,
     env: &PluginEnv,
 ) -> anyhow::Result<()> {
+    if let Err(err) = permissions::check(cmd, &env.permissions, &env.allowed_paths) {
+        // Plugins wait on a response to their requests
+        if let PluginCommandRequest::Http(_) = cmd {
+            wasi_write(&env.wasi_env, &Err::<HttpResponse, String>(err.clone()))?;
+        }
+        return Err(Error::msg(format!("permission denied: {}", err)));
+    }
+
     match cmd {
+        // Hand out a connection's access token, if the plugin is allowed to use it
+        PluginCommandRequest::AccessToken {
+            api_id,
+            account,
+            scopes,
+        } => {
+            let token = broker::access_token(env, api_id, account.as_deref(), scopes).await;
+            if let Err(err) = &token {
+                log::warn!("<{}> denied token for {}: {}", env.name, api_id, err);
+            }
+            wasi_write(&env.wasi_env, &token)?;
+        }
         // Delete document from index
         PluginCommandRequest::DeleteDoc { url } => {
             Searcher::delete_by_url(&env.app_state, url).await?
         }
+        // Make an HTTP request, if the plugin may reach the domain
+        PluginCommandRequest::Http(request) => {
+            let resp = http::send(env, request).await;
+            if let Err(err) = &resp {
+                log::warn!("<{}> request to {} failed: {}", env.name, request.url, err);
+            }
+            wasi_write(&env.wasi_env, &resp)?;
+        }
         // Enqueue a list of URLs to be crawled
-        PluginCommandRequest::Enqueue { urls } => handle_plugin_enqueue(env, urls),
+        PluginCommandRequest::Enqueue { urls } => {
+            handle_plugin_enqueue(&env.app_state, &env.name, urls)
+        }
         PluginCommandRequest::ListDir { path } => {
             log::debug!("{} listing path: {}", env.name, path);
             let entries = std::fs::read_dir(path)?
@@ -73,6 +125,33 @@ async fn handle_plugin_cmd_request(
                 .collect::<Vec<ListDirEntry>>();
             wasi_write(&env.wasi_env, &entries)?;
         }
+        PluginCommandRequest::ReportProgress {
+            task,
+            percent,
+            message,
+        } => record_progress(env, task, *percent, message.clone()),
+        // Call back into the plugin w/ the task once the delay is up
+        PluginCommandRequest::ScheduleTask {
+            task,
+            payload,
+            delay_secs,
+        } => {
+            let delay = (*delay_secs).min(MAX_TASK_DELAY_SECS);
+            if delay > 0 {
+                tokio::time::sleep(tokio::time::Duration::from_secs(delay)).await;
+            }
+
+            log::debug!("<{}> running scheduled task {}", env.name, task);
+            env.cmd_writer
+                .send(PluginCommand::HandleUpdate {
+                    plugin_id: env.id,
+                    event: PluginEvent::RunTask {
+                        task: task.clone(),
+                        payload: payload.clone(),
+                    },
+                })
+                .await?;
+        }
         // Subscribe to a plugin event
         PluginCommandRequest::Subscribe(event) => {
             env.cmd_writer
@@ -84,7 +163,8 @@ async fn handle_plugin_cmd_request(
         // This is for plugins who need to run a query against some sqlite3 file,
         // for example the Firefox bookmarks/history are store in such a file.
         PluginCommandRequest::SqliteQuery { path, query } => {
-            let db_path = env.data_dir.join(path);
+            // Only databases copied into the plugin's data folder can be read.
+            let db_path = permissions::data_path(&env.data_dir, path).map_err(Error::msg)?;
             if !db_path.exists() {
                 return Err(Error::msg(format!("Invalid sqlite db path: {}", path)));
             }
@@ -104,7 +184,7 @@ async fn handle_plugin_cmd_request(
                 .collect();
 
             log::debug!("PCR::SqliteQUery: found {} urls", urls.len());
-            handle_plugin_enqueue(env, &urls);
+            handle_plugin_enqueue(&env.app_state, &env.name, &urls);
         }
         PluginCommandRequest::SyncFile { dst, src } => {
             handle_sync_file(env, dst, src);
@@ -143,6 +223,12 @@ pub(crate) fn plugin_cmd(env: &PluginEnv) {
                     env.name,
                     e
                 );
+                logs::record(
+                    &env.app_state,
+                    &env.name,
+                    PluginLogSource::Host,
+                    &format!("host call failed: {}", e),
+                );
             }
         });
     }
@@ -153,6 +239,28 @@ pub(crate) fn plugin_cmd(env: &PluginEnv) {
 pub(crate) fn plugin_log(env: &PluginEnv) {
     if let Ok(msg) = wasi_read_string(&env.wasi_env) {
         log::info!("{}: {}", env.name, msg);
+        logs::record(&env.app_state, &env.name, PluginLogSource::Log, &msg);
+    }
+}
+
+/// Save the latest progress of a plugin task, replacing any previous report
+/// for the same task.
+fn record_progress(env: &PluginEnv, task: &str, percent: u8, message: Option<String>) {
+    let progress = PluginTaskProgress {
+        task: task.to_string(),
+        percent: percent.min(100),
+        message,
+        updated_at: chrono::Utc::now().to_rfc3339(),
+    };
+
+    let mut tasks = env
+        .app_state
+        .plugin_progress
+        .entry(env.name.clone())
+        .or_default();
+    match tasks.iter_mut().find(|existing| existing.task == task) {
+        Some(existing) => *existing = progress,
+        None => tasks.push(progress),
     }
 }
 
@@ -174,16 +282,16 @@ fn handle_sync_file(env: &PluginEnv, dst: &str, src: &str) {
     }
 }
 
-fn handle_plugin_enqueue(env: &PluginEnv, urls: &Vec<String>) {
-    log::info!("{} enqueuing {} urls", env.name, urls.len());
-    let state = env.app_state.clone();
+pub(super) fn handle_plugin_enqueue(state: &AppState, plugin_name: &str, urls: &[String]) {
+    log::info!("{} enqueuing {} urls", plugin_name, urls.len());
+    let state = state.clone();
     // Grab a handle to the plugin manager runtime
     let rt = tokio::runtime::Handle::current();
-    let urls = urls.clone();
+    let urls = urls.to_vec();
 
     // Hacky way to apply lenses to enqueues from the plugins.
-    let mut tags = vec![(TagType::Source, env.name.clone())];
-    match env.name.as_str() {
+    let mut tags = vec![(TagType::Source, plugin_name.to_string())];
+    match plugin_name {
         "chrome-importer" | "firefox-importer" => {
             tags.push((TagType::Lens, "bookmarks".to_owned()));
         }
@@ -221,7 +329,7 @@ pub struct WalkStats {
     pub skipped: i32,
 }
 
-async fn handle_walk_and_enqueue(
+pub(super) async fn handle_walk_and_enqueue(
     state: &AppState,
     path: PathBuf,
     supported_exts: &HashSet<String>,
@@ -0,0 +1,141 @@
+use std::time::Duration;
+
+use spyglass_plugin::{HttpMethod, HttpRequest, HttpResponse};
+use url::Url;
+
+use super::PluginEnv;
+
+/// Plugin requests that take longer than this are given up on.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Responses are cut off after this many bytes, it all has to go through the
+/// plugin's stdin.
+const MAX_RESPONSE_BYTES: usize = 5 * 1024 * 1024;
+
+/// Whether `host` is `domain` or one of its subdomains.
+fn matches_domain(host: &str, domain: &str) -> bool {
+    let domain = domain.trim_start_matches("*.").trim_end_matches('.');
+    host.eq_ignore_ascii_case(domain)
+        || host
+            .to_ascii_lowercase()
+            .ends_with(&format!(".{}", domain.to_ascii_lowercase()))
+}
+
+/// Make sure a plugin may send a request to `url`. The domain has to be in the
+/// plugin manifest & approved by the user.
+fn check_domain(
+    allowed: &[String],
+    granted: Option<&Vec<String>>,
+    url: &Url,
+) -> Result<(), String> {
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return Err(format!("{} is not an HTTP url", url));
+    }
+
+    let host = url
+        .host_str()
+        .ok_or_else(|| format!("{} has no host", url))?;
+    if !allowed.iter().any(|domain| matches_domain(host, domain)) {
+        return Err(format!("{} not listed in plugin manifest", host));
+    }
+
+    let is_granted = granted.map_or(false, |granted| {
+        granted.iter().any(|domain| matches_domain(host, domain))
+    });
+    if !is_granted {
+        return Err(format!("requests to {} not approved for plugin", host));
+    }
+
+    Ok(())
+}
+
+/// Send a request for a plugin. Redirects aren't followed, plugins have to
+/// request the new location themselves so it's checked too.
+pub(crate) async fn send(env: &PluginEnv, request: &HttpRequest) -> Result<HttpResponse, String> {
+    let url = Url::parse(&request.url).map_err(|err| err.to_string())?;
+    check_domain(
+        &env.allowed_domains,
+        env.app_state
+            .user_settings
+            .plugin_domain_grants
+            .get(&env.name),
+        &url,
+    )?;
+
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(|err| err.to_string())?;
+
+    let mut builder = match request.method {
+        HttpMethod::Get => client.get(url),
+        HttpMethod::Post => client.post(url),
+    };
+    for (name, value) in &request.headers {
+        builder = builder.header(name, value);
+    }
+    if let Some(body) = &request.body {
+        builder = builder.body(body.clone());
+    }
+
+    let mut resp = builder.send().await.map_err(|err| err.to_string())?;
+    let status = resp.status().as_u16();
+    let headers = resp
+        .headers()
+        .iter()
+        .filter_map(|(name, value)| {
+            value
+                .to_str()
+                .ok()
+                .map(|value| (name.to_string(), value.to_string()))
+        })
+        .collect();
+
+    let mut body = Vec::new();
+    while let Some(chunk) = resp.chunk().await.map_err(|err| err.to_string())? {
+        body.extend_from_slice(&chunk);
+        if body.len() > MAX_RESPONSE_BYTES {
+            return Err(format!("response larger than {} bytes", MAX_RESPONSE_BYTES));
+        }
+    }
+
+    log::debug!(
+        "<{}> {:?} {} -> {}",
+        env.name,
+        request.method,
+        request.url,
+        status
+    );
+    Ok(HttpResponse {
+        status,
+        headers,
+        body: String::from_utf8_lossy(&body).to_string(),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use url::Url;
+
+    use super::check_domain;
+
+    #[test]
+    fn test_check_domain() {
+        let allowed = vec!["api.example.com".to_string(), "*.news.org".to_string()];
+        let granted = allowed.clone();
+        let check = |url: &str, granted: Option<&Vec<String>>| {
+            check_domain(&allowed, granted, &Url::parse(url).unwrap())
+        };
+
+        assert!(check("https://api.example.com/v1/items", Some(&granted)).is_ok());
+        assert!(check("https://feeds.news.org/latest", Some(&granted)).is_ok());
+        assert!(check("https://news.org/", Some(&granted)).is_ok());
+        // Not approved by the user
+        assert!(check("https://api.example.com/v1/items", None).is_err());
+        assert!(check("https://api.example.com/", Some(&vec!["news.org".into()])).is_err());
+        // Not in the plugin manifest
+        assert!(check("https://example.com/", Some(&granted)).is_err());
+        assert!(check("https://evilnews.org/", Some(&granted)).is_err());
+        assert!(check("file:///etc/passwd", Some(&granted)).is_err());
+    }
+}
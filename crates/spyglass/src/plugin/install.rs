@@ -0,0 +1,375 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use flate2::read::GzDecoder;
+use sha2::{Digest, Sha256};
+use url::Url;
+
+use shared::config::Config;
+use shared::plugin::PluginConfig;
+use shared::response::InstallablePlugin;
+
+/// Plugins that can be installed by name.
+pub const PLUGIN_REGISTRY_URL: &str =
+    "https://raw.githubusercontent.com/spyglass-search/plugin-box/main/index.ron";
+/// Largest plugin archive we'll download.
+pub const MAX_ARCHIVE_BYTES: usize = 50_000_000;
+
+/// Where to download a plugin from & what its archive should hash to.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PluginSource {
+    pub download_url: Url,
+    pub sha256: String,
+}
+
+fn http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .user_agent(concat!(
+            env!("CARGO_PKG_NAME"),
+            "/",
+            env!("CARGO_PKG_VERSION")
+        ))
+        .timeout(std::time::Duration::from_secs(60))
+        .build()
+        .expect("Unable to create reqwest client")
+}
+
+/// Plugins are only downloaded over HTTPS so the checksum can be trusted.
+fn parse_download_url(url: &str) -> anyhow::Result<Url> {
+    let url = Url::parse(url)?;
+    if url.scheme() != "https" {
+        return Err(anyhow::anyhow!("{} is not an HTTPS url", url));
+    }
+
+    Ok(url)
+}
+
+async fn fetch(client: &reqwest::Client, url: &Url) -> anyhow::Result<Vec<u8>> {
+    let resp = client.get(url.clone()).send().await?.error_for_status()?;
+    if resp.content_length().unwrap_or_default() as usize > MAX_ARCHIVE_BYTES {
+        return Err(anyhow::anyhow!("{} is too large", url));
+    }
+
+    let bytes = resp.bytes().await?;
+    if bytes.len() > MAX_ARCHIVE_BYTES {
+        return Err(anyhow::anyhow!("{} is too large", url));
+    }
+
+    Ok(bytes.to_vec())
+}
+
+/// Find the plugin `name` in the registry index.
+pub fn find_in_registry(index: &str, name: &str) -> anyhow::Result<PluginSource> {
+    let plugins = ron::from_str::<Vec<InstallablePlugin>>(index)?;
+    let plugin = plugins
+        .into_iter()
+        .find(|plugin| plugin.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| anyhow::anyhow!("No plugin named {} in the registry", name))?;
+
+    Ok(PluginSource {
+        download_url: parse_download_url(&plugin.download_url)?,
+        sha256: plugin.sha256,
+    })
+}
+
+/// An archive installed by URL, w/ the checksum its author published. The
+/// checksum can't be fetched from next to the archive since whoever can swap
+/// out one can swap out the other.
+pub fn url_source(url: &str, sha256: Option<&str>) -> anyhow::Result<PluginSource> {
+    let download_url = parse_download_url(url)?;
+    let sha256 = sha256
+        .map(|sha256| sha256.trim())
+        .filter(|sha256| sha256.len() == 64 && sha256.chars().all(|c| c.is_ascii_hexdigit()))
+        .ok_or_else(|| {
+            anyhow::anyhow!("A SHA-256 checksum is required to install {}", download_url)
+        })?;
+
+    Ok(PluginSource {
+        download_url,
+        sha256: sha256.to_lowercase(),
+    })
+}
+
+/// Look up where to get the plugin from. Names are found in the registry,
+/// archives installed by URL need a checksum from the user.
+pub async fn resolve(url_or_name: &str, sha256: Option<&str>) -> anyhow::Result<PluginSource> {
+    if url_or_name.contains("://") {
+        return url_source(url_or_name, sha256);
+    }
+
+    let index = fetch(&http_client(), &Url::parse(PLUGIN_REGISTRY_URL)?).await?;
+    let source = find_in_registry(&String::from_utf8_lossy(&index), url_or_name.trim())?;
+    match sha256 {
+        Some(sha256) if !sha256.trim().eq_ignore_ascii_case(&source.sha256) => Err(
+            anyhow::anyhow!("Checksum doesn't match the registry's for {}", url_or_name),
+        ),
+        _ => Ok(source),
+    }
+}
+
+/// Make sure the downloaded archive is the one that was published.
+pub fn verify_checksum(bytes: &[u8], sha256: &str) -> anyhow::Result<()> {
+    let digest = hex::encode(Sha256::digest(bytes));
+    if digest.eq_ignore_ascii_case(sha256.trim()) {
+        Ok(())
+    } else {
+        Err(anyhow::anyhow!(
+            "Checksum mismatch, expected {} but got {}",
+            sha256,
+            digest
+        ))
+    }
+}
+
+/// Unpack a gzip'd tarball into `dest`, refusing anything that isn't a plain
+/// file or folder inside of it.
+pub fn unpack(bytes: &[u8], dest: &Path) -> anyhow::Result<()> {
+    let mut archive = tar::Archive::new(GzDecoder::new(bytes));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_path_buf();
+        if !path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+        {
+            return Err(anyhow::anyhow!(
+                "Invalid path in archive: {}",
+                path.display()
+            ));
+        }
+
+        let entry_type = entry.header().entry_type();
+        if !entry_type.is_file() && !entry_type.is_dir() {
+            return Err(anyhow::anyhow!(
+                "Unsupported entry in archive: {}",
+                path.display()
+            ));
+        }
+
+        entry.unpack_in(dest)?;
+    }
+
+    Ok(())
+}
+
+/// Folder w/ the plugin's manifest, either the root of the archive or the
+/// single folder in it.
+fn plugin_root(unpacked: &Path) -> Option<PathBuf> {
+    if unpacked.join("manifest.ron").is_file() {
+        return Some(unpacked.to_path_buf());
+    }
+
+    let folders = fs::read_dir(unpacked)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect::<Vec<_>>();
+
+    match folders.as_slice() {
+        [folder] if folder.join("manifest.ron").is_file() => Some(folder.clone()),
+        _ => None,
+    }
+}
+
+/// Plugin names become folder names, keep them to something safe.
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('.')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Move an unpacked plugin into `plugins_dir`, replacing an older version
+/// but keeping its data.
+pub fn install_unpacked(unpacked: &Path, plugins_dir: &Path) -> anyhow::Result<PluginConfig> {
+    let root =
+        plugin_root(unpacked).ok_or_else(|| anyhow::anyhow!("No manifest.ron found in archive"))?;
+    let plugin = Config::load_plugin_manifest(&root)
+        .ok_or_else(|| anyhow::anyhow!("Invalid plugin manifest"))?;
+
+    if !is_valid_name(&plugin.name) {
+        return Err(anyhow::anyhow!("Invalid plugin name: {}", plugin.name));
+    }
+
+    if !root.join("main.wasm").is_file() {
+        return Err(anyhow::anyhow!("No main.wasm found in archive"));
+    }
+
+    let dest = plugins_dir.join(&plugin.name);
+    if dest.exists() {
+        let data = dest.join("data");
+        if data.is_dir() && !root.join("data").exists() {
+            fs::rename(&data, root.join("data"))?;
+        }
+        fs::remove_dir_all(&dest)?;
+    }
+    fs::rename(&root, &dest)?;
+
+    Config::load_plugin_manifest(&dest).ok_or_else(|| anyhow::anyhow!("Invalid plugin manifest"))
+}
+
+/// Download, verify & unpack a plugin into the plugins folder. Returns the
+/// installed plugin's config, ready to be initialized.
+pub async fn install(
+    config: &Config,
+    url_or_name: &str,
+    sha256: Option<&str>,
+) -> anyhow::Result<PluginConfig> {
+    let source = resolve(url_or_name, sha256).await?;
+    log::info!("installing plugin from <{}>", source.download_url);
+
+    let archive = fetch(&http_client(), &source.download_url).await?;
+    verify_checksum(&archive, &source.sha256)?;
+
+    // Unpacked outside of the plugins folder so the plugin watcher doesn't
+    // pick up a half written plugin.
+    let staging = config
+        .data_dir()
+        .join(format!("plugin-install-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&staging)?;
+
+    let installed =
+        unpack(&archive, &staging).and_then(|_| install_unpacked(&staging, &config.plugins_dir()));
+    let _ = fs::remove_dir_all(&staging);
+    installed
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use sha2::{Digest, Sha256};
+
+    use super::{find_in_registry, install_unpacked, unpack, url_source, verify_checksum};
+
+    const MANIFEST: &str = r#"(
+        name: "chrome-importer",
+        author: "spyglass-search",
+        description: "Imports bookmarks",
+        version: "1",
+        trigger: "chrome",
+        plugin_type: Lens,
+        user_settings: {},
+    )"#;
+
+    fn archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        for (path, contents) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, path, *contents)
+                .expect("Unable to add file");
+        }
+
+        builder
+            .into_inner()
+            .and_then(|encoder| encoder.finish())
+            .expect("Unable to build archive")
+    }
+
+    fn temp_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("{}-{}", name, uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).expect("Unable to create temp dir");
+        dir
+    }
+
+    #[test]
+    fn test_verify_checksum() {
+        let sha256 = hex::encode(Sha256::digest(b"plugin"));
+        assert!(verify_checksum(b"plugin", &sha256).is_ok());
+        assert!(verify_checksum(b"plugin", &sha256.to_uppercase()).is_ok());
+        assert!(verify_checksum(b"tampered", &sha256).is_err());
+    }
+
+    #[test]
+    fn test_find_in_registry() {
+        let index = r#"[(
+            name: "chrome-importer",
+            author: "spyglass-search",
+            description: "Imports bookmarks",
+            version: "1",
+            download_url: "https://example.com/chrome-importer.tar.gz",
+            sha256: "abc123",
+        )]"#;
+
+        let source = find_in_registry(index, "chrome-importer").expect("found");
+        assert_eq!(
+            source.download_url.as_str(),
+            "https://example.com/chrome-importer.tar.gz"
+        );
+        assert_eq!(source.sha256, "abc123");
+        assert!(find_in_registry(index, "firefox-importer").is_err());
+    }
+
+    #[test]
+    fn test_url_source() {
+        let url = "https://example.com/chrome-importer.tar.gz";
+        let sha256 = hex::encode(Sha256::digest(b"plugin"));
+
+        let source = url_source(url, Some(&sha256.to_uppercase())).expect("valid");
+        assert_eq!(source.download_url.as_str(), url);
+        assert_eq!(source.sha256, sha256);
+
+        assert!(url_source(url, None).is_err());
+        assert!(url_source(url, Some("abc123")).is_err());
+        assert!(url_source("http://example.com/chrome-importer.tar.gz", Some(&sha256)).is_err());
+    }
+
+    #[test]
+    fn test_install_unpacked() {
+        let plugins_dir = temp_dir("plugins");
+        let staging = temp_dir("staging");
+
+        // Data from an older version is kept
+        let old_data = plugins_dir.join("chrome-importer").join("data");
+        std::fs::create_dir_all(&old_data).unwrap();
+        std::fs::write(old_data.join("state.json"), "{}").unwrap();
+
+        let bytes = archive(&[
+            ("chrome-importer/manifest.ron", MANIFEST.as_bytes()),
+            ("chrome-importer/main.wasm", b"\0asm"),
+        ]);
+        unpack(&bytes, &staging).expect("unpacked");
+        let plugin = install_unpacked(&staging, &plugins_dir).expect("installed");
+
+        let dest = plugins_dir.join("chrome-importer");
+        assert_eq!(plugin.name, "chrome-importer");
+        assert_eq!(plugin.path, Some(dest.join("main.wasm")));
+        assert!(dest.join("main.wasm").is_file());
+        assert!(dest.join("data").join("state.json").is_file());
+
+        let _ = std::fs::remove_dir_all(&plugins_dir);
+        let _ = std::fs::remove_dir_all(&staging);
+    }
+
+    #[test]
+    fn test_unpack_rejects_escapes() {
+        let staging = temp_dir("staging");
+        let bytes = archive(&[("manifest.ron", MANIFEST.as_bytes())]);
+        assert!(unpack(&bytes, &staging).is_ok());
+        // No wasm to run
+        assert!(install_unpacked(&staging, Path::new("/nonexistent")).is_err());
+        let _ = std::fs::remove_dir_all(&staging);
+
+        // tar::Builder refuses to write `..` paths, so patch the raw header.
+        let mut header = tar::Header::new_gnu();
+        header.set_size(4);
+        header.set_mode(0o644);
+        header.as_old_mut().name[..11].copy_from_slice(b"../evil.txt");
+        header.set_cksum();
+        let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+        builder.append(&header, &b"evil"[..]).unwrap();
+        let bytes = builder.into_inner().unwrap().finish().unwrap();
+
+        let staging = temp_dir("staging");
+        assert!(unpack(&bytes, &staging).is_err());
+        let _ = std::fs::remove_dir_all(&staging);
+    }
+}
@@ -0,0 +1,88 @@
+use std::collections::VecDeque;
+
+use shared::response::{PluginLogEntry, PluginLogSource};
+
+use crate::state::AppState;
+
+/// Lines kept per plugin, older lines are dropped.
+pub const MAX_LOG_LINES: usize = 500;
+/// Longest line kept, the rest is cut off.
+const MAX_LINE_LENGTH: usize = 2_000;
+
+/// Recent output of a plugin, kept so users can see why it isn't working w/o
+/// digging through the app logs.
+#[derive(Debug, Default)]
+pub struct PluginLogs {
+    lines: VecDeque<PluginLogEntry>,
+}
+
+impl PluginLogs {
+    pub fn push(&mut self, source: PluginLogSource, message: &str) {
+        let timestamp = chrono::Utc::now().to_rfc3339();
+        for line in message.lines().filter(|line| !line.trim().is_empty()) {
+            let mut line = line.trim_end().to_string();
+            if line.len() > MAX_LINE_LENGTH {
+                let mut end = MAX_LINE_LENGTH;
+                while !line.is_char_boundary(end) {
+                    end -= 1;
+                }
+                line.truncate(end);
+            }
+
+            if self.lines.len() >= MAX_LOG_LINES {
+                self.lines.pop_front();
+            }
+            self.lines.push_back(PluginLogEntry {
+                timestamp: timestamp.clone(),
+                source,
+                message: line,
+            });
+        }
+    }
+
+    /// The last `num` lines, oldest first.
+    pub fn tail(&self, num: usize) -> Vec<PluginLogEntry> {
+        let skip = self.lines.len().saturating_sub(num);
+        self.lines.iter().skip(skip).cloned().collect()
+    }
+}
+
+/// Keep `message` in the logs of the plugin `name`.
+pub fn record(state: &AppState, name: &str, source: PluginLogSource, message: &str) {
+    state
+        .plugin_logs
+        .entry(name.to_string())
+        .or_default()
+        .push(source, message);
+}
+
+#[cfg(test)]
+mod test {
+    use shared::response::PluginLogSource;
+
+    use super::{PluginLogs, MAX_LOG_LINES};
+
+    #[test]
+    fn test_plugin_logs() {
+        let mut logs = PluginLogs::default();
+        logs.push(PluginLogSource::Log, "syncing 2 folders");
+        logs.push(
+            PluginLogSource::Stderr,
+            "panicked at 'oops'\n\nnote: run w/ backtrace",
+        );
+
+        let tail = logs.tail(10);
+        assert_eq!(tail.len(), 3);
+        assert_eq!(tail[0].message, "syncing 2 folders");
+        assert_eq!(tail[2].source, PluginLogSource::Stderr);
+        assert_eq!(tail[2].message, "note: run w/ backtrace");
+        assert_eq!(logs.tail(1)[0].message, "note: run w/ backtrace");
+
+        for idx in 0..MAX_LOG_LINES {
+            logs.push(PluginLogSource::Stdout, &format!("line {}", idx));
+        }
+        let tail = logs.tail(MAX_LOG_LINES + 10);
+        assert_eq!(tail.len(), MAX_LOG_LINES);
+        assert_eq!(tail[0].message, "line 0");
+    }
+}
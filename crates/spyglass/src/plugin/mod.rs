@@ -5,27 +5,49 @@ use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
 use dashmap::DashMap;
+use entities::sea_orm::sea_query::Expr;
 use entities::sea_orm::{ColumnTrait, EntityTrait, QueryFilter};
 use ignore::WalkBuilder;
 use notify::{event::ModifyKind, EventKind, RecursiveMode, Watcher};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
-use spyglass_plugin::SearchFilter;
-use tokio::sync::mpsc;
+use spyglass_plugin::{ParseRequest, ParsedDocument, SearchFilter};
+use tokio::sync::{mpsc, OwnedMutexGuard, Semaphore};
 use tokio::task::JoinHandle;
 use wasmer::{Instance, Module, Store, WasmerEnv};
 use wasmer_wasi::{Pipe, WasiEnv, WasiState};
 
 use entities::models::lens;
 use shared::config::{Config, LensConfig};
-use shared::plugin::{PluginConfig, PluginType};
+use shared::plugin::{PluginConfig, PluginPermissions, PluginType};
+use shared::response::PluginLogSource;
 use spyglass_plugin::{consts::env, PluginEvent, PluginSubscription};
 
 use crate::state::AppState;
 
+mod broker;
 mod exports;
+mod http;
+pub mod install;
+pub mod logs;
+mod native;
+mod permissions;
+mod reload;
+mod replay;
+mod scan;
+mod watchdog;
+
+use native::{NativeHost, NativePlugin};
+use replay::MissedEvents;
+use watchdog::{Verdict, Watchdog};
 
 type PluginId = usize;
+/// Max number of plugins running an update at the same time.
+const MAX_CONCURRENT_UPDATES: usize = 4;
+/// Largest document handed to a parser plugin, it's sent over the plugin's
+/// stdin.
+pub const MAX_PARSE_BYTES: usize = 20_000_000;
+
 #[derive(Debug)]
 pub enum PluginCommand {
     DisablePlugin(String),
@@ -41,6 +63,10 @@ pub enum PluginCommand {
     QueueIntervalCheck,
     // Queue up file change notifications for subs
     QueueFileNotify(notify::Event),
+    // Queue up reloads for plugins whose WASM changed
+    QueuePluginReload(notify::Event),
+    // Re-initialize the plugin loaded from this WASM file
+    ReloadPlugin(PathBuf),
 }
 
 /// Plugin context whenever we get a call from the one of the plugins
@@ -58,26 +84,56 @@ pub(crate) struct PluginEnv {
     wasi_env: WasiEnv,
     /// host specific requests
     cmd_writer: mpsc::Sender<PluginCommand>,
+    /// Connections & scopes the plugin may request access tokens for
+    connections: HashMap<String, Vec<String>>,
+    /// Domains the plugin may make HTTP requests to, if the user approves
+    allowed_domains: Vec<String>,
+    /// Declared permissions the user approved
+    permissions: PluginPermissions,
+    /// Folders the plugin may access, expanded from its filesystem permissions
+    allowed_paths: Vec<PathBuf>,
+}
+
+#[derive(Clone)]
+pub enum PluginBackend {
+    Wasm {
+        instance: Instance,
+        env: WasiEnv,
+    },
+    /// First-party plugins that run in the host, see `native`.
+    Native {
+        plugin: Arc<Mutex<Box<dyn NativePlugin>>>,
+        host: NativeHost,
+    },
 }
 
 #[derive(Clone)]
 pub struct PluginInstance {
     pub id: PluginId,
     pub config: PluginConfig,
-    pub instance: Instance,
-    pub env: WasiEnv,
+    pub backend: PluginBackend,
+    /// Set when a call into the plugin fails, events are queued up until
+    /// the plugin is re-initialized.
+    pub crashed: bool,
+    /// Held while calling into the plugin, calls share its stdin/stdout so
+    /// they can't overlap.
+    pub busy: Arc<tokio::sync::Mutex<()>>,
 }
 
 impl PluginInstance {
     pub async fn search_filters(&self) -> Vec<SearchFilter> {
-        if let Err(e) =
-            PluginManager::call_plugin_func(self.instance.clone(), "search_filter").await
-        {
-            log::error!("search_filters: {}", e);
-            return Vec::new();
-        }
+        let (instance, env) = match &self.backend {
+            PluginBackend::Wasm { instance, env } => (instance, env),
+            PluginBackend::Native { plugin, .. } => {
+                return plugin
+                    .lock()
+                    .map(|plugin| plugin.search_filters())
+                    .unwrap_or_default()
+            }
+        };
 
-        match wasi_read::<Vec<SearchFilter>>(&self.env) {
+        let busy = self.busy.clone().lock_owned().await;
+        match call_and_read::<Vec<SearchFilter>>(instance, env, busy, "search_filter").await {
             Ok(res) => res,
             Err(e) => {
                 log::error!(
@@ -90,29 +146,78 @@ impl PluginInstance {
         }
     }
 
-    pub fn update(&mut self, event: PluginEvent) {
-        if !self.config.is_enabled {
-            return;
+    /// Have a Parser plugin pull the text out of a document.
+    pub async fn parse_document(&self, request: &ParseRequest) -> anyhow::Result<ParsedDocument> {
+        let (instance, env) = match &self.backend {
+            PluginBackend::Wasm { instance, env } => (instance, env),
+            PluginBackend::Native { .. } => {
+                return Err(anyhow::anyhow!(
+                    "<{}> doesn't parse documents",
+                    self.config.name
+                ))
+            }
+        };
+
+        if request.content.len() > MAX_PARSE_BYTES {
+            return Err(anyhow::anyhow!(
+                "{} is too large to parse ({} bytes)",
+                request.url,
+                request.content.len()
+            ));
         }
 
-        if let Ok(func) = self.instance.exports.get_function("update") {
-            match wasi_write(&self.env, &event) {
-                Err(e) => {
-                    log::error!("unable to request update from plugin: {}", e)
-                }
-                Ok(_) => {
-                    if let Err(e) = func.call(&[]) {
-                        log::error!("update failed: {}", e);
-                    }
+        let busy = self.busy.clone().lock_owned().await;
+        wasi_write(env, request)?;
+        call_and_read::<Result<ParsedDocument, String>>(instance, env, busy, "parse_document")
+            .await?
+            .map_err(anyhow::Error::msg)
+    }
+
+    /// Whether events can be sent to the plugin right now.
+    pub fn is_running(&self) -> bool {
+        self.config.is_enabled && !self.crashed
+    }
+
+    /// Blocks until the plugin is done w/ the event, run this off of the
+    /// async runtime.
+    pub fn update(&mut self, state: &AppState, event: PluginEvent) -> anyhow::Result<()> {
+        let _busy = self.busy.blocking_lock();
+        match &self.backend {
+            PluginBackend::Wasm { instance, env } => {
+                if let Ok(func) = instance.exports.get_function("update") {
+                    wasi_write(env, &event)?;
+                    let res = func.call(&[]);
+                    // Panics are printed to stderr before the plugin traps
+                    capture_output(state, &self.config.name, env);
+                    res?;
                 }
             }
+            // Treat panics like a trapped WASM plugin rather than taking the
+            // event loop down w/ it.
+            PluginBackend::Native { plugin, host } => {
+                let mut plugin = plugin
+                    .lock()
+                    .map_err(|_| anyhow::anyhow!("plugin poisoned by an earlier panic"))?;
+                std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                    plugin.update(host, event)
+                }))
+                .map_err(|_| anyhow::anyhow!("plugin panicked"))?;
+            }
         }
+
+        Ok(())
     }
 }
 
 pub struct PluginManager {
     check_update_subs: HashSet<PluginId>,
     plugins: DashMap<PluginId, PluginInstance>,
+    /// Events for disabled/crashed plugins, by plugin name.
+    missed_events: HashMap<String, MissedEvents>,
+    /// Queues feeding each plugin's updates, see `dispatch_updates`.
+    dispatchers: HashMap<PluginId, mpsc::UnboundedSender<PluginEvent>>,
+    update_limit: Arc<Semaphore>,
+    watchdog: Watchdog,
 }
 
 impl Default for PluginManager {
@@ -122,28 +227,26 @@ impl Default for PluginManager {
 }
 
 impl PluginManager {
+    /// Call `func_name` in the plugin, giving up after `watchdog::CALL_TIMEOUT`.
+    /// WASM can't be interrupted, so a hung call keeps its thread until the
+    /// plugin returns.
     pub async fn call_plugin_func(instance: Instance, func_name: &str) -> anyhow::Result<()> {
-        let exports = instance.exports.clone();
         let func = func_name.to_owned();
-        // Wrap this bad boy in something we can send across threads.
-        let async_exports = Arc::new(Mutex::new(exports));
-        // Spawn a thread so that plugins don't hold up the main thread.
-        let handle: JoinHandle<Result<(), anyhow::Error>> = tokio::spawn(async move {
-            if let Ok(exports) = async_exports.lock() {
-                let func = exports.get_function(&func)?;
-                func.call(&[])?;
-            }
-
+        call_with_timeout(func_name, move || {
+            instance.exports.get_function(&func)?.call(&[])?;
             Ok(())
-        });
-        let _ = handle.await?;
-        Ok(())
+        })
+        .await
     }
 
     pub fn new() -> Self {
         PluginManager {
             check_update_subs: Default::default(),
             plugins: Default::default(),
+            missed_events: Default::default(),
+            dispatchers: Default::default(),
+            update_limit: Arc::new(Semaphore::new(MAX_CONCURRENT_UPDATES)),
+            watchdog: Default::default(),
         }
     }
 
@@ -156,6 +259,208 @@ impl PluginManager {
 
         None
     }
+
+    /// Running Parser plugin for documents w/ this `extension` or `mime_type`.
+    pub fn find_parser(
+        &self,
+        extension: Option<&str>,
+        mime_type: Option<&str>,
+    ) -> Option<PluginInstance> {
+        self.plugins
+            .iter()
+            .find(|plugin| {
+                plugin.config.plugin_type == PluginType::Parser
+                    && plugin.is_running()
+                    && plugin.config.parser_formats.handles(extension, mime_type)
+            })
+            .map(|plugin| plugin.value().clone())
+    }
+
+    /// Queue an event for the plugin. Plugins handle their events in order,
+    /// but independently of each other.
+    fn dispatch(&mut self, state: &AppState, plugin_id: PluginId, event: PluginEvent) {
+        let update_limit = self.update_limit.clone();
+        let dispatcher = self.dispatchers.entry(plugin_id).or_insert_with(|| {
+            let (tx, rx) = mpsc::unbounded_channel();
+            tokio::spawn(dispatch_updates(state.clone(), plugin_id, rx, update_limit));
+            tx
+        });
+
+        if let Err(err) = dispatcher.send(event) {
+            log::error!("Unable to dispatch to plugin id {}: {}", plugin_id, err);
+            self.dispatchers.remove(&plugin_id);
+        }
+    }
+
+    fn queue_missed(&mut self, name: &str, event: PluginEvent) {
+        self.missed_events
+            .entry(name.to_string())
+            .or_default()
+            .push(event);
+    }
+
+    /// Send a freshly (re-)initialized plugin everything it missed while it
+    /// was disabled or crashed.
+    fn replay_missed(&mut self, state: &AppState, plugin_id: PluginId, name: &str) {
+        let missed = match self.missed_events.remove(name) {
+            Some(missed) if !missed.is_empty() => missed,
+            _ => return,
+        };
+
+        if missed.dropped() > 0 {
+            log::warn!(
+                "<{}> missed too many file changes, {} weren't kept",
+                name,
+                missed.dropped()
+            );
+        }
+
+        log::info!("replaying {} missed events for <{}>", missed.len(), name);
+        for event in missed.into_events() {
+            self.dispatch(state, plugin_id, event);
+        }
+    }
+}
+
+/// Runs a plugin's updates one at a time. The plugin manager isn't locked
+/// during the update itself, so a slow plugin only holds up its own events.
+async fn dispatch_updates(
+    state: AppState,
+    plugin_id: PluginId,
+    mut events: mpsc::UnboundedReceiver<PluginEvent>,
+    update_limit: Arc<Semaphore>,
+) {
+    while let Some(event) = events.recv().await {
+        let plugin = {
+            let manager = state.plugin_manager.lock().await;
+            manager
+                .plugins
+                .get(&plugin_id)
+                .map(|plugin| plugin.value().clone())
+        };
+
+        let mut plugin = match plugin {
+            Some(plugin) => plugin,
+            None => {
+                log::error!("Unable to find plugin id: {}", plugin_id);
+                continue;
+            }
+        };
+
+        let name = plugin.config.name.clone();
+        if !plugin.is_running() {
+            state.plugin_manager.lock().await.queue_missed(&name, event);
+            continue;
+        }
+
+        let permit = match update_limit.clone().acquire_owned().await {
+            Ok(permit) => permit,
+            // Only closed on shutdown
+            Err(_) => return,
+        };
+        let update = {
+            let event = event.clone();
+            let state = state.clone();
+            tokio::task::spawn_blocking(move || plugin.update(&state, event))
+        };
+        // Hung plugins don't get to hold on to a permit.
+        let res = tokio::time::timeout(watchdog::CALL_TIMEOUT, update).await;
+        drop(permit);
+
+        let res = match res {
+            Ok(res) => res.map(|res| res.map_err(|err| (err, event))),
+            Err(_) => {
+                handle_timeout(&state, plugin_id, &name, event).await;
+                continue;
+            }
+        };
+
+        match res {
+            Ok(Ok(_)) => state.plugin_manager.lock().await.watchdog.responded(&name),
+            Ok(Err((err, event))) => {
+                log::error!(
+                    "<{}> update failed, queuing events until it's restarted: {}",
+                    name,
+                    err
+                );
+                logs::record(
+                    &state,
+                    &name,
+                    PluginLogSource::Host,
+                    &format!("update failed: {}", err),
+                );
+                let mut manager = state.plugin_manager.lock().await;
+                if let Some(mut plugin) = manager.plugins.get_mut(&plugin_id) {
+                    plugin.crashed = true;
+                }
+                manager.queue_missed(&name, event);
+            }
+            Err(err) => log::error!("<{}> update task failed: {}", name, err),
+        }
+    }
+}
+
+/// Give up on a plugin update that's taking too long. The plugin is restarted
+/// w/ a fresh instance, or disabled if it keeps hanging, & the event is
+/// replayed once it's running again.
+async fn handle_timeout(state: &AppState, plugin_id: PluginId, name: &str, event: PluginEvent) {
+    let (verdict, config) = {
+        let mut manager = state.plugin_manager.lock().await;
+        manager.queue_missed(name, event);
+        let verdict = manager.watchdog.timed_out(name);
+        let config = manager.plugins.get_mut(&plugin_id).map(|mut plugin| {
+            plugin.crashed = true;
+            if verdict == Verdict::Disable {
+                plugin.config.is_enabled = false;
+            }
+            plugin.config.clone()
+        });
+
+        (verdict, config)
+    };
+
+    let config = match config {
+        Some(config) => config,
+        None => return,
+    };
+
+    logs::record(
+        state,
+        name,
+        PluginLogSource::Host,
+        &format!(
+            "update timed out after {}s",
+            watchdog::CALL_TIMEOUT.as_secs()
+        ),
+    );
+    let cmd = match verdict {
+        Verdict::Restart => {
+            log::warn!(
+                "<{}> update timed out after {}s, restarting",
+                name,
+                watchdog::CALL_TIMEOUT.as_secs()
+            );
+            PluginCommand::Initialize(config)
+        }
+        Verdict::Disable => {
+            log::error!(
+                "<{}> timed out {} times in a row, disabling",
+                name,
+                watchdog::MAX_CONSECUTIVE_TIMEOUTS
+            );
+            // Keep it off across restarts, the user can enable it again.
+            let _ = lens::Entity::update_many()
+                .col_expr(lens::Column::IsEnabled, Expr::value(false))
+                .filter(lens::Column::Name.eq(name))
+                .exec(&state.db)
+                .await;
+            PluginCommand::DisablePlugin(name.to_string())
+        }
+    };
+
+    if let Some(cmd_tx) = state.plugin_cmd_tx.lock().await.as_ref() {
+        let _ = cmd_tx.send(cmd).await;
+    }
 }
 
 /// Manages plugin events
@@ -184,6 +489,27 @@ pub async fn plugin_event_loop(
     })
     .expect("Unable to watch lens directory");
     let mut file_watch_subs: HashMap<PluginId, PathBuf> = HashMap::new();
+    // Directories we've caught up on since starting.
+    let mut scanned_dirs: HashSet<PathBuf> = HashSet::new();
+
+    // Reload plugins when their WASM is rebuilt, so plugins can be developed
+    // w/o restarting.
+    let (plugin_tx, mut plugin_events) = tokio::sync::mpsc::channel(1);
+    let mut plugin_watcher = notify::recommended_watcher(move |res| {
+        futures::executor::block_on(async {
+            if !plugin_tx.is_closed() {
+                if let Err(err) = plugin_tx.send(res).await {
+                    log::error!("plugin watcher error: {}", err.to_string());
+                }
+            }
+        })
+    })
+    .expect("Unable to watch plugins directory");
+    if let Err(err) = plugin_watcher.watch(&config.plugins_dir(), RecursiveMode::Recursive) {
+        log::error!("Unable to watch plugins directory: {}", err);
+    }
+    // WASM files waiting to be reloaded, see `reload::RELOAD_DELAY`.
+    let mut pending_reloads: HashSet<PathBuf> = HashSet::new();
 
     // Subscribe plugins check for updates every 10 minutes
     let mut interval = tokio::time::interval(Duration::from_secs(10 * 60));
@@ -202,12 +528,21 @@ pub async fn plugin_event_loop(
                     None
                 }
             },
+            // Listen for rebuilt plugins
+            plugin_event = plugin_events.recv() => {
+                if let Some(Ok(plugin_event)) = plugin_event {
+                    Some(PluginCommand::QueuePluginReload(plugin_event))
+                } else {
+                    None
+                }
+            },
             // Handle interval checks
             _ = interval.tick() => Some(PluginCommand::QueueIntervalCheck),
             // SHUT IT DOWN
             _ = shutdown_rx.recv() => {
                 log::info!("🛑 Shutting down plugin manager");
                 file_events.close();
+                plugin_events.close();
                 cmd_queue.close();
                 return;
             }
@@ -217,18 +552,14 @@ pub async fn plugin_event_loop(
             Some(PluginCommand::DisablePlugin(plugin_name)) => {
                 log::info!("disabling plugin <{}>", plugin_name);
 
-                let mut disabled = Vec::new();
-                let mut manager = state.plugin_manager.lock().await;
+                // Subscriptions are kept around so that events are queued up
+                // while disabled & replayed when the plugin is enabled again.
+                let manager = state.plugin_manager.lock().await;
                 if let Some(plugin) = manager.find_by_name(plugin_name) {
                     if let Some(mut instance) = manager.plugins.get_mut(&plugin.id) {
                         instance.config.is_enabled = false;
-                        disabled.push(plugin.id);
                     }
                 }
-
-                disabled.iter().for_each(|pid| {
-                    manager.check_update_subs.remove(pid);
-                })
             }
             Some(PluginCommand::EnablePlugin(plugin_name)) => {
                 log::info!("enabling plugin <{}>", plugin_name);
@@ -244,29 +575,49 @@ pub async fn plugin_event_loop(
                 }
             }
             Some(PluginCommand::HandleUpdate { plugin_id, event }) => {
-                let manager = state.plugin_manager.lock().await;
-                if let Some(mut plugin) = manager.plugins.get_mut(&plugin_id) {
-                    plugin.update(event);
-                } else {
-                    log::error!("Unable to find plugin id: {}", plugin_id);
-                };
+                let mut manager = state.plugin_manager.lock().await;
+                manager.dispatch(&state, plugin_id, event);
             }
             Some(PluginCommand::Initialize(plugin)) => {
-                let manager = state.plugin_manager.lock().await;
-                let plugin_id = manager.plugins.len();
-                match plugin_init(plugin_id, &state, &cmd_writer, &plugin).await {
-                    Ok((instance, env)) => {
+                let mut manager = state.plugin_manager.lock().await;
+                // Re-initialized plugins keep their id so existing subscriptions
+                // & scheduled tasks still reach them.
+                let plugin_id = manager
+                    .find_by_name(plugin.name.clone())
+                    .map_or(manager.plugins.len(), |existing| existing.id);
+                let backend = match native::for_plugin(&plugin) {
+                    Some(native) => native_init(plugin_id, &state, &cmd_writer, &plugin, native),
+                    None => plugin_init(plugin_id, &state, &cmd_writer, &plugin)
+                        .await
+                        .map(|(instance, env)| PluginBackend::Wasm { instance, env }),
+                };
+
+                match backend {
+                    Ok(backend) => {
                         manager.plugins.insert(
                             plugin_id,
                             PluginInstance {
                                 id: plugin_id,
                                 config: plugin.clone(),
-                                instance: instance.clone(),
-                                env: env.clone(),
+                                backend,
+                                crashed: false,
+                                busy: Default::default(),
                             },
                         );
+
+                        if plugin.is_enabled {
+                            manager.replay_missed(&state, plugin_id, &plugin.name);
+                        }
+                    }
+                    Err(e) => {
+                        log::error!("Unable to init plugin <{}>: {}", plugin.name, e);
+                        logs::record(
+                            &state,
+                            &plugin.name,
+                            PluginLogSource::Host,
+                            &format!("unable to start: {}", e),
+                        );
                     }
-                    Err(e) => log::error!("Unable to init plugin <{}>: {}", plugin.name, e),
                 }
             }
             Some(PluginCommand::Subscribe(plugin_id, event)) => match event {
@@ -294,6 +645,17 @@ pub async fn plugin_event_loop(
                             },
                         );
 
+                        // Catch up on changes made while we weren't running
+                        if scanned_dirs.insert(path.clone()) {
+                            tokio::spawn(queue_startup_scan(
+                                state.clone(),
+                                cmd_writer.clone(),
+                                plugin_id,
+                                path.clone(),
+                                recurse,
+                            ));
+                        }
+
                         file_watch_subs.insert(plugin_id, path);
                     }
                 }
@@ -373,6 +735,49 @@ pub async fn plugin_event_loop(
                     }
                 }
             }
+            Some(PluginCommand::QueuePluginReload(plugin_event)) => {
+                for path in reload::changed_wasm(&plugin_event) {
+                    // Already waiting for the write to finish
+                    if !pending_reloads.insert(path.clone()) {
+                        continue;
+                    }
+
+                    let cmd_writer = cmd_writer.clone();
+                    tokio::spawn(async move {
+                        tokio::time::sleep(reload::RELOAD_DELAY).await;
+                        let _ = cmd_writer.send(PluginCommand::ReloadPlugin(path)).await;
+                    });
+                }
+            }
+            Some(PluginCommand::ReloadPlugin(path)) => {
+                pending_reloads.remove(&path);
+                let plugin = {
+                    let mut manager = state.plugin_manager.lock().await;
+                    let plugin = manager
+                        .plugins
+                        .iter()
+                        .find(|plugin| {
+                            plugin.config.path.as_ref() == Some(&path)
+                                && matches!(plugin.backend, PluginBackend::Wasm { .. })
+                        })
+                        .map(|plugin| plugin.value().clone());
+
+                    // The plugin subscribes again when it starts up.
+                    if let Some(plugin) = &plugin {
+                        manager.check_update_subs.remove(&plugin.id);
+                    }
+                    plugin
+                };
+
+                if let Some(plugin) = plugin {
+                    log::info!("<{}> changed, reloading", plugin.config.name);
+                    file_watch_subs.remove(&plugin.id);
+                    // Replaces the old instance, keeping the plugin's id.
+                    let _ = cmd_writer
+                        .send(PluginCommand::Initialize(plugin.config))
+                        .await;
+                }
+            }
             None => {}
         }
 
@@ -381,6 +786,39 @@ pub async fn plugin_event_loop(
     }
 }
 
+/// Send a plugin the changes made to a watched directory since it was last
+/// indexed. Runs outside of the event loop since the walk can take a while.
+async fn queue_startup_scan(
+    state: AppState,
+    cmd_writer: mpsc::Sender<PluginCommand>,
+    plugin_id: PluginId,
+    path: PathBuf,
+    recurse: bool,
+) {
+    let events = match scan::changes_since_indexed(&state.db, &path, recurse).await {
+        Ok(events) => events,
+        Err(err) => {
+            log::error!("Unable to scan <{}>: {}", path.display(), err);
+            return;
+        }
+    };
+
+    log::info!(
+        "found {} changes in <{}> since last run",
+        events.len(),
+        path.display()
+    );
+    for event in events {
+        if cmd_writer
+            .send(PluginCommand::HandleUpdate { plugin_id, event })
+            .await
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
 // Loop through plugins found in the plugins directory, enabling
 pub async fn plugin_load(
     state: &AppState,
@@ -410,44 +848,107 @@ pub async fn plugin_load(
         config.user_settings.plugin_settings = user_plugin_settings.clone();
         let _ = config.save_user_settings(&config.user_settings);
 
-        // Enable plugins that are lenses, this is the only type right so technically they
-        // all will be enabled as a lens.
-        if plug.plugin_type == PluginType::Lens {
-            let plug = plug.clone();
-            let lens_config = LensConfig {
-                name: plug.name.clone(),
-                author: plug.author,
-                description: Some(plug.description.clone()),
-                trigger: plug.trigger.clone(),
-                ..Default::default()
-            };
-
-            match lens::add_or_enable(&state.db, &lens_config, lens::LensType::Plugin).await {
-                Ok(is_new) => {
-                    log::info!("loaded lens {}, new? {}", plug.name, is_new)
-                }
-                Err(e) => log::error!("Unable to add lens: {}", e),
+        plugin_register(state, plug, cmds).await;
+    }
+}
+
+/// Add the lens a plugin is enabled/disabled through & start it up.
+pub async fn plugin_register(
+    state: &AppState,
+    mut plug: PluginConfig,
+    cmds: &mpsc::Sender<PluginCommand>,
+) {
+    // Plugins are enabled & disabled through their lens, parsers get one
+    // too so they show up alongside the other plugins.
+    if matches!(plug.plugin_type, PluginType::Lens | PluginType::Parser) {
+        let plug = plug.clone();
+        let lens_config = LensConfig {
+            name: plug.name.clone(),
+            author: plug.author,
+            description: Some(plug.description.clone()),
+            trigger: plug.trigger.clone(),
+            ..Default::default()
+        };
+
+        match lens::add_or_enable(&state.db, &lens_config, lens::LensType::Plugin).await {
+            Ok(is_new) => {
+                log::info!("loaded lens {}, new? {}", plug.name, is_new)
             }
+            Err(e) => log::error!("Unable to add lens: {}", e),
         }
+    }
 
-        // Is this plugin enabled?
-        let lens_config = lens::Entity::find()
-            .filter(lens::Column::Name.eq(plug.name.clone()))
-            .one(&state.db)
-            .await;
+    // Is this plugin enabled?
+    let lens_config = lens::Entity::find()
+        .filter(lens::Column::Name.eq(plug.name.clone()))
+        .one(&state.db)
+        .await;
 
-        if let Ok(Some(lens_config)) = lens_config {
-            plug.is_enabled = lens_config.is_enabled;
-        }
+    if let Ok(Some(lens_config)) = lens_config {
+        plug.is_enabled = lens_config.is_enabled;
+    }
 
-        if cmds
-            .send(PluginCommand::Initialize(plug.clone()))
-            .await
-            .is_ok()
-        {
-            log::info!("<{}> plugin found", &plug.name);
-        }
+    if cmds
+        .send(PluginCommand::Initialize(plug.clone()))
+        .await
+        .is_ok()
+    {
+        log::info!("<{}> plugin found", &plug.name);
+    }
+}
+
+/// Set up a first-party plugin that runs in the host instead of as WASM.
+fn native_init(
+    plugin_id: PluginId,
+    state: &AppState,
+    cmd_writer: &mpsc::Sender<PluginCommand>,
+    plugin: &PluginConfig,
+    mut native: Box<dyn NativePlugin>,
+) -> anyhow::Result<PluginBackend> {
+    std::fs::create_dir_all(plugin.data_folder())?;
+
+    let host = NativeHost::new(plugin_id, state, cmd_writer, plugin);
+    if plugin.is_enabled {
+        log::info!("STARTING <{}> (native)", plugin.name);
+        native.load(&host);
     }
+
+    Ok(PluginBackend::Native {
+        plugin: Arc::new(Mutex::new(native)),
+        host,
+    })
+}
+
+/// Environment variables a plugin is started w/, the host folders & OS along
+/// w/ the plugin's user settings.
+fn plugin_env_vars(plugin: &PluginConfig) -> HashMap<String, String> {
+    // Detect base data dir and send that to the plugin
+    let base_dirs = directories::BaseDirs::new();
+    let base_config_dir = base_dirs
+        .as_ref()
+        .map_or_else(String::new, |base| base.config_dir().display().to_string());
+    let base_data_dir = base_dirs
+        .as_ref()
+        .map_or_else(String::new, |base| base.data_dir().display().to_string());
+    let home_dir = base_dirs
+        .as_ref()
+        .map_or_else(String::new, |base| base.home_dir().display().to_string());
+
+    let mut vars = HashMap::from([
+        (env::BASE_CONFIG_DIR.to_string(), base_config_dir),
+        (env::BASE_DATA_DIR.to_string(), base_data_dir),
+        (env::HOST_HOME_DIR.to_string(), home_dir),
+        (env::HOST_OS.to_string(), std::env::consts::OS.to_string()),
+    ]);
+    // Load user settings as environment variables
+    vars.extend(
+        plugin
+            .user_settings
+            .iter()
+            .map(|(name, opts)| (name.clone(), opts.value.clone())),
+    );
+
+    vars
 }
 
 pub async fn plugin_init(
@@ -473,39 +974,18 @@ pub async fn plugin_init(
 
     let store = Store::default();
     let module = Module::from_file(&store, path)?;
-    let user_settings = &plugin.user_settings;
-
-    // Detect base data dir and send that to the plugin
-    let base_config_dir = directories::BaseDirs::new()
-        .map(|base| base.config_dir().display().to_string())
-        .map_or_else(|| "".to_string(), |dir| dir);
-
-    let base_data_dir: String = directories::BaseDirs::new()
-        .map(|base| base.data_dir().display().to_string())
-        .map_or_else(|| "".to_string(), |dir| dir);
-
-    let home_dir: String = directories::BaseDirs::new()
-        .map(|base| base.home_dir().display().to_string())
-        .map_or_else(|| "".to_string(), |dir| dir);
 
     let mut wasi_env = WasiState::new(&plugin.name)
         // Attach the plugin data directory. Anything created by the plugin will live
         // there.
         .map_dir("/", plugin.data_folder())
         .expect("Unable to mount plugin data folder")
-        .env(env::BASE_CONFIG_DIR, base_config_dir)
-        .env(env::BASE_DATA_DIR, base_data_dir)
-        .env(env::HOST_HOME_DIR, home_dir)
-        .env(env::HOST_OS, std::env::consts::OS)
-        // Load user settings as environment variables
-        .envs(
-            user_settings
-                .iter()
-                .map(|(name, opts)| (name, opts.value.clone())),
-        )
+        .envs(plugin_env_vars(plugin))
         // Override stdin/out with pipes for comms
         .stdin(Box::new(input))
         .stdout(Box::new(output))
+        // Kept in the plugin's logs
+        .stderr(Box::new(Pipe::new()))
         .finalize()?;
 
     let mut import_object = wasi_env.import_object(&module)?;
@@ -521,12 +1001,56 @@ pub async fn plugin_init(
     // Lets call the `_start` function, which is our `main` function in Rust
     if plugin.is_enabled {
         log::info!("STARTING <{}>", plugin.name);
-        PluginManager::call_plugin_func(instance.clone(), "_start").await?;
+        let res = PluginManager::call_plugin_func(instance.clone(), "_start").await;
+        capture_output(state, &plugin.name, &wasi_env);
+        res?;
     }
 
     Ok((instance.clone(), wasi_env))
 }
 
+/// Run `call` on a blocking thread so plugins don't hold up the runtime,
+/// giving up after `watchdog::CALL_TIMEOUT`. WASM can't be interrupted, so a
+/// hung call keeps its thread, & anything moved into `call`, until it returns.
+async fn call_with_timeout<T, F>(func_name: &str, call: F) -> anyhow::Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> anyhow::Result<T> + Send + 'static,
+{
+    let handle: JoinHandle<anyhow::Result<T>> = tokio::task::spawn_blocking(call);
+    match tokio::time::timeout(watchdog::CALL_TIMEOUT, handle).await {
+        Ok(res) => res?,
+        Err(_) => Err(anyhow::anyhow!(
+            "{} timed out after {}s",
+            func_name,
+            watchdog::CALL_TIMEOUT.as_secs()
+        )),
+    }
+}
+
+/// Call `func_name` in the plugin & read back its response. The plugin's
+/// `busy` lock is only released once the call returns, even if it's given up
+/// on, so a hung call's output can't end up mixed in w/ the next call's.
+async fn call_and_read<T>(
+    instance: &Instance,
+    env: &WasiEnv,
+    busy: OwnedMutexGuard<()>,
+    func_name: &str,
+) -> anyhow::Result<T>
+where
+    T: DeserializeOwned + Send + 'static,
+{
+    let instance = instance.clone();
+    let env = env.clone();
+    let func = func_name.to_owned();
+    call_with_timeout(func_name, move || {
+        let _busy = busy;
+        instance.exports.get_function(&func)?.call(&[])?;
+        wasi_read::<T>(&env)
+    })
+    .await
+}
+
 // --------------------------------------------------------------------------------
 // Utility functions for wasi <> spyglass comms
 // --------------------------------------------------------------------------------
@@ -545,6 +1069,31 @@ fn wasi_read_string(wasi_env: &WasiEnv) -> anyhow::Result<String> {
     Ok(buf)
 }
 
+fn wasi_read_stderr(wasi_env: &WasiEnv) -> anyhow::Result<String> {
+    let mut state = wasi_env.state();
+    let stderr = state
+        .fs
+        .stderr_mut()?
+        .as_mut()
+        .ok_or_else(|| anyhow::Error::msg("Unable to unwrap stderr"))?;
+
+    let mut buf = String::new();
+    stderr.read_to_string(&mut buf)?;
+    Ok(buf)
+}
+
+/// Keep anything the plugin printed outside of its host calls in its logs.
+/// Only call this while holding the plugin's `busy` lock (or before it's
+/// running) so responses waiting to be read aren't swallowed.
+fn capture_output(state: &AppState, name: &str, env: &WasiEnv) {
+    if let Ok(stdout) = wasi_read_string(env) {
+        logs::record(state, name, PluginLogSource::Stdout, &stdout);
+    }
+    if let Ok(stderr) = wasi_read_stderr(env) {
+        logs::record(state, name, PluginLogSource::Stderr, &stderr);
+    }
+}
+
 fn wasi_write_string(env: &WasiEnv, buf: &str) -> anyhow::Result<()> {
     let mut state = env.state();
     let stdin = state
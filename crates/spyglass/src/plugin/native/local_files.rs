@@ -0,0 +1,171 @@
+/// Native version of the `local-file-indexer` plugin. Reads the same user
+/// settings & sync data so switching between the two doesn't recrawl.
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use shared::plugin::PluginConfig;
+use spyglass_plugin::{utils::path_to_uri, PluginEvent, PluginSubscription, SearchFilter};
+
+use super::{NativeHost, NativePlugin};
+
+pub const PLUGIN_NAME: &str = "local-file-importer";
+const PLUGIN_DATA: &str = "data.json";
+const FOLDERS_LIST: &str = "FOLDERS_LIST";
+const EXTS_LIST: &str = "EXTS_LIST";
+
+#[derive(Default, Deserialize, Serialize)]
+struct SyncData {
+    path_to_times: HashMap<PathBuf, DateTime<Utc>>,
+}
+
+pub struct LocalFileIndexer {
+    extensions: HashSet<String>,
+    folders: Vec<PathBuf>,
+}
+
+impl LocalFileIndexer {
+    pub fn new(config: &PluginConfig) -> Self {
+        let setting = |name: &str| {
+            config
+                .user_settings
+                .get(name)
+                .map(|opts| opts.value.clone())
+                .unwrap_or_default()
+        };
+
+        let extensions = serde_json::from_str::<HashSet<String>>(&setting(EXTS_LIST))
+            .unwrap_or_else(|_| HashSet::from(["md".to_string(), "txt".to_string()]));
+        let folders = serde_json::from_str::<Vec<String>>(&setting(FOLDERS_LIST))
+            .unwrap_or_default()
+            .into_iter()
+            .map(PathBuf::from)
+            .collect();
+
+        LocalFileIndexer {
+            extensions,
+            folders,
+        }
+    }
+
+    fn is_supported(&self, path: &Path) -> bool {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .map_or(false, |ext| self.extensions.contains(ext))
+    }
+}
+
+impl NativePlugin for LocalFileIndexer {
+    fn load(&mut self, host: &NativeHost) {
+        let data_path = host.data_dir().join(PLUGIN_DATA);
+        let mut last_synced = std::fs::read_to_string(&data_path)
+            .ok()
+            .and_then(|blob| serde_json::from_str::<SyncData>(&blob).ok())
+            .unwrap_or_default();
+
+        let now = Utc::now();
+        for path in &self.folders {
+            // Full walks are expensive, the file watcher picks up anything
+            // in between.
+            let last_processed_time = last_synced.path_to_times.entry(path.clone()).or_default();
+            if (now - *last_processed_time).num_days() > 1 {
+                if path.exists() {
+                    host.walk_and_enqueue(path.clone(), self.extensions.clone());
+                    *last_processed_time = now;
+                } else {
+                    log::warn!("Unable to process dir: {} does not exist", path.display());
+                }
+            }
+
+            host.subscribe(PluginSubscription::WatchDirectory {
+                path: path.clone(),
+                recurse: true,
+            });
+        }
+
+        match serde_json::to_string_pretty(&last_synced) {
+            Ok(blob) => {
+                if let Err(err) = std::fs::write(&data_path, blob) {
+                    log::error!("Unable to save {}: {}", data_path.display(), err);
+                }
+            }
+            Err(err) => log::error!("Unable to serialize sync data: {}", err),
+        }
+    }
+
+    fn update(&mut self, host: &NativeHost, event: PluginEvent) {
+        match event {
+            PluginEvent::FileCreated(path) | PluginEvent::FileUpdated(path) => {
+                if self.is_supported(&path) {
+                    host.enqueue(&[path_to_uri(path)]);
+                }
+            }
+            PluginEvent::FileDeleted(path) => host.delete_doc(&path_to_uri(path)),
+            _ => {}
+        }
+    }
+
+    fn search_filters(&self) -> Vec<SearchFilter> {
+        vec![SearchFilter::URLRegexAllow("file://.*".to_string())]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::path::{Path, PathBuf};
+
+    use shared::form::{FormType, SettingOpts};
+    use shared::plugin::{PluginConfig, PluginType};
+
+    use super::{LocalFileIndexer, EXTS_LIST, FOLDERS_LIST, PLUGIN_NAME};
+
+    fn config(settings: &[(&str, &str)]) -> PluginConfig {
+        PluginConfig {
+            name: PLUGIN_NAME.into(),
+            author: "spyglass".into(),
+            description: "".into(),
+            version: "1".into(),
+            trigger: "files".into(),
+            path: None,
+            plugin_type: PluginType::Lens,
+            user_settings: settings
+                .iter()
+                .map(|(name, value)| {
+                    (
+                        name.to_string(),
+                        SettingOpts {
+                            label: name.to_string(),
+                            value: value.to_string(),
+                            form_type: FormType::Text,
+                            help_text: None,
+                        },
+                    )
+                })
+                .collect(),
+            is_enabled: true,
+            connections: HashMap::new(),
+            allowed_domains: Vec::new(),
+            permissions: Default::default(),
+            parser_formats: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_settings() {
+        let indexer = LocalFileIndexer::new(&config(&[
+            (FOLDERS_LIST, r#"["/home/user/notes"]"#),
+            (EXTS_LIST, r#"["md", "org"]"#),
+        ]));
+        assert_eq!(indexer.folders, vec![PathBuf::from("/home/user/notes")]);
+        assert!(indexer.is_supported(Path::new("/home/user/notes/todo.org")));
+        assert!(!indexer.is_supported(Path::new("/home/user/notes/todo.txt")));
+
+        // Falls back to the defaults when unset
+        let indexer = LocalFileIndexer::new(&config(&[(FOLDERS_LIST, "")]));
+        assert!(indexer.folders.is_empty());
+        assert!(indexer.is_supported(Path::new("/home/user/notes/todo.txt")));
+    }
+}
@@ -0,0 +1,96 @@
+/// First-party plugins built into the host. These get the same events as WASM
+/// plugins but skip the round trips through the WASI pipes, which adds up on
+/// hot paths like indexing a large home directory.
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use tokio::sync::mpsc;
+
+use shared::plugin::PluginConfig;
+use spyglass_plugin::{PluginEvent, PluginSubscription, SearchFilter};
+
+use super::{exports, PluginCommand, PluginId};
+use crate::search::Searcher;
+use crate::state::AppState;
+
+mod local_files;
+
+/// Same lifecycle as `spyglass_plugin::SpyglassPlugin`.
+pub trait NativePlugin: Send {
+    fn load(&mut self, host: &NativeHost);
+    fn update(&mut self, host: &NativeHost, event: PluginEvent);
+    fn search_filters(&self) -> Vec<SearchFilter>;
+}
+
+/// Native replacement for a plugin, if there is one.
+pub fn for_plugin(config: &PluginConfig) -> Option<Box<dyn NativePlugin>> {
+    match config.name.as_str() {
+        local_files::PLUGIN_NAME => Some(Box::new(local_files::LocalFileIndexer::new(config))),
+        _ => None,
+    }
+}
+
+/// Host functions available to native plugins, the equivalent of the
+/// `PluginCommandRequest`s WASM plugins send.
+#[derive(Clone)]
+pub struct NativeHost {
+    id: PluginId,
+    name: String,
+    state: AppState,
+    cmd_writer: mpsc::Sender<PluginCommand>,
+    data_dir: PathBuf,
+}
+
+impl NativeHost {
+    pub fn new(
+        id: PluginId,
+        state: &AppState,
+        cmd_writer: &mpsc::Sender<PluginCommand>,
+        config: &PluginConfig,
+    ) -> Self {
+        NativeHost {
+            id,
+            name: config.name.clone(),
+            state: state.clone(),
+            cmd_writer: cmd_writer.clone(),
+            data_dir: config.data_folder(),
+        }
+    }
+
+    /// Where the plugin can keep its own data.
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    pub fn enqueue(&self, urls: &[String]) {
+        exports::handle_plugin_enqueue(&self.state, &self.name, urls);
+    }
+
+    pub fn delete_doc(&self, url: &str) {
+        let state = self.state.clone();
+        let url = url.to_string();
+        tokio::spawn(async move {
+            if let Err(err) = Searcher::delete_by_url(&state, &url).await {
+                log::error!("Unable to delete <{}>: {}", url, err);
+            }
+        });
+    }
+
+    /// Commands are sent from a separate task since plugins are loaded &
+    /// updated from within the plugin event loop.
+    pub fn subscribe(&self, subscription: PluginSubscription) {
+        let cmd_writer = self.cmd_writer.clone();
+        let cmd = PluginCommand::Subscribe(self.id, subscription);
+        tokio::spawn(async move {
+            let _ = cmd_writer.send(cmd).await;
+        });
+    }
+
+    pub fn walk_and_enqueue(&self, path: PathBuf, extensions: HashSet<String>) {
+        log::info!("{} crawling path: {}", self.name, path.display());
+        let state = self.state.clone();
+        tokio::spawn(async move {
+            exports::handle_walk_and_enqueue(&state, path, &extensions).await;
+        });
+    }
+}
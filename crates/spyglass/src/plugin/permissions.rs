@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+use shared::plugin::PluginPermissions;
+use spyglass_plugin::{PluginCommandRequest, PluginSubscription};
+
+/// Replace `$NAME` in the filesystem permissions w/ the matching entry in
+/// `vars`. Path list settings (JSON arrays) expand to one folder per entry &
+/// permissions w/ a missing or empty variable are dropped.
+pub fn expand_paths(entries: &[String], vars: &HashMap<String, String>) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    for entry in entries {
+        let (name, rest) = match entry.strip_prefix('$') {
+            Some(var) => var.split_once('/').unwrap_or((var, "")),
+            None => {
+                paths.push(PathBuf::from(entry));
+                continue;
+            }
+        };
+
+        let value = match vars.get(name) {
+            Some(value) if !value.trim().is_empty() => value,
+            _ => continue,
+        };
+
+        let values =
+            serde_json::from_str::<Vec<String>>(value).unwrap_or_else(|_| vec![value.to_string()]);
+        for value in values.iter().filter(|value| !value.trim().is_empty()) {
+            let base = PathBuf::from(value);
+            paths.push(if rest.is_empty() {
+                base
+            } else {
+                base.join(rest)
+            });
+        }
+    }
+
+    paths
+}
+
+/// Resolve `.` & `..` w/o touching the filesystem, the path may not exist.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+/// Resolve symlinks when the path exists, so they can't point outside of an
+/// allowed folder.
+fn resolve(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| normalize(path))
+}
+
+/// Make sure `path` is inside one of the `allowed` folders.
+pub fn check_path(allowed: &[PathBuf], path: &Path) -> Result<(), String> {
+    if !path.is_absolute() {
+        return Err(format!("{} is not an absolute path", path.display()));
+    }
+
+    let path = resolve(path);
+    if allowed
+        .iter()
+        .any(|folder| path.starts_with(resolve(folder)))
+    {
+        Ok(())
+    } else {
+        Err(format!("no filesystem permission for {}", path.display()))
+    }
+}
+
+/// Resolve `path` inside the plugin's data folder, rejecting anything that
+/// escapes it w/ `..`, symlinks or an absolute path.
+pub fn data_path(data_dir: &Path, path: &str) -> Result<PathBuf, String> {
+    let data_dir = resolve(data_dir);
+    let path = resolve(&data_dir.join(path));
+    check_path(&[data_dir], &path)?;
+    Ok(path)
+}
+
+/// Make sure a plugin has been given permission to make a host call.
+pub fn check(
+    cmd: &PluginCommandRequest,
+    permissions: &PluginPermissions,
+    allowed_paths: &[PathBuf],
+) -> Result<(), String> {
+    let require = |granted: bool, name: &str| {
+        if granted {
+            Ok(())
+        } else {
+            Err(format!("{} permission not granted", name))
+        }
+    };
+
+    match cmd {
+        PluginCommandRequest::DeleteDoc { .. } => require(permissions.delete_docs, "delete_docs"),
+        // Queries enqueue the URLs they find
+        PluginCommandRequest::Enqueue { .. } | PluginCommandRequest::SqliteQuery { .. } => {
+            require(permissions.enqueue, "enqueue")
+        }
+        PluginCommandRequest::Http(_) => require(permissions.network, "network"),
+        PluginCommandRequest::ListDir { path } => check_path(allowed_paths, Path::new(path)),
+        PluginCommandRequest::Subscribe(PluginSubscription::WatchDirectory { path, .. }) => {
+            check_path(allowed_paths, path)
+        }
+        PluginCommandRequest::SyncFile { src, .. } => check_path(allowed_paths, Path::new(src)),
+        PluginCommandRequest::WalkAndEnqueue { path, .. } => {
+            require(permissions.enqueue, "enqueue")?;
+            check_path(allowed_paths, path)
+        }
+        // Connection access is granted separately & the rest only touches the
+        // plugin itself.
+        PluginCommandRequest::AccessToken { .. }
+        | PluginCommandRequest::ReportProgress { .. }
+        | PluginCommandRequest::ScheduleTask { .. }
+        | PluginCommandRequest::Subscribe(_) => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::{HashMap, HashSet};
+    use std::path::{Path, PathBuf};
+
+    use shared::plugin::PluginPermissions;
+    use spyglass_plugin::PluginCommandRequest;
+
+    use super::{check, check_path, data_path, expand_paths};
+
+    #[test]
+    fn test_expand_paths() {
+        let vars = HashMap::from([
+            ("HOME_DIR".to_string(), "/home/alice".to_string()),
+            (
+                "FOLDERS_LIST".to_string(),
+                r#"["/home/alice/notes", "/mnt/docs"]"#.to_string(),
+            ),
+            ("FIREFOX_DATA_FOLDER".to_string(), "".to_string()),
+        ]);
+        let entries = vec![
+            "$HOME_DIR/.mozilla/firefox".to_string(),
+            "$FOLDERS_LIST".to_string(),
+            "$FIREFOX_DATA_FOLDER".to_string(),
+            "$CHROME_DATA_FOLDER".to_string(),
+            "/tmp/exports".to_string(),
+        ];
+
+        assert_eq!(
+            expand_paths(&entries, &vars),
+            vec![
+                PathBuf::from("/home/alice/.mozilla/firefox"),
+                PathBuf::from("/home/alice/notes"),
+                PathBuf::from("/mnt/docs"),
+                PathBuf::from("/tmp/exports"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_path() {
+        let allowed = vec![PathBuf::from("/home/alice/notes")];
+        assert!(check_path(&allowed, Path::new("/home/alice/notes")).is_ok());
+        assert!(check_path(&allowed, Path::new("/home/alice/notes/todo.md")).is_ok());
+        assert!(check_path(&allowed, Path::new("/home/alice/notes-old")).is_err());
+        assert!(check_path(&allowed, Path::new("/home/alice/notes/../.ssh/id_rsa")).is_err());
+        assert!(check_path(&allowed, Path::new("notes/todo.md")).is_err());
+    }
+
+    #[test]
+    fn test_data_path() {
+        let data_dir = Path::new("/home/alice/.spyglass/plugins/firefox-importer/data");
+        assert_eq!(
+            data_path(data_dir, "places.sqlite"),
+            Ok(data_dir.join("places.sqlite"))
+        );
+        assert!(data_path(data_dir, "../../../../.ssh/id_rsa").is_err());
+        assert!(data_path(data_dir, "/etc/passwd").is_err());
+    }
+
+    #[test]
+    fn test_check() {
+        let permissions = PluginPermissions {
+            enqueue: true,
+            ..Default::default()
+        };
+        let allowed = vec![PathBuf::from("/home/alice/notes")];
+
+        let enqueue = PluginCommandRequest::Enqueue {
+            urls: vec!["https://example.com".into()],
+        };
+        assert!(check(&enqueue, &permissions, &allowed).is_ok());
+        assert!(check(&enqueue, &PluginPermissions::default(), &allowed).is_err());
+
+        let delete = PluginCommandRequest::DeleteDoc {
+            url: "https://example.com".into(),
+        };
+        assert!(check(&delete, &permissions, &allowed).is_err());
+
+        let walk = |path: &str| PluginCommandRequest::WalkAndEnqueue {
+            path: PathBuf::from(path),
+            extensions: HashSet::new(),
+        };
+        assert!(check(&walk("/home/alice/notes/work"), &permissions, &allowed).is_ok());
+        assert!(check(&walk("/etc"), &permissions, &allowed).is_err());
+
+        let list = PluginCommandRequest::ListDir {
+            path: "/home/alice".into(),
+        };
+        assert!(check(&list, &permissions, &allowed).is_err());
+    }
+}
@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use notify::event::ModifyKind;
+use notify::EventKind;
+
+/// Wait this long after a plugin's WASM changes before reloading it, builds
+/// write the file in several chunks.
+pub const RELOAD_DELAY: Duration = Duration::from_millis(500);
+
+/// WASM files that were (re)built by the file `event`.
+pub fn changed_wasm(event: &notify::Event) -> Vec<PathBuf> {
+    let is_change = match &event.kind {
+        EventKind::Create(_) => true,
+        EventKind::Modify(modify_kind) => !matches!(modify_kind, ModifyKind::Metadata(_)),
+        _ => false,
+    };
+
+    if !is_change {
+        return Vec::new();
+    }
+
+    event
+        .paths
+        .iter()
+        .filter(|path| path.extension().map_or(false, |ext| ext == "wasm"))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use notify::event::{CreateKind, DataChange, MetadataKind, ModifyKind, RemoveKind};
+    use notify::{Event, EventKind};
+
+    use super::changed_wasm;
+
+    #[test]
+    fn test_changed_wasm() {
+        let wasm = PathBuf::from("/plugins/local-file-indexer/main.wasm");
+        let manifest = PathBuf::from("/plugins/local-file-indexer/manifest.ron");
+        let event = |kind: EventKind| {
+            Event::new(kind)
+                .add_path(wasm.clone())
+                .add_path(manifest.clone())
+        };
+
+        assert_eq!(
+            changed_wasm(&event(EventKind::Modify(ModifyKind::Data(
+                DataChange::Content
+            )))),
+            vec![wasm.clone()]
+        );
+        assert_eq!(
+            changed_wasm(&event(EventKind::Create(CreateKind::File))),
+            vec![wasm.clone()]
+        );
+        assert!(changed_wasm(&event(EventKind::Modify(ModifyKind::Metadata(
+            MetadataKind::AccessTime
+        ))))
+        .is_empty());
+        assert!(changed_wasm(&event(EventKind::Remove(RemoveKind::File))).is_empty());
+    }
+}
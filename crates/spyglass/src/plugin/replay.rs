@@ -0,0 +1,164 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use spyglass_plugin::PluginEvent;
+
+/// Max number of distinct paths remembered for a plugin, past this point
+/// file changes are dropped.
+const MAX_MISSED_FILES: usize = 10_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum FileChange {
+    Created,
+    Updated,
+    Deleted,
+}
+
+impl FileChange {
+    /// What the plugin needs to know about a file that had `self` happen to
+    /// it & then `next`. `None` if the changes cancel each other out.
+    fn then(self, next: FileChange) -> Option<FileChange> {
+        match (self, next) {
+            // Never seen by the plugin, nothing to do.
+            (FileChange::Created, FileChange::Deleted) => None,
+            (FileChange::Created, _) => Some(FileChange::Created),
+            // Replaced w/ a new file, which is an update as far as the
+            // plugin is concerned.
+            (FileChange::Deleted, FileChange::Created) => Some(FileChange::Updated),
+            (_, next) => Some(next),
+        }
+    }
+}
+
+/// Events a plugin missed while it was disabled or crashed, replayed once it
+/// is initialized again. Changes to the same file are coalesced into a
+/// single event & interval ticks into one update.
+#[derive(Debug, Default)]
+pub struct MissedEvents {
+    interval_update: bool,
+    files: BTreeMap<PathBuf, FileChange>,
+    tasks: Vec<PluginEvent>,
+    dropped: usize,
+}
+
+impl MissedEvents {
+    pub fn push(&mut self, event: PluginEvent) {
+        let (path, change) = match event {
+            PluginEvent::IntervalUpdate => {
+                self.interval_update = true;
+                return;
+            }
+            PluginEvent::RunTask { .. } => {
+                if !self.tasks.contains(&event) {
+                    self.tasks.push(event);
+                }
+                return;
+            }
+            PluginEvent::FileCreated(path) => (path, FileChange::Created),
+            PluginEvent::FileUpdated(path) => (path, FileChange::Updated),
+            PluginEvent::FileDeleted(path) => (path, FileChange::Deleted),
+        };
+
+        match self.files.get(&path).copied() {
+            Some(prev) => match prev.then(change) {
+                Some(change) => {
+                    self.files.insert(path, change);
+                }
+                None => {
+                    self.files.remove(&path);
+                }
+            },
+            None if self.files.len() >= MAX_MISSED_FILES => self.dropped += 1,
+            None => {
+                self.files.insert(path, change);
+            }
+        }
+    }
+
+    /// Number of events that will be replayed.
+    pub fn len(&self) -> usize {
+        self.files.len() + self.tasks.len() + usize::from(self.interval_update)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// File changes that didn't fit in the queue & won't be replayed.
+    pub fn dropped(&self) -> usize {
+        self.dropped
+    }
+
+    /// Events in the order they should be replayed: file changes, scheduled
+    /// tasks & then a single interval update.
+    pub fn into_events(self) -> Vec<PluginEvent> {
+        let files = self.files.into_iter().map(|(path, change)| match change {
+            FileChange::Created => PluginEvent::FileCreated(path),
+            FileChange::Updated => PluginEvent::FileUpdated(path),
+            FileChange::Deleted => PluginEvent::FileDeleted(path),
+        });
+
+        files
+            .chain(self.tasks)
+            .chain(self.interval_update.then_some(PluginEvent::IntervalUpdate))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::PathBuf;
+
+    use spyglass_plugin::PluginEvent;
+
+    use super::MissedEvents;
+
+    #[test]
+    fn test_coalesce_file_events() {
+        let path = |name: &str| PathBuf::from(format!("/notes/{}", name));
+        let mut missed = MissedEvents::default();
+
+        // Created & edited a couple times
+        missed.push(PluginEvent::FileCreated(path("new.md")));
+        missed.push(PluginEvent::FileUpdated(path("new.md")));
+        missed.push(PluginEvent::FileUpdated(path("new.md")));
+        // Created & removed before the plugin ever saw it
+        missed.push(PluginEvent::FileCreated(path("tmp.md")));
+        missed.push(PluginEvent::FileDeleted(path("tmp.md")));
+        // Replaced by a new file
+        missed.push(PluginEvent::FileDeleted(path("old.md")));
+        missed.push(PluginEvent::FileCreated(path("old.md")));
+        // Edited & then removed
+        missed.push(PluginEvent::FileUpdated(path("gone.md")));
+        missed.push(PluginEvent::FileDeleted(path("gone.md")));
+
+        missed.push(PluginEvent::IntervalUpdate);
+        missed.push(PluginEvent::IntervalUpdate);
+
+        assert_eq!(missed.len(), 4);
+        assert_eq!(
+            missed.into_events(),
+            vec![
+                PluginEvent::FileDeleted(path("gone.md")),
+                PluginEvent::FileCreated(path("new.md")),
+                PluginEvent::FileUpdated(path("old.md")),
+                PluginEvent::IntervalUpdate,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_dedupe_tasks() {
+        let task = |payload: &str| PluginEvent::RunTask {
+            task: "import".into(),
+            payload: payload.into(),
+        };
+
+        let mut missed = MissedEvents::default();
+        missed.push(task("page=2"));
+        missed.push(task("page=2"));
+        missed.push(task("page=3"));
+
+        assert_eq!(missed.into_events(), vec![task("page=2"), task("page=3")]);
+    }
+}
@@ -0,0 +1,140 @@
+/// Catch up on changes made to watched directories while the app wasn't
+/// running. File watchers only see changes as they happen, so anything that
+/// changed in between would otherwise be missed until the next recrawl.
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+use entities::models::indexed_document;
+use entities::sea_orm::{ColumnTrait, DatabaseConnection, EntityTrait, QueryFilter};
+use ignore::WalkBuilder;
+use spyglass_plugin::{utils::path_to_uri, PluginEvent};
+use url::Url;
+
+/// Files under `dir` & when they were last modified, skipping anything the
+/// file watcher would ignore.
+fn files_on_disk(dir: &Path, recurse: bool) -> HashMap<String, (PathBuf, DateTime<Utc>)> {
+    WalkBuilder::new(dir)
+        .standard_filters(true)
+        .max_depth(if recurse { None } else { Some(1) })
+        .build()
+        .flatten()
+        .filter(|entry| entry.file_type().map_or(false, |ft| ft.is_file()))
+        .filter_map(|entry| {
+            let modified: SystemTime = entry.metadata().ok()?.modified().ok()?;
+            let path = entry.into_path();
+            Some((path_to_uri(path.clone()), (path, modified.into())))
+        })
+        .collect()
+}
+
+/// Events for the differences between what's on disk & what was indexed, by
+/// URL. Files that are on disk but weren't indexed are reported as created,
+/// ones modified after they were indexed as updated, and indexed files that
+/// are no longer on disk as deleted.
+fn diff(
+    on_disk: &HashMap<String, (PathBuf, DateTime<Utc>)>,
+    indexed: &HashMap<String, DateTime<Utc>>,
+    is_deleted: impl Fn(&Path) -> bool,
+) -> Vec<PluginEvent> {
+    let mut events = Vec::new();
+    for (url, (path, modified)) in on_disk {
+        match indexed.get(url) {
+            None => events.push(PluginEvent::FileCreated(path.clone())),
+            Some(updated_at) if modified > updated_at => {
+                events.push(PluginEvent::FileUpdated(path.clone()))
+            }
+            _ => {}
+        }
+    }
+
+    for url in indexed.keys() {
+        if on_disk.contains_key(url) {
+            continue;
+        }
+
+        let path = Url::parse(url).ok().and_then(|url| url.to_file_path().ok());
+        if let Some(path) = path {
+            if is_deleted(&path) {
+                events.push(PluginEvent::FileDeleted(path));
+            }
+        }
+    }
+
+    events
+}
+
+/// Changes to files under `dir` that happened since they were last indexed.
+pub async fn changes_since_indexed(
+    db: &DatabaseConnection,
+    dir: &Path,
+    recurse: bool,
+) -> anyhow::Result<Vec<PluginEvent>> {
+    let walk_dir = dir.to_path_buf();
+    let on_disk = tokio::task::spawn_blocking(move || files_on_disk(&walk_dir, recurse)).await?;
+
+    let prefix = format!("{}/", path_to_uri(dir.to_path_buf()).trim_end_matches('/'));
+    let indexed = indexed_document::Entity::find()
+        .filter(indexed_document::Column::Url.starts_with(&prefix))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|doc| (doc.url, doc.updated_at))
+        .collect::<HashMap<_, _>>();
+
+    // Files in sub-directories aren't ours to report if we're not recursing.
+    let is_deleted = |path: &Path| (recurse || path.parent() == Some(dir)) && !path.exists();
+    Ok(diff(&on_disk, &indexed, is_deleted))
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    use chrono::{Duration, Utc};
+    use spyglass_plugin::{utils::path_to_uri, PluginEvent};
+
+    use super::diff;
+
+    #[test]
+    fn test_diff() {
+        let now = Utc::now();
+        let path = |name: &str| PathBuf::from(format!("/notes/{}", name));
+
+        let mut on_disk = HashMap::new();
+        for (name, modified) in [
+            ("new.md", now),
+            ("edited.md", now),
+            ("unchanged.md", now - Duration::days(2)),
+        ] {
+            on_disk.insert(path_to_uri(path(name)), (path(name), modified));
+        }
+
+        let mut indexed = HashMap::new();
+        for name in ["edited.md", "unchanged.md", "removed.md"] {
+            indexed.insert(path_to_uri(path(name)), now - Duration::days(1));
+        }
+
+        let mut events = diff(&on_disk, &indexed, |_| true)
+            .into_iter()
+            .map(|event| format!("{:?}", event))
+            .collect::<Vec<_>>();
+        events.sort();
+
+        let mut expected = vec![
+            PluginEvent::FileCreated(path("new.md")),
+            PluginEvent::FileUpdated(path("edited.md")),
+            PluginEvent::FileDeleted(path("removed.md")),
+        ]
+        .into_iter()
+        .map(|event| format!("{:?}", event))
+        .collect::<Vec<_>>();
+        expected.sort();
+
+        assert_eq!(events, expected);
+        // Indexed files that still exist somewhere we didn't walk are kept.
+        assert_eq!(diff(&on_disk, &indexed, |_| false).len(), 2);
+    }
+}
@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Calls into a plugin that take longer than this are given up on.
+pub const CALL_TIMEOUT: Duration = Duration::from_secs(60);
+/// Plugins are disabled after timing out this many times in a row.
+pub const MAX_CONSECUTIVE_TIMEOUTS: u32 = 3;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Verdict {
+    /// Abandon the hung call & start a fresh instance of the plugin.
+    Restart,
+    /// Keeps hanging, leave it off until the user enables it again.
+    Disable,
+}
+
+/// Keeps track of plugins that stop responding, by plugin name so counts
+/// survive the plugin being restarted.
+#[derive(Debug, Default)]
+pub struct Watchdog {
+    timeouts: HashMap<String, u32>,
+}
+
+impl Watchdog {
+    /// Record a call into the plugin that timed out.
+    pub fn timed_out(&mut self, name: &str) -> Verdict {
+        let timeouts = self.timeouts.entry(name.to_string()).or_default();
+        *timeouts += 1;
+
+        if *timeouts >= MAX_CONSECUTIVE_TIMEOUTS {
+            self.timeouts.remove(name);
+            Verdict::Disable
+        } else {
+            Verdict::Restart
+        }
+    }
+
+    /// The plugin finished a call in time, earlier timeouts no longer count.
+    pub fn responded(&mut self, name: &str) {
+        self.timeouts.remove(name);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Verdict, Watchdog, MAX_CONSECUTIVE_TIMEOUTS};
+
+    #[test]
+    fn test_watchdog() {
+        let mut watchdog = Watchdog::default();
+        for _ in 1..MAX_CONSECUTIVE_TIMEOUTS {
+            assert_eq!(watchdog.timed_out("slow"), Verdict::Restart);
+        }
+        // Only counts timeouts in a row
+        watchdog.responded("slow");
+        assert_eq!(watchdog.timed_out("slow"), Verdict::Restart);
+
+        for _ in 1..MAX_CONSECUTIVE_TIMEOUTS - 1 {
+            watchdog.timed_out("slow");
+        }
+        assert_eq!(watchdog.timed_out("slow"), Verdict::Disable);
+        // Starts over once it's enabled again
+        assert_eq!(watchdog.timed_out("slow"), Verdict::Restart);
+        assert_eq!(watchdog.timed_out("other"), Verdict::Restart);
+    }
+}
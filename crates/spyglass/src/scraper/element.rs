@@ -115,6 +115,14 @@ impl Element {
     pub fn name(&self) -> String {
         self.name.local.to_string()
     }
+
+    /// Value of the attribute w/ the local name `name`, if any.
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(key, _)| key.local.deref() == name)
+            .map(|(_, value)| value.deref())
+    }
 }
 
 impl fmt::Debug for Element {
@@ -0,0 +1,272 @@
+use std::collections::HashMap;
+
+use ego_tree::NodeRef;
+use serde_json::Value;
+use shared::config::ExtractRule;
+
+use crate::scraper::element::Node;
+use crate::scraper::html::Html;
+use crate::scraper::selector::Selector;
+
+/// Text content of a node and all its children, w/ whitespace collapsed.
+pub(crate) fn node_text(node: &NodeRef<Node>) -> String {
+    let mut text = String::new();
+    for child in node.descendants() {
+        if let Some(t) = child.value().as_text() {
+            text.push_str(t);
+        }
+    }
+
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+/// Parsed contents of any `<script type="application/ld+json">` blocks.
+pub(crate) fn json_ld(html: &Html) -> Vec<Value> {
+    let mut blocks = Vec::new();
+    for node in html.tree.root().descendants() {
+        let is_json_ld = node.value().as_element().map_or(false, |el| {
+            el.name() == "script" && el.attr("type") == Some("application/ld+json")
+        });
+
+        if is_json_ld {
+            match serde_json::from_str::<Value>(&node_text(&node)) {
+                // Flatten out lists & @graph containers so each item can be checked.
+                Ok(Value::Array(items)) => blocks.extend(items),
+                Ok(mut value) => {
+                    if let Some(Value::Array(items)) = value.get_mut("@graph").map(Value::take) {
+                        blocks.extend(items);
+                    } else {
+                        blocks.push(value);
+                    }
+                }
+                Err(err) => log::debug!("invalid JSON-LD block: {}", err),
+            }
+        }
+    }
+
+    blocks
+}
+
+/// Walk a dot-separated `path` through a JSON value. Arrays can be indexed
+/// into w/ a number, otherwise the first item is used.
+fn json_path(value: &Value, path: &str) -> Option<String> {
+    let mut current = value;
+    for segment in path.split('.') {
+        current = match current {
+            Value::Array(items) => match segment.parse::<usize>() {
+                Ok(idx) => items.get(idx)?,
+                Err(_) => items.first()?.get(segment)?,
+            },
+            _ => current.get(segment)?,
+        };
+    }
+
+    json_to_string(current)
+}
+
+fn json_to_string(value: &Value) -> Option<String> {
+    let value = match value {
+        Value::String(val) => val.trim().to_string(),
+        Value::Number(val) => val.to_string(),
+        Value::Bool(val) => val.to_string(),
+        Value::Array(items) => return items.first().and_then(json_to_string),
+        _ => return None,
+    };
+
+    if value.is_empty() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+fn css(html: &Html, selector: &str) -> Option<String> {
+    // Grab an attribute rather than the text content.
+    let (selector, attr) = match selector.rsplit_once('@') {
+        Some((selector, attr)) => (selector, Some(attr)),
+        None => (selector, None),
+    };
+
+    let node = Selector::parse(selector)?.select_first(html.tree.root())?;
+    let value = match attr {
+        Some(attr) => node.value().as_element()?.attr(attr)?.trim().to_string(),
+        None => node_text(&node),
+    };
+
+    if value.is_empty() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+/// Value of the first microdata property w/ `itemprop` = `name`.
+fn microdata(html: &Html, name: &str) -> Option<String> {
+    let node = html.tree.root().descendants().find(|node| {
+        node.value()
+            .as_element()
+            .map_or(false, |el| el.attr("itemprop") == Some(name))
+    })?;
+
+    let element = node.value().as_element()?;
+    let value = ["content", "datetime", "src", "href"]
+        .iter()
+        .find_map(|attr| element.attr(attr))
+        .map(|value| value.trim().to_string())
+        .unwrap_or_else(|| node_text(&node));
+
+    if value.is_empty() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+/// Schema.org type from the first element w/ an `itemtype`,
+/// e.g. "https://schema.org/Recipe" -> "Recipe"
+fn microdata_type(html: &Html) -> Option<String> {
+    html.tree.root().descendants().find_map(|node| {
+        node.value()
+            .as_element()
+            .and_then(|el| el.attr("itemtype"))
+            .and_then(|itemtype| itemtype.trim_end_matches('/').rsplit('/').next())
+            .filter(|itemtype| !itemtype.is_empty())
+            .map(|itemtype| itemtype.to_string())
+    })
+}
+
+/// Pull common document metadata (type, author, published date, image) from
+/// JSON-LD, microdata & OpenGraph/meta tags, in that order of preference.
+pub fn page_metadata(html: &Html, meta: &HashMap<String, String>) -> Vec<(String, String)> {
+    let json_ld = json_ld(html);
+    let from_json_ld = |paths: &[&str]| {
+        json_ld
+            .iter()
+            .find_map(|block| paths.iter().find_map(|path| json_path(block, path)))
+    };
+    let from_meta = |keys: &[&str]| {
+        keys.iter()
+            .find_map(|key| meta.get(*key))
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    let doc_type = from_json_ld(&["@type"])
+        .or_else(|| microdata_type(html))
+        .or_else(|| from_meta(&["og:type"]));
+
+    let author = from_json_ld(&["author.name", "author"])
+        .or_else(|| microdata(html, "author"))
+        .or_else(|| from_meta(&["author", "article:author"]));
+
+    let published = from_json_ld(&["datePublished"])
+        .or_else(|| microdata(html, "datePublished"))
+        .or_else(|| from_meta(&["article:published_time", "date"]));
+
+    let image = from_json_ld(&["image.url", "image"])
+        .or_else(|| microdata(html, "image"))
+        .or_else(|| from_meta(&["og:image", "twitter:image"]));
+
+    [
+        ("type", doc_type),
+        ("author", author),
+        ("published", published),
+        ("image", image),
+    ]
+    .into_iter()
+    .filter_map(|(name, value)| value.map(|value| (name.to_string(), value)))
+    .collect()
+}
+
+/// Apply lens extraction rules to a page, returning a list of (field, value) pairs.
+pub fn extract_fields(raw_body: &str, rules: &[ExtractRule]) -> Vec<(String, String)> {
+    if rules.is_empty() {
+        return Vec::new();
+    }
+
+    let html = Html::parse(raw_body);
+    let json_ld = json_ld(&html);
+
+    rules
+        .iter()
+        .filter_map(|rule| {
+            let value = match rule {
+                ExtractRule::Css(_, selector) => css(&html, selector),
+                ExtractRule::JsonPath(_, path) => {
+                    json_ld.iter().find_map(|block| json_path(block, path))
+                }
+            };
+
+            value.map(|value| (rule.field().to_string(), value))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{extract_fields, page_metadata};
+    use crate::scraper::html::Html;
+    use shared::config::ExtractRule;
+
+    #[test]
+    fn test_page_metadata() {
+        let raw = r#"<html><head>
+            <meta property="og:type" content="article">
+            <meta property="og:image" content="https://example.com/cake.png">
+            <script type="application/ld+json">
+                {"@context": "https://schema.org", "@graph": [
+                    {"@type": "Recipe", "author": {"@type": "Person", "name": "Jane"}}
+                ]}
+            </script>
+        </head><body>
+            <time itemprop="datePublished" datetime="2022-12-01">Dec 1st</time>
+        </body></html>"#;
+
+        let html = Html::parse(raw);
+        let metadata = page_metadata(&html, &html.meta());
+        assert_eq!(
+            metadata,
+            vec![
+                ("type".to_string(), "Recipe".to_string()),
+                ("author".to_string(), "Jane".to_string()),
+                ("published".to_string(), "2022-12-01".to_string()),
+                (
+                    "image".to_string(),
+                    "https://example.com/cake.png".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_fields() {
+        let html = r#"<html><head>
+            <script type="application/ld+json">
+                {"@type": "Book", "author": [{"name": "Mary Shelley"}], "offers": {"price": 9.99}}
+            </script>
+            <meta itemprop="datePublished" content="1818-01-01">
+        </head><body><h1 class="title"> Frankenstein </h1></body></html>"#;
+
+        let rules = vec![
+            ExtractRule::Css("title".into(), "h1.title".into()),
+            ExtractRule::Css(
+                "published".into(),
+                "meta[itemprop=datePublished]@content".into(),
+            ),
+            ExtractRule::JsonPath("author".into(), "author.name".into()),
+            ExtractRule::JsonPath("price".into(), "offers.price".into()),
+            ExtractRule::JsonPath("missing".into(), "offers.currency".into()),
+        ];
+
+        let fields = extract_fields(html, &rules);
+        assert_eq!(
+            fields,
+            vec![
+                ("title".to_string(), "Frankenstein".to_string()),
+                ("published".to_string(), "1818-01-01".to_string()),
+                ("author".to_string(), "Mary Shelley".to_string()),
+                ("price".to_string(), "9.99".to_string()),
+            ]
+        );
+    }
+}
@@ -1,26 +1,51 @@
 #![allow(dead_code)]
 
 mod element;
+mod extract;
 mod html;
+mod readability;
+mod selector;
 
 use ego_tree::NodeRef;
 use html5ever::QualName;
 use std::collections::{HashMap, HashSet};
 use url::Url;
 
-use crate::scraper::element::Node;
+use crate::scraper::element::{Element, Node};
+use crate::scraper::extract::{node_text, page_metadata};
 use crate::scraper::html::Html;
+pub use extract::extract_fields;
 
 pub const DEFAULT_DESC_LENGTH: usize = 256;
 
+/// A part of a page that starts at a heading w/ an anchor we can link to.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Section {
+    /// Fragment used to jump to this section, e.g. `usage` for `page.html#usage`
+    pub anchor: String,
+    pub heading: String,
+    pub content: String,
+}
+
 #[derive(Debug)]
 pub struct ScrapeResult {
     pub title: Option<String>,
     /// Page description, extracted from meta tags or summarized from the actual content
     pub description: String,
     pub meta: HashMap<String, String>,
+    /// Document metadata (type, author, etc.) from schema.org/OpenGraph markup
+    pub metadata: Vec<(String, String)>,
     pub content: String,
+    /// Words of text on the whole page, before reader mode picks out the main
+    /// content.
+    pub page_word_count: usize,
+    /// Contents of code blocks (`<pre>`), w/ whitespace preserved.
+    pub code: Vec<String>,
+    /// Content split up by anchored headings.
+    pub sections: Vec<Section>,
     pub links: HashSet<String>,
+    /// Links only marked `rel="nofollow"`, also included in `links`.
+    pub nofollow_links: HashSet<String>,
     /// Index should use this URL instead of the one that lead to the content.
     pub canonical_url: Option<Url>,
 }
@@ -48,8 +73,72 @@ fn filter_p_nodes(root: &NodeRef<Node>, p_list: &mut Vec<String>) {
     }
 }
 
-/// Filters a DOM tree into a text document used for indexing
-fn filter_text_nodes(root: &NodeRef<Node>, doc: &mut String, links: &mut HashSet<String>) {
+/// Raw text of a node, keeping whitespace intact.
+fn raw_text(root: &NodeRef<Node>) -> String {
+    let mut text = String::new();
+    for node in root.descendants() {
+        if let Some(t) = node.value().as_text() {
+            text.push_str(t);
+        }
+    }
+
+    text
+}
+
+/// Walk the DOM and grab the contents of any code blocks.
+fn filter_code_nodes(root: &NodeRef<Node>, code: &mut Vec<String>) {
+    for child in root.children() {
+        if let Some(element) = child.value().as_element() {
+            if element.name().eq_ignore_ascii_case("pre") {
+                let block = raw_text(&child);
+                let block = block.trim_matches(|c: char| c == '\n' || c == '\r');
+                if !block.trim().is_empty() {
+                    code.push(block.to_string());
+                }
+                // No need to look for nested code blocks
+                continue;
+            }
+        }
+
+        if child.has_children() {
+            filter_code_nodes(&child, code);
+        }
+    }
+}
+
+/// Render a table one row per line w/ cells separated by `|` so the columns
+/// are still readable in the indexed content & snippets.
+fn table_to_text(table: &NodeRef<Node>) -> String {
+    let mut rows = Vec::new();
+    for row in table.descendants() {
+        let is_row = row
+            .value()
+            .as_element()
+            .map_or(false, |el| el.name().eq_ignore_ascii_case("tr"));
+        if !is_row {
+            continue;
+        }
+
+        let cells = row
+            .children()
+            .filter(|cell| {
+                cell.value().as_element().map_or(false, |el| {
+                    el.name().eq_ignore_ascii_case("td") || el.name().eq_ignore_ascii_case("th")
+                })
+            })
+            .map(|cell| node_text(&cell))
+            .collect::<Vec<String>>();
+
+        if cells.iter().any(|cell| !cell.is_empty()) {
+            rows.push(cells.join(" | "));
+        }
+    }
+
+    rows.join("\n")
+}
+
+/// Whether an element should be skipped when building the indexed content.
+fn is_ignored(element: &Element) -> bool {
     // TODO: move to config file? turn into a whitelist?
     // TODO: Ignore list could also be updated per domain as well if needed
     let ignore_list: HashSet<String> = HashSet::from([
@@ -68,27 +157,137 @@ fn filter_text_nodes(root: &NodeRef<Node>, doc: &mut String, links: &mut HashSet
         "style".into(),
     ]);
 
+    // Ignore elements on the ignore list
+    if ignore_list.contains(&element.name()) {
+        return true;
+    }
+
+    // Ignore elements whose role is "navigation"
+    // TODO: Filter out full-list of ARIA roles that are not content
+    matches!(
+        element.attr("role"),
+        Some("navigation") | Some("contentinfo") | Some("button")
+    )
+}
+
+fn is_heading(element: &Element) -> bool {
+    matches!(
+        element.name().to_lowercase().as_str(),
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6"
+    )
+}
+
+/// Anchor for a heading, either its own id or an id/name on something inside
+/// it (e.g. `<h2><span id="usage">Usage</span></h2>`).
+fn heading_anchor(heading: &NodeRef<Node>) -> Option<String> {
+    heading
+        .descendants()
+        .filter_map(|node| {
+            let element = node.value().as_element()?;
+            match element.attr("id") {
+                Some(id) => Some(id),
+                None if element.name() == "a" => element.attr("name"),
+                None => None,
+            }
+        })
+        .find(|anchor| !anchor.trim().is_empty())
+        .map(|anchor| anchor.to_string())
+}
+
+/// Split the DOM into sections, each starting at a heading w/ an anchor. Any
+/// content before the first anchored heading is skipped.
+fn filter_sections(root: &NodeRef<Node>, sections: &mut Vec<Section>) {
+    for child in root.children() {
+        let node = child.value();
+        if let Some(text) = node.as_text() {
+            if let Some(section) = sections.last_mut() {
+                section.content.push_str(text);
+            }
+            continue;
+        }
+
+        let element = match node.as_element() {
+            Some(element) => element,
+            None => continue,
+        };
+
+        if is_ignored(element) {
+            continue;
+        }
+
+        if is_heading(element) {
+            if let Some(anchor) = heading_anchor(&child) {
+                sections.push(Section {
+                    anchor,
+                    heading: node_text(&child),
+                    content: String::new(),
+                });
+                continue;
+            }
+        }
+
+        let has_headings = child
+            .descendants()
+            .skip(1)
+            .any(|node| node.value().as_element().map_or(false, is_heading));
+
+        if has_headings {
+            filter_sections(&child, sections);
+        } else if let Some(section) = sections.last_mut() {
+            if element.name().eq_ignore_ascii_case("table") {
+                section.content.push('\n');
+                section.content.push_str(&table_to_text(&child));
+                section.content.push('\n');
+            } else {
+                filter_text_nodes(&child, &mut section.content, &mut HashSet::new());
+                section.content.push(' ');
+            }
+        }
+    }
+}
+
+/// Whether a link asks crawlers not to follow it, i.e. `<a rel="nofollow">`.
+fn is_nofollow(element: &Element) -> bool {
+    element.attr("rel").map_or(false, |rel| {
+        rel.split_whitespace()
+            .any(|value| value.eq_ignore_ascii_case("nofollow"))
+    })
+}
+
+/// Links that are marked `rel="nofollow"` everywhere they appear on the page.
+fn filter_nofollow_links(root: &NodeRef<Node>) -> HashSet<String> {
+    let mut nofollow = HashSet::new();
+    let mut followed = HashSet::new();
+    for node in root.descendants() {
+        let element = match node.value().as_element() {
+            Some(element) if element.name() == "a" => element,
+            _ => continue,
+        };
+
+        if let Some(href) = element.attr("href") {
+            if is_nofollow(element) {
+                nofollow.insert(href.to_string());
+            } else {
+                followed.insert(href.to_string());
+            }
+        }
+    }
+
+    nofollow.retain(|href| !followed.contains(href));
+    nofollow
+}
+
+/// Filters a DOM tree into a text document used for indexing
+fn filter_text_nodes(root: &NodeRef<Node>, doc: &mut String, links: &mut HashSet<String>) {
     let href_key = QualName::new(None, ns!(), local_name!("href"));
-    let role_key = QualName::new(None, ns!(), local_name!("role"));
 
     for child in root.children() {
         let node = child.value();
         if node.is_text() {
             doc.push_str(node.as_text().unwrap());
         } else if node.is_element() {
-            // Ignore elements on the ignore list
             let element = node.as_element().unwrap();
-            if ignore_list.contains(&element.name()) {
-                continue;
-            }
-
-            // Ignore elements whose role is "navigation"
-            // TODO: Filter out full-list of ARIA roles that are not content
-            if element.attrs.contains_key(&role_key)
-                && (element.attrs.get(&role_key).unwrap().to_string() == *"navigation"
-                    || element.attrs.get(&role_key).unwrap().to_string() == *"contentinfo"
-                    || element.attrs.get(&role_key).unwrap().to_string() == *"button")
-            {
+            if is_ignored(element) {
                 continue;
             }
 
@@ -101,6 +300,17 @@ fn filter_text_nodes(root: &NodeRef<Node>, doc: &mut String, links: &mut HashSet
                 }
             } else if element.name() == "br" && !doc.ends_with(' ') {
                 doc.push(' ');
+            } else if element.name().eq_ignore_ascii_case("table") {
+                // Still need to grab any links from inside the table.
+                filter_text_nodes(&child, &mut String::new(), links);
+
+                let table = table_to_text(&child);
+                if !table.is_empty() {
+                    doc.push('\n');
+                    doc.push_str(&table);
+                    doc.push('\n');
+                }
+                continue;
             }
 
             if child.has_children() {
@@ -120,20 +330,50 @@ fn filter_text_nodes(root: &NodeRef<Node>, doc: &mut String, links: &mut HashSet
     }
 }
 
-/// Filters a DOM tree into a text document used for indexing
-pub fn html_to_text(doc: &str) -> ScrapeResult {
+/// Filters a DOM tree into a text document used for indexing. With `reader_mode`
+/// only the main content of the page is indexed, dropping navigation, ads, etc.
+pub fn html_to_text(doc: &str, reader_mode: bool) -> ScrapeResult {
     let parsed = Html::parse(doc);
     let root = parsed.tree.root();
     // Meta tags
     let meta = parsed.meta();
     let link_tags = parsed.link_tags();
+    let metadata = page_metadata(&parsed, &meta);
     // Content
     let title = parsed.title();
     let mut content = String::from("");
     let mut links = HashSet::new();
     filter_text_nodes(&root, &mut content, &mut links);
+    let nofollow_links = filter_nofollow_links(&root);
+    let page_word_count = content.split_whitespace().count();
+
+    // Links are still pulled from the entire page so crawling isn't affected.
+    let main = if reader_mode {
+        readability::main_content(&parsed)
+    } else {
+        None
+    };
+
+    if let Some(main) = main {
+        content.clear();
+        filter_text_nodes(&main, &mut content, &mut HashSet::new());
+    }
     content = content.trim().to_string();
 
+    let mut sections = Vec::new();
+    filter_sections(&main.unwrap_or(root), &mut sections);
+    for section in sections.iter_mut() {
+        section.content = section
+            .content
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ");
+    }
+    sections.retain(|section| !section.content.is_empty());
+
+    let mut code = Vec::new();
+    filter_code_nodes(&root, &mut code);
+
     let mut description = if meta.contains_key("description") {
         meta.get("description").unwrap().to_string()
     } else if meta.contains_key("og:description") {
@@ -176,10 +416,15 @@ pub fn html_to_text(doc: &str) -> ScrapeResult {
 
     ScrapeResult {
         canonical_url,
+        code,
         content,
         description,
         links,
         meta,
+        metadata,
+        nofollow_links,
+        page_word_count,
+        sections,
         title,
     }
 }
@@ -191,17 +436,96 @@ mod test {
     #[test]
     fn test_html_to_text() {
         let html = include_str!("../../../../fixtures/html/raw.html");
-        let doc = html_to_text(html);
+        let doc = html_to_text(html, false);
         assert_eq!(doc.title, Some("Old School RuneScape Wiki".to_string()));
         assert_eq!(doc.meta.len(), 9);
         assert!(doc.content.len() > 0);
         assert_eq!(doc.links.len(), 58);
     }
 
+    #[test]
+    fn test_code_and_tables() {
+        let html = r#"<html><body>
+            <p>Install it w/ cargo:</p>
+            <pre><code>cargo install spyglass
+cargo run</code></pre>
+            <table>
+                <tr><th>Name</th><th>Value</th></tr>
+                <tr><td>a</td><td><a href="/one">one</a></td></tr>
+            </table>
+        </body></html>"#;
+
+        let doc = html_to_text(html, false);
+        assert_eq!(
+            doc.code,
+            vec!["cargo install spyglass\ncargo run".to_string()]
+        );
+        assert!(doc.content.contains("Name | Value\na | one"));
+        assert!(doc.links.contains("/one"));
+    }
+
+    #[test]
+    fn test_nofollow_links() {
+        let html = r#"<html><body>
+            <a href="/about">About</a>
+            <a href="/login" rel="nofollow">Log in</a>
+            <a href="/ad" rel="sponsored NOFOLLOW">Ad</a>
+            <a href="/about" rel="nofollow">About us</a>
+        </body></html>"#;
+
+        let doc = html_to_text(html, false);
+        assert_eq!(doc.links.len(), 3);
+        assert_eq!(doc.nofollow_links.len(), 2);
+        assert!(doc.nofollow_links.contains("/login"));
+        assert!(doc.nofollow_links.contains("/ad"));
+    }
+
+    #[test]
+    fn test_sections() {
+        let html = r#"<html><body>
+            <p>Intro that isn't part of any section</p>
+            <h2 id="install">Installation</h2>
+            <p>Download the installer.</p>
+            <div>
+                <h2><span id="usage">Usage</span></h2>
+                <p>Run the app.</p>
+            </div>
+            <h2>No anchor here</h2>
+            <p>Still usage.</p>
+        </body></html>"#;
+
+        let doc = html_to_text(html, false);
+        let sections = doc
+            .sections
+            .iter()
+            .map(|s| (s.anchor.as_str(), s.heading.as_str(), s.content.as_str()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            sections,
+            vec![
+                ("install", "Installation", "Download the installer."),
+                ("usage", "Usage", "Run the app. No anchor here Still usage.")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_html_to_text_reader_mode() {
+        let html = include_str!("../../../../fixtures/html/wikipedia_entry.html");
+        let full = html_to_text(html, false);
+        let reader = html_to_text(html, true);
+
+        assert!(reader.content.len() > 0);
+        assert!(reader.content.len() < full.content.len());
+        assert_eq!(reader.links.len(), full.links.len());
+        assert_eq!(reader.description, full.description);
+    }
+
     #[test]
     fn test_description_extraction() {
         let html = include_str!("../../../../fixtures/html/wikipedia_entry.html");
-        let doc = html_to_text(html);
+        let doc = html_to_text(html, false);
 
         assert_eq!(
             doc.title.unwrap(),
@@ -210,7 +534,7 @@ mod test {
         assert_eq!(doc.description, "Rust is a multi-paradigm, general-purpose programming language designed for performance and safety, especially safe concurrency. Rust is syntactically similar to C++, but can guarantee memory safety by using a borrow checker to validate references. Rust achieves memory safety without garbage collection, and reference counting is optional. Rust has been called a systems programming language, and in addition to high-level features such as functional programming it also offers mechanisms for low-level memory management.");
 
         let html = include_str!("../../../../fixtures/html/personal_blog.html");
-        let doc = html_to_text(html);
+        let doc = html_to_text(html, false);
         // ugh need to fix this
         assert_eq!(doc.description, "2020 July 15 - San Francisco |  855 words");
     }
@@ -218,7 +542,7 @@ mod test {
     #[test]
     fn test_description_extraction_yc() {
         let html = include_str!("../../../../fixtures/html/summary_test.html");
-        let doc = html_to_text(html);
+        let doc = html_to_text(html, false);
 
         assert_eq!(doc.title.unwrap(), "Why YC");
         assert_eq!(doc.description, "March 2006, rev August 2009 Yesterday one of the founders we funded asked me why we started Y Combinator.  Or more precisely, he asked if we'd started YC mainly for fun. Kind of, but not quite.  It is enormously fun to be able to work with Rtm and Trevor again.  I missed that after we sold Viaweb, and for all the years after I always had a background process running, looking for something we could do together.  There is definitely an aspect of a band reunion to Y Combinator.  Every couple days I slip and call it \"Viaweb.\" Viaweb we started very explicitly to make money.  I was sick of living from one freelance project to the next, and decided to just work as hard as I could till I'd made enough to solve the problem once and for all.  Viaweb was sometimes fun, but it wasn't designed for fun, and mostly it wasn't.  I'd be surprised if any startup is. All startups are mostly schleps. The real reason we started Y Combinator is neither selfish nor virtuous.  We didn't start it mainly to make money; we have no idea what our average returns might be, and won't know for years.  Nor did we start YC mainly to help out young would-be founders, though we do like the idea, and comfort ourselves occasionally with the thought that if all our investments tank, we will thus have been doing something unselfish.  (It's oddly nondeterministic.) The real");
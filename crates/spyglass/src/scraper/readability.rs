@@ -0,0 +1,261 @@
+use std::collections::HashMap;
+
+use ego_tree::{NodeId, NodeRef};
+
+use crate::scraper::element::{Element, Node};
+use crate::scraper::extract::node_text;
+use crate::scraper::html::Html;
+
+/// Paragraphs shorter than this are usually captions, buttons, etc.
+const MIN_PARAGRAPH_LENGTH: usize = 25;
+/// If the best candidate has less text than this, we probably picked the
+/// wrong thing and it's safer to index the whole page.
+const MIN_CONTENT_LENGTH: usize = 250;
+
+/// Elements that never contain the main content.
+const SKIP_TAGS: [&str; 10] = [
+    "aside", "footer", "form", "header", "nav", "noscript", "script", "style", "head", "iframe",
+];
+
+/// class/id hints that an element is page chrome rather than content.
+const NEGATIVE_HINTS: [&str; 24] = [
+    "ad-",
+    "ads",
+    "advert",
+    "banner",
+    "breadcrumb",
+    "combx",
+    "comment",
+    "cookie",
+    "disqus",
+    "footer",
+    "header",
+    "menu",
+    "modal",
+    "nav",
+    "popup",
+    "promo",
+    "related",
+    "share",
+    "shoutbox",
+    "sidebar",
+    "social",
+    "sponsor",
+    "subscribe",
+    "widget",
+];
+
+/// class/id hints that an element holds the content we're after.
+const POSITIVE_HINTS: [&str; 8] = [
+    "article", "body", "content", "entry", "main", "page", "post", "text",
+];
+
+/// Block level elements, used to figure out whether a `div` is being used as
+/// a paragraph.
+const BLOCK_TAGS: [&str; 12] = [
+    "article",
+    "blockquote",
+    "div",
+    "dl",
+    "h1",
+    "h2",
+    "h3",
+    "ol",
+    "p",
+    "pre",
+    "table",
+    "ul",
+];
+
+fn hints(element: &Element) -> String {
+    format!(
+        "{} {}",
+        element.attr("class").unwrap_or_default(),
+        element.attr("id").unwrap_or_default()
+    )
+    .to_lowercase()
+}
+
+fn class_weight(element: &Element) -> f32 {
+    let hints = hints(element);
+    let mut weight = 0.0;
+    if NEGATIVE_HINTS.iter().any(|hint| hints.contains(hint)) {
+        weight -= 25.0;
+    }
+
+    if POSITIVE_HINTS.iter().any(|hint| hints.contains(hint)) {
+        weight += 25.0;
+    }
+
+    weight
+}
+
+fn is_unlikely(element: &Element) -> bool {
+    if SKIP_TAGS.contains(&element.name().as_str()) {
+        return true;
+    }
+
+    match element.attr("role") {
+        Some("navigation") | Some("contentinfo") | Some("banner") | Some("complementary") => {
+            return true
+        }
+        _ => {}
+    }
+
+    let hints = hints(element);
+    NEGATIVE_HINTS.iter().any(|hint| hints.contains(hint))
+        && !POSITIVE_HINTS.iter().any(|hint| hints.contains(hint))
+}
+
+fn tag_weight(element: &Element) -> f32 {
+    match element.name().as_str() {
+        "article" | "main" => 10.0,
+        "div" => 5.0,
+        "blockquote" | "pre" | "td" => 3.0,
+        "address" | "dl" | "dd" | "dt" | "li" | "ol" | "ul" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    }
+}
+
+/// Fraction of the text inside a node that is part of a link.
+fn link_density(node: &NodeRef<Node>, text_len: usize) -> f32 {
+    if text_len == 0 {
+        return 1.0;
+    }
+
+    let link_len: usize = node
+        .descendants()
+        .filter(|child| {
+            child
+                .value()
+                .as_element()
+                .map_or(false, |el| el.name() == "a")
+        })
+        .map(|link| node_text(&link).len())
+        .sum();
+
+    link_len as f32 / text_len as f32
+}
+
+fn is_paragraph(node: &NodeRef<Node>, element: &Element) -> bool {
+    match element.name().as_str() {
+        "p" | "pre" | "blockquote" => true,
+        // Plenty of sites use divs w/ no block children as paragraphs
+        "div" => !node.children().any(|child| {
+            child
+                .value()
+                .as_element()
+                .map_or(false, |el| BLOCK_TAGS.contains(&el.name().as_str()))
+        }),
+        _ => false,
+    }
+}
+
+/// Walk the tree, skipping over anything that looks like page chrome, and
+/// collect the nodes that look like paragraphs of content.
+fn collect_paragraphs<'a>(root: NodeRef<'a, Node>, paragraphs: &mut Vec<NodeRef<'a, Node>>) {
+    for child in root.children() {
+        if let Some(element) = child.value().as_element() {
+            if is_unlikely(element) {
+                continue;
+            }
+
+            if is_paragraph(&child, element) {
+                paragraphs.push(child);
+            } else {
+                collect_paragraphs(child, paragraphs);
+            }
+        }
+    }
+}
+
+/// Find the node that holds the main content of a page, readability style.
+/// Paragraphs push their score up to their parent & grandparent, and the
+/// best scoring container (after penalizing link-heavy nodes) wins.
+///
+/// Returns None when no candidate has enough content to be trusted.
+pub fn main_content(html: &Html) -> Option<NodeRef<Node>> {
+    let mut paragraphs = Vec::new();
+    collect_paragraphs(html.tree.root(), &mut paragraphs);
+
+    // Keep track of insertion order so ties are broken consistently.
+    let mut candidates: Vec<NodeRef<Node>> = Vec::new();
+    let mut scores: HashMap<NodeId, f32> = HashMap::new();
+
+    for paragraph in paragraphs {
+        let text = node_text(&paragraph);
+        if text.len() < MIN_PARAGRAPH_LENGTH {
+            continue;
+        }
+
+        let score = 1.0 + text.matches(',').count() as f32 + (text.len() / 100).min(3) as f32;
+
+        let ancestors = paragraph
+            .ancestors()
+            .filter(|node| node.value().is_element())
+            .take(2);
+        for (level, ancestor) in ancestors.enumerate() {
+            let element = ancestor.value().as_element().expect("Expected element");
+            let entry = scores.entry(ancestor.id()).or_insert_with(|| {
+                candidates.push(ancestor);
+                tag_weight(element) + class_weight(element)
+            });
+
+            // Grandparents get half the credit
+            *entry += if level == 0 { score } else { score / 2.0 };
+        }
+    }
+
+    let mut best: Option<(NodeRef<Node>, f32)> = None;
+    for candidate in candidates {
+        let text_len = node_text(&candidate).len();
+        let score = scores.get(&candidate.id()).copied().unwrap_or_default()
+            * (1.0 - link_density(&candidate, text_len));
+
+        if best.map_or(true, |(_, best_score)| score > best_score) {
+            best = Some((candidate, score));
+        }
+    }
+
+    best.map(|(node, _)| node)
+        .filter(|node| node_text(node).len() >= MIN_CONTENT_LENGTH)
+}
+
+#[cfg(test)]
+mod test {
+    use super::main_content;
+    use crate::scraper::extract::node_text;
+    use crate::scraper::html::Html;
+
+    #[test]
+    fn test_main_content() {
+        let paragraph = "Lorem ipsum dolor sit amet, consectetur adipiscing elit, sed do \
+            eiusmod tempor incididunt ut labore et dolore magna aliqua, ut enim ad minim.";
+
+        let html = Html::parse(&format!(
+            r#"<html><body>
+                <div class="navbar"><a href="/">Home</a><a href="/about">About us, and more</a></div>
+                <div id="sidebar"><p>Subscribe to our newsletter, it's really quite good and has lots of news</p></div>
+                <div class="post-content">
+                    <h1>Title</h1>
+                    <p>{p}</p><p>{p}</p><p>{p}</p>
+                </div>
+                <div class="footer"><p>Copyright 2022, all rights reserved by the company</p></div>
+            </body></html>"#,
+            p = paragraph
+        ));
+
+        let content = main_content(&html).expect("Expected main content");
+        let text = node_text(&content);
+        assert!(text.starts_with("Title Lorem ipsum"));
+        assert!(!text.contains("newsletter"));
+        assert!(!text.contains("Copyright"));
+    }
+
+    #[test]
+    fn test_main_content_too_short() {
+        let html = Html::parse("<html><body><div><p>Not much to see here.</p></div></body></html>");
+        assert!(main_content(&html).is_none());
+    }
+}
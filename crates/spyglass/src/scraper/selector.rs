@@ -0,0 +1,207 @@
+use std::iter::Peekable;
+use std::str::Chars;
+
+use ego_tree::NodeRef;
+
+use crate::scraper::element::Node;
+
+/// A small subset of CSS selectors, enough for lens extraction rules.
+/// Supports type, `#id`, `.class`, `[attr]` & `[attr=value]` selectors joined
+/// by descendant (` `) or child (`>`) combinators.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Selector {
+    /// Compound selectors, left to right, w/ the combinator joining each one to
+    /// the one before it.
+    parts: Vec<(Combinator, Compound)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Combinator {
+    Descendant,
+    Child,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Compound {
+    tag: Option<String>,
+    id: Option<String>,
+    classes: Vec<String>,
+    attrs: Vec<(String, Option<String>)>,
+}
+
+impl Compound {
+    fn parse(input: &str) -> Option<Self> {
+        let mut compound = Compound::default();
+        let mut chars = input.chars().peekable();
+
+        let tag = read_ident(&mut chars);
+        if !tag.is_empty() {
+            compound.tag = Some(tag.to_lowercase());
+        } else if chars.peek() == Some(&'*') {
+            chars.next();
+        }
+
+        while let Some(ch) = chars.next() {
+            match ch {
+                '#' => compound.id = Some(read_ident(&mut chars)),
+                '.' => compound.classes.push(read_ident(&mut chars)),
+                '[' => {
+                    let attr: String = chars.by_ref().take_while(|ch| *ch != ']').collect();
+                    let attr = match attr.split_once('=') {
+                        Some((name, value)) => (
+                            name.trim().to_string(),
+                            Some(
+                                value
+                                    .trim()
+                                    .trim_matches(|c: char| c == '"' || c == '\'')
+                                    .to_string(),
+                            ),
+                        ),
+                        None => (attr.trim().to_string(), None),
+                    };
+                    compound.attrs.push(attr);
+                }
+                _ => return None,
+            }
+        }
+
+        Some(compound)
+    }
+
+    fn matches(&self, node: &NodeRef<Node>) -> bool {
+        let element = match node.value().as_element() {
+            Some(element) => element,
+            None => return false,
+        };
+
+        if let Some(tag) = &self.tag {
+            if !element.name().eq_ignore_ascii_case(tag) {
+                return false;
+            }
+        }
+
+        if let Some(id) = &self.id {
+            if element.id.as_deref() != Some(id.as_str()) {
+                return false;
+            }
+        }
+
+        if !self
+            .classes
+            .iter()
+            .all(|class| element.classes.iter().any(|c| &**c == class.as_str()))
+        {
+            return false;
+        }
+
+        self.attrs
+            .iter()
+            .all(|(name, value)| match element.attr(name) {
+                Some(attr) => value.as_ref().map_or(true, |value| attr == value),
+                None => false,
+            })
+    }
+}
+
+fn read_ident(chars: &mut Peekable<Chars>) -> String {
+    let mut ident = String::new();
+    while let Some(ch) = chars.peek() {
+        if ch.is_alphanumeric() || *ch == '-' || *ch == '_' {
+            ident.push(*ch);
+            chars.next();
+        } else {
+            break;
+        }
+    }
+    ident
+}
+
+impl Selector {
+    pub fn parse(selector: &str) -> Option<Self> {
+        let spaced = selector.replace('>', " > ");
+
+        let mut parts = Vec::new();
+        let mut combinator = Combinator::Descendant;
+        for token in spaced.split_whitespace() {
+            if token == ">" {
+                combinator = Combinator::Child;
+                continue;
+            }
+
+            parts.push((combinator, Compound::parse(token)?));
+            combinator = Combinator::Descendant;
+        }
+
+        if parts.is_empty() {
+            None
+        } else {
+            Some(Self { parts })
+        }
+    }
+
+    pub fn matches(&self, node: &NodeRef<Node>) -> bool {
+        self.matches_at(node, self.parts.len() - 1)
+    }
+
+    fn matches_at(&self, node: &NodeRef<Node>, idx: usize) -> bool {
+        let (combinator, compound) = &self.parts[idx];
+        if !compound.matches(node) {
+            return false;
+        }
+
+        if idx == 0 {
+            return true;
+        }
+
+        match combinator {
+            Combinator::Child => node
+                .parent()
+                .map_or(false, |parent| self.matches_at(&parent, idx - 1)),
+            Combinator::Descendant => node
+                .ancestors()
+                .any(|ancestor| self.matches_at(&ancestor, idx - 1)),
+        }
+    }
+
+    /// First node under `root` (in document order) that matches this selector.
+    pub fn select_first<'a>(&self, root: NodeRef<'a, Node>) -> Option<NodeRef<'a, Node>> {
+        root.descendants().find(|node| self.matches(node))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Selector;
+    use crate::scraper::html::Html;
+
+    #[test]
+    fn test_select_first() {
+        let html = Html::parse(
+            r#"<html><body>
+                <div id="product"><span class="price sale">$10</span></div>
+                <ul><li><span class="price">$20</span></li></ul>
+                <meta itemprop="author" content="Jane">
+            </body></html>"#,
+        );
+        let root = html.tree.root();
+
+        let text_of = |selector: &str| {
+            Selector::parse(selector)
+                .and_then(|selector| selector.select_first(root))
+                .map(|node| {
+                    node.descendants()
+                        .filter_map(|n| n.value().as_text().map(|t| t.to_string()))
+                        .collect::<String>()
+                })
+        };
+
+        assert_eq!(text_of("span.price"), Some("$10".to_string()));
+        assert_eq!(text_of("ul > li .price"), Some("$20".to_string()));
+        assert_eq!(text_of("#product > .sale"), Some("$10".to_string()));
+        assert_eq!(text_of("ul > .price"), None);
+
+        let meta = Selector::parse("meta[itemprop=author]")
+            .and_then(|selector| selector.select_first(root));
+        assert!(meta.is_some());
+    }
+}
@@ -0,0 +1,142 @@
+//! Ranks the documents the user keeps opening from search results higher.
+//! Clicks count for less as they get older, so what's useful now wins over
+//! what was useful months ago.
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use entities::models::search_click;
+use entities::schema::{DocFields, SearchDocument};
+use entities::sea_orm::ConnectionTrait;
+use tantivy::DocAddress;
+
+use super::{Score, PINNED_BOOST};
+
+/// Clicks lose half their weight every this many days.
+const HALF_LIFE_DAYS: f32 = 30.0;
+/// How much each (decayed) click adds, w/ diminishing returns.
+const CLICK_WEIGHT: f32 = 0.25;
+/// Most a document's score is multiplied by, however often it's opened.
+const MAX_BOOST: f32 = 2.0;
+/// Clicks older than this are ignored & pruned, they'd barely count anyway.
+pub const MAX_CLICK_AGE_DAYS: i64 = 365;
+
+/// Score multipliers for documents the user has opened.
+#[derive(Clone, Debug, Default)]
+pub struct ClickBoosts {
+    boosts: HashMap<String, f32>,
+}
+
+impl ClickBoosts {
+    pub async fn load<C: ConnectionTrait>(db: &C) -> Self {
+        let now = Utc::now();
+        match search_click::since(db, now - Duration::days(MAX_CLICK_AGE_DAYS)).await {
+            Ok(clicks) => Self::from_clicks(clicks, now),
+            Err(err) => {
+                log::error!("Unable to load search clicks: {}", err);
+                Self::default()
+            }
+        }
+    }
+
+    /// Boosts from (doc id, clicked @) pairs.
+    pub fn from_clicks(
+        clicks: impl IntoIterator<Item = (String, DateTime<Utc>)>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let mut weights: HashMap<String, f32> = HashMap::new();
+        for (doc_id, clicked_at) in clicks {
+            let age_days = (now - clicked_at).num_hours().max(0) as f32 / 24.0;
+            *weights.entry(doc_id).or_default() += 0.5f32.powf(age_days / HALF_LIFE_DAYS);
+        }
+
+        let boosts = weights
+            .into_iter()
+            .map(|(doc_id, weight)| {
+                let boost = 1.0 + CLICK_WEIGHT * weight.ln_1p();
+                (doc_id, boost.min(MAX_BOOST))
+            })
+            .collect();
+
+        Self { boosts }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.boosts.is_empty()
+    }
+
+    pub fn boost(&self, doc_id: &str) -> f32 {
+        self.boosts.get(doc_id).copied().unwrap_or(1.0)
+    }
+
+    /// Re-rank search results by their boosts, keeping the best `limit`.
+    /// Sections & past versions count the clicks of their document. Pinned
+    /// & filtered out (negative) results keep their scores.
+    pub fn rerank(
+        &self,
+        searcher: &tantivy::Searcher,
+        results: Vec<(Score, DocAddress)>,
+        limit: usize,
+    ) -> Vec<(Score, DocAddress)> {
+        let fields = DocFields::as_fields();
+        let mut reranked = results
+            .into_iter()
+            .map(|(score, addr)| {
+                if !(0.0..PINNED_BOOST).contains(&score) {
+                    return (score, addr);
+                }
+
+                let doc_id = searcher.doc(addr).ok().and_then(|doc| {
+                    [fields.parent_id, fields.version_of, fields.id]
+                        .into_iter()
+                        .find_map(|field| {
+                            doc.get_first(field)
+                                .and_then(|value| value.as_text())
+                                .map(|value| value.to_string())
+                        })
+                });
+                match doc_id {
+                    Some(doc_id) => (score * self.boost(&doc_id), addr),
+                    None => (score, addr),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        reranked.sort_by(|a, b| b.0.total_cmp(&a.0));
+        reranked.truncate(limit);
+        reranked
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{Duration, Utc};
+
+    use super::{ClickBoosts, MAX_BOOST};
+
+    #[test]
+    fn test_from_clicks() {
+        let now = Utc::now();
+        let clicks = |doc_id: &str, count: usize, days_ago: i64| {
+            vec![(doc_id.to_string(), now - Duration::days(days_ago)); count]
+        };
+
+        let boosts = ClickBoosts::from_clicks(
+            [
+                clicks("often", 10, 1),
+                clicks("once", 1, 1),
+                clicks("long-ago", 10, 300),
+                clicks("constantly", 1_000, 0),
+            ]
+            .concat(),
+            now,
+        );
+
+        assert_eq!(boosts.boost("never"), 1.0);
+        assert!(boosts.boost("once") > 1.0);
+        assert!(boosts.boost("often") > boosts.boost("once"));
+        // Old clicks barely count
+        assert!(boosts.boost("long-ago") < boosts.boost("once"));
+        assert_eq!(boosts.boost("constantly"), MAX_BOOST);
+    }
+}
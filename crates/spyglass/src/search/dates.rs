@@ -0,0 +1,620 @@
+//! Dates in `after:` & `before:` filters, e.g. `after:2024-03-01`,
+//! `before:"2 weeks ago"` or `after:"letzten Dienstag"`. Words are read in the
+//! user's `DateLocale` (& English), which also decides the order of numeric
+//! dates like `03/04/2024`.
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+use shared::config::DateLocale;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Unit {
+    Hour,
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Word {
+    Today,
+    Yesterday,
+    /// Marks a time ago, e.g. "2 weeks ago" or "vor 2 Wochen".
+    Ago,
+    /// Marks the previous day, week, etc, e.g. "last tuesday".
+    Last,
+    Number(u32),
+    Unit(Unit),
+    Weekday(Weekday),
+    /// 1 for January.
+    Month(u32),
+}
+
+/// Date words in a language.
+struct Vocabulary {
+    today: &'static [&'static str],
+    yesterday: &'static [&'static str],
+    ago: &'static [&'static str],
+    last: &'static [&'static str],
+    /// Words that don't change the meaning, e.g. "the" or "de".
+    filler: &'static [&'static str],
+    units: &'static [(&'static str, Unit)],
+    /// Starting w/ Monday.
+    weekdays: [&'static [&'static str]; 7],
+    /// Starting w/ January.
+    months: [&'static [&'static str]; 12],
+}
+
+static ENGLISH: Vocabulary = Vocabulary {
+    today: &["today"],
+    yesterday: &["yesterday"],
+    ago: &["ago"],
+    last: &["last", "past", "previous"],
+    filler: &["the", "of", "on", "a", "an"],
+    units: &[
+        ("hour", Unit::Hour),
+        ("hours", Unit::Hour),
+        ("day", Unit::Day),
+        ("days", Unit::Day),
+        ("week", Unit::Week),
+        ("weeks", Unit::Week),
+        ("month", Unit::Month),
+        ("months", Unit::Month),
+        ("year", Unit::Year),
+        ("years", Unit::Year),
+    ],
+    weekdays: [
+        &["monday", "mon"],
+        &["tuesday", "tue", "tues"],
+        &["wednesday", "wed"],
+        &["thursday", "thu", "thur", "thurs"],
+        &["friday", "fri"],
+        &["saturday", "sat"],
+        &["sunday", "sun"],
+    ],
+    months: [
+        &["january", "jan"],
+        &["february", "feb"],
+        &["march", "mar"],
+        &["april", "apr"],
+        &["may"],
+        &["june", "jun"],
+        &["july", "jul"],
+        &["august", "aug"],
+        &["september", "sep", "sept"],
+        &["october", "oct"],
+        &["november", "nov"],
+        &["december", "dec"],
+    ],
+};
+
+static GERMAN: Vocabulary = Vocabulary {
+    today: &["heute"],
+    yesterday: &["gestern"],
+    ago: &["vor"],
+    last: &[
+        "letzten",
+        "letzte",
+        "letzter",
+        "letztes",
+        "vergangenen",
+        "vergangene",
+        "vergangener",
+        "vergangenes",
+    ],
+    filler: &["am", "der", "den", "dem", "im"],
+    units: &[
+        ("stunde", Unit::Hour),
+        ("stunden", Unit::Hour),
+        ("tag", Unit::Day),
+        ("tage", Unit::Day),
+        ("tagen", Unit::Day),
+        ("woche", Unit::Week),
+        ("wochen", Unit::Week),
+        ("monat", Unit::Month),
+        ("monate", Unit::Month),
+        ("monaten", Unit::Month),
+        ("jahr", Unit::Year),
+        ("jahre", Unit::Year),
+        ("jahren", Unit::Year),
+    ],
+    weekdays: [
+        &["montag", "mo"],
+        &["dienstag", "di"],
+        &["mittwoch", "mi"],
+        &["donnerstag", "do"],
+        &["freitag", "fr"],
+        &["samstag", "sonnabend", "sa"],
+        &["sonntag", "so"],
+    ],
+    months: [
+        &["januar", "jänner"],
+        &["februar"],
+        &["märz", "maerz"],
+        &["april"],
+        &["mai"],
+        &["juni"],
+        &["juli"],
+        &["august"],
+        &["september"],
+        &["oktober", "okt"],
+        &["november"],
+        &["dezember", "dez"],
+    ],
+};
+
+static FRENCH: Vocabulary = Vocabulary {
+    today: &["aujourd'hui", "aujourd’hui"],
+    yesterday: &["hier"],
+    // "il y a 2 semaines"
+    ago: &["a"],
+    last: &[
+        "dernier",
+        "dernière",
+        "derniere",
+        "passé",
+        "passe",
+        "passée",
+        "passee",
+    ],
+    filler: &["il", "y", "le", "la", "de", "du"],
+    units: &[
+        ("heure", Unit::Hour),
+        ("heures", Unit::Hour),
+        ("jour", Unit::Day),
+        ("jours", Unit::Day),
+        ("semaine", Unit::Week),
+        ("semaines", Unit::Week),
+        ("mois", Unit::Month),
+        ("an", Unit::Year),
+        ("ans", Unit::Year),
+        ("année", Unit::Year),
+        ("années", Unit::Year),
+        ("annee", Unit::Year),
+        ("annees", Unit::Year),
+    ],
+    weekdays: [
+        &["lundi"],
+        &["mardi"],
+        &["mercredi"],
+        &["jeudi"],
+        &["vendredi"],
+        &["samedi"],
+        &["dimanche"],
+    ],
+    months: [
+        &["janvier", "janv"],
+        &["février", "fevrier", "févr", "fevr"],
+        &["mars"],
+        &["avril", "avr"],
+        &["mai"],
+        &["juin"],
+        &["juillet", "juil"],
+        &["août", "aout"],
+        &["septembre"],
+        &["octobre"],
+        &["novembre"],
+        &["décembre", "decembre", "déc"],
+    ],
+};
+
+static SPANISH: Vocabulary = Vocabulary {
+    today: &["hoy"],
+    yesterday: &["ayer"],
+    ago: &["hace"],
+    last: &[
+        "pasado", "pasada", "último", "ultimo", "última", "ultima", "anterior",
+    ],
+    filler: &["el", "la", "de", "del"],
+    units: &[
+        ("hora", Unit::Hour),
+        ("horas", Unit::Hour),
+        ("día", Unit::Day),
+        ("días", Unit::Day),
+        ("dia", Unit::Day),
+        ("dias", Unit::Day),
+        ("semana", Unit::Week),
+        ("semanas", Unit::Week),
+        ("mes", Unit::Month),
+        ("meses", Unit::Month),
+        ("año", Unit::Year),
+        ("años", Unit::Year),
+    ],
+    weekdays: [
+        &["lunes"],
+        &["martes"],
+        &["miércoles", "miercoles"],
+        &["jueves"],
+        &["viernes"],
+        &["sábado", "sabado"],
+        &["domingo"],
+    ],
+    months: [
+        &["enero"],
+        &["febrero"],
+        &["marzo"],
+        &["abril"],
+        &["mayo"],
+        &["junio"],
+        &["julio"],
+        &["agosto"],
+        &["septiembre", "setiembre"],
+        &["octubre"],
+        &["noviembre"],
+        &["diciembre"],
+    ],
+};
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Mon,
+    Weekday::Tue,
+    Weekday::Wed,
+    Weekday::Thu,
+    Weekday::Fri,
+    Weekday::Sat,
+    Weekday::Sun,
+];
+
+/// Vocabularies to read dates w/, the locale's own first so its words win
+/// over English ones, e.g. "a" in "il y a".
+fn vocabularies(locale: DateLocale) -> Vec<&'static Vocabulary> {
+    match locale {
+        DateLocale::En | DateLocale::EnGb => vec![&ENGLISH],
+        DateLocale::De => vec![&GERMAN, &ENGLISH],
+        DateLocale::Fr => vec![&FRENCH, &ENGLISH],
+        DateLocale::Es => vec![&SPANISH, &ENGLISH],
+    }
+}
+
+/// Numbers, including ordinals like "4th", "4." or "1er".
+fn parse_number(word: &str) -> Option<u32> {
+    let split = word
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(word.len());
+    let (number, suffix) = word.split_at(split);
+    match suffix {
+        "" | "." | "st" | "nd" | "rd" | "th" | "er" | "re" | "e" | "º" => number.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Reads a word, `None` if it's filler.
+fn read_word(word: &str, vocabularies: &[&Vocabulary]) -> Result<Option<Word>, ()> {
+    if let Some(number) = parse_number(word) {
+        return Ok(Some(Word::Number(number)));
+    }
+
+    for vocabulary in vocabularies {
+        let is = |words: &[&str]| words.contains(&word);
+        if is(vocabulary.today) {
+            return Ok(Some(Word::Today));
+        } else if is(vocabulary.yesterday) {
+            return Ok(Some(Word::Yesterday));
+        } else if is(vocabulary.ago) {
+            return Ok(Some(Word::Ago));
+        } else if is(vocabulary.last) {
+            return Ok(Some(Word::Last));
+        } else if is(vocabulary.filler) {
+            return Ok(None);
+        }
+
+        if let Some((_, unit)) = vocabulary.units.iter().find(|(name, _)| *name == word) {
+            return Ok(Some(Word::Unit(*unit)));
+        }
+        if let Some(idx) = vocabulary.weekdays.iter().position(|names| is(names)) {
+            return Ok(Some(Word::Weekday(WEEKDAYS[idx])));
+        }
+        if let Some(idx) = vocabulary.months.iter().position(|names| is(names)) {
+            return Ok(Some(Word::Month(idx as u32 + 1)));
+        }
+    }
+
+    Err(())
+}
+
+fn to_utc<Tz: TimeZone>(timezone: &Tz, datetime: &NaiveDateTime) -> Option<DateTime<Utc>> {
+    timezone
+        .from_local_datetime(datetime)
+        .earliest()
+        .map(|datetime| datetime.with_timezone(&Utc))
+}
+
+fn start_of_day<Tz: TimeZone>(timezone: &Tz, date: NaiveDate) -> Option<DateTime<Utc>> {
+    to_utc(timezone, &date.and_hms_opt(0, 0, 0)?)
+}
+
+/// Same day `months` months earlier, or the end of the month if it's shorter,
+/// e.g. a month before March 31st is the end of February.
+fn sub_months(date: NaiveDate, months: u32) -> Option<NaiveDate> {
+    let total = (date.year() as i64 * 12 + date.month0() as i64).checked_sub(months.into())?;
+    let year = i32::try_from(total.div_euclid(12)).ok()?;
+    let month = total.rem_euclid(12) as u32 + 1;
+    (1..=date.day())
+        .rev()
+        .find_map(|day| NaiveDate::from_ymd_opt(year, month, day))
+}
+
+fn sub_units(datetime: &NaiveDateTime, unit: Unit, count: u32) -> Option<NaiveDateTime> {
+    let count = i64::from(count);
+    match unit {
+        Unit::Hour => datetime.checked_sub_signed(Duration::hours(count)),
+        Unit::Day => datetime.checked_sub_signed(Duration::days(count)),
+        Unit::Week => datetime.checked_sub_signed(Duration::weeks(count)),
+        Unit::Month => {
+            Some(sub_months(datetime.date(), u32::try_from(count).ok()?)?.and_time(datetime.time()))
+        }
+        Unit::Year => Some(
+            sub_months(datetime.date(), u32::try_from(count * 12).ok()?)?.and_time(datetime.time()),
+        ),
+    }
+}
+
+/// First day of the week `date` is in, Sunday in the US & Monday elsewhere.
+fn start_of_week(date: NaiveDate, locale: DateLocale) -> NaiveDate {
+    let days = match locale {
+        DateLocale::En => date.weekday().num_days_from_sunday(),
+        _ => date.weekday().num_days_from_monday(),
+    };
+    date - Duration::days(days.into())
+}
+
+/// Four digit years as is, two digit ones in this century.
+fn full_year(year: u32) -> Option<i32> {
+    match year {
+        0..=99 => Some(2000 + year as i32),
+        1000..=9999 => Some(year as i32),
+        _ => None,
+    }
+}
+
+/// Dates written w/ numbers, e.g. `2024-03-04` or `04.03.2024`.
+fn parse_numeric(value: &str, locale: DateLocale) -> Option<NaiveDate> {
+    let parts = value
+        .split(|c| c == '-' || c == '/' || c == '.')
+        .collect::<Vec<_>>();
+    if parts.len() != 3
+        || parts
+            .iter()
+            .any(|part| part.is_empty() || !part.chars().all(|c| c.is_ascii_digit()))
+    {
+        return None;
+    }
+
+    let numbers = parts
+        .iter()
+        .map(|part| part.parse::<u32>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    let (year, month, day) = if parts[0].len() == 4 {
+        (numbers[0], numbers[1], numbers[2])
+    } else if locale == DateLocale::En {
+        (numbers[2], numbers[0], numbers[1])
+    } else {
+        (numbers[2], numbers[1], numbers[0])
+    };
+
+    NaiveDate::from_ymd_opt(full_year(year)?, month, day)
+}
+
+/// Date w/ the words read from a filter, relative to `now`.
+fn resolve<Tz: TimeZone>(
+    words: &[Word],
+    locale: DateLocale,
+    now: &DateTime<Tz>,
+) -> Option<DateTime<Utc>> {
+    let timezone = now.timezone();
+    let local = now.naive_local();
+    let today = local.date();
+
+    let has = |word: Word| words.contains(&word);
+    let numbers = words
+        .iter()
+        .filter_map(|word| match word {
+            Word::Number(number) => Some(*number),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let unit = words.iter().find_map(|word| match word {
+        Word::Unit(unit) => Some(*unit),
+        _ => None,
+    });
+    let weekday = words.iter().find_map(|word| match word {
+        Word::Weekday(weekday) => Some(*weekday),
+        _ => None,
+    });
+    let month = words.iter().find_map(|word| match word {
+        Word::Month(month) => Some(*month),
+        _ => None,
+    });
+
+    match words {
+        [Word::Today] => return start_of_day(&timezone, today),
+        [Word::Yesterday] => return start_of_day(&timezone, today.pred_opt()?),
+        // A year on its own, e.g. after:2023
+        [Word::Number(year)] if *year >= 1000 => {
+            return start_of_day(&timezone, NaiveDate::from_ymd_opt(full_year(*year)?, 1, 1)?)
+        }
+        _ => {}
+    }
+
+    // "2 weeks ago", "a month ago"
+    if has(Word::Ago) {
+        if words.len() != 2 + numbers.len() || numbers.len() > 1 {
+            return None;
+        }
+        let count = numbers.first().copied().unwrap_or(1);
+        return to_utc(&timezone, &sub_units(&local, unit?, count)?);
+    }
+
+    // "last tuesday" or just "tuesday", the most recent one before today
+    if let Some(weekday) = weekday {
+        if words.len() != 1 + has(Word::Last) as usize {
+            return None;
+        }
+        let days_back =
+            (7 + today.weekday().num_days_from_monday() - weekday.num_days_from_monday()) % 7;
+        let days_back = if days_back == 0 { 7 } else { days_back };
+        return start_of_day(&timezone, today - Duration::days(days_back.into()));
+    }
+
+    // "last week", the start of the previous one
+    if has(Word::Last) {
+        if words.len() != 2 {
+            return None;
+        }
+        let start = match unit? {
+            Unit::Hour => return to_utc(&timezone, &sub_units(&local, Unit::Hour, 1)?),
+            Unit::Day => today.pred_opt()?,
+            Unit::Week => start_of_week(today, locale) - Duration::weeks(1),
+            Unit::Month => sub_months(today.with_day(1)?, 1)?,
+            Unit::Year => NaiveDate::from_ymd_opt(today.year() - 1, 1, 1)?,
+        };
+        return start_of_day(&timezone, start);
+    }
+
+    // "march 4", "4 mars 2024" or "march 2024"
+    let month = month?;
+    if words.len() != 1 + numbers.len() {
+        return None;
+    }
+    let (day, year) = match numbers.as_slice() {
+        [] => (1, None),
+        [year] if *year > 31 => (1, Some(*year)),
+        [day] => (*day, None),
+        [year, day] if *year > 31 => (*day, Some(*year)),
+        [day, year] => (*day, Some(*year)),
+        _ => return None,
+    };
+    let date = match year {
+        Some(year) => NaiveDate::from_ymd_opt(full_year(year)?, month, day)?,
+        // The most recent one, e.g. "december 24" in January is last year's
+        None => {
+            let date = NaiveDate::from_ymd_opt(today.year(), month, day)?;
+            if date > today {
+                NaiveDate::from_ymd_opt(today.year() - 1, month, day)?
+            } else {
+                date
+            }
+        }
+    };
+
+    start_of_day(&timezone, date)
+}
+
+/// Parses the value of an `after:` or `before:` filter into the time it refers
+/// to: the start of the day (in `now`'s time zone) for dates & days, or the
+/// exact time for values like "3 hours ago".
+pub fn parse_date<Tz: TimeZone>(
+    value: &str,
+    locale: DateLocale,
+    now: &DateTime<Tz>,
+) -> Option<DateTime<Utc>> {
+    let value = value.trim().to_lowercase();
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(&value.to_uppercase()) {
+        return Some(timestamp.with_timezone(&Utc));
+    }
+    if let Some(date) = parse_numeric(&value, locale) {
+        return start_of_day(&now.timezone(), date);
+    }
+
+    let vocabularies = vocabularies(locale);
+    let words = value
+        .replace(',', " ")
+        .split_whitespace()
+        // Elided articles, e.g. "l'année dernière"
+        .map(|word| word.strip_prefix("l'").unwrap_or(word))
+        .map(|word| read_word(word, &vocabularies))
+        .collect::<Result<Vec<_>, _>>()
+        .ok()?
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>();
+
+    resolve(&words, locale, now)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::{DateTime, TimeZone, Utc};
+    use shared::config::DateLocale;
+
+    use super::parse_date;
+
+    /// Friday, October 16th 2026 @ 15:30 UTC
+    fn now() -> DateTime<Utc> {
+        Utc.ymd(2026, 10, 16).and_hms(15, 30, 0)
+    }
+
+    fn date(value: &str, locale: DateLocale) -> Option<DateTime<Utc>> {
+        parse_date(value, locale, &now())
+    }
+
+    fn day(year: i32, month: u32, day: u32) -> Option<DateTime<Utc>> {
+        Some(Utc.ymd(year, month, day).and_hms(0, 0, 0))
+    }
+
+    #[test]
+    fn test_absolute_dates() {
+        assert_eq!(date("2024-03-04", DateLocale::En), day(2024, 3, 4));
+        assert_eq!(
+            date("2024-03-04T10:00:00Z", DateLocale::En),
+            Some(Utc.ymd(2024, 3, 4).and_hms(10, 0, 0))
+        );
+        assert_eq!(date("2023", DateLocale::En), day(2023, 1, 1));
+
+        // Numeric dates are read in the locale's order
+        assert_eq!(date("03/04/2024", DateLocale::En), day(2024, 3, 4));
+        assert_eq!(date("03/04/2024", DateLocale::EnGb), day(2024, 4, 3));
+        assert_eq!(date("04.03.24", DateLocale::De), day(2024, 3, 4));
+
+        assert_eq!(date("March 4th, 2024", DateLocale::En), day(2024, 3, 4));
+        assert_eq!(date("4 mars 2024", DateLocale::Fr), day(2024, 3, 4));
+        assert_eq!(date("4 de marzo de 2024", DateLocale::Es), day(2024, 3, 4));
+        assert_eq!(date("4. März", DateLocale::De), day(2026, 3, 4));
+        // The most recent december 24th
+        assert_eq!(date("dec 24", DateLocale::En), day(2025, 12, 24));
+        assert_eq!(date("september 2025", DateLocale::En), day(2025, 9, 1));
+    }
+
+    #[test]
+    fn test_relative_dates() {
+        assert_eq!(date("today", DateLocale::En), day(2026, 10, 16));
+        assert_eq!(date("yesterday", DateLocale::En), day(2026, 10, 15));
+        assert_eq!(date("gestern", DateLocale::De), day(2026, 10, 15));
+
+        assert_eq!(date("last tuesday", DateLocale::En), day(2026, 10, 13));
+        assert_eq!(date("friday", DateLocale::En), day(2026, 10, 9));
+        assert_eq!(date("letzten Dienstag", DateLocale::De), day(2026, 10, 13));
+        assert_eq!(date("mardi dernier", DateLocale::Fr), day(2026, 10, 13));
+        assert_eq!(date("el martes pasado", DateLocale::Es), day(2026, 10, 13));
+
+        let ago = |days: i64| Some(now() - chrono::Duration::days(days));
+        assert_eq!(date("2 weeks ago", DateLocale::En), ago(14));
+        assert_eq!(date("a week ago", DateLocale::En), ago(7));
+        assert_eq!(date("vor 2 Wochen", DateLocale::De), ago(14));
+        assert_eq!(date("il y a 2 semaines", DateLocale::Fr), ago(14));
+        assert_eq!(date("hace 2 semanas", DateLocale::Es), ago(14));
+        // English works in every locale
+        assert_eq!(date("2 weeks ago", DateLocale::Fr), ago(14));
+        assert_eq!(
+            date("3 months ago", DateLocale::En),
+            Some(Utc.ymd(2026, 7, 16).and_hms(15, 30, 0))
+        );
+
+        // Weeks start on Sunday in the US
+        assert_eq!(date("last week", DateLocale::En), day(2026, 10, 4));
+        assert_eq!(date("last week", DateLocale::EnGb), day(2026, 10, 5));
+        assert_eq!(date("letzten Monat", DateLocale::De), day(2026, 9, 1));
+        assert_eq!(date("last year", DateLocale::En), day(2025, 1, 1));
+        assert_eq!(date("l'année dernière", DateLocale::Fr), day(2025, 1, 1));
+    }
+
+    #[test]
+    fn test_invalid_dates() {
+        assert_eq!(date("", DateLocale::En), None);
+        assert_eq!(date("someday", DateLocale::En), None);
+        assert_eq!(date("february 30", DateLocale::En), None);
+        assert_eq!(date("2 weeks", DateLocale::En), None);
+        assert_eq!(date("vor 2 Wochen", DateLocale::En), None);
+        assert_eq!(date("13/13/2024", DateLocale::En), None);
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use entities::models::indexed_document;
+use entities::sea_orm::ConnectionTrait;
+use shared::config::{LensConfig, UsageDecay};
+use url::Url;
+
+/// Per-domain score multipliers that demote sources the user hasn't opened
+/// anything from in a while.
+#[derive(Clone, Debug, Default)]
+pub struct DomainDecay {
+    boosts: HashMap<String, f32>,
+}
+
+impl DomainDecay {
+    pub async fn load<C: ConnectionTrait>(
+        db: &C,
+        settings: &UsageDecay,
+        lenses: &[LensConfig],
+    ) -> Self {
+        if !settings.enabled || settings.half_life_days == 0 {
+            return Self::default();
+        }
+
+        let activity = match indexed_document::domain_activity(db).await {
+            Ok(activity) => activity,
+            Err(err) => {
+                log::error!("Unable to load domain activity: {}", err);
+                return Self::default();
+            }
+        };
+
+        let exempt = exempt_domains(lenses);
+        let now = Utc::now();
+        let boosts = activity
+            .into_iter()
+            .filter(|domain| !exempt.contains(&domain.domain))
+            .map(|domain| {
+                let last_active = domain.last_opened_at.unwrap_or(domain.first_indexed_at);
+                let boost = decayed_boost(settings, last_active, now);
+                (domain.domain, boost)
+            })
+            .collect();
+
+        Self { boosts }
+    }
+
+    pub fn boost(&self, domain: &str) -> f32 {
+        self.boosts.get(domain).copied().unwrap_or(1.0)
+    }
+
+    /// Rank results from `domain` higher, on top of any decay, e.g. the site
+    /// the user is searching from.
+    pub fn boost_domain(&mut self, domain: &str, boost: f32) {
+        let boosted = self.boost(domain) * boost;
+        self.boosts.insert(domain.to_string(), boosted);
+    }
+}
+
+fn decayed_boost(settings: &UsageDecay, last_active: DateTime<Utc>, now: DateTime<Utc>) -> f32 {
+    let idle_days = (now - last_active).num_hours().max(0) as f32 / 24.0;
+    let boost = 0.5f32.powf(idle_days / settings.half_life_days as f32);
+    boost.max(settings.min_boost)
+}
+
+/// Domains belonging to lenses that opted out of usage decay.
+fn exempt_domains(lenses: &[LensConfig]) -> Vec<String> {
+    let mut domains = Vec::new();
+    for lens in lenses.iter().filter(|lens| lens.disable_usage_decay) {
+        domains.extend(lens.domains.iter().cloned());
+        domains.extend(
+            lens.urls
+                .iter()
+                .filter_map(|prefix| Url::parse(prefix.trim_end_matches('$')).ok())
+                .filter_map(|url| url.host_str().map(|host| host.to_string())),
+        );
+    }
+
+    domains
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decayed_boost, exempt_domains, DomainDecay};
+    use chrono::{Duration, Utc};
+    use shared::config::{LensConfig, UsageDecay};
+
+    #[test]
+    fn test_decayed_boost() {
+        let settings = UsageDecay::default();
+        let now = Utc::now();
+
+        assert_eq!(decayed_boost(&settings, now, now), 1.0);
+        let boost = decayed_boost(&settings, now - Duration::days(45), now);
+        assert!((boost - 0.707).abs() < 0.01);
+        assert_eq!(
+            decayed_boost(&settings, now - Duration::days(365), now),
+            settings.min_boost
+        );
+    }
+
+    #[test]
+    fn test_boost_domain() {
+        let mut decay = DomainDecay::default();
+        decay.boosts.insert("example.com".into(), 0.5);
+        decay.boost_domain("example.com", 2.0);
+        decay.boost_domain("docs.rs", 2.0);
+
+        assert_eq!(decay.boost("example.com"), 1.0);
+        assert_eq!(decay.boost("docs.rs"), 2.0);
+        assert_eq!(decay.boost("news.ycombinator.com"), 1.0);
+    }
+
+    #[test]
+    fn test_exempt_domains() {
+        let lenses = vec![
+            LensConfig {
+                domains: vec!["docs.rs".into()],
+                urls: vec!["https://doc.rust-lang.org/std/$".into()],
+                disable_usage_decay: true,
+                ..Default::default()
+            },
+            LensConfig {
+                domains: vec!["news.ycombinator.com".into()],
+                ..Default::default()
+            },
+        ];
+
+        assert_eq!(
+            exempt_domains(&lenses),
+            vec!["docs.rs".to_string(), "doc.rust-lang.org".to_string()]
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use regex::Regex;
+use shared::config::DeepLinkRule;
+use url::Url;
+
+/// Source files we'd rather open in an editor than the default app.
+const CODE_EXTENSIONS: [&str; 28] = [
+    "c", "cpp", "cs", "css", "go", "h", "hpp", "html", "java", "js", "json", "jsx", "kt", "lua",
+    "php", "py", "rb", "rs", "scss", "sh", "sql", "swift", "toml", "ts", "tsx", "vue", "yaml",
+    "yml",
+];
+
+/// Whether the folders notes are in belong to an Obsidian vault, so results
+/// from the same folder only look for the vault once per search.
+#[derive(Debug, Default)]
+pub struct VaultCache {
+    in_vault: HashMap<PathBuf, bool>,
+}
+
+impl VaultCache {
+    /// Whether `path` is in a folder that is (or is inside) an Obsidian vault.
+    fn is_in_vault(&mut self, path: &Path) -> bool {
+        let folder = match path.parent() {
+            Some(folder) => folder,
+            None => return false,
+        };
+
+        *self
+            .in_vault
+            .entry(folder.to_path_buf())
+            .or_insert_with(|| folder.ancestors().any(|dir| dir.join(".obsidian").is_dir()))
+    }
+}
+
+/// The user's deep link rules w/ their patterns compiled, built once when the
+/// settings are loaded.
+#[derive(Debug, Default)]
+pub struct DeepLinks {
+    rules: Vec<(Regex, String)>,
+}
+
+impl DeepLinks {
+    pub fn new(rules: &[DeepLinkRule]) -> Self {
+        let rules = rules
+            .iter()
+            .filter_map(|rule| match Regex::new(&rule.pattern) {
+                Ok(regex) => Some((regex, rule.template.clone())),
+                Err(err) => {
+                    log::warn!("Invalid deep link pattern {}: {}", rule.pattern, err);
+                    None
+                }
+            })
+            .collect();
+
+        Self { rules }
+    }
+
+    fn from_rules(&self, url: &str) -> Option<String> {
+        self.rules.iter().find_map(|(regex, template)| {
+            let captures = regex.captures(url)?;
+            let mut app_url = String::new();
+            captures.expand(template, &mut app_url);
+            Some(app_url)
+        })
+    }
+
+    /// Convert the URL of a document into a URI that opens it in the right
+    /// native app (Obsidian, VS Code, Slack, etc.), if there is one. User
+    /// defined rules are checked before the built-in mappings.
+    pub fn app_url(&self, url: &str, vaults: &mut VaultCache) -> Option<String> {
+        if let Some(app_url) = self.from_rules(url) {
+            return Some(app_url);
+        }
+
+        let parsed = Url::parse(url).ok()?;
+        match parsed.scheme() {
+            "file" => from_file(&parsed, vaults),
+            "http" | "https" => from_web(&parsed),
+            _ => None,
+        }
+    }
+}
+
+fn from_file(url: &Url, vaults: &mut VaultCache) -> Option<String> {
+    let path = url.to_file_path().ok()?;
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+        .unwrap_or_default();
+
+    if ext == "md" && vaults.is_in_vault(&path) {
+        let path = path.to_string_lossy();
+        return Some(format!(
+            "obsidian://open?path={}",
+            utf8_percent_encode(&path, NON_ALPHANUMERIC)
+        ));
+    }
+
+    if CODE_EXTENSIONS.contains(&ext.as_str()) {
+        // URL paths are already percent-encoded w/ forward slashes on every platform.
+        return Some(format!("vscode://file{}", url.path()));
+    }
+
+    None
+}
+
+fn from_web(url: &Url) -> Option<String> {
+    match url.host_str()? {
+        // https://app.slack.com/client/<team>/<channel>
+        "app.slack.com" => {
+            let mut segments = url.path_segments()?;
+            if segments.next() != Some("client") {
+                return None;
+            }
+
+            let team = segments.next().filter(|team| !team.is_empty())?;
+            match segments.next().filter(|channel| !channel.is_empty()) {
+                Some(channel) => Some(format!("slack://channel?team={}&id={}", team, channel)),
+                None => Some(format!("slack://open?team={}", team)),
+            }
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{DeepLinks, VaultCache};
+    use shared::config::DeepLinkRule;
+
+    fn app_url(rules: &[DeepLinkRule], url: &str) -> Option<String> {
+        DeepLinks::new(rules).app_url(url, &mut VaultCache::default())
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        let rules = vec![
+            DeepLinkRule {
+                pattern: "(unclosed".into(),
+                template: "never://".into(),
+            },
+            DeepLinkRule {
+                pattern: r"^https://github\.com/(?P<repo>[^/]+/[^/]+)$".into(),
+                template: "x-github-client://openRepo/https://github.com/$repo".into(),
+            },
+        ];
+
+        assert_eq!(
+            app_url(&rules, "https://github.com/spyglass-search/spyglass"),
+            Some("x-github-client://openRepo/https://github.com/spyglass-search/spyglass".into())
+        );
+        assert_eq!(app_url(&rules, "https://example.com"), None);
+    }
+
+    #[test]
+    fn test_builtin() {
+        assert_eq!(
+            app_url(&[], "file:///home/user/code/main.rs"),
+            Some("vscode://file/home/user/code/main.rs".into())
+        );
+        assert_eq!(app_url(&[], "file:///home/user/notes.txt"), None);
+
+        assert_eq!(
+            app_url(&[], "https://app.slack.com/client/T0123/C0456"),
+            Some("slack://channel?team=T0123&id=C0456".into())
+        );
+        assert_eq!(app_url(&[], "api://mail/123"), None);
+    }
+
+    #[test]
+    fn test_obsidian() {
+        let vault = std::env::temp_dir().join(format!("vault-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(vault.join(".obsidian")).expect("Unable to create vault");
+
+        let links = DeepLinks::default();
+        let mut vaults = VaultCache::default();
+        let url = url::Url::from_file_path(vault.join("note.md")).expect("Invalid path");
+        let link = links
+            .app_url(url.as_str(), &mut vaults)
+            .expect("Expected obsidian link");
+        assert!(link.starts_with("obsidian://open?path="));
+
+        // The vault is only looked for once per folder
+        let _ = std::fs::remove_dir_all(&vault);
+        let url = url::Url::from_file_path(vault.join("other.md")).expect("Invalid path");
+        assert!(links.app_url(url.as_str(), &mut vaults).is_some());
+        assert!(links
+            .app_url(url.as_str(), &mut VaultCache::default())
+            .is_none());
+    }
+}
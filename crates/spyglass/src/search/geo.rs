@@ -0,0 +1,195 @@
+/// Documents within this distance of a `near:` place match.
+const NEAR_RADIUS_KM: f64 = 50.0;
+const KM_PER_DEGREE: f64 = 111.0;
+
+/// Coordinates are indexed as numbers, which can't be negative, so they're
+/// shifted into positive ranges.
+pub const LAT_FIELD: &str = "geo_lat";
+pub const LNG_FIELD: &str = "geo_lng";
+const LAT_OFFSET: f64 = 90.0;
+const LNG_OFFSET: f64 = 180.0;
+
+/// Small offline gazetteer used to geocode `near:` filters & location text.
+/// (name, latitude, longitude)
+const PLACES: [(&str, f64, f64); 60] = [
+    ("amsterdam", 52.3676, 4.9041),
+    ("athens", 37.9838, 23.7275),
+    ("atlanta", 33.7490, -84.3880),
+    ("austin", 30.2672, -97.7431),
+    ("bangkok", 13.7563, 100.5018),
+    ("barcelona", 41.3874, 2.1686),
+    ("beijing", 39.9042, 116.4074),
+    ("berlin", 52.5200, 13.4050),
+    ("boston", 42.3601, -71.0589),
+    ("brussels", 50.8503, 4.3517),
+    ("buenos aires", -34.6037, -58.3816),
+    ("cairo", 30.0444, 31.2357),
+    ("cape town", -33.9249, 18.4241),
+    ("chicago", 41.8781, -87.6298),
+    ("copenhagen", 55.6761, 12.5683),
+    ("dallas", 32.7767, -96.7970),
+    ("delhi", 28.7041, 77.1025),
+    ("denver", 39.7392, -104.9903),
+    ("dubai", 25.2048, 55.2708),
+    ("dublin", 53.3498, -6.2603),
+    ("edinburgh", 55.9533, -3.1883),
+    ("helsinki", 60.1699, 24.9384),
+    ("hong kong", 22.3193, 114.1694),
+    ("honolulu", 21.3069, -157.8583),
+    ("istanbul", 41.0082, 28.9784),
+    ("jakarta", -6.2088, 106.8456),
+    ("lagos", 6.5244, 3.3792),
+    ("las vegas", 36.1699, -115.1398),
+    ("lisbon", 38.7223, -9.1393),
+    ("london", 51.5072, -0.1276),
+    ("los angeles", 34.0522, -118.2437),
+    ("madrid", 40.4168, -3.7038),
+    ("melbourne", -37.8136, 144.9631),
+    ("mexico city", 19.4326, -99.1332),
+    ("miami", 25.7617, -80.1918),
+    ("milan", 45.4642, 9.1900),
+    ("montreal", 45.5017, -73.5673),
+    ("moscow", 55.7558, 37.6173),
+    ("mumbai", 19.0760, 72.8777),
+    ("munich", 48.1351, 11.5820),
+    ("nairobi", -1.2921, 36.8219),
+    ("new york", 40.7128, -74.0060),
+    ("oslo", 59.9139, 10.7522),
+    ("paris", 48.8566, 2.3522),
+    ("portland", 45.5152, -122.6784),
+    ("prague", 50.0755, 14.4378),
+    ("rome", 41.9028, 12.4964),
+    ("san francisco", 37.7749, -122.4194),
+    ("santiago", -33.4489, -70.6693),
+    ("sao paulo", -23.5558, -46.6396),
+    ("seattle", 47.6062, -122.3321),
+    ("seoul", 37.5665, 126.9780),
+    ("shanghai", 31.2304, 121.4737),
+    ("singapore", 1.3521, 103.8198),
+    ("stockholm", 59.3293, 18.0686),
+    ("sydney", -33.8688, 151.2093),
+    ("tokyo", 35.6762, 139.6503),
+    ("toronto", 43.6532, -79.3832),
+    ("vancouver", 49.2827, -123.1207),
+    ("vienna", 48.2082, 16.3738),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GeoPoint {
+    pub lat: f64,
+    pub lng: f64,
+}
+
+impl GeoPoint {
+    pub fn new(lat: f64, lng: f64) -> Option<Self> {
+        if (-90.0..=90.0).contains(&lat) && (-180.0..=180.0).contains(&lng) {
+            Some(Self { lat, lng })
+        } else {
+            None
+        }
+    }
+
+    /// Numeric fields to index for this point.
+    pub fn as_numbers(&self) -> Vec<(String, f64)> {
+        vec![
+            (LAT_FIELD.to_string(), self.lat + LAT_OFFSET),
+            (LNG_FIELD.to_string(), self.lng + LNG_OFFSET),
+        ]
+    }
+
+    /// Indexed (min, max) latitude & longitude of the box around this point
+    /// that `near:` filters match.
+    pub fn near_bounds(&self) -> ((f64, f64), (f64, f64)) {
+        let lat_delta = NEAR_RADIUS_KM / KM_PER_DEGREE;
+        // Degrees of longitude get narrower towards the poles
+        let lng_delta = NEAR_RADIUS_KM / (KM_PER_DEGREE * self.lat.to_radians().cos().max(0.01));
+
+        (
+            (
+                (self.lat - lat_delta).max(-90.0) + LAT_OFFSET,
+                (self.lat + lat_delta).min(90.0) + LAT_OFFSET,
+            ),
+            (
+                (self.lng - lng_delta).max(-180.0) + LNG_OFFSET,
+                (self.lng + lng_delta).min(180.0) + LNG_OFFSET,
+            ),
+        )
+    }
+}
+
+/// Lowercase `text` w/ punctuation & separators replaced by single spaces.
+fn normalize(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+/// Geocode a `near:` value, either a place name (`new_york`, `san-francisco`)
+/// or a `lat,long` pair.
+pub fn geocode(value: &str) -> Option<GeoPoint> {
+    if let Some((lat, lng)) = value.split_once(',') {
+        if let (Ok(lat), Ok(lng)) = (lat.trim().parse::<f64>(), lng.trim().parse::<f64>()) {
+            return GeoPoint::new(lat, lng);
+        }
+    }
+
+    let name = normalize(value);
+    PLACES
+        .iter()
+        .find(|(place, _, _)| *place == name)
+        .and_then(|(_, lat, lng)| GeoPoint::new(*lat, *lng))
+}
+
+/// Find the place mentioned in free form location text, e.g. the location of
+/// a calendar event ("Pier 39, San Francisco, CA"). Longer names win so "new
+/// york" isn't mistaken for "york".
+pub fn locate(text: &str) -> Option<(&'static str, GeoPoint)> {
+    let text = format!(" {} ", normalize(text));
+    PLACES
+        .iter()
+        .filter(|(place, _, _)| text.contains(&format!(" {} ", place)))
+        .max_by_key(|(place, _, _)| place.len())
+        .and_then(|(place, lat, lng)| GeoPoint::new(*lat, *lng).map(|point| (*place, point)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{geocode, locate, GeoPoint, LAT_OFFSET, LNG_OFFSET};
+
+    #[test]
+    fn test_geocode() {
+        assert_eq!(geocode("paris"), GeoPoint::new(48.8566, 2.3522));
+        assert_eq!(geocode("New_York"), GeoPoint::new(40.7128, -74.0060));
+        assert_eq!(geocode("san-francisco"), GeoPoint::new(37.7749, -122.4194));
+        assert_eq!(geocode("-33.86,151.20"), GeoPoint::new(-33.86, 151.20));
+        assert_eq!(geocode("91,0"), None);
+        assert_eq!(geocode("atlantis"), None);
+    }
+
+    #[test]
+    fn test_locate() {
+        let (place, _) = locate("Pier 39, San Francisco, CA 94133").unwrap();
+        assert_eq!(place, "san francisco");
+        assert!(locate("Conference room B").is_none());
+    }
+
+    #[test]
+    fn test_near_bounds() {
+        let paris = geocode("paris").unwrap();
+        let versailles = GeoPoint::new(48.8049, 2.1204).unwrap();
+        let london = geocode("london").unwrap();
+
+        let ((min_lat, max_lat), (min_lng, max_lng)) = paris.near_bounds();
+        let contains = |point: GeoPoint| {
+            let lat = point.lat + LAT_OFFSET;
+            let lng = point.lng + LNG_OFFSET;
+            lat >= min_lat && lat <= max_lat && lng >= min_lng && lng <= max_lng
+        };
+
+        assert!(contains(paris));
+        assert!(contains(versailles));
+        assert!(!contains(london));
+    }
+}
@@ -0,0 +1,240 @@
+use entities::schema::{DocFields, TOKENIZER_CJK, TOKENIZER_DE, TOKENIZER_ES, TOKENIZER_FR};
+use tantivy::schema::{Document, Field};
+use tantivy::tokenizer::{
+    BoxTokenStream, Language, LowerCaser, RemoveLongFilter, SimpleTokenizer, Stemmer, TextAnalyzer,
+    Token, TokenStream, Tokenizer, TokenizerManager,
+};
+use whatlang::Lang;
+
+/// Only the start of a document is used to detect its language, which is
+/// plenty & keeps indexing large documents fast.
+const DETECT_CHARS: usize = 2_000;
+
+/// Detect the language of `text`, if it can be done reliably.
+pub fn detect(text: &str) -> Option<Lang> {
+    let text = match text.char_indices().nth(DETECT_CHARS) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    };
+
+    whatlang::detect(text)
+        .filter(|info| info.is_reliable())
+        .map(|info| info.lang())
+}
+
+/// ISO 639-3 code for a `lang:` filter value, either a code (`deu`) or the
+/// English name of the language (`german`).
+pub fn lang_code(value: &str) -> Option<&'static str> {
+    let value = value.trim().to_lowercase();
+    Lang::from_code(&value)
+        .or_else(|| {
+            Lang::all()
+                .iter()
+                .find(|lang| lang.eng_name().eq_ignore_ascii_case(&value))
+                .copied()
+        })
+        .map(|lang| lang.code())
+}
+
+/// Field the title & content of a document in `lang` are tokenized into, if
+/// the default tokenizer isn't good enough for it.
+fn language_field(fields: &DocFields, lang: Lang) -> Option<Field> {
+    match lang {
+        Lang::Deu => Some(fields.content_de),
+        Lang::Spa => Some(fields.content_es),
+        Lang::Fra => Some(fields.content_fr),
+        Lang::Cmn | Lang::Jpn | Lang::Kor => Some(fields.content_cjk),
+        _ => None,
+    }
+}
+
+/// Record the language of a document & index `texts` (its title, content,
+/// etc.) w/ the tokenizer for that language.
+pub fn add_language_fields(doc: &mut Document, lang: Option<Lang>, texts: &[&str]) {
+    let lang = match lang {
+        Some(lang) => lang,
+        None => return,
+    };
+
+    let fields = DocFields::as_fields();
+    doc.add_text(fields.lang, lang.code());
+    if let Some(field) = language_field(&fields, lang) {
+        for text in texts {
+            doc.add_text(field, text);
+        }
+    }
+}
+
+/// Register the tokenizers used by the language specific content fields.
+pub fn register_tokenizers(tokenizers: &TokenizerManager) {
+    let stemmed = [
+        (TOKENIZER_DE, Language::German),
+        (TOKENIZER_ES, Language::Spanish),
+        (TOKENIZER_FR, Language::French),
+    ];
+    for (name, language) in stemmed {
+        tokenizers.register(
+            name,
+            TextAnalyzer::from(SimpleTokenizer)
+                .filter(RemoveLongFilter::limit(40))
+                .filter(LowerCaser)
+                .filter(Stemmer::new(language)),
+        );
+    }
+
+    tokenizers.register(
+        TOKENIZER_CJK,
+        TextAnalyzer::from(CjkTokenizer)
+            .filter(RemoveLongFilter::limit(40))
+            .filter(LowerCaser),
+    );
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c,
+        // CJK ideographs
+        '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{20000}'..='\u{2A6DF}'
+        // Hiragana & katakana
+        | '\u{3040}'..='\u{30FF}'
+        | '\u{31F0}'..='\u{31FF}'
+        // Hangul
+        | '\u{1100}'..='\u{11FF}'
+        | '\u{3130}'..='\u{318F}'
+        | '\u{AC00}'..='\u{D7AF}')
+}
+
+/// Chinese, Japanese & Korean text doesn't separate words w/ spaces, so runs
+/// of CJK characters are split into overlapping pairs (bigrams), which finds
+/// most words w/o needing a dictionary. Other text is split into words like
+/// the default tokenizer.
+#[derive(Clone)]
+pub struct CjkTokenizer;
+
+pub struct CjkTokenStream {
+    tokens: Vec<Token>,
+    current: usize,
+}
+
+impl Tokenizer for CjkTokenizer {
+    fn token_stream<'a>(&self, text: &'a str) -> BoxTokenStream<'a> {
+        BoxTokenStream::from(CjkTokenStream {
+            tokens: cjk_tokens(text),
+            current: 0,
+        })
+    }
+}
+
+impl TokenStream for CjkTokenStream {
+    fn advance(&mut self) -> bool {
+        if self.current < self.tokens.len() {
+            self.current += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn token(&self) -> &Token {
+        &self.tokens[self.current - 1]
+    }
+
+    fn token_mut(&mut self) -> &mut Token {
+        &mut self.tokens[self.current - 1]
+    }
+}
+
+fn cjk_tokens(text: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let push = |tokens: &mut Vec<Token>, from: usize, to: usize| {
+        let position = tokens.len();
+        tokens.push(Token {
+            offset_from: from,
+            offset_to: to,
+            position,
+            text: text[from..to].to_string(),
+            position_length: 1,
+        });
+    };
+
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let end_of = |idx: usize| {
+        chars
+            .get(idx)
+            .map(|(offset, _)| *offset)
+            .unwrap_or(text.len())
+    };
+
+    let mut idx = 0;
+    while idx < chars.len() {
+        let (start, c) = chars[idx];
+        if is_cjk(c) {
+            let mut run_end = idx;
+            while run_end < chars.len() && is_cjk(chars[run_end].1) {
+                run_end += 1;
+            }
+
+            if run_end - idx == 1 {
+                push(&mut tokens, start, end_of(idx + 1));
+            } else {
+                for pair in idx..run_end - 1 {
+                    push(&mut tokens, chars[pair].0, end_of(pair + 2));
+                }
+            }
+            idx = run_end;
+        } else if c.is_alphanumeric() {
+            let mut word_end = idx;
+            while word_end < chars.len()
+                && chars[word_end].1.is_alphanumeric()
+                && !is_cjk(chars[word_end].1)
+            {
+                word_end += 1;
+            }
+
+            push(&mut tokens, start, end_of(word_end));
+            idx = word_end;
+        } else {
+            idx += 1;
+        }
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod test {
+    use super::{cjk_tokens, detect, lang_code};
+    use whatlang::Lang;
+
+    #[test]
+    fn test_detect() {
+        let german = "Der schnelle braune Fuchs springt über den faulen Hund und \
+            läuft dann schnell in den Wald zurück.";
+        assert_eq!(detect(german), Some(Lang::Deu));
+        assert_eq!(detect("ok"), None);
+    }
+
+    #[test]
+    fn test_lang_code() {
+        assert_eq!(lang_code("deu"), Some("deu"));
+        assert_eq!(lang_code("German"), Some("deu"));
+        assert_eq!(lang_code("klingon"), None);
+    }
+
+    #[test]
+    fn test_cjk_tokens() {
+        let tokens = cjk_tokens("东京大学 rust 2023")
+            .into_iter()
+            .map(|token| token.text)
+            .collect::<Vec<String>>();
+        assert_eq!(tokens, vec!["东京", "京大", "大学", "rust", "2023"]);
+
+        let tokens = cjk_tokens("日 本")
+            .into_iter()
+            .map(|token| token.text)
+            .collect::<Vec<String>>();
+        assert_eq!(tokens, vec!["日", "本"]);
+    }
+}
@@ -1,6 +1,7 @@
 use std::fs;
 
-use entities::models::crawl_queue::EnqueueSettings;
+use entities::models::crawl_queue::{EnqueueSettings, TaskPriority};
+use entities::models::tag::TagType;
 use entities::models::{crawl_queue, indexed_document, lens};
 use entities::sea_orm::{ColumnTrait, EntityTrait, ModelTrait, QueryFilter};
 use shared::regex::{regex_for_robots, WildcardType};
@@ -9,7 +10,7 @@ use url::Url;
 use shared::config::{Config, LensConfig, LensRule};
 use spyglass_plugin::SearchFilter;
 
-use crate::search::Searcher;
+use crate::search::{FilterNames, Searcher};
 use crate::state::AppState;
 use crate::task::{CollectTask, ManagerCommand};
 
@@ -70,6 +71,16 @@ pub async fn load_lenses(state: AppState) {
                 .await;
         }
 
+        for sitemap in lens.sitemaps.iter() {
+            let _ = state
+                .schedule_work(ManagerCommand::Collect(CollectTask::Sitemap {
+                    lens: lens.name.clone(),
+                    url: sitemap.clone(),
+                    pipeline: lens.pipeline.clone(),
+                }))
+                .await;
+        }
+
         process_urls(&lens, &state).await;
         process_lens_rules(lens, &state).await;
     }
@@ -92,6 +103,8 @@ pub async fn process_urls(lens: &LensConfig, state: &AppState) {
                 &state.user_settings,
                 &EnqueueSettings {
                     force_allow: true,
+                    priority: TaskPriority::Lens,
+                    tags: vec![(TagType::Lens, lens.name.clone())],
                     ..Default::default()
                 },
                 pipeline_kind.clone(),
@@ -190,6 +203,44 @@ async fn process_lens_rules(lens: LensConfig, state: &AppState) {
     }
 }
 
+/// Convert a lens into search filters, either using the filters defined by
+/// its configuration or by asking its plugin.
+async fn filters_for_lens(state: &AppState, lens: lens::Model) -> Vec<SearchFilter> {
+    let mut filters = Vec::new();
+    match lens.lens_type {
+        // Load lens configuration from files
+        lens::LensType::Simple => {
+            if let Some(lens_config) = state.lenses.get(&lens.name) {
+                let lens_filters = lens_config.into_regexes();
+                filters.extend(
+                    lens_filters
+                        .allowed
+                        .into_iter()
+                        .map(SearchFilter::URLRegexAllow)
+                        .collect::<Vec<SearchFilter>>(),
+                );
+
+                filters.extend(
+                    lens_filters
+                        .skipped
+                        .into_iter()
+                        .map(SearchFilter::URLRegexSkip)
+                        .collect::<Vec<SearchFilter>>(),
+                );
+            }
+        }
+        // Ask plugin for any filter information
+        lens::LensType::Plugin => {
+            let manager = state.plugin_manager.lock().await;
+            if let Some(plugin) = manager.find_by_name(lens.name) {
+                filters.extend(plugin.search_filters().await);
+            }
+        }
+    }
+
+    filters
+}
+
 /// Utility function to map a trigger to the matching lens(es) & convert that into
 /// search filters ready to be applied to a search.
 pub async fn lens_to_filters(state: AppState, trigger: &str) -> Vec<SearchFilter> {
@@ -201,40 +252,9 @@ pub async fn lens_to_filters(state: AppState, trigger: &str) -> Vec<SearchFilter
         .await
         .ok();
 
-    // Based on the lens type, either use filters defined by the configuration
-    // or ask the plugin for the search filter.
     let mut filters = Vec::new();
     for lens in results.unwrap_or_default() {
-        match lens.lens_type {
-            // Load lens configuration from files
-            lens::LensType::Simple => {
-                if let Some(lens_config) = state.lenses.get(&lens.name) {
-                    let lens_filters = lens_config.into_regexes();
-                    filters.extend(
-                        lens_filters
-                            .allowed
-                            .into_iter()
-                            .map(SearchFilter::URLRegexAllow)
-                            .collect::<Vec<SearchFilter>>(),
-                    );
-
-                    filters.extend(
-                        lens_filters
-                            .skipped
-                            .into_iter()
-                            .map(SearchFilter::URLRegexSkip)
-                            .collect::<Vec<SearchFilter>>(),
-                    );
-                }
-            }
-            // Ask plugin for any filter information
-            lens::LensType::Plugin => {
-                let manager = state.plugin_manager.lock().await;
-                if let Some(plugin) = manager.find_by_name(lens.name) {
-                    filters.extend(plugin.search_filters().await);
-                }
-            }
-        }
+        filters.extend(filters_for_lens(&state, lens).await);
     }
 
     if filters.is_empty() {
@@ -245,16 +265,66 @@ pub async fn lens_to_filters(state: AppState, trigger: &str) -> Vec<SearchFilter
     filters
 }
 
+/// Map lens names to search filters, scoping a search to documents that belong
+/// to any of the lenses. Disabled & unknown lenses are ignored.
+pub async fn lens_names_to_filters(state: AppState, names: &[String]) -> Vec<SearchFilter> {
+    if names.is_empty() {
+        return Vec::new();
+    }
+
+    let results = lens::Entity::find()
+        .filter(lens::Column::Name.is_in(names.to_vec()))
+        .filter(lens::Column::IsEnabled.eq(true))
+        .all(&state.db)
+        .await
+        .unwrap_or_else(|err| {
+            log::error!("Unable to find lenses {:?}: {}", names, err);
+            Vec::new()
+        });
+
+    for name in names {
+        if !results.iter().any(|lens| &lens.name == name) {
+            log::warn!("No enabled lens found w/ name: {}", name);
+        }
+    }
+
+    let mut filters = Vec::new();
+    for lens in results {
+        filters.extend(filters_for_lens(&state, lens).await);
+    }
+
+    filters
+}
+
+/// Whether `filters` actually limit a search to the documents of some lens.
+/// Lenses that only skip URLs (or plugin lenses w/o any URLs) don't, so a
+/// search scoped to them has to match nothing rather than everything.
+pub fn limits_scope(filters: &[SearchFilter]) -> bool {
+    filters
+        .iter()
+        .any(|filter| matches!(filter, SearchFilter::URLRegexAllow(_)))
+}
+
+/// Filters queries can use, including the fields lenses extract from pages.
+pub fn filter_names(state: &AppState) -> FilterNames {
+    FilterNames::new(state.lenses.iter().flat_map(|lens| {
+        lens.extract
+            .iter()
+            .map(|rule| rule.field().to_string())
+            .collect::<Vec<_>>()
+    }))
+}
+
 #[cfg(test)]
 mod test {
     use crate::search::IndexPath;
     use entities::models::lens;
     use entities::sea_orm::EntityTrait;
     use entities::test::setup_test_db;
-    use shared::config::{LensConfig, UserSettings};
+    use shared::config::{LensConfig, LensRule, UserSettings};
     use spyglass_plugin::SearchFilter;
 
-    use super::{lens_to_filters, AppState};
+    use super::{lens_names_to_filters, lens_to_filters, limits_scope, AppState};
 
     #[tokio::test]
     async fn test_lens_to_filter() {
@@ -288,4 +358,80 @@ mod test {
             SearchFilter::URLRegexAllow("^https://oldschool.runescape.wiki/wiki/.*".to_owned())
         );
     }
+
+    #[tokio::test]
+    async fn test_lens_names_to_filters() {
+        let db = setup_test_db().await;
+        let work = LensConfig {
+            name: "work".to_owned(),
+            trigger: "work".to_owned(),
+            domains: vec!["docs.rs".to_string()],
+            ..Default::default()
+        };
+        let wiki = LensConfig {
+            name: "wiki".to_owned(),
+            trigger: "wiki".to_owned(),
+            urls: vec!["https://en.wikipedia.org/wiki/".to_string()],
+            ..Default::default()
+        };
+
+        for lens in [&work, &wiki] {
+            lens::add_or_enable(&db, lens, lens::LensType::Simple)
+                .await
+                .unwrap();
+        }
+
+        let state = AppState::builder()
+            .with_db(db)
+            .with_lenses(&vec![work, wiki])
+            .with_user_settings(&UserSettings::default())
+            .with_index(&IndexPath::Memory)
+            .build();
+
+        let filters = lens_names_to_filters(
+            state.clone(),
+            &[
+                "work".to_string(),
+                "wiki".to_string(),
+                "missing".to_string(),
+            ],
+        )
+        .await;
+        assert_eq!(filters.len(), 2);
+        assert!(filters.contains(&SearchFilter::URLRegexAllow(
+            "^https://en.wikipedia.org/wiki/.*".to_owned()
+        )));
+
+        assert!(lens_names_to_filters(state, &[]).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_skip_only_lens_does_not_limit_scope() {
+        let db = setup_test_db().await;
+        let private = LensConfig {
+            name: "private".to_owned(),
+            trigger: "private".to_owned(),
+            rules: vec![LensRule::SkipURL(
+                "https://example.com/private/*".to_string(),
+            )],
+            ..Default::default()
+        };
+        lens::add_or_enable(&db, &private, lens::LensType::Simple)
+            .await
+            .unwrap();
+
+        let state = AppState::builder()
+            .with_db(db)
+            .with_lenses(&vec![private])
+            .with_user_settings(&UserSettings::default())
+            .with_index(&IndexPath::Memory)
+            .build();
+
+        let filters = lens_names_to_filters(state, &["private".to_string()]).await;
+        assert!(!filters.is_empty());
+        assert!(!limits_scope(&filters));
+        assert!(limits_scope(&[SearchFilter::URLRegexAllow(
+            "^https://docs.rs/.*".to_owned()
+        )]));
+    }
 }
@@ -0,0 +1,144 @@
+//! Index upkeep. Every commit adds a segment to the index & while tantivy
+//! merges them as it goes, long running installs that index a trickle of
+//! documents still end up w/ lots of tiny segments, which slows searches.
+
+use anyhow::anyhow;
+use tantivy::SegmentId;
+
+use super::Searcher;
+
+/// Don't bother merging until there are at least this many small segments.
+const MIN_SMALL_SEGMENTS: usize = 8;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MergeResult {
+    pub segments_before: usize,
+    pub segments_after: usize,
+}
+
+fn num_segments(searcher: &Searcher) -> anyhow::Result<usize> {
+    Ok(searcher.index.searchable_segment_ids()?.len())
+}
+
+/// Merge `segment_ids` into a single segment, waiting for the merge to finish.
+async fn merge(searcher: &Searcher, segment_ids: &[SegmentId]) -> anyhow::Result<()> {
+    let merge = {
+        let mut writer = searcher
+            .writer
+            .lock()
+            .map_err(|err| anyhow!("Unable to lock index writer: {}", err))?;
+        writer.merge(segment_ids)
+    };
+    merge.await?;
+
+    Ok(())
+}
+
+/// Merge segments w/ fewer than `max_docs` documents together, if there are
+/// enough of them to be worth it. Returns `None` if nothing was merged.
+pub async fn merge_small_segments(
+    searcher: &Searcher,
+    max_docs: u32,
+) -> anyhow::Result<Option<MergeResult>> {
+    let metas = searcher.index.searchable_segment_metas()?;
+    let small = metas
+        .iter()
+        .filter(|meta| meta.num_docs() < max_docs)
+        .map(|meta| meta.id())
+        .collect::<Vec<SegmentId>>();
+
+    if small.len() < MIN_SMALL_SEGMENTS {
+        return Ok(None);
+    }
+
+    merge(searcher, &small).await?;
+    Ok(Some(MergeResult {
+        segments_before: metas.len(),
+        segments_after: num_segments(searcher)?,
+    }))
+}
+
+/// Commit any pending changes & merge the whole index into one segment, then
+/// clean up files that are no longer used. Can take a while for big indexes.
+pub async fn optimize(searcher: &Searcher) -> anyhow::Result<MergeResult> {
+    {
+        let mut writer = searcher
+            .writer
+            .lock()
+            .map_err(|err| anyhow!("Unable to lock index writer: {}", err))?;
+        writer.commit()?;
+    }
+
+    let segment_ids = searcher.index.searchable_segment_ids()?;
+    if segment_ids.len() > 1 {
+        merge(searcher, &segment_ids).await?;
+    }
+
+    let cleanup = {
+        let writer = searcher
+            .writer
+            .lock()
+            .map_err(|err| anyhow!("Unable to lock index writer: {}", err))?;
+        writer.garbage_collect_files()
+    };
+    cleanup.await?;
+
+    Ok(MergeResult {
+        segments_before: segment_ids.len(),
+        segments_after: num_segments(searcher)?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use tantivy::merge_policy::NoMergePolicy;
+
+    use super::{merge_small_segments, optimize, MIN_SMALL_SEGMENTS};
+    use crate::search::{DocumentUpdate, IndexPath, Searcher};
+
+    /// Index w/ one segment per document.
+    fn fragmented_index(num_segments: usize) -> Searcher {
+        let searcher = Searcher::with_index(&IndexPath::Memory).expect("Unable to open index");
+        let mut writer = searcher.writer.lock().unwrap();
+        writer.set_merge_policy(Box::new(NoMergePolicy));
+        for idx in 0..num_segments {
+            let url = format!("https://example.com/{}", idx);
+            Searcher::upsert_document(
+                &mut writer,
+                DocumentUpdate {
+                    title: "Salinas River",
+                    url: &url,
+                    content: "The Salinas River runs through the Salinas Valley.",
+                    ..Default::default()
+                },
+            )
+            .expect("Unable to add doc");
+            writer.commit().expect("Unable to commit");
+        }
+        drop(writer);
+
+        searcher
+    }
+
+    #[tokio::test]
+    async fn test_merge_small_segments() {
+        let searcher = fragmented_index(MIN_SMALL_SEGMENTS - 1);
+        assert_eq!(merge_small_segments(&searcher, 100).await.unwrap(), None);
+
+        let searcher = fragmented_index(MIN_SMALL_SEGMENTS);
+        let merged = merge_small_segments(&searcher, 100)
+            .await
+            .unwrap()
+            .expect("Should merge");
+        assert_eq!(merged.segments_before, MIN_SMALL_SEGMENTS);
+        assert_eq!(merged.segments_after, 1);
+    }
+
+    #[tokio::test]
+    async fn test_optimize() {
+        let searcher = fragmented_index(3);
+        let optimized = optimize(&searcher).await.unwrap();
+        assert_eq!(optimized.segments_before, 3);
+        assert_eq!(optimized.segments_after, 1);
+    }
+}
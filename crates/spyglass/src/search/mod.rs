@@ -1,32 +1,88 @@
+use std::collections::HashSet;
 use std::fmt::{Debug, Error, Formatter};
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
-use regex::RegexSetBuilder;
-use tantivy::collector::TopDocs;
+use chrono::{DateTime, Local, SecondsFormat, Utc};
+use regex::{Regex, RegexSetBuilder};
+use tantivy::collector::{DocSetCollector, FacetCollector, TopDocs};
 use tantivy::directory::MmapDirectory;
-use tantivy::query::TermQuery;
+use tantivy::query::{BooleanQuery, Occur, Query, TermQuery};
 use tantivy::{schema::*, DocAddress, DocId, SegmentReader};
 use tantivy::{Index, IndexReader, IndexWriter, ReloadPolicy};
 use uuid::Uuid;
 
-use crate::search::query::build_query;
+use crate::scraper::{Section, DEFAULT_DESC_LENGTH};
+use crate::search::clicks::ClickBoosts;
+use crate::search::decay::DomainDecay;
+use crate::search::query::{build_query, ids_query, parse_as_of, version_filter, ResolvedFilters};
 use crate::search::utils::ff_to_string;
 use crate::state::AppState;
-use entities::models::indexed_document;
+use entities::models::tag::{TagType, TagValue};
+use entities::models::{
+    collection, collection_document, document_note, document_version, indexed_document,
+    pinned_result, search_click,
+};
 use entities::schema::{DocFields, SearchDocument};
-use entities::sea_orm::{prelude::*, DatabaseConnection};
+use entities::sea_orm::{prelude::*, DatabaseConnection, Iterable};
+use shared::config::DateLocale;
 use spyglass_plugin::SearchFilter;
 
+pub mod clicks;
+mod dates;
+pub mod decay;
+pub mod deeplink;
+pub mod geo;
 pub mod grouping;
+mod language;
 pub mod lens;
+pub mod maintenance;
+mod parser;
+pub mod preview;
 mod query;
+pub mod snippet;
+pub mod synonyms;
 mod utils;
 
+pub use parser::FilterNames;
+pub use query::parse_as_of;
+pub use synonyms::Synonyms;
+
+/// How queries are read, from the user's settings.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct QueryOptions<'a> {
+    /// Typos tolerated per search term.
+    pub fuzzy_distance: u8,
+    /// Words searched for along w/ the ones in the query.
+    pub synonyms: Option<&'a Synonyms>,
+    /// Language `after:` & `before:` dates are read in.
+    pub date_locale: DateLocale,
+    /// Documents pinned for the query, if they've already been loaded.
+    /// They're looked up when searching otherwise.
+    pub pinned: Option<&'a [String]>,
+    /// Filters queries can use, only the built-in ones when not set.
+    pub filter_names: Option<&'a FilterNames>,
+}
+
 type Score = f32;
 type SearchResult = (Score, DocAddress);
 
+/// Results returned per search.
+const MAX_RESULTS: usize = 5;
+/// Results re-ranked w/ how often they've been opened, so documents a bit
+/// further down can move up.
+const RERANK_CANDIDATES: usize = 20;
+/// Added to the score of pinned results so they outrank anything else.
+const PINNED_BOOST: Score = 1_000_000.0;
+/// Starred documents rank above similarly relevant ones.
+const FAVORITE_BOOST: Score = 1.5;
+/// Limits for exact/regex searches, which read stored content rather than
+/// using the index.
+const SCAN_MAX_DOCS: usize = 50_000;
+const SCAN_MAX_RESULTS: usize = 5;
+const SCAN_TIMEOUT: Duration = Duration::from_secs(5);
+
 pub enum IndexPath {
     // Directory
     LocalPath(PathBuf),
@@ -34,6 +90,170 @@ pub enum IndexPath {
     Memory,
 }
 
+/// Document contents used to add/update an entry in the index.
+#[derive(Clone, Debug, Default)]
+pub struct DocumentUpdate<'a> {
+    pub doc_id: Option<String>,
+    pub title: &'a str,
+    pub description: &'a str,
+    pub domain: &'a str,
+    pub url: &'a str,
+    pub content: &'a str,
+    /// Structured (name, value) pairs, e.g. from lens extraction rules.
+    pub fields: &'a [(String, String)],
+    /// Numeric (name, value) pairs, filterable w/ range queries like `size:>10mb`.
+    pub numbers: &'a [(String, f64)],
+    /// Code blocks, searchable w/ `code:` queries.
+    pub code: &'a [String],
+    /// Anchored sections, indexed separately for long documents.
+    pub sections: &'a [Section],
+    /// (note id, text) for notes the user attached to this document.
+    pub notes: &'a [(i64, String)],
+    /// When the current content was first crawled, for `as_of:` queries.
+    pub crawled_at: Option<DateTime<Utc>>,
+    /// (label, value) tags, counted w/ `Searcher::tag_counts`.
+    pub tags: &'a [(String, String)],
+}
+
+/// Documents w/ more content than this are also indexed section by section so
+/// results can link to the relevant part of the page.
+const SECTION_SPLIT_LENGTH: usize = 20_000;
+
+/// Documents are indexed in parts of at most this many bytes, so giant logs &
+/// pages don't blow up the index writer's memory & snippets come from the part
+/// that matched.
+const PART_SPLIT_LENGTH: usize = 1_000_000;
+
+/// Tags are indexed as "/label/value" facets.
+fn tag_facet(label: &str, value: &str) -> Facet {
+    Facet::from_path([label, value])
+}
+
+/// Structured fields are indexed as a single lowercase "name:value" term so
+/// filters are case-insensitive.
+pub fn field_term(name: &str, value: &str) -> String {
+    format!("{}:{}", name.trim(), value.trim()).to_lowercase()
+}
+
+/// Largest number that can be indexed, anything bigger wouldn't fit in the
+/// padded term.
+const MAX_NUMBER: f64 = 1e20;
+
+/// Numeric fields are indexed as a "name:value" term w/ the value zero padded
+/// to a fixed width, so comparing terms compares the values. Negative values
+/// are treated as zero.
+pub fn number_term(name: &str, value: f64) -> String {
+    format!(
+        "{}:{:024.3}",
+        name.trim().to_lowercase(),
+        value.clamp(0.0, MAX_NUMBER)
+    )
+}
+
+/// Index id for a note attached to the document `doc_id`.
+pub fn note_doc_id(doc_id: &str, note_id: i64) -> String {
+    format!("{}#note-{}", doc_id, note_id)
+}
+
+/// Index id for part `part` (starting at 2, the document itself is part 1) of
+/// an oversized document `doc_id`.
+pub fn part_doc_id(doc_id: &str, part: usize) -> String {
+    format!("{}/part-{}", doc_id, part)
+}
+
+/// Part number of a document split by size, `None` if `doc_id` isn't a part.
+pub fn part_number(doc_id: &str) -> Option<usize> {
+    doc_id
+        .rsplit_once("/part-")
+        .and_then(|(_, part)| part.parse().ok())
+}
+
+/// Split `content` into chunks of at most `max_len` bytes, breaking at a line
+/// or word boundary when there's one in the second half of the chunk.
+fn split_parts(content: &str, max_len: usize) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut rest = content;
+    while rest.len() > max_len {
+        let mut end = max_len;
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        let chunk = &rest[..end];
+        let break_after = |is_break: fn(char) -> bool| {
+            chunk
+                .char_indices()
+                .rev()
+                .find(|(_, ch)| is_break(*ch))
+                .map(|(idx, ch)| idx + ch.len_utf8())
+                .filter(|idx| *idx >= end / 2)
+        };
+        let end = break_after(|ch| ch == '\n')
+            .or_else(|| break_after(char::is_whitespace))
+            .unwrap_or(end);
+
+        parts.push(&rest[..end]);
+        rest = &rest[end..];
+    }
+    parts.push(rest);
+
+    parts
+}
+
+/// Versions are indexed w/ second precision timestamps so they can be
+/// compared as strings.
+pub fn version_timestamp(timestamp: &DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Index id for the past version of `doc_id` first crawled @ `crawled_at`.
+pub fn version_doc_id(doc_id: &str, crawled_at: &DateTime<Utc>) -> String {
+    format!("{}@{}", doc_id, crawled_at.timestamp())
+}
+
+/// Notes are indexed as children of the document they're attached to, so
+/// matches show up as the parent document.
+fn note_document(
+    doc_id: &str,
+    note_id: i64,
+    text: &str,
+    title: &str,
+    domain: &str,
+    url: &str,
+) -> Document {
+    let fields = DocFields::as_fields();
+    let description = text
+        .split_whitespace()
+        .take(DEFAULT_DESC_LENGTH)
+        .collect::<Vec<&str>>()
+        .join(" ");
+
+    let mut doc = Document::default();
+    doc.add_text(fields.id, note_doc_id(doc_id, note_id));
+    doc.add_text(fields.parent_id, doc_id);
+    doc.add_text(fields.content, text);
+    doc.add_text(fields.description, description);
+    doc.add_text(fields.domain, domain);
+    doc.add_text(fields.title, title);
+    doc.add_text(fields.url, url);
+    doc
+}
+
+/// Language specific content isn't stored, so it's added back from the stored
+/// title & content when rebuilding a document from `stored`.
+fn add_stored_language_fields(doc: &mut Document, stored: &Document) {
+    let fields = DocFields::as_fields();
+    let stored_text = |field: Field| {
+        stored
+            .get_first(field)
+            .and_then(|value| value.as_text())
+            .unwrap_or_default()
+    };
+
+    let (title, content) = (stored_text(fields.title), stored_text(fields.content));
+    language::add_language_fields(doc, language::detect(content), &[title, content]);
+}
+
 #[derive(Clone)]
 pub struct Searcher {
     pub index: Index,
@@ -65,15 +285,36 @@ impl Searcher {
         // Remove from search index, immediately.
         if let Ok(mut writer) = state.index.writer.lock() {
             Searcher::remove_from_index(&mut writer, doc_id)?;
+            let fields = DocFields::as_fields();
+            writer.delete_term(Term::from_field_text(fields.version_of, doc_id));
         };
 
-        // Remove from indexed_doc table
+        // Remove from indexed_doc table, along w/ any notes on it, its history,
+        // the times it was opened, the collections it was added to & the
+        // queries it was pinned for.
         if let Some(model) = indexed_document::Entity::find()
             .filter(indexed_document::Column::DocId.eq(doc_id))
             .one(&state.db)
             .await?
         {
-            let _ = model.delete(&state.db).await;
+            collection_document::Entity::delete_many()
+                .filter(collection_document::Column::IndexedDocumentId.eq(model.id))
+                .exec(&state.db)
+                .await?;
+            pinned_result::Entity::delete_many()
+                .filter(pinned_result::Column::IndexedDocumentId.eq(model.id))
+                .exec(&state.db)
+                .await?;
+            let _ = document_note::Entity::delete_many()
+                .filter(document_note::Column::IndexedDocumentId.eq(model.id))
+                .exec(&state.db)
+                .await;
+            let _ = search_click::Entity::delete_many()
+                .filter(search_click::Column::IndexedDocumentId.eq(model.id))
+                .exec(&state.db)
+                .await;
+            let _ = document_version::delete_for_url(&state.db, &model.url).await;
+            model.delete(&state.db).await?;
         }
 
         Ok(())
@@ -96,9 +337,47 @@ impl Searcher {
     pub fn remove_from_index(writer: &mut IndexWriter, doc_id: &str) -> anyhow::Result<()> {
         let fields = DocFields::as_fields();
         writer.delete_term(Term::from_field_text(fields.id, doc_id));
+        writer.delete_term(Term::from_field_text(fields.parent_id, doc_id));
+        Ok(())
+    }
+
+    /// Keep `previous`, the indexed copy of `doc_id` from before it was
+    /// re-crawled, searchable w/ `as_of:` queries.
+    pub fn add_version(
+        writer: &mut IndexWriter,
+        previous: &Document,
+        doc_id: &str,
+        crawled_at: &DateTime<Utc>,
+        superseded_at: &DateTime<Utc>,
+    ) -> tantivy::Result<()> {
+        let fields = DocFields::as_fields();
+
+        let mut doc = Document::default();
+        for field_value in previous.field_values() {
+            let field = field_value.field();
+            if field != fields.id && field != fields.crawled_at && field != fields.lang {
+                doc.add_field_value(field, field_value.value().clone());
+            }
+        }
+        add_stored_language_fields(&mut doc, previous);
+        doc.add_text(fields.id, version_doc_id(doc_id, crawled_at));
+        doc.add_text(fields.version_of, doc_id);
+        doc.add_text(fields.crawled_at, version_timestamp(crawled_at));
+        doc.add_text(fields.superseded_at, version_timestamp(superseded_at));
+        writer.add_document(doc)?;
+
         Ok(())
     }
 
+    /// Remove the past version of `doc_id` first crawled @ `crawled_at`.
+    pub fn remove_version(writer: &mut IndexWriter, doc_id: &str, crawled_at: &DateTime<Utc>) {
+        let fields = DocFields::as_fields();
+        writer.delete_term(Term::from_field_text(
+            fields.id,
+            &version_doc_id(doc_id, crawled_at),
+        ));
+    }
+
     /// Get document with `doc_id` from index.
     pub fn get_by_id(reader: &IndexReader, doc_id: &str) -> Option<Document> {
         let fields = DocFields::as_fields();
@@ -136,6 +415,7 @@ impl Searcher {
             }
             IndexPath::Memory => Index::create_in_ram(schema),
         };
+        language::register_tokenizers(index.tokenizers());
 
         // Should only be one writer at a time. This single IndexWriter is already
         // multithreaded.
@@ -160,34 +440,373 @@ impl Searcher {
 
     pub fn upsert_document(
         writer: &mut IndexWriter,
-        doc_id: Option<String>,
-        title: &str,
-        description: &str,
-        domain: &str,
-        url: &str,
-        content: &str,
+        doc_update: DocumentUpdate,
     ) -> tantivy::Result<String> {
         let fields = DocFields::as_fields();
 
-        let doc_id = doc_id.map_or_else(|| Uuid::new_v4().as_hyphenated().to_string(), |s| s);
+        let doc_id = doc_update
+            .doc_id
+            .map_or_else(|| Uuid::new_v4().as_hyphenated().to_string(), |s| s);
+
+        let parts = split_parts(doc_update.content, PART_SPLIT_LENGTH);
+        let content = parts[0];
 
         let mut doc = Document::default();
         doc.add_text(fields.content, content);
-        doc.add_text(fields.description, description);
-        doc.add_text(fields.domain, domain);
+        doc.add_text(fields.description, doc_update.description);
+        doc.add_text(fields.domain, doc_update.domain);
         doc.add_text(fields.id, &doc_id);
-        doc.add_text(fields.title, title);
-        doc.add_text(fields.url, url);
+        doc.add_text(fields.title, doc_update.title);
+        doc.add_text(fields.url, doc_update.url);
+        if let Some(crawled_at) = &doc_update.crawled_at {
+            doc.add_text(fields.crawled_at, version_timestamp(crawled_at));
+        }
+        for (name, value) in doc_update.fields {
+            doc.add_text(fields.fields, field_term(name, value));
+        }
+        for (name, value) in doc_update.numbers {
+            doc.add_text(fields.numbers, number_term(name, *value));
+        }
+        let words = doc_update.content.split_whitespace().count();
+        doc.add_text(fields.numbers, number_term("words", words as f64));
+        for block in doc_update.code {
+            doc.add_text(fields.code, block);
+        }
+        for (label, value) in doc_update.tags {
+            doc.add_facet(fields.tags, tag_facet(label, value));
+        }
+        let lang = language::detect(content);
+        language::add_language_fields(&mut doc, lang, &[doc_update.title, content]);
         writer.add_document(doc)?;
 
+        // The rest of an oversized document is indexed as parts pointing back
+        // to it. Its sections would repeat all of the content, so they're left
+        // out.
+        for (idx, part) in parts.iter().enumerate().skip(1) {
+            let mut doc = Document::default();
+            doc.add_text(fields.id, part_doc_id(&doc_id, idx + 1));
+            doc.add_text(fields.parent_id, &doc_id);
+            doc.add_text(fields.content, part);
+            doc.add_text(fields.domain, doc_update.domain);
+            doc.add_text(fields.title, doc_update.title);
+            doc.add_text(fields.url, doc_update.url);
+            doc.add_text(fields.numbers, number_term("part", (idx + 1) as f64));
+            if let Some(crawled_at) = &doc_update.crawled_at {
+                doc.add_text(fields.crawled_at, version_timestamp(crawled_at));
+            }
+            language::add_language_fields(&mut doc, lang, &[doc_update.title, part]);
+            writer.add_document(doc)?;
+        }
+
+        if parts.len() == 1
+            && doc_update.content.len() > SECTION_SPLIT_LENGTH
+            && doc_update.sections.len() > 1
+        {
+            for section in doc_update.sections {
+                let description = section
+                    .content
+                    .split(' ')
+                    .take(DEFAULT_DESC_LENGTH)
+                    .collect::<Vec<&str>>()
+                    .join(" ");
+
+                let mut doc = Document::default();
+                doc.add_text(fields.id, format!("{}#{}", doc_id, section.anchor));
+                doc.add_text(fields.parent_id, &doc_id);
+                doc.add_text(fields.anchor, &section.anchor);
+                doc.add_text(fields.content, &section.content);
+                doc.add_text(fields.description, description);
+                doc.add_text(fields.domain, doc_update.domain);
+                doc.add_text(fields.title, &section.heading);
+                doc.add_text(fields.url, doc_update.url);
+                if let Some(crawled_at) = &doc_update.crawled_at {
+                    doc.add_text(fields.crawled_at, version_timestamp(crawled_at));
+                }
+                language::add_language_fields(
+                    &mut doc,
+                    lang,
+                    &[&section.heading, &section.content],
+                );
+                writer.add_document(doc)?;
+            }
+        }
+
+        for (note_id, text) in doc_update.notes {
+            writer.add_document(note_document(
+                &doc_id,
+                *note_id,
+                text,
+                doc_update.title,
+                doc_update.domain,
+                doc_update.url,
+            ))?;
+        }
+
         Ok(doc_id)
     }
 
+    /// Replace the tags of the already indexed document `doc_id`, e.g. after
+    /// it's been starred.
+    pub fn update_tags(
+        writer: &mut IndexWriter,
+        reader: &IndexReader,
+        doc_id: &str,
+        tags: &[(String, String)],
+    ) -> anyhow::Result<()> {
+        let fields = DocFields::as_fields();
+        let existing = Self::get_by_id(reader, doc_id)
+            .ok_or_else(|| anyhow::anyhow!("Document {} is not in the index", doc_id))?;
+
+        // Everything but the language specific content is stored, so the
+        // document can be rebuilt as is.
+        let mut doc = Document::default();
+        for field_value in existing.field_values() {
+            if field_value.field() != fields.tags && field_value.field() != fields.lang {
+                doc.add_field_value(field_value.field(), field_value.value().clone());
+            }
+        }
+        for (label, value) in tags {
+            doc.add_facet(fields.tags, tag_facet(label, value));
+        }
+        add_stored_language_fields(&mut doc, &existing);
+
+        writer.delete_term(Term::from_field_text(fields.id, doc_id));
+        writer.add_document(doc)?;
+
+        Ok(())
+    }
+
+    /// Adds the language fields to documents indexed before they existed.
+    /// Returns the number of documents that were updated.
+    pub fn backfill_language_fields(&self) -> anyhow::Result<usize> {
+        let fields = DocFields::as_fields();
+        let searcher = self.reader.searcher();
+        let mut writer = self
+            .writer
+            .lock()
+            .map_err(|err| anyhow::anyhow!("Unable to lock index writer: {}", err))?;
+
+        let mut updated = 0;
+        for (segment_ord, segment_reader) in searcher.segment_readers().iter().enumerate() {
+            for doc_id in segment_reader.doc_ids_alive() {
+                let existing = searcher.doc(DocAddress::new(segment_ord as u32, doc_id))?;
+                if existing.get_first(fields.lang).is_some() {
+                    continue;
+                }
+                let id = match existing
+                    .get_first(fields.id)
+                    .and_then(|value| value.as_text())
+                {
+                    Some(id) => id.to_string(),
+                    None => continue,
+                };
+
+                let mut doc = Document::default();
+                for field_value in existing.field_values() {
+                    doc.add_field_value(field_value.field(), field_value.value().clone());
+                }
+                add_stored_language_fields(&mut doc, &existing);
+                // No language detected, nothing to add.
+                if doc.get_first(fields.lang).is_none() {
+                    continue;
+                }
+
+                writer.delete_term(Term::from_field_text(fields.id, &id));
+                writer.add_document(doc)?;
+                updated += 1;
+            }
+        }
+
+        writer.commit()?;
+        Ok(updated)
+    }
+
+    /// Add/replace a note on the already indexed document `doc_id`.
+    pub fn upsert_note(
+        writer: &mut IndexWriter,
+        reader: &IndexReader,
+        doc_id: &str,
+        note_id: i64,
+        text: &str,
+    ) -> anyhow::Result<()> {
+        let fields = DocFields::as_fields();
+        let parent = Self::get_by_id(reader, doc_id)
+            .ok_or_else(|| anyhow::anyhow!("Document {} is not in the index", doc_id))?;
+        let parent_text = |field: Field| {
+            parent
+                .get_first(field)
+                .and_then(|value| value.as_text())
+                .unwrap_or_default()
+                .to_string()
+        };
+
+        writer.delete_term(Term::from_field_text(
+            fields.id,
+            &note_doc_id(doc_id, note_id),
+        ));
+        writer.add_document(note_document(
+            doc_id,
+            note_id,
+            text,
+            &parent_text(fields.title),
+            &parent_text(fields.domain),
+            &parent_text(fields.url),
+        ))?;
+
+        Ok(())
+    }
+
+    /// Query for `query_string` (see the `parser` module for the syntax), along
+    /// w/ the `as_of:` date to search if there was one.
+    async fn parse_query(
+        db: &DatabaseConnection,
+        index: &Index,
+        query_string: &str,
+        options: QueryOptions<'_>,
+    ) -> (BooleanQuery, Option<DateTime<Utc>>) {
+        let tokenizers = index.tokenizers().clone();
+        let default_names = FilterNames::default();
+        let names = options.filter_names.unwrap_or(&default_names);
+        let parsed = parser::parse(query_string, names);
+        let clauses = match options.synonyms {
+            Some(synonyms) => synonyms.expand(parsed),
+            None => parsed,
+        };
+
+        // Search what documents said @ a point in time rather than their
+        // current content w/ as_of.
+        let as_of = parser::filter_values(&clauses, "as_of")
+            .last()
+            .and_then(|value| {
+                let parsed = parse_as_of(value);
+                if parsed.is_none() {
+                    log::warn!("Ignoring invalid as_of date: {}", value);
+                }
+                parsed
+            });
+
+        // Collections live in the database rather than the index, so resolve
+        // them to the set of documents they contain.
+        let mut resolved = ResolvedFilters::default();
+        for name in parser::filter_values(&clauses, "collection") {
+            if resolved.collections.contains_key(&name) {
+                continue;
+            }
+
+            let doc_ids = match collection::doc_ids(db, &name).await {
+                Ok(doc_ids) => doc_ids,
+                Err(err) => {
+                    log::error!("Unable to get documents for collection {}: {}", name, err);
+                    Vec::new()
+                }
+            };
+            resolved.collections.insert(name, doc_ids);
+        }
+
+        let now = Local::now();
+        for filter in ["after", "before"] {
+            for value in parser::filter_values(&clauses, filter) {
+                match dates::parse_date(&value, options.date_locale, &now) {
+                    Some(date) => {
+                        resolved.dates.insert(value, date);
+                    }
+                    None => log::warn!("Ignoring invalid {} date: {}", filter, value),
+                }
+            }
+        }
+
+        let query = build_query(
+            index.schema(),
+            tokenizers,
+            DocFields::as_fields(),
+            &clauses,
+            &resolved,
+            options.fuzzy_distance,
+        );
+
+        (query, as_of)
+    }
+
+    /// Number of documents matching `query_string` w/ each tag, most common
+    /// first. Lens filters aren't applied since they're matched against URLs
+    /// while scoring.
+    pub async fn tag_counts(
+        db: &DatabaseConnection,
+        searcher: &Searcher,
+        query_string: &str,
+        options: QueryOptions<'_>,
+    ) -> tantivy::Result<Vec<(String, String, u64)>> {
+        let fields = DocFields::as_fields();
+        let (query, as_of) = Self::parse_query(db, &searcher.index, query_string, options).await;
+        let query = BooleanQuery::new(vec![
+            (Occur::Must, Box::new(query) as Box<dyn Query>),
+            version_filter(&fields, as_of.as_ref()),
+        ]);
+
+        let mut collector = FacetCollector::for_field(fields.tags);
+        for label in TagType::iter() {
+            collector.add_facet(Facet::from_path([label.as_ref()]));
+        }
+
+        let facet_counts = searcher.reader.searcher().search(&query, &collector)?;
+        let mut counts = TagType::iter()
+            .flat_map(|label| {
+                facet_counts
+                    .get(Facet::from_path([label.as_ref()]))
+                    .filter_map(|(facet, count)| match facet.to_path().as_slice() {
+                        [label, value] => Some((label.to_string(), value.to_string(), count)),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        counts.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| (&a.0, &a.1).cmp(&(&b.0, &b.1))));
+
+        Ok(counts)
+    }
+
+    /// Which of the documents w/ `doc_ids` match `query_string`, e.g. to find
+    /// out if newly indexed documents match a saved search.
+    pub async fn matching_ids(
+        db: &DatabaseConnection,
+        searcher: &Searcher,
+        query_string: &str,
+        doc_ids: &[String],
+        options: QueryOptions<'_>,
+    ) -> tantivy::Result<Vec<String>> {
+        if doc_ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let fields = DocFields::as_fields();
+        let (query, as_of) = Self::parse_query(db, &searcher.index, query_string, options).await;
+        let query = BooleanQuery::new(vec![
+            (Occur::Must, Box::new(query) as Box<dyn Query>),
+            (Occur::Must, Box::new(ids_query(&fields, doc_ids))),
+            version_filter(&fields, as_of.as_ref()),
+        ]);
+
+        let searcher = searcher.reader.searcher();
+        let matches = searcher.search(&query, &DocSetCollector)?;
+        let mut matching = Vec::new();
+        for addr in matches {
+            let doc = searcher.doc(addr)?;
+            if let Some(doc_id) = doc.get_first(fields.id).and_then(|id| id.as_text()) {
+                matching.push(doc_id.to_string());
+            }
+        }
+
+        Ok(matching)
+    }
+
     pub async fn search_with_lens(
-        _db: DatabaseConnection,
+        db: DatabaseConnection,
         applied_lenses: &Vec<SearchFilter>,
         searcher: &Searcher,
         query_string: &str,
+        decay: &DomainDecay,
+        clicks: &ClickBoosts,
+        options: QueryOptions<'_>,
     ) -> Vec<SearchResult> {
         let start_timer = Instant::now();
 
@@ -195,8 +814,41 @@ impl Searcher {
         let reader = &searcher.reader;
         let fields = DocFields::as_fields();
         let searcher = reader.searcher();
-        let tokenizers = index.tokenizers().clone();
-        let query = build_query(index.schema(), tokenizers, fields.clone(), query_string);
+        let (query, as_of) = Self::parse_query(&db, index, query_string, options).await;
+
+        // Pinned documents are included even if they don't match the query.
+        let pinned = match options.pinned {
+            Some(pinned) => pinned.to_vec(),
+            None => pinned_result::doc_ids(&db, query_string)
+                .await
+                .unwrap_or_else(|err| {
+                    log::error!("Unable to get pinned results: {}", err);
+                    Vec::new()
+                }),
+        };
+        let favorites = indexed_document::find_by_tag(
+            &db,
+            &(TagType::Favorited, TagValue::Favorited.as_ref().to_string()),
+        )
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .map(|doc| doc.doc_id)
+        .collect::<HashSet<String>>();
+
+        let query = if pinned.is_empty() {
+            query
+        } else {
+            BooleanQuery::new(vec![
+                (Occur::Should, Box::new(query) as Box<dyn Query>),
+                (Occur::Should, Box::new(ids_query(&fields, &pinned))),
+            ])
+        };
+
+        let query = BooleanQuery::new(vec![
+            (Occur::Must, Box::new(query) as Box<dyn Query>),
+            version_filter(&fields, as_of.as_ref()),
+        ]);
 
         let mut allowed = Vec::new();
         let mut skipped = Vec::new();
@@ -219,16 +871,32 @@ impl Searcher {
             .build()
             .expect("Unable to build regexset");
 
+        let limit = if clicks.is_empty() {
+            MAX_RESULTS
+        } else {
+            RERANK_CANDIDATES
+        };
         let collector =
-            TopDocs::with_limit(5).tweak_score(move |segment_reader: &SegmentReader| {
+            TopDocs::with_limit(limit).tweak_score(move |segment_reader: &SegmentReader| {
                 let regex_allow = regex_allow.clone();
                 let regex_skip = regex_skip.clone();
                 let fields = fields.clone();
+                let pinned = pinned.clone();
+                let favorites = favorites.clone();
+                let decay = decay.clone();
 
                 let inverted_index = segment_reader
                     .inverted_index(fields.url)
                     .expect("Failed to get inverted index for segment");
 
+                let id_index = segment_reader
+                    .inverted_index(fields.id)
+                    .expect("Failed to get inverted index for segment");
+
+                let domain_index = segment_reader
+                    .inverted_index(fields.domain)
+                    .expect("Failed to get inverted index for segment");
+
                 let id_reader = segment_reader
                     .fast_fields()
                     .u64s(fields.id)
@@ -239,25 +907,49 @@ impl Searcher {
                     .u64s(fields.url)
                     .expect("Unable to get fast field for URL");
 
+                let domain_reader = segment_reader
+                    .fast_fields()
+                    .u64s(fields.domain)
+                    .expect("Unable to get fast field for domain");
+
                 // We can now define our actual scoring function
                 move |doc: DocId, original_score: Score| {
                     let inverted_index = inverted_index.clone();
                     let terms = inverted_index.terms();
 
-                    let _id = ff_to_string(doc, &id_reader, terms);
+                    let id = ff_to_string(doc, &id_reader, id_index.terms());
                     let url = ff_to_string(doc, &url_reader, terms);
+                    let original_score =
+                        match ff_to_string(doc, &domain_reader, domain_index.terms()) {
+                            Some(domain) => original_score * decay.boost(&domain),
+                            None => original_score,
+                        };
 
-                    if let Some(url) = url {
+                    let score = if let Some(url) = url {
                         if regex_skip.is_match(&url) {
                             -1.0
                         } else if regex_allow.is_empty() || regex_allow.is_match(&url) {
-                            original_score * 1.0
+                            match &id {
+                                Some(id) if favorites.contains(id) => {
+                                    original_score * FAVORITE_BOOST
+                                }
+                                _ => original_score * 1.0,
+                            }
                         } else {
                             -1.0
                         }
                     } else {
                         // blank URL? that seems like an error somewhere.
                         -1.0
+                    };
+
+                    // Pins still respect lens filters, but otherwise go to the top
+                    // in the order they were pinned.
+                    match id.and_then(|id| pinned.iter().position(|p| *p == id)) {
+                        Some(pos) if score >= 0.0 => {
+                            score + PINNED_BOOST * (pinned.len() - pos) as Score
+                        }
+                        _ => score,
                     }
                 }
             });
@@ -265,6 +957,11 @@ impl Searcher {
         let top_docs = searcher
             .search(&query, &collector)
             .expect("Unable to execute query");
+        let top_docs = if clicks.is_empty() {
+            top_docs
+        } else {
+            clicks.rerank(&searcher, top_docs, MAX_RESULTS)
+        };
 
         log::debug!(
             "query `{}` returned {} results from {} docs in {} ms",
@@ -280,81 +977,214 @@ impl Searcher {
             .filter(|(score, _)| *score >= 0.0)
             .collect()
     }
+
+    /// Finds documents whose stored content or code matches `pattern`, for
+    /// when tokenized search can't express what's being looked for. Stops
+    /// after `SCAN_MAX_DOCS` documents or `SCAN_TIMEOUT`, whichever is first.
+    pub fn scan(
+        searcher: &Searcher,
+        applied_lenses: &[SearchFilter],
+        pattern: &Regex,
+    ) -> Vec<SearchResult> {
+        let start_timer = Instant::now();
+        let fields = DocFields::as_fields();
+        let reader = searcher.reader.searcher();
+
+        let mut allowed = Vec::new();
+        let mut skipped = Vec::new();
+        for filter in applied_lenses {
+            match filter {
+                SearchFilter::URLRegexAllow(regex) => allowed.push(regex),
+                SearchFilter::URLRegexSkip(regex) => skipped.push(regex),
+                SearchFilter::None => {}
+            }
+        }
+
+        let regex_allow = RegexSetBuilder::new(allowed)
+            .size_limit(100_000_000)
+            .build()
+            .expect("Unable to build regexset");
+        let regex_skip = RegexSetBuilder::new(skipped)
+            .size_limit(100_000_000)
+            .build()
+            .expect("Unable to build regexset");
+
+        let text = |doc: &Document, field: Field| -> Vec<String> {
+            doc.get_all(field)
+                .filter_map(|value| value.as_text())
+                .map(|value| value.to_string())
+                .collect()
+        };
+
+        let mut results = Vec::new();
+        let mut num_scanned = 0;
+        'segments: for (segment_ord, segment_reader) in reader.segment_readers().iter().enumerate()
+        {
+            for doc_id in segment_reader.doc_ids_alive() {
+                if results.len() >= SCAN_MAX_RESULTS {
+                    break 'segments;
+                }
+
+                if num_scanned >= SCAN_MAX_DOCS || start_timer.elapsed() >= SCAN_TIMEOUT {
+                    log::warn!(
+                        "scan for `{}` stopped early after {} docs",
+                        pattern,
+                        num_scanned
+                    );
+                    break 'segments;
+                }
+                num_scanned += 1;
+
+                let address = DocAddress::new(segment_ord as u32, doc_id);
+                let doc = match reader.doc(address) {
+                    Ok(doc) => doc,
+                    Err(_) => continue,
+                };
+
+                // Sections, notes & past versions are covered by the
+                // documents they belong to. Parts hold content the document
+                // itself doesn't.
+                let is_part = text(&doc, fields.id)
+                    .first()
+                    .map_or(false, |id| part_number(id).is_some());
+                if (doc.get_first(fields.parent_id).is_some() && !is_part)
+                    || doc.get_first(fields.superseded_at).is_some()
+                {
+                    continue;
+                }
+
+                let url = text(&doc, fields.url).join("");
+                if regex_skip.is_match(&url)
+                    || (!regex_allow.is_empty() && !regex_allow.is_match(&url))
+                {
+                    continue;
+                }
+
+                let is_match = text(&doc, fields.content)
+                    .iter()
+                    .chain(text(&doc, fields.code).iter())
+                    .any(|content| pattern.is_match(content));
+                if is_match {
+                    results.push((1.0, address));
+                }
+            }
+        }
+
+        log::debug!(
+            "scan for `{}` returned {} results from {} docs in {} ms",
+            pattern,
+            results.len(),
+            num_scanned,
+            start_timer.elapsed().as_millis()
+        );
+
+        results
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::search::{IndexPath, Searcher};
-    use entities::models::create_connection;
-    use shared::config::{Config, LensConfig};
+    use crate::scraper::Section;
+    use crate::search::clicks::ClickBoosts;
+    use crate::search::decay::DomainDecay;
+    use crate::search::snippet::Snippets;
+    use crate::search::{
+        part_number, split_parts, version_doc_id, DocumentUpdate, FilterNames, IndexPath,
+        QueryOptions, Searcher, Synonyms, PART_SPLIT_LENGTH,
+    };
+    use crate::state::AppState;
+    use chrono::{TimeZone, Utc};
+    use entities::models::{
+        collection, collection_document, create_connection, indexed_document, pinned_result,
+    };
+    use entities::schema::{DocFields, SearchDocument};
+    use entities::sea_orm::{ActiveModelTrait, EntityTrait, Set};
+    use entities::test::setup_test_db;
+    use regex::Regex;
+    use shared::config::{Config, DateLocale, LensConfig, UserSettings};
     use spyglass_plugin::SearchFilter;
 
     fn _build_test_index(searcher: &mut Searcher) {
         let writer = &mut searcher.writer.lock().unwrap();
         Searcher::upsert_document(
             writer,
-            None,
-            "Of Mice and Men",
-            "Of Mice and Men passage",
-            "example.com",
-            "https://example.com/mice_and_men",
-            "A few miles south of Soledad, the Salinas River drops in close to the hillside
-            bank and runs deep and green. The water is warm too, for it has slipped twinkling
-            over the yellow sands in the sunlight before reaching the narrow pool. On one
-            side of the river the golden foothill slopes curve up to the strong and rocky
-            Gabilan Mountains, but on the valley side the water is lined with trees—willows
-            fresh and green with every spring, carrying in their lower leaf junctures the
-            debris of the winter’s flooding; and sycamores with mottled, white, recumbent
-            limbs and branches that arch over the pool",
+            DocumentUpdate {
+                doc_id: None,
+                title: "Of Mice and Men",
+                description: "Of Mice and Men passage",
+                domain: "example.com",
+                url: "https://example.com/mice_and_men",
+                content:
+                    "A few miles south of Soledad, the Salinas River drops in close to the hillside
+                bank and runs deep and green. The water is warm too, for it has slipped twinkling
+                over the yellow sands in the sunlight before reaching the narrow pool. On one
+                side of the river the golden foothill slopes curve up to the strong and rocky
+                Gabilan Mountains, but on the valley side the water is lined with trees—willows
+                fresh and green with every spring, carrying in their lower leaf junctures the
+                debris of the winter’s flooding; and sycamores with mottled, white, recumbent
+                limbs and branches that arch over the pool",
+                ..Default::default()
+            },
         )
         .expect("Unable to add doc");
 
         Searcher::upsert_document(
             writer,
-            None,
-            "Of Mice and Men",
-            "Of Mice and Men passage",
-            "en.wikipedia.org",
-            "https://en.wikipedia.org/mice_and_men",
-            "A few miles south of Soledad, the Salinas River drops in close to the hillside
-            bank and runs deep and green. The water is warm too, for it has slipped twinkling
-            over the yellow sands in the sunlight before reaching the narrow pool. On one
-            side of the river the golden foothill slopes curve up to the strong and rocky
-            Gabilan Mountains, but on the valley side the water is lined with trees—willows
-            fresh and green with every spring, carrying in their lower leaf junctures the
-            debris of the winter’s flooding; and sycamores with mottled, white, recumbent
-            limbs and branches that arch over the pool",
+            DocumentUpdate {
+                doc_id: None,
+                title: "Of Mice and Men",
+                description: "Of Mice and Men passage",
+                domain: "en.wikipedia.org",
+                url: "https://en.wikipedia.org/mice_and_men",
+                content:
+                    "A few miles south of Soledad, the Salinas River drops in close to the hillside
+                bank and runs deep and green. The water is warm too, for it has slipped twinkling
+                over the yellow sands in the sunlight before reaching the narrow pool. On one
+                side of the river the golden foothill slopes curve up to the strong and rocky
+                Gabilan Mountains, but on the valley side the water is lined with trees—willows
+                fresh and green with every spring, carrying in their lower leaf junctures the
+                debris of the winter’s flooding; and sycamores with mottled, white, recumbent
+                limbs and branches that arch over the pool",
+                ..Default::default()
+            },
         )
         .expect("Unable to add doc");
 
         Searcher::upsert_document(
             writer,
-            None,
-            "Of Cheese and Crackers",
-            "Of Cheese and Crackers Passage",
-            "en.wikipedia.org",
-            "https://en.wikipedia.org/cheese_and_crackers",
-            "Lorem ipsum dolor sit amet, consectetur adipiscing elit. Nulla
-            tellus tortor, varius sit amet fermentum a, finibus porttitor erat. Proin
-            suscipit, dui ac posuere vulputate, justo est faucibus est, a bibendum
-            nulla nulla sed elit. Vivamus et libero a tortor ultricies feugiat in vel
-            eros. Donec rhoncus mauris libero, et imperdiet neque sagittis sed. Nulla
-            ac volutpat massa. Vivamus sed imperdiet est, id pretium ex. Praesent suscipit
-            mattis ipsum, a lacinia nunc semper vitae.",
+            DocumentUpdate {
+                doc_id: None,
+                title: "Of Cheese and Crackers",
+                description: "Of Cheese and Crackers Passage",
+                domain: "en.wikipedia.org",
+                url: "https://en.wikipedia.org/cheese_and_crackers",
+                content: "Lorem ipsum dolor sit amet, consectetur adipiscing elit. Nulla
+                tellus tortor, varius sit amet fermentum a, finibus porttitor erat. Proin
+                suscipit, dui ac posuere vulputate, justo est faucibus est, a bibendum
+                nulla nulla sed elit. Vivamus et libero a tortor ultricies feugiat in vel
+                eros. Donec rhoncus mauris libero, et imperdiet neque sagittis sed. Nulla
+                ac volutpat massa. Vivamus sed imperdiet est, id pretium ex. Praesent suscipit
+                mattis ipsum, a lacinia nunc semper vitae.",
+                ..Default::default()
+            },
         )
         .expect("Unable to add doc");
 
         Searcher::upsert_document(
             writer,
-            None,
-            "Frankenstein: The Modern Prometheus",
-            "A passage from Frankenstein",
-            "monster.com",
-            "https://example.com/frankenstein",
-            "You will rejoice to hear that no disaster has accompanied the commencement of an
-             enterprise which you have regarded with such evil forebodings.  I arrived here
-             yesterday, and my first task is to assure my dear sister of my welfare and
-             increasing confidence in the success of my undertaking.",
+            DocumentUpdate {
+                doc_id: None,
+                title: "Frankenstein: The Modern Prometheus",
+                description: "A passage from Frankenstein",
+                domain: "monster.com",
+                url: "https://example.com/frankenstein",
+                content: "You will rejoice to hear that no disaster has accompanied the commencement of an
+                 enterprise which you have regarded with such evil forebodings.  I arrived here
+                 yesterday, and my first task is to assure my dear sister of my welfare and
+                 increasing confidence in the success of my undertaking.",
+                code: &["let mut monsters = HashMap::new();".to_string()],
+                ..Default::default()
+            },
         )
         .expect("Unable to add doc");
 
@@ -367,6 +1197,1206 @@ mod test {
         std::thread::sleep(std::time::Duration::from_millis(1000));
     }
 
+    #[tokio::test]
+    pub async fn test_tag_counts() {
+        let db = setup_test_db().await;
+        let searcher = Searcher::with_index(&IndexPath::Memory).expect("Unable to open index");
+        let tag = |label: &str, value: &str| (label.to_string(), value.to_string());
+
+        let doc_id = {
+            let mut writer = searcher.writer.lock().unwrap();
+            let doc_id = Searcher::upsert_document(
+                &mut writer,
+                DocumentUpdate {
+                    title: "Salinas River",
+                    url: "https://example.com/river",
+                    content: "the salinas river runs deep and green",
+                    tags: &[tag("Source", "web"), tag("Lens", "rivers")],
+                    ..Default::default()
+                },
+            )
+            .expect("Unable to add doc");
+            Searcher::upsert_document(
+                &mut writer,
+                DocumentUpdate {
+                    title: "Salinas Valley",
+                    url: "file:///notes/valley.md",
+                    content: "the salinas valley is long and narrow",
+                    tags: &[tag("Source", "local")],
+                    ..Default::default()
+                },
+            )
+            .expect("Unable to add doc");
+            writer.commit().expect("Unable to commit");
+            doc_id
+        };
+        searcher.reader.reload().expect("Unable to reload");
+
+        let counts = Searcher::tag_counts(&db, &searcher, "salinas", QueryOptions::default())
+            .await
+            .expect("Unable to count tags");
+        assert_eq!(
+            counts,
+            vec![
+                ("Lens".to_string(), "rivers".to_string(), 1),
+                ("Source".to_string(), "local".to_string(), 1),
+                ("Source".to_string(), "web".to_string(), 1),
+            ]
+        );
+
+        // Only counts what matches the query
+        let counts = Searcher::tag_counts(&db, &searcher, "valley", QueryOptions::default())
+            .await
+            .expect("Unable to count tags");
+        assert_eq!(counts, vec![("Source".into(), "local".into(), 1)]);
+
+        // Filtering on tags
+        let counts = Searcher::tag_counts(
+            &db,
+            &searcher,
+            "salinas tag:rivers",
+            QueryOptions::default(),
+        )
+        .await
+        .expect("Unable to count tags");
+        assert_eq!(counts.len(), 2);
+        assert!(counts.iter().all(|(_, value, _)| value != "local"));
+
+        {
+            let mut writer = searcher.writer.lock().unwrap();
+            Searcher::update_tags(
+                &mut writer,
+                &searcher.reader,
+                &doc_id,
+                &[tag("Source", "web"), tag("Favorited", "favorited")],
+            )
+            .expect("Unable to update tags");
+            writer.commit().expect("Unable to commit");
+        }
+        searcher.reader.reload().expect("Unable to reload");
+
+        let counts = Searcher::tag_counts(&db, &searcher, "river", QueryOptions::default())
+            .await
+            .expect("Unable to count tags");
+        assert_eq!(
+            counts,
+            vec![
+                ("Favorited".to_string(), "favorited".to_string(), 1),
+                ("Source".to_string(), "web".to_string(), 1),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_snippets() {
+        let mut searcher = Searcher::with_index(&IndexPath::Memory).expect("Unable to open index");
+        _build_test_index(&mut searcher);
+
+        let doc = searcher
+            .reader
+            .searcher()
+            .doc(tantivy::DocAddress::new(0, 0))
+            .expect("Unable to get doc");
+
+        // Filters aren't highlighted
+        let snippets = Snippets::new(
+            &searcher,
+            "salinas domain:example.com",
+            &FilterNames::default(),
+            50,
+        );
+        let snippet = snippets.for_doc(&doc).expect("No snippet");
+        assert_eq!(snippet.field, "content");
+        assert!(snippet.text.len() <= 50);
+        assert_eq!(snippet.highlights.len(), 1);
+        let (start, end) = snippet.highlights[0];
+        assert_eq!(&snippet.text[start..end], "Salinas");
+
+        // Falls back to other fields when the content doesn't match
+        let snippet = Snippets::new(&searcher, "mice", &FilterNames::default(), 50)
+            .for_doc(&doc)
+            .expect("No snippet");
+        assert_eq!(snippet.field, "description");
+        assert!(snippet.text.contains("Mice"));
+
+        assert!(
+            Snippets::new(&searcher, "frankenstein", &FilterNames::default(), 50)
+                .for_doc(&doc)
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_code_search() {
+        let db = create_connection(&Config::default(), true).await.unwrap();
+        let mut searcher = Searcher::with_index(&IndexPath::Memory).expect("Unable to open index");
+        _build_test_index(&mut searcher);
+
+        let results = Searcher::search_with_lens(
+            db.clone(),
+            &Vec::new(),
+            &searcher,
+            "code:HashMap::new",
+            &DomainDecay::default(),
+            &ClickBoosts::default(),
+            QueryOptions::default(),
+        )
+        .await;
+        assert_eq!(results.len(), 1);
+
+        // Only matches code blocks, not the rest of the content.
+        let results = Searcher::search_with_lens(
+            db,
+            &Vec::new(),
+            &searcher,
+            "code:salinas",
+            &DomainDecay::default(),
+            &ClickBoosts::default(),
+            QueryOptions::default(),
+        )
+        .await;
+        assert_eq!(results.len(), 0);
+    }
+
+    #[tokio::test]
+    pub async fn test_fuzzy_search() {
+        let db = create_connection(&Config::default(), true).await.unwrap();
+        let mut searcher = Searcher::with_index(&IndexPath::Memory).expect("Unable to open index");
+        _build_test_index(&mut searcher);
+
+        let results = Searcher::search_with_lens(
+            db.clone(),
+            &Vec::new(),
+            &searcher,
+            "salnias",
+            &DomainDecay::default(),
+            &ClickBoosts::default(),
+            QueryOptions {
+                fuzzy_distance: 1,
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(results.len(), 2);
+
+        // Typos aren't tolerated when turned off
+        let results = Searcher::search_with_lens(
+            db,
+            &Vec::new(),
+            &searcher,
+            "salnias",
+            &DomainDecay::default(),
+            &ClickBoosts::default(),
+            QueryOptions::default(),
+        )
+        .await;
+        assert_eq!(results.len(), 0);
+    }
+
+    #[tokio::test]
+    pub async fn test_synonym_search() {
+        let db = create_connection(&Config::default(), true).await.unwrap();
+        let mut searcher = Searcher::with_index(&IndexPath::Memory).expect("Unable to open index");
+        _build_test_index(&mut searcher);
+
+        let synonyms = Synonyms::parse("monterey => salinas");
+        let results = Searcher::search_with_lens(
+            db.clone(),
+            &Vec::new(),
+            &searcher,
+            "monterey",
+            &DomainDecay::default(),
+            &ClickBoosts::default(),
+            QueryOptions {
+                synonyms: Some(&synonyms),
+                ..Default::default()
+            },
+        )
+        .await;
+        assert_eq!(results.len(), 2);
+
+        let results = Searcher::search_with_lens(
+            db,
+            &Vec::new(),
+            &searcher,
+            "monterey",
+            &DomainDecay::default(),
+            &ClickBoosts::default(),
+            QueryOptions::default(),
+        )
+        .await;
+        assert_eq!(results.len(), 0);
+    }
+
+    #[tokio::test]
+    pub async fn test_language_search() {
+        let db = create_connection(&Config::default(), true).await.unwrap();
+        let searcher = Searcher::with_index(&IndexPath::Memory).expect("Unable to open index");
+        {
+            let mut writer = searcher.writer.lock().unwrap();
+            Searcher::upsert_document(
+                &mut writer,
+                DocumentUpdate {
+                    title: "Die Häuser am Fluss",
+                    domain: "example.de",
+                    url: "https://example.de/haeuser",
+                    content: "Die alten Häuser stehen seit vielen Jahren am Ufer des Flusses. \
+                        Im Sommer sitzen die Familien in ihren Gärten und schauen auf das Wasser, \
+                        während die Kinder zwischen den Bäumen spielen.",
+                    ..Default::default()
+                },
+            )
+            .expect("Unable to add doc");
+            writer.commit().expect("Unable to commit");
+        }
+        searcher.reader.reload().expect("Unable to reload");
+
+        // Stemming matches other forms of the same word.
+        let results = Searcher::search_with_lens(
+            db.clone(),
+            &Vec::new(),
+            &searcher,
+            "haus",
+            &DomainDecay::default(),
+            &ClickBoosts::default(),
+            QueryOptions::default(),
+        )
+        .await;
+        assert_eq!(results.len(), 1);
+
+        let results = Searcher::search_with_lens(
+            db.clone(),
+            &Vec::new(),
+            &searcher,
+            "gärten lang:german",
+            &DomainDecay::default(),
+            &ClickBoosts::default(),
+            QueryOptions::default(),
+        )
+        .await;
+        assert_eq!(results.len(), 1);
+
+        let results = Searcher::search_with_lens(
+            db,
+            &Vec::new(),
+            &searcher,
+            "gärten lang:fra",
+            &DomainDecay::default(),
+            &ClickBoosts::default(),
+            QueryOptions::default(),
+        )
+        .await;
+        assert_eq!(results.len(), 0);
+    }
+
+    #[tokio::test]
+    pub async fn test_exclusion_search() {
+        let db = create_connection(&Config::default(), true).await.unwrap();
+        let mut searcher = Searcher::with_index(&IndexPath::Memory).expect("Unable to open index");
+        _build_test_index(&mut searcher);
+
+        let results = Searcher::search_with_lens(
+            db.clone(),
+            &Vec::new(),
+            &searcher,
+            "salinas -domain:example.com",
+            &DomainDecay::default(),
+            &ClickBoosts::default(),
+            QueryOptions::default(),
+        )
+        .await;
+        assert_eq!(results.len(), 1);
+
+        // Only exclusions matches everything else
+        let results = Searcher::search_with_lens(
+            db,
+            &Vec::new(),
+            &searcher,
+            "-salinas -domain:monster.com",
+            &DomainDecay::default(),
+            &ClickBoosts::default(),
+            QueryOptions::default(),
+        )
+        .await;
+        assert_eq!(results.len(), 1);
+    }
+
+    #[tokio::test]
+    pub async fn test_query_language() {
+        let db = create_connection(&Config::default(), true).await.unwrap();
+        let mut searcher = Searcher::with_index(&IndexPath::Memory).expect("Unable to open index");
+        _build_test_index(&mut searcher);
+
+        let expected = [
+            // Subdomains & URL prefixes
+            ("site:wikipedia.org", 2),
+            ("site:example.com", 1),
+            ("site:https://example.com/franken", 1),
+            ("salinas site:example.com/cheese", 0),
+            // Phrases
+            (r#""river drops""#, 2),
+            (r#""drops river""#, 0),
+            (r#"salinas -"cheese and crackers""#, 2),
+            // Groups
+            ("cheese OR frankenstein", 2),
+            ("(site:example.com OR site:monster.com) -salinas", 1),
+            ("-(salinas OR cheese)", 1),
+        ];
+
+        for (query, num_results) in expected {
+            let results = Searcher::search_with_lens(
+                db.clone(),
+                &Vec::new(),
+                &searcher,
+                query,
+                &DomainDecay::default(),
+                &ClickBoosts::default(),
+                QueryOptions::default(),
+            )
+            .await;
+            assert_eq!(results.len(), num_results, "{}", query);
+        }
+    }
+
+    #[test]
+    pub fn test_scan() {
+        let mut searcher = Searcher::with_index(&IndexPath::Memory).expect("Unable to open index");
+        _build_test_index(&mut searcher);
+
+        // Matches code blocks character for character
+        let pattern = Regex::new(&regex::escape("HashMap::new(")).unwrap();
+        let results = Searcher::scan(&searcher, &[], &pattern);
+        assert_eq!(results.len(), 1);
+
+        let pattern = Regex::new(r"Salinas\s+River").unwrap();
+        let results = Searcher::scan(&searcher, &[], &pattern);
+        assert_eq!(results.len(), 2);
+
+        // Lens filters still apply
+        let filters = vec![SearchFilter::URLRegexAllow(
+            "^https://en.wikipedia.org/.*".to_string(),
+        )];
+        let results = Searcher::scan(&searcher, &filters, &pattern);
+        assert_eq!(results.len(), 1);
+    }
+
+    #[tokio::test]
+    pub async fn test_number_search() {
+        let db = create_connection(&Config::default(), true).await.unwrap();
+        let mut searcher = Searcher::with_index(&IndexPath::Memory).expect("Unable to open index");
+        _build_test_index(&mut searcher);
+
+        // Word counts are indexed for every document
+        let results = Searcher::search_with_lens(
+            db.clone(),
+            &Vec::new(),
+            &searcher,
+            "words:<60",
+            &DomainDecay::default(),
+            &ClickBoosts::default(),
+            QueryOptions::default(),
+        )
+        .await;
+        assert_eq!(results.len(), 1);
+
+        let results = Searcher::search_with_lens(
+            db,
+            &Vec::new(),
+            &searcher,
+            "-words:<60",
+            &DomainDecay::default(),
+            &ClickBoosts::default(),
+            QueryOptions::default(),
+        )
+        .await;
+        assert_eq!(results.len(), 3);
+    }
+
+    #[tokio::test]
+    pub async fn test_section_search() {
+        let db = create_connection(&Config::default(), true).await.unwrap();
+        let searcher = Searcher::with_index(&IndexPath::Memory).expect("Unable to open index");
+        let fields = DocFields::as_fields();
+
+        let sections = vec![
+            Section {
+                anchor: "intro".into(),
+                heading: "Introduction".into(),
+                content: "filler ".repeat(4000),
+            },
+            Section {
+                anchor: "troubleshooting".into(),
+                heading: "Troubleshooting".into(),
+                content: "If the flux capacitor overheats, restart it.".into(),
+            },
+        ];
+        let content = sections
+            .iter()
+            .map(|s| s.content.clone())
+            .collect::<Vec<String>>()
+            .join(" ");
+
+        let doc_id = {
+            let mut writer = searcher.writer.lock().unwrap();
+            let doc_id = Searcher::upsert_document(
+                &mut writer,
+                DocumentUpdate {
+                    title: "Manual",
+                    url: "https://example.com/manual",
+                    content: &content,
+                    sections: &sections,
+                    ..Default::default()
+                },
+            )
+            .expect("Unable to add doc");
+            writer.commit().expect("Unable to commit");
+            doc_id
+        };
+        searcher.reader.reload().expect("Unable to reload");
+
+        let results = Searcher::search_with_lens(
+            db.clone(),
+            &Vec::new(),
+            &searcher,
+            "capacitor",
+            &DomainDecay::default(),
+            &ClickBoosts::default(),
+            QueryOptions::default(),
+        )
+        .await;
+        let parents = results
+            .iter()
+            .filter_map(|(_, addr)| searcher.reader.searcher().doc(*addr).ok())
+            .filter_map(|doc| {
+                doc.get_first(fields.parent_id)
+                    .and_then(|v| v.as_text())
+                    .map(|v| v.to_string())
+            })
+            .collect::<Vec<String>>();
+        assert_eq!(parents, vec![doc_id.clone()]);
+
+        // Removing the document also removes its sections
+        {
+            let mut writer = searcher.writer.lock().unwrap();
+            Searcher::remove_from_index(&mut writer, &doc_id).expect("Unable to remove");
+            writer.commit().expect("Unable to commit");
+        }
+        searcher.reader.reload().expect("Unable to reload");
+        let results = Searcher::search_with_lens(
+            db,
+            &Vec::new(),
+            &searcher,
+            "capacitor",
+            &DomainDecay::default(),
+            &ClickBoosts::default(),
+            QueryOptions::default(),
+        )
+        .await;
+        assert!(results.is_empty());
+    }
+
+    #[test]
+    pub fn test_split_parts() {
+        assert_eq!(split_parts("short", 10), vec!["short"]);
+        // Breaks on lines, then words
+        assert_eq!(
+            split_parts("one two\nthree four five", 10),
+            vec!["one two\n", "three ", "four five"]
+        );
+        // Or wherever it has to, but never inside a character
+        assert_eq!(split_parts("ééééé", 5), vec!["éé", "éé", "é"]);
+    }
+
+    #[tokio::test]
+    pub async fn test_part_search() {
+        let db = create_connection(&Config::default(), true).await.unwrap();
+        let searcher = Searcher::with_index(&IndexPath::Memory).expect("Unable to open index");
+        let fields = DocFields::as_fields();
+
+        let content = format!(
+            "{} If the flux capacitor overheats, restart it.",
+            "filler ".repeat(PART_SPLIT_LENGTH / 7)
+        );
+        let doc_id = {
+            let mut writer = searcher.writer.lock().unwrap();
+            let doc_id = Searcher::upsert_document(
+                &mut writer,
+                DocumentUpdate {
+                    title: "server.log",
+                    url: "file:///var/log/server.log",
+                    content: &content,
+                    ..Default::default()
+                },
+            )
+            .expect("Unable to add doc");
+            writer.commit().expect("Unable to commit");
+            doc_id
+        };
+        searcher.reader.reload().expect("Unable to reload");
+
+        let stored = Searcher::get_by_id(&searcher.reader, &doc_id).expect("Missing doc");
+        let stored_len = stored
+            .get_first(fields.content)
+            .and_then(|value| value.as_text())
+            .map_or(0, |content| content.len());
+        assert!(stored_len <= PART_SPLIT_LENGTH);
+
+        let results = Searcher::search_with_lens(
+            db.clone(),
+            &Vec::new(),
+            &searcher,
+            "capacitor",
+            &DomainDecay::default(),
+            &ClickBoosts::default(),
+            QueryOptions::default(),
+        )
+        .await;
+        let parts = results
+            .iter()
+            .filter_map(|(_, addr)| searcher.reader.searcher().doc(*addr).ok())
+            .filter_map(|doc| {
+                let parent = doc.get_first(fields.parent_id)?.as_text()?.to_string();
+                let id = doc.get_first(fields.id)?.as_text()?;
+                Some((parent, part_number(id)))
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(parts, vec![(doc_id.clone(), Some(2))]);
+
+        // Parts go along w/ the document
+        {
+            let mut writer = searcher.writer.lock().unwrap();
+            Searcher::remove_from_index(&mut writer, &doc_id).expect("Unable to remove");
+            writer.commit().expect("Unable to commit");
+        }
+        searcher.reader.reload().expect("Unable to reload");
+        let results = Searcher::search_with_lens(
+            db,
+            &Vec::new(),
+            &searcher,
+            "capacitor",
+            &DomainDecay::default(),
+            &ClickBoosts::default(),
+            QueryOptions::default(),
+        )
+        .await;
+        assert!(results.is_empty());
+    }
+
+    #[tokio::test]
+    pub async fn test_collection_search() {
+        let db = setup_test_db().await;
+        let mut searcher = Searcher::with_index(&IndexPath::Memory).expect("Unable to open index");
+        _build_test_index(&mut searcher);
+
+        let doc_id = {
+            let mut writer = searcher.writer.lock().unwrap();
+            let doc_id = Searcher::upsert_document(
+                &mut writer,
+                DocumentUpdate {
+                    title: "Deploy runbook",
+                    domain: "wiki.example.com",
+                    url: "https://wiki.example.com/runbooks/deploy",
+                    content: "Steps for rolling back a bad deploy of the river gauge dashboard.",
+                    ..Default::default()
+                },
+            )
+            .expect("Unable to add doc");
+            writer.commit().expect("Unable to commit");
+            doc_id
+        };
+        searcher.reader.reload().expect("Unable to reload");
+
+        let doc = indexed_document::ActiveModel {
+            domain: Set("wiki.example.com".into()),
+            url: Set("https://wiki.example.com/runbooks/deploy".into()),
+            doc_id: Set(doc_id),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        let research = collection::ActiveModel {
+            name: Set("research".into()),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        collection::add_document(&db, &research, &doc)
+            .await
+            .unwrap();
+
+        // Other docs in the test index also mention rivers
+        let results = Searcher::search_with_lens(
+            db.clone(),
+            &Vec::new(),
+            &searcher,
+            "river collection:research",
+            &DomainDecay::default(),
+            &ClickBoosts::default(),
+            QueryOptions::default(),
+        )
+        .await;
+        assert_eq!(results.len(), 1);
+
+        let results = Searcher::search_with_lens(
+            db.clone(),
+            &Vec::new(),
+            &searcher,
+            "collection:research",
+            &DomainDecay::default(),
+            &ClickBoosts::default(),
+            QueryOptions::default(),
+        )
+        .await;
+        assert_eq!(results.len(), 1);
+
+        // Collections in groups & exclusions
+        let results = Searcher::search_with_lens(
+            db.clone(),
+            &Vec::new(),
+            &searcher,
+            "river (collection:research OR collection:unknown)",
+            &DomainDecay::default(),
+            &ClickBoosts::default(),
+            QueryOptions::default(),
+        )
+        .await;
+        assert_eq!(results.len(), 1);
+
+        let results = Searcher::search_with_lens(
+            db.clone(),
+            &Vec::new(),
+            &searcher,
+            "river collection:research -(collection:research OR tag:archive)",
+            &DomainDecay::default(),
+            &ClickBoosts::default(),
+            QueryOptions::default(),
+        )
+        .await;
+        assert!(results.is_empty());
+
+        let results = Searcher::search_with_lens(
+            db,
+            &Vec::new(),
+            &searcher,
+            "river collection:unknown",
+            &DomainDecay::default(),
+            &ClickBoosts::default(),
+            QueryOptions::default(),
+        )
+        .await;
+        assert!(results.is_empty());
+    }
+
+    #[tokio::test]
+    pub async fn test_delete_collected_pinned_document() {
+        let db = setup_test_db().await;
+        let state = AppState::builder()
+            .with_db(db.clone())
+            .with_user_settings(&UserSettings::default())
+            .with_index(&IndexPath::Memory)
+            .build();
+
+        let doc = indexed_document::ActiveModel {
+            domain: Set("wiki.example.com".into()),
+            url: Set("https://wiki.example.com/runbooks/deploy".into()),
+            doc_id: Set("deploy-runbook".into()),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        let research = collection::ActiveModel {
+            name: Set("research".into()),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        collection::add_document(&db, &research, &doc)
+            .await
+            .unwrap();
+        pinned_result::pin(&db, "rollback", &doc).await.unwrap();
+
+        Searcher::delete_by_id(&state, "deploy-runbook")
+            .await
+            .expect("Unable to delete doc");
+        assert!(indexed_document::Entity::find_by_id(doc.id)
+            .one(&db)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(collection_document::Entity::find()
+            .all(&db)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(pinned_result::Entity::find()
+            .all(&db)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    pub async fn test_note_search() {
+        let db = create_connection(&Config::default(), true).await.unwrap();
+        let searcher = Searcher::with_index(&IndexPath::Memory).expect("Unable to open index");
+        let fields = DocFields::as_fields();
+
+        let doc_id = {
+            let mut writer = searcher.writer.lock().unwrap();
+            let doc_id = Searcher::upsert_document(
+                &mut writer,
+                DocumentUpdate {
+                    title: "Deploy runbook",
+                    url: "https://wiki.example.com/runbooks/deploy",
+                    content: "Steps for rolling back a bad deploy.",
+                    notes: &[(1, "ask the platform team about canaries".into())],
+                    ..Default::default()
+                },
+            )
+            .expect("Unable to add doc");
+            writer.commit().expect("Unable to commit");
+            doc_id
+        };
+        searcher.reader.reload().expect("Unable to reload");
+
+        let parent_of = |results: Vec<(f32, tantivy::DocAddress)>| {
+            results
+                .iter()
+                .filter_map(|(_, addr)| searcher.reader.searcher().doc(*addr).ok())
+                .filter_map(|doc| {
+                    doc.get_first(fields.parent_id)
+                        .and_then(|v| v.as_text())
+                        .map(|v| v.to_string())
+                })
+                .collect::<Vec<String>>()
+        };
+
+        let results = Searcher::search_with_lens(
+            db.clone(),
+            &Vec::new(),
+            &searcher,
+            "canaries",
+            &DomainDecay::default(),
+            &ClickBoosts::default(),
+            QueryOptions::default(),
+        )
+        .await;
+        assert_eq!(parent_of(results), vec![doc_id.clone()]);
+
+        // Updating a note replaces the old text
+        {
+            let mut writer = searcher.writer.lock().unwrap();
+            Searcher::upsert_note(
+                &mut writer,
+                &searcher.reader,
+                &doc_id,
+                1,
+                "ask about feature flags",
+            )
+            .expect("Unable to update note");
+            writer.commit().expect("Unable to commit");
+        }
+        searcher.reader.reload().expect("Unable to reload");
+
+        let results = Searcher::search_with_lens(
+            db.clone(),
+            &Vec::new(),
+            &searcher,
+            "canaries",
+            &DomainDecay::default(),
+            &ClickBoosts::default(),
+            QueryOptions::default(),
+        )
+        .await;
+        assert!(results.is_empty());
+        let results = Searcher::search_with_lens(
+            db,
+            &Vec::new(),
+            &searcher,
+            "flags",
+            &DomainDecay::default(),
+            &ClickBoosts::default(),
+            QueryOptions::default(),
+        )
+        .await;
+        assert_eq!(parent_of(results), vec![doc_id]);
+    }
+
+    #[tokio::test]
+    pub async fn test_pinned_search() {
+        let db = setup_test_db().await;
+        let mut searcher = Searcher::with_index(&IndexPath::Memory).expect("Unable to open index");
+        _build_test_index(&mut searcher);
+
+        let doc_id = {
+            let mut writer = searcher.writer.lock().unwrap();
+            let doc_id = Searcher::upsert_document(
+                &mut writer,
+                DocumentUpdate {
+                    title: "Deploy runbook",
+                    domain: "wiki.example.com",
+                    url: "https://wiki.example.com/runbooks/deploy",
+                    content: "Steps for rolling back a bad deploy.",
+                    ..Default::default()
+                },
+            )
+            .expect("Unable to add doc");
+            writer.commit().expect("Unable to commit");
+            doc_id
+        };
+        searcher.reader.reload().expect("Unable to reload");
+
+        let doc = indexed_document::ActiveModel {
+            domain: Set("wiki.example.com".into()),
+            url: Set("https://wiki.example.com/runbooks/deploy".into()),
+            doc_id: Set(doc_id.clone()),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .unwrap();
+        pinned_result::pin(&db, "salinas river", &doc)
+            .await
+            .unwrap();
+
+        // Pinned doc doesn't mention the river at all, but still comes first.
+        let fields = DocFields::as_fields();
+        let results = Searcher::search_with_lens(
+            db.clone(),
+            &Vec::new(),
+            &searcher,
+            "Salinas River",
+            &DomainDecay::default(),
+            &ClickBoosts::default(),
+            QueryOptions::default(),
+        )
+        .await;
+        assert!(results.len() > 1);
+        let top = searcher
+            .reader
+            .searcher()
+            .doc(results[0].1)
+            .expect("Unable to get doc");
+        assert_eq!(
+            top.get_first(fields.id).and_then(|v| v.as_text()),
+            Some(doc_id.as_str())
+        );
+
+        // Other queries are unaffected
+        let results = Searcher::search_with_lens(
+            db,
+            &Vec::new(),
+            &searcher,
+            "salinas",
+            &DomainDecay::default(),
+            &ClickBoosts::default(),
+            QueryOptions::default(),
+        )
+        .await;
+        assert!(results.iter().all(|(_, addr)| {
+            let doc = searcher
+                .reader
+                .searcher()
+                .doc(*addr)
+                .expect("Unable to get doc");
+            doc.get_first(fields.id).and_then(|v| v.as_text()) != Some(doc_id.as_str())
+        }));
+    }
+
+    #[tokio::test]
+    pub async fn test_as_of_search() {
+        let db = setup_test_db().await;
+        let searcher = Searcher::with_index(&IndexPath::Memory).expect("Unable to open index");
+        let fields = DocFields::as_fields();
+        let first_crawl = Utc.ymd(2022, 11, 1).and_hms(12, 0, 0);
+        let second_crawl = Utc.ymd(2022, 12, 1).and_hms(12, 0, 0);
+
+        let update = DocumentUpdate {
+            doc_id: Some("status".into()),
+            title: "Bridge status",
+            domain: "city.example.com",
+            url: "https://city.example.com/bridge",
+            content: "The drawbridge is open to traffic.",
+            crawled_at: Some(first_crawl),
+            ..Default::default()
+        };
+        {
+            let mut writer = searcher.writer.lock().unwrap();
+            Searcher::upsert_document(&mut writer, update.clone()).expect("Unable to add doc");
+            writer.commit().expect("Unable to commit");
+        }
+        searcher.reader.reload().expect("Unable to reload");
+
+        // Re-crawled w/ new content, keeping the old version around
+        {
+            let previous =
+                Searcher::get_by_id(&searcher.reader, "status").expect("Missing document");
+            let mut writer = searcher.writer.lock().unwrap();
+            Searcher::add_version(
+                &mut writer,
+                &previous,
+                "status",
+                &first_crawl,
+                &second_crawl,
+            )
+            .expect("Unable to add version");
+            Searcher::remove_from_index(&mut writer, "status").expect("Unable to remove");
+            Searcher::upsert_document(
+                &mut writer,
+                DocumentUpdate {
+                    content: "The drawbridge is closed for repairs.",
+                    crawled_at: Some(second_crawl),
+                    ..update
+                },
+            )
+            .expect("Unable to add doc");
+            writer.commit().expect("Unable to commit");
+        }
+        searcher.reader.reload().expect("Unable to reload");
+
+        let search = |query: &'static str| {
+            let db = db.clone();
+            let searcher = searcher.clone();
+            let fields = fields.clone();
+            async move {
+                Searcher::search_with_lens(
+                    db,
+                    &Vec::new(),
+                    &searcher,
+                    query,
+                    &DomainDecay::default(),
+                    &ClickBoosts::default(),
+                    QueryOptions::default(),
+                )
+                .await
+                .iter()
+                .filter_map(|(_, addr)| searcher.reader.searcher().doc(*addr).ok())
+                .filter_map(|doc| {
+                    doc.get_first(fields.id)
+                        .and_then(|v| v.as_text())
+                        .map(|v| v.to_string())
+                })
+                .collect::<Vec<String>>()
+            }
+        };
+
+        // Past versions are hidden unless searching w/ as_of
+        assert_eq!(search("drawbridge").await, vec!["status".to_string()]);
+        assert!(search("open").await.is_empty());
+
+        let version_id = version_doc_id("status", &first_crawl);
+        assert_eq!(
+            search("open as_of:2022-11-15").await,
+            vec![version_id.clone()]
+        );
+        assert_eq!(
+            search("drawbridge as_of:2022-11-15").await,
+            vec![version_id]
+        );
+        assert!(search("closed as_of:2022-11-15").await.is_empty());
+        assert_eq!(
+            search("closed as_of:2022-12-15").await,
+            vec!["status".to_string()]
+        );
+        // Nothing had been crawled yet
+        assert!(search("drawbridge as_of:2022-10-01").await.is_empty());
+    }
+
+    #[tokio::test]
+    pub async fn test_click_rerank() {
+        let db = setup_test_db().await;
+        let searcher = Searcher::with_index(&IndexPath::Memory).expect("Unable to open index");
+        let fields = DocFields::as_fields();
+
+        {
+            let mut writer = searcher.writer.lock().unwrap();
+            // Same content, so only clicks set them apart
+            for doc_id in ["upstream", "downstream"] {
+                let url = format!("https://example.com/{}", doc_id);
+                Searcher::upsert_document(
+                    &mut writer,
+                    DocumentUpdate {
+                        doc_id: Some(doc_id.into()),
+                        title: "Salinas River",
+                        domain: "example.com",
+                        url: &url,
+                        content: "The Salinas River runs through the Salinas Valley.",
+                        ..Default::default()
+                    },
+                )
+                .expect("Unable to add doc");
+            }
+            writer.commit().expect("Unable to commit");
+        }
+        searcher.reader.reload().expect("Unable to reload");
+
+        let search = |clicks: ClickBoosts| {
+            let db = db.clone();
+            let searcher = searcher.clone();
+            let fields = fields.clone();
+            async move {
+                Searcher::search_with_lens(
+                    db,
+                    &Vec::new(),
+                    &searcher,
+                    "salinas river",
+                    &DomainDecay::default(),
+                    &clicks,
+                    QueryOptions::default(),
+                )
+                .await
+                .iter()
+                .filter_map(|(_, addr)| searcher.reader.searcher().doc(*addr).ok())
+                .filter_map(|doc| {
+                    doc.get_first(fields.id)
+                        .and_then(|v| v.as_text())
+                        .map(|v| v.to_string())
+                })
+                .collect::<Vec<String>>()
+            }
+        };
+
+        let now = Utc::now();
+        for opened in ["upstream", "downstream"] {
+            let clicks = ClickBoosts::from_clicks(vec![(opened.to_string(), now); 10], now);
+            assert_eq!(search(clicks).await[0], opened.to_string());
+        }
+    }
+
+    #[tokio::test]
+    pub async fn test_date_search() {
+        let db = setup_test_db().await;
+        let searcher = Searcher::with_index(&IndexPath::Memory).expect("Unable to open index");
+        let fields = DocFields::as_fields();
+
+        {
+            let mut writer = searcher.writer.lock().unwrap();
+            for (doc_id, crawled_at) in [
+                ("november", Utc.ymd(2022, 11, 1).and_hms(12, 0, 0)),
+                ("december", Utc.ymd(2022, 12, 1).and_hms(12, 0, 0)),
+            ] {
+                let url = format!("https://city.example.com/{}", doc_id);
+                Searcher::upsert_document(
+                    &mut writer,
+                    DocumentUpdate {
+                        doc_id: Some(doc_id.into()),
+                        title: "Bridge status",
+                        domain: "city.example.com",
+                        url: &url,
+                        content: "The drawbridge is open to traffic.",
+                        crawled_at: Some(crawled_at),
+                        ..Default::default()
+                    },
+                )
+                .expect("Unable to add doc");
+            }
+            writer.commit().expect("Unable to commit");
+        }
+        searcher.reader.reload().expect("Unable to reload");
+
+        let search = |query: &'static str, date_locale: DateLocale| {
+            let db = db.clone();
+            let searcher = searcher.clone();
+            let fields = fields.clone();
+            async move {
+                Searcher::search_with_lens(
+                    db,
+                    &Vec::new(),
+                    &searcher,
+                    query,
+                    &DomainDecay::default(),
+                    &ClickBoosts::default(),
+                    QueryOptions {
+                        date_locale,
+                        ..Default::default()
+                    },
+                )
+                .await
+                .iter()
+                .filter_map(|(_, addr)| searcher.reader.searcher().doc(*addr).ok())
+                .filter_map(|doc| {
+                    doc.get_first(fields.id)
+                        .and_then(|v| v.as_text())
+                        .map(|v| v.to_string())
+                })
+                .collect::<Vec<String>>()
+            }
+        };
+
+        assert_eq!(
+            search("drawbridge after:2022-11-15", DateLocale::En).await,
+            vec!["december".to_string()]
+        );
+        assert_eq!(
+            search("drawbridge before:\"november 15 2022\"", DateLocale::En).await,
+            vec!["november".to_string()]
+        );
+        // Dates on their own match everything crawled then
+        assert_eq!(
+            search("after:\"15 novembre 2022\"", DateLocale::Fr).await,
+            vec!["december".to_string()]
+        );
+        assert!(search("drawbridge after:\"2 weeks ago\"", DateLocale::En)
+            .await
+            .is_empty());
+        // Invalid dates are ignored
+        assert_eq!(
+            search("drawbridge after:someday", DateLocale::En)
+                .await
+                .len(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    pub async fn test_matching_ids() {
+        let db = setup_test_db().await;
+        let searcher = Searcher::with_index(&IndexPath::Memory).expect("Unable to open index");
+
+        {
+            let mut writer = searcher.writer.lock().unwrap();
+            for (doc_id, content) in [
+                ("river", "The Salinas River runs through the valley."),
+                ("lake", "Lake Tahoe is in the Sierra Nevada."),
+                ("valley", "The Salinas Valley is known for its vegetables."),
+            ] {
+                let url = format!("https://example.com/{}", doc_id);
+                Searcher::upsert_document(
+                    &mut writer,
+                    DocumentUpdate {
+                        doc_id: Some(doc_id.into()),
+                        title: doc_id,
+                        url: &url,
+                        content,
+                        ..Default::default()
+                    },
+                )
+                .expect("Unable to add doc");
+            }
+            writer.commit().expect("Unable to commit");
+        }
+        searcher.reader.reload().expect("Unable to reload");
+
+        let doc_ids = vec!["river".to_string(), "lake".to_string()];
+        let matching =
+            Searcher::matching_ids(&db, &searcher, "salinas", &doc_ids, QueryOptions::default())
+                .await
+                .expect("Unable to search");
+        // The valley matches too, but wasn't asked about
+        assert_eq!(matching, vec!["river".to_string()]);
+
+        assert!(
+            Searcher::matching_ids(&db, &searcher, "salinas", &[], QueryOptions::default())
+                .await
+                .expect("Unable to search")
+                .is_empty()
+        );
+    }
+
     #[tokio::test]
     pub async fn test_basic_lense_search() {
         let db = create_connection(&Config::default(), true).await.unwrap();
@@ -387,7 +2417,16 @@ mod test {
         _build_test_index(&mut searcher);
 
         let query = "salinas";
-        let results = Searcher::search_with_lens(db, &applied_lens, &searcher, query).await;
+        let results = Searcher::search_with_lens(
+            db,
+            &applied_lens,
+            &searcher,
+            query,
+            &DomainDecay::default(),
+            &ClickBoosts::default(),
+            QueryOptions::default(),
+        )
+        .await;
         assert_eq!(results.len(), 1);
     }
 
@@ -412,7 +2451,16 @@ mod test {
         _build_test_index(&mut searcher);
 
         let query = "salinas";
-        let results = Searcher::search_with_lens(db, &applied_lens, &searcher, query).await;
+        let results = Searcher::search_with_lens(
+            db,
+            &applied_lens,
+            &searcher,
+            query,
+            &DomainDecay::default(),
+            &ClickBoosts::default(),
+            QueryOptions::default(),
+        )
+        .await;
         assert_eq!(results.len(), 1);
     }
 
@@ -438,7 +2486,16 @@ mod test {
         _build_test_index(&mut searcher);
 
         let query = "salinas";
-        let results = Searcher::search_with_lens(db, &applied_lens, &searcher, query).await;
+        let results = Searcher::search_with_lens(
+            db,
+            &applied_lens,
+            &searcher,
+            query,
+            &DomainDecay::default(),
+            &ClickBoosts::default(),
+            QueryOptions::default(),
+        )
+        .await;
         assert_eq!(results.len(), 0);
     }
 }
@@ -0,0 +1,529 @@
+//! Query language for searches. Besides free text, queries can have:
+//! - `name:value` filters, e.g. `site:example.com`, `type:pdf` or `tag:work`.
+//!   Values w/ spaces can be quoted, e.g. `author:"jane doe"` or
+//!   `after:"last tuesday"`. Only the names in `FilterNames` are filters,
+//!   anything else is searched for as text.
+//! - Quoted phrases, e.g. `"salinas river"`.
+//! - `-` to exclude a word, phrase, filter or group, e.g. `-domain:reddit.com`.
+//! - `OR` between terms & parentheses to group them, e.g.
+//!   `(site:docs.rs OR site:crates.io) tokio`.
+
+use std::collections::HashSet;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Clause {
+    /// A word of free text.
+    Text(String),
+    /// Quoted text, matched as a phrase.
+    Phrase(String),
+    /// `name:value` filter.
+    Filter(String, String),
+    /// Excludes anything matching the clause.
+    Not(Box<Clause>),
+    /// Matches any of the clauses.
+    Any(Vec<Clause>),
+    /// Matches all of the clauses, from a parenthesized group.
+    All(Vec<Clause>),
+}
+
+impl Clause {
+    fn negate(self, negated: bool) -> Clause {
+        if negated {
+            Clause::Not(Box::new(self))
+        } else {
+            self
+        }
+    }
+}
+
+/// Groups nested deeper than this are flattened into the group they're in,
+/// so queries can't nest without limit.
+const MAX_GROUP_DEPTH: usize = 8;
+
+/// Filters handled by the search itself rather than matching a field.
+const SEARCH_FILTERS: &[&str] = &[
+    "after",
+    "as_of",
+    "before",
+    "code",
+    "collection",
+    "domain",
+    "lang",
+    "near",
+    "site",
+    "tag",
+    "type",
+];
+
+/// Fields documents are indexed w/ by crawlers, parsers & importers. Fields
+/// from parser plugins aren't known ahead of time, so they can't be filtered.
+const DOCUMENT_FIELDS: &[&str] = &[
+    "author",
+    "book",
+    "camera",
+    "captured",
+    "captured_at",
+    "channel",
+    "date",
+    "duration",
+    "height",
+    "image",
+    "label",
+    "location",
+    "part",
+    "published",
+    "show",
+    "size",
+    "updated",
+    "updated_at",
+    "width",
+    "words",
+];
+
+/// Names that are read as `name:value` filters. Anything else that looks like
+/// one, e.g. `c++:` or `note:todo`, stays part of the text.
+#[derive(Clone, Debug)]
+pub struct FilterNames {
+    names: HashSet<String>,
+}
+
+impl FilterNames {
+    /// The built-in filters & document fields, along w/ `fields` extracted
+    /// by lenses.
+    pub fn new<I: IntoIterator<Item = String>>(fields: I) -> Self {
+        let names = SEARCH_FILTERS
+            .iter()
+            .chain(DOCUMENT_FIELDS)
+            .map(|name| name.to_string())
+            .chain(fields.into_iter().map(|name| name.to_lowercase()))
+            .collect();
+
+        Self { names }
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.names.contains(&name.to_lowercase())
+    }
+}
+
+impl Default for FilterNames {
+    fn default() -> Self {
+        Self::new(Vec::new())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Open { negated: bool },
+    Close,
+    Or,
+    Clause(Clause),
+}
+
+/// Splits a `name:value` filter, ignoring unknown names & things like URLs
+/// (https://) & paths (Config::new). The name keeps any `-` prefix.
+fn split_filter<'a>(token: &'a str, names: &FilterNames) -> Option<(&'a str, &'a str)> {
+    let (name, value) = token.split_once(':')?;
+    let is_name = names.contains(name.strip_prefix('-').unwrap_or(name));
+
+    if is_name && !value.is_empty() && !value.starts_with(|c: char| c == '/' || c == ':') {
+        Some((name, value))
+    } else {
+        None
+    }
+}
+
+fn filter(name: &str, value: &str) -> Clause {
+    match name.strip_prefix('-') {
+        Some(name) => Clause::Filter(name.to_string(), value.to_string()).negate(true),
+        None => Clause::Filter(name.to_string(), value.to_string()),
+    }
+}
+
+fn word(word: &str, names: &FilterNames) -> Clause {
+    if let Some((name, value)) = split_filter(word, names) {
+        return filter(name, value);
+    }
+
+    match word.strip_prefix('-') {
+        Some(excluded) if excluded.starts_with(char::is_alphanumeric) => {
+            Clause::Text(excluded.to_string()).negate(true)
+        }
+        _ => Clause::Text(word.to_string()),
+    }
+}
+
+/// Text up to the closing quote, or the end of the query if there isn't one,
+/// & the index after it.
+fn read_quoted(chars: &[char], start: usize) -> (String, usize) {
+    match chars[start..].iter().position(|c| *c == '"') {
+        Some(len) => (chars[start..start + len].iter().collect(), start + len + 1),
+        None => (chars[start..].iter().collect(), chars.len()),
+    }
+}
+
+fn lex(query: &str, names: &FilterNames) -> Vec<Token> {
+    let chars = query.chars().collect::<Vec<char>>();
+    let mut tokens = Vec::new();
+    let mut depth = 0;
+    // Parens past `MAX_GROUP_DEPTH` that are being ignored
+    let mut flattened = 0;
+    let mut idx = 0;
+
+    while idx < chars.len() {
+        if chars[idx].is_whitespace() {
+            idx += 1;
+            continue;
+        }
+
+        let negated = chars[idx] == '-' && matches!(chars.get(idx + 1), Some('"') | Some('('));
+        let start = if negated { idx + 1 } else { idx };
+        match chars[start] {
+            '(' if depth >= MAX_GROUP_DEPTH => {
+                flattened += 1;
+                idx = start + 1;
+            }
+            ')' if flattened > 0 => {
+                flattened -= 1;
+                idx = start + 1;
+            }
+            '(' => {
+                depth += 1;
+                tokens.push(Token::Open { negated });
+                idx = start + 1;
+            }
+            ')' if depth > 0 => {
+                depth -= 1;
+                tokens.push(Token::Close);
+                idx = start + 1;
+            }
+            '"' => {
+                let (phrase, end) = read_quoted(&chars, start + 1);
+                if !phrase.trim().is_empty() {
+                    tokens.push(Token::Clause(Clause::Phrase(phrase).negate(negated)));
+                }
+                idx = end;
+            }
+            _ => {
+                let mut end = start;
+                while end < chars.len() && !chars[end].is_whitespace() {
+                    // Quoted filter value, e.g. author:"jane doe"
+                    if chars[end] == '"' && end > start && chars[end - 1] == ':' {
+                        break;
+                    }
+                    end += 1;
+                }
+
+                let token = chars[start..end].iter().collect::<String>();
+                if end < chars.len() && chars[end] == '"' {
+                    let (value, next) = read_quoted(&chars, end + 1);
+                    let name = token.trim_end_matches(':');
+                    let clause = match split_filter(&format!("{}:x", name), names) {
+                        Some(_) if !value.trim().is_empty() => filter(name, &value),
+                        _ => word(&format!("{}\"{}\"", token, value), names),
+                    };
+                    tokens.push(Token::Clause(clause));
+                    idx = next;
+                    continue;
+                }
+
+                // Closing parens stuck to the end of a word close any open groups
+                let mut token = token.as_str();
+                let mut closes = 0;
+                while (depth > 0 || flattened > 0) && token.len() > 1 && token.ends_with(')') {
+                    token = &token[..token.len() - 1];
+                    if flattened > 0 {
+                        flattened -= 1;
+                    } else {
+                        depth -= 1;
+                        closes += 1;
+                    }
+                }
+
+                if token == "OR" || token == "|" {
+                    tokens.push(Token::Or);
+                } else {
+                    tokens.push(Token::Clause(word(token, names)));
+                }
+                tokens.extend(std::iter::repeat(Token::Close).take(closes));
+                idx = end;
+            }
+        }
+    }
+
+    tokens
+}
+
+/// A single clause or group, leaving `OR`s & the end of groups alone.
+fn parse_unary(tokens: &[Token], pos: &mut usize) -> Option<Clause> {
+    match tokens.get(*pos)? {
+        Token::Open { negated } => {
+            *pos += 1;
+            let mut group = parse_group(tokens, pos);
+            let clause = match group.len() {
+                0 => return None,
+                1 => group.pop()?,
+                _ => Clause::All(group),
+            };
+            Some(clause.negate(*negated))
+        }
+        Token::Clause(clause) => {
+            *pos += 1;
+            Some(clause.clone())
+        }
+        Token::Close | Token::Or => None,
+    }
+}
+
+/// Clauses up to the end of the current group.
+fn parse_group(tokens: &[Token], pos: &mut usize) -> Vec<Clause> {
+    let mut clauses = Vec::new();
+    while let Some(token) = tokens.get(*pos) {
+        match token {
+            Token::Close => {
+                *pos += 1;
+                break;
+            }
+            // Dangling ORs are ignored
+            Token::Or => {
+                *pos += 1;
+                if let Some(next) = parse_unary(tokens, pos) {
+                    match clauses.pop() {
+                        Some(Clause::Any(mut any)) => {
+                            any.push(next);
+                            clauses.push(Clause::Any(any));
+                        }
+                        Some(prev) => clauses.push(Clause::Any(vec![prev, next])),
+                        None => clauses.push(next),
+                    }
+                }
+            }
+            _ => {
+                if let Some(clause) = parse_unary(tokens, pos) {
+                    clauses.push(clause);
+                }
+            }
+        }
+    }
+
+    clauses
+}
+
+/// Parse a query into the clauses that all have to match, w/ `names` as the
+/// filters it can use.
+pub fn parse(query: &str, names: &FilterNames) -> Vec<Clause> {
+    let tokens = lex(query, names);
+    let mut pos = 0;
+    let mut clauses = Vec::new();
+    // Unmatched closing parens end a group early, keep going w/ the rest.
+    while pos < tokens.len() {
+        clauses.extend(parse_group(&tokens, &mut pos));
+    }
+
+    clauses
+}
+
+/// Values of the `name` filters anywhere in `clauses`, including in groups &
+/// exclusions.
+pub fn filter_values(clauses: &[Clause], name: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    for clause in clauses {
+        match clause {
+            Clause::Filter(filter, value) if filter.eq_ignore_ascii_case(name) => {
+                values.push(value.clone())
+            }
+            Clause::Not(clause) => values.extend(filter_values(std::slice::from_ref(clause), name)),
+            Clause::Any(clauses) | Clause::All(clauses) => {
+                values.extend(filter_values(clauses, name))
+            }
+            Clause::Text(_) | Clause::Phrase(_) | Clause::Filter(..) => {}
+        }
+    }
+
+    values
+}
+
+/// Words being searched for, i.e. everything but filters & exclusions.
+pub fn search_words(clauses: &[Clause]) -> Vec<String> {
+    let mut words = Vec::new();
+    for clause in clauses {
+        match clause {
+            Clause::Text(text) | Clause::Phrase(text) => words.push(text.clone()),
+            Clause::Any(clauses) | Clause::All(clauses) => words.extend(search_words(clauses)),
+            Clause::Filter(..) | Clause::Not(_) => {}
+        }
+    }
+
+    words
+}
+
+#[cfg(test)]
+mod test {
+    use super::{filter_values, search_words, Clause, FilterNames, MAX_GROUP_DEPTH};
+
+    fn parse(query: &str) -> Vec<Clause> {
+        super::parse(query, &FilterNames::default())
+    }
+
+    fn text(value: &str) -> Clause {
+        Clause::Text(value.into())
+    }
+
+    fn filter(name: &str, value: &str) -> Clause {
+        Clause::Filter(name.into(), value.into())
+    }
+
+    fn not(clause: Clause) -> Clause {
+        Clause::Not(Box::new(clause))
+    }
+
+    #[test]
+    fn test_parse_filters() {
+        assert_eq!(
+            parse("chocolate cake type:Recipe author:jane"),
+            vec![
+                text("chocolate"),
+                text("cake"),
+                filter("type", "Recipe"),
+                filter("author", "jane"),
+            ]
+        );
+
+        // Not filters
+        assert_eq!(
+            parse("https://example.com Config::new 10:30"),
+            vec![
+                text("https://example.com"),
+                text("Config::new"),
+                text("10:30")
+            ]
+        );
+
+        assert_eq!(
+            parse(r#"site:example.com author:"jane doe""#),
+            vec![filter("site", "example.com"), filter("author", "jane doe")]
+        );
+    }
+
+    #[test]
+    fn test_parse_unknown_filters() {
+        assert_eq!(
+            parse("note:todo c++:templates Type:pdf"),
+            vec![
+                text("note:todo"),
+                text("c++:templates"),
+                filter("Type", "pdf")
+            ]
+        );
+
+        // Fields extracted by lenses
+        let names = FilterNames::new(vec!["Price".to_string()]);
+        assert_eq!(
+            super::parse("price:10..20 note:todo", &names),
+            vec![filter("price", "10..20"), text("note:todo")]
+        );
+    }
+
+    #[test]
+    fn test_parse_exclusions() {
+        assert_eq!(
+            parse("rust async -tokio -domain:reddit.com -tag:archive"),
+            vec![
+                text("rust"),
+                text("async"),
+                not(text("tokio")),
+                not(filter("domain", "reddit.com")),
+                not(filter("tag", "archive")),
+            ]
+        );
+
+        // Dashes that aren't exclusions
+        assert_eq!(
+            parse("a - b --verbose"),
+            vec![text("a"), text("-"), text("b"), text("--verbose")]
+        );
+
+        assert_eq!(
+            parse(r#"-"of mice" -(site:a.com OR site:b.com)"#),
+            vec![
+                not(Clause::Phrase("of mice".into())),
+                not(Clause::Any(vec![
+                    filter("site", "a.com"),
+                    filter("site", "b.com")
+                ])),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_phrases() {
+        assert_eq!(
+            parse(r#""salinas river" valley "unclosed"#),
+            vec![
+                Clause::Phrase("salinas river".into()),
+                text("valley"),
+                Clause::Phrase("unclosed".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_or() {
+        assert_eq!(
+            parse("cats OR dogs OR type:pdf fish"),
+            vec![
+                Clause::Any(vec![text("cats"), text("dogs"), filter("type", "pdf")]),
+                text("fish"),
+            ]
+        );
+
+        assert_eq!(
+            parse("(site:docs.rs OR site:crates.io) tokio (async runtime)"),
+            vec![
+                Clause::Any(vec![filter("site", "docs.rs"), filter("site", "crates.io")]),
+                text("tokio"),
+                Clause::All(vec![text("async"), text("runtime")]),
+            ]
+        );
+
+        // Dangling operators & parens
+        assert_eq!(parse("OR cats OR"), vec![text("cats")]);
+        assert_eq!(parse("(cats) dogs)"), vec![text("cats"), text("dogs)")]);
+        assert_eq!(parse("foo() (bar"), vec![text("foo()"), text("bar")]);
+    }
+
+    #[test]
+    fn test_search_words() {
+        let clauses = parse(r#""salinas river" -valley (cats OR site:a.com)"#);
+        assert_eq!(search_words(&clauses), vec!["salinas river", "cats"]);
+    }
+
+    #[test]
+    fn test_filter_values() {
+        let clauses = parse("collection:a (collection:b OR tag:c) -(x collection:d)");
+        assert_eq!(filter_values(&clauses, "collection"), vec!["a", "b", "d"]);
+        assert_eq!(filter_values(&clauses, "tag"), vec!["c"]);
+        assert!(filter_values(&clauses, "as_of").is_empty());
+    }
+
+    #[test]
+    fn test_parse_max_depth() {
+        let depth = MAX_GROUP_DEPTH + 4;
+        let query = format!("{}a OR b{} c", "(".repeat(depth), ")".repeat(depth));
+        let parsed = parse(&query);
+
+        fn depth_of(clause: &Clause) -> usize {
+            match clause {
+                Clause::Any(clauses) | Clause::All(clauses) => {
+                    1 + clauses.iter().map(depth_of).max().unwrap_or(0)
+                }
+                Clause::Not(clause) => depth_of(clause),
+                _ => 0,
+            }
+        }
+
+        assert!(parsed.iter().map(depth_of).max().unwrap() <= MAX_GROUP_DEPTH);
+        // Parens after the flattened ones still match up
+        assert_eq!(parsed.last(), Some(&text("c")));
+    }
+}
@@ -0,0 +1,172 @@
+//! The parsed content of indexed documents, read back from the index so
+//! clients can show a preview w/o refetching the original.
+
+use std::ops::Range;
+
+use entities::schema::{DocFields, SearchDocument};
+use tantivy::collector::DocSetCollector;
+use tantivy::query::TermQuery;
+use tantivy::schema::{Document, Field, IndexRecordOption};
+use tantivy::{IndexReader, Term};
+
+use crate::scraper::Section;
+use crate::search::part_number;
+
+/// Most content returned at once, longer documents are read in chunks.
+pub const MAX_PREVIEW_BYTES: usize = 64 * 1024;
+
+fn stored_text(doc: &Document, field: Field) -> String {
+    doc.get_first(field)
+        .and_then(|value| value.as_text())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Stored sections of `doc_id`, in the order they appear. Only long documents
+/// are split into sections when they're indexed.
+pub fn sections(reader: &IndexReader, doc_id: &str) -> tantivy::Result<Vec<Section>> {
+    let fields = DocFields::as_fields();
+    let searcher = reader.searcher();
+    let query = TermQuery::new(
+        Term::from_field_text(fields.parent_id, doc_id),
+        IndexRecordOption::Basic,
+    );
+
+    // Sections are added right after their document, so their addresses keep
+    // them in order.
+    let mut addresses = searcher
+        .search(&query, &DocSetCollector)?
+        .into_iter()
+        .collect::<Vec<_>>();
+    addresses.sort();
+
+    let mut sections = Vec::new();
+    for address in addresses {
+        let doc = searcher.doc(address)?;
+        // Notes share the parent, but aren't sections.
+        if doc.get_first(fields.anchor).is_none() {
+            continue;
+        }
+
+        sections.push(Section {
+            anchor: stored_text(&doc, fields.anchor),
+            heading: stored_text(&doc, fields.title),
+            content: stored_text(&doc, fields.content),
+        });
+    }
+
+    Ok(sections)
+}
+
+/// Stored content of the parts an oversized `doc_id` was split into, in order.
+pub fn parts(reader: &IndexReader, doc_id: &str) -> tantivy::Result<Vec<String>> {
+    let fields = DocFields::as_fields();
+    let searcher = reader.searcher();
+    let query = TermQuery::new(
+        Term::from_field_text(fields.parent_id, doc_id),
+        IndexRecordOption::Basic,
+    );
+
+    let mut parts = Vec::new();
+    for address in searcher.search(&query, &DocSetCollector)? {
+        let doc = searcher.doc(address)?;
+        if let Some(part) = part_number(&stored_text(&doc, fields.id)) {
+            parts.push((part, stored_text(&doc, fields.content)));
+        }
+    }
+    parts.sort_by_key(|(part, _)| *part);
+
+    Ok(parts.into_iter().map(|(_, content)| content).collect())
+}
+
+/// The byte range of `content` to return for a request from `start` to `end`,
+/// limited to `MAX_PREVIEW_BYTES` & moved back to character boundaries so the
+/// range can always be sliced.
+pub fn byte_range(content: &str, start: Option<usize>, end: Option<usize>) -> Range<usize> {
+    let floor = |mut idx: usize| {
+        idx = idx.min(content.len());
+        while !content.is_char_boundary(idx) {
+            idx -= 1;
+        }
+        idx
+    };
+
+    let start = floor(start.unwrap_or_default());
+    let end = end
+        .unwrap_or(usize::MAX)
+        .min(start.saturating_add(MAX_PREVIEW_BYTES));
+    start..floor(end.max(start))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{byte_range, sections, MAX_PREVIEW_BYTES};
+    use crate::scraper::Section;
+    use crate::search::{DocumentUpdate, IndexPath, Searcher};
+
+    #[test]
+    fn test_byte_range() {
+        let content = "Salinas River";
+        assert_eq!(byte_range(content, None, None), 0..13);
+        assert_eq!(byte_range(content, Some(8), None), 8..13);
+        assert_eq!(byte_range(content, Some(0), Some(7)), 0..7);
+        assert_eq!(byte_range(content, Some(100), None), 13..13);
+        assert_eq!(byte_range(content, Some(8), Some(2)), 8..8);
+
+        // Never splits a character
+        let content = "café au lait";
+        assert_eq!(byte_range(content, Some(0), Some(4)), 0..3);
+        assert_eq!(
+            &content[byte_range(content, Some(4), Some(100))],
+            "é au lait"
+        );
+
+        let content = "a".repeat(MAX_PREVIEW_BYTES * 2);
+        assert_eq!(
+            byte_range(&content, Some(10), None),
+            10..10 + MAX_PREVIEW_BYTES
+        );
+    }
+
+    #[test]
+    fn test_sections() {
+        let searcher = Searcher::with_index(&IndexPath::Memory).expect("Unable to open index");
+        let section = |anchor: &str, heading: &str| Section {
+            anchor: anchor.into(),
+            heading: heading.into(),
+            content: format!("{} ", heading).repeat(2_000),
+        };
+        let doc_sections = vec![
+            section("source", "Source"),
+            section("course", "Course"),
+            section("mouth", "Mouth"),
+        ];
+        let content = doc_sections
+            .iter()
+            .map(|section| section.content.as_str())
+            .collect::<String>();
+
+        {
+            let mut writer = searcher.writer.lock().unwrap();
+            Searcher::upsert_document(
+                &mut writer,
+                DocumentUpdate {
+                    doc_id: Some("river".into()),
+                    title: "Salinas River",
+                    url: "https://example.com/river",
+                    content: &content,
+                    sections: &doc_sections,
+                    notes: &[(1, "Visit in spring".to_string())],
+                    ..Default::default()
+                },
+            )
+            .expect("Unable to add doc");
+            writer.commit().expect("Unable to commit");
+        }
+        searcher.reader.reload().expect("Unable to reload");
+
+        let found = sections(&searcher.reader, "river").expect("Unable to get sections");
+        assert_eq!(found, doc_sections);
+        assert!(sections(&searcher.reader, "lake").unwrap().is_empty());
+    }
+}
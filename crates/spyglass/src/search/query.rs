@@ -1,12 +1,38 @@
-use tantivy::query::{BooleanQuery, BoostQuery, Occur, PhraseQuery, Query, TermQuery};
+use std::collections::HashMap;
+use std::ops::Bound;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use entities::models::tag::TagType;
+use entities::sea_orm::Iterable;
+use tantivy::query::{
+    AllQuery, BooleanQuery, BoostQuery, FuzzyTermQuery, Occur, PhraseQuery, Query, RangeQuery,
+    RegexQuery, TermQuery,
+};
 use tantivy::schema::*;
 use tantivy::tokenizer::TokenizerManager;
 use tantivy::Score;
 
-use super::DocFields;
+use super::geo::{self, LAT_FIELD, LNG_FIELD};
+use super::language;
+use super::parser::{self, Clause, FilterNames};
+use super::{field_term, number_term, tag_facet, version_timestamp, DocFields};
 
 type QueryVec = Vec<(Occur, Box<dyn Query>)>;
 
+/// Filters that are looked up before building a query since they need the
+/// database or the user's settings, by filter value.
+#[derive(Debug, Default)]
+pub struct ResolvedFilters {
+    /// Ids of the documents in each `collection:`.
+    pub collections: HashMap<String, Vec<String>>,
+    /// `after:` & `before:` dates. Invalid ones are left out & ignored.
+    pub dates: HashMap<String, DateTime<Utc>>,
+}
+
+/// Most typos tolerated per search term, the largest edit distance tantivy
+/// supports.
+pub const MAX_FUZZY_DISTANCE: u8 = 2;
+
 fn _boosted_term(term: Term, boost: Score) -> Box<BoostQuery> {
     Box::new(BoostQuery::new(
         Box::new(TermQuery::new(
@@ -18,16 +44,552 @@ fn _boosted_term(term: Term, boost: Score) -> Box<BoostQuery> {
     ))
 }
 
+/// Typos tolerated in `term`, up to `max_distance`. Short words are matched
+/// exactly since a typo or two could turn them into almost anything.
+fn fuzzy_distance(term: &Term, max_distance: u8) -> u8 {
+    let len = term.as_str().map(|text| text.chars().count()).unwrap_or(0);
+    let allowed = match len {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => MAX_FUZZY_DISTANCE,
+    };
+
+    allowed.min(max_distance)
+}
+
+/// Matches `term` w/ up to `max_distance` typos, if it's long enough to
+/// tolerate any.
+fn _boosted_fuzzy_term(term: Term, max_distance: u8, boost: Score) -> Option<Box<BoostQuery>> {
+    match fuzzy_distance(&term, max_distance) {
+        0 => None,
+        distance => Some(Box::new(BoostQuery::new(
+            // Transpositions ("teh") count as a single typo.
+            Box::new(FuzzyTermQuery::new(term, distance, true)),
+            boost,
+        ))),
+    }
+}
+
 fn _boosted_phrase(terms: Vec<Term>, boost: Score) -> Box<BoostQuery> {
     Box::new(BoostQuery::new(Box::new(PhraseQuery::new(terms)), boost))
 }
 
+/// Parses a number w/ an optional size unit, e.g. `500`, `1.5k` or `10mb`.
+fn parse_number(value: &str) -> Option<f64> {
+    let value = value.trim().to_lowercase();
+    let split = value
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let multiplier = match unit {
+        "" => 1.0,
+        "k" => 1e3,
+        "m" => 1e6,
+        "b" => 1.0,
+        "kb" => 1024.0,
+        "mb" => 1024.0 * 1024.0,
+        "gb" => 1024.0 * 1024.0 * 1024.0,
+        "tb" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    };
+
+    number.parse::<f64>().ok().map(|number| number * multiplier)
+}
+
+/// Parses the value of a numeric range filter, e.g. the `>10mb` in
+/// `size:>10mb` or `100..500`, into the bounds of the range.
+pub fn parse_range(value: &str) -> Option<(Bound<f64>, Bound<f64>)> {
+    if let Some((start, end)) = value.split_once("..") {
+        return Some((
+            Bound::Included(parse_number(start)?),
+            Bound::Included(parse_number(end)?),
+        ));
+    }
+
+    if let Some(value) = value.strip_prefix(">=") {
+        Some((Bound::Included(parse_number(value)?), Bound::Unbounded))
+    } else if let Some(value) = value.strip_prefix("<=") {
+        Some((Bound::Unbounded, Bound::Included(parse_number(value)?)))
+    } else if let Some(value) = value.strip_prefix('>') {
+        Some((Bound::Excluded(parse_number(value)?), Bound::Unbounded))
+    } else if let Some(value) = value.strip_prefix('<') {
+        Some((Bound::Unbounded, Bound::Excluded(parse_number(value)?)))
+    } else {
+        None
+    }
+}
+
+/// Matches documents w/ a `name` number in the range.
+fn number_range_query(
+    fields: &DocFields,
+    name: &str,
+    range: (Bound<f64>, Bound<f64>),
+) -> RangeQuery {
+    let to_term = |bound: Bound<f64>| match bound {
+        Bound::Included(value) => Bound::Included(number_term(name, value)),
+        Bound::Excluded(value) => Bound::Excluded(number_term(name, value)),
+        Bound::Unbounded => Bound::Unbounded,
+    };
+
+    // Keep unbounded ends from spilling over into other numbers. ';' sorts
+    // right after the ':' separating the name & value.
+    let name = name.trim().to_lowercase();
+    let lower = match to_term(range.0) {
+        Bound::Unbounded => Bound::Included(format!("{}:", name)),
+        bound => bound,
+    };
+    let upper = match to_term(range.1) {
+        Bound::Unbounded => Bound::Excluded(format!("{};", name)),
+        bound => bound,
+    };
+
+    fn as_str(bound: &Bound<String>) -> Bound<&str> {
+        match bound {
+            Bound::Included(term) => Bound::Included(term.as_str()),
+            Bound::Excluded(term) => Bound::Excluded(term.as_str()),
+            Bound::Unbounded => Bound::Unbounded,
+        }
+    }
+
+    RangeQuery::new_str_bounds(fields.numbers, as_str(&lower), as_str(&upper))
+}
+
+/// Parses the value of an `as_of:` filter, either an RFC 3339 timestamp or a
+/// date, which is treated as the end of that day (UTC).
+pub fn parse_as_of(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+        return Some(timestamp.with_timezone(&Utc));
+    }
+
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(23, 59, 59))
+        .map(|timestamp| DateTime::<Utc>::from_utc(timestamp, Utc))
+}
+
+/// Matches any document w/ a value for `field`.
+fn has_value(field: Field) -> Box<dyn Query> {
+    Box::new(RangeQuery::new_str_bounds(
+        field,
+        Bound::Unbounded,
+        Bound::Unbounded,
+    ))
+}
+
+/// Limits results to the versions of documents that were current @ `as_of`,
+/// or to their latest versions when not set.
+pub fn version_filter(
+    fields: &DocFields,
+    as_of: Option<&DateTime<Utc>>,
+) -> (Occur, Box<dyn Query>) {
+    let as_of = match as_of {
+        Some(as_of) => version_timestamp(as_of),
+        None => return (Occur::MustNot, has_value(fields.superseded_at)),
+    };
+
+    let crawled_before = || -> Box<dyn Query> {
+        Box::new(RangeQuery::new_str_bounds(
+            fields.crawled_at,
+            Bound::Unbounded,
+            Bound::Included(&as_of),
+        ))
+    };
+
+    let versions: QueryVec = vec![
+        // Past versions that hadn't been replaced yet
+        (
+            Occur::Should,
+            Box::new(BooleanQuery::new(vec![
+                (Occur::Must, crawled_before()),
+                (
+                    Occur::Must,
+                    Box::new(RangeQuery::new_str_bounds(
+                        fields.superseded_at,
+                        Bound::Excluded(&as_of),
+                        Bound::Unbounded,
+                    )),
+                ),
+            ])),
+        ),
+        // Current versions that were already around
+        (
+            Occur::Should,
+            Box::new(BooleanQuery::new(vec![
+                (Occur::Must, crawled_before()),
+                (Occur::MustNot, has_value(fields.superseded_at)),
+            ])),
+        ),
+        // Documents indexed before versions were tracked
+        (
+            Occur::Should,
+            Box::new(BooleanQuery::new(vec![
+                (Occur::Must, Box::new(AllQuery)),
+                (Occur::MustNot, has_value(fields.crawled_at)),
+                (Occur::MustNot, has_value(fields.superseded_at)),
+            ])),
+        ),
+    ];
+
+    (Occur::Must, Box::new(BooleanQuery::new(versions)))
+}
+
+/// Limits results to documents crawled @ or after `after` & before `before`,
+/// from `after:` & `before:` filters. `None` if neither is set.
+pub fn crawled_filter(
+    fields: &DocFields,
+    after: Option<&DateTime<Utc>>,
+    before: Option<&DateTime<Utc>>,
+) -> Option<(Occur, Box<dyn Query>)> {
+    if after.is_none() && before.is_none() {
+        return None;
+    }
+
+    let after = after.map(version_timestamp);
+    let before = before.map(version_timestamp);
+    let lower = match &after {
+        Some(after) => Bound::Included(after.as_str()),
+        None => Bound::Unbounded,
+    };
+    let upper = match &before {
+        Some(before) => Bound::Excluded(before.as_str()),
+        None => Bound::Unbounded,
+    };
+
+    Some((
+        Occur::Must,
+        Box::new(RangeQuery::new_str_bounds(fields.crawled_at, lower, upper)),
+    ))
+}
+
+/// Matches the documents w/ the given ids, including any of their sections.
+fn doc_id_query(fields: &DocFields, doc_ids: &[String]) -> BooleanQuery {
+    let mut query: QueryVec = Vec::new();
+    for doc_id in doc_ids {
+        for field in [fields.id, fields.parent_id] {
+            query.push((
+                Occur::Should,
+                Box::new(TermQuery::new(
+                    Term::from_field_text(field, doc_id),
+                    IndexRecordOption::Basic,
+                )),
+            ));
+        }
+    }
+
+    BooleanQuery::new(query)
+}
+
+/// Matches the documents w/ the given ids, ignoring their sections.
+pub fn ids_query(fields: &DocFields, doc_ids: &[String]) -> BooleanQuery {
+    let query: QueryVec = doc_ids
+        .iter()
+        .map(|doc_id| {
+            (
+                Occur::Should,
+                Box::new(TermQuery::new(
+                    Term::from_field_text(fields.id, doc_id),
+                    IndexRecordOption::Basic,
+                )) as Box<dyn Query>,
+            )
+        })
+        .collect();
+
+    BooleanQuery::new(query)
+}
+
+/// Matches documents containing `word` in their title or content, in any
+/// language.
+fn word_query(
+    schema: &Schema,
+    tokenizers: &TokenizerManager,
+    fields: &DocFields,
+    word: &str,
+) -> BooleanQuery {
+    let mut query: QueryVec = Vec::new();
+    let mut search_fields = vec![fields.content, fields.title];
+    search_fields.extend(fields.language_fields());
+    for field in search_fields {
+        let mut terms = terms_for_field(schema, tokenizers, word, field);
+        match terms.len() {
+            0 => {}
+            1 => query.push((
+                Occur::Should,
+                Box::new(TermQuery::new(terms.remove(0), IndexRecordOption::Basic)),
+            )),
+            _ => query.push((Occur::Should, Box::new(PhraseQuery::new(terms)))),
+        }
+    }
+
+    BooleanQuery::new(query)
+}
+
+/// Matches any of the words & phrases being searched for, used to find
+/// what to highlight in results rather than to search.
+pub fn highlight_query(
+    schema: &Schema,
+    tokenizers: &TokenizerManager,
+    fields: &DocFields,
+    query_string: &str,
+    names: &FilterNames,
+) -> BooleanQuery {
+    let text = parser::search_words(&parser::parse(query_string, names)).join(" ");
+
+    let mut query: QueryVec = Vec::new();
+    for field in [fields.content, fields.description, fields.title] {
+        for term in terms_for_field(schema, tokenizers, &text, field) {
+            query.push((
+                Occur::Should,
+                Box::new(TermQuery::new(term, IndexRecordOption::Basic)),
+            ));
+        }
+    }
+
+    BooleanQuery::new(query)
+}
+
+/// Mime types for `type:` filters, which can use short names like `pdf`.
+fn mime_types(value: &str) -> Vec<String> {
+    let value = value.trim().to_lowercase();
+    let types: &[&str] = match value.as_str() {
+        "pdf" => &["application/pdf"],
+        "html" | "web" => &["text/html"],
+        "md" | "markdown" => &["text/markdown"],
+        "txt" | "text" => &["text/plain"],
+        "csv" => &["text/csv"],
+        "json" => &["application/json"],
+        "doc" | "docx" => &[
+            "application/msword",
+            "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        ],
+        "xls" | "xlsx" => &[
+            "application/vnd.ms-excel",
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        ],
+        _ if value.contains('/') => return vec![value],
+        _ => &[],
+    };
+
+    types.iter().map(|mime| mime.to_string()).collect()
+}
+
+/// Matches documents from `site`, either a domain (including its subdomains)
+/// or a URL prefix like `example.com/docs`.
+fn site_query(fields: &DocFields, site: &str) -> Option<Box<dyn Query>> {
+    let site = site
+        .trim_start_matches("https://")
+        .trim_start_matches("http://")
+        .trim_end_matches('/');
+    let (host, path) = match site.split_once('/') {
+        Some((host, path)) => (host, Some(path)),
+        None => (site, None),
+    };
+    let host = format!(r"(.*\.)?{}", regex::escape(&host.to_lowercase()));
+
+    let query = match path {
+        Some(path) => RegexQuery::from_pattern(
+            &format!(r"[a-z]+://{}/{}.*", host, regex::escape(path)),
+            fields.url,
+        ),
+        None => RegexQuery::from_pattern(&host, fields.domain),
+    };
+
+    match query {
+        Ok(query) => Some(Box::new(query)),
+        Err(err) => {
+            log::warn!("Ignoring invalid site filter {}: {}", site, err);
+            None
+        }
+    }
+}
+
+/// Matches documents w/ `value` for the `name` filter.
+fn filter_query(
+    schema: &Schema,
+    tokenizers: &TokenizerManager,
+    fields: &DocFields,
+    name: &str,
+    value: &str,
+) -> Option<Box<dyn Query>> {
+    let term_query =
+        |term: Term| -> Box<dyn Query> { Box::new(TermQuery::new(term, IndexRecordOption::Basic)) };
+
+    // Code blocks are tokenized, so match on terms instead of the exact value.
+    if name.eq_ignore_ascii_case("code") {
+        let mut code_terms = terms_for_field(schema, tokenizers, value, fields.code);
+        return match code_terms.len() {
+            0 => None,
+            1 => Some(Box::new(TermQuery::new(
+                code_terms.remove(0),
+                IndexRecordOption::WithFreqs,
+            ))),
+            _ => Some(Box::new(PhraseQuery::new(code_terms))),
+        };
+    }
+
+    // Places that can't be geocoded fall through to a regular filter,
+    // which won't match anything.
+    if name.eq_ignore_ascii_case("near") {
+        if let Some(point) = geo::geocode(value) {
+            let (lat, lng) = point.near_bounds();
+            let bounds = |(min, max): (f64, f64)| (Bound::Included(min), Bound::Included(max));
+            return Some(Box::new(BooleanQuery::new(vec![
+                (
+                    Occur::Must,
+                    Box::new(number_range_query(fields, LAT_FIELD, bounds(lat))) as Box<dyn Query>,
+                ),
+                (
+                    Occur::Must,
+                    Box::new(number_range_query(fields, LNG_FIELD, bounds(lng))),
+                ),
+            ])));
+        }
+    }
+
+    if let Some(range) = parse_range(value) {
+        return Some(Box::new(number_range_query(fields, name, range)));
+    }
+
+    if name.eq_ignore_ascii_case("domain") {
+        return Some(term_query(Term::from_field_text(
+            fields.domain,
+            &value.to_lowercase(),
+        )));
+    }
+
+    if name.eq_ignore_ascii_case("site") {
+        return site_query(fields, value);
+    }
+
+    // Languages that aren't recognized fall through to a regular filter,
+    // which won't match anything.
+    if name.eq_ignore_ascii_case("lang") {
+        if let Some(code) = language::lang_code(value) {
+            return Some(term_query(Term::from_field_text(fields.lang, code)));
+        }
+    }
+
+    // Either the structured type (e.g. type:Recipe) or the mime type of files.
+    if name.eq_ignore_ascii_case("type") {
+        let mut any: QueryVec = vec![(
+            Occur::Should,
+            term_query(Term::from_field_text(
+                fields.fields,
+                &field_term(name, value),
+            )),
+        )];
+        for mime in mime_types(value) {
+            let facet = tag_facet(TagType::MimeType.as_ref(), &mime);
+            any.push((
+                Occur::Should,
+                term_query(Term::from_facet(fields.tags, &facet)),
+            ));
+        }
+        return Some(Box::new(BooleanQuery::new(any)));
+    }
+
+    // Tags w/ the value under any label
+    if name.eq_ignore_ascii_case("tag") {
+        let any: QueryVec = TagType::iter()
+            .map(|label| {
+                let facet = tag_facet(label.as_ref(), value);
+                (
+                    Occur::Should,
+                    term_query(Term::from_facet(fields.tags, &facet)),
+                )
+            })
+            .collect();
+        return Some(Box::new(BooleanQuery::new(any)));
+    }
+
+    Some(term_query(Term::from_field_text(
+        fields.fields,
+        &field_term(name, value),
+    )))
+}
+
+/// Query for a clause of the query language, see the `parser` module.
+fn clause_query(
+    schema: &Schema,
+    tokenizers: &TokenizerManager,
+    fields: &DocFields,
+    resolved: &ResolvedFilters,
+    clause: &Clause,
+) -> Option<Box<dyn Query>> {
+    match clause {
+        Clause::Text(text) | Clause::Phrase(text) => {
+            Some(Box::new(word_query(schema, tokenizers, fields, text)))
+        }
+        // Unknown collections don't have any documents to match.
+        Clause::Filter(name, value) if name.eq_ignore_ascii_case("collection") => {
+            let doc_ids = resolved.collections.get(value).map(Vec::as_slice);
+            Some(Box::new(doc_id_query(fields, doc_ids.unwrap_or_default())))
+        }
+        Clause::Filter(name, value)
+            if name.eq_ignore_ascii_case("after") || name.eq_ignore_ascii_case("before") =>
+        {
+            let date = resolved.dates.get(value)?;
+            let (_, query) = if name.eq_ignore_ascii_case("after") {
+                crawled_filter(fields, Some(date), None)?
+            } else {
+                crawled_filter(fields, None, Some(date))?
+            };
+            Some(query)
+        }
+        // Picks the versions searched rather than matching documents, so it
+        // applies to the whole search wherever it's written.
+        Clause::Filter(name, _) if name.eq_ignore_ascii_case("as_of") => None,
+        Clause::Filter(name, value) => filter_query(schema, tokenizers, fields, name, value),
+        Clause::Not(clause) => {
+            let excluded = clause_query(schema, tokenizers, fields, resolved, clause)?;
+            Some(Box::new(BooleanQuery::new(vec![
+                (Occur::Must, Box::new(AllQuery)),
+                (Occur::MustNot, excluded),
+            ])))
+        }
+        Clause::Any(clauses) | Clause::All(clauses) => {
+            let occur = match clause {
+                Clause::Any(_) => Occur::Should,
+                _ => Occur::Must,
+            };
+            let group: QueryVec = clauses
+                .iter()
+                .filter_map(|clause| {
+                    clause_query(schema, tokenizers, fields, resolved, clause)
+                        .map(|query| (occur, query))
+                })
+                .collect();
+
+            if group.is_empty() {
+                None
+            } else {
+                Some(Box::new(BooleanQuery::new(group)))
+            }
+        }
+    }
+}
+
+/// Build the query for the parsed `clauses` of a search. Free text is scored
+/// as a whole, everything else has to match. Free text words also match w/ up
+/// to `fuzzy_distance` typos, scored below exact matches. Filters that need
+/// the database or the user's settings are looked up in `resolved`.
 pub fn build_query(
     schema: Schema,
     tokenizers: TokenizerManager,
     fields: DocFields,
-    query_string: &str,
+    clauses: &[Clause],
+    resolved: &ResolvedFilters,
+    fuzzy_distance: u8,
 ) -> BooleanQuery {
+    let query_string = clauses
+        .iter()
+        .filter_map(|clause| match clause {
+            Clause::Text(text) => Some(text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join(" ");
+    let query_string = query_string.as_str();
     let content_terms = terms_for_field(&schema, &tokenizers, query_string, fields.content);
     let title_terms: Vec<Term> = terms_for_field(&schema, &tokenizers, query_string, fields.title);
 
@@ -51,14 +613,59 @@ pub fn build_query(
     }
 
     for term in content_terms {
+        if let Some(fuzzy) = _boosted_fuzzy_term(term.clone(), fuzzy_distance, 0.5) {
+            term_query.push((Occur::Should, fuzzy));
+        }
         term_query.push((Occur::Should, _boosted_term(term, 1.0)));
     }
 
     for term in title_terms {
+        if let Some(fuzzy) = _boosted_fuzzy_term(term.clone(), fuzzy_distance, 1.0) {
+            term_query.push((Occur::Should, fuzzy));
+        }
         term_query.push((Occur::Should, _boosted_term(term, 2.0)));
     }
 
-    BooleanQuery::new(vec![(Occur::Must, Box::new(BooleanQuery::new(term_query)))])
+    // Documents in languages w/ their own tokenizer, e.g. stemmed German or
+    // segmented Chinese, match on those terms too.
+    for field in fields.language_fields() {
+        let language_terms = terms_for_field(&schema, &tokenizers, query_string, field);
+        if language_terms.len() > 1 {
+            let boost = 2.0 * language_terms.len() as f32;
+            term_query.push((
+                Occur::Should,
+                _boosted_phrase(language_terms.clone(), boost),
+            ));
+        }
+
+        for term in language_terms {
+            term_query.push((Occur::Should, _boosted_term(term, 1.0)));
+        }
+    }
+
+    let mut query: QueryVec = Vec::new();
+    if !term_query.is_empty() {
+        query.push((Occur::Must, Box::new(BooleanQuery::new(term_query))));
+    }
+
+    for clause in clauses {
+        let (occur, clause) = match clause {
+            Clause::Text(_) => continue,
+            Clause::Not(clause) => (Occur::MustNot, clause.as_ref()),
+            clause => (Occur::Must, clause),
+        };
+
+        if let Some(clause_query) = clause_query(&schema, &tokenizers, &fields, resolved, clause) {
+            query.push((occur, clause_query));
+        }
+    }
+
+    // Queries made up only of exclusions match everything else.
+    if !query.is_empty() && query.iter().all(|(occur, _)| *occur == Occur::MustNot) {
+        query.push((Occur::Must, Box::new(AllQuery)));
+    }
+
+    BooleanQuery::new(query)
 }
 
 /**
@@ -88,3 +695,53 @@ fn terms_for_field(
 
     terms
 }
+
+#[cfg(test)]
+mod test {
+    use std::ops::Bound;
+
+    use tantivy::schema::{Field, Term};
+
+    use super::{fuzzy_distance, parse_as_of, parse_range};
+
+    #[test]
+    fn test_fuzzy_distance() {
+        let term = |text: &str| Term::from_field_text(Field::from_field_id(0), text);
+        assert_eq!(fuzzy_distance(&term("the"), 2), 0);
+        assert_eq!(fuzzy_distance(&term("river"), 2), 1);
+        assert_eq!(fuzzy_distance(&term("kubernets"), 2), 2);
+        assert_eq!(fuzzy_distance(&term("kubernets"), 1), 1);
+        assert_eq!(fuzzy_distance(&term("kubernets"), 0), 0);
+    }
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(
+            parse_range(">10mb"),
+            Some((Bound::Excluded(10.0 * 1024.0 * 1024.0), Bound::Unbounded))
+        );
+        assert_eq!(
+            parse_range("<=500"),
+            Some((Bound::Unbounded, Bound::Included(500.0)))
+        );
+        assert_eq!(
+            parse_range("1.5k..2k"),
+            Some((Bound::Included(1500.0), Bound::Included(2000.0)))
+        );
+        assert_eq!(parse_range("500"), None);
+        assert_eq!(parse_range(">lots"), None);
+    }
+
+    #[test]
+    fn test_parse_as_of() {
+        assert_eq!(
+            parse_as_of("2022-11-15").map(|t| t.to_rfc3339()),
+            Some("2022-11-15T23:59:59+00:00".to_string())
+        );
+        assert_eq!(
+            parse_as_of("2022-11-15T08:00:00-05:00").map(|t| t.to_rfc3339()),
+            Some("2022-11-15T13:00:00+00:00".to_string())
+        );
+        assert_eq!(parse_as_of("last-month"), None);
+    }
+}
@@ -0,0 +1,58 @@
+use shared::response::SearchSnippet;
+use tantivy::{Document, SnippetGenerator};
+
+use super::query::highlight_query;
+use super::{DocFields, FilterNames, Searcher};
+
+/// Length of snippets when the search doesn't ask for one.
+pub const DEFAULT_SNIPPET_CHARS: usize = 200;
+
+/// Picks out the passages of documents that best match a query, w/ the
+/// matching words highlighted.
+pub struct Snippets {
+    /// Tried in order, the first field w/ a match is used.
+    generators: Vec<(&'static str, SnippetGenerator)>,
+}
+
+impl Snippets {
+    pub fn new(searcher: &Searcher, query: &str, names: &FilterNames, max_chars: usize) -> Self {
+        let fields = DocFields::as_fields();
+        let schema = searcher.index.schema();
+        let query = highlight_query(&schema, searcher.index.tokenizers(), &fields, query, names);
+        let reader = searcher.reader.searcher();
+
+        let generators = [
+            ("content", fields.content),
+            ("description", fields.description),
+            ("title", fields.title),
+        ]
+        .into_iter()
+        .filter_map(|(name, field)| {
+            let mut generator = SnippetGenerator::create(&reader, &query, field).ok()?;
+            generator.set_max_num_chars(max_chars);
+            Some((name, generator))
+        })
+        .collect();
+
+        Snippets { generators }
+    }
+
+    pub fn for_doc(&self, doc: &Document) -> Option<SearchSnippet> {
+        self.generators.iter().find_map(|(name, generator)| {
+            let snippet = generator.snippet_from_doc(doc);
+            if snippet.highlighted().is_empty() {
+                return None;
+            }
+
+            Some(SearchSnippet {
+                field: name.to_string(),
+                text: snippet.fragment().to_string(),
+                highlights: snippet
+                    .highlighted()
+                    .iter()
+                    .map(|section| section.bounds())
+                    .collect(),
+            })
+        })
+    }
+}
@@ -0,0 +1,228 @@
+//! Synonyms the user has set up, used to expand the words in a query before
+//! it's searched, e.g. so `js` also finds documents that say `javascript`.
+//! Synonyms are loaded from a plain text file w/ a rule per line:
+//! - `js, javascript, ecmascript`: each word is a synonym for the others.
+//! - `k8s => kubernetes`: `k8s` also searches for `kubernetes`, but not the
+//!   other way around.
+//!
+//! Blank lines & lines starting w/ `#` are ignored. Synonyms can be more than
+//! one word, e.g. `nyc, new york city`.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use super::parser::Clause;
+
+#[derive(Clone, Debug, Default)]
+pub struct Synonyms {
+    /// Lowercased word to the words/phrases it should also match.
+    expansions: HashMap<String, Vec<String>>,
+}
+
+impl Synonyms {
+    /// Load synonyms from `path`, which doesn't have to exist.
+    pub fn load(path: &Path) -> Self {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => {
+                let synonyms = Self::parse(&contents);
+                log::info!(
+                    "loaded synonyms for {} words from {:?}",
+                    synonyms.expansions.len(),
+                    path
+                );
+                synonyms
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Self::default(),
+            Err(err) => {
+                log::error!("Unable to read synonyms from {:?}: {}", path, err);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn parse(contents: &str) -> Self {
+        let mut synonyms = Self::default();
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let split = |words: &str| {
+                words
+                    .split(',')
+                    .map(|word| word.trim().to_lowercase())
+                    .filter(|word| !word.is_empty())
+                    .collect::<Vec<String>>()
+            };
+
+            match line.split_once("=>") {
+                Some((from, to)) => {
+                    let to = split(to);
+                    for word in split(from) {
+                        synonyms.add(&word, &to);
+                    }
+                }
+                None => {
+                    let group = split(line);
+                    for word in &group {
+                        synonyms.add(word, &group);
+                    }
+                }
+            }
+        }
+
+        synonyms
+    }
+
+    fn add(&mut self, word: &str, synonyms: &[String]) {
+        let expansions = self.expansions.entry(word.to_string()).or_default();
+        for synonym in synonyms {
+            if synonym != word && !expansions.contains(synonym) {
+                expansions.push(synonym.clone());
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.expansions.is_empty()
+    }
+
+    /// Synonyms for a single word, if there are any.
+    pub fn get(&self, word: &str) -> Option<&Vec<String>> {
+        self.expansions.get(&word.to_lowercase())
+    }
+
+    /// Expand the words in a parsed query w/ their synonyms. Free text words
+    /// are already scored as "any of these", so synonyms are added as more
+    /// free text. Words in groups are replaced by a group matching the word
+    /// or any of its synonyms. Phrases, filters & exclusions are left alone.
+    pub fn expand(&self, clauses: Vec<Clause>) -> Vec<Clause> {
+        if self.is_empty() {
+            return clauses;
+        }
+
+        let mut expanded = Vec::new();
+        let mut extra: Vec<String> = Vec::new();
+        for clause in clauses {
+            match clause {
+                Clause::Text(word) => {
+                    let synonyms = self.get(&word).into_iter().flatten();
+                    for synonym in synonyms.flat_map(|synonym| synonym.split_whitespace()) {
+                        if !extra.iter().any(|existing| existing == synonym) {
+                            extra.push(synonym.to_string());
+                        }
+                    }
+                    expanded.push(Clause::Text(word));
+                }
+                clause => expanded.push(self.expand_grouped(clause)),
+            }
+        }
+
+        // Don't double up on words the user already typed.
+        let typed = |word: &String| {
+            expanded.iter().any(
+                |clause| matches!(clause, Clause::Text(text) if text.eq_ignore_ascii_case(word)),
+            )
+        };
+        extra.retain(|word| !typed(word));
+        expanded.extend(extra.into_iter().map(Clause::Text));
+        expanded
+    }
+
+    fn expand_grouped(&self, clause: Clause) -> Clause {
+        match clause {
+            Clause::Text(word) => match self.get(&word) {
+                Some(synonyms) => {
+                    let mut any = vec![Clause::Text(word.clone())];
+                    any.extend(synonyms.iter().map(|synonym| {
+                        if synonym.contains(char::is_whitespace) {
+                            Clause::Phrase(synonym.clone())
+                        } else {
+                            Clause::Text(synonym.clone())
+                        }
+                    }));
+                    Clause::Any(any)
+                }
+                None => Clause::Text(word),
+            },
+            Clause::Any(clauses) => Clause::Any(
+                clauses
+                    .into_iter()
+                    .map(|clause| self.expand_grouped(clause))
+                    .collect(),
+            ),
+            Clause::All(clauses) => Clause::All(
+                clauses
+                    .into_iter()
+                    .map(|clause| self.expand_grouped(clause))
+                    .collect(),
+            ),
+            clause => clause,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::parser::{self, Clause, FilterNames};
+    use super::Synonyms;
+
+    fn parse(query: &str) -> Vec<Clause> {
+        parser::parse(query, &FilterNames::default())
+    }
+
+    fn text(value: &str) -> Clause {
+        Clause::Text(value.into())
+    }
+
+    #[test]
+    fn test_parse() {
+        let synonyms = Synonyms::parse(
+            "# comment\n\
+            js, JavaScript\n\
+            \n\
+            k8s => kubernetes, kube\n",
+        );
+
+        assert_eq!(synonyms.get("JS"), Some(&vec!["javascript".to_string()]));
+        assert_eq!(synonyms.get("javascript"), Some(&vec!["js".to_string()]));
+        assert_eq!(
+            synonyms.get("k8s"),
+            Some(&vec!["kubernetes".to_string(), "kube".to_string()])
+        );
+        assert_eq!(synonyms.get("kubernetes"), None);
+    }
+
+    #[test]
+    fn test_expand() {
+        let synonyms = Synonyms::parse("js, javascript\nnyc, new york city");
+
+        assert_eq!(
+            synonyms.expand(parse("js tutorial -nyc")),
+            vec![
+                text("js"),
+                text("tutorial"),
+                Clause::Not(Box::new(text("nyc"))),
+                text("javascript"),
+            ]
+        );
+
+        // Already searching for the synonym
+        assert_eq!(
+            synonyms.expand(parse("js javascript")),
+            vec![text("js"), text("javascript")]
+        );
+
+        assert_eq!(
+            synonyms.expand(parse("(nyc OR boston) site:example.com")),
+            vec![
+                Clause::Any(vec![
+                    Clause::Any(vec![text("nyc"), Clause::Phrase("new york city".into())]),
+                    text("boston"),
+                ]),
+                Clause::Filter("site".into(), "example.com".into()),
+            ]
+        );
+    }
+}
@@ -5,16 +5,20 @@ use entities::models::create_connection;
 use entities::sea_orm::DatabaseConnection;
 use tokio::sync::mpsc::error::SendError;
 use tokio::sync::Mutex;
-use tokio::sync::{broadcast, mpsc};
+use tokio::sync::{broadcast, mpsc, Notify};
 
 use crate::task::AppShutdown;
 use crate::{
+    archive::{ArchivePath, BlobArchive},
+    content::{caption::Captioner, ContentRules},
+    lock::PrivacyLock,
     pipeline::PipelineCommand,
-    plugin::{PluginCommand, PluginManager},
-    search::{IndexPath, Searcher},
+    plugin::{logs::PluginLogs, PluginCommand, PluginManager},
+    search::{deeplink::DeepLinks, IndexPath, Searcher, Synonyms},
     task::{AppPause, ManagerCommand},
 };
 use shared::config::{Config, LensConfig, PipelineConfiguration, UserSettings};
+use shared::response::PluginTaskProgress;
 
 #[derive(Clone)]
 pub struct AppState {
@@ -23,7 +27,17 @@ pub struct AppState {
     pub lenses: Arc<DashMap<String, LensConfig>>,
     pub pipelines: Arc<DashMap<String, PipelineConfiguration>>,
     pub user_settings: UserSettings,
+    /// Compiled from `user_settings`, which only change w/ a restart.
+    pub content_rules: Arc<ContentRules>,
+    /// Compiled from `user_settings` too.
+    pub deep_links: Arc<DeepLinks>,
     pub index: Searcher,
+    pub archive: BlobArchive,
+    /// Labels photos when image captioning is turned on.
+    pub captioner: Option<Arc<Captioner>>,
+    pub privacy_lock: Arc<PrivacyLock>,
+    /// Used to expand search queries, loaded from the user's synonyms file.
+    pub synonyms: Arc<Synonyms>,
     // Task scheduler command/control
     pub manager_cmd_tx: Arc<Mutex<Option<mpsc::UnboundedSender<ManagerCommand>>>>,
     pub shutdown_cmd_tx: Arc<Mutex<broadcast::Sender<AppShutdown>>>,
@@ -32,8 +46,14 @@ pub struct AppState {
     // Plugin command/control
     pub plugin_cmd_tx: Arc<Mutex<Option<mpsc::Sender<PluginCommand>>>>,
     pub plugin_manager: Arc<Mutex<PluginManager>>,
+    /// Progress reported by plugins for their long-running tasks, by plugin name.
+    pub plugin_progress: Arc<DashMap<String, Vec<PluginTaskProgress>>>,
+    /// Recent output of each plugin, by plugin name.
+    pub plugin_logs: Arc<DashMap<String, PluginLogs>>,
     // Pipeline command/control
     pub pipeline_cmd_tx: Arc<Mutex<Option<mpsc::Sender<PipelineCommand>>>>,
+    /// Wakes up clients waiting on saved search alerts when there are new matches.
+    pub saved_search_alerts: Arc<Notify>,
 }
 
 impl AppState {
@@ -46,6 +66,25 @@ impl AppState {
         let index = Searcher::with_index(&IndexPath::LocalPath(config.index_dir()))
             .expect("Unable to open index.");
 
+        let archive = BlobArchive::from_config(config).expect("Unable to open archive.");
+
+        let captioner = if config.user_settings.image_captioning {
+            let model_dir = config.models_dir().join("captioning");
+            match Captioner::load(&model_dir) {
+                Ok(captioner) => Some(Arc::new(captioner)),
+                Err(err) => {
+                    log::error!(
+                        "Unable to load captioning model from {:?}: {}",
+                        model_dir,
+                        err
+                    );
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
         // TODO: Load from saved preferences
         let app_state = DashMap::new();
         app_state.insert("paused".to_string(), "false".to_string());
@@ -67,15 +106,24 @@ impl AppState {
             db,
             app_state: Arc::new(app_state),
             user_settings: config.user_settings.clone(),
+            content_rules: Arc::new(ContentRules::new(&config.user_settings.content_rules)),
+            deep_links: Arc::new(DeepLinks::new(&config.user_settings.deep_links)),
             lenses: Arc::new(lenses),
             pipelines: Arc::new(pipelines),
             index,
+            archive,
+            captioner,
+            privacy_lock: Arc::new(PrivacyLock::from_config(config)),
+            synonyms: Arc::new(Synonyms::load(&Config::synonyms_file())),
             shutdown_cmd_tx: Arc::new(Mutex::new(shutdown_tx)),
             pause_cmd_tx: Arc::new(Mutex::new(None)),
             plugin_cmd_tx: Arc::new(Mutex::new(None)),
             pipeline_cmd_tx: Arc::new(Mutex::new(None)),
             plugin_manager: Arc::new(Mutex::new(PluginManager::new())),
+            plugin_progress: Arc::new(DashMap::new()),
+            plugin_logs: Arc::new(DashMap::new()),
             manager_cmd_tx: Arc::new(Mutex::new(None)),
+            saved_search_alerts: Arc::new(Notify::new()),
         }
     }
 
@@ -91,12 +139,35 @@ impl AppState {
         let cmd_tx = cmd_tx.as_ref().expect("Manager channel not open");
         cmd_tx.send(task)
     }
+
+    /// Whether crawling has been paused by the user.
+    pub fn is_paused(&self) -> bool {
+        self.app_state
+            .get("paused")
+            .map_or(false, |paused| paused.value() == "true")
+    }
+
+    /// Pause/resume the crawler. Workers are told over the pause channel, the
+    /// flag itself keeps the manager from dequeuing anything in the meantime.
+    pub async fn set_paused(&self, is_paused: bool) {
+        self.app_state
+            .insert("paused".to_string(), is_paused.to_string());
+
+        if let Some(sender) = self.pause_cmd_tx.lock().await.as_ref() {
+            let _ = sender.send(if is_paused {
+                AppPause::Pause
+            } else {
+                AppPause::Run
+            });
+        }
+    }
 }
 
 #[derive(Default)]
 pub struct AppStateBuilder {
     db: Option<DatabaseConnection>,
     index: Option<Searcher>,
+    archive: Option<BlobArchive>,
     lenses: Option<Vec<LensConfig>>,
     pipelines: Option<Vec<PipelineConfiguration>>,
     user_settings: Option<UserSettings>,
@@ -124,6 +195,12 @@ impl AppStateBuilder {
             Searcher::with_index(&IndexPath::Memory).expect("Unable to open search index")
         };
 
+        let archive = if let Some(archive) = &self.archive {
+            archive.to_owned()
+        } else {
+            BlobArchive::with_path(&ArchivePath::Memory)
+        };
+
         let user_settings = if let Some(settings) = &self.user_settings {
             settings.to_owned()
         } else {
@@ -132,11 +209,19 @@ impl AppStateBuilder {
 
         let (shutdown_tx, _) = broadcast::channel::<AppShutdown>(16);
 
+        let privacy_lock = PrivacyLock::new(&uuid::Uuid::new_v4().to_string(), &user_settings);
+
         AppState {
             app_state: Arc::new(DashMap::new()),
             db: self.db.as_ref().expect("Must set db").to_owned(),
+            content_rules: Arc::new(ContentRules::new(&user_settings.content_rules)),
+            deep_links: Arc::new(DeepLinks::new(&user_settings.deep_links)),
             user_settings,
             index,
+            archive,
+            captioner: None,
+            privacy_lock: Arc::new(privacy_lock),
+            synonyms: Arc::new(Synonyms::default()),
             lenses: Arc::new(lenses),
             shutdown_cmd_tx: Arc::new(Mutex::new(shutdown_tx)),
             pipelines: Arc::new(pipelines),
@@ -144,7 +229,10 @@ impl AppStateBuilder {
             plugin_cmd_tx: Arc::new(Mutex::new(None)),
             pipeline_cmd_tx: Arc::new(Mutex::new(None)),
             plugin_manager: Arc::new(Mutex::new(PluginManager::new())),
+            plugin_progress: Arc::new(DashMap::new()),
+            plugin_logs: Arc::new(DashMap::new()),
             manager_cmd_tx: Arc::new(Mutex::new(None)),
+            saved_search_alerts: Arc::new(Notify::new()),
         }
     }
 
@@ -171,4 +259,9 @@ impl AppStateBuilder {
         self.index = Some(Searcher::with_index(index).expect("Unable to open index"));
         self
     }
+
+    pub fn with_archive(&mut self, archive: &ArchivePath) -> &mut Self {
+        self.archive = Some(BlobArchive::with_path(archive));
+        self
+    }
 }
@@ -3,10 +3,15 @@ use notify::{EventKind, RecursiveMode, Watcher};
 use std::time::Duration;
 use tokio::sync::{broadcast, mpsc};
 
-use shared::config::Config;
+use entities::models::crawl_queue::{self, EnqueueSettings};
+use entities::models::tag::TagType;
+use shared::config::{Config, ImportSource};
+use spyglass_plugin::utils::path_to_uri;
 
 use crate::connection::load_connection;
+use crate::content::ocr;
 use crate::crawler::bootstrap;
+use crate::importer;
 use crate::search::lens::{load_lenses, read_lenses};
 use crate::state::AppState;
 use crate::task::worker::FetchResult;
@@ -14,6 +19,11 @@ use crate::task::worker::FetchResult;
 mod manager;
 mod worker;
 
+pub use worker::{index_snapshot, reindex_url};
+
+/// Max number of queued crawls the worker picks up at once.
+const MAX_CRAWL_BATCH: usize = 16;
+
 #[derive(Debug, Clone)]
 pub struct CrawlTask {
     pub id: i64,
@@ -27,11 +37,21 @@ pub enum CollectTask {
         seed_url: String,
         pipeline: Option<String>,
     },
+    // Enqueue the URLs listed in a lens sitemap
+    Sitemap {
+        lens: String,
+        url: String,
+        pipeline: Option<String>,
+    },
     // Connects to an integration and discovers all the crawlable URIs
     ConnectionSync {
         api_id: String,
         account: String,
     },
+    // Reads another app's data/export & adds the documents in it to the queue
+    ImportSync {
+        source: ImportSource,
+    },
 }
 
 /// Tell the manager to schedule some tasks
@@ -81,6 +101,14 @@ pub async fn manager_task(
 
     let mut queue_check_interval = tokio::time::interval(Duration::from_millis(100));
     let mut commit_check_interval = tokio::time::interval(Duration::from_secs(10));
+    let mut watch_check_interval = tokio::time::interval(Duration::from_secs(60));
+    let mut saved_search_interval = tokio::time::interval(Duration::from_secs(60));
+    let mut import_sync_interval = tokio::time::interval(Duration::from_secs(60 * 60));
+    let mut retention_interval = tokio::time::interval(Duration::from_secs(60 * 60 * 24));
+    let mut stall_check_interval = tokio::time::interval(Duration::from_secs(60));
+    let mut stale_task_interval = tokio::time::interval(Duration::from_secs(5 * 60));
+    let mut maintenance_interval = tokio::time::interval(Duration::from_secs(15 * 60));
+    let mut last_dequeue = chrono::Utc::now();
     let mut shutdown_rx = state.shutdown_cmd_tx.lock().await.subscribe();
 
     loop {
@@ -105,6 +133,7 @@ pub async fn manager_task(
                                 // first tick always completes immediately.
                                 queue_check_interval.tick().await;
                             } else {
+                                last_dequeue = chrono::Utc::now();
                                 queue_check_interval = tokio::time::interval(Duration::from_millis(100));
                                 // first tick always completes immediately.
                                 queue_check_interval.tick().await;
@@ -117,6 +146,43 @@ pub async fn manager_task(
             _ = commit_check_interval.tick() => {
                 let _ = queue.send(WorkerCommand::CommitIndex).await;
             }
+            // Recrawl watched pages that are due for a check
+            _ = watch_check_interval.tick() => {
+                manager::check_watched_pages(&state, &queue).await;
+            }
+            // Let the user know about new matches for their saved searches
+            _ = saved_search_interval.tick() => {
+                manager::check_saved_searches(&state).await;
+            }
+            // Pick up new/updated documents from imported apps
+            _ = import_sync_interval.tick() => {
+                for source in &state.user_settings.imports {
+                    let task = CollectTask::ImportSync { source: source.clone() };
+                    if let Err(err) = queue.send(WorkerCommand::Collect(task)).await {
+                        log::error!("Unable to send worker cmd: {}", err.to_string());
+                    }
+                }
+            }
+            // Unstick the queue if nothing has been dequeued in a while
+            _ = stall_check_interval.tick() => {
+                if manager::check_for_stall(&state, last_dequeue).await {
+                    // Give the requeued tasks a chance before checking again
+                    last_dequeue = chrono::Utc::now();
+                }
+            }
+            // Requeue tasks left behind by crashed/hung crawls
+            _ = stale_task_interval.tick() => {
+                manager::requeue_stale_tasks(&state).await;
+            }
+            // Prune documents past their connection's retention policy
+            _ = retention_interval.tick() => {
+                manager::apply_retention(&state).await;
+                manager::prune_crawl_queue(&state).await;
+            }
+            // Tidy up the index while things are quiet
+            _ = maintenance_interval.tick() => {
+                manager::merge_when_idle(&state, last_dequeue).await;
+            }
             // If we're not handling anything, continually poll for jobs.
             _ = queue_check_interval.tick() => {
                 if let Err(err) = manager_cmd_tx.send(ManagerCommand::CheckForJobs) {
@@ -133,6 +199,7 @@ pub async fn manager_task(
 }
 
 /// Grabs a task
+#[tracing::instrument(skip_all)]
 pub async fn worker_task(
     state: AppState,
     mut queue: mpsc::Receiver<WorkerCommand>,
@@ -141,6 +208,8 @@ pub async fn worker_task(
     log::info!("worker started");
     let mut is_paused = false;
     let mut updated_docs = 0;
+    // Pulled off the queue while gathering a batch of crawls.
+    let mut deferred = None;
     let mut shutdown_rx = state.shutdown_cmd_tx.lock().await.subscribe();
 
     loop {
@@ -164,104 +233,145 @@ pub async fn worker_task(
             continue;
         }
 
-        tokio::select! {
-            res = queue.recv() => {
-                if let Some(cmd) = res {
-                    match cmd {
-                        WorkerCommand::Collect(task) => match task {
-                            CollectTask::Bootstrap {
-                                lens,
-                                seed_url,
-                                pipeline,
-                            } => {
-                                log::debug!("handling Bootstrap for {} - {}", lens, seed_url);
-                                let state = state.clone();
-                                tokio::spawn(async move {
-                                    if let Some(lens_config) = &state.lenses.get(&lens) {
-                                        worker::handle_bootstrap(&state, lens_config, &seed_url, pipeline)
-                                            .await;
-                                    }
-                                });
-                            }
-                            CollectTask::ConnectionSync { api_id, account } => {
-                                log::debug!("handling ConnectionSync for {}", api_id);
-                                let state = state.clone();
-                                tokio::spawn(async move {
-                                    match load_connection(&state, &api_id, &account).await {
-                                        Ok(mut conn) => {
-                                            conn.as_mut().sync(&state).await;
-                                        }
-                                        Err(err) => log::error!(
-                                            "Unable to sync w/ connection: {} - {}",
-                                            api_id,
-                                            err.to_string()
-                                        ),
-                                    }
-                                });
-                            }
-                        },
-                        WorkerCommand::CommitIndex => {
-                            let state = state.clone();
-                            if updated_docs > 0 {
-                                log::debug!("committing {} new/updated docs in index", updated_docs);
-                                updated_docs = 0;
-                                tokio::spawn(async move {
-                                    match state.index.writer.lock() {
-                                        Ok(mut writer) => {
-                                            let _ = writer.commit();
-                                        }
-                                        Err(err) => {
-                                            log::debug!(
-                                                "Unable to acquire lock on index writer: {}",
-                                                err.to_string()
-                                            )
-                                        }
-                                    }
-                                });
-                            }
+        let cmd = match deferred.take() {
+            Some(cmd) => cmd,
+            None => tokio::select! {
+                res = queue.recv() => match res {
+                    Some(cmd) => cmd,
+                    None => continue,
+                },
+                res = pause_rx.recv() => {
+                    if let Ok(AppPause::Pause) = res {
+                        is_paused = true;
+                    }
+                    continue;
+                },
+                _ = shutdown_rx.recv() => {
+                    log::info!("🛑 Shutting down worker");
+                    queue.close();
+                    return;
+                }
+            },
+        };
+
+        manager::record_worker_activity();
+        match cmd {
+            WorkerCommand::Collect(task) => match task {
+                CollectTask::Bootstrap {
+                    lens,
+                    seed_url,
+                    pipeline,
+                } => {
+                    log::debug!("handling Bootstrap for {} - {}", lens, seed_url);
+                    let state = state.clone();
+                    tokio::spawn(async move {
+                        if let Some(lens_config) = &state.lenses.get(&lens) {
+                            worker::handle_bootstrap(&state, lens_config, &seed_url, pipeline)
+                                .await;
                         }
-                        WorkerCommand::Crawl { id } => {
-                            if let Ok(fetch_result) =
-                                tokio::spawn(worker::handle_fetch(state.clone(), CrawlTask { id })).await
-                            {
-                                match fetch_result {
-                                    FetchResult::New | FetchResult::Updated => updated_docs += 1,
-                                    _ => {}
-                                }
+                    });
+                }
+                CollectTask::Sitemap {
+                    lens,
+                    url,
+                    pipeline,
+                } => {
+                    log::debug!("handling Sitemap for {} - {}", lens, url);
+                    let state = state.clone();
+                    tokio::spawn(async move {
+                        if let Some(lens_config) = &state.lenses.get(&lens) {
+                            worker::handle_sitemap(&state, lens_config, &url, pipeline).await;
+                        }
+                    });
+                }
+                CollectTask::ConnectionSync { api_id, account } => {
+                    log::debug!("handling ConnectionSync for {}", api_id);
+                    let state = state.clone();
+                    tokio::spawn(async move {
+                        match load_connection(&state, &api_id, &account).await {
+                            Ok(mut conn) => {
+                                conn.as_mut().sync(&state).await;
                             }
+                            Err(err) => log::error!(
+                                "Unable to sync w/ connection: {} - {}",
+                                api_id,
+                                err.to_string()
+                            ),
                         }
-                        WorkerCommand::Recrawl { id } => {
-                            if let Ok(fetch_result) = tokio::spawn(worker::handle_fetch(state.clone(), CrawlTask { id })).await
-                            {
-                                match fetch_result {
-                                    FetchResult::New | FetchResult::Updated => updated_docs += 1,
-                                    FetchResult::NotFound => {
-                                        // URL no longer exists, delete from index.
-                                        log::debug!("URI not found, deleting from index");
-                                        let _ = tokio::spawn(worker::handle_deletion(state.clone(), id)).await;
-                                    }
-                                    FetchResult::Error(err) => {
-                                        log::warn!("Unable to recrawl {} - {}", id, err);
-                                    },
-                                    FetchResult::Ignore => {}
-                                }
+                    });
+                }
+                CollectTask::ImportSync { source } => {
+                    log::debug!("handling ImportSync for {:?}", source);
+                    let state = state.clone();
+                    tokio::spawn(async move {
+                        importer::sync(&state, source).await;
+                    });
+                }
+            },
+            WorkerCommand::CommitIndex => {
+                let state = state.clone();
+                if updated_docs > 0 {
+                    log::debug!("committing {} new/updated docs in index", updated_docs);
+                    updated_docs = 0;
+                    tokio::spawn(async move {
+                        match state.index.writer.lock() {
+                            Ok(mut writer) => {
+                                let _ = writer.commit();
                             }
+                            Err(err) => {
+                                log::debug!(
+                                    "Unable to acquire lock on index writer: {}",
+                                    err.to_string()
+                                )
+                            }
+                        }
+                    });
+                }
+            }
+            WorkerCommand::Crawl { id } => {
+                // Crawl whatever else is already waiting along w/ this
+                // task so their statuses are written together.
+                let mut tasks = vec![CrawlTask { id }];
+                while tasks.len() < MAX_CRAWL_BATCH {
+                    match queue.try_recv() {
+                        Ok(WorkerCommand::Crawl { id }) => tasks.push(CrawlTask { id }),
+                        Ok(cmd) => {
+                            deferred = Some(cmd);
+                            break;
                         }
-                        WorkerCommand::Tag => {}
+                        Err(_) => break,
                     }
                 }
-            },
-            res = pause_rx.recv() => {
-                if let Ok(AppPause::Pause) = res {
-                    is_paused = true;
+
+                if let Ok(fetch_results) =
+                    tokio::spawn(worker::handle_fetch_batch(state.clone(), tasks)).await
+                {
+                    updated_docs += fetch_results
+                        .iter()
+                        .filter(|res| matches!(res, FetchResult::New | FetchResult::Updated))
+                        .count();
                 }
-            },
-            _ = shutdown_rx.recv() => {
-                log::info!("🛑 Shutting down worker");
-                queue.close();
-                return;
             }
-        };
+            WorkerCommand::Recrawl { id } => {
+                if let Ok(fetch_result) =
+                    tokio::spawn(worker::handle_fetch(state.clone(), CrawlTask { id })).await
+                {
+                    match fetch_result {
+                        FetchResult::New | FetchResult::Updated => updated_docs += 1,
+                        FetchResult::NotFound => {
+                            // URL no longer exists, delete from index.
+                            log::debug!("URI not found, deleting from index");
+                            let _ = tokio::spawn(worker::handle_deletion(state.clone(), id)).await;
+                        }
+                        FetchResult::Error(err) => {
+                            log::warn!("Unable to recrawl {} - {}", id, err);
+                        }
+                        FetchResult::Ignore => {}
+                    }
+                }
+            }
+            WorkerCommand::Tag => {}
+        }
     }
 }
 
@@ -357,3 +467,125 @@ pub async fn lens_watcher(
         }
     }
 }
+
+/// Watches the screenshots folder & queues up new screenshots as soon as
+/// they're taken so their text can be OCR'd & indexed.
+pub async fn screenshot_watcher(state: AppState, mut pause_rx: broadcast::Receiver<AppPause>) {
+    if !state.user_settings.screenshot_ocr {
+        return;
+    }
+
+    let screenshots_dir = match ocr::screenshots_dir(&state.user_settings) {
+        Some(dir) if dir.exists() => dir,
+        _ => {
+            log::warn!("Unable to find screenshots folder, screenshot OCR disabled");
+            return;
+        }
+    };
+
+    log::info!(
+        "📸 screenshot watcher started: {}",
+        screenshots_dir.display()
+    );
+    let mut shutdown_rx = state.shutdown_cmd_tx.lock().await.subscribe();
+
+    let mut is_paused = false;
+    let (tx, mut rx) = tokio::sync::mpsc::channel(1);
+
+    let mut watcher = notify::recommended_watcher(move |res| {
+        futures::executor::block_on(async {
+            if !tx.is_closed() {
+                if let Err(err) = tx.send(res).await {
+                    log::error!("fseventwatcher channel error: {}. If we're in shutdown mode, nothing to worry about.", err.to_string());
+                }
+            }
+        })
+    })
+    .expect("Unable to watch screenshots directory");
+
+    let _ = watcher.watch(&screenshots_dir, RecursiveMode::NonRecursive);
+
+    let enqueue_settings = EnqueueSettings {
+        force_allow: true,
+        tags: vec![(TagType::Source, ocr::SCREENSHOT_TAG.to_string())],
+        ..Default::default()
+    };
+
+    loop {
+        if is_paused {
+            tokio::select! {
+                res = pause_rx.recv() => {
+                    if let Ok(AppPause::Run) = res {
+                        is_paused = false;
+                    }
+                },
+                _ = shutdown_rx.recv() => {
+                    log::info!("🛑 Shutting down screenshot watcher");
+                    return;
+                }
+            };
+
+            tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+            continue;
+        }
+
+        let event = tokio::select! {
+            res = rx.recv() => res,
+            res = pause_rx.recv() => {
+                if let Ok(AppPause::Pause) = res {
+                    is_paused = true;
+                }
+
+                None
+            },
+            _ = shutdown_rx.recv() => {
+                log::info!("🛑 Shutting down screenshot watcher");
+                return;
+            }
+        };
+
+        let event = match event {
+            Some(Ok(event)) => event,
+            Some(Err(e)) => {
+                log::error!("watch error: {:?}", e);
+                continue;
+            }
+            None => continue,
+        };
+
+        if !matches!(
+            event.kind,
+            EventKind::Create(_) | EventKind::Modify(ModifyKind::Name(_))
+        ) {
+            continue;
+        }
+
+        let urls = event
+            .paths
+            .iter()
+            .filter(|path| path.is_file() && ocr::is_screenshot(&state.user_settings, path))
+            .map(|path| path_to_uri(path.to_path_buf()))
+            .collect::<Vec<String>>();
+
+        if urls.is_empty() {
+            continue;
+        }
+
+        log::debug!("queuing {} new screenshot(s)", urls.len());
+        match crawl_queue::enqueue_all(
+            &state.db,
+            &urls,
+            &[],
+            &state.user_settings,
+            &enqueue_settings,
+            None,
+        )
+        .await
+        {
+            Ok(_) => {
+                let _ = state.schedule_work(ManagerCommand::CheckForJobs).await;
+            }
+            Err(err) => log::error!("error adding screenshots to queue: {}", err),
+        }
+    }
+}
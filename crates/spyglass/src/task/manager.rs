@@ -1,13 +1,57 @@
-use entities::models::crawl_queue;
+use std::sync::atomic::{AtomicI64, AtomicUsize, Ordering};
+
+use chrono::{DateTime, Utc};
+use entities::models::crawl_queue::CrawlStatus;
+use entities::models::{crawl_queue, indexed_document, saved_search, watched_page};
+use entities::sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QueryOrder, QuerySelect};
+use regex::Regex;
+use spyglass_plugin::SearchFilter;
 use tokio::sync::mpsc;
 
 use super::{CrawlTask, WorkerCommand};
 use crate::pipeline::PipelineCommand;
+use crate::search::{
+    lens::{filter_names, lens_names_to_filters, limits_scope},
+    maintenance, QueryOptions, Searcher,
+};
 use crate::state::AppState;
 
+/// Every Nth check looks for recrawls before new crawls, so a large crawl
+/// queue doesn't keep indexed documents from ever being refreshed.
+const RECRAWL_EVERY: usize = 10;
+static NUM_CHECKS: AtomicUsize = AtomicUsize::new(0);
+/// Minutes w/o anything being dequeued, while there are tasks ready to go,
+/// before the manager is considered stalled.
+const STALL_TIMEOUT_MINS: i64 = 10;
+/// Tasks processing for longer than this are assumed to have been abandoned.
+const PROCESSING_TIMEOUT_MINS: i64 = 30;
+/// When the worker last picked up a command (unix timestamp), for diagnostics.
+static LAST_WORKER_CMD: AtomicI64 = AtomicI64::new(0);
+/// Seconds to wait before checking new documents against saved searches,
+/// leaving time for them to be committed to the index.
+const SAVED_SEARCH_DELAY_SECS: i64 = 30;
+/// Most new documents checked against a saved search at a time.
+const MAX_SAVED_SEARCH_DOCS: u64 = 1_000;
+
+pub fn record_worker_activity() {
+    LAST_WORKER_CMD.store(Utc::now().timestamp(), Ordering::Relaxed);
+}
+
 // Check for new jobs in the crawl queue and add them to the worker queue.
 #[tracing::instrument(skip(state, queue))]
 pub async fn check_for_jobs(state: &AppState, queue: &mpsc::Sender<WorkerCommand>) -> bool {
+    // Leave everything queued, dequeuing marks tasks as processing & they'd
+    // sit in the worker channel until crawling is resumed.
+    if state.is_paused() {
+        return false;
+    }
+
+    if NUM_CHECKS.fetch_add(1, Ordering::Relaxed) % RECRAWL_EVERY == 0
+        && check_for_recrawl(state, queue).await
+    {
+        return true;
+    }
+
     // Do we have any crawl tasks?
     match crawl_queue::dequeue(&state.db, state.user_settings.clone()).await {
         Ok(Some(task)) => {
@@ -43,30 +87,367 @@ pub async fn check_for_jobs(state: &AppState, queue: &mpsc::Sender<WorkerCommand
     }
 
     // No crawl tasks, check for recrawl tasks
-    match crawl_queue::dequeue_recrawl(&state.db, &state.user_settings).await {
-        Ok(Some(task)) => {
+    check_for_recrawl(state, queue).await
+}
+
+async fn check_for_recrawl(state: &AppState, queue: &mpsc::Sender<WorkerCommand>) -> bool {
+    let task = match crawl_queue::dequeue_recrawl(&state.db, &state.user_settings).await {
+        Ok(Some(task)) => Some(task),
+        Err(err) => {
+            log::error!("Unable to dequeue_recrawl jobs: {}", err.to_string());
+            return false;
+        }
+        // No local files to recrawl, check for web pages that are due
+        Ok(None) => check_for_refresh(state).await,
+    };
+
+    match task {
+        Some(task) => {
             // Send to worker
             let cmd = WorkerCommand::Recrawl { id: task.id };
             if queue.send(cmd).await.is_err() {
                 log::error!("unable to send command to worker");
             }
-            return true;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Web page from a lens w/ a `refresh_interval` that's due to be refetched.
+async fn check_for_refresh(state: &AppState) -> Option<crawl_queue::Model> {
+    let schedules = state
+        .lenses
+        .iter()
+        .filter(|lens| lens.is_enabled)
+        .filter_map(|lens| {
+            let interval = chrono::Duration::from_std(lens.refresh_interval()?).ok()?;
+            Some((lens.name.clone(), interval))
+        })
+        .collect::<Vec<_>>();
+
+    for (lens, interval) in schedules {
+        match crawl_queue::dequeue_refresh(&state.db, &state.user_settings, &lens, interval).await {
+            Ok(Some(task)) => return Some(task),
+            Ok(None) => {}
+            Err(err) => log::error!("Unable to check {} for refreshes: {}", lens, err),
         }
+    }
+
+    None
+}
+
+/// Recrawl any watched pages that are due for a check.
+#[tracing::instrument(skip(state, queue))]
+pub async fn check_watched_pages(state: &AppState, queue: &mpsc::Sender<WorkerCommand>) {
+    if state.is_paused() {
+        return;
+    }
+
+    let due = match watched_page::due(&state.db).await {
+        Ok(due) => due,
         Err(err) => {
-            log::error!("Unable to dequeue_recrawl jobs: {}", err.to_string());
+            log::error!("Unable to check watched pages: {}", err);
+            return;
+        }
+    };
+
+    for page in due {
+        match crawl_queue::start_recrawl(&state.db, &page.url).await {
+            Ok(Some(task)) => {
+                if queue
+                    .send(WorkerCommand::Recrawl { id: task.id })
+                    .await
+                    .is_err()
+                {
+                    log::error!("unable to send command to worker");
+                    continue;
+                }
+            }
+            // Already being crawled, check again once it's done.
+            Ok(None) => continue,
+            Err(err) => {
+                log::error!("Unable to recrawl <{}>: {}", page.url, err);
+                continue;
+            }
+        }
+
+        if let Err(err) = watched_page::mark_checked(&state.db, page.id).await {
+            log::error!("Unable to update watched page <{}>: {}", page.url, err);
+        }
+    }
+}
+
+/// Checks whether the manager has stopped dequeuing tasks even though there's
+/// work queued up, e.g. after a worker died mid-crawl & its tasks are counted
+/// against the inflight limit forever. Stuck tasks are requeued.
+///
+/// Returns true if a stall was detected.
+#[tracing::instrument(skip(state))]
+pub async fn check_for_stall(state: &AppState, last_dequeue: DateTime<Utc>) -> bool {
+    let stalled_for = Utc::now() - last_dequeue;
+    if state.is_paused() || stalled_for < chrono::Duration::minutes(STALL_TIMEOUT_MINS) {
+        return false;
+    }
+
+    let num_ready = match crawl_queue::num_ready(&state.db).await {
+        Ok(0) => return false,
+        Ok(num_ready) => num_ready,
+        Err(err) => {
+            log::error!("Unable to check crawl queue: {}", err);
             return false;
         }
-        _ => {}
+    };
+
+    let num_processing = crawl_queue::num_queued(&state.db, CrawlStatus::Processing)
+        .await
+        .unwrap_or_default();
+    let last_worker_cmd = match LAST_WORKER_CMD.load(Ordering::Relaxed) {
+        0 => "never".to_string(),
+        ts => format!("{}s ago", Utc::now().timestamp() - ts),
+    };
+    log::warn!(
+        "nothing dequeued in {} mins: {} tasks ready, {} processing (inflight limit: {:?}), worker last active: {}",
+        stalled_for.num_minutes(),
+        num_ready,
+        num_processing,
+        state.user_settings.inflight_crawl_limit,
+        last_worker_cmd
+    );
+
+    if num_processing > 0 {
+        match crawl_queue::reset_processing(&state.db).await {
+            Ok(num_reset) => log::warn!("requeued {} stuck tasks", num_reset),
+            Err(err) => log::error!("Unable to requeue stuck tasks: {}", err),
+        }
     }
 
-    false
+    true
+}
+
+/// Requeue tasks that have been processing for too long. Unlike the stall
+/// check this doesn't wait for the whole queue to back up.
+#[tracing::instrument(skip(state))]
+pub async fn requeue_stale_tasks(state: &AppState) {
+    let timeout = chrono::Duration::minutes(PROCESSING_TIMEOUT_MINS);
+    match crawl_queue::reset_stale_processing(&state.db, timeout).await {
+        Ok(0) => {}
+        Ok(num_reset) => log::warn!(
+            "requeued {} tasks processing for over {} mins",
+            num_reset,
+            PROCESSING_TIMEOUT_MINS
+        ),
+        Err(err) => log::error!("Unable to requeue stale tasks: {}", err),
+    }
+}
+
+/// Remove completed crawls older than `completed_task_retention_days` from
+/// the crawl queue, if set.
+#[tracing::instrument(skip(state))]
+pub async fn prune_crawl_queue(state: &AppState) {
+    let days = match state.user_settings.completed_task_retention_days {
+        Some(days) => days,
+        None => return,
+    };
+
+    let cutoff = chrono::Utc::now() - chrono::Duration::days(days.into());
+    match crawl_queue::remove_completed(&state.db, cutoff).await {
+        Ok(0) => {}
+        Ok(removed) => log::info!("pruned {} completed tasks from the crawl queue", removed),
+        Err(err) => log::error!("Unable to prune crawl queue: {}", err),
+    }
+}
+
+/// Remove documents older than their connection's retention policy from the
+/// index & database.
+#[tracing::instrument(skip(state))]
+pub async fn apply_retention(state: &AppState) {
+    for (api_id, days) in &state.user_settings.connection_retention_days {
+        let cutoff = chrono::Utc::now() - chrono::Duration::days(*days as i64);
+        let expired =
+            match indexed_document::expired_for_connection(&state.db, api_id, cutoff).await {
+                Ok(expired) => expired,
+                Err(err) => {
+                    log::error!("Unable to apply {} retention: {}", api_id, err);
+                    continue;
+                }
+            };
+
+        if expired.is_empty() {
+            continue;
+        }
+
+        let urls = expired
+            .iter()
+            .map(|doc| doc.url.clone())
+            .collect::<Vec<String>>();
+        for doc in expired {
+            if let Err(err) = Searcher::delete_by_id(state, &doc.doc_id).await {
+                log::error!("Unable to remove <{}>: {}", doc.url, err);
+            }
+        }
+        let _ = Searcher::save(state).await;
+
+        // Otherwise the next sync would recrawl them
+        if let Err(err) = crawl_queue::Entity::delete_many()
+            .filter(crawl_queue::Column::Url.is_in(urls.clone()))
+            .exec(&state.db)
+            .await
+        {
+            log::error!("Unable to remove expired tasks: {}", err);
+        }
+
+        log::info!(
+            "removed {} {} documents older than {} days",
+            urls.len(),
+            api_id,
+            days
+        );
+    }
+}
+
+/// Merge small index segments together once nothing has been crawled for a
+/// while.
+#[tracing::instrument(skip(state))]
+pub async fn merge_when_idle(state: &AppState, last_dequeue: DateTime<Utc>) {
+    let settings = &state.user_settings.index_maintenance;
+    let idle_for = Utc::now() - last_dequeue;
+    if !settings.enabled || idle_for < chrono::Duration::minutes(settings.idle_minutes as i64) {
+        return;
+    }
+
+    match maintenance::merge_small_segments(&state.index, settings.small_segment_docs).await {
+        Ok(Some(merged)) => log::info!(
+            "merged index from {} to {} segments",
+            merged.segments_before,
+            merged.segments_after
+        ),
+        Ok(None) => {}
+        Err(err) => log::error!("Unable to merge index segments: {}", err),
+    }
+}
+
+/// Whether `url` gets through the lens `filters`, the same way search results do.
+fn passes_filters(filters: &[SearchFilter], url: &str) -> bool {
+    let mut allowed = false;
+    let mut has_allow = false;
+    for filter in filters {
+        match filter {
+            SearchFilter::URLRegexSkip(regex) => {
+                if Regex::new(regex).map_or(false, |regex| regex.is_match(url)) {
+                    return false;
+                }
+            }
+            SearchFilter::URLRegexAllow(regex) => {
+                has_allow = true;
+                allowed |= Regex::new(regex).map_or(false, |regex| regex.is_match(url));
+            }
+            SearchFilter::None => {}
+        }
+    }
+
+    allowed || !has_allow
+}
+
+/// Check documents indexed since the last check against each saved search,
+/// waking up anyone waiting on alerts if there are new matches.
+#[tracing::instrument(skip(state))]
+pub async fn check_saved_searches(state: &AppState) {
+    let searches = match saved_search::list(&state.db).await {
+        Ok(searches) => searches,
+        Err(err) => {
+            log::error!("Unable to check saved searches: {}", err);
+            return;
+        }
+    };
+
+    // Documents are added to the database before the index is committed, so
+    // give the latest ones a chance to show up in the index first.
+    let checked_at = Utc::now() - chrono::Duration::seconds(SAVED_SEARCH_DELAY_SECS);
+    let names = filter_names(state);
+    let options = QueryOptions {
+        fuzzy_distance: state.user_settings.fuzzy_distance,
+        synonyms: None,
+        date_locale: state.user_settings.date_locale,
+        pinned: None,
+        filter_names: Some(&names),
+    };
+
+    let mut has_matches = false;
+    for search in searches {
+        if search.last_checked_at >= checked_at {
+            continue;
+        }
+
+        let new_docs = indexed_document::Entity::find()
+            .filter(indexed_document::Column::CreatedAt.gt(search.last_checked_at))
+            .filter(indexed_document::Column::CreatedAt.lte(checked_at))
+            .order_by_asc(indexed_document::Column::CreatedAt)
+            .limit(MAX_SAVED_SEARCH_DOCS)
+            .all(&state.db)
+            .await;
+        let new_docs = match new_docs {
+            Ok(docs) => docs,
+            Err(err) => {
+                log::error!("Unable to find new documents: {}", err);
+                return;
+            }
+        };
+
+        // Pick up where this left off next time if there were too many to check.
+        let search_checked_at = match new_docs.last() {
+            Some(doc) if new_docs.len() as u64 >= MAX_SAVED_SEARCH_DOCS => doc.created_at,
+            _ => checked_at,
+        };
+
+        // Lenses that don't allow any URLs leave nothing to match, rather than
+        // everything.
+        let filters = lens_names_to_filters(state.clone(), &search.lenses.lenses).await;
+        let in_scope = search.lenses.lenses.is_empty() || limits_scope(&filters);
+        let doc_ids = new_docs
+            .into_iter()
+            .filter(|doc| in_scope && passes_filters(&filters, &doc.url))
+            .map(|doc| doc.doc_id)
+            .collect::<Vec<_>>();
+
+        let matching =
+            match Searcher::matching_ids(&state.db, &state.index, &search.query, &doc_ids, options)
+                .await
+            {
+                Ok(matching) => matching,
+                Err(err) => {
+                    log::error!("Unable to check saved search {}: {}", search.name, err);
+                    continue;
+                }
+            };
+
+        if !matching.is_empty() {
+            log::info!(
+                "{} new matches for saved search {}",
+                matching.len(),
+                search.name
+            );
+            has_matches = true;
+        }
+
+        let name = search.name.clone();
+        if let Err(err) =
+            saved_search::mark_checked(&state.db, search, search_checked_at, &matching).await
+        {
+            log::error!("Unable to update saved search {}: {}", name, err);
+        }
+    }
+
+    if has_matches {
+        state.saved_search_alerts.notify_waiters();
+    }
 }
 
 #[cfg(test)]
 mod test {
     use tokio::sync::mpsc;
 
-    use super::check_for_jobs;
+    use super::{check_for_jobs, check_for_stall};
     use crate::{state::AppState, task::WorkerCommand};
     use entities::models::crawl_queue::{self, CrawlStatus, CrawlType};
     use entities::sea_orm::{ActiveModelTrait, Set};
@@ -100,6 +481,74 @@ mod test {
         );
     }
 
+    #[tokio::test]
+    async fn test_check_for_jobs_paused() {
+        let db = setup_test_db().await;
+        let state = AppState::builder().with_db(db.clone()).build();
+
+        let task = crawl_queue::ActiveModel {
+            url: Set("https://example.com".to_owned()),
+            domain: Set("example.com".to_owned()),
+            crawl_type: Set(CrawlType::Normal),
+            status: Set(CrawlStatus::Queued),
+            ..Default::default()
+        };
+        task.save(&db).await.expect("Unable to save dummy task");
+
+        let (sender, mut recv) = mpsc::channel(10);
+        state.set_paused(true).await;
+        assert!(!check_for_jobs(&state, &sender).await);
+        assert!(recv.try_recv().is_err());
+
+        state.set_paused(false).await;
+        assert!(check_for_jobs(&state, &sender).await);
+        assert!(recv.try_recv().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_check_for_stall() {
+        let db = setup_test_db().await;
+        let state = AppState::builder().with_db(db.clone()).build();
+
+        for (url, status) in [
+            ("https://example.com/stuck", CrawlStatus::Processing),
+            ("https://example.com/ready", CrawlStatus::Queued),
+        ] {
+            let task = crawl_queue::ActiveModel {
+                url: Set(url.to_owned()),
+                domain: Set("example.com".to_owned()),
+                crawl_type: Set(CrawlType::Normal),
+                status: Set(status),
+                ..Default::default()
+            };
+            task.save(&db).await.expect("Unable to save dummy task");
+        }
+
+        // Recently dequeued something, leave it be.
+        assert!(!check_for_stall(&state, chrono::Utc::now()).await);
+        assert_eq!(
+            crawl_queue::num_queued(&db, CrawlStatus::Processing)
+                .await
+                .unwrap(),
+            1
+        );
+
+        let last_dequeue = chrono::Utc::now() - chrono::Duration::minutes(30);
+        assert!(check_for_stall(&state, last_dequeue).await);
+        assert_eq!(
+            crawl_queue::num_queued(&db, CrawlStatus::Processing)
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(
+            crawl_queue::num_queued(&db, CrawlStatus::Queued)
+                .await
+                .unwrap(),
+            2
+        );
+    }
+
     #[tokio::test]
     async fn test_check_for_jobs_recrawl() {
         let db = setup_test_db().await;
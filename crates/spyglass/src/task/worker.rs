@@ -1,14 +1,22 @@
+use chrono::{DateTime, Utc};
 use url::Url;
 
-use entities::models::{bootstrap_queue, crawl_queue, indexed_document, tag};
+use entities::models::crawl_queue::TaskError;
+use entities::models::{
+    bootstrap_queue, crawl_queue, document_note, document_version, fetch_history, indexed_document,
+    tag, watched_page,
+};
+use entities::schema::{DocFields, SearchDocument};
 use entities::sea_orm::prelude::*;
 use entities::sea_orm::{ColumnTrait, EntityTrait, QueryFilter, Set};
 use shared::config::LensConfig;
+use shared::normalize::UrlNormalizer;
 
 use super::bootstrap;
 use super::CrawlTask;
-use crate::crawler::{CrawlError, CrawlResult, Crawler};
-use crate::search::Searcher;
+use crate::content::{diff, quality, ContentVerdict};
+use crate::crawler::{images, sitemap, CrawlError, CrawlResult, Crawler, ScrapeOptions};
+use crate::search::{DocumentUpdate, Searcher};
 use crate::state::AppState;
 
 /// Check if we've already bootstrapped a prefix / otherwise add it to the queue.
@@ -51,6 +59,28 @@ pub async fn handle_bootstrap(
     false
 }
 
+/// Enqueue the URLs listed in a lens sitemap.
+#[tracing::instrument(skip(state, lens))]
+pub async fn handle_sitemap(
+    state: &AppState,
+    lens: &LensConfig,
+    sitemap_url: &str,
+    pipeline: Option<String>,
+) {
+    let url = match Url::parse(sitemap_url) {
+        Ok(url) => url,
+        Err(_) => {
+            log::error!("{} is an invalid URL", sitemap_url);
+            return;
+        }
+    };
+
+    match sitemap::ingest(state, lens, &url, pipeline).await {
+        Ok(cnt) => log::info!("enqueued {} urls from sitemap {}", cnt, sitemap_url),
+        Err(err) => log::error!("error reading sitemap <{}>: {}", sitemap_url, err),
+    }
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub enum FetchResult {
     New,
@@ -60,6 +90,68 @@ pub enum FetchResult {
     Updated,
 }
 
+/// Keeps the previously indexed content of `existing` searchable w/ `as_of:`
+/// queries if it changed, returning when the current content was first crawled.
+async fn update_versions(
+    state: &AppState,
+    existing: Option<&indexed_document::Model>,
+    url: &str,
+    content: &str,
+    content_hash: Option<String>,
+) -> anyhow::Result<DateTime<Utc>> {
+    let now = Utc::now();
+    let current = match (document_version::current(&state.db, url).await?, existing) {
+        (Some(current), _) => Some(current),
+        // Indexed before versions were tracked, start the history w/ what we have.
+        (None, Some(doc)) => {
+            Some(document_version::record(&state.db, url, None, doc.updated_at).await?)
+        }
+        (None, None) => None,
+    };
+
+    let fields = DocFields::as_fields();
+    let previous = existing.and_then(|doc| Searcher::get_by_id(&state.index.reader, &doc.doc_id));
+    if let (Some(current), Some(previous), Some(doc)) = (&current, &previous, existing) {
+        let previous_content = previous
+            .get_first(fields.content)
+            .and_then(|value| value.as_text())
+            .unwrap_or_default();
+        if previous_content == content {
+            return Ok(current.crawled_at);
+        }
+
+        // Alert the user if this is a page they're watching.
+        if watched_page::find_by_url(&state.db, url).await?.is_some()
+            && diff::is_meaningful_change(previous_content, content)
+        {
+            let summary = diff::summarize(&diff::diff_text(previous_content, content));
+            watched_page::record_change(&state.db, url, &summary).await?;
+        }
+
+        if let Ok(mut writer) = state.index.writer.lock() {
+            Searcher::add_version(
+                &mut writer,
+                previous,
+                &doc.doc_id,
+                &current.crawled_at,
+                &now,
+            )?;
+        }
+    }
+
+    document_version::record(&state.db, url, content_hash, now).await?;
+    let expired =
+        document_version::prune(&state.db, url, state.user_settings.max_document_versions).await?;
+    if let (Some(doc), Ok(mut writer)) = (existing, state.index.writer.lock()) {
+        for version in expired {
+            Searcher::remove_version(&mut writer, &doc.doc_id, &version.crawled_at);
+        }
+    }
+
+    Ok(now)
+}
+
+#[tracing::instrument(skip(state, crawl_result), fields(url = %crawl_result.url))]
 pub async fn process_crawl(
     state: &AppState,
     task_id: i64,
@@ -73,6 +165,19 @@ pub async fn process_crawl(
             None => return Err(CrawlError::Other("task no longer exists".to_owned())),
         };
 
+    // Grab enabled lenses, links are routed to the pipeline of the lens they
+    // belong to.
+    let lenses: Vec<LensConfig> = state
+        .lenses
+        .iter()
+        .map(|entry| entry.value().clone())
+        .collect();
+
+    // Index the page under the URL form its lenses settle on, so variants of
+    // the same URL don't show up as separate documents.
+    let mut crawl_result = crawl_result.clone();
+    crawl_result.url = UrlNormalizer::new(&lenses).normalize(&crawl_result.url);
+
     // Update URL in crawl_task to match the canonical URL extracted in the crawl result.
     if task.url != crawl_result.url {
         log::debug!("Updating task URL {} -> {}", task.url, crawl_result.url);
@@ -92,17 +197,16 @@ pub async fn process_crawl(
         }
     }
 
+    if let Some(fetch_info) = &crawl_result.fetch_info {
+        if let Err(err) = crawl_queue::set_fetch_info(&state.db, task.id, fetch_info.clone()).await
+        {
+            log::error!("Unable to save fetch info for task {}: {}", task.id, err);
+        }
+    }
+
     // Add all valid, non-duplicate, non-indexed links found to crawl queue
     let to_enqueue: Vec<String> = crawl_result.links.clone().into_iter().collect();
 
-    // Grab enabled lenses
-    let lenses: Vec<LensConfig> = state
-        .lenses
-        .iter()
-        .filter(|entry| entry.value().pipeline.is_none())
-        .map(|entry| entry.value().clone())
-        .collect();
-
     if let Err(err) = crawl_queue::enqueue_all(
         &state.db,
         &to_enqueue,
@@ -116,6 +220,56 @@ pub async fn process_crawl(
         log::error!("error enqueuing all: {}", err);
     }
 
+    // Links are still followed, but the page itself asked not to be indexed.
+    if crawl_result.noindex {
+        log::info!("Skipping <{}>, marked noindex", crawl_result.url);
+        let _ = Searcher::delete_by_url(state, &crawl_result.url).await;
+        if let Err(err) = crawl_queue::mark_skipped(&state.db, task.id).await {
+            log::error!("Unable to mark task {} skipped: {}", task.id, err);
+        }
+        return Ok(FetchResult::Ignore);
+    }
+
+    // Keep junk pages (near empty, mostly boilerplate) & error pages served
+    // w/ a 200 out of the index, their links are still followed.
+    if let Ok(url) = Url::parse(&crawl_result.url) {
+        let rules = quality::rules_for_url(&lenses, &url);
+        if let Some(reason) = quality::check(&rules, &crawl_result) {
+            let _ = Searcher::delete_by_url(state, &crawl_result.url).await;
+            // Error pages fail like any other page we couldn't get content from.
+            if reason.is_error_page() {
+                log::info!("<{}> is an error page: {}", crawl_result.url, reason);
+                return Err(CrawlError::ParseError(format!("error page ({})", reason)));
+            }
+
+            log::info!("Skipping <{}>, low quality: {}", crawl_result.url, reason);
+            if let Err(err) = crawl_queue::mark_skipped(&state.db, task.id).await {
+                log::error!("Unable to mark task {} skipped: {}", task.id, err);
+            }
+            return Ok(FetchResult::Ignore);
+        }
+    }
+
+    // Check content rules before anything makes it into the index.
+    let verdict = state.content_rules.apply(&mut crawl_result);
+    let has_secrets = verdict.has_secrets();
+    match verdict {
+        ContentVerdict::Skip(rule) => {
+            log::info!("Skipping <{}>, content matched {}", crawl_result.url, rule);
+            // Make sure an older version of this document doesn't stick around
+            let _ = Searcher::delete_by_url(state, &crawl_result.url).await;
+            return Ok(FetchResult::Ignore);
+        }
+        ContentVerdict::Index(redacted) if !redacted.is_empty() => {
+            log::info!(
+                "Redacted {} from <{}>",
+                redacted.join(", "),
+                crawl_result.url
+            );
+        }
+        _ => {}
+    }
+
     // Add / update search index w/ crawl result.
     if let Some(content) = crawl_result.content.clone() {
         let url = Url::parse(&crawl_result.url);
@@ -138,6 +292,27 @@ pub async fn process_crawl(
             .await
             .unwrap_or_default();
 
+        // Web content is archived, so keep a bounded history of how it changed.
+        let crawled_at = if url.scheme() != "file" {
+            match update_versions(
+                state,
+                existing.as_ref(),
+                url.as_str(),
+                &content,
+                crawl_result.content_hash.clone(),
+            )
+            .await
+            {
+                Ok(crawled_at) => Some(crawled_at),
+                Err(err) => {
+                    log::warn!("Unable to update versions of <{}>: {}", url, err);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
         // Delete old document, if any.
         if let Some(doc) = &existing {
             if let Ok(mut index_writer) = state.index.writer.lock() {
@@ -145,25 +320,77 @@ pub async fn process_crawl(
             }
         }
 
+        // Notes are removed from the index w/ the old document, add them back.
+        let notes = match &existing {
+            Some(doc) => document_note::for_document(&state.db, doc.id)
+                .await
+                .unwrap_or_default()
+                .iter()
+                .map(|note| (note.id, note.indexed_text()))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let task_tags = task
+            .find_related(tag::Entity)
+            .all(&state.db)
+            .await
+            .unwrap_or_default();
+
+        let mut tag_pairs: Vec<tag::TagPair> = task_tags
+            .iter()
+            .map(|tag| (tag.label.to_owned(), tag.value.to_string()))
+            .collect();
+
+        if has_secrets {
+            tag_pairs.push((
+                tag::TagType::Flag,
+                tag::TagValue::HasSecrets.as_ref().to_string(),
+            ));
+        }
+
+        // Tags already on the document, e.g. favorites, are kept.
+        let mut index_tags: Vec<(String, String)> = match &existing {
+            Some(doc) => doc
+                .find_related(tag::Entity)
+                .all(&state.db)
+                .await
+                .unwrap_or_default()
+                .iter()
+                .map(|tag| (tag.label.as_ref().to_string(), tag.value.clone()))
+                .collect(),
+            None => Vec::new(),
+        };
+        for (label, value) in &tag_pairs {
+            let pair = (label.as_ref().to_string(), value.clone());
+            if !index_tags.contains(&pair) {
+                index_tags.push(pair);
+            }
+        }
+
         // Add document to index
         let doc_id: String = {
             if let Ok(mut index_writer) = state.index.writer.lock() {
                 match Searcher::upsert_document(
                     &mut index_writer,
-                    existing.clone().map(|d| d.doc_id),
-                    &crawl_result.title.clone().unwrap_or_default(),
-                    &crawl_result.description.clone().unwrap_or_default(),
-                    url_host,
-                    url.as_str(),
-                    &content,
+                    DocumentUpdate {
+                        doc_id: existing.clone().map(|d| d.doc_id),
+                        title: &crawl_result.title.clone().unwrap_or_default(),
+                        description: &crawl_result.description.clone().unwrap_or_default(),
+                        domain: url_host,
+                        url: url.as_str(),
+                        content: &content,
+                        fields: &crawl_result.fields,
+                        numbers: &crawl_result.numbers,
+                        code: &crawl_result.code,
+                        sections: &crawl_result.sections,
+                        notes: &notes,
+                        crawled_at,
+                        tags: &index_tags,
+                    },
                 ) {
                     Ok(new_doc_id) => new_doc_id,
-                    Err(err) => {
-                        return Err(CrawlError::Other(format!(
-                            "Unable to save document: {}",
-                            err
-                        )));
-                    }
+                    Err(err) => return Err(index_error(err)),
                 }
             } else {
                 return Err(CrawlError::Other(
@@ -172,12 +399,40 @@ pub async fn process_crawl(
             }
         };
 
+        // Keep a copy of web content around, local files are already on disk.
+        if url.scheme() != "file" {
+            // Uploads to remote storage can be slow, so don't hold up the crawl.
+            if let Some(hash) = crawl_result.content_hash.clone() {
+                let archive = state.archive.clone();
+                let data = content.clone();
+                let archived_url = url.clone();
+                tokio::spawn(async move {
+                    if let Err(err) = archive.put(&hash, data.into()).await {
+                        log::warn!("Unable to archive <{}>: {}", archived_url, err);
+                    }
+                });
+            }
+
+            let image = crawl_result
+                .fields
+                .iter()
+                .find(|(name, _)| name == "image")
+                .map(|(_, value)| value.to_owned());
+            tokio::spawn(images::cache_images(
+                state.clone(),
+                url.clone(),
+                doc_id.clone(),
+                image,
+            ));
+        }
+
         // Update/create index reference in our database
         let is_update = existing.is_some();
         let indexed = if let Some(doc) = existing {
             let mut update: indexed_document::ActiveModel = doc.into();
             update.doc_id = Set(doc_id);
             update.open_url = Set(crawl_result.open_url.clone());
+            update.document_date = Set(crawl_result.document_date);
             update
         } else {
             indexed_document::ActiveModel {
@@ -185,6 +440,7 @@ pub async fn process_crawl(
                 url: Set(url.as_str().to_string()),
                 open_url: Set(crawl_result.open_url.clone()),
                 doc_id: Set(doc_id),
+                document_date: Set(crawl_result.document_date),
                 ..Default::default()
             }
         };
@@ -192,17 +448,6 @@ pub async fn process_crawl(
         return match indexed.save(&state.db).await {
             Ok(doc) => {
                 // attach tags to document once we're all done.
-                let task_tags = task
-                    .find_related(tag::Entity)
-                    .all(&state.db)
-                    .await
-                    .unwrap_or_default();
-
-                let tag_pairs: Vec<tag::TagPair> = task_tags
-                    .iter()
-                    .map(|tag| (tag.label.to_owned(), tag.value.to_string()))
-                    .collect();
-
                 let _ = doc.insert_tags(&state.db, &tag_pairs).await;
                 if is_update {
                     Ok(FetchResult::Updated)
@@ -217,20 +462,131 @@ pub async fn process_crawl(
     Err(CrawlError::ParseError("No content found".to_string()))
 }
 
+/// Index `url` from HTML the user already has, e.g. a DOM snapshot sent by the
+/// browser extension, instead of fetching it. Returns the doc_id of the page,
+/// or None if a content rule kept it out of the index.
+#[tracing::instrument(skip(state, html))]
+pub async fn index_snapshot(
+    state: &AppState,
+    url: &Url,
+    html: &str,
+) -> anyhow::Result<Option<String>, CrawlError> {
+    let task = match crawl_queue::start_recrawl(&state.db, url.as_str()).await {
+        Ok(Some(task)) => task,
+        Ok(None) => return Err(CrawlError::Other("page is being crawled".to_owned())),
+        Err(err) => return Err(CrawlError::Other(err.to_string())),
+    };
+
+    let options = ScrapeOptions::for_url(state, url);
+    let crawl_result = Crawler::new().scrape_page(url, html, &options).await;
+    match process_crawl(state, task.id, &crawl_result).await {
+        Ok(FetchResult::Ignore) => Ok(None),
+        Ok(_) => {
+            let doc = indexed_document::Entity::find()
+                .filter(indexed_document::Column::Url.eq(crawl_result.url.clone()))
+                .one(&state.db)
+                .await
+                .map_err(|err| CrawlError::Other(err.to_string()))?;
+            Ok(doc.map(|doc| doc.doc_id))
+        }
+        Err(err) => {
+            crawl_queue::mark_failed(
+                &state.db,
+                &state.user_settings,
+                task.id,
+                Some((&err).into()),
+            )
+            .await;
+            Err(err)
+        }
+    }
+}
+
+/// A document that was refetched & reindexed on request.
+#[derive(Debug)]
+pub struct Reindexed {
+    pub result: FetchResult,
+    /// Parse output w/ content rules applied, i.e. what went into the index.
+    pub crawl_result: CrawlResult,
+    pub verdict: ContentVerdict,
+}
+
+/// Fetch, parse & index `url` right away, even if it was fetched recently or
+/// is already indexed.
 #[tracing::instrument(skip(state))]
-pub async fn handle_fetch(state: AppState, task: CrawlTask) -> FetchResult {
+pub async fn reindex_url(state: &AppState, url: &Url) -> anyhow::Result<Reindexed, CrawlError> {
+    if let Err(err) = fetch_history::delete_by_url(&state.db, url).await {
+        log::warn!("Unable to clear fetch history of <{}>: {}", url, err);
+    }
+
+    let task = match crawl_queue::start_recrawl(&state.db, url.as_str()).await {
+        Ok(Some(task)) => task,
+        Ok(None) => return Err(CrawlError::Other("page is being crawled".to_owned())),
+        Err(err) => return Err(CrawlError::Other(err.to_string())),
+    };
+
+    let processed = match Crawler::new().fetch_by_job(state, task.id, true).await {
+        Ok(crawl_result) => process_crawl(state, task.id, &crawl_result)
+            .await
+            .map(|result| (result, crawl_result)),
+        Err(err) => Err(err),
+    };
+
+    match processed {
+        Ok((result, mut crawl_result)) => {
+            let verdict = state.content_rules.apply(&mut crawl_result);
+            Ok(Reindexed {
+                result,
+                crawl_result,
+                verdict,
+            })
+        }
+        Err(err) => {
+            crawl_queue::mark_failed(
+                &state.db,
+                &state.user_settings,
+                task.id,
+                Some((&err).into()),
+            )
+            .await;
+            Err(err)
+        }
+    }
+}
+
+/// Out of disk space, e.g. `ENOSPC` on unix & `ERROR_DISK_FULL` on Windows.
+const DISK_FULL_OS_ERROR: i32 = if cfg!(windows) { 112 } else { 28 };
+
+fn index_error(err: tantivy::TantivyError) -> CrawlError {
+    match &err {
+        tantivy::TantivyError::IoError(io) if io.raw_os_error() == Some(DISK_FULL_OS_ERROR) => {
+            CrawlError::IndexFull(err.to_string())
+        }
+        _ => CrawlError::Other(format!("Unable to save document: {}", err)),
+    }
+}
+
+/// Status of a task once it's been fetched, written along w/ the rest of its
+/// batch. Tasks that were indexed are marked done as part of `process_crawl`.
+enum TaskStatus {
+    Done,
+    Failed(TaskError),
+}
+
+async fn fetch(state: &AppState, task: &CrawlTask) -> (FetchResult, Option<TaskStatus>) {
     let crawler = Crawler::new();
-    let result = crawler.fetch_by_job(&state, task.id, true).await;
+    let result = crawler.fetch_by_job(state, task.id, true).await;
 
     match result {
-        Ok(crawl_result) => match process_crawl(&state, task.id, &crawl_result).await {
+        Ok(crawl_result) => match process_crawl(state, task.id, &crawl_result).await {
             Ok(res) => {
                 log::debug!("Crawled task id: {} - {:?}", task.id, res);
-                res
+                (res, None)
             }
             Err(err) => {
                 log::warn!("Unable to crawl id: {} - {:?}", task.id, err);
-                FetchResult::Error(err)
+                let status = TaskStatus::Failed((&err).into());
+                (FetchResult::Error(err), Some(status))
             }
         },
         Err(err) => {
@@ -238,33 +594,69 @@ pub async fn handle_fetch(state: AppState, task: CrawlTask) -> FetchResult {
             match err {
                 // Ignore skips, recently fetched crawls, or not found
                 CrawlError::Denied(_) | CrawlError::RecentlyFetched => {
-                    let _ = crawl_queue::mark_done(&state.db, task.id, None).await;
-                    FetchResult::Ignore
+                    (FetchResult::Ignore, Some(TaskStatus::Done))
                 }
-                CrawlError::NotFound => {
-                    let _ = crawl_queue::mark_done(&state.db, task.id, None).await;
-                    FetchResult::NotFound
+                CrawlError::NotFound => (FetchResult::NotFound, Some(TaskStatus::Done)),
+                // Connection is paused, leave it queued until the quota resets.
+                CrawlError::QuotaExceeded(_) => {
+                    crawl_queue::requeue(&state.db, task.id, Some((&err).into())).await;
+                    (FetchResult::Ignore, None)
                 }
-                // Retry timeouts, might be a network issue
-                CrawlError::Timeout => {
-                    log::info!("Retrying task {} if possible", task.id);
-                    crawl_queue::mark_failed(&state.db, task.id, true).await;
-                    FetchResult::Error(err.clone())
-                }
-                // No need to retry these, mark as failed.
-                CrawlError::FetchError(_)
+                // Retried if the retry policy for the error allows it, e.g.
+                // timeouts, otherwise marked as failed.
+                CrawlError::Timeout
+                | CrawlError::FetchError(_)
                 | CrawlError::ParseError(_)
                 | CrawlError::Unsupported(_)
+                | CrawlError::TlsError(_)
+                | CrawlError::HttpStatus(_)
+                | CrawlError::IndexFull(_)
                 | CrawlError::Other(_) => {
-                    // mark crawl as failed
-                    crawl_queue::mark_failed(&state.db, task.id, false).await;
-                    FetchResult::Error(err.clone())
+                    let status = TaskStatus::Failed((&err).into());
+                    (FetchResult::Error(err), Some(status))
                 }
             }
         }
     }
 }
 
+/// Fetch a batch of tasks one after another, updating the status of those
+/// that weren't indexed in bulk once they're all done.
+#[tracing::instrument(skip(state))]
+pub async fn handle_fetch_batch(state: AppState, tasks: Vec<CrawlTask>) -> Vec<FetchResult> {
+    let mut results = Vec::new();
+    let mut done = Vec::new();
+    let mut failed = Vec::new();
+    for task in &tasks {
+        let (result, status) = fetch(&state, task).await;
+        match status {
+            Some(TaskStatus::Done) => done.push(task.id),
+            Some(TaskStatus::Failed(error)) => failed.push((task.id, error)),
+            None => {}
+        }
+        results.push(result);
+    }
+
+    if let Err(err) = crawl_queue::mark_many_done(&state.db, &done).await {
+        log::error!("Unable to mark {} tasks as done: {}", done.len(), err);
+    }
+
+    if let Err(err) = crawl_queue::mark_many_failed(&state.db, &state.user_settings, &failed).await
+    {
+        log::error!("Unable to mark {} tasks as failed: {}", failed.len(), err);
+    }
+
+    results
+}
+
+#[tracing::instrument(skip(state))]
+pub async fn handle_fetch(state: AppState, task: CrawlTask) -> FetchResult {
+    handle_fetch_batch(state, vec![task])
+        .await
+        .pop()
+        .unwrap_or(FetchResult::Ignore)
+}
+
 #[tracing::instrument(skip(state))]
 pub async fn handle_deletion(state: AppState, task_id: i64) -> anyhow::Result<(), DbErr> {
     let task = crawl_queue::Entity::find_by_id(task_id)
@@ -298,16 +690,20 @@ pub async fn handle_deletion(state: AppState, task_id: i64) -> anyhow::Result<()
 
 #[cfg(test)]
 mod test {
-    use crate::crawler::CrawlResult;
+    use crate::crawler::{CrawlError, CrawlResult};
     use crate::search::IndexPath;
-    use entities::models::crawl_queue::{self, CrawlStatus, CrawlType};
+    use entities::models::crawl_queue::{self, CrawlStatus, CrawlType, FetchInfo};
     use entities::models::tag::{self, TagType};
     use entities::models::{bootstrap_queue, indexed_document};
-    use entities::sea_orm::{ActiveModelTrait, EntityTrait, ModelTrait, Set};
+    use entities::sea_orm::{
+        ActiveModelTrait, ColumnTrait, EntityTrait, ModelTrait, QueryFilter, Set,
+    };
     use entities::test::setup_test_db;
-    use shared::config::UserSettings;
+    use shared::config::{LensConfig, UserSettings};
+    use spyglass_plugin::utils::path_to_uri;
+    use url::Url;
 
-    use super::{handle_bootstrap, process_crawl, AppState, FetchResult};
+    use super::{handle_bootstrap, process_crawl, reindex_url, AppState, FetchResult};
 
     #[tokio::test]
     async fn test_handle_bootstrap() {
@@ -343,10 +739,18 @@ mod test {
         };
         let task = model.insert(&db).await.expect("Unable to save model");
 
+        let fetch_info = FetchInfo {
+            status: 200,
+            final_url: "https://example.com/test".to_owned(),
+            content_type: Some("text/html; charset=utf-8".to_owned()),
+            duration_ms: 120,
+            fetched_at: chrono::Utc::now(),
+        };
         let crawl_result = CrawlResult {
             content: Some("fake content".to_owned()),
             title: Some("Title".to_owned()),
             url: "https://example.com/test".to_owned(),
+            fetch_info: Some(fetch_info.clone()),
             ..Default::default()
         };
 
@@ -356,15 +760,14 @@ mod test {
             .expect("success");
         assert_eq!(result, FetchResult::New);
 
-        // Should update the task status
+        // Should update the task status & keep how it was fetched
         let task = crawl_queue::Entity::find_by_id(task.id)
             .one(&db)
             .await
-            .expect("Unable to query crawl task");
-        assert_eq!(
-            task.expect("Unable to find task").status,
-            CrawlStatus::Completed
-        );
+            .expect("Unable to query crawl task")
+            .expect("Unable to find task");
+        assert_eq!(task.status, CrawlStatus::Completed);
+        assert_eq!(task.fetch_info, Some(fetch_info));
 
         // Should add a new indexed_document obj
         let docs = indexed_document::Entity::find()
@@ -425,6 +828,111 @@ mod test {
         assert_eq!(docs.len(), 1);
     }
 
+    #[tokio::test]
+    async fn test_process_crawl_noindex() {
+        let db = setup_test_db().await;
+        let state = AppState::builder()
+            .with_db(db.clone())
+            .with_user_settings(&UserSettings::default())
+            .with_lenses(&vec![LensConfig {
+                name: "example".into(),
+                domains: vec!["example.com".into()],
+                ..Default::default()
+            }])
+            .with_index(&IndexPath::Memory)
+            .build();
+
+        let task = crawl_queue::ActiveModel {
+            domain: Set("example.com".to_owned()),
+            url: Set("https://example.com/test".to_owned()),
+            status: Set(CrawlStatus::Processing),
+            crawl_type: Set(CrawlType::Normal),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .expect("Unable to save model");
+
+        let crawl_result = CrawlResult {
+            content: Some("fake content".to_owned()),
+            title: Some("Title".to_owned()),
+            url: "https://example.com/test".to_owned(),
+            links: vec!["https://example.com/other".to_owned()]
+                .into_iter()
+                .collect(),
+            noindex: true,
+            ..Default::default()
+        };
+
+        let result = process_crawl(&state, task.id, &crawl_result)
+            .await
+            .expect("success");
+        assert_eq!(result, FetchResult::Ignore);
+
+        let task = crawl_queue::Entity::find_by_id(task.id)
+            .one(&db)
+            .await
+            .expect("Unable to query crawl task")
+            .expect("Unable to find task");
+        assert_eq!(task.status, CrawlStatus::Skipped);
+
+        // Not indexed, but its links are still followed
+        let docs = indexed_document::Entity::find()
+            .all(&db)
+            .await
+            .unwrap_or_default();
+        assert!(docs.is_empty());
+        let queued = crawl_queue::Entity::find()
+            .filter(crawl_queue::Column::Url.eq("https://example.com/other"))
+            .one(&db)
+            .await
+            .expect("Unable to query crawl queue");
+        assert!(queued.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_process_crawl_error_page() {
+        let db = setup_test_db().await;
+        let state = AppState::builder()
+            .with_db(db.clone())
+            .with_user_settings(&UserSettings::default())
+            .with_lenses(&vec![LensConfig {
+                name: "example".into(),
+                domains: vec!["example.com".into()],
+                ..Default::default()
+            }])
+            .with_index(&IndexPath::Memory)
+            .build();
+
+        let task = crawl_queue::ActiveModel {
+            domain: Set("example.com".to_owned()),
+            url: Set("https://example.com/missing".to_owned()),
+            status: Set(CrawlStatus::Processing),
+            crawl_type: Set(CrawlType::Normal),
+            ..Default::default()
+        }
+        .insert(&db)
+        .await
+        .expect("Unable to save model");
+
+        let crawl_result = CrawlResult {
+            content: Some("Sorry, the page you were looking for doesn't exist.".to_owned()),
+            title: Some("Page not found".to_owned()),
+            url: "https://example.com/missing".to_owned(),
+            page_word_count: Some(40),
+            ..Default::default()
+        };
+
+        let result = process_crawl(&state, task.id, &crawl_result).await;
+        assert!(matches!(result, Err(CrawlError::ParseError(msg)) if msg.contains("Soft404")));
+
+        let docs = indexed_document::Entity::find()
+            .all(&db)
+            .await
+            .unwrap_or_default();
+        assert!(docs.is_empty());
+    }
+
     #[tokio::test]
     async fn test_process_crawl_new_with_tags() {
         let db = setup_test_db().await;
@@ -574,4 +1082,35 @@ mod test {
             .unwrap_or_default();
         assert_eq!(task_tags.len(), 3);
     }
+
+    #[tokio::test]
+    async fn test_reindex_url() {
+        let db = setup_test_db().await;
+        let state = AppState::builder()
+            .with_db(db.clone())
+            .with_user_settings(&UserSettings::default())
+            .with_index(&IndexPath::Memory)
+            .build();
+
+        let test_folder = std::env::temp_dir().join("reindex_url");
+        std::fs::create_dir_all(&test_folder).expect("Unable to create test dir");
+        let test_path = test_folder.join("notes.txt");
+        std::fs::write(&test_path, "first draft").expect("Unable to write test file");
+        let url = Url::parse(&path_to_uri(test_path.clone())).unwrap();
+
+        let reindexed = reindex_url(&state, &url).await.expect("Unable to reindex");
+        assert_eq!(reindexed.result, FetchResult::New);
+        let content = reindexed.crawl_result.content.unwrap_or_default();
+        assert!(content.contains("first draft"));
+
+        // Indexed again right away, w/ the new content
+        std::fs::write(&test_path, "second draft").expect("Unable to write test file");
+        let reindexed = reindex_url(&state, &url).await.expect("Unable to reindex");
+        assert_eq!(reindexed.result, FetchResult::Updated);
+        let content = reindexed.crawl_result.content.unwrap_or_default();
+        assert!(content.contains("second draft"));
+
+        let docs = indexed_document::Entity::find().all(&db).await.unwrap();
+        assert_eq!(docs.len(), 1);
+    }
 }
@@ -71,7 +71,21 @@ pub async fn open_settings_folder(_: tauri::Window) -> Result<(), String> {
 }
 
 #[tauri::command]
-pub async fn open_result(_: tauri::Window, url: &str) -> Result<(), String> {
+pub async fn open_result(
+    win: tauri::Window,
+    url: &str,
+    doc_id: Option<String>,
+) -> Result<(), String> {
+    // Keep track of what's opened so stale sources can be ranked lower.
+    if let Some(doc_id) = doc_id {
+        if let Some(rpc) = win.app_handle().try_state::<rpc::RpcMutex>() {
+            let rpc = rpc.lock().await;
+            if let Err(err) = rpc.client.record_open(doc_id).await {
+                log::error!("Unable to record open: {}", err);
+            }
+        }
+    }
+
     if let Ok(mut url) = url::Url::parse(url) {
         // treat open files as a local action.
         if url.scheme() == "file" {
@@ -126,11 +140,17 @@ pub async fn search_docs<'r>(
     win: tauri::Window,
     lenses: Vec<String>,
     query: &str,
+    lens_names: Option<Vec<String>>,
+    mode: Option<request::SearchMode>,
 ) -> Result<SearchResults, String> {
     if let Some(rpc) = win.app_handle().try_state::<rpc::RpcMutex>() {
         let data = request::SearchParam {
             lenses,
             query: query.to_string(),
+            lens_names: lens_names.unwrap_or_default(),
+            mode: mode.unwrap_or_default(),
+            snippet_length: None,
+            context_url: None,
         };
 
         let rpc = rpc.lock().await;
@@ -337,14 +357,43 @@ pub async fn save_user_settings(
                                     "data_directory" => {
                                         current_settings.data_directory = PathBuf::from(val);
                                     }
+                                    "date_locale" => match val.parse() {
+                                        Ok(locale) => current_settings.date_locale = locale,
+                                        Err(_) => {
+                                            errors.insert(
+                                                key.to_string(),
+                                                format!("Unknown date language: {}", val),
+                                            );
+                                        }
+                                    },
                                     "disable_autolaunch" => {
                                         current_settings.disable_autolaunch =
                                             serde_json::from_str(value).unwrap_or_default();
                                     }
+                                    "disable_personalization" => {
+                                        current_settings.disable_personalization =
+                                            serde_json::from_str(value).unwrap_or_default();
+                                    }
                                     "disable_telemetry" => {
                                         current_settings.disable_telemetry =
                                             serde_json::from_str(value).unwrap_or_default();
                                     }
+                                    "domain_crawl_delay_ms" => {
+                                        current_settings.domain_crawl_delay_ms =
+                                            serde_json::from_str(value).unwrap_or_else(|_| {
+                                                UserSettings::default_domain_crawl_delay_ms()
+                                            });
+                                    }
+                                    "fuzzy_distance" => {
+                                        current_settings.fuzzy_distance =
+                                            serde_json::from_str(value).unwrap_or_else(|_| {
+                                                UserSettings::default_fuzzy_distance()
+                                            });
+                                    }
+                                    "image_captioning" => {
+                                        current_settings.image_captioning =
+                                            serde_json::from_str(value).unwrap_or_default();
+                                    }
                                     "inflight_crawl_limit" => {
                                         let limit: u32 = serde_json::from_str(value).unwrap_or(10);
                                         current_settings.inflight_crawl_limit =
@@ -359,6 +408,10 @@ pub async fn save_user_settings(
                                         current_settings.port = serde_json::from_str(value)
                                             .unwrap_or_else(|_| UserSettings::default_port());
                                     }
+                                    "screenshot_ocr" => {
+                                        current_settings.screenshot_ocr =
+                                            serde_json::from_str(value).unwrap_or_default();
+                                    }
                                     _ => {}
                                 }
                             }
@@ -9,6 +9,8 @@ pub const MIN_WINDOW_HEIGHT: f64 = 480.0;
 pub const VERSION_CHECK_INTERVAL_S: u64 = 60 * 60 * 6;
 // Check on start & every hour for new lenses
 pub const LENS_UPDATE_CHECK_INTERVAL_S: u64 = 60 * 60;
+// Check every minute for changes to watched pages
+pub const WATCH_ALERT_CHECK_INTERVAL_S: u64 = 60;
 
 pub const APP_USER_AGENT: &str = "spyglass (github.com/a5huynh/spyglass)";
 pub const LENS_DIRECTORY_INDEX_URL: &str =
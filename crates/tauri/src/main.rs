@@ -108,6 +108,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let app = tauri::Builder::default()
         .plugin(plugins::lens_updater::init())
         .plugin(plugins::startup::init())
+        .plugin(plugins::watch_alerts::init())
         .invoke_handler(tauri::generate_handler![
             cmd::authorize_connection,
             cmd::choose_folder,
@@ -1,2 +1,3 @@
 pub mod lens_updater;
 pub mod startup;
+pub mod watch_alerts;
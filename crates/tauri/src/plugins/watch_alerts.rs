@@ -0,0 +1,93 @@
+use tauri::{
+    async_runtime::JoinHandle,
+    plugin::{Builder, TauriPlugin},
+    AppHandle, Manager, RunEvent, Wry,
+};
+use tokio::sync::broadcast;
+use tokio::time::{self, Duration};
+
+use crate::{constants, rpc, window, AppShutdown};
+use spyglass_rpc::RpcClient;
+
+pub struct WatchAlertsHandle(JoinHandle<()>);
+
+/// Periodically asks the backend for watched pages that changed & saved
+/// searches w/ new results, showing a notification for each of them.
+pub fn init() -> TauriPlugin<Wry> {
+    Builder::new("watch-alerts")
+        .on_event(|app_handle, event| match event {
+            RunEvent::Ready => {
+                let app_handle = app_handle.clone();
+                let app_clone = app_handle.clone();
+                let handle = tauri::async_runtime::spawn(async move {
+                    let mut interval = time::interval(Duration::from_secs(
+                        constants::WATCH_ALERT_CHECK_INTERVAL_S,
+                    ));
+
+                    let shutdown_tx = app_handle.state::<broadcast::Sender<AppShutdown>>();
+                    let mut shutdown = shutdown_tx.subscribe();
+
+                    loop {
+                        tokio::select! {
+                            _ = shutdown.recv() => {
+                                log::info!("🛑 Shutting down watch alerts");
+                                return;
+                            },
+                            _ = interval.tick() => {
+                                if let Err(err) = check_for_alerts(&app_handle).await {
+                                    log::error!("Unable to check for watch alerts: {}", err);
+                                }
+                            },
+                        }
+                    }
+                });
+
+                app_clone.manage(WatchAlertsHandle(handle));
+            }
+            RunEvent::Exit => {
+                if let Some(handle) = app_handle.try_state::<WatchAlertsHandle>() {
+                    handle.0.abort();
+                }
+            }
+            _ => {}
+        })
+        .build()
+}
+
+async fn check_for_alerts(app_handle: &AppHandle) -> anyhow::Result<()> {
+    let mutex = app_handle
+        .try_state::<rpc::RpcMutex>()
+        .ok_or_else(|| anyhow::anyhow!("Unable to get RpcMutex"))?;
+
+    // Saved searches are checked w/o waiting, long-polling would hold up
+    // every other request while the client is locked.
+    let (alerts, search_alerts) = {
+        let rpc = mutex.lock().await;
+        (
+            rpc.client.take_watch_alerts().await?,
+            rpc.client.wait_saved_search_alerts(Some(0)).await?,
+        )
+    };
+
+    for alert in alerts {
+        let body = alert
+            .change_summary
+            .unwrap_or_else(|| "Content changed".to_string());
+        window::notify(app_handle, &format!("Updated: {}", alert.url), &body)?;
+    }
+
+    for alert in search_alerts {
+        let body = match alert.results.as_slice() {
+            [] => continue,
+            [result] => result.title.clone(),
+            [result, rest @ ..] => format!("{} & {} more", result.title, rest.len()),
+        };
+        window::notify(
+            app_handle,
+            &format!("New results for \"{}\"", alert.search.name),
+            &body,
+        )?;
+    }
+
+    Ok(())
+}
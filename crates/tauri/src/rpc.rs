@@ -1,9 +1,10 @@
+use std::path::{Path, PathBuf};
 use std::sync::{
     atomic::{AtomicU8, Ordering},
     Arc,
 };
 
-use jsonrpsee::http_client::{HttpClient, HttpClientBuilder};
+use jsonrpsee::http_client::{HeaderMap, HeaderValue, HttpClient, HttpClientBuilder};
 use tauri::api::dialog::blocking::message;
 use tauri::async_runtime::JoinHandle;
 use tauri::{
@@ -24,33 +25,50 @@ pub type RpcMutex = Arc<Mutex<SpyglassServerClient>>;
 pub struct SpyglassServerClient {
     pub client: HttpClient,
     pub endpoint: String,
+    pub token_path: PathBuf,
     pub sidecar_handle: Option<JoinHandle<()>>,
     pub restarts: AtomicU8,
     pub app_handle: AppHandle,
 }
 
 /// Build client & attempt a connection to the health check endpoint.
-async fn try_connect(endpoint: &str) -> anyhow::Result<HttpClient> {
-    match HttpClientBuilder::default()
+async fn try_connect(endpoint: &str, token_path: &Path) -> anyhow::Result<HttpClient> {
+    // Wait until we have a connection. The backend creates its token the
+    // first time it starts, so it's read again on each attempt.
+    let retry_strategy = FixedInterval::from_millis(5000).take(4);
+    let client = Retry::spawn(retry_strategy, || async {
+        let client = build_client(endpoint, token_path)?;
+        client
+            .protocol_version()
+            .await
+            .map(|v| {
+                log::info!("connected to daemon w/ version: {}", v);
+                client
+            })
+            .map_err(|err| anyhow::anyhow!(err.to_string()))
+    })
+    .await?;
+
+    Ok(client)
+}
+
+/// Client that sends the backend's token w/ every request.
+fn build_client(endpoint: &str, token_path: &Path) -> anyhow::Result<HttpClient> {
+    let token = std::fs::read_to_string(token_path)?;
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        "authorization",
+        HeaderValue::from_str(&format!("Bearer {}", token.trim()))?,
+    );
+
+    HttpClientBuilder::default()
         .request_timeout(std::time::Duration::from_secs(30))
+        .set_headers(headers)
         .build(endpoint)
-    {
-        Ok(client) => {
-            // Wait until we have a connection
-            let retry_strategy = FixedInterval::from_millis(5000).take(4);
-            match Retry::spawn(retry_strategy, || client.protocol_version()).await {
-                Ok(v) => {
-                    log::info!("connected to daemon w/ version: {}", v);
-                    Ok(client)
-                }
-                Err(err) => Err(anyhow::anyhow!(err.to_string())),
-            }
-        }
-        Err(e) => {
-            sentry::capture_error(&e);
-            Err(anyhow::anyhow!(e.to_string()))
-        }
-    }
+        .map_err(|err| {
+            sentry::capture_error(&err);
+            anyhow::anyhow!(err.to_string())
+        })
 }
 
 impl SpyglassServerClient {
@@ -92,7 +110,8 @@ impl SpyglassServerClient {
         let sidecar_handle = Some(SpyglassServerClient::check_and_start_backend());
 
         log::info!("backend started");
-        let client = match try_connect(&endpoint).await {
+        let token_path = config.rpc_token_path();
+        let client = match try_connect(&endpoint, &token_path).await {
             Ok(client) => Some(client),
             Err(err) => {
                 if let Some(window) = app_handle.get_window(constants::SEARCH_WIN_NAME) {
@@ -119,6 +138,7 @@ impl SpyglassServerClient {
         SpyglassServerClient {
             client: client.expect("Unable to create search client"),
             endpoint: endpoint.clone(),
+            token_path,
             sidecar_handle,
             restarts: AtomicU8::new(0),
             app_handle: app_handle.clone(),
@@ -137,7 +157,7 @@ impl SpyglassServerClient {
         }
 
         log::info!("reconnecting to {}", self.endpoint);
-        match try_connect(&self.endpoint).await {
+        match try_connect(&self.endpoint, &self.token_path).await {
             Ok(client) => {
                 self.client = client;
             }